@@ -0,0 +1,754 @@
+// The status bar is wired as producer-consumer fan-outs: each subsystem
+// (Hyprland title, workspace, split hint and outputs, UPower battery, BlueZ,
+// NetworkManager) pushes labels into a drop-oldest channel and a
+// glib-local task drains it onto the corresponding GTK widget on the main
+// thread. This module owns the Bus (one sender per subsystem, cloned into
+// each producer at spawn time) and the typed send
+// helpers; the widget layer (setup_*_updates) owns the receivers. The
+// PipeWire volume and privacy channels stay outside the Bus: their producer is
+// a dedicated std::thread that already takes its senders as parameters (see
+// pw::start_pipewire_thread).
+//
+// Producers are IPC listeners reading sockets and must never block on
+// backpressure or the kernel buffer fills, the connection drops, and the
+// listener dies. See branch experiment-title-sender-bounded for the autopsy.
+// So the channels are bounded without blocking: past CAPACITY queued updates
+// the oldest is dropped (see channel.rs), which keeps a stalled main loop
+// from growing memory while the producers carry on.
+//
+// The senders used to live in process-wide OnceLock statics. That made
+// wiring order a runtime property (the D-Bus monitor could race the
+// bluetooth sender's initialization and drop its first update) and capped
+// the suite at one bus-mediated test per process (OnceLock sets once, ever).
+// Passing a Bus handle instead makes "consumers wired before producers
+// spawn" a property of the call graph in activate(), and lets every test
+// build its own private Bus.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use tokio::sync::{mpsc, watch};
+
+use crate::channel::{self, Receiver, Sender};
+use crate::state::AppState;
+
+// Per channel. Updates are whole states, so anything past the last few is
+// stale by the time the main loop gets to it; the slack absorbs startup,
+// when every producer reports at once.
+pub const CAPACITY: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceUpdate {
+    pub name: String,
+    pub id: hyprland::shared::WorkspaceId,
+    // A special (scratchpad) workspace is on screen. Its id is a sentinel, so
+    // the widget colors by this flag instead of by id.
+    pub special: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TitleUpdate {
+    pub title: String,
+    pub class: String,
+    // The window's process, for the tooltip; None where the compositor does
+    // not say (wlr-foreign-toplevel has no PID).
+    pub pid: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeUpdate {
+    pub name: String,
+    pub volume_percent: Option<u8>,  // Main volume 0-100%
+    pub channel_percent: Option<u8>, // First channel volume 0-100% (most accurate for user changes)
+    pub is_muted: Option<bool>,
+}
+
+// A device-backed audio sink that appeared after PipeWire's initial
+// enumeration: something was plugged in (see sinks.rs).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SinkAdded {
+    pub id: u32,
+    pub node_name: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sink {
+    pub node_name: String,
+    pub description: String,
+}
+
+// Every audio sink PipeWire knows, sorted by description, and the node.name
+// of the default one (see audio.rs).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SinkList {
+    pub sinks: Vec<Sink>,
+    pub default: Option<String>,
+}
+
+// Why the volume widget has no level to show (see pw.rs). The next
+// VolumeUpdate ends it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoAudio {
+    // PipeWire runs but has no default sink: nothing plugged into an
+    // HDMI-only machine, or the default was unplugged.
+    NoSink,
+    // PipeWire could not be reached, or the connection dropped.
+    Unavailable,
+}
+
+// What the volume widget shows next (see pw.rs). Levels and outages share one
+// channel so they arrive in the order PipeWire reported them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VolumeReport {
+    Level(VolumeUpdate),
+    NoAudio(NoAudio),
+}
+
+// Which kinds of capture are running right now (see privacy.rs).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PrivacyUpdate {
+    pub microphone: bool,
+    pub camera: bool,
+    pub screen: bool,
+}
+
+// Which kind of profile a Bluetooth headset is playing in (see headset.rs).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HeadsetMode {
+    // "off", an audio-gateway profile, or nothing reported yet.
+    #[default]
+    Other,
+    // A2DP: stereo playback, no microphone.
+    HighQuality,
+    // HSP/HFP: the microphone works, the sound is mono and narrowband.
+    Headset,
+}
+
+// The connected Bluetooth headset's audio profile: an empty `device` means
+// none is connected. `switch_to` describes the profile a toggle selects.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeadsetUpdate {
+    pub device: String,
+    pub mode: HeadsetMode,
+    pub profile: String,
+    pub switch_to: Option<String>,
+}
+
+// Audio output streams playing right now (see playback.rs).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlaybackUpdate {
+    pub streams: Vec<PlaybackStream>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaybackStream {
+    // The PipeWire node id, which AudioCommand::SetStreamMute takes.
+    pub node_id: u32,
+    // The client's PID, then its parents', nearest first.
+    pub pids: Vec<i32>,
+    pub muted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskbarWindow {
+    // Hyprland's window address ("0x55d0..."), the handle dispatchers take,
+    // or a wlr toplevel's protocol id (see toplevel.rs).
+    pub address: String,
+    pub class: String,
+    pub title: String,
+    pub workspace_id: hyprland::shared::WorkspaceId,
+    pub focused: bool,
+    // The owning process, matched against playback streams; None where the
+    // window backend does not say (wlr toplevels).
+    pub pid: Option<i32>,
+}
+
+// Every mapped window, grouped by workspace, plus the workspace on screen so
+// the widget can narrow the list to it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskbarUpdate {
+    pub windows: Vec<TaskbarWindow>,
+    pub active_workspace: hyprland::shared::WorkspaceId,
+}
+
+// Empty text hides the GPU widget; `warning` marks a threshold crossed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GpuUpdate {
+    pub text: String,
+    pub warning: bool,
+}
+
+// Per-core load: empty text hides the widget, `detail` fills its popover, and
+// the flags mark the warning and critical thresholds.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadAvgUpdate {
+    pub text: String,
+    pub detail: String,
+    pub warning: bool,
+    pub critical: bool,
+}
+
+// Empty text hides the notifications widget (no swaync or dunst running);
+// `dnd` marks do-not-disturb.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NotificationsUpdate {
+    pub text: String,
+    pub dnd: bool,
+}
+
+// Empty text hides the keyboard backlight widget; the level and maximum are
+// what scrolling steps from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KbdBacklightUpdate {
+    pub text: String,
+    pub level: i32,
+    pub max: i32,
+}
+
+// LauncherEntry jobs: empty text hides the chip; `fraction` (0.0 to 1.0)
+// fills its bar.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProgressUpdate {
+    pub text: String,
+    pub fraction: f64,
+    pub tooltip: String,
+}
+
+// Airplane mode: empty text hides the widget (no Wi-Fi or Bluetooth radio);
+// `blocked` marks a radio that rfkill holds off.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RfkillUpdate {
+    pub text: String,
+    pub blocked: bool,
+    pub tooltip: String,
+}
+
+// Bluetooth devices with their battery levels; empty text hides the widget.
+// `connecting` marks a device that is connecting or pairing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BluetoothUpdate {
+    pub text: String,
+    pub connecting: bool,
+}
+
+// The system battery: empty text hides the widget (no battery). The charge
+// and state let the bar warn about a low battery and animate charging.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BatteryUpdate {
+    pub text: String,
+    pub percent: Option<f64>,
+    pub state: BatteryState,
+}
+
+// UPower's device states, with the pending ones folded into the state they
+// are about to enter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BatteryState {
+    #[default]
+    Unknown,
+    Charging,
+    Discharging,
+    Empty,
+    Full,
+}
+
+// Something a `[hooks]` command may want to run on (see hooks.rs).
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookEvent {
+    Workspace {
+        id: hyprland::shared::WorkspaceId,
+        name: String,
+    },
+    Battery {
+        percent: f64,
+        discharging: bool,
+    },
+    BluetoothConnected {
+        name: String,
+        address: String,
+    },
+}
+
+// Producer-side handle: cheap to clone (one Sender clone per channel), Send +
+// Sync, so it moves freely into tokio tasks and hyprland-rs handler closures.
+#[derive(Clone)]
+pub struct Bus {
+    workspace: Sender<WorkspaceUpdate>,
+    title: Sender<TitleUpdate>,
+    battery: Sender<BatteryUpdate>,
+    bluetooth: Sender<BluetoothUpdate>,
+    network: Sender<String>,
+    split: Sender<String>,
+    displays: Sender<String>,
+    // Windows per occupied regular workspace, by id.
+    window_count: Sender<BTreeMap<i32, usize>>,
+    floating: Sender<String>,
+    // The special workspaces shown, by name.
+    scratchpad: Sender<Vec<String>>,
+    cpufreq: Sender<String>,
+    vpn: Sender<String>,
+    hotspot: Sender<String>,
+    rfkill: Sender<RfkillUpdate>,
+    units: Sender<Vec<String>>,
+    updates: Sender<String>,
+    clipboard: Sender<String>,
+    gpu: Sender<GpuUpdate>,
+    loadavg: Sender<LoadAvgUpdate>,
+    bandwidth: Sender<String>,
+    notifications: Sender<NotificationsUpdate>,
+    progress: Sender<ProgressUpdate>,
+    kbd_backlight: Sender<KbdBacklightUpdate>,
+    lock_keys: Sender<String>,
+    // Events, not states: a tokio mpsc like channel.rs says, so a burst
+    // never drops a battery-low or connect. Its consumer is a tokio task
+    // rather than the main loop, and the events are rare, so unbounded.
+    hooks: mpsc::UnboundedSender<HookEvent>,
+    taskbar: Sender<TaskbarUpdate>,
+    // Bumped when the machine wakes from suspend; pollers and the clock
+    // subscribe to refresh at once rather than on their next tick.
+    resume: Arc<watch::Sender<u64>>,
+    // The latest workspace, window, battery and Bluetooth reading, for views
+    // that combine them (see state.rs).
+    state: AppState,
+}
+
+// Consumer side, produced exactly once per Bus by Bus::new. Receivers are not
+// cloneable; each field is moved into its widget's glib-local drain task.
+pub struct BusReceivers {
+    pub workspace: Receiver<WorkspaceUpdate>,
+    pub title: Receiver<TitleUpdate>,
+    pub battery: Receiver<BatteryUpdate>,
+    pub bluetooth: Receiver<BluetoothUpdate>,
+    pub network: Receiver<String>,
+    pub split: Receiver<String>,
+    pub displays: Receiver<String>,
+    pub window_count: Receiver<BTreeMap<i32, usize>>,
+    pub floating: Receiver<String>,
+    pub scratchpad: Receiver<Vec<String>>,
+    pub cpufreq: Receiver<String>,
+    pub vpn: Receiver<String>,
+    pub hotspot: Receiver<String>,
+    pub rfkill: Receiver<RfkillUpdate>,
+    pub units: Receiver<Vec<String>>,
+    pub updates: Receiver<String>,
+    pub clipboard: Receiver<String>,
+    pub gpu: Receiver<GpuUpdate>,
+    pub loadavg: Receiver<LoadAvgUpdate>,
+    pub bandwidth: Receiver<String>,
+    pub notifications: Receiver<NotificationsUpdate>,
+    pub progress: Receiver<ProgressUpdate>,
+    pub kbd_backlight: Receiver<KbdBacklightUpdate>,
+    pub lock_keys: Receiver<String>,
+    pub hooks: mpsc::UnboundedReceiver<HookEvent>,
+    pub taskbar: Receiver<TaskbarUpdate>,
+}
+
+impl Bus {
+    pub fn new() -> (Bus, BusReceivers) {
+        let (workspace_tx, workspace_rx) = channel::channel("workspace", CAPACITY);
+        let (title_tx, title_rx) = channel::channel("title", CAPACITY);
+        let (battery_tx, battery_rx) = channel::channel("battery", CAPACITY);
+        let (bluetooth_tx, bluetooth_rx) = channel::channel("bluetooth", CAPACITY);
+        let (network_tx, network_rx) = channel::channel("network", CAPACITY);
+        let (split_tx, split_rx) = channel::channel("split", CAPACITY);
+        let (displays_tx, displays_rx) = channel::channel("displays", CAPACITY);
+        let (window_count_tx, window_count_rx) = channel::channel("window_count", CAPACITY);
+        let (floating_tx, floating_rx) = channel::channel("floating", CAPACITY);
+        let (scratchpad_tx, scratchpad_rx) = channel::channel("scratchpad", CAPACITY);
+        let (cpufreq_tx, cpufreq_rx) = channel::channel("cpufreq", CAPACITY);
+        let (vpn_tx, vpn_rx) = channel::channel("vpn", CAPACITY);
+        let (hotspot_tx, hotspot_rx) = channel::channel("hotspot", CAPACITY);
+        let (rfkill_tx, rfkill_rx) = channel::channel("rfkill", CAPACITY);
+        let (units_tx, units_rx) = channel::channel("units", CAPACITY);
+        let (updates_tx, updates_rx) = channel::channel("updates", CAPACITY);
+        let (clipboard_tx, clipboard_rx) = channel::channel("clipboard", CAPACITY);
+        let (gpu_tx, gpu_rx) = channel::channel("gpu", CAPACITY);
+        let (loadavg_tx, loadavg_rx) = channel::channel("loadavg", CAPACITY);
+        let (bandwidth_tx, bandwidth_rx) = channel::channel("bandwidth", CAPACITY);
+        let (notifications_tx, notifications_rx) = channel::channel("notifications", CAPACITY);
+        let (progress_tx, progress_rx) = channel::channel("progress", CAPACITY);
+        let (kbd_backlight_tx, kbd_backlight_rx) = channel::channel("kbd_backlight", CAPACITY);
+        let (lock_keys_tx, lock_keys_rx) = channel::channel("lock_keys", CAPACITY);
+        let (hooks_tx, hooks_rx) = mpsc::unbounded_channel();
+        let (taskbar_tx, taskbar_rx) = channel::channel("taskbar", CAPACITY);
+
+        (
+            Bus {
+                workspace: workspace_tx,
+                title: title_tx,
+                battery: battery_tx,
+                bluetooth: bluetooth_tx,
+                network: network_tx,
+                split: split_tx,
+                displays: displays_tx,
+                window_count: window_count_tx,
+                floating: floating_tx,
+                scratchpad: scratchpad_tx,
+                cpufreq: cpufreq_tx,
+                vpn: vpn_tx,
+                hotspot: hotspot_tx,
+                rfkill: rfkill_tx,
+                units: units_tx,
+                updates: updates_tx,
+                clipboard: clipboard_tx,
+                gpu: gpu_tx,
+                loadavg: loadavg_tx,
+                bandwidth: bandwidth_tx,
+                notifications: notifications_tx,
+                progress: progress_tx,
+                kbd_backlight: kbd_backlight_tx,
+                lock_keys: lock_keys_tx,
+                hooks: hooks_tx,
+                taskbar: taskbar_tx,
+                resume: Arc::new(watch::channel(0).0),
+                state: AppState::new(),
+            },
+            BusReceivers {
+                workspace: workspace_rx,
+                title: title_rx,
+                battery: battery_rx,
+                bluetooth: bluetooth_rx,
+                network: network_rx,
+                split: split_rx,
+                displays: displays_rx,
+                window_count: window_count_rx,
+                floating: floating_rx,
+                scratchpad: scratchpad_rx,
+                cpufreq: cpufreq_rx,
+                vpn: vpn_rx,
+                hotspot: hotspot_rx,
+                rfkill: rfkill_rx,
+                units: units_rx,
+                updates: updates_rx,
+                clipboard: clipboard_rx,
+                gpu: gpu_rx,
+                loadavg: loadavg_rx,
+                bandwidth: bandwidth_rx,
+                notifications: notifications_rx,
+                progress: progress_rx,
+                kbd_backlight: kbd_backlight_rx,
+                lock_keys: lock_keys_rx,
+                hooks: hooks_rx,
+                taskbar: taskbar_rx,
+            },
+        )
+    }
+
+    // These helpers are intentionally synchronous. channel::Sender::send is
+    // non-blocking and returns Result, not a Future; declaring them `async fn`
+    // would force every caller into an async context and mis-signal a yield
+    // point that does not exist.
+
+    pub fn send_workspace_update(&self, update: WorkspaceUpdate) -> Result<()> {
+        self.send_hook_event(HookEvent::Workspace {
+            id: update.id,
+            name: update.name.clone(),
+        });
+        self.state.set_workspace(update.clone());
+        self.workspace
+            .send(update)
+            .context("Failed to send workspace update")
+    }
+
+    pub fn send_title_update(&self, update: TitleUpdate) -> Result<()> {
+        self.state.set_active_window(update.clone());
+        self.title
+            .send(update)
+            .context("Failed to send title update")
+    }
+
+    pub fn send_battery_update(&self, update: BatteryUpdate) -> Result<()> {
+        self.state.set_power(update.clone());
+        self.battery
+            .send(update)
+            .context("Failed to send battery update")
+    }
+
+    pub fn send_bluetooth_update(&self, update: BluetoothUpdate) -> Result<()> {
+        self.state.set_bluetooth(update.clone());
+        self.bluetooth
+            .send(update)
+            .context("Failed to send bluetooth update")
+    }
+
+    pub fn send_network_update(&self, update: String) -> Result<()> {
+        self.network
+            .send(update)
+            .context("Failed to send network update")
+    }
+
+    pub fn send_split_update(&self, update: String) -> Result<()> {
+        self.split
+            .send(update)
+            .context("Failed to send split update")
+    }
+
+    pub fn send_displays_update(&self, update: String) -> Result<()> {
+        self.displays
+            .send(update)
+            .context("Failed to send displays update")
+    }
+
+    pub fn send_window_count_update(&self, update: BTreeMap<i32, usize>) -> Result<()> {
+        self.window_count
+            .send(update)
+            .context("Failed to send window count update")
+    }
+
+    pub fn send_floating_update(&self, update: String) -> Result<()> {
+        self.floating
+            .send(update)
+            .context("Failed to send floating window update")
+    }
+
+    pub fn send_scratchpad_update(&self, update: Vec<String>) -> Result<()> {
+        self.scratchpad
+            .send(update)
+            .context("Failed to send scratchpad update")
+    }
+
+    pub fn send_cpufreq_update(&self, update: String) -> Result<()> {
+        self.cpufreq
+            .send(update)
+            .context("Failed to send CPU frequency update")
+    }
+
+    pub fn send_vpn_update(&self, update: String) -> Result<()> {
+        self.vpn.send(update).context("Failed to send VPN update")
+    }
+
+    pub fn send_hotspot_update(&self, update: String) -> Result<()> {
+        self.hotspot
+            .send(update)
+            .context("Failed to send hotspot update")
+    }
+
+    pub fn send_rfkill_update(&self, update: RfkillUpdate) -> Result<()> {
+        self.rfkill
+            .send(update)
+            .context("Failed to send rfkill update")
+    }
+
+    // Labels of the watched units that are currently failed.
+    pub fn send_units_update(&self, failed: Vec<String>) -> Result<()> {
+        self.units
+            .send(failed)
+            .context("Failed to send unit health update")
+    }
+
+    pub fn send_updates_update(&self, update: String) -> Result<()> {
+        self.updates
+            .send(update)
+            .context("Failed to send package updates update")
+    }
+
+    pub fn send_clipboard_update(&self, update: String) -> Result<()> {
+        self.clipboard
+            .send(update)
+            .context("Failed to send clipboard update")
+    }
+
+    pub fn send_gpu_update(&self, update: GpuUpdate) -> Result<()> {
+        self.gpu.send(update).context("Failed to send GPU update")
+    }
+
+    pub fn send_notifications_update(&self, update: NotificationsUpdate) -> Result<()> {
+        self.notifications
+            .send(update)
+            .context("Failed to send notifications update")
+    }
+
+    pub fn send_progress_update(&self, update: ProgressUpdate) -> Result<()> {
+        self.progress
+            .send(update)
+            .context("Failed to send progress update")
+    }
+
+    pub fn send_loadavg_update(&self, update: LoadAvgUpdate) -> Result<()> {
+        self.loadavg
+            .send(update)
+            .context("Failed to send load average update")
+    }
+
+    pub fn send_kbd_backlight_update(&self, update: KbdBacklightUpdate) -> Result<()> {
+        self.kbd_backlight
+            .send(update)
+            .context("Failed to send keyboard backlight update")
+    }
+
+    pub fn send_lock_keys_update(&self, update: String) -> Result<()> {
+        self.lock_keys
+            .send(update)
+            .context("Failed to send lock keys update")
+    }
+
+    // Unlike the widget channels, nobody has to listen: without `[hooks]`
+    // the receiver is dropped and events go nowhere.
+    pub fn send_hook_event(&self, event: HookEvent) {
+        let _ = self.hooks.send(event);
+    }
+
+    // Nobody has to listen here either; send_modify stores the bump even
+    // with no receiver alive.
+    pub fn send_resume(&self) {
+        self.resume.send_modify(|count| *count += 1);
+    }
+
+    // Changes once per resume from here on.
+    pub fn resumes(&self) -> watch::Receiver<u64> {
+        self.resume.subscribe()
+    }
+
+    pub fn state(&self) -> &AppState {
+        &self.state
+    }
+
+    pub fn send_bandwidth_update(&self, update: String) -> Result<()> {
+        self.bandwidth
+            .send(update)
+            .context("Failed to send bandwidth update")
+    }
+
+    pub fn send_taskbar_update(&self, update: TaskbarUpdate) -> Result<()> {
+        self.taskbar
+            .send(update)
+            .context("Failed to send taskbar update")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every test builds its own Bus, so unlike the old OnceLock statics there
+    // is no per-process limit on bus-mediated tests. try_recv (rather than
+    // .await) is enough because channel::Sender::send is fully synchronous —
+    // the message is in the queue before send returns.
+
+    #[test]
+    fn workspace_update_round_trips() {
+        let (bus, mut rx) = Bus::new();
+        bus.send_workspace_update(WorkspaceUpdate {
+            name: "ws".to_string(),
+            id: 1,
+            special: false,
+        })
+        .expect("send_workspace_update should succeed");
+        let ws = rx.workspace.try_recv().expect("workspace message in queue");
+        assert_eq!(ws.name, "ws");
+        assert_eq!(ws.id, 1);
+        assert!(!ws.special);
+    }
+
+    #[test]
+    fn title_update_round_trips() {
+        let (bus, mut rx) = Bus::new();
+        let update = TitleUpdate {
+            title: "hello".to_string(),
+            class: "kitty".to_string(),
+            pid: Some(4242),
+        };
+        bus.send_title_update(update.clone())
+            .expect("send_title_update should succeed");
+        assert_eq!(rx.title.try_recv().expect("title message"), update);
+    }
+
+    #[test]
+    fn sends_also_update_the_state() {
+        let (bus, _rx) = Bus::new();
+        let mut title = bus.state().active_window();
+        let update = TitleUpdate {
+            title: "hello".to_string(),
+            class: "kitty".to_string(),
+            pid: None,
+        };
+        bus.send_title_update(update.clone())
+            .expect("send_title_update should succeed");
+        assert!(title.has_changed().expect("state is alive"));
+        assert_eq!(*title.borrow_and_update(), update);
+    }
+
+    #[test]
+    fn every_subscriber_sees_a_resume() {
+        let (bus, _rx) = Bus::new();
+        let mut first = bus.resumes();
+        let mut second = bus.clone().resumes();
+        assert!(!first.has_changed().expect("sender is alive"));
+        bus.send_resume();
+        assert!(first.has_changed().expect("sender is alive"));
+        assert!(second.has_changed().expect("sender is alive"));
+        first.mark_unchanged();
+        assert!(!first.has_changed().expect("sender is alive"));
+        assert_eq!(*second.borrow_and_update(), 1);
+    }
+
+    #[test]
+    fn status_updates_round_trip() {
+        let (bus, mut rx) = Bus::new();
+        let battery = BatteryUpdate {
+            text: "🔋 80%".to_string(),
+            percent: Some(80.0),
+            state: BatteryState::Discharging,
+        };
+        bus.send_battery_update(battery.clone())
+            .expect("send_battery_update should succeed");
+        let bluetooth = BluetoothUpdate {
+            text: "P80".to_string(),
+            connecting: false,
+        };
+        bus.send_bluetooth_update(bluetooth.clone())
+            .expect("send_bluetooth_update should succeed");
+        bus.send_network_update("🌐 ✓".to_string())
+            .expect("send_network_update should succeed");
+        assert_eq!(rx.battery.try_recv().expect("battery message"), battery);
+        assert_eq!(
+            rx.bluetooth.try_recv().expect("bluetooth message"),
+            bluetooth
+        );
+        assert_eq!(rx.network.try_recv().expect("network message"), "🌐 ✓");
+    }
+
+    // Two bars in one process (or two tests in one binary) each own their Bus.
+    // Nothing is global, so a send on one must never surface on the other.
+    #[test]
+    fn separate_buses_are_isolated() {
+        let (first, mut first_rx) = Bus::new();
+        let (_second, mut second_rx) = Bus::new();
+        first
+            .send_network_update("🌐 ✓".to_string())
+            .expect("send_network_update should succeed");
+        assert_eq!(first_rx.network.try_recv().expect("own message"), "🌐 ✓");
+        assert!(second_rx.network.try_recv().is_err());
+    }
+
+    // With the receivers dropped, sends must fail with the layered context
+    // (helper's message wrapping tokio's closed-channel error) rather than
+    // panic. Widgets never drop their receivers in practice, but the
+    // supervisors keep producers alive across GTK teardown so the error path
+    // is reachable during shutdown.
+    #[test]
+    fn send_into_closed_channel_reports_layered_context() {
+        let (bus, rx) = Bus::new();
+        drop(rx);
+        let err = bus
+            .send_title_update(TitleUpdate {
+                title: "x".to_string(),
+                class: "example".to_string(),
+                pid: None,
+            })
+            .expect_err("send into closed channel must fail");
+        let chain = format!("{:#}", err);
+        assert!(
+            chain.contains("Failed to send title update"),
+            "outer context missing: {}",
+            chain
+        );
+        assert!(
+            chain.to_lowercase().contains("channel closed"),
+            "root cause missing: {}",
+            chain
+        );
+    }
+}
@@ -0,0 +1,425 @@
+// D-Bus connections shared by every monitor: one to the system bus (UPower,
+// BlueZ, logind, NetworkManager) and one to the session bus (portals, and the
+// MPRIS, notification and obex features to come), each opened on first use.
+// Not to be confused with bus::Bus, the bar's internal producer channels.
+//
+// A monitor declares the bus it needs and the MatchRules it cares about, and
+// gets back a Signals stream carrying only the signals those rules match. One
+// dispatcher task per connection reads everything that arrives and routes it
+// through the registry of subscriptions, so monitors no longer each open a
+// connection and sift through one another's traffic. Rules are registered
+// with the bus daemon once however many subscriptions share them, and
+// removed when the last of those is dropped.
+//
+// Signals arrive from the sender's unique name, so a rule's well-known sender
+// (org.bluez, say) can only be checked by the bus daemon, not by the routing
+// here. Every rule should therefore also pin a path or path namespace, or it
+// picks up other subscriptions' signals of the same shape.
+//
+// Reconnection stays with the monitors' supervisors: when a connection drops,
+// its dispatcher ends every subscription's stream, the supervisors see their
+// monitor return and retry as before, and the first subscribe after that opens
+// a fresh connection.
+//
+// Because the dispatcher drains the connection into unbounded channels as fast
+// as signals arrive, a monitor that is busy awaiting a method reply can never
+// backpressure the connection's reader, so replies and signals can share one
+// connection.
+//
+// The tray keeps its own session connection: it may own the
+// StatusNotifierWatcher name and serve objects, which have to go away with it.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use futures_util::StreamExt;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+use zbus::message::Type as MessageType;
+use zbus::{Connection, MatchRule, Message, fdo};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BusKind {
+    System,
+    Session,
+}
+
+impl BusKind {
+    async fn connect(self) -> Result<Connection> {
+        match self {
+            Self::System => Connection::system()
+                .await
+                .context("connect to system D-Bus"),
+            Self::Session => Connection::session()
+                .await
+                .context("connect to session D-Bus"),
+        }
+    }
+}
+
+struct Subscriber {
+    id: u64,
+    rules: Vec<MatchRule<'static>>,
+    tx: mpsc::UnboundedSender<Message>,
+}
+
+// Subscriptions of one connection, and how many of them use each rule. A rule
+// is registered with the bus daemon while its count is above zero.
+#[derive(Default)]
+struct Registry {
+    next_id: u64,
+    subscribers: Vec<Subscriber>,
+    rule_counts: HashMap<MatchRule<'static>, usize>,
+}
+
+impl Registry {
+    // The new subscription's id and the rules no one had registered yet.
+    fn add(
+        &mut self,
+        rules: Vec<MatchRule<'static>>,
+        tx: mpsc::UnboundedSender<Message>,
+    ) -> (u64, Vec<MatchRule<'static>>) {
+        let id = self.next_id;
+        self.next_id += 1;
+        let new_rules = self.acquire(&rules);
+        self.subscribers.push(Subscriber { id, rules, tx });
+        (id, new_rules)
+    }
+
+    // The rules no remaining subscription uses.
+    fn remove(&mut self, id: u64) -> Vec<MatchRule<'static>> {
+        let Some(index) = self
+            .subscribers
+            .iter()
+            .position(|subscriber| subscriber.id == id)
+        else {
+            return Vec::new();
+        };
+        let subscriber = self.subscribers.swap_remove(index);
+        self.release(subscriber.rules)
+    }
+
+    // Adds rules to a live subscription, skipping those it has already;
+    // returns the ones no one had registered yet.
+    fn extend(&mut self, id: u64, rules: Vec<MatchRule<'static>>) -> Vec<MatchRule<'static>> {
+        let Some(index) = self
+            .subscribers
+            .iter()
+            .position(|subscriber| subscriber.id == id)
+        else {
+            return Vec::new();
+        };
+        let mut added = Vec::new();
+        for rule in rules {
+            if !self.subscribers[index].rules.contains(&rule) && !added.contains(&rule) {
+                added.push(rule);
+            }
+        }
+        self.subscribers[index].rules.extend(added.iter().cloned());
+        self.acquire(&added)
+    }
+
+    // Drops rules from a live subscription; returns the ones no remaining
+    // subscription uses.
+    fn retract(&mut self, id: u64, rules: &[MatchRule<'static>]) -> Vec<MatchRule<'static>> {
+        let Some(subscriber) = self
+            .subscribers
+            .iter_mut()
+            .find(|subscriber| subscriber.id == id)
+        else {
+            return Vec::new();
+        };
+        let (dropped, kept) = std::mem::take(&mut subscriber.rules)
+            .into_iter()
+            .partition(|rule| rules.contains(rule));
+        subscriber.rules = kept;
+        self.release(dropped)
+    }
+
+    fn acquire(&mut self, rules: &[MatchRule<'static>]) -> Vec<MatchRule<'static>> {
+        let mut new_rules = Vec::new();
+        for rule in rules {
+            let count = self.rule_counts.entry(rule.clone()).or_default();
+            *count += 1;
+            if *count == 1 {
+                new_rules.push(rule.clone());
+            }
+        }
+        new_rules
+    }
+
+    fn release(&mut self, rules: Vec<MatchRule<'static>>) -> Vec<MatchRule<'static>> {
+        let mut unused = Vec::new();
+        for rule in rules {
+            let Some(count) = self.rule_counts.get_mut(&rule) else {
+                continue;
+            };
+            *count -= 1;
+            if *count == 0 {
+                self.rule_counts.remove(&rule);
+                unused.push(rule);
+            }
+        }
+        unused
+    }
+
+    // Hand the signal to every subscription with a matching rule, forgetting
+    // those whose Signals was dropped meanwhile (their rules are released by
+    // the drop).
+    fn dispatch(&mut self, message: &Message) {
+        self.subscribers.retain(|subscriber| {
+            let matches = subscriber
+                .rules
+                .iter()
+                .any(|rule| rule.matches(message).unwrap_or(false));
+            !matches || subscriber.tx.send(message.clone()).is_ok()
+        });
+    }
+}
+
+struct Live {
+    connection: Connection,
+    registry: Arc<Mutex<Registry>>,
+    dispatcher: JoinHandle<()>,
+}
+
+#[derive(Default)]
+struct SharedBus {
+    live: tokio::sync::Mutex<Option<Live>>,
+}
+
+// Ends every subscription once the connection is gone, which is how monitors
+// learn about it.
+async fn run_dispatcher(kind: BusKind, connection: Connection, registry: Arc<Mutex<Registry>>) {
+    let mut stream = zbus::MessageStream::from(&connection);
+    while let Some(message) = stream.next().await {
+        let message = match message {
+            Ok(message) => message,
+            Err(e) => {
+                debug!(?kind, "Skipping unreadable D-Bus message: {}", e);
+                continue;
+            }
+        };
+        if message.message_type() != MessageType::Signal {
+            continue;
+        }
+        lock(&registry).dispatch(&message);
+    }
+    warn!(?kind, "D-Bus connection closed; ending its subscriptions");
+    lock(&registry).subscribers.clear();
+}
+
+// The registry is only held for plain bookkeeping, never across an await, so
+// a poisoned lock means a panic mid-bookkeeping; the counts are still the best
+// information there is.
+fn lock(registry: &Mutex<Registry>) -> std::sync::MutexGuard<'_, Registry> {
+    registry
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[derive(Clone, Default)]
+pub struct BusManager {
+    system: Arc<SharedBus>,
+    session: Arc<SharedBus>,
+}
+
+impl BusManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn shared(&self, kind: BusKind) -> &SharedBus {
+        match kind {
+            BusKind::System => &self.system,
+            BusKind::Session => &self.session,
+        }
+    }
+
+    async fn live(&self, kind: BusKind) -> Result<(Connection, Arc<Mutex<Registry>>)> {
+        let mut live = self.shared(kind).live.lock().await;
+        if let Some(current) = live.as_ref()
+            && !current.dispatcher.is_finished()
+        {
+            return Ok((current.connection.clone(), current.registry.clone()));
+        }
+        let connection = kind.connect().await?;
+        info!(?kind, "Opened shared D-Bus connection");
+        let registry = Arc::new(Mutex::new(Registry::default()));
+        let dispatcher = tokio::spawn(run_dispatcher(kind, connection.clone(), registry.clone()));
+        *live = Some(Live {
+            connection: connection.clone(),
+            registry: registry.clone(),
+            dispatcher,
+        });
+        Ok((connection, registry))
+    }
+
+    // The shared connection, for method calls and property reads.
+    pub async fn connection(&self, kind: BusKind) -> Result<Connection> {
+        Ok(self.live(kind).await?.0)
+    }
+
+    // Signals matching any of `rules`. Once this returns the rules are in
+    // effect, so state read afterwards cannot miss a change: take snapshots
+    // after subscribing, never before.
+    pub async fn subscribe(
+        &self,
+        kind: BusKind,
+        rules: Vec<MatchRule<'static>>,
+    ) -> Result<Signals> {
+        let (connection, registry) = self.live(kind).await?;
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (id, new_rules) = lock(&registry).add(rules, tx);
+        // Built before registering, so a failure below releases the rules.
+        let signals = Signals {
+            rx,
+            id,
+            connection: connection.clone(),
+            registry,
+        };
+        let dbus = fdo::DBusProxy::new(&connection)
+            .await
+            .context("open D-Bus proxy for match rules")?;
+        for rule in new_rules {
+            debug!(?kind, %rule, "Registering match rule");
+            dbus.add_match_rule(rule)
+                .await
+                .context("register match rule")?;
+        }
+        Ok(signals)
+    }
+}
+
+// One subscription's signals. Dropping it unsubscribes.
+pub struct Signals {
+    rx: mpsc::UnboundedReceiver<Message>,
+    id: u64,
+    connection: Connection,
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl Signals {
+    // None once the connection is gone.
+    pub async fn next(&mut self) -> Option<Message> {
+        self.rx.recv().await
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    // Widens the subscription, for rules that depend on what the monitor has
+    // discovered (one per device, say). As with subscribe, the rules are in
+    // effect once this returns.
+    pub async fn add_rules(&mut self, rules: Vec<MatchRule<'static>>) -> Result<()> {
+        let new_rules = lock(&self.registry).extend(self.id, rules);
+        if new_rules.is_empty() {
+            return Ok(());
+        }
+        let dbus = fdo::DBusProxy::new(&self.connection)
+            .await
+            .context("open D-Bus proxy for match rules")?;
+        for rule in new_rules {
+            debug!(%rule, "Registering match rule");
+            dbus.add_match_rule(rule)
+                .await
+                .context("register match rule")?;
+        }
+        Ok(())
+    }
+
+    pub async fn remove_rules(&mut self, rules: &[MatchRule<'static>]) -> Result<()> {
+        let unused = lock(&self.registry).retract(self.id, rules);
+        if unused.is_empty() {
+            return Ok(());
+        }
+        let dbus = fdo::DBusProxy::new(&self.connection)
+            .await
+            .context("open D-Bus proxy for match rules")?;
+        for rule in unused {
+            debug!(%rule, "Removing match rule");
+            dbus.remove_match_rule(rule)
+                .await
+                .context("remove match rule")?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Signals {
+    fn drop(&mut self) {
+        let unused = lock(&self.registry).remove(self.id);
+        if unused.is_empty() {
+            return;
+        }
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        let connection = self.connection.clone();
+        runtime.spawn(async move {
+            let Ok(dbus) = fdo::DBusProxy::new(&connection).await else {
+                return;
+            };
+            for rule in unused {
+                if let Err(e) = dbus.remove_match_rule(rule).await {
+                    debug!("Failed to remove match rule: {}", e);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(member: &'static str) -> MatchRule<'static> {
+        MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .member(member)
+            .expect("valid member name")
+            .build()
+    }
+
+    #[test]
+    fn shared_rules_register_once_and_release_with_the_last_user() {
+        let mut registry = Registry::default();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let (first, new) = registry.add(vec![rule("A"), rule("B")], tx.clone());
+        assert_eq!(new, vec![rule("A"), rule("B")]);
+        let (second, new) = registry.add(vec![rule("B"), rule("C")], tx);
+        assert_eq!(new, vec![rule("C")]);
+
+        assert_eq!(registry.remove(first), vec![rule("A")]);
+        assert_eq!(registry.remove(second), vec![rule("B"), rule("C")]);
+        assert!(registry.remove(second).is_empty());
+        assert!(registry.rule_counts.is_empty());
+    }
+
+    #[test]
+    fn rules_added_later_are_counted_like_the_first() {
+        let mut registry = Registry::default();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let (first, _) = registry.add(vec![rule("A")], tx.clone());
+        let (second, _) = registry.add(vec![rule("B")], tx);
+
+        assert_eq!(
+            registry.extend(first, vec![rule("A"), rule("B"), rule("C")]),
+            vec![rule("C")]
+        );
+        assert!(registry.extend(first, vec![rule("C")]).is_empty());
+        assert_eq!(registry.rule_counts[&rule("B")], 2);
+
+        assert!(registry.retract(first, &[rule("B")]).is_empty());
+        assert_eq!(
+            registry.retract(first, &[rule("C"), rule("D")]),
+            vec![rule("C")]
+        );
+        assert_eq!(registry.remove(second), vec![rule("B")]);
+        assert_eq!(registry.remove(first), vec![rule("A")]);
+        assert!(registry.extend(first, vec![rule("A")]).is_empty());
+    }
+}
@@ -0,0 +1,242 @@
+// A bounded channel that drops its oldest message instead of blocking or
+// failing when full. The producers behind the Bus and PipeWire are IPC
+// listeners that must never wait on the GTK main loop (see bus.rs), but an
+// unbounded queue lets a stalled main loop grow memory with every D-Bus signal
+// and volume change until it comes back. Nearly everything sent is the whole
+// current state rather than a delta, so once a queue is full the oldest entry
+// is the one nobody needs: the consumer catches up to the newest state all the
+// same, just without the stale steps in between.
+//
+// The API mirrors tokio's unbounded mpsc (send is synchronous and fails only
+// once the receiver is gone; recv, try_recv and poll_recv on the other end),
+// so the Bus, its tests and the widget drains read as before. Channels whose
+// messages are commands, deltas or events (tray items, IPC, notification
+// popups, hook events, sinks being plugged in) stay on tokio's mpsc: dropping
+// one of those would lose something for good.
+
+use std::collections::VecDeque;
+use std::future::poll_fn;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+use tokio::sync::mpsc::error::{SendError, TryRecvError};
+use tracing::{debug, warn};
+
+struct Queue<T> {
+    messages: VecDeque<T>,
+    waker: Option<Waker>,
+    senders: usize,
+    receiver_alive: bool,
+    // Dropped since the receiver last caught up; non-zero while overflowing.
+    dropped: u64,
+}
+
+struct Shared<T> {
+    name: &'static str,
+    capacity: usize,
+    queue: Mutex<Queue<T>>,
+}
+
+impl<T> Shared<T> {
+    // A panic while holding the lock cannot leave the queue half-updated, so
+    // a poisoned lock is still good to use.
+    fn lock(&self) -> MutexGuard<'_, Queue<T>> {
+        self.queue
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+// `name` labels the overflow warning; `capacity` is at least one.
+pub fn channel<T>(name: &'static str, capacity: usize) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        name,
+        capacity: capacity.max(1),
+        queue: Mutex::new(Queue {
+            messages: VecDeque::new(),
+            waker: None,
+            senders: 1,
+            receiver_alive: true,
+            dropped: 0,
+        }),
+    });
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared },
+    )
+}
+
+impl<T> Sender<T> {
+    // Never blocks. Fails, handing the message back, once the receiver is
+    // dropped.
+    pub fn send(&self, message: T) -> Result<(), SendError<T>> {
+        let waker = {
+            let mut queue = self.shared.lock();
+            if !queue.receiver_alive {
+                return Err(SendError(message));
+            }
+            if queue.messages.len() >= self.shared.capacity {
+                queue.messages.pop_front();
+                if queue.dropped == 0 {
+                    warn!(
+                        channel = self.shared.name,
+                        capacity = self.shared.capacity,
+                        "⚠️ Consumer is not keeping up; dropping the oldest updates"
+                    );
+                }
+                queue.dropped += 1;
+            }
+            queue.messages.push_back(message);
+            queue.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    pub fn is_closed(&self) -> bool {
+        !self.shared.lock().receiver_alive
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().senders += 1;
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let waker = {
+            let mut queue = self.shared.lock();
+            queue.senders -= 1;
+            if queue.senders > 0 {
+                return;
+            }
+            queue.waker.take()
+        };
+        // The last sender is gone: a waiting recv has to see the end.
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    // None once every sender is dropped and the queue is empty.
+    pub async fn recv(&mut self) -> Option<T> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut queue = self.shared.lock();
+        if let Some(message) = self.take(&mut queue) {
+            return Poll::Ready(Some(message));
+        }
+        if queue.senders == 0 {
+            return Poll::Ready(None);
+        }
+        queue.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut queue = self.shared.lock();
+        if let Some(message) = self.take(&mut queue) {
+            return Ok(message);
+        }
+        if queue.senders == 0 {
+            return Err(TryRecvError::Disconnected);
+        }
+        Err(TryRecvError::Empty)
+    }
+
+    fn take(&self, queue: &mut Queue<T>) -> Option<T> {
+        let message = queue.messages.pop_front()?;
+        if queue.messages.is_empty() && queue.dropped > 0 {
+            debug!(
+                channel = self.shared.name,
+                dropped = queue.dropped,
+                "Consumer caught up"
+            );
+            queue.dropped = 0;
+        }
+        Some(message)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut queue = self.shared.lock();
+        queue.receiver_alive = false;
+        // Nobody will read them; free them now rather than with the last
+        // sender.
+        queue.messages.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_full_channel_drops_the_oldest() {
+        let (tx, mut rx) = channel("test", 2);
+        for n in 1..=5 {
+            tx.send(n).expect("receiver is alive");
+        }
+        assert_eq!(rx.try_recv(), Ok(4));
+        assert_eq!(rx.try_recv(), Ok(5));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        tx.send(6).expect("receiver is alive");
+        assert_eq!(rx.try_recv(), Ok(6));
+    }
+
+    #[test]
+    fn either_end_closing_is_seen_by_the_other() {
+        let (tx, mut rx) = channel("test", 4);
+        let second = tx.clone();
+        tx.send(1).expect("receiver is alive");
+        drop(tx);
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        drop(second);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+
+        let (tx, rx) = channel("test", 4);
+        drop(rx);
+        assert!(tx.is_closed());
+        assert_eq!(tx.send(7).map_err(|SendError(n)| n), Err(7));
+    }
+
+    #[test]
+    fn recv_wakes_on_send_and_ends_with_the_senders() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .expect("build test runtime");
+        runtime.block_on(async {
+            let (tx, mut rx) = channel("test", 4);
+            let producer = tokio::spawn(async move {
+                tokio::task::yield_now().await;
+                tx.send("late").expect("receiver is alive");
+            });
+            assert_eq!(rx.recv().await, Some("late"));
+            producer.await.expect("producer finished");
+            assert_eq!(rx.recv().await, None);
+        });
+    }
+}
@@ -0,0 +1,374 @@
+// What the bar needs from a compositor, behind one trait so the workspace and
+// title widgets work on more than Hyprland: the active workspace and focused
+// window as streams into the Bus, the workspace list, switching workspaces,
+// and focusing or closing a window. hypr.rs and sway.rs (Sway and i3) implement it; AnyCompositor
+// picks one at startup from the environment each compositor exports to its
+// clients.
+//
+// Hyprland-only extras (split hint, display count, taskbar, focus mode) stay
+// in hypr.rs and are simply not started elsewhere.
+
+use std::future::Future;
+
+use anyhow::{Result, anyhow};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+use crate::bus::{Bus, WorkspaceUpdate};
+use crate::dbus::supervise;
+use crate::format::WorkspaceLabels;
+use crate::hypr;
+use crate::sway::Sway;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Workspace {
+    pub id: i32,
+    // Display name, already formatted for the widget.
+    pub name: String,
+    pub focused: bool,
+    pub special: bool,
+}
+
+pub trait Compositor: Clone + Send + Sync + 'static {
+    const NAME: &'static str;
+
+    // Stream active-workspace changes into the bus until the connection
+    // fails. The initial workspace comes from `workspaces`.
+    fn watch_workspace(&self, bus: &Bus) -> impl Future<Output = Result<()>> + Send;
+
+    // Send the focused window now and on every change, until the connection
+    // fails.
+    fn watch_active_window(&self, bus: &Bus) -> impl Future<Output = Result<()>> + Send;
+
+    fn workspaces(&self) -> impl Future<Output = Result<Vec<Workspace>>> + Send;
+
+    fn focus_window(&self, address: &str) -> impl Future<Output = Result<()>> + Send;
+
+    fn close_window(&self, address: &str) -> impl Future<Output = Result<()>> + Send;
+
+    // Show the workspace with this id (Sway's number), creating it if needed.
+    fn focus_workspace(&self, id: i32) -> impl Future<Output = Result<()>> + Send;
+}
+
+// The workspace `step` places from the focused one, for scroll switching:
+// +1 the next, -1 the previous. Only numbered, non-special workspaces count.
+// With `skip_empty` the steps go through the workspaces that exist (both
+// compositors drop an empty workspace once it loses focus); without it they go
+// by number, past the highest into a new one unless `wrap` brings them back
+// to 1. None when there is nowhere to go, or no numbered workspace is focused.
+pub fn scroll_target(
+    workspaces: &[Workspace],
+    step: i32,
+    wrap: bool,
+    skip_empty: bool,
+) -> Option<i32> {
+    let mut ids: Vec<i32> = workspaces
+        .iter()
+        .filter(|workspace| !workspace.special && workspace.id > 0)
+        .map(|workspace| workspace.id)
+        .collect();
+    ids.sort_unstable();
+    ids.dedup();
+    let current = workspaces
+        .iter()
+        .find(|workspace| workspace.focused && !workspace.special && workspace.id > 0)?
+        .id;
+
+    let target = if skip_empty {
+        let index = ids.iter().position(|id| *id == current)? as i32 + step;
+        if wrap {
+            Some(ids[index.rem_euclid(ids.len() as i32) as usize])
+        } else {
+            usize::try_from(index)
+                .ok()
+                .and_then(|index| ids.get(index))
+                .copied()
+        }
+    } else {
+        let highest = ids.last().copied().unwrap_or(current).max(current);
+        match current + step {
+            target if target < 1 => wrap.then_some(highest),
+            target if target > highest && wrap => Some(1),
+            target => Some(target),
+        }
+    };
+    // A lone workspace wraps onto itself.
+    target.filter(|target| *target != current)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Hyprland {
+    pub labels: WorkspaceLabels,
+}
+
+impl Compositor for Hyprland {
+    const NAME: &'static str = "Hyprland";
+
+    async fn watch_workspace(&self, bus: &Bus) -> Result<()> {
+        hypr::setup_workspace_event_listener(bus, &self.labels).await
+    }
+
+    async fn watch_active_window(&self, bus: &Bus) -> Result<()> {
+        hypr::setup_title_event_listener(bus).await
+    }
+
+    async fn workspaces(&self) -> Result<Vec<Workspace>> {
+        hypr::workspaces(&self.labels).await
+    }
+
+    async fn focus_window(&self, address: &str) -> Result<()> {
+        hypr::focus_window(address).await
+    }
+
+    async fn close_window(&self, address: &str) -> Result<()> {
+        hypr::close_window(address).await
+    }
+
+    async fn focus_workspace(&self, id: i32) -> Result<()> {
+        hypr::focus_workspace(id).await
+    }
+}
+
+impl Compositor for Sway {
+    const NAME: &'static str = "Sway";
+
+    async fn watch_workspace(&self, bus: &Bus) -> Result<()> {
+        Sway::watch_workspace(self, bus).await
+    }
+
+    async fn watch_active_window(&self, bus: &Bus) -> Result<()> {
+        Sway::watch_active_window(self, bus).await
+    }
+
+    async fn workspaces(&self) -> Result<Vec<Workspace>> {
+        Sway::workspaces(self).await
+    }
+
+    async fn focus_window(&self, address: &str) -> Result<()> {
+        Sway::focus_window(self, address).await
+    }
+
+    async fn close_window(&self, address: &str) -> Result<()> {
+        Sway::close_window(self, address).await
+    }
+
+    async fn focus_workspace(&self, id: i32) -> Result<()> {
+        Sway::focus_workspace(self, id).await
+    }
+}
+
+async fn send_initial_workspace<C: Compositor>(compositor: &C, bus: &Bus) -> Result<()> {
+    let workspaces = compositor.workspaces().await?;
+    let focused = workspaces
+        .into_iter()
+        .find(|workspace| workspace.focused)
+        .ok_or_else(|| anyhow!("no focused workspace"))?;
+    bus.send_workspace_update(WorkspaceUpdate {
+        name: focused.name,
+        id: focused.id,
+        special: focused.special,
+    })
+}
+
+// Supervised workspace stream. The inner watcher returns when the compositor
+// drops the IPC connection (or a send fails); dbus::supervise logs the cause,
+// sleeps with exponential backoff (1s -> 2s -> 4s -> ... capped at 60s), and
+// reconnects.
+// Backoff resets if the previous attempt ran for more than 30s, so a stable
+// listener that briefly hiccups recovers fast, while a persistent failure
+// (e.g. wrong env, compositor gone) doesn't busy-loop.
+//
+// This function never returns and is meant to be `tokio::spawn`ed.
+pub async fn run_workspace_listener_supervised<C: Compositor>(compositor: C, bus: Bus) {
+    info!(compositor = C::NAME, "Watching workspaces");
+    supervise("workspace event listener", || async {
+        if let Err(e) = send_initial_workspace(&compositor, &bus).await {
+            error!("Failed to get initial workspace state: {:#}", e);
+            let fallback_update = WorkspaceUpdate {
+                name: "Workspace ?".to_string(),
+                id: 1,
+                special: false,
+            };
+            if let Err(e) = bus.send_workspace_update(fallback_update) {
+                error!("Failed to send fallback workspace update: {}", e);
+            }
+        }
+        compositor.watch_workspace(&bus).await
+    })
+    .await
+}
+
+// Same supervisor for the focused-window stream; both consume the same IPC
+// and fail in the same shapes, so the policy is identical.
+pub async fn run_title_listener_supervised<C: Compositor>(compositor: C, bus: Bus) {
+    info!(compositor = C::NAME, "Watching the active window title");
+    supervise("title event listener", || {
+        compositor.watch_active_window(&bus)
+    })
+    .await
+}
+
+// The compositor this bar runs under, decided once at startup.
+#[derive(Debug, Clone)]
+pub enum AnyCompositor {
+    Hyprland(Hyprland),
+    Sway(Sway),
+}
+
+impl Default for AnyCompositor {
+    fn default() -> Self {
+        Self::Hyprland(Hyprland::default())
+    }
+}
+
+impl AnyCompositor {
+    // Hyprland wins when both are advertised (a nested session, say). With
+    // neither, Hyprland's listeners still start and report why they fail.
+    pub fn detect() -> Self {
+        if std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+            return Self::Hyprland(Hyprland::default());
+        }
+        if let Some(sway) = Sway::from_env() {
+            return Self::Sway(sway);
+        }
+        warn!("Neither HYPRLAND_INSTANCE_SIGNATURE nor SWAYSOCK/I3SOCK is set; assuming Hyprland");
+        Self::Hyprland(Hyprland::default())
+    }
+
+    pub fn is_hyprland(&self) -> bool {
+        matches!(self, Self::Hyprland(_))
+    }
+
+    // The labels the workspace names are formatted with; set them from the
+    // config, at startup and on reload.
+    pub fn workspace_labels(&self) -> &WorkspaceLabels {
+        match self {
+            Self::Hyprland(hyprland) => &hyprland.labels,
+            Self::Sway(sway) => &sway.labels,
+        }
+    }
+
+    pub fn spawn_workspace_listener(&self, bus: Bus) -> JoinHandle<()> {
+        match self {
+            Self::Hyprland(hyprland) => {
+                tokio::spawn(run_workspace_listener_supervised(hyprland.clone(), bus))
+            }
+            Self::Sway(sway) => tokio::spawn(run_workspace_listener_supervised(sway.clone(), bus)),
+        }
+    }
+
+    pub fn spawn_title_listener(&self, bus: Bus) -> JoinHandle<()> {
+        match self {
+            Self::Hyprland(hyprland) => {
+                tokio::spawn(run_title_listener_supervised(hyprland.clone(), bus))
+            }
+            Self::Sway(sway) => tokio::spawn(run_title_listener_supervised(sway.clone(), bus)),
+        }
+    }
+
+    // Fire-and-forget, for click handlers on the GTK thread.
+    pub fn focus_window(&self, address: &str) {
+        let address = address.to_string();
+        match self.clone() {
+            Self::Hyprland(hyprland) => tokio::spawn(async move {
+                if let Err(e) = hyprland.focus_window(&address).await {
+                    warn!(address, "Failed to focus window: {:#}", e);
+                }
+            }),
+            Self::Sway(sway) => tokio::spawn(async move {
+                if let Err(e) = sway.focus_window(&address).await {
+                    warn!(address, "Failed to focus window: {:#}", e);
+                }
+            }),
+        };
+    }
+
+    pub fn close_window(&self, address: &str) {
+        let address = address.to_string();
+        match self.clone() {
+            Self::Hyprland(hyprland) => tokio::spawn(async move {
+                if let Err(e) = hyprland.close_window(&address).await {
+                    warn!(address, "Failed to close window: {:#}", e);
+                }
+            }),
+            Self::Sway(sway) => tokio::spawn(async move {
+                if let Err(e) = sway.close_window(&address).await {
+                    warn!(address, "Failed to close window: {:#}", e);
+                }
+            }),
+        };
+    }
+
+    // Move `step` workspaces from the focused one (see scroll_target).
+    // Fire-and-forget, for scroll handlers on the GTK thread.
+    pub fn scroll_workspaces(&self, step: i32, wrap: bool, skip_empty: bool) {
+        match self.clone() {
+            Self::Hyprland(hyprland) => tokio::spawn(async move {
+                if let Err(e) = scroll_workspaces(&hyprland, step, wrap, skip_empty).await {
+                    warn!(step, "Failed to switch workspace: {:#}", e);
+                }
+            }),
+            Self::Sway(sway) => tokio::spawn(async move {
+                if let Err(e) = scroll_workspaces(&sway, step, wrap, skip_empty).await {
+                    warn!(step, "Failed to switch workspace: {:#}", e);
+                }
+            }),
+        };
+    }
+}
+
+async fn scroll_workspaces<C: Compositor>(
+    compositor: &C,
+    step: i32,
+    wrap: bool,
+    skip_empty: bool,
+) -> Result<()> {
+    let workspaces = compositor.workspaces().await?;
+    let Some(target) = scroll_target(&workspaces, step, wrap, skip_empty) else {
+        debug!(step, "No workspace to scroll to");
+        return Ok(());
+    };
+    debug!(target, "Scrolling to workspace");
+    compositor.focus_workspace(target).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace(id: i32, focused: bool) -> Workspace {
+        Workspace {
+            id,
+            name: id.to_string(),
+            focused,
+            special: id < 0,
+        }
+    }
+
+    #[test]
+    fn scrolling_steps_through_workspaces() {
+        // 1, 2 and 5 exist, 2 is focused, and a scratchpad is listed too.
+        let workspaces = [
+            workspace(5, false),
+            workspace(1, false),
+            workspace(2, true),
+            workspace(-98, false),
+        ];
+        assert_eq!(scroll_target(&workspaces, 1, false, true), Some(5));
+        assert_eq!(scroll_target(&workspaces, -1, false, true), Some(1));
+        assert_eq!(scroll_target(&workspaces, 1, false, false), Some(3));
+
+        let last = [workspace(1, false), workspace(5, true)];
+        assert_eq!(scroll_target(&last, 1, false, true), None);
+        assert_eq!(scroll_target(&last, 1, true, true), Some(1));
+        assert_eq!(scroll_target(&last, 1, false, false), Some(6));
+        assert_eq!(scroll_target(&last, 1, true, false), Some(1));
+
+        let first = [workspace(1, true), workspace(3, false)];
+        assert_eq!(scroll_target(&first, -1, false, false), None);
+        assert_eq!(scroll_target(&first, -1, true, false), Some(3));
+        assert_eq!(scroll_target(&first, -1, true, true), Some(3));
+        assert_eq!(scroll_target(&[workspace(1, true)], 1, true, true), None);
+        assert_eq!(scroll_target(&[workspace(-98, true)], 1, true, true), None);
+    }
+}
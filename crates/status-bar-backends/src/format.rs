@@ -0,0 +1,478 @@
+// Text formatting for the title and workspace widgets, apart from the
+// compositors' event plumbing so it can be tested on its own: workspace names
+// and their `[workspace.labels]`, `[[title_rewrite]]` rules, and cutting a
+// title down to a length with an ellipsis.
+//
+// Lengths count grapheme clusters (unicode-segmentation) rather than chars, so
+// a flag, an emoji with a skin tone or a ZWJ sequence, or a letter with
+// combining accents is kept or cut whole. Counting chars used to split those
+// into code points that render as something else (a lone regional indicator,
+// a bare accent on the ellipsis), and count one on-screen symbol as up to
+// seven.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+
+// Producers cut titles only this far, against the odd multi-kilobyte one (a
+// data: URL in a browser tab); the title widget applies the configured limit.
+pub const MAX_TITLE_CHARS: usize = 512;
+
+const ELLIPSIS: &str = "…";
+
+// Custom workspace labels (`[workspace.labels]` in the bar's config), keyed
+// by workspace name or id, with special workspaces as "special:<name>" the
+// way Hyprland names them. Shared between the front-end and the listeners, so
+// a config reload shows on the next workspace event without a restart.
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceLabels(Arc<RwLock<BTreeMap<String, String>>>);
+
+impl WorkspaceLabels {
+    pub fn set(&self, labels: BTreeMap<String, String>) {
+        // A panic elsewhere mid-write leaves a whole map either way.
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = labels;
+    }
+
+    fn get(&self, key: &str) -> Option<String> {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(key)
+            .cloned()
+    }
+}
+
+pub fn format_workspace_name_from_string(
+    name: &str,
+    id: hyprland::shared::WorkspaceId,
+    labels: &WorkspaceLabels,
+) -> String {
+    if let Some(label) = labels.get(name).or_else(|| labels.get(&id.to_string())) {
+        return label;
+    }
+    if name.is_empty() {
+        return format!("Workspace {}", id);
+    }
+    format!("Workspace {}", name)
+}
+
+pub fn format_workspace_name_from_type(
+    name: &hyprland::shared::WorkspaceType,
+    id: hyprland::shared::WorkspaceId,
+    labels: &WorkspaceLabels,
+) -> String {
+    match name {
+        hyprland::shared::WorkspaceType::Regular(name) => {
+            format_workspace_name_from_string(name, id, labels)
+        }
+        hyprland::shared::WorkspaceType::Special(name_opt) => match name_opt {
+            Some(name) if !name.is_empty() => labels
+                .get(&format!("special:{name}"))
+                .unwrap_or_else(|| format!("Special: {}", name)),
+            _ => format!("Special {}", id),
+        },
+    }
+}
+
+// A `[[title_rewrite]]` rule with its regexes built.
+#[derive(Debug, Clone)]
+pub struct TitleRewrite {
+    class: Option<Regex>,
+    pattern: Option<Regex>,
+    replace: String,
+}
+
+impl TitleRewrite {
+    pub fn new(
+        class: Option<&str>,
+        pattern: Option<&str>,
+        replace: &str,
+    ) -> Result<Self, regex::Error> {
+        Ok(Self {
+            class: class.map(Regex::new).transpose()?,
+            pattern: pattern.map(Regex::new).transpose()?,
+            replace: replace.to_string(),
+        })
+    }
+}
+
+// The first rule whose class and pattern both match rewrites the title; a
+// rule without a pattern replaces it whole.
+pub fn rewrite_title(title: &str, class: &str, rewrites: &[TitleRewrite]) -> String {
+    for rewrite in rewrites {
+        if rewrite
+            .class
+            .as_ref()
+            .is_some_and(|regex| !regex.is_match(class))
+        {
+            continue;
+        }
+        let Some(pattern) = &rewrite.pattern else {
+            return rewrite.replace.clone();
+        };
+        if pattern.is_match(title) {
+            return pattern
+                .replace_all(title, rewrite.replace.as_str())
+                .into_owned();
+        }
+    }
+    title.to_string()
+}
+
+// Which part of a cut text the ellipsis stands in for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ellipsis {
+    Start,
+    Middle,
+    End,
+}
+
+pub fn grapheme_count(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+// `text` when it is at most `max` graphemes long; otherwise `max` graphemes
+// with the ellipsis counted among them. The ellipsis alone stands for any
+// text when even one grapheme of it will not fit (max 0 or 1).
+pub fn ellipsize(text: &str, max: usize, at: Ellipsis) -> String {
+    let graphemes: Vec<&str> = text.graphemes(true).collect();
+    if graphemes.len() <= max {
+        return text.to_string();
+    }
+    let keep = max.saturating_sub(1);
+    let (head, tail) = match at {
+        Ellipsis::Start => (0, keep),
+        // The tail gets the odd grapheme.
+        Ellipsis::Middle => (keep / 2, keep - keep / 2),
+        Ellipsis::End => (keep, 0),
+    };
+    let mut cut = graphemes[..head].concat();
+    cut.push_str(ELLIPSIS);
+    cut.push_str(&graphemes[graphemes.len() - tail..].concat());
+    cut
+}
+
+pub fn format_title_string(title: String, max_length: usize) -> String {
+    if title.len() <= max_length {
+        // No more graphemes than bytes.
+        return title;
+    }
+    ellipsize(&title, max_length, Ellipsis::Middle)
+}
+
+#[cfg(test)]
+mod tests {
+    use hyprland::shared::WorkspaceType;
+    use proptest::prelude::*;
+
+    use super::*;
+
+    // Short, exact-length, empty, ASCII, multi-byte, wide (CJK) and
+    // multi-code-point graphemes: flags, a skin tone, a ZWJ family, combining
+    // accents, and a CRLF.
+    const SAMPLES: &[&str] = &[
+        "",
+        "a",
+        "hello",
+        "0123456789",
+        "Inbox - Mail - Firefox",
+        "🚀🚀🚀🚀🚀🚀🚀🚀🚀🚀🚀🚀",
+        "東京の天気予報 - ブラウザ",
+        "🇩🇪🇫🇷🇯🇵🇺🇸 flags",
+        "👍🏽 thumbs 👍🏿",
+        "👨‍👩‍👧‍👦 family 👨‍👩‍👧‍👦",
+        "e\u{301}e\u{301}e\u{301} accents a\u{308}o\u{308}",
+        "line\r\nbreak",
+    ];
+
+    // The generated properties below, pinned to the samples at every limit
+    // and side, plus where the Middle cut puts the odd grapheme.
+    #[test]
+    fn ellipsize_examples_keep_whole_graphemes_within_the_limit() {
+        for text in SAMPLES {
+            let graphemes: Vec<&str> = text.graphemes(true).collect();
+            for max in 0..=graphemes.len() + 2 {
+                for at in [Ellipsis::Start, Ellipsis::Middle, Ellipsis::End] {
+                    let cut = ellipsize(text, max, at);
+                    if graphemes.len() <= max {
+                        assert_eq!(&cut, text);
+                        continue;
+                    }
+                    assert_eq!(grapheme_count(&cut), max.max(1), "{text:?} at {max} {at:?}");
+                    let (head, tail) = cut.split_once(ELLIPSIS).expect("ellipsis in the cut");
+                    assert!(text.starts_with(head) && text.ends_with(tail));
+                    let head_count = grapheme_count(head);
+                    let tail_count = grapheme_count(tail);
+                    assert_eq!(graphemes[..head_count].concat(), head);
+                    assert_eq!(graphemes[graphemes.len() - tail_count..].concat(), tail);
+                    match at {
+                        Ellipsis::Start => assert_eq!(head, ""),
+                        Ellipsis::Middle => assert!(tail_count - head_count <= 1),
+                        Ellipsis::End => assert_eq!(tail, ""),
+                    }
+                }
+            }
+        }
+    }
+
+    // One grapheme-ish piece of a title: ASCII, a letter with up to three
+    // combining marks, a width-2 CJK or kana character, a ZWJ emoji sequence,
+    // a skin-toned emoji, a flag or a CRLF. Pieces can still merge once
+    // concatenated (a flag's halves, a stray mark), which is the point: the
+    // properties check against the text's actual segmentation.
+    fn piece() -> impl Strategy<Value = String> {
+        let emoji = prop::sample::select(vec!['👨', '👩', '👧', '❤', '🚀', '🏳']);
+        prop_oneof![
+            "[ -~]",
+            (
+                "[a-z]",
+                prop::collection::vec(prop::char::range('\u{300}', '\u{36f}'), 1..=3)
+            )
+                .prop_map(|(base, marks)| base + &marks.into_iter().collect::<String>()),
+            prop_oneof![
+                prop::char::range('\u{4e00}', '\u{9fff}'),
+                prop::char::range('\u{3041}', '\u{3096}')
+            ]
+            .prop_map(String::from),
+            prop::collection::vec(emoji, 2..=4).prop_map(|people| {
+                people
+                    .iter()
+                    .map(char::to_string)
+                    .collect::<Vec<_>>()
+                    .join("\u{200d}")
+            }),
+            (
+                prop::char::range('\u{1f44a}', '\u{1f450}'),
+                prop::char::range('\u{1f3fb}', '\u{1f3ff}')
+            )
+                .prop_map(|(hand, tone)| format!("{hand}{tone}")),
+            (
+                prop::char::range('\u{1f1e6}', '\u{1f1ff}'),
+                prop::char::range('\u{1f1e6}', '\u{1f1ff}')
+            )
+                .prop_map(|(a, b)| format!("{a}{b}")),
+            Just("\r\n".to_string()),
+        ]
+    }
+
+    fn title() -> impl Strategy<Value = String> {
+        prop::collection::vec(piece(), 0..24).prop_map(|pieces| pieces.concat())
+    }
+
+    fn side() -> impl Strategy<Value = Ellipsis> {
+        prop_oneof![
+            Just(Ellipsis::Start),
+            Just(Ellipsis::Middle),
+            Just(Ellipsis::End)
+        ]
+    }
+
+    proptest! {
+        // The cut never exceeds the limit (a lone ellipsis below 2), keeps
+        // text that fits as it is, and what it keeps on either side of the
+        // ellipsis is whole graphemes from the text's own start and end.
+        #[test]
+        fn ellipsize_fits_the_limit_on_grapheme_boundaries(
+            text in title(),
+            max in 0usize..32,
+            at in side(),
+        ) {
+            let graphemes: Vec<&str> = text.graphemes(true).collect();
+            let cut = ellipsize(&text, max, at);
+            if graphemes.len() <= max {
+                prop_assert_eq!(cut, text);
+                return Ok(());
+            }
+            prop_assert_eq!(grapheme_count(&cut), max.max(1));
+            let (head, tail) = cut.split_once(ELLIPSIS).expect("ellipsis in the cut");
+            let head_count = grapheme_count(head);
+            let tail_count = grapheme_count(tail);
+            prop_assert_eq!(graphemes[..head_count].concat(), head);
+            prop_assert_eq!(graphemes[graphemes.len() - tail_count..].concat(), tail);
+        }
+
+        #[test]
+        fn format_title_never_exceeds_max_length(text in title(), max in 0usize..32) {
+            let out = format_title_string(text.clone(), max);
+            prop_assert!(grapheme_count(&out) <= max.max(1));
+            if grapheme_count(&text) <= max {
+                prop_assert_eq!(out, text);
+            }
+        }
+    }
+
+    #[test]
+    fn ellipsize_counts_clusters_not_chars() {
+        let flags = "🇩🇪🇫🇷🇯🇵🇺🇸";
+        assert_eq!(ellipsize(flags, 3, Ellipsis::End), "🇩🇪🇫🇷…");
+        assert_eq!(ellipsize(flags, 3, Ellipsis::Start), "…🇯🇵🇺🇸");
+        let accents = "e\u{301}e\u{301}e\u{301}e\u{301}";
+        assert_eq!(ellipsize(accents, 2, Ellipsis::Middle), "…e\u{301}");
+        assert_eq!(ellipsize(accents, 4, Ellipsis::Middle), accents);
+    }
+
+    // format_title_string: short input passes through unchanged.
+    #[test]
+    fn format_title_short_passthrough() {
+        assert_eq!(format_title_string("hello".to_string(), 10), "hello");
+    }
+
+    // Exactly max_length graphemes also passes through (≤ comparison), even
+    // when it is longer in bytes.
+    #[test]
+    fn format_title_exact_max_length_passthrough() {
+        let s = "0123456789".to_string();
+        assert_eq!(format_title_string(s.clone(), 10), s);
+        let wide = "東京の天気予報です。".to_string();
+        assert_eq!(format_title_string(wide.clone(), 10), wide);
+    }
+
+    // Empty string is a no-op regardless of max_length.
+    #[test]
+    fn format_title_empty_passthrough() {
+        assert_eq!(format_title_string(String::new(), 64), "");
+    }
+
+    // Long input gets cropped with an ellipsis in the middle, and the output
+    // fits max_length exactly (the … counts toward the limit). For
+    // max_length=10: 4 left, 5 right.
+    #[test]
+    fn format_title_long_cropped_with_ellipsis() {
+        let out = format_title_string("1234567890ABCDEF".to_string(), 10);
+        assert_eq!(out, "1234…BCDEF");
+        let out = format_title_string("🚀".repeat(16), 10);
+        assert_eq!(out, format!("{}…{}", "🚀".repeat(4), "🚀".repeat(5)));
+    }
+
+    // Degenerate limits must not underflow usize (the old arithmetic panicked
+    // for max_length < 2 on any over-long input). max_length=1 crops to just
+    // the ellipsis; max_length=0 degrades to the same single char.
+    #[test]
+    fn format_title_tiny_max_length_does_not_underflow() {
+        assert_eq!(format_title_string("abcdef".to_string(), 1), "…");
+        assert_eq!(format_title_string("abcdef".to_string(), 0), "…");
+        // max_length=2: 0 left, 1 right.
+        assert_eq!(format_title_string("abcdef".to_string(), 2), "…f");
+    }
+
+    #[test]
+    fn rewrites_apply_in_order_by_class_and_pattern() {
+        let rewrites = [
+            TitleRewrite::new(Some("^org\\.wezfurlong\\.wezterm$"), None, "Terminal")
+                .expect("valid rule"),
+            TitleRewrite::new(None, Some(" — (Mozilla )?Firefox$"), "").expect("valid rule"),
+        ];
+        assert_eq!(
+            rewrite_title("vim", "org.wezfurlong.wezterm", &rewrites),
+            "Terminal"
+        );
+        assert_eq!(
+            rewrite_title("Inbox — Mozilla Firefox", "firefox", &rewrites),
+            "Inbox"
+        );
+        assert_eq!(rewrite_title("Files", "nautilus", &rewrites), "Files");
+        assert!(TitleRewrite::new(Some("("), None, "").is_err());
+    }
+
+    // format_workspace_name_from_string: empty name falls back to id.
+    #[test]
+    fn workspace_name_from_string_empty_uses_id() {
+        let labels = WorkspaceLabels::default();
+        assert_eq!(
+            format_workspace_name_from_string("", 3, &labels),
+            "Workspace 3"
+        );
+    }
+
+    #[test]
+    fn workspace_name_from_string_non_empty() {
+        let labels = WorkspaceLabels::default();
+        assert_eq!(
+            format_workspace_name_from_string("dev", 1, &labels),
+            "Workspace dev"
+        );
+    }
+
+    // format_workspace_name_from_type: Regular delegates to the string form.
+    #[test]
+    fn workspace_name_from_type_regular_delegates() {
+        let ws = WorkspaceType::Regular("scratch".to_string());
+        let labels = WorkspaceLabels::default();
+        assert_eq!(
+            format_workspace_name_from_type(&ws, 7, &labels),
+            "Workspace scratch"
+        );
+    }
+
+    // Special with a name uses "Special: <name>".
+    #[test]
+    fn workspace_name_from_type_special_with_name() {
+        let ws = WorkspaceType::Special(Some("magic".to_string()));
+        let labels = WorkspaceLabels::default();
+        assert_eq!(
+            format_workspace_name_from_type(&ws, 4, &labels),
+            "Special: magic"
+        );
+    }
+
+    // Special with None falls back to "Special <id>".
+    #[test]
+    fn workspace_name_from_type_special_none_uses_id() {
+        let ws = WorkspaceType::Special(None);
+        let labels = WorkspaceLabels::default();
+        assert_eq!(
+            format_workspace_name_from_type(&ws, 5, &labels),
+            "Special 5"
+        );
+    }
+
+    // Special with Some("") is treated like None per the guard `if !name.is_empty()`.
+    #[test]
+    fn workspace_name_from_type_special_empty_string_uses_id() {
+        let ws = WorkspaceType::Special(Some(String::new()));
+        let labels = WorkspaceLabels::default();
+        assert_eq!(
+            format_workspace_name_from_type(&ws, 9, &labels),
+            "Special 9"
+        );
+    }
+
+    // Labels match by name, then by id; special ones by "special:<name>".
+    #[test]
+    fn workspace_labels_replace_the_name() {
+        let labels = WorkspaceLabels::default();
+        labels.set(BTreeMap::from([
+            ("1".to_string(), "\u{f269}".to_string()),
+            ("mail".to_string(), "\u{f0e0}".to_string()),
+            ("special:magic".to_string(), "\u{f0d0}".to_string()),
+        ]));
+        assert_eq!(
+            format_workspace_name_from_string("1", 1, &labels),
+            "\u{f269}"
+        );
+        assert_eq!(
+            format_workspace_name_from_string("mail", 4, &labels),
+            "\u{f0e0}"
+        );
+        assert_eq!(
+            format_workspace_name_from_string("", 1, &labels),
+            "\u{f269}"
+        );
+        assert_eq!(
+            format_workspace_name_from_string("2", 2, &labels),
+            "Workspace 2"
+        );
+        let magic = WorkspaceType::Special(Some("magic".to_string()));
+        assert_eq!(
+            format_workspace_name_from_type(&magic, -98, &labels),
+            "\u{f0d0}"
+        );
+        let other = WorkspaceType::Special(Some("term".to_string()));
+        assert_eq!(
+            format_workspace_name_from_type(&other, -97, &labels),
+            "Special: term"
+        );
+    }
+}
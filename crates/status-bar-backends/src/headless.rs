@@ -0,0 +1,287 @@
+// Headless output for `gtk-status-bar --output json`: the same producers the
+// bar runs, taken as the Streams in streams.rs, with no GTK anywhere. Every
+// change prints the whole state as one JSON object on its own line, so another
+// bar or a script can read the lines and render them however it likes:
+//
+//   {"workspace":{"id":2,"name":"web","special":false},"window":{...},
+//    "audio":{...},"sinks":[...],"battery":{...},"bluetooth":{...},
+//    "privacy":{...},"playing":1,"headset":{...}}
+//
+// A source that has not reported yet is null (or empty, where its widget
+// treats empty as hidden); audio turns null again while there is no sink. A
+// PipeWire that fails to start leaves the audio keys as they started rather
+// than ending the output. Identical states in a row are printed once.
+
+use std::io::Write;
+
+use anyhow::{Context, Result};
+use futures_util::StreamExt;
+use futures_util::stream::{BoxStream, SelectAll, select_all};
+use serde_json::{Value, json};
+use tracing::{info, warn};
+
+use crate::bus::{
+    BatteryState, BatteryUpdate, BluetoothUpdate, HeadsetMode, HeadsetUpdate, NoAudio,
+    PlaybackUpdate, PrivacyUpdate, SinkAdded, SinkList, TitleUpdate, VolumeReport, VolumeUpdate,
+    WorkspaceUpdate,
+};
+use crate::buses::BusManager;
+use crate::streams::{self, AudioStreams, BackendStream};
+
+// The latest reading from every source.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Snapshot {
+    pub workspace: Option<WorkspaceUpdate>,
+    pub window: TitleUpdate,
+    pub audio: Option<VolumeUpdate>,
+    pub sinks: SinkList,
+    pub battery: Option<BatteryUpdate>,
+    pub bluetooth: BluetoothUpdate,
+    pub privacy: PrivacyUpdate,
+    pub playback: PlaybackUpdate,
+    pub headset: HeadsetUpdate,
+}
+
+enum Change {
+    Workspace(WorkspaceUpdate),
+    Window(TitleUpdate),
+    Audio(VolumeUpdate),
+    NoAudio(NoAudio),
+    Sinks(SinkList),
+    Battery(BatteryUpdate),
+    Bluetooth(BluetoothUpdate),
+    Privacy(PrivacyUpdate),
+    Playback(PlaybackUpdate),
+    Headset(HeadsetUpdate),
+}
+
+impl Snapshot {
+    fn apply(&mut self, change: Change) {
+        match change {
+            Change::Workspace(update) => self.workspace = Some(update),
+            Change::Window(update) => self.window = update,
+            Change::Audio(update) => self.audio = Some(update),
+            Change::NoAudio(_) => self.audio = None,
+            Change::Sinks(update) => self.sinks = update,
+            Change::Battery(update) => self.battery = Some(update),
+            Change::Bluetooth(update) => self.bluetooth = update,
+            Change::Privacy(update) => self.privacy = update,
+            Change::Playback(update) => self.playback = update,
+            Change::Headset(update) => self.headset = update,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "workspace": self.workspace.as_ref().map(|workspace| json!({
+                "id": workspace.id,
+                "name": workspace.name,
+                "special": workspace.special,
+            })),
+            "window": {
+                "title": self.window.title,
+                "class": self.window.class,
+                "pid": self.window.pid,
+            },
+            "audio": self.audio.as_ref().map(|audio| json!({
+                "sink": audio.name,
+                // The first channel, as the volume widget shows it.
+                "percent": audio.channel_percent.or(audio.volume_percent),
+                "muted": audio.is_muted,
+            })),
+            "sinks": self.sinks.sinks.iter().map(|sink| json!({
+                "name": sink.node_name,
+                "description": sink.description,
+                "default": self.sinks.default.as_deref() == Some(sink.node_name.as_str()),
+            })).collect::<Vec<_>>(),
+            "battery": self.battery.as_ref().map(|battery| json!({
+                "text": battery.text,
+                "percent": battery.percent,
+                "state": battery_state(battery.state),
+            })),
+            "bluetooth": {
+                "text": self.bluetooth.text,
+                "connecting": self.bluetooth.connecting,
+            },
+            "privacy": {
+                "microphone": self.privacy.microphone,
+                "camera": self.privacy.camera,
+                "screen": self.privacy.screen,
+            },
+            "playing": self.playback.streams.len(),
+            "headset": (!self.headset.device.is_empty()).then(|| json!({
+                "device": self.headset.device,
+                "mode": headset_mode(self.headset.mode),
+                "profile": self.headset.profile,
+            })),
+        })
+    }
+}
+
+fn headset_mode(mode: HeadsetMode) -> &'static str {
+    match mode {
+        HeadsetMode::Other => "other",
+        HeadsetMode::HighQuality => "a2dp",
+        HeadsetMode::Headset => "hfp",
+    }
+}
+
+fn battery_state(state: BatteryState) -> &'static str {
+    match state {
+        BatteryState::Unknown => "unknown",
+        BatteryState::Charging => "charging",
+        BatteryState::Discharging => "discharging",
+        BatteryState::Empty => "empty",
+        BatteryState::Full => "full",
+    }
+}
+
+// Every backend's updates merged into one Snapshot, for the headless
+// front-ends (this JSON and swaybar.rs). Starting it starts the producers;
+// dropping it stops them.
+pub struct Snapshots {
+    changes: SelectAll<BoxStream<'static, Change>>,
+    snapshot: Snapshot,
+    // Plug-in notices are for the bar's sink switching; holding the stream
+    // keeps PipeWire from logging each one as a failed send.
+    _sink_added: Option<BackendStream<SinkAdded>>,
+}
+
+impl Snapshots {
+    pub fn start(buses: BusManager) -> Self {
+        let mut sources: Vec<BoxStream<'static, Change>> = vec![
+            streams::workspaces().map(Change::Workspace).boxed(),
+            streams::titles().map(Change::Window).boxed(),
+            streams::battery(buses.clone()).map(Change::Battery).boxed(),
+            streams::bluetooth(buses).map(Change::Bluetooth).boxed(),
+        ];
+        let sink_added = match streams::audio() {
+            Ok(AudioStreams {
+                volume,
+                privacy,
+                playback,
+                sinks,
+                sink_added,
+                headset,
+                ..
+            }) => {
+                sources.push(
+                    volume
+                        .map(|report| match report {
+                            VolumeReport::Level(update) => Change::Audio(update),
+                            VolumeReport::NoAudio(reason) => Change::NoAudio(reason),
+                        })
+                        .boxed(),
+                );
+                sources.push(sinks.map(Change::Sinks).boxed());
+                sources.push(privacy.map(Change::Privacy).boxed());
+                sources.push(playback.map(Change::Playback).boxed());
+                sources.push(headset.map(Change::Headset).boxed());
+                Some(sink_added)
+            }
+            Err(e) => {
+                warn!("PipeWire unavailable, audio stays empty: {:#}", e);
+                None
+            }
+        };
+        Self {
+            changes: select_all(sources),
+            snapshot: Snapshot::default(),
+            _sink_added: sink_added,
+        }
+    }
+
+    // The state after the next update; None once every producer has
+    // stopped, which the supervised ones never do.
+    pub async fn next(&mut self) -> Option<&Snapshot> {
+        let change = self.changes.next().await?;
+        self.snapshot.apply(change);
+        Some(&self.snapshot)
+    }
+}
+
+// Runs until writing fails (the reader went away).
+pub async fn run(buses: BusManager, mut out: impl Write) -> Result<()> {
+    info!("Starting headless JSON output");
+    let mut snapshots = Snapshots::start(buses);
+    let mut printed = None;
+    while let Some(snapshot) = snapshots.next().await {
+        let line = snapshot.to_json();
+        if printed.as_ref() == Some(&line) {
+            continue;
+        }
+        writeln!(out, "{line}").context("write JSON status")?;
+        out.flush().context("flush JSON status")?;
+        printed = Some(line);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::Sink;
+
+    #[test]
+    fn snapshot_json_has_every_source() {
+        let mut snapshot = Snapshot::default();
+        let empty = snapshot.to_json();
+        assert_eq!(empty["workspace"], Value::Null);
+        assert_eq!(empty["audio"], Value::Null);
+        assert_eq!(empty["window"]["title"], "");
+        assert_eq!(empty["playing"], 0);
+        assert_eq!(empty["headset"], Value::Null);
+
+        snapshot.apply(Change::Workspace(WorkspaceUpdate {
+            name: "web".to_string(),
+            id: 2,
+            special: false,
+        }));
+        snapshot.apply(Change::Audio(VolumeUpdate {
+            name: "Speakers".to_string(),
+            volume_percent: Some(40),
+            channel_percent: Some(42),
+            is_muted: Some(false),
+        }));
+        snapshot.apply(Change::Sinks(SinkList {
+            sinks: vec![
+                Sink {
+                    node_name: "alsa_output.hdmi".to_string(),
+                    description: "HDMI".to_string(),
+                },
+                Sink {
+                    node_name: "alsa_output.speakers".to_string(),
+                    description: "Speakers".to_string(),
+                },
+            ],
+            default: Some("alsa_output.speakers".to_string()),
+        }));
+        snapshot.apply(Change::Battery(BatteryUpdate {
+            text: "🔋 80%".to_string(),
+            percent: Some(80.0),
+            state: BatteryState::Discharging,
+        }));
+        snapshot.apply(Change::Headset(HeadsetUpdate {
+            device: "WH-1000XM4".to_string(),
+            mode: HeadsetMode::Headset,
+            profile: "Headset Head Unit (HSP/HFP)".to_string(),
+            switch_to: None,
+        }));
+        let json = snapshot.to_json();
+        assert_eq!(
+            json["workspace"],
+            json!({"id": 2, "name": "web", "special": false})
+        );
+        assert_eq!(
+            json["audio"],
+            json!({"sink": "Speakers", "percent": 42, "muted": false})
+        );
+        assert_eq!(json["sinks"][0]["default"], false);
+        assert_eq!(json["sinks"][1]["default"], true);
+        assert_eq!(json["battery"]["state"], "discharging");
+        assert_eq!(json["battery"]["percent"], 80.0);
+        assert_eq!(json["headset"]["mode"], "hfp");
+        // One object per line.
+        assert!(!json.to_string().contains('\n'));
+    }
+}
@@ -0,0 +1,251 @@
+// Bluetooth headset profiles: whether a headset plays in high quality (A2DP)
+// or in headset mode (HSP/HFP), which opens its microphone at the price of
+// telephone-grade sound. A call switches the headset over on its own and the
+// session manager does not always switch it back, which used to leave music
+// sounding broken for no visible reason.
+//
+// pw.rs binds every bluez5 device and reports the profiles its EnumProfile
+// params list and the one its Profile param names as active; this module
+// picks what the widget shows and what a click switches to. With two
+// headsets connected the one PipeWire announced last (the highest id) wins.
+
+use std::collections::BTreeMap;
+
+use crate::bus::{HeadsetMode, HeadsetUpdate};
+
+// One entry of a device's EnumProfile (or its active Profile).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub index: u32,
+    pub name: String,
+    pub description: String,
+    pub priority: i32,
+    // False when PipeWire says the profile cannot be used right now (a
+    // headset without HFP support, a codec the adapter lacks).
+    pub available: bool,
+}
+
+// bluez5 names its profiles "a2dp-sink", "a2dp-sink-aac",
+// "headset-head-unit-msbc" and so on; "off", the audio-gateway profiles and
+// LE Audio are neither mode.
+pub fn mode(name: &str) -> HeadsetMode {
+    if name.starts_with("a2dp-sink") {
+        HeadsetMode::HighQuality
+    } else if name.starts_with("headset-head-unit") {
+        HeadsetMode::Headset
+    } else {
+        HeadsetMode::Other
+    }
+}
+
+#[derive(Debug, Default)]
+struct Headset {
+    name: String,
+    profiles: Vec<Profile>,
+    active: Option<Profile>,
+}
+
+impl Headset {
+    // The profile a click picks: the other mode, falling back to high
+    // quality (then headset mode) from "off" and the like.
+    fn target(&self) -> Option<&Profile> {
+        let best = |wanted| {
+            self.profiles
+                .iter()
+                .filter(|profile| profile.available && mode(&profile.name) == wanted)
+                .max_by_key(|profile| profile.priority)
+        };
+        match self.active.as_ref().map(|profile| mode(&profile.name)) {
+            Some(HeadsetMode::HighQuality) => best(HeadsetMode::Headset),
+            Some(HeadsetMode::Headset) => best(HeadsetMode::HighQuality),
+            _ => best(HeadsetMode::HighQuality).or_else(|| best(HeadsetMode::Headset)),
+        }
+    }
+}
+
+// Bluetooth audio devices by PipeWire id. PipeWire-thread only.
+#[derive(Debug, Default)]
+pub struct HeadsetTracker {
+    headsets: BTreeMap<u32, Headset>,
+}
+
+impl HeadsetTracker {
+    // Each method returns the new summary when it changed, so the caller
+    // sends only real transitions.
+    pub fn add(&mut self, id: u32, name: String) -> Option<HeadsetUpdate> {
+        self.change(|headsets| {
+            headsets.insert(
+                id,
+                Headset {
+                    name,
+                    ..Headset::default()
+                },
+            );
+        })
+    }
+
+    // One EnumProfile entry. A re-enumeration repeats every index, so an
+    // entry replaces the one it shares an index with.
+    pub fn add_profile(&mut self, id: u32, profile: Profile) -> Option<HeadsetUpdate> {
+        self.change(|headsets| {
+            let Some(headset) = headsets.get_mut(&id) else {
+                return;
+            };
+            headset
+                .profiles
+                .retain(|known| known.index != profile.index);
+            headset.profiles.push(profile);
+        })
+    }
+
+    pub fn set_active(&mut self, id: u32, profile: Profile) -> Option<HeadsetUpdate> {
+        self.change(|headsets| {
+            if let Some(headset) = headsets.get_mut(&id) {
+                headset.active = Some(profile);
+            }
+        })
+    }
+
+    pub fn remove(&mut self, id: u32) -> Option<HeadsetUpdate> {
+        self.change(|headsets| {
+            headsets.remove(&id);
+        })
+    }
+
+    // The device id and profile index a click switches to.
+    pub fn toggle_target(&self) -> Option<(u32, u32)> {
+        let (id, headset) = self.headsets.iter().next_back()?;
+        Some((*id, headset.target()?.index))
+    }
+
+    fn change(&mut self, apply: impl FnOnce(&mut BTreeMap<u32, Headset>)) -> Option<HeadsetUpdate> {
+        let before = self.summary();
+        apply(&mut self.headsets);
+        let after = self.summary();
+        (after != before).then_some(after)
+    }
+
+    fn summary(&self) -> HeadsetUpdate {
+        let Some((_, headset)) = self.headsets.iter().next_back() else {
+            return HeadsetUpdate::default();
+        };
+        let active = headset.active.as_ref();
+        HeadsetUpdate {
+            device: headset.name.clone(),
+            mode: active.map_or(HeadsetMode::Other, |profile| mode(&profile.name)),
+            profile: active
+                .map(|profile| profile.description.clone())
+                .unwrap_or_default(),
+            switch_to: headset.target().map(|profile| profile.description.clone()),
+        }
+    }
+}
+
+// The widget text, empty (hiding it) without a headset.
+pub fn indicator_text(update: &HeadsetUpdate) -> String {
+    if update.device.is_empty() {
+        return String::new();
+    }
+    match update.mode {
+        HeadsetMode::HighQuality => "🎧 A2DP".to_string(),
+        HeadsetMode::Headset => "🎧 HFP".to_string(),
+        HeadsetMode::Other => "🎧".to_string(),
+    }
+}
+
+pub fn indicator_tooltip(update: &HeadsetUpdate) -> String {
+    if update.device.is_empty() {
+        return String::new();
+    }
+    let mut lines = vec![if update.profile.is_empty() {
+        update.device.clone()
+    } else {
+        format!("{}: {}", update.device, update.profile)
+    }];
+    lines.push(match &update.switch_to {
+        Some(profile) => format!("Click to switch to {profile}"),
+        None => "No other profile to switch to".to_string(),
+    });
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(index: u32, name: &str, priority: i32) -> Profile {
+        Profile {
+            index,
+            name: name.to_string(),
+            description: name.to_string(),
+            priority,
+            available: true,
+        }
+    }
+
+    #[test]
+    fn profile_names_map_to_modes() {
+        assert_eq!(mode("a2dp-sink"), HeadsetMode::HighQuality);
+        assert_eq!(mode("a2dp-sink-ldac"), HeadsetMode::HighQuality);
+        assert_eq!(mode("headset-head-unit-msbc"), HeadsetMode::Headset);
+        assert_eq!(mode("off"), HeadsetMode::Other);
+        assert_eq!(mode("audio-gateway"), HeadsetMode::Other);
+    }
+
+    #[test]
+    fn a_click_switches_to_the_best_profile_of_the_other_mode() {
+        let mut tracker = HeadsetTracker::default();
+        let update = tracker
+            .add(70, "WH-1000XM4".to_string())
+            .expect("a headset is news");
+        assert_eq!(indicator_text(&update), "🎧");
+        assert_eq!(tracker.add_profile(70, profile(0, "off", 0)), None);
+        tracker.add_profile(70, profile(1, "a2dp-sink-sbc", 18));
+        tracker.add_profile(70, profile(2, "a2dp-sink-aac", 19));
+        tracker.add_profile(70, profile(3, "headset-head-unit-cvsd", 1));
+        tracker.add_profile(70, profile(4, "headset-head-unit-msbc", 2));
+        let unusable = Profile {
+            available: false,
+            ..profile(4, "headset-head-unit-msbc", 2)
+        };
+        tracker.add_profile(70, unusable);
+
+        let update = tracker
+            .set_active(70, profile(2, "a2dp-sink-aac", 19))
+            .expect("active");
+        assert_eq!(indicator_text(&update), "🎧 A2DP");
+        assert_eq!(
+            indicator_tooltip(&update),
+            "WH-1000XM4: a2dp-sink-aac\nClick to switch to headset-head-unit-cvsd"
+        );
+        assert_eq!(tracker.toggle_target(), Some((70, 3)));
+
+        let update = tracker
+            .set_active(70, profile(3, "headset-head-unit-cvsd", 1))
+            .expect("HFP");
+        assert_eq!(indicator_text(&update), "🎧 HFP");
+        assert_eq!(tracker.toggle_target(), Some((70, 2)));
+
+        tracker.set_active(70, profile(0, "off", 0));
+        assert_eq!(tracker.toggle_target(), Some((70, 2)));
+    }
+
+    #[test]
+    fn the_last_headset_announced_is_shown() {
+        let mut tracker = HeadsetTracker::default();
+        tracker.add(70, "Earbuds".to_string());
+        tracker.add(82, "Speaker".to_string());
+        tracker.add_profile(82, profile(1, "a2dp-sink", 16));
+        tracker.set_active(82, profile(1, "a2dp-sink", 16));
+        assert_eq!(tracker.summary().device, "Speaker");
+        assert_eq!(tracker.summary().switch_to, None);
+        assert_eq!(tracker.toggle_target(), None);
+
+        let update = tracker.remove(82).expect("back to the earbuds");
+        assert_eq!(update.device, "Earbuds");
+        let update = tracker.remove(70).expect("no headset left");
+        assert_eq!(update, HeadsetUpdate::default());
+        assert_eq!(indicator_text(&update), "");
+        assert_eq!(tracker.remove(70), None);
+    }
+}
@@ -0,0 +1,988 @@
+// Hyprland subsystem: title, workspace, split hint, displays, floating-window,
+// scratchpad and taskbar listeners, plus the taskbar's focus/close and the
+// scratchpad's toggle dispatches.
+//
+// We connect to Hyprland's IPC event socket (.socket2.sock) via hyprland-rs's
+// AsyncEventListener. The title and workspace listeners are the Hyprland side
+// of the Compositor trait (compositor.rs), which supervises them the same way
+// it does Sway's. If a listener errors out (EOF on the socket, parse
+// failure on an unknown event variant, etc.), its wrapper retries with
+// exponential backoff.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use hyprland::event_listener::AsyncEventListener;
+use hyprland::shared::{HyprData, HyprDataActive, HyprDataActiveOptional, HyprDataVec};
+use tracing::{debug, error, info, warn};
+
+use crate::bus::{Bus, TaskbarUpdate, TaskbarWindow, TitleUpdate, WorkspaceUpdate};
+use crate::dbus::supervise;
+use crate::format::{
+    MAX_TITLE_CHARS, WorkspaceLabels, format_title_string, format_workspace_name_from_string,
+    format_workspace_name_from_type,
+};
+
+// Glyphs for the next-window placement hint. Arrows point where dwindle puts
+// the new window; double arrows mean force_split = 0, where the cursor's side
+// of the focused window decides.
+const SPLIT_RIGHT: &str = "→";
+const SPLIT_LEFT: &str = "←";
+const SPLIT_DOWN: &str = "↓";
+const SPLIT_UP: &str = "↑";
+const SPLIT_HORIZONTAL_BY_CURSOR: &str = "↔";
+const SPLIT_VERTICAL_BY_CURSOR: &str = "↕";
+const SPLIT_EMPTY: &str = "□";
+const SPLIT_MASTER: &str = "M";
+
+// Special workspaces have negative ids in Hyprland, but the activespecial
+// event only carries names. The sentinel only fills WorkspaceUpdate::id; the
+// widget colors special workspaces from the `special` flag (and the configured
+// special color), never from this id.
+const SPECIAL_WORKSPACE_COLOR_ID: hyprland::shared::WorkspaceId = -99;
+
+// What the next tiled window will do, from Hyprland's dwindle rules: the
+// focused window is split side by side when it is wider than
+// `height * split_width_multiplier`, and stacked otherwise; force_split 1
+// places the new window left/top, 2 right/bottom, 0 follows the cursor. An
+// empty workspace (or a floating focus, which dwindle does not split) means
+// the new window simply fills the tiled area. Master layouts always push new
+// windows onto the stack. Unknown/plugin layouts yield no hint at all.
+pub fn split_hint(
+    layout: &str,
+    focused_size: Option<(i16, i16)>,
+    force_split: i64,
+    split_width_multiplier: f64,
+) -> &'static str {
+    match layout {
+        "dwindle" => {
+            let Some((width, height)) = focused_size else {
+                return SPLIT_EMPTY;
+            };
+            let side_by_side = f64::from(width) > f64::from(height) * split_width_multiplier;
+            match (side_by_side, force_split) {
+                (true, 1) => SPLIT_LEFT,
+                (true, 2) => SPLIT_RIGHT,
+                (true, _) => SPLIT_HORIZONTAL_BY_CURSOR,
+                (false, 1) => SPLIT_UP,
+                (false, 2) => SPLIT_DOWN,
+                (false, _) => SPLIT_VERTICAL_BY_CURSOR,
+            }
+        }
+        "master" => SPLIT_MASTER,
+        _ => "",
+    }
+}
+
+async fn keyword_value(key: &str) -> Result<hyprland::keyword::OptionValue> {
+    Ok(hyprland::keyword::Keyword::get_async(key).await?.value)
+}
+
+// Re-read the layout options on every query rather than caching them: a
+// `hyprctl keyword` or config reload can change them at any time and there
+// is no dedicated event for it.
+async fn query_split_hint() -> Result<&'static str> {
+    use hyprland::keyword::OptionValue;
+
+    let layout = match keyword_value("general:layout").await? {
+        OptionValue::String(layout) => layout,
+        other => {
+            warn!("Unexpected general:layout value: {:?}", other);
+            return Ok("");
+        }
+    };
+    let force_split = match keyword_value("dwindle:force_split").await {
+        Ok(OptionValue::Int(value)) => value,
+        _ => 0,
+    };
+    let split_width_multiplier = match keyword_value("dwindle:split_width_multiplier").await {
+        Ok(OptionValue::Float(value)) => value,
+        _ => 1.0,
+    };
+    let focused_size = hyprland::data::Client::get_active_async()
+        .await?
+        .filter(|client| !client.floating)
+        .map(|client| client.size);
+
+    Ok(split_hint(
+        &layout,
+        focused_size,
+        force_split,
+        split_width_multiplier,
+    ))
+}
+
+async fn send_split_hint(bus: &Bus) {
+    match query_split_hint().await {
+        Ok(hint) => {
+            debug!(hint, "Split hint");
+            if let Err(e) = bus.send_split_update(hint.to_string()) {
+                error!("Failed to send split hint update: {}", e);
+            }
+        }
+        Err(e) => error!("Failed to query split hint: {}", e),
+    }
+}
+
+fn option_value_text(value: &hyprland::keyword::OptionValue) -> String {
+    use hyprland::keyword::OptionValue;
+
+    match value {
+        OptionValue::Int(value) => value.to_string(),
+        OptionValue::Float(value) => value.to_string(),
+        OptionValue::String(value) => value.clone(),
+    }
+}
+
+// Set each keyword, returning what it was before so the caller can restore
+// it. A keyword that cannot be read is still set, but is left out of the
+// returned list: putting back a guessed value would be worse than leaving it.
+async fn apply_keywords(keywords: &BTreeMap<String, String>) -> Vec<(String, String)> {
+    let mut previous = Vec::new();
+    for (key, value) in keywords {
+        match keyword_value(key).await {
+            Ok(old) => previous.push((key.clone(), option_value_text(&old))),
+            Err(e) => warn!(key, "Could not read keyword before changing it: {}", e),
+        }
+        set_keyword(key, value).await;
+    }
+    previous
+}
+
+async fn set_keyword(key: &str, value: &str) {
+    let option = hyprland::keyword::OptionValue::String(value.to_string());
+    match hyprland::keyword::Keyword::set_async(key, option).await {
+        Ok(()) => debug!(key, value, "Set Hyprland keyword"),
+        Err(e) => error!(key, value, "Failed to set Hyprland keyword: {}", e),
+    }
+}
+
+// Focus mode for the pomodoro timer: `true` applies the keywords, `false` puts
+// back what they replaced. Repeats are ignored, so the UI can send on every
+// phase change without tracking what is applied. Not supervised: it holds no
+// connection, each keyword call is its own IPC request. A bar that quits
+// mid-phase leaves the keywords applied until `hyprctl reload`.
+pub async fn run_focus_mode(
+    mut rx: tokio::sync::mpsc::UnboundedReceiver<bool>,
+    keywords: BTreeMap<String, String>,
+) {
+    let mut saved: Option<Vec<(String, String)>> = None;
+    while let Some(on) = rx.recv().await {
+        match (on, saved.take()) {
+            (true, None) => {
+                info!(keywords = keywords.len(), "Entering focus mode");
+                saved = Some(apply_keywords(&keywords).await);
+            }
+            (false, Some(previous)) => {
+                info!("Leaving focus mode");
+                for (key, value) in &previous {
+                    set_keyword(key, value).await;
+                }
+            }
+            (_, unchanged) => saved = unchanged,
+        }
+    }
+    debug!("Focus mode channel closed");
+}
+
+pub fn format_display_count(count: usize) -> String {
+    format!("🖥 {}", count)
+}
+
+async fn send_display_count(bus: &Bus) {
+    match hyprland::data::Monitors::get_async().await {
+        Ok(monitors) => {
+            let count = monitors.to_vec().len();
+            debug!(count, "Connected outputs");
+            if let Err(e) = bus.send_displays_update(format_display_count(count)) {
+                error!("Failed to send displays update: {}", e);
+            }
+        }
+        Err(e) => error!("Failed to query monitors: {}", e),
+    }
+}
+
+// Empty for none, so the badge hides.
+pub fn format_floating_badge(count: usize) -> String {
+    if count == 0 {
+        return String::new();
+    }
+    format!("🗗 {}", count)
+}
+
+// Empty for an empty workspace, so the badge hides.
+pub fn format_window_badge(count: usize) -> String {
+    if count == 0 {
+        return String::new();
+    }
+    format!("▣ {}", count)
+}
+
+// Windows per regular workspace, given each mapped window's workspace id.
+// Special workspaces (negative ids) have their own buttons, and a workspace
+// with no windows is left out.
+pub fn count_windows_by_workspace(
+    workspaces: impl IntoIterator<Item = i32>,
+) -> BTreeMap<i32, usize> {
+    let mut counts = BTreeMap::new();
+    for id in workspaces.into_iter().filter(|id| *id > 0) {
+        *counts.entry(id).or_default() += 1;
+    }
+    counts
+}
+
+// Both badges come from one client list, so they never disagree.
+async fn send_window_counts(bus: &Bus) {
+    let active_workspace = match hyprland::data::Workspace::get_active_async().await {
+        Ok(workspace) => workspace.id,
+        Err(e) => {
+            error!("Failed to query the active workspace: {}", e);
+            return;
+        }
+    };
+    let clients = match hyprland::data::Clients::get_async().await {
+        Ok(clients) => clients.to_vec(),
+        Err(e) => {
+            error!("Failed to query clients: {}", e);
+            return;
+        }
+    };
+    let mapped: Vec<_> = clients.iter().filter(|client| client.mapped).collect();
+    let counts = count_windows_by_workspace(mapped.iter().map(|client| client.workspace.id));
+    let floating = mapped
+        .iter()
+        .filter(|client| client.floating && client.workspace.id == active_workspace)
+        .count();
+    debug!(?counts, floating, active_workspace, "Windows per workspace");
+    if let Err(e) = bus.send_window_count_update(counts) {
+        error!("Failed to send window count update: {}", e);
+    }
+    if let Err(e) = bus.send_floating_update(format_floating_badge(floating)) {
+        error!("Failed to send floating window update: {}", e);
+    }
+}
+
+// Like the display count, the taskbar is re-queried whole on every event
+// instead of patched per event: openwindow, closewindow and movewindow each
+// carry only part of a window's state, and a fresh list can't drift.
+async fn send_taskbar(bus: &Bus) {
+    let clients = match hyprland::data::Clients::get_async().await {
+        Ok(clients) => clients.to_vec(),
+        Err(e) => {
+            error!("Failed to query clients: {}", e);
+            return;
+        }
+    };
+    let active_address = match hyprland::data::Client::get_active_async().await {
+        Ok(active) => active.map(|client| client.address.to_string()),
+        Err(e) => {
+            debug!("No active client for the taskbar: {}", e);
+            None
+        }
+    };
+    let active_workspace = match hyprland::data::Workspace::get_active_async().await {
+        Ok(workspace) => workspace.id,
+        Err(e) => {
+            error!("Failed to query the active workspace: {}", e);
+            return;
+        }
+    };
+    let mut windows: Vec<TaskbarWindow> = clients
+        .into_iter()
+        .filter(|client| client.mapped)
+        .map(|client| {
+            let address = client.address.to_string();
+            TaskbarWindow {
+                focused: active_address.as_deref() == Some(address.as_str()),
+                address,
+                class: client.class,
+                title: client.title,
+                workspace_id: client.workspace.id,
+                pid: Some(client.pid),
+            }
+        })
+        .collect();
+    // Stable: within a workspace, Hyprland's own (creation) order is kept so
+    // buttons don't jump around as windows move.
+    windows.sort_by_key(|window| window.workspace_id);
+    debug!(
+        windows = windows.len(),
+        active_workspace, "Taskbar refreshed"
+    );
+    let update = TaskbarUpdate {
+        windows,
+        active_workspace,
+    };
+    if let Err(e) = bus.send_taskbar_update(update) {
+        error!("Failed to send taskbar update: {}", e);
+    }
+}
+
+// The special workspaces shown on some monitor, by name without the
+// "special:" prefix. A monitor showing none reports an empty name.
+pub fn open_special_workspaces<'a>(names: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut open: Vec<String> = names
+        .into_iter()
+        .filter(|name| !name.is_empty())
+        .map(|name| name.strip_prefix("special:").unwrap_or(name).to_string())
+        .collect();
+    open.sort();
+    open.dedup();
+    open
+}
+
+async fn send_open_special_workspaces(bus: &Bus) {
+    let monitors = match hyprland::data::Monitors::get_async().await {
+        Ok(monitors) => monitors.to_vec(),
+        Err(e) => {
+            error!("Failed to query monitors: {}", e);
+            return;
+        }
+    };
+    let open = open_special_workspaces(
+        monitors
+            .iter()
+            .map(|monitor| monitor.special_workspace.name.as_str()),
+    );
+    debug!(?open, "Special workspaces shown");
+    if let Err(e) = bus.send_scratchpad_update(open) {
+        error!("Failed to send scratchpad update: {}", e);
+    }
+}
+
+// `hyprctl dispatch togglespecialworkspace <name>`: shows the special
+// workspace on the focused monitor, or hides it where it is shown.
+pub async fn toggle_special_workspace(name: &str) -> Result<()> {
+    use hyprland::dispatch::{Dispatch, DispatchType};
+    Dispatch::call_async(DispatchType::ToggleSpecialWorkspace(Some(name.to_string()))).await?;
+    Ok(())
+}
+
+pub async fn focus_window(address: &str) -> Result<()> {
+    use hyprland::dispatch::{Dispatch, DispatchType, WindowIdentifier};
+    let target = WindowIdentifier::Address(hyprland::shared::Address::new(address));
+    Dispatch::call_async(DispatchType::FocusWindow(target)).await?;
+    Ok(())
+}
+
+pub async fn close_window(address: &str) -> Result<()> {
+    use hyprland::dispatch::{Dispatch, DispatchType, WindowIdentifier};
+    let target = WindowIdentifier::Address(hyprland::shared::Address::new(address));
+    Dispatch::call_async(DispatchType::CloseWindow(target)).await?;
+    Ok(())
+}
+
+pub async fn focus_workspace(id: i32) -> Result<()> {
+    use hyprland::dispatch::{Dispatch, DispatchType, WorkspaceIdentifierWithSpecial};
+    Dispatch::call_async(DispatchType::Workspace(WorkspaceIdentifierWithSpecial::Id(
+        id,
+    )))
+    .await?;
+    Ok(())
+}
+
+// Every workspace, flagged with whether it is the active one.
+pub async fn workspaces(labels: &WorkspaceLabels) -> Result<Vec<crate::compositor::Workspace>> {
+    let active = hyprland::data::Workspace::get_active_async().await?;
+    let workspaces = hyprland::data::Workspaces::get_async().await?;
+    Ok(workspaces
+        .to_vec()
+        .into_iter()
+        .map(|workspace| {
+            // Special workspaces are listed as "special:<name>" with negative ids.
+            let name = match workspace.name.strip_prefix("special:") {
+                Some(name) => format_workspace_name_from_type(
+                    &hyprland::shared::WorkspaceType::Special(Some(name.to_string())),
+                    workspace.id,
+                    labels,
+                ),
+                None => format_workspace_name_from_string(&workspace.name, workspace.id, labels),
+            };
+            crate::compositor::Workspace {
+                focused: workspace.id == active.id,
+                special: workspace.id < 0,
+                id: workspace.id,
+                name,
+            }
+        })
+        .collect())
+}
+
+async fn get_initial_title_state() -> Result<TitleUpdate> {
+    // We do want to know when the operation is successfull but the title string is not there,
+    // which would be because there is no active client
+    debug!("Fetching initial title state");
+
+    let client = hyprland::data::Client::get_active_async().await?;
+    let update = match client {
+        Some(client) => TitleUpdate {
+            title: format_title_string(client.title, MAX_TITLE_CHARS),
+            class: client.class,
+            pid: Some(client.pid),
+        },
+        None => TitleUpdate::default(),
+    };
+
+    debug!(
+        title = update.title,
+        class = update.class,
+        "Initial title state"
+    );
+    Ok(update)
+}
+
+async fn handle_workspace_change(
+    workspace_data: hyprland::event_listener::WorkspaceEventData,
+    bus: &Bus,
+    labels: &WorkspaceLabels,
+) -> Result<()> {
+    debug!("Handling workspace change event");
+
+    let display_name =
+        format_workspace_name_from_type(&workspace_data.name, workspace_data.id, labels);
+    debug!("Workspace changed to: {}", display_name);
+
+    // Send combined workspace update with both name and ID
+    let update = WorkspaceUpdate {
+        name: display_name,
+        id: workspace_data.id,
+        special: matches!(
+            workspace_data.name,
+            hyprland::shared::WorkspaceType::Special(_)
+        ),
+    };
+    bus.send_workspace_update(update)
+}
+
+async fn handle_title_change(
+    title_data: hyprland::event_listener::WindowTitleEventData,
+    bus: &Bus,
+) -> Result<()> {
+    debug!("Handling title change event");
+
+    // If not active client skip event except if there is no active client, use title_data.address
+    let active_client = hyprland::data::Client::get_active_async()
+        .await?
+        // log + early return, not as debug it is normal sometimes for it to not be an active client,
+        // use combinators
+        .filter(|client| client.address == title_data.address);
+
+    if let Some(client) = active_client {
+        let update = TitleUpdate {
+            title: format_title_string(client.title, MAX_TITLE_CHARS),
+            class: client.class,
+            pid: Some(client.pid),
+        };
+        debug!(title = update.title, class = update.class, "Title changed");
+        bus.send_title_update(update)
+    } else {
+        debug!("No active client matches the title change event");
+        Ok(())
+    }
+}
+
+// The active window event names the window by address only; the client list
+// maps it to a PID.
+async fn client_pid(address: &hyprland::shared::Address) -> Option<i32> {
+    let clients = match hyprland::data::Clients::get_async().await {
+        Ok(clients) => clients.to_vec(),
+        Err(e) => {
+            debug!("Failed to query clients for the window PID: {}", e);
+            return None;
+        }
+    };
+    clients
+        .into_iter()
+        .find(|client| client.address == *address)
+        .map(|client| client.pid)
+}
+
+async fn handle_active_window_change(
+    window_data: Option<hyprland::event_listener::WindowEventData>,
+    bus: &Bus,
+) -> Result<()> {
+    debug!("Handling active window change event");
+
+    let update = match window_data {
+        Some(data) => {
+            debug!(
+                "Window data - class: '{}', title: '{}', address: '{}'",
+                data.class, data.title, data.address
+            );
+            TitleUpdate {
+                pid: client_pid(&data.address).await,
+                title: format_title_string(data.title, MAX_TITLE_CHARS),
+                class: data.class,
+            }
+        }
+        None => {
+            debug!("No active window (window_data is None)");
+            TitleUpdate::default()
+        }
+    };
+
+    debug!(
+        title = update.title,
+        class = update.class,
+        "Active window changed"
+    );
+    bus.send_title_update(update)
+}
+
+// Supervised wrapper around setup_split_event_listener. The inner listener
+// returns when Hyprland disconnects the IPC stream (EOF on .socket2.sock, parse
+// failure on an unknown event variant, or any other I/O error in
+// AsyncEventListener::start_listener_async). dbus::supervise logs the cause,
+// sleeps with exponential backoff (1s -> 2s -> 4s -> ... capped at 60s), and
+// reconnects. Backoff resets if the previous attempt ran for more than 30s, so
+// a stable listener that briefly hiccups recovers fast, while a persistent
+// failure (e.g. wrong env, Hyprland gone) doesn't busy-loop.
+pub async fn run_split_listener_supervised(bus: Bus) {
+    supervise("split hint listener", || setup_split_event_listener(&bus)).await
+}
+
+// The hint depends on the focused window's geometry, so it is recomputed on
+// every event that can change which window is focused or how big it is.
+// Opening and closing a window both move focus, so the active-window event
+// covers them; resizes and moves arrive as windowmoved.
+pub async fn setup_split_event_listener(bus: &Bus) -> Result<()> {
+    debug!("Setting up split hint listener");
+
+    send_split_hint(bus).await;
+
+    let mut event_listener = AsyncEventListener::new();
+
+    let window_bus = bus.clone();
+    event_listener.add_active_window_changed_handler(move |_| {
+        let bus = window_bus.clone();
+        Box::pin(async move { send_split_hint(&bus).await })
+    });
+
+    let moved_bus = bus.clone();
+    event_listener.add_window_moved_handler(move |_| {
+        let bus = moved_bus.clone();
+        Box::pin(async move { send_split_hint(&bus).await })
+    });
+
+    let workspace_bus = bus.clone();
+    event_listener.add_workspace_changed_handler(move |_| {
+        let bus = workspace_bus.clone();
+        Box::pin(async move { send_split_hint(&bus).await })
+    });
+
+    info!("Starting split hint listener");
+    event_listener.start_listener_async().await?;
+
+    Ok(())
+}
+
+// And for the displays listener.
+pub async fn run_displays_listener_supervised(bus: Bus) {
+    supervise("displays listener", || setup_displays_event_listener(&bus)).await
+}
+
+// Recount on every monitor hotplug instead of tracking +1/-1: a profile
+// switch can add and remove several outputs back to back, and a fresh count
+// can't drift.
+pub async fn setup_displays_event_listener(bus: &Bus) -> Result<()> {
+    debug!("Setting up displays listener");
+
+    send_display_count(bus).await;
+
+    let mut event_listener = AsyncEventListener::new();
+
+    let added_bus = bus.clone();
+    event_listener.add_monitor_added_handler(move |_| {
+        let bus = added_bus.clone();
+        Box::pin(async move { send_display_count(&bus).await })
+    });
+
+    let removed_bus = bus.clone();
+    event_listener.add_monitor_removed_handler(move |_| {
+        let bus = removed_bus.clone();
+        Box::pin(async move { send_display_count(&bus).await })
+    });
+
+    info!("Starting displays listener");
+    event_listener.start_listener_async().await?;
+
+    Ok(())
+}
+
+// And for the window and floating-window badges on the workspace widget.
+pub async fn run_floating_listener_supervised(bus: Bus) {
+    supervise("floating window listener", || {
+        setup_floating_event_listener(&bus)
+    })
+    .await
+}
+
+// Re-counted whole on every event that can change it: a window appearing,
+// leaving, moving between workspaces or toggling floating, and the active
+// workspace changing.
+pub async fn setup_floating_event_listener(bus: &Bus) -> Result<()> {
+    debug!("Setting up floating window listener");
+
+    send_window_counts(bus).await;
+
+    let mut event_listener = AsyncEventListener::new();
+
+    let opened_bus = bus.clone();
+    event_listener.add_window_opened_handler(move |_| {
+        let bus = opened_bus.clone();
+        Box::pin(async move { send_window_counts(&bus).await })
+    });
+
+    let closed_bus = bus.clone();
+    event_listener.add_window_closed_handler(move |_| {
+        let bus = closed_bus.clone();
+        Box::pin(async move { send_window_counts(&bus).await })
+    });
+
+    let moved_bus = bus.clone();
+    event_listener.add_window_moved_handler(move |_| {
+        let bus = moved_bus.clone();
+        Box::pin(async move { send_window_counts(&bus).await })
+    });
+
+    let float_bus = bus.clone();
+    event_listener.add_float_state_changed_handler(move |_| {
+        let bus = float_bus.clone();
+        Box::pin(async move { send_window_counts(&bus).await })
+    });
+
+    let workspace_bus = bus.clone();
+    event_listener.add_workspace_changed_handler(move |_| {
+        let bus = workspace_bus.clone();
+        Box::pin(async move { send_window_counts(&bus).await })
+    });
+
+    info!("Starting floating window listener");
+    event_listener.start_listener_async().await?;
+
+    Ok(())
+}
+
+// And for the scratchpad buttons.
+pub async fn run_scratchpad_listener_supervised(bus: Bus) {
+    supervise("scratchpad listener", || {
+        setup_scratchpad_event_listener(&bus)
+    })
+    .await
+}
+
+// activespecial names one monitor's special workspace, and with several
+// monitors another may still show one, so every event re-reads them all
+// rather than tracking a set.
+pub async fn setup_scratchpad_event_listener(bus: &Bus) -> Result<()> {
+    debug!("Setting up scratchpad listener");
+
+    send_open_special_workspaces(bus).await;
+
+    let mut event_listener = AsyncEventListener::new();
+
+    let changed_bus = bus.clone();
+    event_listener.add_changed_special_handler(move |_| {
+        let bus = changed_bus.clone();
+        Box::pin(async move { send_open_special_workspaces(&bus).await })
+    });
+
+    let removed_bus = bus.clone();
+    event_listener.add_special_removed_handler(move |_| {
+        let bus = removed_bus.clone();
+        Box::pin(async move { send_open_special_workspaces(&bus).await })
+    });
+
+    // A monitor going away takes its special workspace with it.
+    let monitor_bus = bus.clone();
+    event_listener.add_monitor_removed_handler(move |_| {
+        let bus = monitor_bus.clone();
+        Box::pin(async move { send_open_special_workspaces(&bus).await })
+    });
+
+    info!("Starting scratchpad listener");
+    event_listener.start_listener_async().await?;
+
+    Ok(())
+}
+
+// And for the taskbar listener, spawned only when the taskbar is enabled.
+pub async fn run_taskbar_listener_supervised(bus: Bus) {
+    supervise("taskbar listener", || setup_taskbar_event_listener(&bus)).await
+}
+
+// Focus changes restyle the focused button and title changes its tooltip, so
+// both refresh the list alongside the open/close/move events.
+pub async fn setup_taskbar_event_listener(bus: &Bus) -> Result<()> {
+    debug!("Setting up taskbar listener");
+
+    send_taskbar(bus).await;
+
+    let mut event_listener = AsyncEventListener::new();
+
+    let opened_bus = bus.clone();
+    event_listener.add_window_opened_handler(move |_| {
+        let bus = opened_bus.clone();
+        Box::pin(async move { send_taskbar(&bus).await })
+    });
+
+    let closed_bus = bus.clone();
+    event_listener.add_window_closed_handler(move |_| {
+        let bus = closed_bus.clone();
+        Box::pin(async move { send_taskbar(&bus).await })
+    });
+
+    let moved_bus = bus.clone();
+    event_listener.add_window_moved_handler(move |_| {
+        let bus = moved_bus.clone();
+        Box::pin(async move { send_taskbar(&bus).await })
+    });
+
+    let window_bus = bus.clone();
+    event_listener.add_active_window_changed_handler(move |_| {
+        let bus = window_bus.clone();
+        Box::pin(async move { send_taskbar(&bus).await })
+    });
+
+    let title_bus = bus.clone();
+    event_listener.add_window_title_changed_handler(move |_| {
+        let bus = title_bus.clone();
+        Box::pin(async move { send_taskbar(&bus).await })
+    });
+
+    let workspace_bus = bus.clone();
+    event_listener.add_workspace_changed_handler(move |_| {
+        let bus = workspace_bus.clone();
+        Box::pin(async move { send_taskbar(&bus).await })
+    });
+
+    info!("Starting taskbar listener");
+    event_listener.start_listener_async().await?;
+
+    Ok(())
+}
+
+pub async fn setup_title_event_listener(bus: &Bus) -> Result<()> {
+    debug!("Setting up title event listener");
+
+    let initial_state = get_initial_title_state().await.unwrap_or_else(|e| {
+        error!("Failed to get initial title state: {}", e);
+        TitleUpdate::default()
+    });
+
+    if let Err(e) = bus.send_title_update(initial_state) {
+        error!("Failed to send initial title update: {}", e);
+    }
+
+    let mut event_listener = AsyncEventListener::new();
+
+    // hyprland-rs's add_*_handler takes Fn(T) -> Pin<Box<dyn Future + Send>>;
+    // its older `async_closure!` macro produced exactly that shape (and is now
+    // deprecated). Native async-closure syntax returns `impl Future`, which
+    // doesn't satisfy the trait bound, so we spell the Box::pin out instead.
+    // Each handler clones the Bus twice: once into the closure (which must be
+    // Fn, callable many times) and once per invocation into the async move.
+    let title_bus = bus.clone();
+    event_listener.add_window_title_changed_handler(move |title_data| {
+        let bus = title_bus.clone();
+        Box::pin(async move {
+            if let Err(e) = handle_title_change(title_data, &bus).await {
+                error!("Failed to handle title change: {}", e);
+            }
+        })
+    });
+
+    let window_bus = bus.clone();
+    event_listener.add_active_window_changed_handler(move |window_data| {
+        let bus = window_bus.clone();
+        Box::pin(async move {
+            if let Err(e) = handle_active_window_change(window_data, &bus).await {
+                error!("Failed to handle active window change: {}", e);
+            }
+        })
+    });
+
+    info!("Starting title event listener");
+    event_listener.start_listener_async().await?;
+
+    Ok(())
+}
+
+// Changes only: the initial workspace comes from the workspace list (see
+// compositor.rs).
+pub async fn setup_workspace_event_listener(bus: &Bus, labels: &WorkspaceLabels) -> Result<()> {
+    debug!("Setting up workspace event listener");
+
+    let mut event_listener = AsyncEventListener::new();
+
+    let workspace_bus = bus.clone();
+    let workspace_labels = labels.clone();
+    event_listener.add_workspace_changed_handler(move |workspace_data| {
+        let bus = workspace_bus.clone();
+        let labels = workspace_labels.clone();
+        Box::pin(async move {
+            if let Err(e) = handle_workspace_change(workspace_data, &bus, &labels).await {
+                error!("Failed to handle workspace change: {}", e);
+            }
+        })
+    });
+
+    // Special workspaces arrive via their own Hyprland event ("activespecial"),
+    // not the workspace-changed one, so without these two handlers toggling a
+    // special workspace left the bar frozen on the previous regular workspace
+    // (VM evidence run, item W3). hyprland-rs splits the event: non-empty name
+    // means a special workspace became visible, empty name (SpecialRemoved)
+    // means it was hidden again.
+    let special_bus = bus.clone();
+    let special_labels = labels.clone();
+    event_listener.add_changed_special_handler(move |special_data| {
+        let bus = special_bus.clone();
+        let labels = special_labels.clone();
+        Box::pin(async move {
+            // The event carries names only; special workspaces have negative
+            // ids in Hyprland, so use a sentinel id and flag the update as
+            // special so the widget applies the scratchpad color and class.
+            let name = special_data
+                .workspace_name
+                .strip_prefix("special:")
+                .unwrap_or(&special_data.workspace_name)
+                .to_string();
+            let update = WorkspaceUpdate {
+                name: format_workspace_name_from_type(
+                    &hyprland::shared::WorkspaceType::Special(Some(name)),
+                    SPECIAL_WORKSPACE_COLOR_ID,
+                    &labels,
+                ),
+                id: SPECIAL_WORKSPACE_COLOR_ID,
+                special: true,
+            };
+            if let Err(e) = bus.send_workspace_update(update) {
+                error!("Failed to send special workspace update: {}", e);
+            }
+        })
+    });
+
+    let special_removed_bus = bus.clone();
+    let special_removed_labels = labels.clone();
+    event_listener.add_special_removed_handler(move |_monitor| {
+        let bus = special_removed_bus.clone();
+        let labels = special_removed_labels.clone();
+        Box::pin(async move {
+            // The special workspace was hidden; restore the regular active
+            // workspace (name + color) by querying it.
+            match hyprland::data::Workspace::get_active_async().await {
+                Ok(workspace) => {
+                    let update = WorkspaceUpdate {
+                        name: format_workspace_name_from_string(
+                            &workspace.name,
+                            workspace.id,
+                            &labels,
+                        ),
+                        id: workspace.id,
+                        special: false,
+                    };
+                    if let Err(e) = bus.send_workspace_update(update) {
+                        error!(
+                            "Failed to send workspace update after special removal: {}",
+                            e
+                        );
+                    }
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to query active workspace after special removal: {}",
+                        e
+                    );
+                }
+            }
+        })
+    });
+
+    info!("Starting workspace event listener");
+    event_listener.start_listener_async().await?;
+
+    Ok(())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn floating_badge_hides_at_zero() {
+        assert_eq!(format_floating_badge(0), "");
+        assert_eq!(format_floating_badge(2), "🗗 2");
+    }
+
+    #[test]
+    fn open_special_workspaces_drop_the_prefix_and_empty_monitors() {
+        assert_eq!(
+            open_special_workspaces(["special:term", "", "special:magic", "special:term"]),
+            ["magic", "term"]
+        );
+        assert!(open_special_workspaces(["", ""]).is_empty());
+    }
+
+    #[test]
+    fn window_badge_hides_on_an_empty_workspace() {
+        assert_eq!(format_window_badge(0), "");
+        assert_eq!(format_window_badge(5), "▣ 5");
+    }
+
+    #[test]
+    fn windows_are_counted_per_regular_workspace() {
+        let counts = count_windows_by_workspace([3, 1, 3, -98, 3, 2]);
+        assert_eq!(counts, BTreeMap::from([(1, 1), (2, 1), (3, 3)]));
+        assert!(count_windows_by_workspace([-99]).is_empty());
+    }
+
+    #[test]
+    fn display_count_label() {
+        assert_eq!(format_display_count(1), "🖥 1");
+        assert_eq!(format_display_count(3), "🖥 3");
+    }
+
+    #[test]
+    fn split_hint_follows_dwindle_aspect_and_force_split() {
+        assert_eq!(
+            split_hint("dwindle", Some((1920, 1080)), 2, 1.0),
+            SPLIT_RIGHT
+        );
+        assert_eq!(
+            split_hint("dwindle", Some((1920, 1080)), 1, 1.0),
+            SPLIT_LEFT
+        );
+        assert_eq!(split_hint("dwindle", Some((960, 1080)), 2, 1.0), SPLIT_DOWN);
+        assert_eq!(
+            split_hint("dwindle", Some((960, 1080)), 0, 1.0),
+            SPLIT_VERTICAL_BY_CURSOR
+        );
+        assert_eq!(
+            split_hint("dwindle", Some((1920, 1080)), 0, 1.0),
+            SPLIT_HORIZONTAL_BY_CURSOR
+        );
+    }
+
+    // split_width_multiplier scales the height side of the comparison, so a
+    // multiplier > 1 keeps moderately wide windows stacking.
+    #[test]
+    fn split_hint_honours_width_multiplier() {
+        assert_eq!(
+            split_hint("dwindle", Some((1200, 1000)), 2, 1.0),
+            SPLIT_RIGHT
+        );
+        assert_eq!(
+            split_hint("dwindle", Some((1200, 1000)), 2, 1.5),
+            SPLIT_DOWN
+        );
+    }
+
+    #[test]
+    fn split_hint_for_empty_master_and_unknown_layouts() {
+        assert_eq!(split_hint("dwindle", None, 0, 1.0), SPLIT_EMPTY);
+        assert_eq!(
+            split_hint("master", Some((1920, 1080)), 0, 1.0),
+            SPLIT_MASTER
+        );
+        assert_eq!(split_hint("hy3", Some((1920, 1080)), 0, 1.0), "");
+    }
+}
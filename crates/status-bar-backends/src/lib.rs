@@ -0,0 +1,25 @@
+// The bar's data-collection backends, without any GTK: Hyprland and Sway
+// events, UPower and BlueZ batteries over D-Bus, and PipeWire volume and streams. The
+// gtk-status-bar binary wires them through a Bus into its widgets; other
+// front-ends can do the same, take the Streams in `streams`, or read what
+// `headless` (JSON lines) and `swaybar` (the i3bar protocol) print from all of
+// them.
+
+pub mod bus;
+pub mod buses;
+pub mod channel;
+pub mod compositor;
+pub mod dbus;
+pub mod format;
+pub mod headless;
+pub mod headset;
+pub mod hypr;
+pub mod logind;
+pub mod playback;
+pub mod privacy;
+pub mod pw;
+pub mod sleep;
+pub mod state;
+pub mod streams;
+pub mod sway;
+pub mod swaybar;
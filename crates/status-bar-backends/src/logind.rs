@@ -0,0 +1,234 @@
+// logind session scoping. On a multi-seat or multi-user machine every session
+// shares one system bus, so anything the bar subscribes to there has to be
+// narrowed to the session (and seat) the bar itself runs in. This module only
+// resolves that scope and answers "is this object ours?"; the UPower and BlueZ
+// monitors in dbus.rs own the connection, the match rules and the dispatch.
+//
+// UPower and BlueZ have no notion of seats: the laptop battery and the paired
+// devices are machine-wide, so their signals are kept as-is. What is
+// per-session is whether the user is looking at this bar at all — while another
+// session holds the seat our bar is invisible, and on reactivation the monitor
+// re-reads its snapshots instead of trusting what it tracked in the background.
+// Waking from suspend (Manager.PrepareForSleep) is machine-wide; sleep.rs
+// watches for it.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use tracing::{debug, info};
+use zbus::message::Type as MessageType;
+use zbus::zvariant::{OwnedObjectPath, Value};
+use zbus::{Connection, MatchRule, Proxy};
+
+pub const LOGIND: &str = "org.freedesktop.login1";
+pub const LOGIND_PATH: &str = "/org/freedesktop/login1";
+pub const MANAGER_IFACE: &str = "org.freedesktop.login1.Manager";
+pub const SESSION_IFACE: &str = "org.freedesktop.login1.Session";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionScope {
+    pub session_id: String,
+    pub session_path: String,
+    // Sessions without a seat (ssh, some nested compositors) have an empty
+    // seat id and the root path "/" in logind; both collapse to None here.
+    pub seat_id: Option<String>,
+    pub seat_path: Option<String>,
+}
+
+impl SessionScope {
+    // Whether a signal from `path` belongs to this bar. Objects outside
+    // /org/freedesktop/login1 are not seat-bound and always pass; logind's own
+    // session and seat objects pass only when they are ours. The manager
+    // object itself is shared and passes too.
+    pub fn owns_path(&self, path: &str) -> bool {
+        let Some(rest) = path.strip_prefix(LOGIND_PATH) else {
+            return true;
+        };
+        if rest.is_empty() {
+            return true;
+        }
+        if rest.starts_with("/session/") {
+            return path == self.session_path;
+        }
+        if rest.starts_with("/seat/") {
+            return self.seat_path.as_deref() == Some(path);
+        }
+        // /org/freedesktop/login1/user/... — the user object is shared by all
+        // of this user's sessions, which is not what "our seat" means.
+        false
+    }
+}
+
+// Resolve the session the bar belongs to. GetSessionByPID works whenever the
+// bar was started from inside the graphical session; when it runs as a
+// systemd user service instead it lives in the user manager's cgroup, has no
+// session of its own, and XDG_SESSION_ID (exported by the session into the
+// user manager's environment) is the fallback.
+pub async fn resolve_session_scope(connection: &Connection) -> Result<SessionScope> {
+    let manager = Proxy::new(connection, LOGIND, LOGIND_PATH, MANAGER_IFACE)
+        .await
+        .context("create logind manager proxy")?;
+
+    let by_pid = manager
+        .call::<_, _, OwnedObjectPath>("GetSessionByPID", &(std::process::id()))
+        .await;
+    let session_path = match by_pid {
+        Ok(path) => path,
+        Err(e) => {
+            debug!("GetSessionByPID failed ({}); trying XDG_SESSION_ID", e);
+            let session_id = std::env::var("XDG_SESSION_ID")
+                .ok()
+                .filter(|id| !id.is_empty())
+                .context("process is outside any logind session and XDG_SESSION_ID is unset")?;
+            manager
+                .call::<_, _, OwnedObjectPath>("GetSession", &(session_id.as_str()))
+                .await
+                .with_context(|| format!("look up logind session {}", session_id))?
+        }
+    };
+
+    let session = Proxy::new(connection, LOGIND, session_path.as_str(), SESSION_IFACE)
+        .await
+        .context("create logind session proxy")?;
+    let session_id: String = session
+        .get_property("Id")
+        .await
+        .context("read logind session Id")?;
+    let (seat_id, seat_path): (String, OwnedObjectPath) = session
+        .get_property("Seat")
+        .await
+        .context("read logind session Seat")?;
+
+    let scope = SessionScope {
+        session_id,
+        session_path: session_path.to_string(),
+        seat_id: Some(seat_id).filter(|id| !id.is_empty()),
+        seat_path: Some(seat_path.to_string()).filter(|path| path != "/"),
+    };
+    info!(
+        session = scope.session_id,
+        seat = scope.seat_id.as_deref().unwrap_or("<none>"),
+        "Resolved logind session scope"
+    );
+    Ok(scope)
+}
+
+// PropertiesChanged for our session object only. Pinning the path in the
+// rule means the bus never delivers other sessions' Active flips to us at all.
+pub fn build_session_match_rule(scope: &SessionScope) -> Result<MatchRule<'static>> {
+    Ok(MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender(LOGIND)
+        .context("logind session rule: set sender")?
+        .interface("org.freedesktop.DBus.Properties")
+        .context("logind session rule: set interface")?
+        .member("PropertiesChanged")
+        .context("logind session rule: set member")?
+        .path(scope.session_path.clone())
+        .context("logind session rule: set path")?
+        .build())
+}
+
+// Manager.PrepareForSleep(start): true on the way down, false once the
+// machine is back.
+pub fn build_sleep_match_rule() -> Result<MatchRule<'static>> {
+    Ok(MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender(LOGIND)
+        .context("logind sleep rule: set sender")?
+        .interface(MANAGER_IFACE)
+        .context("logind sleep rule: set interface")?
+        .member("PrepareForSleep")
+        .context("logind sleep rule: set member")?
+        .path(LOGIND_PATH)
+        .context("logind sleep rule: set path")?
+        .build())
+}
+
+// Some(true) for the PrepareForSleep that follows a resume, None for a
+// malformed body.
+pub fn resumed(msg: &zbus::Message) -> Option<bool> {
+    let start: bool = msg.body().deserialize().ok()?;
+    Some(!start)
+}
+
+// Pull Session.Active out of a PropertiesChanged changed-properties dict.
+// None when the signal is about other properties (IdleHint, LockedHint, ...).
+pub fn active_from_changed(changed_properties: &HashMap<&str, Value<'_>>) -> Option<bool> {
+    match changed_properties.get("Active")? {
+        Value::Bool(active) => Some(*active),
+        Value::Value(inner) => match **inner {
+            Value::Bool(active) => Some(active),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope() -> SessionScope {
+        SessionScope {
+            session_id: "2".to_string(),
+            session_path: "/org/freedesktop/login1/session/_32".to_string(),
+            seat_id: Some("seat0".to_string()),
+            seat_path: Some("/org/freedesktop/login1/seat/seat0".to_string()),
+        }
+    }
+
+    #[test]
+    fn non_logind_paths_are_always_in_scope() {
+        let scope = scope();
+        assert!(scope.owns_path("/org/freedesktop/UPower/devices/battery_BAT0"));
+        assert!(scope.owns_path("/org/bluez/hci0/dev_test"));
+        assert!(scope.owns_path("/org/freedesktop/login1"));
+    }
+
+    #[test]
+    fn other_sessions_and_seats_are_out_of_scope() {
+        let scope = scope();
+        assert!(scope.owns_path("/org/freedesktop/login1/session/_32"));
+        assert!(!scope.owns_path("/org/freedesktop/login1/session/_33"));
+        assert!(scope.owns_path("/org/freedesktop/login1/seat/seat0"));
+        assert!(!scope.owns_path("/org/freedesktop/login1/seat/seat1"));
+        assert!(!scope.owns_path("/org/freedesktop/login1/user/_1000"));
+    }
+
+    #[test]
+    fn seatless_session_owns_no_seat() {
+        let scope = SessionScope {
+            seat_id: None,
+            seat_path: None,
+            ..scope()
+        };
+        assert!(!scope.owns_path("/org/freedesktop/login1/seat/seat0"));
+    }
+
+    #[test]
+    fn active_is_read_from_changed_properties() {
+        let changed = HashMap::from([("Active", Value::Bool(false))]);
+        assert_eq!(active_from_changed(&changed), Some(false));
+
+        let idle = HashMap::from([("IdleHint", Value::Bool(true))]);
+        assert_eq!(active_from_changed(&idle), None);
+    }
+
+    #[test]
+    fn prepare_for_sleep_false_means_resumed() {
+        let signal = |start: bool| {
+            zbus::Message::signal(LOGIND_PATH, MANAGER_IFACE, "PrepareForSleep")
+                .expect("valid signal header")
+                .build(&(start,))
+                .expect("serializable body")
+        };
+        assert_eq!(resumed(&signal(false)), Some(true));
+        assert_eq!(resumed(&signal(true)), Some(false));
+        let malformed = zbus::Message::signal(LOGIND_PATH, MANAGER_IFACE, "PrepareForSleep")
+            .expect("valid signal header")
+            .build(&("soon",))
+            .expect("serializable body");
+        assert_eq!(resumed(&malformed), None);
+    }
+}
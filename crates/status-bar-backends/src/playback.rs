@@ -0,0 +1,196 @@
+// Playback streams: which applications PipeWire is playing audio for right
+// now, so the taskbar can badge their windows.
+//
+// An application's output stream (`Stream/Output/Audio`) names its client
+// process in `application.process.id`. That is often a helper rather than the
+// process owning the window (Chromium plays from its audio service, a player
+// started in a terminal is the shell's child), so each stream also carries
+// the PIDs of its ancestors and a window matches when its PID is any of them.
+//
+// Like a capture node for the privacy indicators, a stream counts while it
+// is Running: a paused player keeps its stream corked in the graph.
+//
+// This module only tracks; pw.rs owns the bindings and sends a
+// PlaybackUpdate whenever the playing streams or their mute states change.
+
+use std::collections::HashMap;
+
+use crate::bus::{PlaybackStream, PlaybackUpdate};
+
+// Enough for a player in a shell in a multiplexer in a terminal.
+const MAX_LINEAGE: usize = 8;
+
+// The client PID of an application's audio output stream, or None for any
+// other node (and for streams from clients that do not say who they are).
+pub fn stream_pid<'a>(prop: impl Fn(&str) -> Option<&'a str>) -> Option<i32> {
+    if prop("media.class")? != "Stream/Output/Audio" {
+        return None;
+    }
+    prop("application.process.id")?.parse().ok()
+}
+
+// "1234 (comm) S 1 ...": the parent is the second field after the command
+// name, which may itself hold spaces and parentheses.
+pub fn parent_pid(stat: &str) -> Option<i32> {
+    let (_, rest) = stat.rsplit_once(')')?;
+    rest.split_whitespace().nth(1)?.parse().ok()
+}
+
+// The PID and its ancestors below init, nearest first. A process that is
+// gone (or in another PID namespace) just ends the walk early.
+pub fn lineage(pid: i32) -> Vec<i32> {
+    let mut pids = vec![pid];
+    let mut current = pid;
+    while pids.len() < MAX_LINEAGE {
+        let Ok(stat) = std::fs::read_to_string(format!("/proc/{current}/stat")) else {
+            break;
+        };
+        match parent_pid(&stat) {
+            Some(parent) if parent > 1 => {
+                pids.push(parent);
+                current = parent;
+            }
+            _ => break,
+        }
+    }
+    pids
+}
+
+// The playing streams a window with this PID owns.
+pub fn streams_for(update: &PlaybackUpdate, pid: i32) -> Vec<&PlaybackStream> {
+    update
+        .streams
+        .iter()
+        .filter(|stream| stream.pids.contains(&pid))
+        .collect()
+}
+
+#[derive(Debug)]
+struct Tracked {
+    pids: Vec<i32>,
+    running: bool,
+    muted: bool,
+}
+
+// Output streams by PipeWire id. PipeWire-thread only.
+#[derive(Debug, Default)]
+pub struct PlaybackTracker {
+    streams: HashMap<u32, Tracked>,
+}
+
+impl PlaybackTracker {
+    // A new stream; it shows once it reports Running.
+    pub fn add(&mut self, id: u32, pids: Vec<i32>) {
+        self.streams.insert(
+            id,
+            Tracked {
+                pids,
+                running: false,
+                muted: false,
+            },
+        );
+    }
+
+    // Each change returns the new summary when it differs from the old one,
+    // so the caller sends only real transitions.
+    pub fn set_running(&mut self, id: u32, running: bool) -> Option<PlaybackUpdate> {
+        self.change(|streams| {
+            if let Some(stream) = streams.get_mut(&id) {
+                stream.running = running;
+            }
+        })
+    }
+
+    pub fn set_muted(&mut self, id: u32, muted: bool) -> Option<PlaybackUpdate> {
+        self.change(|streams| {
+            if let Some(stream) = streams.get_mut(&id) {
+                stream.muted = muted;
+            }
+        })
+    }
+
+    pub fn remove(&mut self, id: u32) -> Option<PlaybackUpdate> {
+        self.change(|streams| {
+            streams.remove(&id);
+        })
+    }
+
+    fn change(&mut self, apply: impl FnOnce(&mut HashMap<u32, Tracked>)) -> Option<PlaybackUpdate> {
+        let before = self.summary();
+        apply(&mut self.streams);
+        let after = self.summary();
+        (after != before).then_some(after)
+    }
+
+    // Sorted by node id, so equal sets compare equal.
+    fn summary(&self) -> PlaybackUpdate {
+        let mut streams: Vec<PlaybackStream> = self
+            .streams
+            .iter()
+            .filter(|(_, stream)| stream.running)
+            .map(|(id, stream)| PlaybackStream {
+                node_id: *id,
+                pids: stream.pids.clone(),
+                muted: stream.muted,
+            })
+            .collect();
+        streams.sort_by_key(|stream| stream.node_id);
+        PlaybackUpdate { streams }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn props<'a>(pairs: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<&'a str> {
+        move |key| {
+            pairs
+                .iter()
+                .find(|(candidate, _)| *candidate == key)
+                .map(|(_, value)| *value)
+        }
+    }
+
+    #[test]
+    fn output_streams_name_their_client() {
+        let player = [
+            ("media.class", "Stream/Output/Audio"),
+            ("application.process.id", "4242"),
+        ];
+        let anonymous = [("media.class", "Stream/Output/Audio")];
+        let recorder = [
+            ("media.class", "Stream/Input/Audio"),
+            ("application.process.id", "7"),
+        ];
+        assert_eq!(stream_pid(props(&player)), Some(4242));
+        assert_eq!(stream_pid(props(&anonymous)), None);
+        assert_eq!(stream_pid(props(&recorder)), None);
+        assert_eq!(
+            parent_pid("4242 (Web Content (x)) S 4100 4242 4100 0"),
+            Some(4100)
+        );
+        assert_eq!(parent_pid("garbage"), None);
+    }
+
+    #[test]
+    fn only_running_streams_are_reported() {
+        let mut tracker = PlaybackTracker::default();
+        tracker.add(60, vec![4300, 4100]);
+        assert_eq!(tracker.set_muted(60, true), None);
+        let update = tracker.set_running(60, true).expect("playing is news");
+        assert_eq!(
+            update.streams,
+            vec![PlaybackStream {
+                node_id: 60,
+                pids: vec![4300, 4100],
+                muted: true,
+            }]
+        );
+        assert_eq!(streams_for(&update, 4100).len(), 1);
+        assert!(streams_for(&update, 4200).is_empty());
+        assert_eq!(tracker.set_running(60, true), None);
+        assert_eq!(tracker.remove(60), Some(PlaybackUpdate::default()));
+        assert_eq!(tracker.remove(60), None);
+    }
+}
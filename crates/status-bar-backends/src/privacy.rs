@@ -0,0 +1,172 @@
+// Privacy indicators: which kinds of capture PipeWire is currently serving.
+//
+// The PipeWire thread binds every node that can capture something and reports
+// its state here. A node counts while it is Running: an idle camera source or a
+// corked recording stream stays in the graph but captures nothing, so mere
+// presence would keep the dots lit long after a call ended.
+//
+// - Microphone: a client's audio input stream (`Stream/Input/Audio`). Peak
+//   meters such as pavucontrol's open monitor streams (`stream.monitor`); those
+//   only read levels and are not counted.
+// - Camera: a video source backed by a capture device (v4l2, libcamera).
+// - Screen: a video source with no device behind it, which is what screencast
+//   portals (xdg-desktop-portal-hyprland, -wlr) publish while sharing.
+//
+// This module only classifies and counts; pw.rs owns the bindings and sends a
+// PrivacyUpdate whenever the set of active kinds changes.
+
+use std::collections::HashMap;
+
+use crate::bus::PrivacyUpdate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capture {
+    Microphone,
+    Camera,
+    Screen,
+}
+
+// Decide from a node's properties whether it is a capture node worth
+// watching. `prop` looks up one property key.
+pub fn classify<'a>(prop: impl Fn(&str) -> Option<&'a str>) -> Option<Capture> {
+    match prop("media.class")? {
+        "Stream/Input/Audio" if prop("stream.monitor") == Some("true") => None,
+        "Stream/Input/Audio" => Some(Capture::Microphone),
+        "Video/Source" if prop("device.api").is_some() => Some(Capture::Camera),
+        "Video/Source" | "Stream/Output/Video" => Some(Capture::Screen),
+        _ => None,
+    }
+}
+
+// Running capture nodes by PipeWire id. PipeWire-thread only.
+#[derive(Debug, Default)]
+pub struct CaptureTracker {
+    running: HashMap<u32, Capture>,
+}
+
+impl CaptureTracker {
+    // Record a node's running state. Returns the new summary when it changed,
+    // so the caller sends only real transitions.
+    pub fn set(&mut self, id: u32, capture: Capture, running: bool) -> Option<PrivacyUpdate> {
+        let before = self.summary();
+        if running {
+            self.running.insert(id, capture);
+        } else {
+            self.running.remove(&id);
+        }
+        let after = self.summary();
+        (after != before).then_some(after)
+    }
+
+    pub fn remove(&mut self, id: u32) -> Option<PrivacyUpdate> {
+        let before = self.summary();
+        self.running.remove(&id);
+        let after = self.summary();
+        (after != before).then_some(after)
+    }
+
+    fn summary(&self) -> PrivacyUpdate {
+        let any = |kind| self.running.values().any(|capture| *capture == kind);
+        PrivacyUpdate {
+            microphone: any(Capture::Microphone),
+            camera: any(Capture::Camera),
+            screen: any(Capture::Screen),
+        }
+    }
+}
+
+// The indicator text: one glyph per active kind, empty (hiding the widget)
+// when nothing captures.
+pub fn indicator_text(update: &PrivacyUpdate) -> String {
+    [
+        (update.screen, "🖥"),
+        (update.camera, "📷"),
+        (update.microphone, "🎤"),
+    ]
+    .iter()
+    .filter(|(active, _)| *active)
+    .map(|(_, glyph)| *glyph)
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+pub fn indicator_tooltip(update: &PrivacyUpdate) -> String {
+    let kinds = [
+        (update.screen, "screen"),
+        (update.camera, "camera"),
+        (update.microphone, "microphone"),
+    ]
+    .iter()
+    .filter(|(active, _)| *active)
+    .map(|(_, kind)| *kind)
+    .collect::<Vec<_>>();
+    if kinds.is_empty() {
+        return String::new();
+    }
+    format!("In use: {}", kinds.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn props<'a>(pairs: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<&'a str> {
+        move |key| {
+            pairs
+                .iter()
+                .find(|(candidate, _)| *candidate == key)
+                .map(|(_, value)| *value)
+        }
+    }
+
+    #[test]
+    fn classifies_capture_nodes() {
+        let mic = [("media.class", "Stream/Input/Audio")];
+        let meter = [
+            ("media.class", "Stream/Input/Audio"),
+            ("stream.monitor", "true"),
+        ];
+        let camera = [("media.class", "Video/Source"), ("device.api", "v4l2")];
+        let portal = [
+            ("media.class", "Video/Source"),
+            ("node.name", "xdph-streaming-0"),
+        ];
+        let sink = [("media.class", "Audio/Sink")];
+        assert_eq!(classify(props(&mic)), Some(Capture::Microphone));
+        assert_eq!(classify(props(&meter)), None);
+        assert_eq!(classify(props(&camera)), Some(Capture::Camera));
+        assert_eq!(classify(props(&portal)), Some(Capture::Screen));
+        assert_eq!(classify(props(&sink)), None);
+        assert_eq!(classify(props(&[])), None);
+    }
+
+    #[test]
+    fn only_transitions_are_reported() {
+        let mut tracker = CaptureTracker::default();
+        let mic_on = PrivacyUpdate {
+            microphone: true,
+            ..PrivacyUpdate::default()
+        };
+        assert_eq!(tracker.set(40, Capture::Microphone, true), Some(mic_on));
+        // A second recording stream changes nothing visible.
+        assert_eq!(tracker.set(41, Capture::Microphone, true), None);
+        assert_eq!(tracker.remove(40), None);
+        assert_eq!(
+            tracker.set(41, Capture::Microphone, false),
+            Some(PrivacyUpdate::default())
+        );
+        assert_eq!(tracker.remove(99), None);
+    }
+
+    #[test]
+    fn indicator_lists_active_kinds() {
+        let update = PrivacyUpdate {
+            microphone: true,
+            camera: false,
+            screen: true,
+        };
+        assert_eq!(indicator_text(&update), "🖥 🎤");
+        assert_eq!(indicator_tooltip(&update), "In use: screen, microphone");
+        assert_eq!(indicator_text(&PrivacyUpdate::default()), "");
+    }
+}
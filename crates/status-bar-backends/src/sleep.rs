@@ -0,0 +1,39 @@
+// Suspend and resume. logind announces both with Manager.PrepareForSleep;
+// on the way back this bumps Bus::send_resume, and whatever subscribed with
+// Bus::resumes refreshes at once: the clock re-reads the wall clock (its
+// timers run on the monotonic clock, which stood still meanwhile), the
+// pollers sample without waiting out their period, and the D-Bus monitors
+// re-read the battery and Bluetooth devices.
+//
+// Nothing else here has an opinion on sleep, so the watch runs on its own,
+// whatever widgets are enabled.
+
+use anyhow::{Result, bail};
+use tracing::{debug, info, warn};
+
+use crate::bus::Bus;
+use crate::buses::{BusKind, BusManager};
+use crate::dbus::supervise;
+use crate::logind;
+
+pub async fn run_sleep_watch_supervised(bus: Bus, buses: BusManager) {
+    supervise("sleep watch", || watch_sleep(&bus, &buses)).await
+}
+
+async fn watch_sleep(bus: &Bus, buses: &BusManager) -> Result<()> {
+    let mut signals = buses
+        .subscribe(BusKind::System, vec![logind::build_sleep_match_rule()?])
+        .await?;
+    debug!("Watching logind for suspend and resume");
+    while let Some(msg) = signals.next().await {
+        match logind::resumed(&msg) {
+            Some(true) => {
+                info!("Resumed from sleep; refreshing widgets");
+                bus.send_resume();
+            }
+            Some(false) => info!("Going to sleep"),
+            None => warn!("Malformed PrepareForSleep signal"),
+        }
+    }
+    bail!("logind subscription ended")
+}
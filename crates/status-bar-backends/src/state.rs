@@ -0,0 +1,155 @@
+// The bar's structured state: the latest workspace, focused window, audio
+// sink, battery, Bluetooth reading and screen brightness, each in a watch
+// channel. The Bus send helpers (and the PipeWire thread, which sends outside
+// the Bus) write here as well as into their widget's channel; the brightness,
+// which no widget shows, is only written here, so anything that needs more than one
+// source subscribes to the ones it combines instead of reaching into another
+// widget's drain: the title pill's color, derived from the workspace, is the
+// first such view (see the bar's views.rs).
+//
+// A watch keeps only the newest value, which is all a view wants; the
+// per-widget mpsc channels stay for the drains, which must see every
+// transition (a mute toggle, a hook event). A value equal to the current one
+// is not a change, so a repeated report wakes nobody.
+
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+use crate::bus::{BatteryUpdate, BluetoothUpdate, TitleUpdate, VolumeUpdate, WorkspaceUpdate};
+
+// Cheap to clone and Send + Sync, like the Bus that carries it.
+#[derive(Clone)]
+pub struct AppState {
+    inner: Arc<Channels>,
+}
+
+struct Channels {
+    // None until the compositor first reports.
+    workspace: watch::Sender<Option<WorkspaceUpdate>>,
+    active_window: watch::Sender<TitleUpdate>,
+    audio: watch::Sender<Option<VolumeUpdate>>,
+    power: watch::Sender<Option<BatteryUpdate>>,
+    bluetooth: watch::Sender<BluetoothUpdate>,
+    // Percent of the backlight's maximum; None without a backlight monitor.
+    brightness: watch::Sender<Option<u8>>,
+}
+
+impl AppState {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Channels {
+                workspace: watch::channel(None).0,
+                active_window: watch::channel(TitleUpdate::default()).0,
+                audio: watch::channel(None).0,
+                power: watch::channel(None).0,
+                bluetooth: watch::channel(BluetoothUpdate::default()).0,
+                brightness: watch::channel(None).0,
+            }),
+        }
+    }
+
+    pub fn set_workspace(&self, update: WorkspaceUpdate) {
+        replace_if_changed(&self.inner.workspace, Some(update));
+    }
+
+    pub fn set_active_window(&self, update: TitleUpdate) {
+        replace_if_changed(&self.inner.active_window, update);
+    }
+
+    pub fn set_audio(&self, update: VolumeUpdate) {
+        replace_if_changed(&self.inner.audio, Some(update));
+    }
+
+    // No default sink, or no PipeWire.
+    pub fn clear_audio(&self) {
+        replace_if_changed(&self.inner.audio, None);
+    }
+
+    pub fn set_power(&self, update: BatteryUpdate) {
+        replace_if_changed(&self.inner.power, Some(update));
+    }
+
+    pub fn set_bluetooth(&self, update: BluetoothUpdate) {
+        replace_if_changed(&self.inner.bluetooth, update);
+    }
+
+    pub fn set_brightness(&self, percent: u8) {
+        replace_if_changed(&self.inner.brightness, Some(percent));
+    }
+
+    // Each receiver starts with the current value marked as seen; read it
+    // with borrow() and wait for the next with changed().
+
+    pub fn workspace(&self) -> watch::Receiver<Option<WorkspaceUpdate>> {
+        self.inner.workspace.subscribe()
+    }
+
+    pub fn active_window(&self) -> watch::Receiver<TitleUpdate> {
+        self.inner.active_window.subscribe()
+    }
+
+    pub fn audio(&self) -> watch::Receiver<Option<VolumeUpdate>> {
+        self.inner.audio.subscribe()
+    }
+
+    pub fn power(&self) -> watch::Receiver<Option<BatteryUpdate>> {
+        self.inner.power.subscribe()
+    }
+
+    pub fn bluetooth(&self) -> watch::Receiver<BluetoothUpdate> {
+        self.inner.bluetooth.subscribe()
+    }
+
+    pub fn brightness(&self) -> watch::Receiver<Option<u8>> {
+        self.inner.brightness.subscribe()
+    }
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// send_if_modified stores the value even with no receiver alive, so a view
+// subscribing later still starts from the latest reading.
+fn replace_if_changed<T: PartialEq>(sender: &watch::Sender<T>, value: T) {
+    sender.send_if_modified(|current| {
+        if *current == value {
+            return false;
+        }
+        *current = value;
+        true
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_a_new_value_is_a_change() {
+        let state = AppState::new();
+        let mut bluetooth = state.bluetooth();
+        let update = BluetoothUpdate {
+            text: "P80".to_string(),
+            connecting: false,
+        };
+        state.set_bluetooth(update.clone());
+        assert!(bluetooth.has_changed().expect("sender is alive"));
+        assert_eq!(*bluetooth.borrow_and_update(), update);
+
+        state.set_bluetooth(update);
+        assert!(!bluetooth.has_changed().expect("sender is alive"));
+
+        // A late subscriber still reads the latest value.
+        let workspace = WorkspaceUpdate {
+            name: "web".to_string(),
+            id: 2,
+            special: false,
+        };
+        state.set_workspace(workspace.clone());
+        assert_eq!(*state.clone().workspace().borrow(), Some(workspace));
+    }
+}
@@ -0,0 +1,203 @@
+// The backends as async Streams, for front-ends that would rather not wire a
+// Bus themselves. Each function starts the same supervised producer the bar
+// runs, on a Bus of its own, and hands back the receiving ends; once every
+// stream from a call is dropped the producer stops. The functions spawn onto
+// the current tokio runtime, so call them from inside one.
+//
+// The streams drop their oldest update when full, like the channels under
+// them (see channel.rs): a consumer that stops polling misses stale updates,
+// never stalls an IPC listener. Events that are not state, like a sink being
+// plugged in, come through tokio's mpsc instead and are never dropped.
+
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use anyhow::Result;
+use futures_util::Stream;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::bus::{
+    BatteryUpdate, BluetoothUpdate, Bus, CAPACITY, HeadsetUpdate, PlaybackUpdate, PrivacyUpdate,
+    SinkAdded, SinkList, TitleUpdate, VolumeReport, WorkspaceUpdate,
+};
+use crate::buses::BusManager;
+use crate::channel::{self, Receiver};
+use crate::compositor::AnyCompositor;
+use crate::dbus;
+use crate::pw::{self, AudioCommand, PipewireChannels, PipewireHandle};
+use crate::state::AppState;
+
+// What keeps a call's streams fed, shared between them.
+enum Producer {
+    Task(JoinHandle<()>),
+    // PipeWire runs on its own thread; stopping joins it, which takes as
+    // long as one ThreadLoop iteration.
+    Thread(Option<PipewireHandle>),
+}
+
+impl Drop for Producer {
+    fn drop(&mut self) {
+        match self {
+            Producer::Task(task) => task.abort(),
+            Producer::Thread(handle) => {
+                if let Some(handle) = handle.take() {
+                    handle.stop();
+                }
+            }
+        }
+    }
+}
+
+// State snapshots on a drop-oldest channel, or events on an mpsc one.
+enum Source<T> {
+    Snapshots(Receiver<T>),
+    Events(mpsc::UnboundedReceiver<T>),
+}
+
+pub struct BackendStream<T> {
+    receiver: Source<T>,
+    _producer: Arc<Producer>,
+}
+
+impl<T> BackendStream<T> {
+    fn new(receiver: Receiver<T>, producer: &Arc<Producer>) -> Self {
+        Self {
+            receiver: Source::Snapshots(receiver),
+            _producer: producer.clone(),
+        }
+    }
+
+    fn events(receiver: mpsc::UnboundedReceiver<T>, producer: &Arc<Producer>) -> Self {
+        Self {
+            receiver: Source::Events(receiver),
+            _producer: producer.clone(),
+        }
+    }
+}
+
+impl<T> Stream for BackendStream<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        match &mut self.receiver {
+            Source::Snapshots(receiver) => receiver.poll_recv(cx),
+            Source::Events(receiver) => receiver.poll_recv(cx),
+        }
+    }
+}
+
+// Workspace lists from Hyprland or Sway, whichever the environment names.
+pub fn workspaces() -> BackendStream<WorkspaceUpdate> {
+    let (bus, receivers) = Bus::new();
+    let producer = Arc::new(Producer::Task(
+        AnyCompositor::detect().spawn_workspace_listener(bus),
+    ));
+    BackendStream::new(receivers.workspace, &producer)
+}
+
+// The focused window's title, class and PID.
+pub fn titles() -> BackendStream<TitleUpdate> {
+    let (bus, receivers) = Bus::new();
+    let producer = Arc::new(Producer::Task(
+        AnyCompositor::detect().spawn_title_listener(bus),
+    ));
+    BackendStream::new(receivers.title, &producer)
+}
+
+// The system battery from UPower; empty text without a battery.
+pub fn battery(buses: BusManager) -> BackendStream<BatteryUpdate> {
+    let (bus, receivers) = Bus::new();
+    let producer = Arc::new(Producer::Task(tokio::spawn(
+        dbus::run_upower_monitor_supervised(bus, buses),
+    )));
+    BackendStream::new(receivers.battery, &producer)
+}
+
+// Connected BlueZ devices and their battery levels.
+pub fn bluetooth(buses: BusManager) -> BackendStream<BluetoothUpdate> {
+    let (bus, receivers) = Bus::new();
+    let producer = Arc::new(Producer::Task(tokio::spawn(
+        dbus::run_bluez_monitor_supervised(bus, buses),
+    )));
+    BackendStream::new(receivers.bluetooth, &producer)
+}
+
+pub struct AudioStreams {
+    // Levels of the default sink, and reports that there is none.
+    pub volume: BackendStream<VolumeReport>,
+    pub privacy: BackendStream<PrivacyUpdate>,
+    pub playback: BackendStream<PlaybackUpdate>,
+    pub sinks: BackendStream<SinkList>,
+    pub sink_added: BackendStream<SinkAdded>,
+    pub headset: BackendStream<HeadsetUpdate>,
+    // Switches the default sink, mutes a stream or toggles a headset's
+    // profile; see AudioCommand.
+    pub commands: pipewire::channel::Sender<AudioCommand>,
+}
+
+pub fn audio() -> Result<AudioStreams> {
+    let (volume, volume_rx) = channel::channel("volume", CAPACITY);
+    let (privacy, privacy_rx) = channel::channel("privacy", CAPACITY);
+    let (playback, playback_rx) = channel::channel("playback", CAPACITY);
+    let (sink_added, sink_added_rx) = mpsc::unbounded_channel();
+    let (sink_list, sink_list_rx) = channel::channel("sink_list", CAPACITY);
+    let (headset, headset_rx) = channel::channel("headset", CAPACITY);
+    let (commands, commands_rx) = pipewire::channel::channel();
+    let handle = pw::start_pipewire_thread(PipewireChannels {
+        volume,
+        privacy,
+        playback,
+        sink_added,
+        sink_list,
+        headset,
+        commands: commands_rx,
+        // Nothing reads it; the streams carry every update already.
+        state: AppState::new(),
+    })?;
+    let producer = Arc::new(Producer::Thread(Some(handle)));
+    Ok(AudioStreams {
+        volume: BackendStream::new(volume_rx, &producer),
+        privacy: BackendStream::new(privacy_rx, &producer),
+        playback: BackendStream::new(playback_rx, &producer),
+        sinks: BackendStream::new(sink_list_rx, &producer),
+        sink_added: BackendStream::events(sink_added_rx, &producer),
+        headset: BackendStream::new(headset_rx, &producer),
+        commands,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+    use tokio::sync::oneshot;
+
+    #[test]
+    fn dropping_the_last_stream_stops_the_producer() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("build test runtime");
+        runtime.block_on(async {
+            let (tx, rx) = channel::channel("test", CAPACITY);
+            let (alive, stopped) = oneshot::channel::<()>();
+            let producer = Arc::new(Producer::Task(tokio::spawn(async move {
+                let _alive = alive;
+                tx.send(7).ok();
+                std::future::pending::<()>().await;
+            })));
+            let mut first = BackendStream::new(rx, &producer);
+            let second = BackendStream::new(channel::channel::<u8>("test", 1).1, &producer);
+            drop(producer);
+
+            assert_eq!(first.next().await, Some(7));
+            drop(first);
+            tokio::task::yield_now().await;
+            drop(second);
+            // The aborted task drops its end of the oneshot.
+            assert!(stopped.await.is_err());
+        });
+    }
+}
@@ -0,0 +1,334 @@
+// Sway (and i3) IPC: the Compositor trait's second implementation.
+//
+// The protocol is a tiny framed one over $SWAYSOCK (i3: $I3SOCK): the magic
+// "i3-ipc", a native-endian u32 payload length and message type, then a JSON
+// payload. Replies share their request's type; events set the high bit. A
+// connection that subscribed to events gets nothing else, so every watcher
+// keeps one subscribed connection and opens short-lived ones for queries.
+//
+// Like the Hyprland listeners, events are only used as "something changed":
+// each one re-queries the workspace list or the tree and sends the result,
+// which cannot drift the way patching per event could.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde_json::Value;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+use tracing::{debug, info};
+
+use crate::bus::{Bus, TitleUpdate, WorkspaceUpdate};
+use crate::compositor::Workspace;
+use crate::format::{
+    MAX_TITLE_CHARS, WorkspaceLabels, format_title_string, format_workspace_name_from_string,
+};
+
+const MAGIC: &[u8] = b"i3-ipc";
+const HEADER_LEN: usize = MAGIC.len() + 8;
+
+const RUN_COMMAND: u32 = 0;
+const GET_WORKSPACES: u32 = 1;
+const SUBSCRIBE: u32 = 2;
+const GET_TREE: u32 = 4;
+const EVENT_BIT: u32 = 0x8000_0000;
+
+fn encode(kind: u32, payload: &str) -> Vec<u8> {
+    let mut message = Vec::with_capacity(HEADER_LEN + payload.len());
+    message.extend_from_slice(MAGIC);
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&kind.to_ne_bytes());
+    message.extend_from_slice(payload.as_bytes());
+    message
+}
+
+// (payload length, message type)
+fn decode_header(header: &[u8; HEADER_LEN]) -> Result<(usize, u32)> {
+    let Some(rest) = header.strip_prefix(MAGIC) else {
+        bail!("IPC message without the i3-ipc magic");
+    };
+    let field = |range: std::ops::Range<usize>| -> Result<u32> {
+        let bytes = rest[range].try_into().context("short IPC header")?;
+        Ok(u32::from_ne_bytes(bytes))
+    };
+    Ok((field(0..4)? as usize, field(4..8)?))
+}
+
+struct Connection {
+    stream: UnixStream,
+}
+
+impl Connection {
+    async fn open(socket: &Path) -> Result<Self> {
+        let stream = UnixStream::connect(socket)
+            .await
+            .with_context(|| format!("connect to {}", socket.display()))?;
+        Ok(Self { stream })
+    }
+
+    async fn read(&mut self) -> Result<(u32, Value)> {
+        let mut header = [0; HEADER_LEN];
+        self.stream
+            .read_exact(&mut header)
+            .await
+            .context("read IPC header")?;
+        let (len, kind) = decode_header(&header)?;
+        let mut payload = vec![0; len];
+        self.stream
+            .read_exact(&mut payload)
+            .await
+            .context("read IPC payload")?;
+        let value = serde_json::from_slice(&payload).context("parse IPC payload")?;
+        Ok((kind, value))
+    }
+
+    async fn request(&mut self, kind: u32, payload: &str) -> Result<Value> {
+        self.stream
+            .write_all(&encode(kind, payload))
+            .await
+            .context("write IPC request")?;
+        let (reply_kind, reply) = self.read().await?;
+        if reply_kind != kind {
+            bail!("IPC reply of type {reply_kind} to a request of type {kind}");
+        }
+        Ok(reply)
+    }
+
+    async fn subscribe(&mut self, events: &[&str]) -> Result<()> {
+        let payload = serde_json::to_string(events).context("encode subscription")?;
+        let reply = self.request(SUBSCRIBE, &payload).await?;
+        if reply["success"] != Value::Bool(true) {
+            bail!("subscription to {events:?} refused");
+        }
+        Ok(())
+    }
+
+    // The next event's type, payload discarded.
+    async fn next_event(&mut self) -> Result<u32> {
+        loop {
+            let (kind, _) = self.read().await?;
+            if kind & EVENT_BIT != 0 {
+                return Ok(kind & !EVENT_BIT);
+            }
+        }
+    }
+}
+
+fn parse_workspaces(reply: &Value, labels: &WorkspaceLabels) -> Result<Vec<Workspace>> {
+    let entries = reply
+        .as_array()
+        .ok_or_else(|| anyhow!("workspace reply is not a list"))?;
+    Ok(entries
+        .iter()
+        .map(|entry| {
+            // Named-only workspaces report num -1.
+            let id = entry["num"].as_i64().unwrap_or(-1) as i32;
+            let name = entry["name"].as_str().unwrap_or_default();
+            Workspace {
+                id,
+                name: format_workspace_name_from_string(name, id, labels),
+                focused: entry["focused"].as_bool().unwrap_or(false),
+                special: false,
+            }
+        })
+        .collect())
+}
+
+fn find_focused(node: &Value) -> Option<&Value> {
+    if node["focused"].as_bool() == Some(true) {
+        return Some(node);
+    }
+    ["nodes", "floating_nodes"]
+        .iter()
+        .filter_map(|key| node[key].as_array())
+        .flatten()
+        .find_map(find_focused)
+}
+
+// The focused container's title and app id. An empty workspace holds the
+// focus itself, which clears the title.
+fn focused_window(tree: &Value) -> TitleUpdate {
+    let Some(node) = find_focused(tree) else {
+        return TitleUpdate::default();
+    };
+    if !matches!(node["type"].as_str(), Some("con" | "floating_con")) {
+        return TitleUpdate::default();
+    }
+    // Xwayland windows have no app_id, only an X11 class.
+    let class = node["app_id"]
+        .as_str()
+        .or_else(|| node["window_properties"]["class"].as_str())
+        .unwrap_or_default();
+    let title = node["name"].as_str().unwrap_or_default().to_string();
+    TitleUpdate {
+        title: format_title_string(title, MAX_TITLE_CHARS),
+        class: class.to_string(),
+        pid: node["pid"].as_i64().and_then(|pid| i32::try_from(pid).ok()),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Sway {
+    socket: PathBuf,
+    pub labels: WorkspaceLabels,
+}
+
+impl Sway {
+    pub fn from_env() -> Option<Self> {
+        let socket = std::env::var_os("SWAYSOCK")
+            .or_else(|| std::env::var_os("I3SOCK"))
+            .filter(|socket| !socket.is_empty())?;
+        Some(Self {
+            socket: PathBuf::from(socket),
+            labels: WorkspaceLabels::default(),
+        })
+    }
+
+    pub async fn workspaces(&self) -> Result<Vec<Workspace>> {
+        let mut connection = Connection::open(&self.socket).await?;
+        let reply = connection.request(GET_WORKSPACES, "").await?;
+        parse_workspaces(&reply, &self.labels)
+    }
+
+    async fn focused_window(&self) -> Result<TitleUpdate> {
+        let mut connection = Connection::open(&self.socket).await?;
+        let tree = connection.request(GET_TREE, "").await?;
+        Ok(focused_window(&tree))
+    }
+
+    pub async fn watch_workspace(&self, bus: &Bus) -> Result<()> {
+        let mut events = Connection::open(&self.socket).await?;
+        events.subscribe(&["workspace"]).await?;
+        info!("Listening for Sway workspace events");
+        loop {
+            events.next_event().await?;
+            let workspaces = self.workspaces().await?;
+            let Some(focused) = workspaces.into_iter().find(|workspace| workspace.focused) else {
+                continue;
+            };
+            debug!("Workspace changed to: {}", focused.name);
+            bus.send_workspace_update(WorkspaceUpdate {
+                name: focused.name,
+                id: focused.id,
+                special: focused.special,
+            })?;
+        }
+    }
+
+    // Switching to an empty workspace moves the focus without a window event,
+    // hence the workspace subscription.
+    pub async fn watch_active_window(&self, bus: &Bus) -> Result<()> {
+        let mut events = Connection::open(&self.socket).await?;
+        events.subscribe(&["window", "workspace"]).await?;
+        info!("Listening for Sway window events");
+        bus.send_title_update(self.focused_window().await?)?;
+        loop {
+            events.next_event().await?;
+            let update = self.focused_window().await?;
+            debug!(
+                title = update.title,
+                class = update.class,
+                "Focused window changed"
+            );
+            bus.send_title_update(update)?;
+        }
+    }
+
+    async fn run_command(&self, command: &str) -> Result<()> {
+        let mut connection = Connection::open(&self.socket).await?;
+        let reply = connection.request(RUN_COMMAND, command).await?;
+        let failure = reply
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|outcome| outcome["success"] != Value::Bool(true));
+        if let Some(failure) = failure {
+            bail!(
+                "{command:?} failed: {}",
+                failure["error"].as_str().unwrap_or("unknown error")
+            );
+        }
+        Ok(())
+    }
+
+    // Addresses are container ids; anything else never reaches the command
+    // string.
+    fn container_id(address: &str) -> Result<u64> {
+        address
+            .parse()
+            .with_context(|| format!("{address:?} is not a Sway container id"))
+    }
+
+    pub async fn focus_window(&self, address: &str) -> Result<()> {
+        let id = Self::container_id(address)?;
+        self.run_command(&format!("[con_id={id}] focus")).await
+    }
+
+    pub async fn close_window(&self, address: &str) -> Result<()> {
+        let id = Self::container_id(address)?;
+        self.run_command(&format!("[con_id={id}] kill")).await
+    }
+
+    pub async fn focus_workspace(&self, id: i32) -> Result<()> {
+        self.run_command(&format!("workspace number {id}")).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn header_round_trips() {
+        let message = encode(GET_TREE, "{}");
+        let header: [u8; HEADER_LEN] = message[..HEADER_LEN]
+            .try_into()
+            .expect("encoded message has a full header");
+        assert_eq!(
+            decode_header(&header).expect("header decodes"),
+            (2, GET_TREE)
+        );
+        assert_eq!(&message[HEADER_LEN..], b"{}");
+        assert!(decode_header(&[0; HEADER_LEN]).is_err());
+    }
+
+    #[test]
+    fn focused_workspace_is_flagged() {
+        let reply = json!([
+            { "num": 1, "name": "1", "focused": false },
+            { "num": -1, "name": "mail", "focused": true },
+        ]);
+        let workspaces =
+            parse_workspaces(&reply, &WorkspaceLabels::default()).expect("workspace list parses");
+        assert_eq!(workspaces.len(), 2);
+        assert!(workspaces[1].focused);
+        assert_eq!(workspaces[1].name, "Workspace mail");
+        assert!(parse_workspaces(&json!({}), &WorkspaceLabels::default()).is_err());
+    }
+
+    #[test]
+    fn title_comes_from_the_focused_container() {
+        let tree = json!({
+            "type": "root", "focused": false,
+            "nodes": [{
+                "type": "workspace", "focused": false,
+                "nodes": [{ "type": "con", "focused": false, "name": "~", "app_id": "foot" }],
+                "floating_nodes": [{
+                    "type": "floating_con", "focused": true, "name": "Steam",
+                    "app_id": null, "pid": 4242, "window_properties": { "class": "steam" }
+                }]
+            }]
+        });
+        assert_eq!(
+            focused_window(&tree),
+            TitleUpdate {
+                title: "Steam".to_string(),
+                class: "steam".to_string(),
+                pid: Some(4242),
+            }
+        );
+        let empty = json!({ "type": "root", "nodes": [{ "type": "workspace", "focused": true }] });
+        assert_eq!(focused_window(&empty), TitleUpdate::default());
+    }
+}
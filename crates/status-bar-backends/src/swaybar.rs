@@ -0,0 +1,223 @@
+// The i3bar/swaybar protocol for `gtk-status-bar --output swaybar`, so swaybar
+// (or i3bar) can show the backends without the GTK bar:
+//
+//   bar {
+//       status_command gtk-status-bar --output swaybar
+//   }
+//
+// After the header comes an endless JSON array with one element per change,
+// the blocks left to right: workspace, window, privacy, volume, Bluetooth,
+// battery. An empty reading leaves its block out, as the bar hides its widget.
+// The header asks for click events, which swaybar writes to stdin as another
+// endless array, one object per line; scrolling over the workspace block
+// switches workspaces like the bar's widget does. Other clicks are ignored.
+
+use std::io::Write;
+
+use anyhow::{Context, Result};
+use serde_json::{Value, json};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tracing::{debug, info};
+
+use crate::bus::BatteryState;
+use crate::buses::BusManager;
+use crate::compositor::AnyCompositor;
+use crate::headless::{Snapshot, Snapshots};
+
+const HEADER: &str = r#"{"version":1,"click_events":true}"#;
+// The bar's default `[battery] critical_percent`.
+const CRITICAL_PERCENT: f64 = 10.0;
+// X11 button numbers, which swaybar keeps for the wheel.
+const SCROLL_UP: u64 = 4;
+const SCROLL_DOWN: u64 = 5;
+
+fn block(name: &str, full_text: &str) -> Value {
+    json!({
+        "name": name,
+        "full_text": full_text,
+    })
+}
+
+pub fn blocks(snapshot: &Snapshot) -> Vec<Value> {
+    let mut blocks = Vec::new();
+    if let Some(workspace) = &snapshot.workspace {
+        blocks.push(block("workspace", &workspace.name));
+    }
+    if !snapshot.window.title.is_empty() {
+        let mut window = block("window", &snapshot.window.title);
+        window["instance"] = json!(snapshot.window.class);
+        blocks.push(window);
+    }
+    let privacy = &snapshot.privacy;
+    let capturing: String = [
+        (privacy.screen, "🖥"),
+        (privacy.camera, "📷"),
+        (privacy.microphone, "🎤"),
+    ]
+    .iter()
+    .filter(|(active, _)| *active)
+    .map(|(_, icon)| *icon)
+    .collect();
+    if !capturing.is_empty() {
+        let mut privacy = block("privacy", &capturing);
+        privacy["urgent"] = json!(true);
+        blocks.push(privacy);
+    }
+    if let Some(audio) = &snapshot.audio
+        && let Some(percent) = audio.channel_percent.or(audio.volume_percent)
+    {
+        // The volume widget's text: icon, the sink's initial, the level.
+        let icon = if audio.is_muted == Some(true) {
+            "🔇"
+        } else {
+            "🔊"
+        };
+        let initial = audio.name.chars().next().unwrap_or('A');
+        let mut volume = block("volume", &format!("{icon}{initial}{percent}"));
+        volume["instance"] = json!(audio.name);
+        blocks.push(volume);
+    }
+    if !snapshot.bluetooth.text.is_empty() {
+        blocks.push(block("bluetooth", &snapshot.bluetooth.text));
+    }
+    if let Some(battery) = &snapshot.battery
+        && !battery.text.is_empty()
+    {
+        let mut power = block("battery", &battery.text);
+        let critical = battery.state == BatteryState::Empty
+            || (battery.state == BatteryState::Discharging
+                && battery
+                    .percent
+                    .is_some_and(|percent| percent <= CRITICAL_PERCENT));
+        if critical {
+            power["urgent"] = json!(true);
+        }
+        blocks.push(power);
+    }
+    blocks
+}
+
+// One line of swaybar's click array: `[` opens it, and every event after the
+// first comes with a leading comma. Returns the block name and button.
+fn parse_click(line: &str) -> Option<(String, u64)> {
+    let line = line.trim().trim_start_matches(['[', ',']).trim();
+    if line.is_empty() {
+        return None;
+    }
+    let event: Value = serde_json::from_str(line).ok()?;
+    Some((
+        event["name"].as_str()?.to_string(),
+        event["button"].as_u64()?,
+    ))
+}
+
+// Until stdin closes; swaybar without click_events support never writes.
+async fn handle_clicks(compositor: AnyCompositor) {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => return,
+            Err(e) => {
+                debug!("Stopped reading swaybar clicks: {}", e);
+                return;
+            }
+        };
+        let Some((name, button)) = parse_click(&line) else {
+            continue;
+        };
+        debug!(name, button, "swaybar click");
+        match (name.as_str(), button) {
+            ("workspace", SCROLL_UP) => compositor.scroll_workspaces(-1, false, true),
+            ("workspace", SCROLL_DOWN) => compositor.scroll_workspaces(1, false, true),
+            _ => {}
+        }
+    }
+}
+
+// Runs until writing fails (swaybar went away).
+pub async fn run(buses: BusManager, mut out: impl Write) -> Result<()> {
+    info!("Starting swaybar protocol output");
+    tokio::spawn(handle_clicks(AnyCompositor::detect()));
+    writeln!(out, "{HEADER}\n[").context("write swaybar header")?;
+    out.flush().context("flush swaybar header")?;
+
+    let mut snapshots = Snapshots::start(buses);
+    let mut printed = None;
+    while let Some(snapshot) = snapshots.next().await {
+        let line = Value::Array(blocks(snapshot));
+        if printed.as_ref() == Some(&line) {
+            continue;
+        }
+        writeln!(out, "{line},").context("write swaybar blocks")?;
+        out.flush().context("flush swaybar blocks")?;
+        printed = Some(line);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::{BatteryUpdate, PrivacyUpdate, TitleUpdate, VolumeUpdate, WorkspaceUpdate};
+
+    #[test]
+    fn blocks_follow_the_widgets() {
+        let mut snapshot = Snapshot::default();
+        assert!(blocks(&snapshot).is_empty());
+
+        snapshot.workspace = Some(WorkspaceUpdate {
+            name: "web".to_string(),
+            id: 2,
+            special: false,
+        });
+        snapshot.window = TitleUpdate {
+            title: "Inbox".to_string(),
+            class: "thunderbird".to_string(),
+            pid: None,
+        };
+        snapshot.privacy = PrivacyUpdate {
+            microphone: true,
+            camera: false,
+            screen: true,
+        };
+        snapshot.audio = Some(VolumeUpdate {
+            name: "Speakers".to_string(),
+            volume_percent: Some(40),
+            channel_percent: None,
+            is_muted: Some(true),
+        });
+        snapshot.battery = Some(BatteryUpdate {
+            text: "🪫 8%".to_string(),
+            percent: Some(8.0),
+            state: BatteryState::Discharging,
+        });
+        let blocks = blocks(&snapshot);
+        let names: Vec<_> = blocks
+            .iter()
+            .map(|block| block["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            ["workspace", "window", "privacy", "volume", "battery"]
+        );
+        assert_eq!(blocks[1]["instance"], "thunderbird");
+        assert_eq!(blocks[2]["full_text"], "🖥🎤");
+        assert_eq!(blocks[3]["full_text"], "🔇S40");
+        assert_eq!(blocks[4]["urgent"], true);
+    }
+
+    #[test]
+    fn click_lines_parse_with_or_without_the_array_punctuation() {
+        assert_eq!(parse_click("["), None);
+        assert_eq!(
+            parse_click(r#"{"name":"workspace","button":4,"x":10}"#),
+            Some(("workspace".to_string(), 4))
+        );
+        assert_eq!(
+            parse_click(r#",{"name":"volume","instance":"Speakers","button":1}"#),
+            Some(("volume".to_string(), 1))
+        );
+        assert_eq!(parse_click(r#",{"button":1}"#), None);
+    }
+}
@@ -6,6 +6,7 @@ use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use anyhow::{Context, Result, bail};
+use clap::{ArgAction, Subcommand};
 use serde::{Deserialize, Serialize};
 use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::net::{UnixListener, UnixStream};
@@ -36,6 +37,38 @@ pub enum IpcRequest {
     MenuActivate { target: String },
     MenuClick { target: String, entry: i32 },
     CloseMenus,
+    // Bar-level controls. They share the socket so one client (trayctl, a
+    // compositor keybind) reaches both, but the bar answers them before the
+    // tray sees the request.
+    Show,
+    Hide,
+    Toggle,
+    ReloadConfig,
+    SetVisible { widget: String, visible: bool },
+    // A widget that lives until removed (or the bar exits), outside the
+    // configuration file. `config` is a `[[file_watch]]` entry as JSON.
+    AddWidget { config: String },
+    // Only widgets added with AddWidget can be removed.
+    RemoveWidget { name: String },
+    // Opens a widget's popover (the volume outputs, the clock's alarms, quick
+    // settings) with keyboard focus, for keyboard-only use from a keybind.
+    Popover { widget: String },
+}
+
+impl IpcRequest {
+    pub fn is_bar_control(&self) -> bool {
+        matches!(
+            self,
+            Self::Show
+                | Self::Hide
+                | Self::Toggle
+                | Self::ReloadConfig
+                | Self::SetVisible { .. }
+                | Self::AddWidget { .. }
+                | Self::RemoveWidget { .. }
+                | Self::Popover { .. }
+        )
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
@@ -74,6 +107,107 @@ impl IpcResponse {
     }
 }
 
+/// A request as typed on a command line (`menu-click Bluetooth 12`,
+/// `set-visible battery off`). trayctl and `gtk-status-bar msg` both take
+/// their verbs from this, so each accepts exactly the verbs the protocol has.
+///
+/// A TARGET is a zero-based index from `list`, an exact item title, or an
+/// exact item key.
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum Command {
+    /// List the tray items: index, title, status, click action and key.
+    List,
+    /// Activate an item, as a left click does.
+    Activate { target: String },
+    /// Secondary-activate an item, as a middle click does.
+    SecondaryActivate { target: String },
+    /// Open an item's menu without taking the keyboard.
+    ContextMenu { target: String },
+    /// Open an item's menu and navigate it with the keyboard.
+    KeyboardMenu { target: String },
+    /// Resume keyboard navigation on the icon the last session ended on, or
+    /// the first icon when that one is gone.
+    Open,
+    /// Move to the next entry of an item's open menu.
+    #[command(alias = "menu-down")]
+    MenuNext { target: String },
+    /// Move to the previous entry of an item's open menu.
+    #[command(alias = "menu-up")]
+    MenuPrevious { target: String },
+    /// Activate the selected entry of an item's open menu.
+    MenuActivate { target: String },
+    /// Click a menu entry by its ID.
+    MenuClick {
+        target: String,
+        #[arg(value_name = "ENTRY_ID", allow_negative_numbers = true)]
+        entry: i32,
+    },
+    /// Close every open tray menu.
+    CloseMenus,
+    /// Show the bar.
+    Show,
+    /// Hide the bar.
+    Hide,
+    /// Show the bar when hidden, hide it otherwise.
+    Toggle,
+    /// Re-read the configuration file and rebuild the bar.
+    ReloadConfig,
+    /// Show or hide one widget, by the name custom layouts use (battery,
+    /// clock, tray, ...).
+    SetVisible {
+        widget: String,
+        #[arg(value_name = "on|off", action = ArgAction::Set, value_parser = parse_switch)]
+        visible: bool,
+    },
+    /// Show a [[file_watch]] widget given as JSON, such as
+    /// '{"name":"meeting","path":"/tmp/meeting","interval_seconds":1}', next to
+    /// the tray until remove-widget takes it away or the bar exits. It survives
+    /// reload-config.
+    AddWidget {
+        #[arg(value_name = "JSON")]
+        config: String,
+    },
+    /// Remove a widget added with add-widget.
+    RemoveWidget { name: String },
+    /// Open a widget's popover (volume, clock, quick_settings, network,
+    /// power, ...) and give it the keyboard until it closes.
+    Popover { widget: String },
+}
+
+fn parse_switch(value: &str) -> Result<bool, String> {
+    match value {
+        "on" | "true" => Ok(true),
+        "off" | "false" => Ok(false),
+        _ => Err("expected on or off".to_string()),
+    }
+}
+
+impl From<Command> for IpcRequest {
+    fn from(command: Command) -> Self {
+        match command {
+            Command::List => Self::List,
+            Command::Activate { target } => Self::Activate { target },
+            Command::SecondaryActivate { target } => Self::SecondaryActivate { target },
+            Command::ContextMenu { target } => Self::ContextMenu { target },
+            Command::KeyboardMenu { target } => Self::KeyboardMenu { target },
+            Command::Open => Self::Open,
+            Command::MenuNext { target } => Self::MenuNext { target },
+            Command::MenuPrevious { target } => Self::MenuPrevious { target },
+            Command::MenuActivate { target } => Self::MenuActivate { target },
+            Command::MenuClick { target, entry } => Self::MenuClick { target, entry },
+            Command::CloseMenus => Self::CloseMenus,
+            Command::Show => Self::Show,
+            Command::Hide => Self::Hide,
+            Command::Toggle => Self::Toggle,
+            Command::ReloadConfig => Self::ReloadConfig,
+            Command::SetVisible { widget, visible } => Self::SetVisible { widget, visible },
+            Command::AddWidget { config } => Self::AddWidget { config },
+            Command::RemoveWidget { name } => Self::RemoveWidget { name },
+            Command::Popover { widget } => Self::Popover { widget },
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct IpcUiRequest {
     pub request: IpcRequest,
@@ -298,6 +432,9 @@ pub async fn send_request(request: &IpcRequest) -> Result<IpcResponse> {
 
 #[cfg(test)]
 mod tests {
+    use clap::error::ErrorKind;
+    use clap::{CommandFactory, Parser};
+
     use super::*;
 
     #[test]
@@ -341,6 +478,146 @@ mod tests {
         );
     }
 
+    #[test]
+    fn set_visible_protocol_round_trips() {
+        let request = IpcRequest::SetVisible {
+            widget: "battery".to_string(),
+            visible: false,
+        };
+        let encoded = serde_json::to_string(&request).expect("request should encode");
+        assert_eq!(
+            encoded,
+            r#"{"command":"set-visible","widget":"battery","visible":false}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<IpcRequest>(&encoded).expect("request should decode"),
+            request
+        );
+        assert!(request.is_bar_control());
+        assert!(!IpcRequest::CloseMenus.is_bar_control());
+    }
+
+    #[test]
+    fn popover_protocol_round_trips() {
+        let request = IpcRequest::Popover {
+            widget: "clock".to_string(),
+        };
+        let encoded = serde_json::to_string(&request).expect("request should encode");
+        assert_eq!(encoded, r#"{"command":"popover","widget":"clock"}"#);
+        assert_eq!(
+            serde_json::from_str::<IpcRequest>(&encoded).expect("request should decode"),
+            request
+        );
+        assert!(request.is_bar_control());
+    }
+
+    #[test]
+    fn add_widget_carries_its_config_as_a_string() {
+        let request = IpcRequest::AddWidget {
+            config: r#"{"name":"meeting","path":"/tmp/meeting"}"#.to_string(),
+        };
+        let encoded = serde_json::to_string(&request).expect("request should encode");
+        assert_eq!(
+            encoded,
+            r#"{"command":"add-widget","config":"{\"name\":\"meeting\",\"path\":\"/tmp/meeting\"}"}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<IpcRequest>(&encoded).expect("request should decode"),
+            request
+        );
+        assert!(request.is_bar_control());
+    }
+
+    #[derive(Debug, Parser)]
+    struct Words {
+        #[command(subcommand)]
+        command: Command,
+    }
+
+    fn parse(values: &[&str]) -> Result<IpcRequest, clap::Error> {
+        Words::try_parse_from(["trayctl"].iter().chain(values)).map(|words| words.command.into())
+    }
+
+    #[test]
+    fn command_line_is_well_formed() {
+        Words::command().debug_assert();
+    }
+
+    #[test]
+    fn commands_parse_from_words() {
+        assert_eq!(
+            parse(&["menu-click", "network", "42"]).expect("should parse"),
+            IpcRequest::MenuClick {
+                target: "network".to_string(),
+                entry: 42
+            }
+        );
+        assert_eq!(
+            parse(&["menu-click", "network", "-1"]).expect("should parse"),
+            IpcRequest::MenuClick {
+                target: "network".to_string(),
+                entry: -1
+            }
+        );
+        assert_eq!(
+            parse(&["toggle"]).expect("should parse"),
+            IpcRequest::Toggle
+        );
+        assert_eq!(
+            parse(&["menu-down", "network"]).expect("alias should parse"),
+            IpcRequest::MenuNext {
+                target: "network".to_string()
+            }
+        );
+        assert_eq!(
+            parse(&["remove-widget", "meeting"]).expect("should parse"),
+            IpcRequest::RemoveWidget {
+                name: "meeting".to_string()
+            }
+        );
+        assert_eq!(
+            parse(&["popover", "quick_settings"]).expect("should parse"),
+            IpcRequest::Popover {
+                widget: "quick_settings".to_string()
+            }
+        );
+        assert_eq!(
+            parse(&["set-visible", "battery", "off"]).expect("should parse"),
+            IpcRequest::SetVisible {
+                widget: "battery".to_string(),
+                visible: false
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_verbs_and_wrong_arguments_are_told_apart() {
+        let kind = |values: &[&str]| parse(values).expect_err("should be rejected").kind();
+        assert_eq!(kind(&["tgogle"]), ErrorKind::InvalidSubcommand);
+        assert_eq!(kind(&["toggle", "now"]), ErrorKind::UnknownArgument);
+        assert_eq!(
+            kind(&["popover", "clock", "volume"]),
+            ErrorKind::UnknownArgument
+        );
+        assert_eq!(
+            kind(&["menu-click", "network"]),
+            ErrorKind::MissingRequiredArgument
+        );
+        assert_eq!(
+            kind(&["menu-click", "network", "x"]),
+            ErrorKind::ValueValidation
+        );
+        assert_eq!(
+            kind(&["set-visible", "battery", "maybe"]),
+            ErrorKind::ValueValidation
+        );
+        assert_eq!(kind(&["add-widget"]), ErrorKind::MissingRequiredArgument);
+        assert_eq!(
+            kind(&[]),
+            ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand
+        );
+    }
+
     #[test]
     fn keyboard_menu_protocol_round_trips() {
         let request = IpcRequest::KeyboardMenu {
@@ -1,76 +1,31 @@
-use std::env;
 use std::process::ExitCode;
 
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
 
-use tray_ipc::{IpcRequest, IpcResponse, send_request, socket_path};
-
-const USAGE: &str = "Usage:
-  trayctl [--json] list
-  trayctl [--json] activate TARGET
-  trayctl [--json] secondary-activate TARGET
-  trayctl [--json] context-menu TARGET
-  trayctl [--json] keyboard-menu TARGET
-  trayctl [--json] open
-  trayctl [--json] menu-next TARGET
-  trayctl [--json] menu-previous TARGET
-  trayctl [--json] menu-activate TARGET
-  trayctl [--json] menu-click TARGET ENTRY_ID
-  trayctl [--json] close-menus
-  trayctl socket-path
+use tray_ipc::{Command, IpcResponse, send_request, socket_path};
 
+const AFTER_HELP: &str = "\
 TARGET is a zero-based index from `trayctl list`, an exact item title, or an
-exact item key. `open` takes no TARGET: it resumes keyboard navigation on the
-icon the last session ended on (or the first icon when that one is gone). Set
-GTK_STATUS_BAR_SOCKET to override the default socket path.";
+exact item key. Set GTK_STATUS_BAR_SOCKET to override the default socket path.";
 
-fn parse_request(arguments: &[String]) -> Result<Option<IpcRequest>> {
-    let Some(command) = arguments.first() else {
-        bail!(USAGE);
-    };
-    let request = match command.as_str() {
-        "list" if arguments.len() == 1 => IpcRequest::List,
-        "activate" => IpcRequest::Activate {
-            target: target(arguments, command)?,
-        },
-        "secondary-activate" => IpcRequest::SecondaryActivate {
-            target: target(arguments, command)?,
-        },
-        "context-menu" => IpcRequest::ContextMenu {
-            target: target(arguments, command)?,
-        },
-        "keyboard-menu" => IpcRequest::KeyboardMenu {
-            target: target(arguments, command)?,
-        },
-        "open" if arguments.len() == 1 => IpcRequest::Open,
-        "menu-next" | "menu-down" => IpcRequest::MenuNext {
-            target: target(arguments, command)?,
-        },
-        "menu-previous" | "menu-up" => IpcRequest::MenuPrevious {
-            target: target(arguments, command)?,
-        },
-        "menu-activate" => IpcRequest::MenuActivate {
-            target: target(arguments, command)?,
-        },
-        "menu-click" if arguments.len() == 3 => IpcRequest::MenuClick {
-            target: arguments[1].clone(),
-            entry: arguments[2]
-                .parse()
-                .with_context(|| format!("invalid menu entry ID {:?}", arguments[2]))?,
-        },
-        "close-menus" if arguments.len() == 1 => IpcRequest::CloseMenus,
-        "socket-path" if arguments.len() == 1 => return Ok(None),
-        "help" | "--help" | "-h" => bail!(USAGE),
-        _ => bail!("unknown or malformed command {command:?}\n\n{USAGE}"),
-    };
-    Ok(Some(request))
+/// Drive gtk-status-bar's tray and bar over its IPC socket.
+#[derive(Debug, Parser)]
+#[command(after_help = AFTER_HELP)]
+struct Cli {
+    /// Print the whole response as JSON.
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: TrayctlCommand,
 }
 
-fn target(arguments: &[String], command: &str) -> Result<String> {
-    if arguments.len() != 2 {
-        bail!("{command} requires exactly one TARGET\n\n{USAGE}");
-    }
-    Ok(arguments[1].clone())
+#[derive(Debug, PartialEq, Eq, Subcommand)]
+enum TrayctlCommand {
+    #[command(flatten)]
+    Request(Command),
+    /// Print the IPC socket path.
+    SocketPath,
 }
 
 fn print_human(response: &IpcResponse) {
@@ -97,26 +52,14 @@ fn print_human(response: &IpcResponse) {
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<ExitCode> {
-    let mut arguments: Vec<String> = env::args().skip(1).collect();
-    let json = match arguments.iter().position(|argument| argument == "--json") {
-        Some(index) => {
-            arguments.remove(index);
-            true
-        }
-        None => false,
-    };
-    if matches!(arguments.as_slice(), [argument] if matches!(argument.as_str(), "help" | "--help" | "-h"))
-    {
-        println!("{USAGE}");
-        return Ok(ExitCode::SUCCESS);
-    }
-    let Some(request) = parse_request(&arguments)? else {
+    let cli = Cli::parse();
+    let TrayctlCommand::Request(command) = cli.command else {
         println!("{}", socket_path()?.display());
         return Ok(ExitCode::SUCCESS);
     };
 
-    let response = send_request(&request).await?;
-    if json {
+    let response = send_request(&command.into()).await?;
+    if cli.json {
         println!(
             "{}",
             serde_json::to_string_pretty(&response).context("encode output as JSON")?
@@ -138,33 +81,64 @@ async fn main() -> Result<ExitCode> {
 
 #[cfg(test)]
 mod tests {
+    use clap::CommandFactory;
+    use tray_ipc::IpcRequest;
+
     use super::*;
 
-    fn arguments(values: &[&str]) -> Vec<String> {
-        values.iter().map(|value| (*value).to_string()).collect()
+    fn parse(values: &[&str]) -> Cli {
+        Cli::try_parse_from(["trayctl"].iter().chain(values)).expect("should parse")
+    }
+
+    fn request(values: &[&str]) -> IpcRequest {
+        let TrayctlCommand::Request(command) = parse(values).command else {
+            panic!("{values:?} is not a request");
+        };
+        command.into()
+    }
+
+    #[test]
+    fn command_line_is_well_formed() {
+        Cli::command().debug_assert();
     }
 
     #[test]
     fn parses_keyboard_menu_as_an_explicit_opt_in() {
-        let parsed = parse_request(&arguments(&["keyboard-menu", "Bluetooth"]))
-            .expect("keyboard-menu should parse");
         assert_eq!(
-            parsed,
-            Some(IpcRequest::KeyboardMenu {
+            request(&["keyboard-menu", "Bluetooth"]),
+            IpcRequest::KeyboardMenu {
                 target: "Bluetooth".to_string()
-            })
+            }
         );
     }
 
+    #[test]
+    fn parses_set_visible_switch() {
+        assert_eq!(
+            request(&["set-visible", "battery", "off"]),
+            IpcRequest::SetVisible {
+                widget: "battery".to_string(),
+                visible: false
+            }
+        );
+        assert!(Cli::try_parse_from(["trayctl", "set-visible", "battery", "maybe"]).is_err());
+    }
+
     #[test]
     fn context_menu_remains_a_non_grabbing_request() {
-        let parsed = parse_request(&arguments(&["context-menu", "Bluetooth"]))
-            .expect("context-menu should parse");
         assert_eq!(
-            parsed,
-            Some(IpcRequest::ContextMenu {
+            request(&["context-menu", "Bluetooth"]),
+            IpcRequest::ContextMenu {
                 target: "Bluetooth".to_string()
-            })
+            }
         );
     }
+
+    #[test]
+    fn json_goes_before_or_after_the_command() {
+        assert!(parse(&["--json", "list"]).json);
+        assert!(parse(&["list", "--json"]).json);
+        assert!(!parse(&["list"]).json);
+        assert_eq!(parse(&["socket-path"]).command, TrayctlCommand::SocketPath);
+    }
 }
@@ -0,0 +1,213 @@
+// One-shot alarms set from the clock popover. An alarm is a wall-clock time
+// plus a label; it is stored as the absolute instant of the next occurrence of
+// that time, so "07:30" set at 23:00 rings tomorrow morning and one set at
+// 06:00 rings today. Alarms persist in the state file, fire once and are
+// removed.
+//
+// Firing posts a desktop notification through the GApplication and, when
+// `[clock] alarm_sound` is configured, runs that command (canberra-gtk-play,
+// pw-play, ...). An alarm whose time passed while the bar was not running
+// fires at the next start, marked as missed.
+
+use std::rc::Rc;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Days, Local, NaiveTime, TimeZone};
+use gtk4::gio;
+use gtk4::gio::prelude::*;
+use gtk4::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::clock::Clock;
+use crate::command;
+use crate::state::StateFile;
+
+// Later than this past its instant and the notification says it was missed.
+const MISSED_AFTER_SECONDS: i64 = 60;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Alarm {
+    // Unix seconds of the instant it rings.
+    pub at: i64,
+    pub label: String,
+}
+
+impl Alarm {
+    // "07:30" in local time, for the popover list and the notification.
+    pub fn time_text(&self) -> String {
+        match Local.timestamp_opt(self.at, 0).single() {
+            Some(at) => at.format("%H:%M").to_string(),
+            None => "--:--".to_string(),
+        }
+    }
+}
+
+// Accepts "7:30" and "07:30" (24-hour).
+pub fn parse_time(text: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(text.trim(), "%H:%M")
+        .with_context(|| format!("{:?} is not a time like 07:30", text.trim()))
+}
+
+// The first instant strictly after `now` whose local time is `time`. A time
+// that a DST jump skips on one day is taken from the next day instead.
+pub fn next_occurrence<Tz: TimeZone>(now: &DateTime<Tz>, time: NaiveTime) -> Option<DateTime<Tz>> {
+    (0..=2).find_map(|days| {
+        let date = now.date_naive().checked_add_days(Days::new(days))?;
+        now.timezone()
+            .from_local_datetime(&date.and_time(time))
+            .earliest()
+            .filter(|candidate| candidate > now)
+    })
+}
+
+// Remove and return the alarms due at `now` (Unix seconds), earliest first.
+fn take_due(alarms: &mut Vec<Alarm>, now: i64) -> Vec<Alarm> {
+    let (mut due, pending): (Vec<_>, Vec<_>) = alarms.drain(..).partition(|alarm| alarm.at <= now);
+    *alarms = pending;
+    due.sort_by_key(|alarm| alarm.at);
+    due
+}
+
+// The clock popover's handle on the alarm list. Cheap to clone; every clone
+// edits the same state file.
+#[derive(Clone)]
+pub struct AlarmBook {
+    state: Rc<StateFile>,
+}
+
+impl AlarmBook {
+    pub fn new(state: Rc<StateFile>) -> Self {
+        Self { state }
+    }
+
+    pub fn alarms(&self) -> Vec<Alarm> {
+        let mut alarms = self.state.read(|state| state.alarms.clone());
+        alarms.sort_by_key(|alarm| alarm.at);
+        alarms
+    }
+
+    pub fn add(&self, time_text: &str, label: &str) -> Result<Alarm> {
+        let time = parse_time(time_text)?;
+        let at = next_occurrence(&Local::now(), time)
+            .with_context(|| format!("{time} does not occur in the next two days"))?;
+        let alarm = Alarm {
+            at: at.timestamp(),
+            label: label.trim().to_string(),
+        };
+        info!(at = %at, label = alarm.label, "Alarm set");
+        self.state.update(|state| state.alarms.push(alarm.clone()));
+        Ok(alarm)
+    }
+
+    pub fn remove(&self, alarm: &Alarm) {
+        info!(at = alarm.at, label = alarm.label, "Alarm removed");
+        self.state
+            .update(|state| state.alarms.retain(|candidate| candidate != alarm));
+    }
+
+    fn take_due(&self, now: i64) -> Vec<Alarm> {
+        let due = self
+            .state
+            .read(|state| state.alarms.iter().any(|alarm| alarm.at <= now));
+        if !due {
+            return Vec::new();
+        }
+        self.state.update(|state| take_due(&mut state.alarms, now))
+    }
+}
+
+fn ring(application: &gtk4::Application, alarm: &Alarm, now: i64, sound: Option<&str>) {
+    let missed = now - alarm.at > MISSED_AFTER_SECONDS;
+    info!(at = alarm.at, label = alarm.label, missed, "Alarm ringing");
+
+    let title = if alarm.label.is_empty() {
+        "Alarm"
+    } else {
+        alarm.label.as_str()
+    };
+    let body = if missed {
+        format!("Missed alarm for {}", alarm.time_text())
+    } else {
+        format!("Alarm for {}", alarm.time_text())
+    };
+    let notification = gio::Notification::new(title);
+    notification.set_body(Some(&body));
+    notification.set_priority(gio::NotificationPriority::Urgent);
+    application.send_notification(Some(&format!("alarm-{}", alarm.at)), &notification);
+
+    // A missed alarm is old news; only one ringing on time makes noise.
+    if let Some(sound) = sound
+        && !missed
+    {
+        command::spawn_shell(sound, "alarm sound");
+    }
+}
+
+// Check for due alarms on every clock second. Runs for the application's
+// lifetime; the weak reference only guards the shutdown race.
+pub fn start_alarm_checks(book: AlarmBook, application: &gtk4::Application, sound: Option<String>) {
+    debug!("Starting alarm checks");
+    let application = application.downgrade();
+    Clock::new()
+        .on_second(move |now| {
+            let Some(application) = application.upgrade() else {
+                return;
+            };
+            let now = now.timestamp();
+            for alarm in book.take_due(now) {
+                ring(&application, &alarm, now, sound.as_deref());
+            }
+        })
+        .start();
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::FixedOffset;
+
+    use super::*;
+
+    fn at(text: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(text).expect("test instant should parse")
+    }
+
+    #[test]
+    fn parses_short_and_padded_times() {
+        let half_past_seven = NaiveTime::from_hms_opt(7, 30, 0).unwrap();
+        assert_eq!(parse_time("7:30").unwrap(), half_past_seven);
+        assert_eq!(parse_time(" 07:30 ").unwrap(), half_past_seven);
+        assert!(parse_time("25:00").is_err());
+        assert!(parse_time("soon").is_err());
+    }
+
+    #[test]
+    fn next_occurrence_rolls_over_to_tomorrow() {
+        let time = NaiveTime::from_hms_opt(7, 30, 0).unwrap();
+        assert_eq!(
+            next_occurrence(&at("2026-03-01T06:00:00+01:00"), time),
+            Some(at("2026-03-01T07:30:00+01:00"))
+        );
+        assert_eq!(
+            next_occurrence(&at("2026-03-01T23:00:00+01:00"), time),
+            Some(at("2026-03-02T07:30:00+01:00"))
+        );
+        // Exactly now is not "next".
+        assert_eq!(
+            next_occurrence(&at("2026-03-01T07:30:00+01:00"), time),
+            Some(at("2026-03-02T07:30:00+01:00"))
+        );
+    }
+
+    #[test]
+    fn due_alarms_are_taken_in_order() {
+        let alarm = |at, label: &str| Alarm {
+            at,
+            label: label.to_string(),
+        };
+        let mut alarms = vec![alarm(30, "later"), alarm(20, "second"), alarm(10, "first")];
+        let due = take_due(&mut alarms, 20);
+        assert_eq!(due, vec![alarm(10, "first"), alarm(20, "second")]);
+        assert_eq!(alarms, vec![alarm(30, "later")]);
+    }
+}
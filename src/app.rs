@@ -0,0 +1,1085 @@
+//! The bar as a library: `Bar::builder()` configures one bar and `build()` brings
+//! it up inside a GTK application, which is all the gtk-status-bar binary does
+//! after parsing its command line.
+//!
+//! `build()` creates one Bus plus the bidirectional tray endpoints, hands every UI
+//! half to its widget drain, and only then spawns the supervised producers.
+//! Every consumer is therefore wired before the first producer can send.
+//! Producers that only feed particular widgets (PipeWire, D-Bus, network, ...)
+//! start after the first frame and only when one of their widgets is placed.
+//! Producers that crash are restarted with exponential backoff by their
+//! run_*_supervised wrappers, and all of them are stopped from the
+//! application's shutdown handler, which SIGINT/SIGTERM reach through a normal
+//! GTK quit.
+
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+use gio::prelude::*;
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4_layer_shell::Layer;
+use tokio::sync::{mpsc, watch};
+use tracing::{debug, error, info, warn};
+use tray_ipc::IpcUiRequest;
+
+use crate::config::{self, Config};
+use crate::dbus::supervise;
+use crate::model::BarModel;
+use crate::network::{self, NetworkConfig};
+use crate::widgets::{self, CustomWidget};
+use crate::{
+    alarm, appearance, audio, autohide, autoprofile, backlight, bandwidth, bus, buses, capture,
+    clipboard, compositor, control, dbus, dispatch, format, gpu, hooks, hotspot, hypr,
+    kbd_backlight, lockkeys, notification_popups, notification_server, notifications, osd, palette,
+    pomodoro, progress, pw, recorder, rfkill, sinks, sleep, state, stats, toplevel, tray, units,
+    updates, views, vpn, wizard,
+};
+
+/// A running bar. Dropping it changes nothing: the window belongs to the
+/// application, and the bar lives until the application shuts down.
+///
+/// ```no_run
+/// use gtk_status_bar::{Bar, Layer};
+/// use gtk4::prelude::*;
+///
+/// // The producers are tokio tasks, so a runtime has to be entered.
+/// let runtime = tokio::runtime::Runtime::new()?;
+/// let _guard = runtime.enter();
+/// let application = gtk4::Application::builder()
+///     .application_id("org.example.Bar")
+///     .build();
+/// application.connect_activate(|application| {
+///     let bar = Bar::builder()
+///         .widget("workspace")
+///         .widget("clock")
+///         .layer(Layer::Top)
+///         .build(application)
+///         .expect("bring the bar up");
+///     bar.window().set_title(Some("example bar"));
+/// });
+/// application.run();
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct Bar {
+    window: gtk4::ApplicationWindow,
+    model: BarModel,
+}
+
+impl Bar {
+    /// A builder with the default configuration and every built-in widget.
+    pub fn builder() -> BarBuilder {
+        BarBuilder::default()
+    }
+
+    /// The layer-shell surface, e.g. to present it or to set its namespace.
+    pub fn window(&self) -> &gtk4::ApplicationWindow {
+        &self.window
+    }
+
+    /// Everything the widgets show, as GObject properties to bind to.
+    pub fn model(&self) -> &BarModel {
+        &self.model
+    }
+}
+
+/// Configures a [`Bar`]; see [`Bar::builder`]. Nothing starts until
+/// [`build`](BarBuilder::build).
+pub struct BarBuilder {
+    config: Config,
+    config_path: Option<PathBuf>,
+    style: Option<PathBuf>,
+    monitor: Option<String>,
+    layer: Layer,
+    network: NetworkConfig,
+    widgets: Vec<String>,
+    custom_widgets: Vec<CustomWidget>,
+    first_run_wizard: bool,
+}
+
+impl Default for BarBuilder {
+    fn default() -> Self {
+        Self {
+            config: Config::default(),
+            config_path: None,
+            style: None,
+            monitor: None,
+            layer: Layer::Bottom,
+            network: NetworkConfig::default(),
+            widgets: Vec::new(),
+            custom_widgets: Vec::new(),
+            first_run_wizard: false,
+        }
+    }
+}
+
+impl BarBuilder {
+    /// The configuration to start from, instead of the defaults. Reading
+    /// config.toml is up to the caller (see [`crate::config::load`]).
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Where `reload-config` reads the configuration from; the default
+    /// location when unset.
+    pub fn config_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_path = Some(path.into());
+        self
+    }
+
+    /// A stylesheet replacing the default lookup of style.css.
+    pub fn style(mut self, path: impl Into<PathBuf>) -> Self {
+        self.style = Some(path.into());
+        self
+    }
+
+    /// The output to show on, by GDK connector name (DP-1); the compositor's
+    /// choice when unset.
+    pub fn monitor(mut self, connector: impl Into<String>) -> Self {
+        self.monitor = Some(connector.into());
+        self
+    }
+
+    /// Bottom by default, under floating windows. `[bar] auto_hide` moves the
+    /// bar to Top regardless.
+    pub fn layer(mut self, layer: Layer) -> Self {
+        self.layer = layer;
+        self
+    }
+
+    /// What the network widget pings to rate the connection, and how often.
+    pub fn network(mut self, network: NetworkConfig) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Place this built-in widget. Once any is named, the others are left
+    /// out, as if listed in `[bar] disabled_widgets`; each keeps its usual
+    /// place in the layout.
+    pub fn widget(mut self, name: impl Into<String>) -> Self {
+        self.widgets.push(name.into());
+        self
+    }
+
+    /// Add a widget of the caller's own right after the tray. `build` runs on
+    /// every build of the bar (a reload rebuilds it) and gets the model, so
+    /// the widget can bind to the bar's state like the built-in ones.
+    pub fn custom_widget(
+        mut self,
+        name: impl Into<String>,
+        build: impl Fn(&BarModel) -> gtk4::Widget + 'static,
+    ) -> Self {
+        self.custom_widgets.push(CustomWidget {
+            name: name.into(),
+            build: Rc::new(build),
+        });
+        self
+    }
+
+    /// Offer the first-run wizard when the default config.toml does not exist
+    /// yet. Off by default; the binary turns it on.
+    pub fn first_run_wizard(mut self, enabled: bool) -> Self {
+        self.first_run_wizard = enabled;
+        self
+    }
+
+    /// Bring the bar up. Call it from the application's activate handler, on
+    /// the GTK main thread, with a tokio runtime entered: the producers are
+    /// tokio tasks.
+    ///
+    /// Fails on a widget name that is not built in, or when the window
+    /// cannot be set up (a `monitor` that is not connected, say).
+    pub fn build(mut self, application: &gtk4::Application) -> Result<Bar> {
+        if let Some(unknown) = self
+            .widgets
+            .iter()
+            .find(|name| !widgets::builtin_widget_names().any(|builtin| builtin == *name))
+        {
+            bail!("there is no built-in widget named {unknown:?}");
+        }
+        widgets::keep_only_widgets(&mut self.config.bar, &self.widgets);
+        activate(application, self)
+    }
+}
+
+async fn run_tray_ipc_supervised(ui_tx: mpsc::UnboundedSender<IpcUiRequest>) {
+    supervise("tray IPC server", || tray_ipc::run_server(ui_tx.clone())).await
+}
+
+// Align GTK's theme preference with the desktop color scheme before any widget
+// realizes, so our symbolic icons and theme-based icon lookups resolve their
+// dark-mode variants. The bar's CSS is always dark, so when no preference is
+// exposed (e.g. a session without a color-scheme portal) we default to dark
+// rather than leaving GTK on its light default.
+fn configure_color_scheme() {
+    let prefer_dark = appearance::detect_prefer_dark().unwrap_or_else(|| {
+        debug!("No desktop color-scheme preference available; defaulting the bar to dark");
+        true
+    });
+    let Some(settings) = gtk4::Settings::default() else {
+        warn!("No default GtkSettings; cannot apply color-scheme preference");
+        return;
+    };
+    settings.set_gtk_application_prefer_dark_theme(prefer_dark);
+    info!(
+        prefer_dark,
+        "Applied color-scheme preference to GTK settings"
+    );
+}
+
+// Everything activate() starts that outlives it. Owned by the application's
+// shutdown handler, which tears it down once the GTK main loop stops: aborting
+// a producer task drops its connections (the system-bus MessageStream, the
+// Hyprland socket, the tray IPC listener), and the PipeWire thread stops its
+// ThreadLoop before the process exits.
+struct Services {
+    tasks: Vec<tokio::task::JoinHandle<()>>,
+    // None until the deferred backends start, and for good when no widget
+    // needs PipeWire.
+    pipewire: Option<pw::PipewireHandle>,
+}
+
+impl Services {
+    fn shutdown(self) {
+        info!(tasks = self.tasks.len(), "Stopping background services");
+        for task in &self.tasks {
+            task.abort();
+        }
+        if let Some(pipewire) = self.pipewire {
+            pipewire.stop();
+        }
+    }
+}
+
+// Producers that exist only to feed particular widgets. Each starts only when
+// the layout holds at least one of its widgets (so a layout without a volume
+// pill never connects to PipeWire), and only after the first frame. A reload
+// that places a new widget starts the producers it lacks; one that drops a
+// widget leaves its producer running. The Hyprland workspace and title
+// listeners, the tray and the IPC socket are not in this list: the bar's own
+// visibility and coloring depend on them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Backend {
+    Pipewire,
+    Network,
+    Battery,
+    Bluetooth,
+    // The screen backlight, for the quick-settings slider and the OSD.
+    Backlight,
+    KbdBacklight,
+    LockKeys,
+    Split,
+    Displays,
+    Floating,
+    Scratchpad,
+    // The cpufreq and load average widgets share one poller.
+    Stats,
+    Gpu,
+    Bandwidth,
+    Notifications,
+    Progress,
+    Vpn,
+    Hotspot,
+    Rfkill,
+    Units,
+    Updates,
+    Clipboard,
+    Taskbar,
+}
+
+const BACKEND_WIDGETS: &[(Backend, &[&str])] = &[
+    (
+        Backend::Pipewire,
+        &["volume", "headset", "privacy", "taskbar", "quick_settings"],
+    ),
+    (Backend::Network, &["network"]),
+    (Backend::Battery, &["battery"]),
+    (Backend::Bluetooth, &["bluetooth"]),
+    (Backend::Backlight, &["quick_settings"]),
+    (Backend::KbdBacklight, &["kbd_backlight"]),
+    (Backend::LockKeys, &["lock_keys"]),
+    (Backend::Split, &["split"]),
+    (Backend::Displays, &["displays"]),
+    (Backend::Floating, &["workspace"]),
+    (Backend::Scratchpad, &["scratchpad"]),
+    (Backend::Stats, &["cpufreq", "loadavg"]),
+    (Backend::Gpu, &["gpu"]),
+    (Backend::Bandwidth, &["bandwidth"]),
+    (Backend::Notifications, &["notifications"]),
+    (Backend::Progress, &["progress"]),
+    (Backend::Vpn, &["vpn"]),
+    (Backend::Hotspot, &["hotspot"]),
+    (Backend::Rfkill, &["rfkill"]),
+    (Backend::Units, &["units"]),
+    (Backend::Updates, &["updates"]),
+    (Backend::Clipboard, &["clipboard"]),
+    (Backend::Taskbar, &["taskbar"]),
+];
+
+// The OSDs are not widgets but listen to the same producers, and the battery
+// and Bluetooth hooks fire from the UPower and BlueZ monitors.
+fn needed_backends(config: &Config, has_widget: impl Fn(&str) -> bool) -> Vec<Backend> {
+    let mut backends: Vec<Backend> = BACKEND_WIDGETS
+        .iter()
+        .filter(|(_, widgets)| widgets.iter().any(|widget| has_widget(widget)))
+        .map(|(backend, _)| *backend)
+        .collect();
+    for (wanted, backend) in [
+        (config.osd.volume, Backend::Pipewire),
+        (config.osd.brightness, Backend::Backlight),
+        (config.hooks.on_battery_low.is_some(), Backend::Battery),
+        (config.hooks.on_bt_connected.is_some(), Backend::Bluetooth),
+    ] {
+        if wanted && !backends.contains(&backend) {
+            backends.push(backend);
+        }
+    }
+    backends
+}
+
+// The needed backends that are not running yet, in order.
+fn missing_backends(started: &[Backend], needed: &[Backend]) -> Vec<Backend> {
+    needed
+        .iter()
+        .filter(|backend| !started.contains(backend))
+        .copied()
+        .collect()
+}
+
+// Starts the widget backends and remembers which ones it started, so the
+// startup layout and every reloaded one get their producers without starting
+// any twice. Shared by activate(), which runs the first start after the first
+// frame, and BarControl, which asks again on reload. GTK-main-thread only.
+pub(crate) struct WidgetBackends {
+    // Once started a backend counts as running, even one that failed to
+    // start: a reload does not retry it.
+    started: Vec<Backend>,
+    // What the first frame starts, and with which configuration; None once
+    // it has painted.
+    pending: Option<(Vec<Backend>, Config)>,
+    window_backend: config::WindowBackend,
+    on_hyprland: bool,
+    bus: bus::Bus,
+    buses: buses::BusManager,
+    network: NetworkConfig,
+    model: BarModel,
+    dispatcher: dispatch::UiDispatcher,
+    // Taken by the PipeWire backend, which starts at most once.
+    audio: Option<(
+        audio::AudioSinks,
+        pipewire::channel::Receiver<audio::AudioCommand>,
+    )>,
+    taskbar: widgets::TaskbarStore,
+    application: glib::WeakRef<gtk4::Application>,
+    services: Rc<RefCell<Option<Services>>>,
+}
+
+impl WidgetBackends {
+    // The backends a layout needs: started now, or with the first frame when
+    // it has not painted yet.
+    pub(crate) fn place(&mut self, config: &Config, has_widget: impl Fn(&str) -> bool) {
+        let needed = needed_backends(config, has_widget);
+        if let Some(pending) = &mut self.pending {
+            *pending = (needed, config.clone());
+            return;
+        }
+        self.start(&needed, config);
+    }
+
+    fn first_frame(&mut self) {
+        if let Some((needed, config)) = self.pending.take() {
+            self.start(&needed, &config);
+        }
+    }
+
+    // Their consumers are wired here too (PipeWire's live in
+    // setup_pipewire_updates), before the producers spawn, as everywhere else.
+    // A bar that already shut down starts nothing.
+    fn start(&mut self, needed: &[Backend], config: &Config) {
+        let backends = missing_backends(&self.started, needed);
+        if backends.is_empty() {
+            return;
+        }
+        let Some(application) = self.application.upgrade() else {
+            return;
+        };
+        let shared = self.services.clone();
+        let mut services = shared.borrow_mut();
+        let Some(services) = services.as_mut() else {
+            return;
+        };
+        info!(?backends, "Starting widget backends");
+        let bus = &self.bus;
+        let buses = &self.buses;
+        let format = format::Format::new(&config.format);
+        let on_hyprland = self.on_hyprland;
+        let window_backend = self.window_backend;
+        for backend in backends {
+            self.started.push(backend);
+            let task = match backend {
+                Backend::Pipewire => {
+                    let Some((audio, audio_commands)) = self.audio.take() else {
+                        continue;
+                    };
+                    match widgets::setup_pipewire_updates(
+                        self.model.clone(),
+                        self.dispatcher.clone(),
+                        &application,
+                        audio,
+                        audio_commands,
+                        self.taskbar.clone(),
+                        bus.state().clone(),
+                    ) {
+                        Ok(pipewire) => services.pipewire = Some(pipewire),
+                        Err(e) => error!("Failed to start PipeWire monitoring: {:#}", e),
+                    }
+                    continue;
+                }
+                Backend::Network => tokio::spawn(network::run_network_monitor_supervised(
+                    bus.clone(),
+                    self.network.clone(),
+                    buses.clone(),
+                )),
+                Backend::Battery => tokio::spawn(dbus::run_upower_monitor_supervised(
+                    bus.clone(),
+                    buses.clone(),
+                )),
+                Backend::Bluetooth => tokio::spawn(dbus::run_bluez_monitor_supervised(
+                    bus.clone(),
+                    buses.clone(),
+                )),
+                Backend::Backlight => tokio::spawn(backlight::run_backlight_monitor(bus.clone())),
+                Backend::KbdBacklight => tokio::spawn(
+                    kbd_backlight::run_kbd_backlight_monitor_supervised(bus.clone(), buses.clone()),
+                ),
+                Backend::LockKeys => tokio::spawn(lockkeys::run_lock_keys_updates(bus.clone())),
+                Backend::Stats => tokio::spawn(stats::run_stats_updates(
+                    bus.clone(),
+                    config.loadavg.clone(),
+                    format,
+                )),
+                Backend::Gpu => tokio::spawn(gpu::run_gpu_updates(
+                    bus.clone(),
+                    config.gpu.clone(),
+                    format,
+                )),
+                Backend::Bandwidth => tokio::spawn(bandwidth::run_bandwidth_updates(
+                    bus.clone(),
+                    config.bandwidth.clone(),
+                    format,
+                )),
+                Backend::Notifications => tokio::spawn(
+                    notifications::run_notifications_monitor_supervised(bus.clone(), buses.clone()),
+                ),
+                Backend::Progress => tokio::spawn(progress::run_progress_monitor_supervised(
+                    bus.clone(),
+                    buses.clone(),
+                )),
+                Backend::Vpn => {
+                    tokio::spawn(vpn::run_vpn_monitor_supervised(bus.clone(), buses.clone()))
+                }
+                Backend::Hotspot => tokio::spawn(hotspot::run_hotspot_monitor_supervised(
+                    bus.clone(),
+                    buses.clone(),
+                )),
+                Backend::Rfkill => tokio::spawn(rfkill::run_rfkill_updates(bus.clone())),
+                Backend::Units => tokio::spawn(units::run_units_monitor_supervised(
+                    bus.clone(),
+                    buses.clone(),
+                    config.units.clone(),
+                )),
+                Backend::Updates => tokio::spawn(updates::run_updates_checker(
+                    bus.clone(),
+                    config.updates.clone(),
+                )),
+                Backend::Clipboard => tokio::spawn(clipboard::run_clipboard_monitor_supervised(
+                    bus.clone(),
+                    config.clipboard.clone(),
+                )),
+                // The toplevel listener already feeds the taskbar along with the
+                // title.
+                Backend::Taskbar if window_backend == config::WindowBackend::Wlr => continue,
+                // The rest only exist on Hyprland; elsewhere their widgets stay
+                // hidden.
+                Backend::Split
+                | Backend::Displays
+                | Backend::Floating
+                | Backend::Scratchpad
+                | Backend::Taskbar
+                    if !on_hyprland =>
+                {
+                    info!(?backend, "Not available on this compositor");
+                    continue;
+                }
+                Backend::Split => tokio::spawn(hypr::run_split_listener_supervised(bus.clone())),
+                Backend::Displays => {
+                    tokio::spawn(hypr::run_displays_listener_supervised(bus.clone()))
+                }
+                Backend::Floating => {
+                    tokio::spawn(hypr::run_floating_listener_supervised(bus.clone()))
+                }
+                Backend::Scratchpad => {
+                    tokio::spawn(hypr::run_scratchpad_listener_supervised(bus.clone()))
+                }
+                Backend::Taskbar => {
+                    tokio::spawn(hypr::run_taskbar_listener_supervised(bus.clone()))
+                }
+            };
+            services.tasks.push(task);
+        }
+    }
+}
+
+// SIGINT/SIGTERM end the GTK main loop the same way closing the application
+// does, so scripted restarts (`pkill gtk-status-bar && gtk-status-bar`) go
+// through the shutdown handler instead of killing the PipeWire thread and the
+// D-Bus connections mid-flight. The signal streams live on tokio; quitting has
+// to happen on the GTK main thread, hence the channel.
+fn setup_signal_handling(application: &gtk4::Application) -> Result<tokio::task::JoinHandle<()>> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut interrupt = signal(SignalKind::interrupt()).context("install SIGINT handler")?;
+    let mut terminate = signal(SignalKind::terminate()).context("install SIGTERM handler")?;
+    let (quit_tx, mut quit_rx) = mpsc::unbounded_channel::<&'static str>();
+
+    let application = application.downgrade();
+    glib::spawn_future_local(async move {
+        if let Some(signal) = quit_rx.recv().await {
+            info!(signal, "Received termination signal; quitting");
+            if let Some(application) = application.upgrade() {
+                application.quit();
+            }
+        }
+    });
+
+    Ok(tokio::spawn(async move {
+        let signal = tokio::select! {
+            _ = interrupt.recv() => "SIGINT",
+            _ = terminate.recv() => "SIGTERM",
+        };
+        quit_tx.send(signal).ok();
+    }))
+}
+
+fn activate(application: &gtk4::Application, options: BarBuilder) -> Result<Bar> {
+    info!("Activating GTK application");
+    let config = &options.config;
+
+    configure_color_scheme();
+    sinks::install_default_sink_action(application);
+
+    let window = gtk4::ApplicationWindow::new(application);
+    window.add_css_class("layer-bar");
+
+    // Widgets bind to the model at construction; the drains below only write
+    // to it. The stylesheet load already reports its errors there.
+    let model = BarModel::new();
+    widgets::load_css_styles(&window, options.style.as_deref(), &model)?;
+    widgets::apply_theme(&window, config.bar.theme);
+    let colors = palette::apply_colors(&window, &config.colors);
+    widgets::configure_layer_shell(
+        &window,
+        options.monitor.as_deref(),
+        config.bar.position,
+        options.layer,
+    )?;
+    let floating_css = widgets::floating_provider(&window);
+    // Auto-hide's hot edge has to touch the screen edge, so it wins.
+    if config.bar.floating.enabled && !config.bar.auto_hide {
+        widgets::apply_floating(
+            &window,
+            &floating_css,
+            config.bar.position,
+            &config.bar.floating,
+        );
+    }
+
+    let state = Rc::new(state::StateFile::load());
+    let alarms = alarm::AlarmBook::new(state.clone());
+    let (focus_tx, focus_rx) = mpsc::unbounded_channel();
+    let has_focus_keywords = !config.pomodoro.focus_keywords.is_empty();
+    let pomodoro = pomodoro::PomodoroTimer::new(
+        model.clone(),
+        pomodoro::Durations {
+            work: Duration::from_secs(config.pomodoro.work_minutes * 60),
+            rest: Duration::from_secs(config.pomodoro.break_minutes * 60),
+        },
+        has_focus_keywords.then_some(focus_tx),
+    );
+    let capture = capture::Capture::new(model.clone());
+    // The command receiver waits here until the PipeWire thread starts (see
+    // WidgetBackends::start).
+    let (audio, audio_commands) = audio::AudioSinks::new();
+    let compositor = compositor::AnyCompositor::detect();
+    compositor
+        .workspace_labels()
+        .set(config.workspace.labels.clone());
+    let on_hyprland = compositor.is_hyprland();
+    let window_backend = config.windows.backend.resolve(on_hyprland);
+    info!(
+        ?compositor,
+        ?window_backend,
+        "Compositor and window backend"
+    );
+    let (window_tx, window_rx) = mpsc::unbounded_channel();
+    let taskbar = widgets::TaskbarStore::new(
+        match window_backend {
+            config::WindowBackend::Wlr => toplevel::WindowActions::Wlr(window_tx),
+            _ => toplevel::WindowActions::Compositor(compositor.clone()),
+        },
+        audio.clone(),
+    );
+    let tray_widget = widgets::create_tray_widget();
+    let mut bar = widgets::build_bar(
+        &widgets::WidgetContext {
+            model: &model,
+            config,
+            alarms: &alarms,
+            pomodoro: &pomodoro,
+            capture: &capture,
+            audio: &audio,
+            taskbar: &taskbar,
+            compositor: &compositor,
+        },
+        &tray_widget,
+    )?;
+    widgets::place_custom_widgets(&mut bar, &model, &options.custom_widgets)?;
+    // The volume OSD listens to PipeWire like the volume widget does.
+    let backends = needed_backends(config, |widget| bar.slots.contains_key(widget));
+    let auto_hide = if config.bar.auto_hide {
+        Some(autohide::AutoHide::install(
+            &window,
+            &bar.root,
+            config.bar.position,
+        ))
+    } else {
+        window.set_child(Some(&bar.root));
+        None
+    };
+    let bar_root = bar.root.clone();
+    window.set_visible(true);
+    widgets::setup_ui_watchdog();
+
+    let (bus, receivers) = bus::Bus::new();
+    // The system and session D-Bus connections every D-Bus monitor shares;
+    // each opens on first use.
+    let buses = buses::BusManager::new();
+    let (tray_backend, tray_ui) = tray::channels();
+    let (ipc_tx, ipc_rx) = mpsc::unbounded_channel();
+    let (tray_ipc_tx, tray_ipc_rx) = mpsc::unbounded_channel();
+    let (color_scheme_tx, color_scheme_rx) = mpsc::unbounded_channel();
+
+    // One dispatcher for the whole bar, so a burst touching several widgets
+    // still lands as a single batched pass, in the same frame.
+    let dispatcher = dispatch::UiDispatcher::new();
+    dispatcher.sync_to_frames(&window);
+
+    let (workspace_colors, workspace_colors_rx) =
+        watch::channel(palette::WorkspaceColors::new(&config.workspace));
+
+    widgets::update_time_widget(model.clone(), config.clock.format.clone(), bus.resumes());
+    pomodoro.start_ticking();
+    alarm::start_alarm_checks(
+        alarms.clone(),
+        application,
+        config.clock.alarm_sound.clone(),
+    );
+    widgets::setup_tray_updates(tray_ui, tray_ipc_rx, tray_widget.clone(), &window);
+    // Socket requests pass through the bar controller first; it answers the
+    // bar-level verbs and forwards the tray's to setup_tray_updates.
+    // Filled in once every startup task below has spawned; the widget
+    // backends add theirs later.
+    let services = Rc::new(RefCell::new(None::<Services>));
+    // The backends start once the bar has painted its first frame (with the
+    // model's placeholders), so PipeWire and the D-Bus connections never sit
+    // between launch and a visible bar. A reload starts the ones a new layout
+    // adds (see control.rs).
+    let widget_backends = Rc::new(RefCell::new(WidgetBackends {
+        started: Vec::new(),
+        pending: Some((backends, config.clone())),
+        window_backend,
+        on_hyprland,
+        bus: bus.clone(),
+        buses: buses.clone(),
+        network: options.network.clone(),
+        model: model.clone(),
+        dispatcher: dispatcher.clone(),
+        audio: Some((audio.clone(), audio_commands)),
+        taskbar: taskbar.clone(),
+        application: application.downgrade(),
+        services: services.clone(),
+    }));
+    let visibility =
+        control::WindowVisibility::new(window.clone(), config.bar.hidden_workspaces.clone());
+    let workspace_visibility = visibility.clone();
+    model.connect_workspace_id_notify(move |model| {
+        workspace_visibility.set_workspace(model.workspace_id());
+    });
+    control::setup_control_requests(
+        ipc_rx,
+        tray_ipc_tx,
+        control::BarControl {
+            window: window.clone(),
+            visibility,
+            model: model.clone(),
+            alarms,
+            pomodoro,
+            capture,
+            audio: audio.clone(),
+            taskbar: taskbar.clone(),
+            auto_hide,
+            bar,
+            tray: tray_widget,
+            layer: options.layer,
+            config_path: options.config_path.clone(),
+            workspace_colors,
+            colors,
+            floating_css,
+            runtime_widgets: Vec::new(),
+            builtin_widgets: options.widgets.clone(),
+            custom_widgets: options.custom_widgets.clone(),
+            compositor: compositor.clone(),
+            backends: widget_backends.clone(),
+        },
+    );
+    // Only for the default location: an explicit --config that does not exist
+    // is a mistake to report, not a first run.
+    if options.first_run_wizard
+        && options.config_path.is_none()
+        && !state.read(|state| state.wizard_done)
+        && let Some(path) = config::default_path()
+        && !path.exists()
+    {
+        wizard::show(wizard::Wizard {
+            anchor: bar_root,
+            window: window.clone(),
+            path,
+            state,
+            control: ipc_tx.clone(),
+        });
+    }
+    widgets::setup_workspace_updates(receivers.workspace, model.clone(), dispatcher.clone());
+    views::spawn_workspace_color(
+        model.clone(),
+        bus.state().workspace(),
+        workspace_colors_rx,
+        dispatcher.clone(),
+    );
+    if config.osd.volume || config.osd.brightness {
+        let osd = osd::Osd::new(
+            application,
+            Duration::from_millis(config.osd.timeout_ms.into()),
+        );
+        if config.osd.volume {
+            osd::spawn_volume_osd(osd.clone(), bus.state().audio());
+        }
+        if config.osd.brightness {
+            osd::spawn_brightness_osd(osd, bus.state().brightness());
+        }
+    }
+    // Cheap enough to run without the quick-settings widget, so one a reload
+    // places shows current levels at once.
+    views::spawn_quick_levels(
+        model.clone(),
+        bus.state().audio(),
+        bus.state().brightness(),
+        dispatcher.clone(),
+    );
+    let notification_server = config.notifications.server.then(|| {
+        let (popups_tx, popups_rx) = mpsc::unbounded_channel();
+        let (replies_tx, replies_rx) = mpsc::unbounded_channel();
+        let max_popups = config.notifications.max_popups as usize;
+        let popups = notification_popups::Popups::new(application, max_popups, replies_tx);
+        notification_popups::spawn_notification_popups(popups, popups_rx);
+        (popups_tx, replies_rx)
+    });
+    widgets::setup_title_updates(receivers.title, model.clone(), dispatcher.clone());
+    widgets::setup_battery_updates(
+        receivers.battery,
+        model.clone(),
+        dispatcher.clone(),
+        application,
+        config.battery.clone(),
+    );
+    widgets::setup_bluetooth_updates(receivers.bluetooth, model.clone(), dispatcher.clone());
+    widgets::setup_network_updates(receivers.network, model.clone(), dispatcher.clone());
+    widgets::setup_split_updates(receivers.split, model.clone(), dispatcher.clone());
+    widgets::setup_displays_updates(receivers.displays, model.clone(), dispatcher.clone());
+    widgets::setup_window_count_updates(receivers.window_count, model.clone(), dispatcher.clone());
+    widgets::setup_floating_updates(receivers.floating, model.clone(), dispatcher.clone());
+    widgets::setup_scratchpad_updates(receivers.scratchpad, model.clone(), dispatcher.clone());
+    widgets::setup_cpufreq_updates(receivers.cpufreq, model.clone(), dispatcher.clone());
+    widgets::setup_gpu_updates(receivers.gpu, model.clone(), dispatcher.clone());
+    widgets::setup_loadavg_updates(receivers.loadavg, model.clone(), dispatcher.clone());
+    widgets::setup_bandwidth_updates(receivers.bandwidth, model.clone(), dispatcher.clone());
+    widgets::setup_lock_keys_updates(receivers.lock_keys, model.clone(), dispatcher.clone());
+    widgets::setup_kbd_backlight_updates(
+        receivers.kbd_backlight,
+        model.clone(),
+        dispatcher.clone(),
+    );
+    widgets::setup_progress_updates(receivers.progress, model.clone(), dispatcher.clone());
+    widgets::setup_notifications_updates(
+        receivers.notifications,
+        model.clone(),
+        dispatcher.clone(),
+    );
+    widgets::setup_vpn_updates(receivers.vpn, model.clone(), dispatcher.clone());
+    widgets::setup_hotspot_updates(receivers.hotspot, model.clone(), dispatcher.clone());
+    widgets::setup_rfkill_updates(receivers.rfkill, model.clone(), dispatcher.clone());
+    widgets::setup_units_updates(receivers.units, model.clone(), dispatcher.clone());
+    widgets::setup_package_updates(receivers.updates, model.clone(), dispatcher.clone());
+    widgets::setup_clipboard_updates(receivers.clipboard, model.clone(), dispatcher.clone());
+    widgets::setup_taskbar_updates(receivers.taskbar, taskbar.clone(), dispatcher.clone());
+    widgets::setup_color_scheme_updates(color_scheme_rx);
+
+    // Every consumer above is wired before any producer below spawns. The
+    // tray also has a UI-to-backend command channel; it still obeys the same
+    // ordering. Widget backends come later, see WidgetBackends.
+    let mut tasks = vec![
+        setup_signal_handling(application)?,
+        compositor.spawn_workspace_listener(bus.clone()),
+        match window_backend {
+            config::WindowBackend::Wlr => tokio::spawn(toplevel::run_toplevel_listener_supervised(
+                bus.clone(),
+                window_rx,
+            )),
+            _ => compositor.spawn_title_listener(bus.clone()),
+        },
+        tokio::spawn(tray::run_tray_supervised(tray_backend)),
+        tokio::spawn(run_tray_ipc_supervised(ipc_tx)),
+        tokio::spawn(appearance::run_color_scheme_supervised(
+            color_scheme_tx,
+            buses.clone(),
+        )),
+        tokio::spawn(sleep::run_sleep_watch_supervised(
+            bus.clone(),
+            buses.clone(),
+        )),
+    ];
+    // The workspace listener always runs and reports its hook events; the
+    // battery and Bluetooth ones come from the UPower and BlueZ monitors,
+    // which a configured hook starts even without their widgets (see
+    // needed_backends). With no hook configured this returns at once and the
+    // events are dropped on send.
+    tasks.push(tokio::spawn(hooks::run_hooks(
+        config.hooks.clone(),
+        receivers.hooks,
+    )));
+    if !config.auto_profile.is_empty() {
+        let (facts_tx, facts_rx) = mpsc::unbounded_channel();
+        // GDK's monitor list follows every output the compositor announces,
+        // whichever compositor that is. The list outlives the bar, so its
+        // handler just stops mattering once the rules task is gone.
+        let monitors = gtk4::prelude::WidgetExt::display(&window).monitors();
+        let _ = facts_tx.send(autoprofile::Fact::Monitors(monitors.n_items()));
+        let monitors_tx = facts_tx.clone();
+        monitors.connect_items_changed(move |monitors, _, _, _| {
+            let _ = monitors_tx.send(autoprofile::Fact::Monitors(monitors.n_items()));
+        });
+        if autoprofile::needs_power(&config.auto_profile) {
+            tasks.push(tokio::spawn(autoprofile::run_power_watch_supervised(
+                facts_tx,
+                buses.clone(),
+            )));
+        }
+        tasks.push(tokio::spawn(autoprofile::run_auto_profiles(
+            config.auto_profile.clone(),
+            config.displays.profiles.clone(),
+            facts_rx,
+        )));
+    }
+    if let Some((popups, replies)) = notification_server {
+        tasks.push(tokio::spawn(
+            notification_server::run_notification_server_supervised(
+                popups,
+                replies,
+                Duration::from_millis(config.notifications.timeout_ms.into()),
+            ),
+        ));
+    }
+    if config.recorder.path.is_some() {
+        tasks.push(tokio::spawn(recorder::run_recorder(
+            config.recorder.clone(),
+        )));
+    }
+    #[cfg(feature = "metrics")]
+    if let Some(listen) = config.metrics.listen {
+        tasks.push(tokio::spawn(crate::metrics::run_metrics_server(
+            listen,
+            bus.state().clone(),
+        )));
+    }
+    #[cfg(not(feature = "metrics"))]
+    if config.metrics.listen.is_some() {
+        warn!("[metrics] listen is set, but this build lacks the metrics feature");
+    }
+    if has_focus_keywords {
+        tasks.push(tokio::spawn(hypr::run_focus_mode(
+            focus_rx,
+            config.pomodoro.focus_keywords.clone(),
+        )));
+    }
+
+    services.replace(Some(Services {
+        tasks,
+        pipewire: None,
+    }));
+    let shutdown_services = services.clone();
+    application.connect_shutdown(move |_| {
+        if let Some(services) = shutdown_services.take() {
+            services.shutdown();
+        }
+    });
+
+    let start = move || widget_backends.borrow_mut().first_frame();
+    // set_visible realized the window, so it has a frame clock; without one
+    // an idle callback is the next best thing to "after the first frame".
+    match window.frame_clock() {
+        Some(clock) => {
+            let handler = Rc::new(RefCell::new(None));
+            let handler_in_paint = handler.clone();
+            let id = clock.connect_after_paint(move |clock| {
+                if let Some(id) = handler_in_paint.take() {
+                    clock.disconnect(id);
+                }
+                start();
+            });
+            handler.replace(Some(id));
+        }
+        None => {
+            glib::idle_add_local_once(start);
+        }
+    }
+
+    info!("Application activated successfully");
+    Ok(Bar { window, model })
+}
+
+const APPLICATION_ID: &str = "sh.wmww.gtk-layer-example";
+const REPLACE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const REPLACE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Claim the application id on the session bus before running, so uniqueness
+/// is decided up front instead of by GApplication quietly forwarding activate
+/// to the primary. Without `replace` a second copy gets an error; with it, the
+/// running bar is asked to quit through its "quit" action and we retry until
+/// the name is free. A GApplication can only register once, so each attempt
+/// builds a fresh one and the winner is returned for run().
+pub fn register_primary_instance(replace: bool) -> Result<gtk4::Application> {
+    let started = Instant::now();
+    let mut asked_to_quit = false;
+    loop {
+        let application = gtk4::Application::new(Some(APPLICATION_ID), Default::default());
+        application
+            .register(None::<&gio::Cancellable>)
+            .context("register application on the session bus")?;
+        if !application.is_remote() {
+            return Ok(application);
+        }
+        if !replace {
+            bail!("another gtk-status-bar is already running (use --replace to take over)");
+        }
+        if !asked_to_quit {
+            info!("Asking the running instance to quit");
+            application.activate_action("quit", None);
+            // Remote action activation is a fire-and-forget D-Bus call; flush
+            // so it leaves before this GApplication is dropped.
+            if let Some(connection) = application.dbus_connection() {
+                connection
+                    .flush_sync(None::<&gio::Cancellable>)
+                    .context("send quit request to the running instance")?;
+            }
+            asked_to_quit = true;
+        }
+        if started.elapsed() >= REPLACE_TIMEOUT {
+            bail!(
+                "the running gtk-status-bar did not quit within {:?}",
+                REPLACE_TIMEOUT
+            );
+        }
+        std::thread::sleep(REPLACE_POLL_INTERVAL);
+    }
+}
+
+/// Exposed on the session bus as org.gtk.Actions, which is how --replace (or
+/// `gapplication action sh.wmww.gtk-layer-example quit`) stops a running bar.
+pub fn install_quit_action(application: &gtk4::Application) {
+    let quit = gio::SimpleAction::new("quit", None);
+    let app = application.downgrade();
+    quit.connect_activate(move |_, _| {
+        info!("Quit requested over D-Bus");
+        if let Some(app) = app.upgrade() {
+            app.quit();
+        }
+    });
+    application.add_action(&quit);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backends_follow_placed_widgets() {
+        let config = Config::default();
+        let placed = ["workspace", "title", "privacy", "battery", "clock"];
+        assert_eq!(
+            needed_backends(&config, |widget| placed.contains(&widget)),
+            vec![Backend::Pipewire, Backend::Battery, Backend::Floating]
+        );
+        assert!(needed_backends(&config, |_| false).is_empty());
+    }
+
+    #[test]
+    fn the_osds_need_their_producers_without_widgets() {
+        let mut config = Config::default();
+        config.osd.volume = true;
+        config.osd.brightness = true;
+        assert_eq!(
+            needed_backends(&config, |_| false),
+            vec![Backend::Pipewire, Backend::Backlight]
+        );
+        assert_eq!(
+            needed_backends(&config, |widget| widget == "quick_settings"),
+            vec![Backend::Pipewire, Backend::Backlight]
+        );
+    }
+
+    #[test]
+    fn hooks_need_the_monitors_that_report_them() {
+        let mut config = Config::default();
+        config.hooks.on_battery_low = Some("notify-send low".to_string());
+        assert_eq!(needed_backends(&config, |_| false), vec![Backend::Battery]);
+        config.hooks.on_bt_connected = Some("notify-send bt".to_string());
+        assert_eq!(
+            needed_backends(&config, |widget| widget == "battery"),
+            vec![Backend::Battery, Backend::Bluetooth]
+        );
+    }
+
+    // The default layout places every built-in widget the config leaves
+    // enabled; a reload that enables one starts only its backend.
+    #[test]
+    fn a_reload_that_places_a_widget_starts_its_backend() {
+        let startup: Config =
+            toml::from_str("[bar]\ndisabled_widgets = [\"battery\", \"volume\"]\n")
+                .expect("startup config");
+        let reloaded: Config =
+            toml::from_str("[bar]\ndisabled_widgets = [\"volume\"]\n").expect("reloaded config");
+        let placed = |config: &Config, widget: &str| {
+            ["workspace", "clock", "battery", "volume"].contains(&widget)
+                && !config.bar.is_disabled(widget)
+        };
+
+        let started = needed_backends(&startup, |widget| placed(&startup, widget));
+        assert_eq!(started, vec![Backend::Floating]);
+        let needed = needed_backends(&reloaded, |widget| placed(&reloaded, widget));
+        assert_eq!(missing_backends(&started, &needed), vec![Backend::Battery]);
+        assert!(missing_backends(&needed, &needed).is_empty());
+    }
+}
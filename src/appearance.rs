@@ -8,13 +8,16 @@
 // thread, matching the consumer-before-producer wiring every other subsystem
 // uses.
 
-use std::time::{Duration, Instant};
+use std::ops::ControlFlow;
 
 use anyhow::{Context, Result};
-use futures_util::StreamExt;
 use tokio::sync::mpsc;
-use tracing::{debug, error, info, warn};
-use zbus::{Connection, Proxy};
+use tracing::{debug, info};
+use zbus::message::Type as MessageType;
+use zbus::{MatchRule, Proxy};
+
+use crate::buses::{BusKind, BusManager};
+use crate::dbus::supervise_until;
 
 const PORTAL_DEST: &str = "org.freedesktop.portal.Desktop";
 const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
@@ -74,25 +77,42 @@ async fn read_prefer_dark(proxy: &Proxy<'_>) -> Option<bool> {
     value_to_prefer_dark(value)
 }
 
-// Connect, emit the current preference so a reconnect re-syncs the bar, then
+fn setting_changed_rule() -> Result<MatchRule<'static>> {
+    Ok(MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender(PORTAL_DEST)
+        .context("portal SettingChanged rule: set sender")?
+        .interface(SETTINGS_INTERFACE)
+        .context("portal SettingChanged rule: set interface")?
+        .member("SettingChanged")
+        .context("portal SettingChanged rule: set member")?
+        .path(PORTAL_PATH)
+        .context("portal SettingChanged rule: set path")?
+        .build())
+}
+
+// Subscribe, emit the current preference so a reconnect re-syncs the bar, then
 // forward every color-scheme change until a stream or the channel closes.
-async fn run_color_scheme(tx: &mpsc::UnboundedSender<bool>) -> Result<()> {
-    let connection = Connection::session()
-        .await
-        .context("connect color-scheme watcher to session D-Bus")?;
-    let proxy = Proxy::new(&connection, PORTAL_DEST, PORTAL_PATH, SETTINGS_INTERFACE)
+async fn run_color_scheme(tx: &mpsc::UnboundedSender<bool>, buses: &BusManager) -> Result<()> {
+    let mut changes = buses
+        .subscribe(BusKind::Session, vec![setting_changed_rule()?])
         .await
-        .context("create portal Settings proxy")?;
+        .context("subscribe to portal SettingChanged")?;
+    let proxy = Proxy::new(
+        changes.connection(),
+        PORTAL_DEST,
+        PORTAL_PATH,
+        SETTINGS_INTERFACE,
+    )
+    .await
+    .context("create portal Settings proxy")?;
 
     if let Some(prefer_dark) = read_prefer_dark(&proxy).await
-        && tx.send(prefer_dark).is_err() {
-            return Ok(());
-        }
+        && tx.send(prefer_dark).is_err()
+    {
+        return Ok(());
+    }
 
-    let mut changes = proxy
-        .receive_signal("SettingChanged")
-        .await
-        .context("subscribe to portal SettingChanged")?;
     while let Some(message) = changes.next().await {
         let body = message.body();
         let (namespace, key, value): (String, String, zvariant::Value) = match body.deserialize() {
@@ -120,30 +140,16 @@ async fn run_color_scheme(tx: &mpsc::UnboundedSender<bool>) -> Result<()> {
 // Supervised wrapper: the portal comes and goes with xdg-desktop-portal, so
 // retry with the same backoff policy as the other D-Bus-backed producers rather
 // than giving up after one failure. Returns once the GTK consumer is gone.
-pub async fn run_color_scheme_supervised(tx: mpsc::UnboundedSender<bool>) {
-    let max_delay = Duration::from_secs(60);
-    let reset_threshold = Duration::from_secs(30);
-    let mut delay = Duration::from_secs(1);
-
-    loop {
-        let started = Instant::now();
-        info!("Starting desktop color-scheme watcher");
-        match run_color_scheme(&tx).await {
-            Ok(()) => warn!("Color-scheme watcher stopped (stream closed)"),
-            Err(error) => error!("Color-scheme watcher failed: {:#}", error),
-        }
-
+pub async fn run_color_scheme_supervised(tx: mpsc::UnboundedSender<bool>, buses: BusManager) {
+    supervise_until("desktop color-scheme watcher", || async {
+        let result = run_color_scheme(&tx, &buses).await;
         if tx.is_closed() {
             debug!("Color-scheme consumer is gone; stopping watcher");
-            return;
-        }
-        if started.elapsed() >= reset_threshold {
-            delay = Duration::from_secs(1);
+            return Ok(ControlFlow::Break(()));
         }
-        warn!(restart_delay = ?delay, "Restarting color-scheme watcher");
-        tokio::time::sleep(delay).await;
-        delay = std::cmp::min(delay * 2, max_delay);
-    }
+        result.map(ControlFlow::Continue)
+    })
+    .await
 }
 
 #[cfg(test)]
@@ -0,0 +1,87 @@
+// Output device switching: the GTK side of the sink list behind the volume
+// widget's right-click menu, of the taskbar's per-window mute and of the
+// headset widget's profile toggle.
+//
+// The PipeWire thread owns the truth. It sends a full SinkList snapshot
+// whenever a sink appears or goes away or the default changes, and takes
+// AudioCommands back over a pipewire::channel, which wakes its loop directly.
+// Switching writes `default.configured.audio.sink` on the "default" metadata
+// object, the same key `wpctl set-default` and desktop settings panels write
+// and that WirePlumber remembers across restarts.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gtk4::glib;
+use tracing::{debug, info, warn};
+
+use crate::bus::SinkList;
+use crate::channel;
+pub use crate::pw::AudioCommand;
+
+// Shared by the volume widget's menu, the taskbar and the sink list drain.
+// Cheap to clone.
+#[derive(Clone)]
+pub struct AudioSinks {
+    list: Rc<RefCell<SinkList>>,
+    commands: pipewire::channel::Sender<AudioCommand>,
+}
+
+impl AudioSinks {
+    // The receiver goes to pw::start_pipewire_thread.
+    pub fn new() -> (Self, pipewire::channel::Receiver<AudioCommand>) {
+        let (commands, receiver) = pipewire::channel::channel();
+        let sinks = Self {
+            list: Rc::default(),
+            commands,
+        };
+        (sinks, receiver)
+    }
+
+    pub fn list(&self) -> SinkList {
+        self.list.borrow().clone()
+    }
+
+    pub fn set_default(&self, node_name: &str) {
+        info!(sink = node_name, "Switching default sink");
+        if self
+            .commands
+            .send(AudioCommand::SetDefaultSink(node_name.to_string()))
+            .is_err()
+        {
+            warn!("PipeWire thread is gone; default sink left as it is");
+        }
+    }
+
+    pub fn set_stream_mute(&self, node_id: u32, mute: bool) {
+        info!(node_id, mute, "Muting playback stream");
+        if self
+            .commands
+            .send(AudioCommand::SetStreamMute { node_id, mute })
+            .is_err()
+        {
+            warn!("PipeWire thread is gone; stream mute left as it is");
+        }
+    }
+
+    pub fn toggle_headset_profile(&self) {
+        info!("Toggling headset profile");
+        if self
+            .commands
+            .send(AudioCommand::ToggleHeadsetProfile)
+            .is_err()
+        {
+            warn!("PipeWire thread is gone; headset profile left as it is");
+        }
+    }
+}
+
+pub fn setup_sink_list_updates(mut rx: channel::Receiver<SinkList>, sinks: AudioSinks) {
+    debug!("Setting up sink list updates");
+    glib::spawn_future_local(async move {
+        while let Some(list) = rx.recv().await {
+            debug!(sinks = list.sinks.len(), default = ?list.default, "Sink list changed");
+            *sinks.list.borrow_mut() = list;
+        }
+    });
+}
@@ -0,0 +1,169 @@
+// Auto-hide: with `[bar] auto_hide = true` the bar gives up its exclusive zone
+// and slides out of sight, leaving a few-pixel strip along its edge (top, or
+// the one `[bar] position` names). Touching that strip slides it back in; leaving it slides it away again
+// after a short delay, unless one of the bar's popovers (clock, displays, a
+// tray menu) is still open, since hiding would unmap the widget it hangs from.
+//
+// The IPC show/hide/toggle verbs drive the revealer instead of the window in
+// this mode, and a bar shown over IPC stays pinned until hidden the same way,
+// so a keybind can peek at it without the pointer.
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4_layer_shell::{Layer, LayerShell};
+use tracing::{debug, info};
+
+use crate::config::Position;
+
+// Thickness of the strip that stays on screen to catch the pointer. A layer
+// surface anchored to one edge cannot be zero-sized in the other direction.
+const HOT_EDGE_PIXELS: i32 = 2;
+const HIDE_DELAY: Duration = Duration::from_millis(600);
+const SLIDE_MILLIS: u32 = 200;
+
+pub struct AutoHide {
+    revealer: gtk4::Revealer,
+    hovered: Cell<bool>,
+    pinned: Cell<bool>,
+    // A pending hide polls rather than holding a SourceId, so re-entering the
+    // bar never has to cancel a source that may already have fired.
+    hide_pending: Cell<bool>,
+}
+
+impl AutoHide {
+    // Wrap `bar` in the revealer and take over the window's child, layer and
+    // exclusive zone. The bar starts hidden.
+    pub fn install(
+        window: &gtk4::ApplicationWindow,
+        bar: &gtk4::Widget,
+        position: Position,
+    ) -> Rc<Self> {
+        info!("Auto-hide enabled");
+        let revealer = gtk4::Revealer::new();
+        revealer.set_transition_type(match position {
+            Position::Top => gtk4::RevealerTransitionType::SlideDown,
+            Position::Bottom => gtk4::RevealerTransitionType::SlideUp,
+            Position::Left => gtk4::RevealerTransitionType::SlideRight,
+            Position::Right => gtk4::RevealerTransitionType::SlideLeft,
+        });
+        revealer.set_transition_duration(SLIDE_MILLIS);
+        revealer.set_reveal_child(false);
+        revealer.set_child(Some(bar));
+
+        let edge = if position.is_vertical() {
+            let edge = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
+            edge.set_size_request(HOT_EDGE_PIXELS, -1);
+            edge
+        } else {
+            let edge = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+            edge.set_size_request(-1, HOT_EDGE_PIXELS);
+            edge
+        };
+        match position {
+            Position::Bottom => edge.set_valign(gtk4::Align::End),
+            Position::Right => edge.set_halign(gtk4::Align::End),
+            Position::Top | Position::Left => {}
+        }
+        edge.append(&revealer);
+        window.set_child(Some(&edge));
+
+        // Windows keep the full screen, so the revealed bar has to sit above
+        // them rather than on the Bottom layer it normally shares with them.
+        window.set_layer(Layer::Top);
+        window.set_exclusive_zone(0);
+
+        let auto_hide = Rc::new(Self {
+            revealer,
+            hovered: Cell::new(false),
+            pinned: Cell::new(false),
+            hide_pending: Cell::new(false),
+        });
+
+        let motion = gtk4::EventControllerMotion::new();
+        let weak = Rc::downgrade(&auto_hide);
+        motion.connect_enter(move |_, _, _| {
+            if let Some(auto_hide) = weak.upgrade() {
+                auto_hide.hovered.set(true);
+                auto_hide.revealer.set_reveal_child(true);
+            }
+        });
+        let weak = Rc::downgrade(&auto_hide);
+        motion.connect_leave(move |_| {
+            if let Some(auto_hide) = weak.upgrade() {
+                auto_hide.hovered.set(false);
+                auto_hide.schedule_hide();
+            }
+        });
+        window.add_controller(motion);
+
+        auto_hide
+    }
+
+    // Replace the wrapped bar after a configuration reload.
+    pub fn set_bar(&self, bar: &gtk4::Widget) {
+        self.revealer.set_child(Some(bar));
+    }
+
+    pub fn is_revealed(&self) -> bool {
+        self.revealer.reveals_child()
+    }
+
+    pub fn show_pinned(&self) {
+        self.pinned.set(true);
+        self.revealer.set_reveal_child(true);
+    }
+
+    pub fn hide(&self) {
+        self.pinned.set(false);
+        if !self.hovered.get() {
+            self.revealer.set_reveal_child(false);
+        }
+    }
+
+    fn schedule_hide(self: &Rc<Self>) {
+        if self.hide_pending.replace(true) {
+            return;
+        }
+        let weak = Rc::downgrade(self);
+        glib::timeout_add_local(HIDE_DELAY, move || {
+            let Some(auto_hide) = weak.upgrade() else {
+                return glib::ControlFlow::Break;
+            };
+            if auto_hide.hovered.get() || auto_hide.pinned.get() {
+                auto_hide.hide_pending.set(false);
+                return glib::ControlFlow::Break;
+            }
+            let Some(bar) = auto_hide.revealer.child() else {
+                auto_hide.hide_pending.set(false);
+                return glib::ControlFlow::Break;
+            };
+            if has_open_popover(&bar) {
+                debug!("Keeping the bar revealed while a popover is open");
+                return glib::ControlFlow::Continue;
+            }
+            auto_hide.hide_pending.set(false);
+            auto_hide.revealer.set_reveal_child(false);
+            glib::ControlFlow::Break
+        });
+    }
+}
+
+// Popovers are parented to the widget they open from, so they are part of the
+// bar's widget tree even though they draw on their own surface.
+fn has_open_popover(widget: &gtk4::Widget) -> bool {
+    if widget.is::<gtk4::Popover>() && widget.is_visible() {
+        return true;
+    }
+    let mut child = widget.first_child();
+    while let Some(current) = child {
+        if has_open_popover(&current) {
+            return true;
+        }
+        child = current.next_sibling();
+    }
+    false
+}
@@ -0,0 +1,236 @@
+// Automatic profiles, kanshi-style: `[[auto_profile]]` rules match on how many
+// monitors are connected and whether the machine runs on AC, and the first
+// rule that matches is applied: its display profile's command runs (the same
+// `[[displays.profiles]]` entry the displays popover offers) and then its
+// hook. Docking a laptop can thereby switch outputs and, say, restart a
+// wallpaper daemon without anyone touching the bar.
+//
+// Facts come in over a channel: the monitor count from GDK (so this works on
+// any compositor, not only Hyprland), AC power from UPower's OnBattery. A dock
+// adds outputs and AC within a second or so of each other, so the rules are
+// only evaluated once the facts have been quiet for SETTLE; a rule is applied
+// when it becomes the match, not again while it stays one.
+
+use std::ops::ControlFlow;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+use zbus::message::Type as MessageType;
+use zbus::{Connection, MatchRule, Proxy};
+
+use crate::buses::{BusKind, BusManager};
+use crate::command;
+use crate::config::{AutoProfileRule, DisplayProfile};
+use crate::dbus::supervise_until;
+
+const SETTLE: Duration = Duration::from_secs(2);
+const UPOWER: &str = "org.freedesktop.UPower";
+const UPOWER_PATH: &str = "/org/freedesktop/UPower";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fact {
+    Monitors(u32),
+    OnAc(bool),
+}
+
+// What is known so far; a rule that needs an unknown fact does not match.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Facts {
+    pub monitors: Option<u32>,
+    pub on_ac: Option<bool>,
+}
+
+impl Facts {
+    fn apply(&mut self, fact: Fact) {
+        match fact {
+            Fact::Monitors(count) => self.monitors = Some(count),
+            Fact::OnAc(on_ac) => self.on_ac = Some(on_ac),
+        }
+    }
+}
+
+fn matches(rule: &AutoProfileRule, facts: &Facts) -> bool {
+    let monitors_match = match (rule.min_monitors, rule.max_monitors) {
+        (None, None) => true,
+        (min, max) => facts.monitors.is_some_and(|count| {
+            min.is_none_or(|min| count >= min) && max.is_none_or(|max| count <= max)
+        }),
+    };
+    let power_matches = rule.on_ac.is_none_or(|on_ac| facts.on_ac == Some(on_ac));
+    monitors_match && power_matches
+}
+
+pub fn matching_rule<'a>(
+    rules: &'a [AutoProfileRule],
+    facts: &Facts,
+) -> Option<&'a AutoProfileRule> {
+    rules.iter().find(|rule| matches(rule, facts))
+}
+
+// Whether any rule looks at the power supply, so UPower is only watched when
+// it matters.
+pub fn needs_power(rules: &[AutoProfileRule]) -> bool {
+    rules.iter().any(|rule| rule.on_ac.is_some())
+}
+
+fn apply(rule: &AutoProfileRule, profiles: &[DisplayProfile]) {
+    info!(rule = rule.name, "Applying automatic profile");
+    if let Some(name) = &rule.profile {
+        // config::parse checked the name, so a miss means a config built some
+        // other way.
+        match profiles.iter().find(|profile| profile.name == *name) {
+            Some(profile) => command::spawn_shell(&profile.command, "display profile"),
+            None => warn!(rule = rule.name, profile = name, "Unknown display profile"),
+        }
+    }
+    if let Some(hook) = &rule.hook {
+        command::spawn_shell(hook, "automatic profile hook");
+    }
+}
+
+pub async fn run_auto_profiles(
+    rules: Vec<AutoProfileRule>,
+    profiles: Vec<DisplayProfile>,
+    mut facts_rx: mpsc::UnboundedReceiver<Fact>,
+) {
+    info!(rules = rules.len(), "Starting automatic profiles");
+    let mut facts = Facts::default();
+    let mut settle_at = None;
+    // Nothing is applied yet, so whatever matches first runs, as kanshi does
+    // on start.
+    let mut active: Option<String> = None;
+    loop {
+        tokio::select! {
+            fact = facts_rx.recv() => {
+                let Some(fact) = fact else {
+                    debug!("Fact sources are gone; stopping automatic profiles");
+                    return;
+                };
+                debug!(?fact, "Automatic profile fact");
+                facts.apply(fact);
+                settle_at = Some(Instant::now() + SETTLE);
+            }
+            _ = async move {
+                match settle_at {
+                    Some(deadline) => tokio::time::sleep_until(deadline).await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                settle_at = None;
+                let matched = matching_rule(&rules, &facts);
+                let name = matched.map(|rule| rule.name.clone());
+                if name == active {
+                    continue;
+                }
+                debug!(?facts, rule = ?name, "Automatic profile match changed");
+                active = name;
+                if let Some(rule) = matched {
+                    apply(rule, &profiles);
+                }
+            }
+        }
+    }
+}
+
+fn upower_rule() -> Result<MatchRule<'static>> {
+    Ok(MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender(UPOWER)
+        .context("UPower rule: set sender")?
+        .interface("org.freedesktop.DBus.Properties")
+        .context("UPower rule: set interface")?
+        .member("PropertiesChanged")
+        .context("UPower rule: set member")?
+        .path(UPOWER_PATH)
+        .context("UPower rule: set path")?
+        .build())
+}
+
+async fn on_ac(connection: &Connection) -> Result<bool> {
+    let upower = Proxy::new(connection, UPOWER, UPOWER_PATH, UPOWER)
+        .await
+        .context("create UPower proxy")?;
+    let on_battery: bool = upower
+        .get_property("OnBattery")
+        .await
+        .context("read UPower OnBattery")?;
+    Ok(!on_battery)
+}
+
+async fn watch_power(tx: &mpsc::UnboundedSender<Fact>, buses: &BusManager) -> Result<()> {
+    let mut signals = buses
+        .subscribe(BusKind::System, vec![upower_rule()?])
+        .await?;
+    let mut last = on_ac(signals.connection()).await?;
+    tx.send(Fact::OnAc(last)).context("send power fact")?;
+    while signals.next().await.is_some() {
+        let now = on_ac(signals.connection()).await?;
+        if now == last {
+            continue;
+        }
+        last = now;
+        tx.send(Fact::OnAc(now)).context("send power fact")?;
+    }
+    bail!("UPower subscription ended")
+}
+
+pub async fn run_power_watch_supervised(tx: mpsc::UnboundedSender<Fact>, buses: BusManager) {
+    supervise_until("power watch", || async {
+        let result = watch_power(&tx, &buses).await;
+        if tx.is_closed() {
+            return Ok(ControlFlow::Break(()));
+        }
+        result.map(ControlFlow::Continue)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(name: &str, min_monitors: Option<u32>, on_ac: Option<bool>) -> AutoProfileRule {
+        AutoProfileRule {
+            name: name.to_string(),
+            min_monitors,
+            max_monitors: None,
+            on_ac,
+            profile: None,
+            hook: None,
+        }
+    }
+
+    #[test]
+    fn first_matching_rule_wins_and_unknown_facts_do_not_match() {
+        let rules = vec![
+            rule("docked", Some(2), Some(true)),
+            rule("plugged", None, Some(true)),
+            rule("anywhere", None, None),
+        ];
+        let name = |facts: Facts| matching_rule(&rules, &facts).map(|rule| rule.name.as_str());
+
+        assert_eq!(name(Facts::default()), Some("anywhere"));
+        let docked = Facts {
+            monitors: Some(3),
+            on_ac: Some(true),
+        };
+        assert_eq!(name(docked), Some("docked"));
+        assert_eq!(
+            name(Facts {
+                monitors: None,
+                ..docked
+            }),
+            Some("plugged")
+        );
+        assert_eq!(
+            name(Facts {
+                on_ac: Some(false),
+                ..docked
+            }),
+            Some("anywhere")
+        );
+    }
+}
@@ -0,0 +1,163 @@
+// Screen brightness for the brightness OSD and the quick-settings slider: the
+// first device under /sys/class/backlight, as a percentage of its
+// max_brightness. The result goes into the AppState, where both pick it up
+// (see osd.rs and quick_settings.rs). The slider sets it through logind's
+// SetBrightness, which the session may call without root.
+//
+// The kernel only notifies for hotkey changes handled by the firmware, and a
+// write to `brightness` (brightnessctl, light, a compositor binding) reports
+// nothing at all, so this polls like the lock key LEDs do.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tracing::{debug, info, warn};
+use zbus::{Connection, Proxy};
+
+use crate::bus::Bus;
+use crate::logind;
+
+const BACKLIGHT_ROOT: &str = "/sys/class/backlight";
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+fn read_value(path: &Path) -> Option<u32> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+// The first backlight by name, so the choice is stable across reads.
+fn first_device(root: &Path) -> Option<PathBuf> {
+    let mut devices: Vec<_> = std::fs::read_dir(root)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .collect();
+    devices.sort();
+    devices.into_iter().next()
+}
+
+// None without a backlight (a desktop) or a readable max.
+pub fn read_brightness(root: &Path) -> Option<u8> {
+    let device = first_device(root)?;
+    let max = read_value(&device.join("max_brightness")).filter(|max| *max > 0)?;
+    // actual_brightness is what the hardware reports; some drivers lack it.
+    let level = read_value(&device.join("actual_brightness"))
+        .or_else(|| read_value(&device.join("brightness")))?;
+    let percent = (u64::from(level.min(max)) * 100 + u64::from(max) / 2) / u64::from(max);
+    u8::try_from(percent).ok()
+}
+
+pub async fn run_backlight_monitor(bus: Bus) {
+    if read_brightness(Path::new(BACKLIGHT_ROOT)).is_none() {
+        info!(
+            "No backlight under {}; brightness stays unavailable",
+            BACKLIGHT_ROOT
+        );
+        return;
+    }
+    info!("Starting backlight monitor");
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    let mut last = None;
+    loop {
+        interval.tick().await;
+        let Some(percent) = read_brightness(Path::new(BACKLIGHT_ROOT)) else {
+            continue;
+        };
+        if last == Some(percent) {
+            continue;
+        }
+        last = Some(percent);
+        debug!(percent, "Backlight changed");
+        bus.state().set_brightness(percent);
+    }
+}
+
+// The raw value for `percent` of `max`, kept off zero: a black screen is
+// not what a slider dragged to the left means.
+fn raw_level(percent: u8, max: u32) -> u32 {
+    let raw = (u64::from(percent.min(100)) * u64::from(max) + 50) / 100;
+    u32::try_from(raw).unwrap_or(max).max(1)
+}
+
+async fn write_brightness(percent: u8) -> Result<()> {
+    let device = first_device(Path::new(BACKLIGHT_ROOT)).context("no backlight")?;
+    let name = device
+        .file_name()
+        .and_then(|name| name.to_str())
+        .context("backlight name")?
+        .to_string();
+    let max = read_value(&device.join("max_brightness"))
+        .filter(|max| *max > 0)
+        .context("read max_brightness")?;
+    let connection = Connection::system()
+        .await
+        .context("connect to system D-Bus")?;
+    let scope = logind::resolve_session_scope(&connection).await?;
+    let session = Proxy::new(
+        &connection,
+        logind::LOGIND,
+        scope.session_path.as_str(),
+        logind::SESSION_IFACE,
+    )
+    .await
+    .context("create logind session proxy")?;
+    let level = raw_level(percent, max);
+    debug!(device = name, level, percent, "Setting brightness");
+    session
+        .call::<_, _, ()>("SetBrightness", &("backlight", name.as_str(), level))
+        .await
+        .context("logind SetBrightness")?;
+    Ok(())
+}
+
+// Fire-and-forget from the GTK thread. The monitor's next poll reports the
+// new level.
+pub fn set_brightness(percent: u8) {
+    tokio::spawn(async move {
+        if let Err(e) = write_brightness(percent).await {
+            warn!(percent, "Failed to set the brightness: {:#}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn brightness_is_a_rounded_share_of_the_max() {
+        let root = TempDir::new("backlight");
+        for (device, max, actual) in [
+            ("intel_backlight", "19200\n", "9600\n"),
+            ("zz_other", "10\n", "1\n"),
+        ] {
+            std::fs::create_dir_all(root.join(device)).expect("create fake backlight");
+            std::fs::write(root.join(device).join("max_brightness"), max).expect("write max");
+            std::fs::write(root.join(device).join("actual_brightness"), actual)
+                .expect("write actual");
+        }
+        let half = read_brightness(&root);
+
+        std::fs::write(
+            root.join("intel_backlight").join("actual_brightness"),
+            "19199\n",
+        )
+        .expect("write actual");
+        let nearly_full = read_brightness(&root);
+        let path = root.to_path_buf();
+        drop(root);
+
+        assert_eq!(half, Some(50));
+        assert_eq!(nearly_full, Some(100));
+        assert_eq!(read_brightness(&path), None);
+    }
+
+    #[test]
+    fn raw_levels_round_and_never_reach_zero() {
+        assert_eq!(raw_level(50, 19200), 9600);
+        assert_eq!(raw_level(100, 19200), 19200);
+        assert_eq!(raw_level(0, 19200), 1);
+        assert_eq!(raw_level(33, 10), 3);
+    }
+}
@@ -0,0 +1,187 @@
+// Network throughput: receive and transmit rates of one interface, from the
+// byte counters in /proc/net/dev sampled every `[bandwidth] interval_seconds`.
+// The interface is `[bandwidth] interface` when set, otherwise whichever one
+// carries the default route right now (re-resolved on every sample, so
+// switching from Wi-Fi to a cable follows along). Without a default route the
+// widget hides.
+//
+// Like cpufreq.rs this polls; the counters are generated on read and never
+// block.
+
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use tracing::{debug, info, warn};
+
+use crate::bus::Bus;
+use crate::config::BandwidthConfig;
+use crate::format::Format;
+use crate::resume;
+
+pub const PROC_NET_DEV: &str = "/proc/net/dev";
+const PROC_NET_ROUTE: &str = "/proc/net/route";
+const DOWN_ICON: &str = "\u{f063}";
+const UP_ICON: &str = "\u{f062}";
+
+// Each interface with its received and transmitted byte counters.
+pub fn net_dev_counters(net_dev: &str) -> impl Iterator<Item = (&str, u64, u64)> {
+    net_dev.lines().filter_map(|line| {
+        let (interface, counters) = line.split_once(':')?;
+        let counters: Vec<u64> = counters
+            .split_whitespace()
+            .filter_map(|counter| counter.parse().ok())
+            .collect();
+        // rx bytes is the first column, tx bytes the ninth.
+        Some((interface.trim(), *counters.first()?, *counters.get(8)?))
+    })
+}
+
+// The interface of the lowest-metric default route that is up.
+pub fn default_route_interface(route: &str) -> Option<String> {
+    const RTF_UP: u32 = 0x1;
+    route
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (interface, destination, flags, metric) = (
+                fields.first()?,
+                fields.get(1)?,
+                fields.get(3)?,
+                fields.get(6)?,
+            );
+            let flags = u32::from_str_radix(flags, 16).ok()?;
+            let metric: u32 = metric.parse().ok()?;
+            (*destination == "00000000" && flags & RTF_UP != 0).then_some((metric, *interface))
+        })
+        .min_by_key(|(metric, _)| *metric)
+        .map(|(_, interface)| interface.to_string())
+}
+
+pub fn display_text(format: &Format, rx_per_second: f64, tx_per_second: f64) -> String {
+    format!(
+        "{DOWN_ICON}{} {UP_ICON}{}",
+        format.rate(rx_per_second),
+        format.rate(tx_per_second)
+    )
+}
+
+fn interface(config: &BandwidthConfig) -> Result<Option<String>> {
+    if let Some(interface) = &config.interface {
+        return Ok(Some(interface.clone()));
+    }
+    let route = std::fs::read_to_string(PROC_NET_ROUTE).context("read /proc/net/route")?;
+    Ok(default_route_interface(&route))
+}
+
+fn read_counters(interface: &str) -> Result<Option<(u64, u64)>> {
+    let net_dev = std::fs::read_to_string(PROC_NET_DEV).context("read /proc/net/dev")?;
+    Ok(net_dev_counters(&net_dev)
+        .find(|(name, _, _)| *name == interface)
+        .map(|(_, rx, tx)| (rx, tx)))
+}
+
+struct Sample {
+    interface: String,
+    rx: u64,
+    tx: u64,
+    at: Instant,
+}
+
+// None hides the widget. The first sample of an interface has nothing to
+// compare against and waits for the next.
+fn sample(
+    config: &BandwidthConfig,
+    format: &Format,
+    previous: &mut Option<Sample>,
+) -> Result<Option<String>> {
+    let Some(interface) = interface(config)? else {
+        *previous = None;
+        return Ok(None);
+    };
+    let Some((rx, tx)) = read_counters(&interface)? else {
+        *previous = None;
+        return Ok(None);
+    };
+    let current = Sample {
+        interface,
+        rx,
+        tx,
+        at: Instant::now(),
+    };
+    let text = previous
+        .as_ref()
+        .filter(|before| before.interface == current.interface)
+        .map(|before| {
+            let seconds = current
+                .at
+                .duration_since(before.at)
+                .as_secs_f64()
+                .max(f64::EPSILON);
+            // Counters reset when an interface is recreated; saturating
+            // reads that as one idle interval.
+            let rate = |before: u64, after: u64| after.saturating_sub(before) as f64 / seconds;
+            display_text(
+                format,
+                rate(before.rx, current.rx),
+                rate(before.tx, current.tx),
+            )
+        });
+    *previous = Some(current);
+    Ok(text)
+}
+
+pub async fn run_bandwidth_updates(bus: Bus, config: BandwidthConfig, format: Format) {
+    info!(interface = ?config.interface, "Starting bandwidth updates");
+    let period = Duration::from_secs(u64::from(config.interval_seconds.max(1)));
+    let mut interval = tokio::time::interval(period);
+    let mut resumes = bus.resumes();
+    let mut previous = None;
+    let mut last = None;
+    loop {
+        resume::tick_or_resume(&mut interval, &mut resumes).await;
+        let text = match sample(&config, &format, &mut previous) {
+            Ok(Some(text)) => text,
+            Ok(None) => String::new(),
+            Err(e) => {
+                debug!("Bandwidth unavailable: {:#}", e);
+                String::new()
+            }
+        };
+        if last.as_ref() == Some(&text) {
+            continue;
+        }
+        last = Some(text.clone());
+        if let Err(e) = bus.send_bandwidth_update(text) {
+            warn!("Bandwidth consumer is gone: {:#}", e);
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_route_prefers_the_lowest_metric() {
+        let route = "\
+Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+wlan0\t00000000\t0102A8C0\t0003\t0\t0\t600\t00000000\t0\t0\t0
+eth0\t00000000\t0101A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0
+eth0\t0001A8C0\t00000000\t0001\t0\t0\t100\t00FFFFFF\t0\t0\t0
+";
+        assert_eq!(default_route_interface(route).as_deref(), Some("eth0"));
+        assert_eq!(default_route_interface("Iface\tDestination\n"), None);
+    }
+
+    #[test]
+    fn rates_fit_in_a_fixed_width() {
+        let format = Format::default();
+        assert_eq!(format.rate(0.0), "   0B");
+        assert_eq!(format.rate(999.0), " 999B");
+        assert_eq!(format.rate(1536.0), " 1.5K");
+        assert_eq!(format.rate(350.0 * 1024.0), " 350K");
+        assert_eq!(format.rate(12.0 * 1024.0 * 1024.0), "  12M");
+    }
+}
@@ -0,0 +1,216 @@
+// Low-battery warnings. While the battery discharges, reaching `[battery]
+// warning_percent` or `critical_percent` marks the battery pill with the
+// `warning` or `critical` class and, with `notify`, posts a desktop
+// notification through the GApplication (org.freedesktop.Notifications, or
+// the portal inside a sandbox). Each level notifies once per discharge;
+// plugging in clears the classes and withdraws the notification.
+//
+// With `animate_charging` the icon cycles through `charging_frames` while the
+// battery charges, on a timer that only runs meanwhile.
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use gtk4::gio;
+use gtk4::glib;
+use gtk4::prelude::*;
+use tracing::info;
+
+use crate::bus::{BatteryState, BatteryUpdate};
+use crate::config::BatteryConfig;
+use crate::model::BarModel;
+
+// One id, so the critical notification replaces the warning one.
+const NOTIFICATION_ID: &str = "battery-low";
+const FRAME_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BatteryLevel {
+    #[default]
+    Normal,
+    Warning,
+    Critical,
+}
+
+pub fn level(update: &BatteryUpdate, config: &BatteryConfig) -> BatteryLevel {
+    let Some(percent) = update
+        .percent
+        .filter(|_| update.state == BatteryState::Discharging)
+    else {
+        return BatteryLevel::Normal;
+    };
+    if percent <= f64::from(config.critical_percent) {
+        BatteryLevel::Critical
+    } else if percent <= f64::from(config.warning_percent) {
+        BatteryLevel::Warning
+    } else {
+        BatteryLevel::Normal
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alert {
+    Notify(BatteryLevel),
+    Withdraw,
+}
+
+// The level the last update left behind, to tell which new ones are news.
+#[derive(Debug, Default)]
+pub struct BatteryAlerts {
+    last: BatteryLevel,
+}
+
+impl BatteryAlerts {
+    // Only a step down notifies: hovering around a threshold, or UPower
+    // revising the estimate upwards, stays quiet.
+    pub fn check(&mut self, level: BatteryLevel) -> Option<Alert> {
+        let last = std::mem::replace(&mut self.last, level);
+        if level > last {
+            Some(Alert::Notify(level))
+        } else if level == BatteryLevel::Normal && last != BatteryLevel::Normal {
+            Some(Alert::Withdraw)
+        } else {
+            None
+        }
+    }
+}
+
+pub fn send_alert(application: &gtk4::Application, alert: Alert, update: &BatteryUpdate) {
+    let level = match alert {
+        Alert::Notify(level) => level,
+        Alert::Withdraw => {
+            application.withdraw_notification(NOTIFICATION_ID);
+            return;
+        }
+    };
+    let percent = update.percent.unwrap_or_default();
+    info!(?level, percent, "Battery is low");
+    let (title, priority) = match level {
+        BatteryLevel::Critical => ("Battery critically low", gio::NotificationPriority::Urgent),
+        _ => ("Battery low", gio::NotificationPriority::High),
+    };
+    let notification = gio::Notification::new(title);
+    notification.set_body(Some(&format!("{percent:.0}% remaining")));
+    notification.set_priority(priority);
+    application.send_notification(Some(NOTIFICATION_ID), &notification);
+}
+
+pub fn charging_text(frame: &str, percent: f64) -> String {
+    format!("{frame} {percent:.0}%")
+}
+
+// The charging icon's timer. GTK main thread only.
+pub struct ChargingAnimation {
+    model: BarModel,
+    frames: Rc<Vec<String>>,
+    frame: Rc<Cell<usize>>,
+    percent: Rc<Cell<f64>>,
+    timer: Option<glib::SourceId>,
+}
+
+impl ChargingAnimation {
+    pub fn new(model: BarModel, config: &BatteryConfig) -> Self {
+        let frames = if config.animate_charging {
+            config.charging_frames.clone()
+        } else {
+            Vec::new()
+        };
+        Self {
+            model,
+            frames: Rc::new(frames),
+            frame: Rc::new(Cell::new(0)),
+            percent: Rc::new(Cell::new(0.0)),
+            timer: None,
+        }
+    }
+
+    // The label to show for the update: the current frame while charging,
+    // when the timer takes over, else the producer's own text.
+    pub fn text(&mut self, update: &BatteryUpdate) -> String {
+        let (BatteryState::Charging, Some(percent), Some(first)) =
+            (update.state, update.percent, self.frames.first())
+        else {
+            self.stop();
+            return update.text.clone();
+        };
+        self.percent.set(percent);
+        if self.timer.is_none() {
+            self.frame.set(0);
+            self.timer = Some(self.start());
+            return charging_text(first, percent);
+        }
+        charging_text(&self.frames[self.frame.get()], percent)
+    }
+
+    fn start(&self) -> glib::SourceId {
+        let model = self.model.clone();
+        let frames = self.frames.clone();
+        let frame = self.frame.clone();
+        let percent = self.percent.clone();
+        glib::timeout_add_local(FRAME_INTERVAL, move || {
+            let next = (frame.get() + 1) % frames.len();
+            frame.set(next);
+            model.set_battery(charging_text(&frames[next], percent.get()));
+            glib::ControlFlow::Continue
+        })
+    }
+
+    fn stop(&mut self) {
+        if let Some(timer) = self.timer.take() {
+            timer.remove();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn battery(percent: f64, discharging: bool) -> BatteryUpdate {
+        BatteryUpdate {
+            text: String::new(),
+            percent: Some(percent),
+            state: if discharging {
+                BatteryState::Discharging
+            } else {
+                BatteryState::Charging
+            },
+        }
+    }
+
+    #[test]
+    fn levels_need_a_draining_battery() {
+        let config = BatteryConfig::default();
+        assert_eq!(level(&battery(50.0, true), &config), BatteryLevel::Normal);
+        assert_eq!(level(&battery(20.0, true), &config), BatteryLevel::Warning);
+        assert_eq!(level(&battery(9.6, true), &config), BatteryLevel::Critical);
+        assert_eq!(level(&battery(5.0, false), &config), BatteryLevel::Normal);
+        assert_eq!(
+            level(&BatteryUpdate::default(), &config),
+            BatteryLevel::Normal
+        );
+        assert_eq!(charging_text("\u{f0086}", 19.6), "\u{f0086} 20%");
+    }
+
+    #[test]
+    fn each_level_alerts_once_per_discharge() {
+        let mut alerts = BatteryAlerts::default();
+        assert_eq!(alerts.check(BatteryLevel::Normal), None);
+        assert_eq!(
+            alerts.check(BatteryLevel::Warning),
+            Some(Alert::Notify(BatteryLevel::Warning))
+        );
+        assert_eq!(alerts.check(BatteryLevel::Warning), None);
+        assert_eq!(
+            alerts.check(BatteryLevel::Critical),
+            Some(Alert::Notify(BatteryLevel::Critical))
+        );
+        assert_eq!(alerts.check(BatteryLevel::Warning), None);
+        assert_eq!(alerts.check(BatteryLevel::Normal), Some(Alert::Withdraw));
+        assert_eq!(
+            alerts.check(BatteryLevel::Warning),
+            Some(Alert::Notify(BatteryLevel::Warning))
+        );
+    }
+}
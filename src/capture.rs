@@ -0,0 +1,191 @@
+// Screenshots and screen recordings from the capture widget. A click runs
+// `[capture] screenshot`; a right click starts `[capture] record`, and either
+// click stops it again. The indicator follows the recorder's own process: it
+// shows ⏺ and the elapsed time from the spawn until that child exits, however
+// it ends, so a recorder that never gets going (slurp cancelled with Escape)
+// clears the indicator at once and one killed from a terminal does too.
+//
+// Stopping sends SIGINT to the recorder's process group, which is what
+// wf-recorder expects (it is Ctrl-C in a terminal) before it finalizes the
+// file. The group is the recorder's own, so the signal reaches the command
+// behind `sh -c` and nothing of the bar's; it also means a recording outlives
+// a bar that quits.
+
+use std::cell::{Cell, RefCell};
+use std::os::unix::process::CommandExt;
+use std::process::Stdio;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use gtk4::glib;
+use tokio::process::Command;
+use tokio::sync::oneshot;
+use tracing::{error, info, warn};
+
+use crate::command;
+use crate::model::BarModel;
+
+const IDLE_TEXT: &str = "📷";
+const ELAPSED_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy)]
+struct Recording {
+    pid: u32,
+    started: Instant,
+}
+
+struct Inner {
+    model: BarModel,
+    recording: Cell<Option<Recording>>,
+    timer: RefCell<Option<glib::SourceId>>,
+}
+
+// Shared by the widget's click handlers, so the recording carries over a
+// rebuilt bar. GTK main thread only; cheap to clone.
+#[derive(Clone)]
+pub struct Capture {
+    inner: Rc<Inner>,
+}
+
+impl Capture {
+    pub fn new(model: BarModel) -> Self {
+        model.set_capture(IDLE_TEXT);
+        Self {
+            inner: Rc::new(Inner {
+                model,
+                recording: Cell::new(None),
+                timer: RefCell::new(None),
+            }),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.inner.recording.get().is_some()
+    }
+
+    pub fn screenshot(&self, command: &str) {
+        command::spawn_shell(command, "screenshot");
+    }
+
+    pub fn toggle_recording(&self, command: &str) {
+        if self.is_recording() {
+            self.stop_recording();
+        } else {
+            self.start_recording(command);
+        }
+    }
+
+    fn start_recording(&self, command: &str) {
+        let mut process = std::process::Command::new("sh");
+        process
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::null())
+            .process_group(0);
+        let mut child = match Command::from(process).spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                error!(command, "Failed to launch recorder: {}", e);
+                return;
+            }
+        };
+        // Only None once the child has been waited for, which it has not.
+        let Some(pid) = child.id() else {
+            warn!(command, "Recorder exited before it could be tracked");
+            return;
+        };
+        info!(pid, command, "Recording started");
+        let started = Instant::now();
+        self.inner.recording.set(Some(Recording { pid, started }));
+        self.inner.model.set_capture_recording(true);
+        self.inner.model.set_capture(recording_text(Duration::ZERO));
+        let model = self.inner.model.clone();
+        self.inner
+            .timer
+            .replace(Some(glib::timeout_add_local(ELAPSED_INTERVAL, move || {
+                model.set_capture(recording_text(started.elapsed()));
+                glib::ControlFlow::Continue
+            })));
+
+        // The wait needs tokio's reactor; the result comes back to the main
+        // thread through the oneshot.
+        let (exited_tx, exited_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let status = child.wait().await.map(|status| status.to_string());
+            let _ = exited_tx.send(status);
+        });
+        let capture = self.clone();
+        glib::spawn_future_local(async move {
+            match exited_rx.await {
+                Ok(Ok(status)) => info!(pid, %status, "Recorder exited"),
+                Ok(Err(e)) => error!(pid, "Failed to wait for recorder: {}", e),
+                Err(_) => warn!(pid, "Lost track of the recorder"),
+            }
+            capture.recording_ended(pid);
+        });
+    }
+
+    fn stop_recording(&self) {
+        let Some(recording) = self.inner.recording.get() else {
+            return;
+        };
+        info!(pid = recording.pid, "Stopping recording");
+        let group = format!("-{}", recording.pid);
+        tokio::spawn(async move {
+            match Command::new("kill")
+                .args(["-s", "INT", "--", &group])
+                .status()
+                .await
+            {
+                Ok(status) if status.success() => {}
+                Ok(status) => warn!(group, %status, "kill failed"),
+                Err(e) => error!(group, "Failed to run kill: {}", e),
+            }
+        });
+    }
+
+    // Only the recording that ended clears the indicator: a stop and a quick
+    // restart can see the old recorder exit after the new one started.
+    fn recording_ended(&self, pid: u32) {
+        if self
+            .inner
+            .recording
+            .get()
+            .is_none_or(|recording| recording.pid != pid)
+        {
+            return;
+        }
+        self.inner.recording.set(None);
+        if let Some(timer) = self.inner.timer.take() {
+            timer.remove();
+        }
+        self.inner.model.set_capture_recording(false);
+        self.inner.model.set_capture(IDLE_TEXT);
+    }
+}
+
+fn recording_text(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    if seconds >= 3600 {
+        format!(
+            "⏺ {}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("⏺ {:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elapsed_time_grows_an_hour_field() {
+        assert_eq!(recording_text(Duration::ZERO), "⏺ 00:00");
+        assert_eq!(recording_text(Duration::from_secs(61)), "⏺ 01:01");
+        assert_eq!(recording_text(Duration::from_secs(3723)), "⏺ 1:02:03");
+    }
+}
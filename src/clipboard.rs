@@ -0,0 +1,122 @@
+// Clipboard history: the number of entries `[clipboard] count_command` reports
+// (cliphist's list by default), recounted whenever `watch_command` prints a
+// line. The default watch is `wl-paste --watch echo`, which prints one per
+// clipboard change, so nothing polls. Clicking the widget runs `picker`.
+//
+// The history itself is kept by whatever stores it (`wl-paste --watch
+// cliphist store` in the compositor's autostart); the bar only reads it. That
+// store runs on the same clipboard change as our watch, so each recount waits
+// a moment for it to finish and for a burst of changes to settle.
+//
+// The count follows the updates checker's rule: the number of non-empty
+// lines, or the number itself when the command prints a single integer.
+
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tracing::{debug, info, warn};
+
+use crate::bus::Bus;
+use crate::config::ClipboardConfig;
+use crate::dbus::supervise;
+use crate::updates::count_updates;
+
+const ICON: &str = "📋";
+const SETTLE_DELAY: Duration = Duration::from_millis(250);
+const COUNT_DEADLINE: Duration = Duration::from_secs(10);
+
+pub fn display_text(count: usize) -> String {
+    format!("{ICON} {count}")
+}
+
+async fn count_entries(command: &str) -> Result<usize> {
+    let output = Command::new("sh")
+        .kill_on_drop(true)
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .output();
+    let output = tokio::time::timeout(COUNT_DEADLINE, output)
+        .await
+        .context("clipboard count exceeded its deadline")?
+        .context("launch clipboard count")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!(
+            "clipboard count failed ({}): {}",
+            output.status,
+            stderr.trim()
+        );
+    }
+    Ok(count_updates(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// Send the count when it changed. A failed count keeps the last one on
+// screen; only a consumer that went away is an error.
+async fn refresh(bus: &Bus, config: &ClipboardConfig, last: &mut Option<usize>) -> Result<()> {
+    let count = match count_entries(&config.count_command).await {
+        Ok(count) => count,
+        Err(e) => {
+            warn!(
+                command = config.count_command,
+                "Clipboard count failed: {:#}", e
+            );
+            return Ok(());
+        }
+    };
+    debug!(count, "Counted clipboard entries");
+    if *last == Some(count) {
+        return Ok(());
+    }
+    *last = Some(count);
+    bus.send_clipboard_update(display_text(count))
+}
+
+async fn watch_clipboard(bus: &Bus, config: &ClipboardConfig) -> Result<()> {
+    let mut child = Command::new("sh")
+        .kill_on_drop(true)
+        .arg("-c")
+        .arg(&config.watch_command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("launch clipboard watch")?;
+    let stdout = child
+        .stdout
+        .take()
+        .context("clipboard watch has no stdout")?;
+    let mut lines = BufReader::new(stdout).lines();
+
+    let mut last = None;
+    refresh(bus, config, &mut last).await?;
+    while lines
+        .next_line()
+        .await
+        .context("read clipboard watch")?
+        .is_some()
+    {
+        tokio::time::sleep(SETTLE_DELAY).await;
+        refresh(bus, config, &mut last).await?;
+    }
+    let status = child.wait().await.context("wait for clipboard watch")?;
+    bail!("clipboard watch exited ({status})")
+}
+
+pub async fn run_clipboard_monitor_supervised(bus: Bus, config: ClipboardConfig) {
+    info!(command = config.watch_command, "Watching the clipboard");
+    supervise("clipboard watch", || watch_clipboard(&bus, &config)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_empty_history_still_shows() {
+        assert_eq!(display_text(0), "📋 0");
+        assert_eq!(display_text(count_updates("3\tfoo\n2\tbar\n")), "📋 2");
+    }
+}
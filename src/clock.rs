@@ -1,19 +1,29 @@
+use std::cell::Cell;
 use std::rc::Rc;
 use std::time::Duration;
 
 use chrono::{DateTime, Local, Timelike};
 use gtk4::glib;
+use tokio::sync::watch;
+use tracing::debug;
 
 type Callback = Box<dyn Fn(DateTime<Local>) + 'static>;
 
+// strftime conversions that print seconds, alone or as part of a composite.
+const SECOND_CONVERSIONS: [char; 6] = ['S', 'T', 'X', 'r', 'c', 's'];
+
 pub struct Clock {
     second_subscribers: Vec<Callback>,
+    minute_subscribers: Vec<Callback>,
+    resumes: Option<watch::Receiver<u64>>,
 }
 
 impl Clock {
     pub fn new() -> Self {
         Self {
             second_subscribers: Vec::new(),
+            minute_subscribers: Vec::new(),
+            resumes: None,
         }
     }
 
@@ -22,27 +32,127 @@ impl Clock {
         self
     }
 
-    /// Start dispatching on the GTK main thread at wall-clock second boundaries.
+    pub fn on_minute(mut self, callback: impl Fn(DateTime<Local>) + 'static) -> Self {
+        self.minute_subscribers.push(Box::new(callback));
+        self
+    }
+
+    // glib's timeouts run on the monotonic clock, which stops during
+    // suspend: a wait armed before the lid closed would end late by however
+    // long the machine slept. Each resume re-reads the wall clock and
+    // re-arms the timers instead.
+    pub fn resync_on(mut self, resumes: watch::Receiver<u64>) -> Self {
+        self.resumes = Some(resumes);
+        self
+    }
+
+    /// Start dispatching on the GTK main thread at wall-clock second and
+    /// minute boundaries. Without second subscribers nothing wakes every
+    /// second.
     pub fn start(self) {
-        let subscribers = Rc::new(self.second_subscribers);
-        dispatch_and_schedule(subscribers);
+        let tickers: Vec<Rc<Ticker>> = [
+            (self.second_subscribers, true),
+            (self.minute_subscribers, false),
+        ]
+        .into_iter()
+        .filter(|(subscribers, _)| !subscribers.is_empty())
+        .map(|(subscribers, every_second)| {
+            Rc::new(Ticker {
+                subscribers,
+                every_second,
+                last_minute: Cell::new(None),
+                timer: Cell::new(None),
+            })
+        })
+        .collect();
+        for ticker in &tickers {
+            ticker.tick();
+        }
+
+        let Some(mut resumes) = self.resumes else {
+            return;
+        };
+        glib::spawn_future_local(async move {
+            while resumes.changed().await.is_ok() {
+                debug!("Resumed from sleep; re-reading the clock");
+                for ticker in &tickers {
+                    ticker.restart();
+                }
+            }
+        });
     }
 }
 
-fn dispatch_and_schedule(subscribers: Rc<Vec<Callback>>) {
-    let now = Local::now();
-    for callback in subscribers.iter() {
-        callback(now);
+struct Ticker {
+    subscribers: Vec<Callback>,
+    every_second: bool,
+    // Minute tickers only run their subscribers when this changes, so a
+    // resync inside the same minute is silent.
+    last_minute: Cell<Option<i64>>,
+    timer: Cell<Option<glib::SourceId>>,
+}
+
+impl Ticker {
+    fn tick(self: &Rc<Self>) {
+        let now = Local::now();
+        let minute = now.timestamp().div_euclid(60);
+        if self.every_second || self.last_minute.replace(Some(minute)) != Some(minute) {
+            for callback in &self.subscribers {
+                callback(now);
+            }
+        }
+
+        let delay = if self.every_second {
+            delay_until_next_second(now.nanosecond())
+        } else {
+            delay_until_next_minute(now.second(), now.nanosecond())
+        };
+        let ticker = self.clone();
+        self.timer
+            .set(Some(glib::timeout_add_local_once(delay, move || {
+                // Fired, so there is nothing left to remove.
+                ticker.timer.take();
+                ticker.tick();
+            })));
     }
 
-    let delay = delay_until_next_second(now.nanosecond());
-    glib::timeout_add_local_once(delay, move || dispatch_and_schedule(subscribers));
+    fn restart(self: &Rc<Self>) {
+        if let Some(timer) = self.timer.take() {
+            timer.remove();
+        }
+        self.tick();
+    }
 }
 
 fn delay_until_next_second(nanosecond: u32) -> Duration {
     Duration::from_millis(1_000 - u64::from(nanosecond / 1_000_000))
 }
 
+// A leap second (second 60) counts as the last of its minute.
+fn delay_until_next_minute(second: u32, nanosecond: u32) -> Duration {
+    let remaining = 59u32.saturating_sub(second);
+    Duration::from_secs(u64::from(remaining)) + delay_until_next_second(nanosecond)
+}
+
+// True when `format` (strftime, as chrono reads it) shows seconds, so the
+// clock needs to tick every second rather than every minute.
+pub fn format_has_seconds(format: &str) -> bool {
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            continue;
+        }
+        // Skip chrono's padding and width modifiers: %-S, %_S, %0S, %3f.
+        let conversion = chars
+            .by_ref()
+            .find(|c| !matches!(c, '-' | '_' | '0'..='9' | '.' | ':'));
+        if conversion.is_some_and(|c| SECOND_CONVERSIONS.contains(&c)) {
+            return true;
+        }
+    }
+    false
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -59,4 +169,24 @@ mod tests {
             Duration::from_millis(1)
         );
     }
+
+    #[test]
+    fn next_minute_delay_lands_on_the_boundary() {
+        assert_eq!(delay_until_next_minute(0, 0), Duration::from_secs(60));
+        assert_eq!(
+            delay_until_next_minute(59, 250_000_000),
+            Duration::from_millis(750)
+        );
+        assert_eq!(delay_until_next_minute(60, 0), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn only_second_conversions_need_a_second_tick() {
+        assert!(!format_has_seconds("%l:%M %p"));
+        assert!(!format_has_seconds("%a %d %b %H:%M"));
+        assert!(!format_has_seconds("100%% %M"));
+        assert!(format_has_seconds("%H:%M:%S"));
+        assert!(format_has_seconds("%T"));
+        assert!(format_has_seconds("%-S"));
+    }
 }
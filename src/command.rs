@@ -0,0 +1,39 @@
+// User-configured shell commands (display profiles and the like). The bar never
+// waits on them: each command runs through `sh -c` on a tokio task that only
+// reaps the child and logs how it ended, so a slow or hanging command can't
+// stall the GTK main loop and a finished one never lingers as a zombie.
+
+use std::process::Stdio;
+
+use tokio::process::Command;
+use tracing::{debug, error, info, warn};
+
+// Spawn `command` with the user's shell semantics (pipes, quoting, ~). `what`
+// names the command's purpose in the logs. Must be called with the tokio
+// runtime entered, which is true on the GTK main thread (main() enters it
+// before running the application).
+pub fn spawn_shell(command: &str, what: &str) {
+    info!(what, command, "Running command");
+    let mut child = match Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            error!(what, command, "Failed to launch command: {}", e);
+            return;
+        }
+    };
+
+    let what = what.to_string();
+    let command = command.to_string();
+    tokio::spawn(async move {
+        match child.wait().await {
+            Ok(status) if status.success() => debug!(what, command, "Command finished"),
+            Ok(status) => warn!(what, command, %status, "Command failed"),
+            Err(e) => error!(what, command, "Failed to wait for command: {}", e),
+        }
+    });
+}
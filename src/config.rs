@@ -0,0 +1,1453 @@
+// User configuration: an optional TOML file for settings that are awkward as
+// repeatable CLI flags. The CLI stays the place for per-launch knobs (monitor,
+// network timings); this file holds the durable look-and-feel choices.
+//
+// Lookup order: an explicit `--config PATH` must exist and parse, otherwise
+// startup fails loudly. Without the flag we read
+// `$XDG_CONFIG_HOME/gtk-status-bar/config.toml` (falling back to
+// `~/.config/...`); a missing default file is normal and yields defaults, but a
+// present-and-broken one is still an error rather than a silent reset.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use tracing::{debug, info};
+
+const CONFIG_DIR_NAME: &str = "gtk-status-bar";
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+// Tokyo Night teal: distinct from every entry of the numeric workspace palette
+// so scratchpad mode is recognizable at a glance.
+pub const DEFAULT_SPECIAL_WORKSPACE_COLOR: &str = "rgba(115, 218, 202, 0.5)";
+// nf-md-battery_charging_10 up to _100.
+const DEFAULT_CHARGING_FRAMES: [&str; 10] = [
+    "\u{f089c}",
+    "\u{f0086}",
+    "\u{f0087}",
+    "\u{f0088}",
+    "\u{f089d}",
+    "\u{f0089}",
+    "\u{f089e}",
+    "\u{f008a}",
+    "\u{f008b}",
+    "\u{f0085}",
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub bar: BarConfig,
+    pub workspace: WorkspaceConfig,
+    pub layout: LayoutConfig,
+    /// Spacing and separators of the built-in layout's groups:
+    /// `[groups.right]`.
+    pub groups: GroupsConfig,
+    pub displays: DisplaysConfig,
+    pub clock: ClockConfig,
+    pub pomodoro: PomodoroConfig,
+    pub title: TitleConfig,
+    pub cpufreq: CpuFreqConfig,
+    pub vpn: VpnConfig,
+    pub power: PowerConfig,
+    pub capture: CaptureConfig,
+    pub units: UnitsConfig,
+    pub updates: UpdatesConfig,
+    pub clipboard: ClipboardConfig,
+    pub gpu: GpuConfig,
+    pub loadavg: LoadAvgConfig,
+    pub battery: BatteryConfig,
+    pub bandwidth: BandwidthConfig,
+    pub osd: OsdConfig,
+    pub notifications: NotificationsConfig,
+    pub format: FormatConfig,
+    pub hooks: HooksConfig,
+    pub taskbar: TaskbarConfig,
+    pub windows: WindowsConfig,
+    pub recorder: RecorderConfig,
+    pub metrics: MetricsConfig,
+    pub file_watch: Vec<FileWatchConfig>,
+    /// Rules that rewrite the window title before it is cut to length, first
+    /// match wins: `[[title_rewrite]]`.
+    pub title_rewrite: Vec<TitleRewriteRule>,
+    /// Rules applied on dock and undock, first match wins: `[[auto_profile]]`.
+    pub auto_profile: Vec<AutoProfileRule>,
+    /// Sizing and click commands of individual widgets, keyed by widget (or
+    /// file watch) name: `[widgets.title]`.
+    pub widgets: BTreeMap<String, WidgetSettings>,
+    /// Named colors for the stylesheet, as `@define-color NAME VALUE`; they
+    /// override the built-in palette and the stylesheet's own: `[colors]`.
+    pub colors: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BarConfig {
+    /// Slide the bar out of sight (without reserving space) until the pointer
+    /// touches the top edge. Read at startup.
+    pub auto_hide: bool,
+    /// Workspace ids on which the bar unmaps entirely, giving up its
+    /// exclusive zone (a fullscreen game on 9, say).
+    pub hidden_workspaces: Vec<i32>,
+    /// Built-in widgets left out of the layout, e.g. `["volume", "network"]`.
+    /// A backend whose widgets are all left out is never started. Read at
+    /// startup.
+    pub disabled_widgets: Vec<String>,
+    /// Screen edge the bar sits on. On the left or right edge the built-in
+    /// layout runs top to bottom, without the title. A reload moves the bar
+    /// unless `auto_hide` is on.
+    pub position: Position,
+    /// Stylesheet preset layered over the stylesheet. Read at startup.
+    pub theme: Theme,
+    /// Detached from the screen edges: `[bar.floating]`.
+    pub floating: FloatingConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FloatingConfig {
+    /// Keep a gap between the bar and the screen edges, round its corners
+    /// and reserve no space, so windows reach under it. Ignored with
+    /// `auto_hide`. Read at startup.
+    pub enabled: bool,
+    /// Pixels between the bar and the edge it sits on.
+    pub edge_margin: u16,
+    /// Pixels between the bar and the edges at its ends: left and right, or
+    /// top and bottom for a side bar.
+    pub side_margin: u16,
+    /// Pixels of corner rounding on the bar's background.
+    pub corner_radius: u16,
+}
+
+impl Default for FloatingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            edge_margin: 8,
+            side_margin: 12,
+            corner_radius: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Position {
+    #[default]
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+impl Position {
+    pub fn is_vertical(self) -> bool {
+        matches!(self, Position::Left | Position::Right)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// The stylesheet alone: translucent pills.
+    #[default]
+    Default,
+    /// Opaque pills, for busy wallpapers.
+    Solid,
+    /// Dark text on light pills.
+    Light,
+}
+
+impl BarConfig {
+    pub fn is_disabled(&self, widget: &str) -> bool {
+        self.disabled_widgets.iter().any(|name| name == widget)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WidgetSettings {
+    /// Pixels the widget never shrinks below, so a changing label does not
+    /// shift its neighbours.
+    pub min_width: Option<u16>,
+    /// Characters after which the widget's text is ellipsized, so a long
+    /// title cannot push the right group off a narrow output.
+    pub max_width: Option<u16>,
+    /// Placement within the widget's slot.
+    pub align: Option<Align>,
+    /// Shell command run on a left click, in place of the widget's own click
+    /// action.
+    pub on_click: Option<String>,
+    pub on_middle_click: Option<String>,
+    pub on_right_click: Option<String>,
+    /// Shell commands run per scroll step, in place of the widget's own
+    /// scrolling in that direction.
+    pub on_scroll_up: Option<String>,
+    pub on_scroll_down: Option<String>,
+    /// Collapse the widget to a compact form and slide the full text open
+    /// while the pointer is over it. Built-in widgets only.
+    pub reveal_on_hover: bool,
+    /// The compact form's text; the full text's first word (usually its
+    /// icon) when unset.
+    pub compact: Option<String>,
+}
+
+impl WidgetSettings {
+    // Each configured command with its key, for validation.
+    fn commands(&self) -> [(&'static str, Option<&str>); 5] {
+        [
+            ("on_click", self.on_click.as_deref()),
+            ("on_middle_click", self.on_middle_click.as_deref()),
+            ("on_right_click", self.on_right_click.as_deref()),
+            ("on_scroll_up", self.on_scroll_up.as_deref()),
+            ("on_scroll_down", self.on_scroll_down.as_deref()),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Align {
+    Start,
+    Center,
+    End,
+    Fill,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GroupsConfig {
+    pub left: GroupConfig,
+    pub right: GroupConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GroupConfig {
+    /// Pixels between neighbouring widgets, on top of their CSS margins.
+    pub spacing: u16,
+    /// Pixels before the group's first widget and after its last.
+    pub padding: u16,
+    /// What sits between neighbouring widgets that are showing.
+    pub separator: SeparatorStyle,
+    /// The separator's text when `separator = "text"`.
+    pub separator_text: String,
+}
+
+impl Default for GroupConfig {
+    fn default() -> Self {
+        Self {
+            spacing: 0,
+            padding: 0,
+            separator: SeparatorStyle::None,
+            separator_text: "|".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SeparatorStyle {
+    #[default]
+    None,
+    /// A label with `separator_text`.
+    Text,
+    /// A GtkSeparator line, themed through `.widget-separator`.
+    Divider,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkspaceConfig {
+    /// Title-pill background while a special (scratchpad) workspace is shown.
+    /// Any GTK CSS color expression is accepted.
+    pub special_color: String,
+    /// Labels or icons shown in place of "Workspace <name>", keyed by
+    /// workspace name or id; special workspaces as "special:<name>".
+    pub labels: BTreeMap<String, String>,
+    /// Title-pill backgrounds keyed by workspace id, in place of the
+    /// palette's `@workspace_<id>`. Color variables such as `@red` work.
+    pub colors: BTreeMap<String, String>,
+    /// Scrolling anywhere on the bar switches workspaces, not only over the
+    /// workspace widget.
+    pub scroll_bar: bool,
+    /// Scrolling past the last workspace comes around to the first, and the
+    /// other way.
+    pub scroll_wrap: bool,
+    /// Scroll only through workspaces that exist, rather than by number
+    /// into empty ones.
+    pub scroll_skip_empty: bool,
+    /// Special workspaces the scratchpad widget has a toggle for, by name
+    /// without "special:". Buttons take their text from `labels`. The
+    /// default is the one Hyprland's example config binds.
+    pub scratchpads: Vec<String>,
+}
+
+impl Default for WorkspaceConfig {
+    fn default() -> Self {
+        Self {
+            special_color: DEFAULT_SPECIAL_WORKSPACE_COLOR.to_string(),
+            labels: BTreeMap::new(),
+            colors: BTreeMap::new(),
+            scroll_bar: false,
+            scroll_wrap: false,
+            scroll_skip_empty: true,
+            scratchpads: vec!["magic".to_string()],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutConfig {
+    /// GtkBuilder .ui file replacing the built-in layout. A relative path is
+    /// resolved against the directory holding config.toml.
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplaysConfig {
+    /// Entries of the displays widget's popover, in order.
+    pub profiles: Vec<DisplayProfile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DisplayProfile {
+    pub name: String,
+    /// Run through `sh -c`, e.g. `kanshictl switch docked`.
+    pub command: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AutoProfileRule {
+    /// For the log.
+    pub name: String,
+    /// Connected monitors the rule needs, inclusive. Either bound may be left
+    /// out.
+    #[serde(default)]
+    pub min_monitors: Option<u32>,
+    #[serde(default)]
+    pub max_monitors: Option<u32>,
+    /// true for on AC only, false for on battery only.
+    #[serde(default)]
+    pub on_ac: Option<bool>,
+    /// `[[displays.profiles]]` entry whose command runs when the rule starts
+    /// matching.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// Run through `sh -c` when the rule starts matching, after the profile.
+    #[serde(default)]
+    pub hook: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClockConfig {
+    /// strftime format of the clock widget. Without seconds in it the clock
+    /// only wakes once a minute. Read at startup.
+    pub format: String,
+    /// Run through `sh -c` when an alarm rings, e.g.
+    /// `canberra-gtk-play -i alarm-clock-elapsed` or `pw-play ~/alarm.oga`.
+    /// Without it alarms only notify.
+    pub alarm_sound: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PomodoroConfig {
+    pub work_minutes: u64,
+    pub break_minutes: u64,
+    /// Hyprland keywords applied for the length of each work phase and put
+    /// back when it ends, e.g. `"general:gaps_in" = "0"`. Read at startup.
+    pub focus_keywords: BTreeMap<String, String>,
+}
+
+impl Default for ClockConfig {
+    fn default() -> Self {
+        Self {
+            format: "%l:%M %p".to_string(),
+            alarm_sound: None,
+        }
+    }
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        Self {
+            work_minutes: 25,
+            break_minutes: 5,
+            focus_keywords: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TitleConfig {
+    /// Characters the title is cut to, the ellipsis included. A flag, an
+    /// emoji sequence or a letter with its accents counts as one.
+    pub max_chars: u16,
+    /// Pixels the title never grows past; GTK ellipsizes it beyond that.
+    pub max_width_px: Option<u16>,
+    /// Which part of a long title gives way to the ellipsis.
+    pub ellipsize: Ellipsize,
+    /// Leave the cutting to GTK, with `max_chars` as the label's maximum
+    /// width, so the title shortens by rendered width rather than character
+    /// count and follows the bar's size.
+    pub native: bool,
+}
+
+impl Default for TitleConfig {
+    fn default() -> Self {
+        Self {
+            max_chars: 64,
+            max_width_px: None,
+            ellipsize: Ellipsize::Middle,
+            native: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TitleRewriteRule {
+    /// Regex the window class (app_id) must match; any class when left out.
+    #[serde(default)]
+    pub class: Option<String>,
+    /// Regex replaced within the title; without it the whole title is
+    /// replaced.
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// Replacement text, with `$1` or `${name}` for the pattern's captures.
+    pub replace: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ellipsize {
+    Start,
+    #[default]
+    Middle,
+    End,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TaskbarConfig {
+    /// Place the taskbar after the split hint in the built-in layout. A custom
+    /// layout places it with a `taskbar` slot instead. Read at startup.
+    pub enabled: bool,
+    /// List the windows of every workspace instead of only the one on screen.
+    pub all_workspaces: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowsConfig {
+    /// Where the title and taskbar widgets learn about windows. Read at
+    /// startup.
+    pub backend: WindowBackend,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WindowBackend {
+    /// `compositor` under Hyprland, otherwise `wlr`.
+    #[default]
+    Auto,
+    /// The compositor's own IPC (Hyprland, Sway or i3). Only Hyprland's
+    /// feeds the taskbar.
+    #[serde(alias = "hyprland")]
+    Compositor,
+    /// The wlr-foreign-toplevel-management protocol (Sway, river, labwc,
+    /// Wayfire, ...). Its windows carry no workspace, so the taskbar lists
+    /// them all.
+    Wlr,
+}
+
+impl WindowBackend {
+    // Never Auto.
+    pub fn resolve(self, on_hyprland: bool) -> Self {
+        match self {
+            Self::Auto if on_hyprland => Self::Compositor,
+            Self::Auto => Self::Wlr,
+            backend => backend,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CpuFreqConfig {
+    /// Run through `sh -c` when the widget is clicked, with `{}` replaced by
+    /// the next governor. Writing the governor needs root, hence pkexec.
+    pub governor_command: String,
+}
+
+impl Default for CpuFreqConfig {
+    fn default() -> Self {
+        Self {
+            governor_command: "pkexec cpupower frequency-set --governor {}".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VpnConfig {
+    /// NetworkManager connection (by name, as `nmcli connection show` lists
+    /// it) that clicking the indicator brings up or down. Without it the
+    /// indicator only shows.
+    pub connection: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PowerConfig {
+    /// Ask once more before logging out, rebooting or shutting down.
+    pub confirm: bool,
+    /// Run through `sh -c` instead of asking logind, e.g. `hyprlock`. Without
+    /// it logind tells the session's idle daemon (hypridle, swayidle) to lock.
+    pub lock: Option<String>,
+    /// Instead of ending the logind session, e.g. `hyprctl dispatch exit`.
+    pub logout: Option<String>,
+    pub suspend: Option<String>,
+    pub reboot: Option<String>,
+    pub shutdown: Option<String>,
+}
+
+impl Default for PowerConfig {
+    fn default() -> Self {
+        Self {
+            confirm: true,
+            lock: None,
+            logout: None,
+            suspend: None,
+            reboot: None,
+            shutdown: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CaptureConfig {
+    /// Run through `sh -c` on a click. The default copies a selected region
+    /// to the clipboard.
+    pub screenshot: String,
+    /// Run through `sh -c` on a right click and interrupted (SIGINT) by the
+    /// next click; the widget shows a recording for as long as it runs.
+    pub record: String,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            screenshot: r#"grim -g "$(slurp)" - | wl-copy"#.to_string(),
+            record: r#"wf-recorder -g "$(slurp)" -f "$HOME/Videos/recording-$(date +%Y%m%d-%H%M%S).mp4""#
+                .to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UnitsConfig {
+    /// System units whose failure the health badge reports, by full name
+    /// (`NetworkManager.service`). Read at startup.
+    pub system: Vec<String>,
+    /// The same for units of the user's own systemd instance.
+    pub user: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UpdatesConfig {
+    /// Run through `sh -c` to list pending updates, one per line (or print
+    /// their number). The updates widget stays hidden without it. Read at
+    /// startup.
+    pub command: String,
+    pub interval_minutes: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClipboardConfig {
+    /// Run through `sh -c` to list the history, one entry per line (or print
+    /// their number). Read at startup.
+    pub count_command: String,
+    /// Run through `sh -c` for as long as the bar runs; every line it prints
+    /// recounts the history. Read at startup.
+    pub watch_command: String,
+    /// Run through `sh -c` when the widget is clicked.
+    pub picker: String,
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            count_command: "cliphist list".to_string(),
+            watch_command: "wl-paste --watch echo".to_string(),
+            picker: "cliphist list | fuzzel --dmenu | cliphist decode | wl-copy".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GpuConfig {
+    pub interval_seconds: u32,
+    /// Utilization, in percent, from which the widget shows as a warning.
+    pub warning_busy_percent: u32,
+    /// Temperature, in °C, from which the widget shows as a warning.
+    pub warning_temperature: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoadAvgConfig {
+    /// One-minute load per CPU, in percent, from which the widget shows as a
+    /// warning.
+    pub warning_percent: u32,
+    /// The same for the critical style; 100 is every CPU busy.
+    pub critical_percent: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BatteryConfig {
+    /// Charge, in percent, at or below which a discharging battery shows as
+    /// a warning. Read at startup.
+    pub warning_percent: u8,
+    /// The same for the critical style.
+    pub critical_percent: u8,
+    /// Post a desktop notification on reaching either level; it is
+    /// withdrawn once the battery charges.
+    pub notify: bool,
+    /// Cycle the icon through `charging_frames` while charging.
+    pub animate_charging: bool,
+    /// Icons shown in turn, twice a second; Nerd Font battery glyphs filling
+    /// up by default.
+    pub charging_frames: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BandwidthConfig {
+    /// Interface to measure, e.g. `wlan0`; by default the one carrying the
+    /// default route.
+    pub interface: Option<String>,
+    pub interval_seconds: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OsdConfig {
+    /// Pop up the volume and a level bar over the screen whenever it
+    /// changes. Starts PipeWire even without the volume widget. Read at
+    /// startup.
+    pub volume: bool,
+    /// The same for the screen brightness, polled from
+    /// /sys/class/backlight. Read at startup.
+    pub brightness: bool,
+    /// How long the popup stays after the last change, in milliseconds.
+    pub timeout_ms: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationsConfig {
+    /// Serve org.freedesktop.Notifications and show notifications as
+    /// popups, in place of mako or dunst. Read at startup.
+    pub server: bool,
+    /// How long a popup stays when the application leaves it to the server,
+    /// in milliseconds. Critical notifications stay until clicked.
+    pub timeout_ms: u32,
+    /// Popups shown at once; a new one past this closes the oldest.
+    pub max_popups: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FormatConfig {
+    /// Between the integer and fractional parts of every number the bar
+    /// shows. Read at startup.
+    pub decimal_separator: DecimalSeparator,
+    /// Byte sizes and rates (bandwidth, GPU memory, process memory).
+    pub byte_units: ByteUnits,
+    /// Temperatures on the bar. `[gpu] warning_temperature` stays in °C.
+    pub temperature: TemperatureUnit,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DecimalSeparator {
+    /// The locale's, from LC_ALL, LC_NUMERIC or LANG.
+    #[default]
+    Auto,
+    Point,
+    Comma,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ByteUnits {
+    /// Powers of 1024 as K, M and G on the bar, KiB, MiB and GiB in
+    /// tooltips.
+    #[default]
+    Short,
+    /// Powers of 1024 as KiB, MiB and GiB everywhere.
+    Binary,
+    /// Powers of 1000 as kB, MB and GB.
+    Decimal,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemperatureUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    /// Run through `sh -c` when the active workspace changes, with
+    /// BAR_WORKSPACE_ID and BAR_WORKSPACE_NAME set.
+    pub on_workspace_change: Option<String>,
+    /// Run once when a discharging battery reaches `battery_low_percent`,
+    /// with BAR_BATTERY_PERCENT set.
+    pub on_battery_low: Option<String>,
+    /// Run when a Bluetooth device connects, with BAR_BT_NAME and
+    /// BAR_BT_ADDRESS set.
+    pub on_bt_connected: Option<String>,
+    pub battery_low_percent: u8,
+    /// Least time between two runs of one hook; events in between collapse
+    /// into one run with the latest data.
+    pub min_interval_seconds: u32,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            on_workspace_change: None,
+            on_battery_low: None,
+            on_bt_connected: None,
+            battery_low_percent: 15,
+            min_interval_seconds: 2,
+        }
+    }
+}
+
+impl Default for BandwidthConfig {
+    fn default() -> Self {
+        Self {
+            interface: None,
+            interval_seconds: 2,
+        }
+    }
+}
+
+impl Default for OsdConfig {
+    fn default() -> Self {
+        Self {
+            volume: false,
+            brightness: false,
+            timeout_ms: 1500,
+        }
+    }
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            server: false,
+            timeout_ms: 5000,
+            max_popups: 3,
+        }
+    }
+}
+
+impl Default for LoadAvgConfig {
+    fn default() -> Self {
+        Self {
+            warning_percent: 70,
+            critical_percent: 100,
+        }
+    }
+}
+
+impl Default for BatteryConfig {
+    fn default() -> Self {
+        Self {
+            warning_percent: 20,
+            critical_percent: 10,
+            notify: true,
+            animate_charging: false,
+            charging_frames: DEFAULT_CHARGING_FRAMES.map(String::from).to_vec(),
+        }
+    }
+}
+
+impl Default for GpuConfig {
+    fn default() -> Self {
+        Self {
+            interval_seconds: 3,
+            warning_busy_percent: 90,
+            warning_temperature: 85,
+        }
+    }
+}
+
+impl Default for UpdatesConfig {
+    fn default() -> Self {
+        Self {
+            command: String::new(),
+            interval_minutes: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecorderConfig {
+    /// CSV file the metrics are appended to; recording is off without it.
+    /// Read at startup.
+    pub path: Option<PathBuf>,
+    pub interval_seconds: u32,
+    /// Columns, in order.
+    pub metrics: Vec<Metric>,
+    /// Size at which the file is rotated to `<path>.1`.
+    pub max_bytes: u64,
+    /// Rotated files kept next to the current one.
+    pub keep: u32,
+}
+
+impl Default for RecorderConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            interval_seconds: 60,
+            metrics: vec![Metric::Battery, Metric::Cpu, Metric::Net],
+            max_bytes: 10 * 1024 * 1024,
+            keep: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    /// Address to serve Prometheus metrics on, e.g. `127.0.0.1:9101`; off
+    /// without it. Needs a build with the `metrics` feature. Read at startup.
+    pub listen: Option<SocketAddr>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Metric {
+    /// Charge of the first battery, in percent.
+    Battery,
+    /// Load over the interval, in percent of all cores.
+    Cpu,
+    /// Received and transmitted bytes per second, loopback excluded.
+    Net,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileWatchConfig {
+    /// Slot name for layouts and set-visible, and the widget's CSS name.
+    pub name: String,
+    pub path: PathBuf,
+    /// Regex applied to the contents; the first capture group is shown, or
+    /// the whole match when the pattern has no groups. Without it the trimmed
+    /// contents are shown. No match hides the widget.
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// `{}` is replaced with the value.
+    #[serde(default = "default_file_watch_format")]
+    pub format: String,
+    /// Re-read period. Files under /sys and /proc never report changes, so
+    /// they rely on it; 0 re-reads only on change notifications.
+    #[serde(default = "default_file_watch_interval")]
+    pub interval_seconds: u32,
+}
+
+fn default_file_watch_format() -> String {
+    "{}".to_string()
+}
+
+fn default_file_watch_interval() -> u32 {
+    5
+}
+
+// `$XDG_CONFIG_HOME/gtk-status-bar`, or `~/.config/gtk-status-bar` when the
+// variable is unset or empty. None only when neither variable is usable.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME").filter(|home| !home.is_empty())?).join(".config"),
+    };
+    Some(base.join(CONFIG_DIR_NAME))
+}
+
+// Where `load` looks without --config.
+pub fn default_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(CONFIG_FILE_NAME))
+}
+
+// What serde cannot check about a file watch; also run on the ones added at
+// runtime over the control socket.
+pub fn validate_file_watch(watch: &FileWatchConfig) -> Result<()> {
+    if let Some(pattern) = &watch.pattern {
+        regex::Regex::new(pattern)
+            .with_context(|| format!("file_watch {:?}: invalid pattern", watch.name))?;
+    }
+    Ok(())
+}
+
+pub fn parse(text: &str) -> Result<Config> {
+    let config: Config = toml::from_str(text).context("parse configuration TOML")?;
+    for watch in &config.file_watch {
+        validate_file_watch(watch)?;
+    }
+    for (index, rule) in config.title_rewrite.iter().enumerate() {
+        if rule.class.is_none() && rule.pattern.is_none() {
+            bail!("title_rewrite #{}: needs a class or a pattern", index + 1);
+        }
+        for regex in [&rule.class, &rule.pattern].into_iter().flatten() {
+            regex::Regex::new(regex)
+                .with_context(|| format!("title_rewrite #{}: invalid regex", index + 1))?;
+        }
+    }
+    for rule in &config.auto_profile {
+        if rule.profile.is_none() && rule.hook.is_none() {
+            bail!("auto_profile {:?}: needs a profile or a hook", rule.name);
+        }
+        if let Some(profile) = &rule.profile
+            && !config
+                .displays
+                .profiles
+                .iter()
+                .any(|candidate| candidate.name == *profile)
+        {
+            bail!(
+                "auto_profile {:?}: no display profile named {:?}",
+                rule.name,
+                profile
+            );
+        }
+    }
+    for (name, value) in &config.colors {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            bail!("colors: {name:?} is not a valid color name");
+        }
+        // The value lands in a stylesheet; it must not end its declaration.
+        if value.contains([';', '{', '}']) {
+            bail!("colors.{name}: {value:?} is not a color");
+        }
+    }
+    for (id, value) in &config.workspace.colors {
+        if id.parse::<i32>().is_err() {
+            bail!("workspace.colors: {id:?} is not a workspace id");
+        }
+        if value.contains([';', '{', '}']) {
+            bail!("workspace.colors.{id}: {value:?} is not a color");
+        }
+    }
+    for (action, command) in [
+        ("lock", &config.power.lock),
+        ("logout", &config.power.logout),
+        ("suspend", &config.power.suspend),
+        ("reboot", &config.power.reboot),
+        ("shutdown", &config.power.shutdown),
+    ] {
+        if command
+            .as_ref()
+            .is_some_and(|command| command.trim().is_empty())
+        {
+            bail!("power.{action}: the command is empty; leave it out to use logind");
+        }
+    }
+    if config.battery.critical_percent > config.battery.warning_percent
+        || config.battery.warning_percent > 100
+    {
+        bail!(
+            "battery thresholds need critical_percent <= warning_percent <= 100, got {} and {}",
+            config.battery.critical_percent,
+            config.battery.warning_percent
+        );
+    }
+    if chrono::format::StrftimeItems::new(&config.clock.format)
+        .any(|item| matches!(item, chrono::format::Item::Error))
+    {
+        bail!(
+            "clock format {:?} is not a valid strftime format",
+            config.clock.format
+        );
+    }
+    if config.gpu.warning_busy_percent > 100 {
+        bail!(
+            "gpu warning_busy_percent must be at most 100, got {}",
+            config.gpu.warning_busy_percent
+        );
+    }
+    if config.osd.timeout_ms == 0 {
+        bail!("osd timeout_ms must be at least 1");
+    }
+    if config.notifications.timeout_ms == 0 {
+        bail!("notifications timeout_ms must be at least 1");
+    }
+    if config.notifications.max_popups == 0 {
+        bail!("notifications max_popups must be at least 1");
+    }
+    for (name, settings) in &config.widgets {
+        for (key, command) in settings.commands() {
+            if command.is_some_and(|command| command.trim().is_empty()) {
+                bail!("widgets.{name} {key} must not be empty");
+            }
+        }
+    }
+    Ok(config)
+}
+
+// Paths inside the file mean "next to this config", not "next to wherever
+// the bar happened to be launched from".
+fn resolve_relative_paths(mut config: Config, config_path: &Path) -> Config {
+    if let (Some(file), Some(base)) = (&config.layout.file, config_path.parent())
+        && file.is_relative()
+    {
+        config.layout.file = Some(base.join(file));
+    }
+    if let (Some(file), Some(base)) = (&config.recorder.path, config_path.parent())
+        && file.is_relative()
+    {
+        config.recorder.path = Some(base.join(file));
+    }
+    if let Some(base) = config_path.parent() {
+        for watch in &mut config.file_watch {
+            if watch.path.is_relative() {
+                watch.path = base.join(&watch.path);
+            }
+        }
+    }
+    config
+}
+
+pub fn load(explicit: Option<&Path>) -> Result<Config> {
+    if let Some(path) = explicit {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("read configuration file {}", path.display()))?;
+        let config =
+            parse(&text).with_context(|| format!("invalid configuration in {}", path.display()))?;
+        info!(path = %path.display(), "Loaded configuration");
+        return Ok(resolve_relative_paths(config, path));
+    }
+
+    let Some(path) = default_path() else {
+        debug!("Neither XDG_CONFIG_HOME nor HOME is set; using default configuration");
+        return Ok(Config::default());
+    };
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == ErrorKind::NotFound => {
+            debug!(path = %path.display(), "No configuration file; using defaults");
+            return Ok(Config::default());
+        }
+        Err(error) => {
+            return Err(error)
+                .with_context(|| format!("read configuration file {}", path.display()));
+        }
+    };
+    let config =
+        parse(&text).with_context(|| format!("invalid configuration in {}", path.display()))?;
+    info!(path = %path.display(), "Loaded configuration");
+    Ok(resolve_relative_paths(config, &path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_file_yields_defaults() {
+        assert_eq!(parse("").expect("empty config parses"), Config::default());
+    }
+
+    #[test]
+    fn special_workspace_color_is_configurable() {
+        let config =
+            parse("[workspace]\nspecial_color = \"#ff00ff\"\n").expect("workspace table parses");
+        assert_eq!(config.workspace.special_color, "#ff00ff");
+    }
+
+    #[test]
+    fn clock_formats_are_checked() {
+        let config = parse("[clock]\nformat = \"%H:%M\"\n").expect("clock table parses");
+        assert_eq!(config.clock.format, "%H:%M");
+        assert!(parse("[clock]\nformat = \"%Q\"\n").is_err());
+    }
+
+    #[test]
+    fn battery_thresholds_must_be_ordered() {
+        let config = parse("[battery]\nwarning_percent = 30\nnotify = false\n")
+            .expect("battery table parses");
+        assert_eq!(config.battery.warning_percent, 30);
+        assert_eq!(config.battery.critical_percent, 10);
+        assert!(!config.battery.notify);
+        assert!(parse("[battery]\nwarning_percent = 5\n").is_err());
+    }
+
+    #[test]
+    fn color_names_and_values_are_checked() {
+        let config = parse("[colors]\nblue = \"#89b4fa\"\n[workspace.colors]\n3 = \"@blue\"\n")
+            .expect("colors parse");
+        assert_eq!(
+            config.colors.get("blue").map(String::as_str),
+            Some("#89b4fa")
+        );
+        assert_eq!(
+            config.workspace.colors.get("3").map(String::as_str),
+            Some("@blue")
+        );
+        assert!(parse("[colors]\n\"my color\" = \"red\"\n").is_err());
+        assert!(parse("[colors]\nblue = \"red; } * { color: red\"\n").is_err());
+        assert!(parse("[workspace.colors]\nweb = \"@blue\"\n").is_err());
+    }
+
+    #[test]
+    fn workspace_labels_take_ids_names_and_specials() {
+        let config = parse("[workspace.labels]\n1 = \"web\"\n\"special:magic\" = \"scratch\"\n")
+            .expect("workspace labels parse");
+        assert_eq!(
+            config.workspace.labels.get("1").map(String::as_str),
+            Some("web")
+        );
+        assert_eq!(
+            config
+                .workspace
+                .labels
+                .get("special:magic")
+                .map(String::as_str),
+            Some("scratch")
+        );
+    }
+
+    #[test]
+    fn scratchpads_default_to_magic() {
+        assert_eq!(Config::default().workspace.scratchpads, ["magic"]);
+        let config =
+            parse("[workspace]\nscratchpads = [\"term\", \"music\"]\n").expect("scratchpads parse");
+        assert_eq!(config.workspace.scratchpads, ["term", "music"]);
+    }
+
+    #[test]
+    fn relative_layout_file_resolves_next_to_config() {
+        let config = parse("[layout]\nfile = \"bar.ui\"\n").expect("layout table parses");
+        let config = resolve_relative_paths(
+            config,
+            Path::new("/home/u/.config/gtk-status-bar/config.toml"),
+        );
+        assert_eq!(
+            config.layout.file.as_deref(),
+            Some(Path::new("/home/u/.config/gtk-status-bar/bar.ui"))
+        );
+
+        let absolute = parse("[layout]\nfile = \"/etc/bar.ui\"\n").expect("layout table parses");
+        let absolute = resolve_relative_paths(absolute, Path::new("/tmp/config.toml"));
+        assert_eq!(
+            absolute.layout.file.as_deref(),
+            Some(Path::new("/etc/bar.ui"))
+        );
+    }
+
+    #[test]
+    fn display_profiles_keep_their_order() {
+        let config = parse(
+            "[[displays.profiles]]\nname = \"Docked\"\ncommand = \"kanshictl switch docked\"\n\
+             [[displays.profiles]]\nname = \"Laptop\"\ncommand = \"kanshictl switch laptop\"\n",
+        )
+        .expect("display profiles parse");
+        let names: Vec<&str> = config
+            .displays
+            .profiles
+            .iter()
+            .map(|profile| profile.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Docked", "Laptop"]);
+        assert!(parse("[[displays.profiles]]\nname = \"No command\"\n").is_err());
+    }
+
+    #[test]
+    fn auto_hide_defaults_off() {
+        assert!(!Config::default().bar.auto_hide);
+        let config =
+            parse("[bar]\nauto_hide = true\nhidden_workspaces = [9]\n").expect("bar table parses");
+        assert!(config.bar.auto_hide);
+        assert_eq!(config.bar.hidden_workspaces, vec![9]);
+    }
+
+    #[test]
+    fn side_positions_are_vertical() {
+        let config = parse("[bar]\nposition = \"right\"\n").expect("position parses");
+        assert_eq!(config.bar.position, Position::Right);
+        assert!(config.bar.position.is_vertical());
+        assert!(!Position::Bottom.is_vertical());
+        assert!(parse("[bar]\nposition = \"center\"\n").is_err());
+    }
+
+    #[test]
+    fn floating_bar_is_off_with_default_margins() {
+        let floating = Config::default().bar.floating;
+        assert!(!floating.enabled);
+        assert_eq!((floating.edge_margin, floating.side_margin), (8, 12));
+        let config = parse(
+            "[bar.floating]
+enabled = true
+corner_radius = 0
+",
+        )
+        .expect("floating table parses");
+        assert!(config.bar.floating.enabled);
+        assert_eq!(config.bar.floating.corner_radius, 0);
+        assert_eq!(config.bar.floating.edge_margin, 8);
+    }
+
+    #[test]
+    fn focus_keywords_take_quoted_hyprland_names() {
+        let config = parse(
+            "[pomodoro]\nwork_minutes = 50\n\
+             focus_keywords = { \"general:gaps_in\" = \"0\", \"animations:enabled\" = \"0\" }\n",
+        )
+        .expect("pomodoro table parses");
+        assert_eq!(config.pomodoro.work_minutes, 50);
+        assert_eq!(config.pomodoro.break_minutes, 5);
+        assert_eq!(
+            config
+                .pomodoro
+                .focus_keywords
+                .get("general:gaps_in")
+                .map(String::as_str),
+            Some("0")
+        );
+    }
+
+    #[test]
+    fn file_watches_fill_in_defaults() {
+        let config = parse(
+            "[[file_watch]]\nname = \"temp\"\npath = \"/sys/class/thermal/thermal_zone0/temp\"\n",
+        )
+        .expect("file watch parses");
+        let watch = &config.file_watch[0];
+        assert_eq!(watch.format, "{}");
+        assert_eq!(watch.interval_seconds, 5);
+        assert_eq!(watch.pattern, None);
+        assert!(parse("[[file_watch]]\nname = \"no path\"\n").is_err());
+        assert!(
+            parse("[[file_watch]]\nname = \"bad\"\npath = \"/tmp/x\"\npattern = \"(\"\n").is_err()
+        );
+    }
+
+    #[test]
+    fn alarm_sound_is_optional() {
+        assert_eq!(Config::default().clock.alarm_sound, None);
+        let config = parse("[clock]\nalarm_sound = \"pw-play /tmp/ring.oga\"\n")
+            .expect("clock table parses");
+        assert_eq!(
+            config.clock.alarm_sound.as_deref(),
+            Some("pw-play /tmp/ring.oga")
+        );
+    }
+
+    #[test]
+    fn recorder_is_off_until_given_a_path() {
+        assert_eq!(Config::default().recorder.path, None);
+        let config = parse("[recorder]\npath = \"metrics.csv\"\nmetrics = [\"cpu\", \"net\"]\n")
+            .expect("recorder table parses");
+        assert_eq!(config.recorder.metrics, vec![Metric::Cpu, Metric::Net]);
+        assert_eq!(config.recorder.interval_seconds, 60);
+        assert!(parse("[recorder]\nmetrics = [\"gpu\"]\n").is_err());
+    }
+
+    #[test]
+    fn metrics_listen_on_a_socket_address() {
+        assert_eq!(Config::default().metrics.listen, None);
+        let config =
+            parse("[metrics]\nlisten = \"127.0.0.1:9101\"\n").expect("metrics table parses");
+        assert_eq!(
+            config.metrics.listen,
+            Some(SocketAddr::from(([127, 0, 0, 1], 9101)))
+        );
+        assert!(parse("[metrics]\nlisten = \"localhost\"\n").is_err());
+    }
+
+    #[test]
+    fn window_backend_names_parse() {
+        assert_eq!(Config::default().windows.backend, WindowBackend::Auto);
+        let config = parse("[windows]\nbackend = \"wlr\"\n").expect("windows table parses");
+        assert_eq!(config.windows.backend, WindowBackend::Wlr);
+        assert_eq!(WindowBackend::Auto.resolve(true), WindowBackend::Compositor);
+        assert_eq!(WindowBackend::Auto.resolve(false), WindowBackend::Wlr);
+        let config = parse("[windows]\nbackend = \"hyprland\"\n").expect("old name still parses");
+        assert_eq!(
+            config.windows.backend.resolve(false),
+            WindowBackend::Compositor
+        );
+        assert!(parse("[windows]\nbackend = \"sway\"\n").is_err());
+    }
+
+    // A typo must surface at startup instead of silently falling back to the
+    // default the user was trying to override.
+    #[test]
+    fn widget_settings_are_keyed_by_name() {
+        let config = parse(
+            "[widgets.title]\nmax_width = 40\nalign = \"center\"\n\
+             [widgets.bluetooth]\non_click = \"blueman-manager\"\n",
+        )
+        .expect("widgets table parses");
+        assert_eq!(
+            config.widgets.get("title"),
+            Some(&WidgetSettings {
+                max_width: Some(40),
+                align: Some(Align::Center),
+                ..WidgetSettings::default()
+            })
+        );
+        assert_eq!(
+            config.widgets["bluetooth"].on_click.as_deref(),
+            Some("blueman-manager")
+        );
+        assert!(parse("[widgets.title]\nalign = \"left\"\n").is_err());
+        assert!(parse("[widgets.title]\non-click = \"htop\"\n").is_err());
+        assert!(parse("[widgets.volume]\non_scroll_up = \" \"\n").is_err());
+        let config = parse("[widgets.battery]\nreveal_on_hover = true\n").expect("reveal parses");
+        assert!(config.widgets["battery"].reveal_on_hover);
+        assert_eq!(config.widgets["battery"].compact, None);
+    }
+
+    #[test]
+    fn auto_profiles_name_existing_display_profiles() {
+        let displays =
+            "[[displays.profiles]]\nname = \"Docked\"\ncommand = \"kanshictl switch docked\"\n";
+        let config = parse(&format!(
+            "{displays}[[auto_profile]]\nname = \"dock\"\nmin_monitors = 2\non_ac = true\nprofile = \"Docked\"\n"
+        ))
+        .expect("auto profile parses");
+        assert_eq!(config.auto_profile[0].min_monitors, Some(2));
+        assert!(
+            parse(&format!(
+                "{displays}[[auto_profile]]\nname = \"dock\"\nprofile = \"Desk\"\n"
+            ))
+            .is_err()
+        );
+        assert!(parse("[[auto_profile]]\nname = \"idle\"\n").is_err());
+    }
+
+    #[test]
+    fn title_rewrites_need_something_to_match() {
+        let config = parse(
+            "[[title_rewrite]]\npattern = \" — Mozilla Firefox$\"\nreplace = \"\"\n\
+             [[title_rewrite]]\nclass = \"^org\\\\.wezfurlong\\\\.wezterm$\"\nreplace = \"Terminal\"\n",
+        )
+        .expect("title rewrites parse");
+        assert_eq!(config.title_rewrite.len(), 2);
+        assert_eq!(config.title_rewrite[1].pattern, None);
+        assert!(parse("[[title_rewrite]]\nreplace = \"x\"\n").is_err());
+        assert!(parse("[[title_rewrite]]\npattern = \"(\"\nreplace = \"x\"\n").is_err());
+    }
+
+    #[test]
+    fn title_truncation_is_configurable() {
+        assert_eq!(Config::default().title.max_chars, 64);
+        let config = parse(
+            "[title]\nmax_chars = 40\nellipsize = \"end\"\nnative = true\nmax_width_px = 600\n",
+        )
+        .expect("title table parses");
+        assert_eq!(config.title.ellipsize, Ellipsize::End);
+        assert_eq!(config.title.max_width_px, Some(600));
+        assert!(config.title.native);
+        assert!(parse("[title]\nellipsize = \"both\"\n").is_err());
+    }
+
+    #[test]
+    fn groups_default_to_no_spacing_or_separators() {
+        assert_eq!(
+            Config::default().groups.right.separator,
+            SeparatorStyle::None
+        );
+        let config =
+            parse("[groups.right]\nspacing = 4\nseparator = \"text\"\nseparator_text = \"·\"\n")
+                .expect("groups table parses");
+        assert_eq!(config.groups.right.spacing, 4);
+        assert_eq!(config.groups.right.separator_text, "·");
+        assert_eq!(config.groups.left, GroupConfig::default());
+        assert!(parse("[groups.center]\nspacing = 4\n").is_err());
+    }
+
+    #[test]
+    fn format_units_parse() {
+        assert_eq!(
+            Config::default().format.decimal_separator,
+            DecimalSeparator::Auto
+        );
+        let config = parse(
+            "[format]\ndecimal_separator = \"comma\"\nbyte_units = \"decimal\"\ntemperature = \"fahrenheit\"\n",
+        )
+        .expect("format table parses");
+        assert_eq!(config.format.byte_units, ByteUnits::Decimal);
+        assert_eq!(config.format.temperature, TemperatureUnit::Fahrenheit);
+        assert!(parse("[format]\nbyte_units = \"si\"\n").is_err());
+    }
+
+    #[test]
+    fn power_commands_are_optional_but_not_blank() {
+        assert!(Config::default().power.confirm);
+        let config =
+            parse("[power]\nconfirm = false\nlock = \"hyprlock\"\n").expect("power table parses");
+        assert_eq!(config.power.lock.as_deref(), Some("hyprlock"));
+        assert_eq!(config.power.reboot, None);
+        assert!(parse("[power]\nshutdown = \" \"\n").is_err());
+    }
+
+    #[test]
+    fn the_osd_is_opt_in() {
+        assert!(!Config::default().osd.volume);
+        assert!(!Config::default().osd.brightness);
+        let config =
+            parse("[osd]\nbrightness = true\ntimeout_ms = 800\n").expect("osd table parses");
+        assert!(config.osd.brightness);
+        assert!(!config.osd.volume);
+        assert_eq!(config.osd.timeout_ms, 800);
+        assert!(parse("[osd]\ntimeout_ms = 0\n").is_err());
+    }
+
+    #[test]
+    fn the_notification_server_is_opt_in() {
+        assert!(!Config::default().notifications.server);
+        let config = parse("[notifications]\nserver = true\nmax_popups = 5\n")
+            .expect("notifications table parses");
+        assert!(config.notifications.server);
+        assert_eq!(config.notifications.max_popups, 5);
+        assert_eq!(config.notifications.timeout_ms, 5000);
+        assert!(parse("[notifications]\nmax_popups = 0\n").is_err());
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(parse("[workspace]\nspecial_colour = \"red\"\n").is_err());
+        assert!(parse("[workspaces]\n").is_err());
+    }
+}
@@ -0,0 +1,342 @@
+// Bar-level control verbs on the IPC socket: show, hide, toggle, reload-config,
+// set-visible, popover, and add-widget/remove-widget for widgets that live
+// outside the configuration file. setup_control_requests sits between the socket server
+// and the tray drain, answers these itself and forwards every other request to
+// the tray unchanged, so a Hyprland keybind can run `trayctl toggle` the same
+// way it runs `trayctl open`.
+//
+// Everything here runs on the GTK main thread: the window, the bar tree and
+// the model are touched directly, with no dispatcher in between, because a
+// control request is a one-off user action rather than a burst to coalesce.
+
+use std::cell::{Cell, RefCell};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use anyhow::{Context, Result, bail};
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4_layer_shell::Layer;
+use tokio::sync::{mpsc, watch};
+use tracing::{debug, info, warn};
+use tray_ipc::{IpcRequest, IpcResponse, IpcUiRequest};
+
+use crate::alarm::AlarmBook;
+use crate::app::WidgetBackends;
+use crate::audio::AudioSinks;
+use crate::autohide::AutoHide;
+use crate::capture::Capture;
+use crate::compositor::AnyCompositor;
+use crate::config;
+use crate::model::BarModel;
+use crate::palette::{self, WorkspaceColors};
+use crate::pomodoro::PomodoroTimer;
+use crate::widgets::{self, Bar, CustomWidget, TaskbarStore, WidgetContext};
+
+// Whether the window is mapped, from two independent inputs: the show/hide
+// verbs and the `[bar] hidden_workspaces` list. Either one hiding wins, so
+// leaving a hidden workspace never undoes a `hide`, and `show` on a hidden
+// workspace waits until the user moves off it.
+pub struct WindowVisibility {
+    window: gtk4::ApplicationWindow,
+    requested: Cell<bool>,
+    workspace: Cell<i32>,
+    hidden_workspaces: RefCell<Vec<i32>>,
+}
+
+fn should_map(requested: bool, hidden_workspaces: &[i32], workspace: i32) -> bool {
+    requested && !hidden_workspaces.contains(&workspace)
+}
+
+impl WindowVisibility {
+    pub fn new(window: gtk4::ApplicationWindow, hidden_workspaces: Vec<i32>) -> Rc<Self> {
+        Rc::new(Self {
+            window,
+            requested: Cell::new(true),
+            workspace: Cell::new(0),
+            hidden_workspaces: RefCell::new(hidden_workspaces),
+        })
+    }
+
+    pub fn set_workspace(&self, workspace: i32) {
+        self.workspace.set(workspace);
+        self.apply();
+    }
+
+    fn set_requested(&self, requested: bool) {
+        self.requested.set(requested);
+        self.apply();
+    }
+
+    fn set_hidden_workspaces(&self, hidden_workspaces: Vec<i32>) {
+        self.hidden_workspaces.replace(hidden_workspaces);
+        self.apply();
+    }
+
+    fn apply(&self) {
+        let map = should_map(
+            self.requested.get(),
+            &self.hidden_workspaces.borrow(),
+            self.workspace.get(),
+        );
+        if self.window.is_visible() != map {
+            debug!(
+                map,
+                workspace = self.workspace.get(),
+                "Changing bar window visibility"
+            );
+            self.window.set_visible(map);
+        }
+    }
+}
+
+pub struct BarControl {
+    pub window: gtk4::ApplicationWindow,
+    pub visibility: Rc<WindowVisibility>,
+    pub model: BarModel,
+    pub alarms: AlarmBook,
+    pub pomodoro: PomodoroTimer,
+    pub capture: Capture,
+    pub audio: AudioSinks,
+    pub taskbar: TaskbarStore,
+    // Set in auto-hide mode, where show/hide/toggle slide the bar in and out
+    // instead of mapping and unmapping the window.
+    pub auto_hide: Option<Rc<AutoHide>>,
+    pub bar: Bar,
+    pub tray: gtk4::Box,
+    // The --layer the bar sits on when it does not float.
+    pub layer: Layer,
+    // The --config path, if any, so a reload reads the same file startup did.
+    pub config_path: Option<PathBuf>,
+    // Read by the title color view (see views.rs), which repaints on a send.
+    pub workspace_colors: watch::Sender<WorkspaceColors>,
+    // The `[colors]` provider, refilled on reload.
+    pub colors: gtk4::CssProvider,
+    // The floating corner radius, refilled on reload (see apply_floating).
+    pub floating_css: gtk4::CssProvider,
+    // Added with add-widget, in order. A reload places them again.
+    pub runtime_widgets: Vec<config::FileWatchConfig>,
+    // From BarBuilder: the built-in widgets to keep (all when empty) and the
+    // embedder's own, both applied again on reload.
+    pub builtin_widgets: Vec<String>,
+    pub custom_widgets: Vec<CustomWidget>,
+    // For the workspace scroll handlers, and its labels, shared with the
+    // workspace listener: new labels show from the next workspace change on.
+    pub compositor: AnyCompositor,
+    // Shared with activate(), which starts the startup layout's backends
+    // after the first frame; a reload starts the ones its layout adds.
+    pub backends: Rc<RefCell<WidgetBackends>>,
+}
+
+impl BarControl {
+    fn handle(&mut self, request: IpcRequest) -> IpcResponse {
+        match request {
+            IpcRequest::Show => self.set_shown(true),
+            IpcRequest::Hide => self.set_shown(false),
+            IpcRequest::Toggle => self.set_shown(!self.is_shown()),
+            IpcRequest::SetVisible { widget, visible } => {
+                let Some(slot) = self.bar.slots.get(widget.as_str()) else {
+                    return IpcResponse::error(format!("the bar has no widget {widget:?}"));
+                };
+                slot.set_visible(visible);
+            }
+            IpcRequest::Popover { widget } => {
+                if let Err(e) = widgets::open_widget_popover(&self.bar, &widget) {
+                    return IpcResponse::error(format!("{e:#}"));
+                }
+            }
+            IpcRequest::ReloadConfig => {
+                if let Err(e) = self.reload() {
+                    warn!("Configuration reload failed: {:#}", e);
+                    return IpcResponse::error(format!("{e:#}"));
+                }
+            }
+            IpcRequest::AddWidget { config } => {
+                if let Err(e) = self.add_widget(&config) {
+                    warn!("Could not add widget: {:#}", e);
+                    return IpcResponse::error(format!("{e:#}"));
+                }
+            }
+            IpcRequest::RemoveWidget { name } => {
+                let Some(index) = self
+                    .runtime_widgets
+                    .iter()
+                    .position(|watch| watch.name == name)
+                else {
+                    return IpcResponse::error(format!("no widget {name:?} was added at runtime"));
+                };
+                self.runtime_widgets.remove(index);
+                widgets::remove_runtime_widget(&mut self.bar, &name);
+                info!(name, "Removed runtime widget");
+            }
+            other => {
+                return IpcResponse::error(format!("{other:?} is not a bar control request"));
+            }
+        }
+        IpcResponse::success(Vec::new())
+    }
+
+    fn is_shown(&self) -> bool {
+        match &self.auto_hide {
+            Some(auto_hide) => auto_hide.is_revealed(),
+            None => self.visibility.requested.get(),
+        }
+    }
+
+    fn set_shown(&self, show: bool) {
+        match (&self.auto_hide, show) {
+            (Some(auto_hide), true) => auto_hide.show_pinned(),
+            (Some(auto_hide), false) => auto_hide.hide(),
+            (None, visible) => self.visibility.set_requested(visible),
+        }
+    }
+
+    fn add_widget(&mut self, config: &str) -> Result<()> {
+        let watch: config::FileWatchConfig =
+            serde_json::from_str(config).context("parse the widget's JSON")?;
+        config::validate_file_watch(&watch)?;
+        // Relative to what? The bar's working directory is nothing a script
+        // can count on.
+        if !watch.path.is_absolute() {
+            bail!("file_watch {:?}: path must be absolute", watch.name);
+        }
+        let after = self
+            .runtime_widgets
+            .last()
+            .and_then(|last| self.bar.slots.get(&last.name))
+            .cloned();
+        widgets::place_runtime_widget(&mut self.bar, &watch, after.as_ref())?;
+        info!(name = watch.name, "Added runtime widget");
+        self.runtime_widgets.push(watch);
+        Ok(())
+    }
+
+    // Re-read the configuration and rebuild the bar tree from it. The widgets
+    // bind to the model, so the new tree shows current state on its first
+    // frame; only the tray strip carries over, moved into the new tree's tray
+    // slot. Widgets hidden with set-visible come back visible, and a widget
+    // the old layout lacked gets its backend started. A config or layout that
+    // fails to load leaves the running bar untouched.
+    fn reload(&mut self) -> Result<()> {
+        let mut config = config::load(self.config_path.as_deref())?;
+        widgets::keep_only_widgets(&mut config.bar, &self.builtin_widgets);
+
+        let previous_slot = widgets::detach_tray(&self.tray);
+        let cx = WidgetContext {
+            model: &self.model,
+            config: &config,
+            alarms: &self.alarms,
+            pomodoro: &self.pomodoro,
+            capture: &self.capture,
+            audio: &self.audio,
+            taskbar: &self.taskbar,
+            compositor: &self.compositor,
+        };
+        let bar = match widgets::build_bar(&cx, &self.tray) {
+            Ok(bar) => bar,
+            Err(e) => {
+                if let Some(slot) = previous_slot {
+                    slot.append(&self.tray);
+                }
+                return Err(e);
+            }
+        };
+        // The new tree runs along the configured edge, so the window moves
+        // there with it, floating off that edge if the config says so;
+        // auto-hide's hot strip stays where it started.
+        match &self.auto_hide {
+            Some(auto_hide) => auto_hide.set_bar(&bar.root),
+            None => {
+                widgets::clear_floating(&self.window, self.layer);
+                widgets::apply_position(&self.window, config.bar.position);
+                let floating = &config.bar.floating;
+                if floating.enabled {
+                    widgets::apply_floating(
+                        &self.window,
+                        &self.floating_css,
+                        config.bar.position,
+                        floating,
+                    );
+                }
+                self.window.set_child(Some(&bar.root));
+            }
+        }
+        self.bar = bar;
+        self.backends
+            .borrow_mut()
+            .place(&config, |widget| self.bar.slots.contains_key(widget));
+        if let Err(e) =
+            widgets::place_custom_widgets(&mut self.bar, &self.model, &self.custom_widgets)
+        {
+            warn!("Custom widgets left out: {:#}", e);
+        }
+        // One the new configuration now claims the name of is dropped.
+        let mut after = None;
+        self.runtime_widgets.retain(|watch| {
+            match widgets::place_runtime_widget(&mut self.bar, watch, after.as_ref()) {
+                Ok(()) => {
+                    after = self.bar.slots.get(&watch.name).cloned();
+                    true
+                }
+                Err(e) => {
+                    warn!(name = watch.name, "Dropping runtime widget: {:#}", e);
+                    false
+                }
+            }
+        });
+
+        self.visibility
+            .set_hidden_workspaces(config.bar.hidden_workspaces.clone());
+        palette::load_colors(&self.colors, &config.colors);
+        self.workspace_colors
+            .send_replace(WorkspaceColors::new(&config.workspace));
+        self.compositor
+            .workspace_labels()
+            .set(config.workspace.labels.clone());
+
+        info!("Configuration reloaded");
+        Ok(())
+    }
+}
+
+// Drain the socket server's requests. Bar controls are answered here; the rest
+// go on to setup_tray_updates over `tray_tx`. A request that cannot be
+// forwarded is dropped with its response sender, which the server reports to
+// the client as a dropped request.
+pub fn setup_control_requests(
+    mut requests: mpsc::UnboundedReceiver<IpcUiRequest>,
+    tray_tx: mpsc::UnboundedSender<IpcUiRequest>,
+    mut control: BarControl,
+) {
+    debug!("Setting up bar control requests");
+
+    glib::spawn_future_local(async move {
+        while let Some(request) = requests.recv().await {
+            if !request.request.is_bar_control() {
+                if tray_tx.send(request).is_err() {
+                    warn!("Tray UI is gone; dropping IPC request");
+                }
+                continue;
+            }
+            info!(request = ?request.request, "Handling bar control request");
+            let response = control.handle(request.request);
+            if request.response.send(response).is_err() {
+                debug!("IPC client disconnected before receiving its response");
+            }
+        }
+        debug!("Bar control request channel closed");
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hidden_workspace_and_hide_request_both_unmap() {
+        assert!(should_map(true, &[9], 1));
+        assert!(!should_map(true, &[9], 9));
+        assert!(!should_map(false, &[9], 1));
+        assert!(!should_map(false, &[], 1));
+    }
+}
@@ -0,0 +1,193 @@
+// CPU frequency and governor: the average current frequency across cpufreq
+// policies plus the active scaling governor, read from
+// /sys/devices/system/cpu/cpufreq every few seconds by the stats poller.
+// sysfs has no change notification for these, hence the polling; the files are
+// generated from kernel memory, so reading them never blocks on I/O.
+//
+// Clicking the widget cycles to the next governor in
+// scaling_available_governors. Writing scaling_governor needs root, so the
+// switch runs `[cpufreq] governor_command` (by default `pkexec cpupower
+// frequency-set --governor {}`), leaving authorization to polkit. The next poll
+// picks up whatever the command managed to change.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use tracing::{debug, info, warn};
+
+use crate::command;
+use crate::format::Format;
+
+pub const CPUFREQ_ROOT: &str = "/sys/devices/system/cpu/cpufreq";
+const ICON: &str = "\u{f4bc}";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuFreq {
+    pub governor: String,
+    pub average_khz: u64,
+}
+
+fn policy_dirs(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut policies: Vec<PathBuf> = std::fs::read_dir(root)
+        .with_context(|| format!("read {}", root.display()))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("policy"))
+        })
+        .collect();
+    policies.sort();
+    Ok(policies)
+}
+
+fn read_value(path: &Path) -> Result<String> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    Ok(text.trim().to_string())
+}
+
+// The first policy's governor stands for all of them: governors are set
+// system-wide in practice, and by this widget always.
+pub fn read_cpufreq(root: &Path) -> Result<CpuFreq> {
+    let policies = policy_dirs(root)?;
+    let Some(first) = policies.first() else {
+        bail!("no cpufreq policies under {}", root.display());
+    };
+    let governor = read_value(&first.join("scaling_governor"))?;
+    let frequencies: Vec<u64> = policies
+        .iter()
+        .filter_map(|policy| read_value(&policy.join("scaling_cur_freq")).ok())
+        .filter_map(|value| value.parse().ok())
+        .collect();
+    if frequencies.is_empty() {
+        bail!("no readable scaling_cur_freq under {}", root.display());
+    }
+    let average_khz = frequencies.iter().sum::<u64>() / frequencies.len() as u64;
+    Ok(CpuFreq {
+        governor,
+        average_khz,
+    })
+}
+
+pub fn display_text(cpufreq: &CpuFreq, format: &Format) -> String {
+    let ghz = cpufreq.average_khz as f64 / 1_000_000.0;
+    format!("{ICON} {}GHz {}", format.number(ghz, 1), cpufreq.governor)
+}
+
+// The governor after `current` in `available` (space-separated, as sysfs
+// lists them), wrapping around. None when there is nothing else to switch to.
+pub fn next_governor(current: &str, available: &str) -> Option<String> {
+    let governors: Vec<&str> = available.split_whitespace().collect();
+    if governors.len() < 2 {
+        return None;
+    }
+    let next = match governors.iter().position(|governor| *governor == current) {
+        Some(index) => governors[(index + 1) % governors.len()],
+        None => governors[0],
+    };
+    Some(next.to_string())
+}
+
+// Only plain governor names reach the shell command.
+fn is_governor_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+// The widget's click handler. Runs on the GTK thread; see the module comment
+// for why the sysfs reads are fine there.
+pub fn cycle_governor(governor_command: &str) {
+    let root = Path::new(CPUFREQ_ROOT);
+    let policy = match policy_dirs(root) {
+        Ok(policies) if !policies.is_empty() => policies[0].clone(),
+        Ok(_) => {
+            warn!("No cpufreq policies; cannot change the governor");
+            return;
+        }
+        Err(e) => {
+            warn!("Cannot change the governor: {:#}", e);
+            return;
+        }
+    };
+    let (current, available) = match (
+        read_value(&policy.join("scaling_governor")),
+        read_value(&policy.join("scaling_available_governors")),
+    ) {
+        (Ok(current), Ok(available)) => (current, available),
+        (Err(e), _) | (_, Err(e)) => {
+            warn!("Cannot change the governor: {:#}", e);
+            return;
+        }
+    };
+    let Some(next) = next_governor(&current, &available) else {
+        debug!(current, "Only one governor available; nothing to cycle");
+        return;
+    };
+    if !is_governor_name(&next) {
+        warn!(
+            governor = next,
+            "Refusing to pass an unexpected governor name to a shell"
+        );
+        return;
+    }
+    info!(from = current, to = next, "Cycling CPU governor");
+    command::spawn_shell(&governor_command.replace("{}", &next), "CPU governor");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn governors_cycle_and_wrap() {
+        let available = "performance powersave";
+        assert_eq!(
+            next_governor("performance", available).as_deref(),
+            Some("powersave")
+        );
+        assert_eq!(
+            next_governor("powersave", available).as_deref(),
+            Some("performance")
+        );
+        assert_eq!(
+            next_governor("schedutil", available).as_deref(),
+            Some("performance")
+        );
+        assert_eq!(next_governor("performance", "performance"), None);
+    }
+
+    #[test]
+    fn reads_and_averages_policies() {
+        let root = TempDir::new("cpufreq");
+        for (policy, khz) in [("policy0", "1200000"), ("policy1", "2400000")] {
+            let dir = root.join(policy);
+            std::fs::create_dir_all(&dir).expect("create fake policy");
+            std::fs::write(dir.join("scaling_governor"), "powersave\n").expect("write governor");
+            std::fs::write(dir.join("scaling_cur_freq"), format!("{khz}\n"))
+                .expect("write frequency");
+        }
+        let cpufreq = read_cpufreq(&root).expect("fake policies should read");
+        assert_eq!(
+            cpufreq,
+            CpuFreq {
+                governor: "powersave".to_string(),
+                average_khz: 1_800_000,
+            }
+        );
+        assert_eq!(
+            display_text(&cpufreq, &Format::default()),
+            format!("{ICON} 1.8GHz powersave")
+        );
+    }
+
+    #[test]
+    fn governor_names_are_plain_words() {
+        assert!(is_governor_name("schedutil"));
+        assert!(!is_governor_name("x; rm -rf ~"));
+        assert!(!is_governor_name(""));
+    }
+}
@@ -0,0 +1,318 @@
+// UI dispatcher: the one place widget drains hand their GTK mutations to.
+//
+// Every setup_*_updates task still owns its receiver, but instead of touching
+// its label directly it submits a closure for its slot. Submissions are queued
+// until the next flush, which runs once in a single idle callback: a burst of
+// D-Bus or Hyprland events (a workspace switch fires workspace, title and
+// active-window events back to back; a bluetooth reconnect fires several
+// PropertiesChanged) collapses to one label write per slot and one relayout
+// instead of one per event.
+//
+// Coalescing is last-write-wins per slot. Every update the drains carry is a
+// full snapshot of that widget's state (not a delta), so dropping a superseded
+// one loses nothing. Slots flush in the order they were first submitted since
+// the previous flush, keeping cross-widget ordering the same as the channel
+// arrival order.
+//
+// Most drains submit through submit_changed, which also remembers the value
+// each slot was last submitted with and drops a repeat of it: PipeWire
+// re-sends Props with the same volume, UPower re-reports the same charge, and
+// each of those would otherwise notify the model's property and re-set a
+// label to the text it already shows. The last submission is either still
+// queued or already applied, so skipping an equal one changes nothing on
+// screen. The mute slot keeps plain submit, as its drain already reports only
+// changes.
+//
+// Once the bar window is attached (sync_to_frames), the flush is a tick
+// callback: it runs in the frame clock's update phase, right before layout
+// and paint, so everything submitted since the previous frame lands in the
+// same one. Without that, a workspace switch whose title event arrives a few
+// milliseconds after the workspace event could paint the new workspace's
+// color under the old title for one frame. The compositor may stop sending
+// frame callbacks while the bar is covered, so a deadline flushes the batch
+// anyway when no frame comes; before the window is mapped the flush runs at
+// HIGH_IDLE, which glib dispatches ahead of GDK's redraw priority.
+//
+// GTK-main-thread only (Rc, not Arc); producers on tokio keep using the Bus.
+
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+use gtk4::glib;
+use gtk4::prelude::*;
+use tracing::trace;
+
+// Three frames at 60 Hz: long enough for a slow frame, short enough that a
+// covered bar still follows along.
+const FRAME_DEADLINE: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Slot {
+    Workspace,
+    // The title color, derived from the workspace (see views.rs).
+    WorkspaceColor,
+    WindowCount,
+    Floating,
+    Scratchpad,
+    Split,
+    Title,
+    Battery,
+    Bluetooth,
+    Network,
+    Displays,
+    CpuFreq,
+    Vpn,
+    Hotspot,
+    Rfkill,
+    // The quick-settings sliders, derived from the AppState (see views.rs).
+    QuickVolume,
+    QuickBrightness,
+    Units,
+    Updates,
+    Clipboard,
+    Gpu,
+    LoadAvg,
+    Bandwidth,
+    Notifications,
+    Progress,
+    KbdBacklight,
+    LockKeys,
+    Taskbar,
+    Volume,
+    // Submitted only when the mute state changes, so a refresh that follows a
+    // toggle in the same batch cannot overwrite the toggle.
+    Mute,
+    Privacy,
+    Headset,
+    // The taskbar's audio badges, apart from its window list so neither
+    // supersedes the other.
+    Playback,
+}
+
+type Apply = Box<dyn FnOnce()>;
+
+// The queue without the main-loop scheduling, so the coalescing rules are
+// testable without a GTK display.
+#[derive(Default)]
+struct PendingUpdates {
+    entries: Vec<(Slot, Apply)>,
+    // Counts takes, so a frame tick or deadline scheduled for a batch that
+    // has already been flushed by the other one can tell.
+    batch: u64,
+    // The value each slot was last submitted with through submit_changed.
+    submitted: HashMap<Slot, Box<dyn Any>>,
+}
+
+impl PendingUpdates {
+    // Replace a queued update for the same slot in place (keeping its original
+    // position) or append a new one. Returns true when the queue was empty,
+    // i.e. when the caller needs to schedule a flush.
+    fn push(&mut self, slot: Slot, apply: Apply) -> bool {
+        let was_empty = self.entries.is_empty();
+        match self.entries.iter_mut().find(|(queued, _)| *queued == slot) {
+            Some(entry) => {
+                trace!(?slot, "Coalescing superseded UI update");
+                entry.1 = apply;
+            }
+            None => self.entries.push((slot, apply)),
+        }
+        was_empty
+    }
+
+    // Records `value` for `slot`; false when it equals the recorded one.
+    fn changed<T: Clone + PartialEq + 'static>(&mut self, slot: Slot, value: &T) -> bool {
+        let last = self
+            .submitted
+            .get(&slot)
+            .and_then(|last| last.downcast_ref::<T>());
+        if last == Some(value) {
+            return false;
+        }
+        self.submitted.insert(slot, Box::new(value.clone()));
+        true
+    }
+
+    fn take(&mut self) -> Vec<(Slot, Apply)> {
+        self.batch += 1;
+        std::mem::take(&mut self.entries)
+    }
+
+    // The batch's updates, or nothing when `batch` was already taken.
+    fn take_batch(&mut self, batch: u64) -> Vec<(Slot, Apply)> {
+        if batch != self.batch {
+            return Vec::new();
+        }
+        self.take()
+    }
+}
+
+// A flush is scheduled exactly when the queue goes from empty to non-empty,
+// so no separate "scheduled" flag is needed.
+#[derive(Clone, Default)]
+pub struct UiDispatcher {
+    pending: Rc<RefCell<PendingUpdates>>,
+    frames: Rc<RefCell<Option<glib::WeakRef<gtk4::Widget>>>>,
+}
+
+impl UiDispatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Flush on `widget`'s frame clock from now on (see the top of the file).
+    pub fn sync_to_frames(&self, widget: &impl IsA<gtk4::Widget>) {
+        *self.frames.borrow_mut() = Some(widget.upcast_ref::<gtk4::Widget>().downgrade());
+    }
+
+    // Queue `apply` for `slot`, superseding anything still pending for it,
+    // and make sure exactly one flush is scheduled.
+    pub fn submit(&self, slot: Slot, apply: impl FnOnce() + 'static) {
+        let first = self.pending.borrow_mut().push(slot, Box::new(apply));
+        if first {
+            self.schedule();
+        }
+    }
+
+    // submit for an update that is a value: `apply` gets it at flush time,
+    // unless it equals the slot's previous submission, which is dropped here.
+    pub fn submit_changed<T: Clone + PartialEq + 'static>(
+        &self,
+        slot: Slot,
+        value: T,
+        apply: impl FnOnce(T) + 'static,
+    ) {
+        if !self.pending.borrow_mut().changed(slot, &value) {
+            trace!(?slot, "Skipping unchanged UI update");
+            return;
+        }
+        self.submit(slot, move || apply(value));
+    }
+
+    fn schedule(&self) {
+        let batch = self.pending.borrow().batch;
+        let widget = self
+            .frames
+            .borrow()
+            .as_ref()
+            .and_then(|widget| widget.upgrade())
+            .filter(|widget| widget.is_mapped());
+        let Some(widget) = widget else {
+            let dispatcher = self.clone();
+            glib::idle_add_local_full(glib::Priority::HIGH_IDLE, move || {
+                dispatcher.flush(batch);
+                glib::ControlFlow::Break
+            });
+            return;
+        };
+        let dispatcher = self.clone();
+        widget.add_tick_callback(move |_widget, _clock| {
+            dispatcher.flush(batch);
+            glib::ControlFlow::Break
+        });
+        let dispatcher = self.clone();
+        glib::timeout_add_local_once(FRAME_DEADLINE, move || {
+            if dispatcher.pending.borrow().batch == batch {
+                trace!("No frame before the deadline; flushing UI updates anyway");
+            }
+            dispatcher.flush(batch);
+        });
+    }
+
+    fn flush(&self, batch: u64) {
+        // Take the batch before applying it: an apply closure that submits
+        // again (or a nested main-loop iteration) must queue into the next
+        // frame rather than re-borrow the queue being drained.
+        let batch = self.pending.borrow_mut().take_batch(batch);
+        if batch.is_empty() {
+            return;
+        }
+        trace!(updates = batch.len(), "Flushing UI updates");
+        for (_slot, apply) in batch {
+            apply();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorder(log: &Rc<RefCell<Vec<&'static str>>>, entry: &'static str) -> Apply {
+        let log = log.clone();
+        Box::new(move || log.borrow_mut().push(entry))
+    }
+
+    #[test]
+    fn later_update_for_the_same_slot_wins() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut pending = PendingUpdates::default();
+        assert!(pending.push(Slot::Battery, recorder(&log, "battery 80")));
+        assert!(!pending.push(Slot::Battery, recorder(&log, "battery 79")));
+
+        for (_, apply) in pending.take() {
+            apply();
+        }
+        assert_eq!(*log.borrow(), vec!["battery 79"]);
+    }
+
+    #[test]
+    fn slots_flush_in_first_submission_order() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut pending = PendingUpdates::default();
+        pending.push(Slot::Workspace, recorder(&log, "workspace 1"));
+        pending.push(Slot::Title, recorder(&log, "title a"));
+        pending.push(Slot::Workspace, recorder(&log, "workspace 2"));
+
+        let batch = pending.take();
+        assert_eq!(
+            batch.iter().map(|(slot, _)| *slot).collect::<Vec<_>>(),
+            vec![Slot::Workspace, Slot::Title]
+        );
+        for (_, apply) in batch {
+            apply();
+        }
+        assert_eq!(*log.borrow(), vec!["workspace 2", "title a"]);
+    }
+
+    #[test]
+    fn take_empties_the_queue() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut pending = PendingUpdates::default();
+        pending.push(Slot::Network, recorder(&log, "net"));
+        assert_eq!(pending.take().len(), 1);
+        assert!(pending.take().is_empty());
+        assert!(pending.push(Slot::Network, recorder(&log, "net again")));
+    }
+
+    #[test]
+    fn only_a_changed_value_is_submitted() {
+        let mut pending = PendingUpdates::default();
+        assert!(pending.changed(Slot::Volume, &"🔊S40".to_string()));
+        assert!(!pending.changed(Slot::Volume, &"🔊S40".to_string()));
+        // Slots are independent.
+        assert!(pending.changed(Slot::Battery, &"🔊S40".to_string()));
+        assert!(pending.changed(Slot::Volume, &"🔊S41".to_string()));
+        // Going back to an earlier value is a change too.
+        assert!(pending.changed(Slot::Volume, &"🔊S40".to_string()));
+        // Taking a batch does not forget what is on screen.
+        pending.take();
+        assert!(!pending.changed(Slot::Volume, &"🔊S40".to_string()));
+    }
+
+    #[test]
+    fn a_batch_is_flushed_once() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut pending = PendingUpdates::default();
+        pending.push(Slot::Workspace, recorder(&log, "workspace 1"));
+        let first = pending.batch;
+        // The frame tick flushes it; the deadline finds it gone, and must not
+        // flush the next batch ahead of its own frame.
+        assert_eq!(pending.take_batch(first).len(), 1);
+        pending.push(Slot::Title, recorder(&log, "title a"));
+        assert!(pending.take_batch(first).is_empty());
+        assert_eq!(pending.take_batch(pending.batch).len(), 1);
+    }
+}
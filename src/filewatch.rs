@@ -0,0 +1,183 @@
+// Generic file-watch widgets (`[[file_watch]]` in config.toml): a label showing
+// a file's contents, or a regex capture from them, so one-off sysfs/procfs
+// readouts (a temperature, a fan speed, a backlight level) need no dedicated
+// producer.
+//
+// Unlike the subsystem producers this runs entirely on the GTK main thread:
+// GIO reads the file asynchronously on its own worker pool, a GFileMonitor
+// (inotify underneath) reports edits to regular files, and a glib timeout
+// covers /sys and /proc, whose files never report changes. Each label owns its
+// watcher through a weak reference, so dropping the label on a config reload
+// stops the timer and the monitor with it.
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use gtk4::gio;
+use gtk4::gio::prelude::*;
+use gtk4::glib;
+use gtk4::prelude::*;
+use regex::Regex;
+use tracing::{debug, warn};
+
+use crate::config::FileWatchConfig;
+
+// The first capture group, else the whole match; without a pattern, the
+// trimmed contents. None when the pattern does not match (or the file is
+// empty), which hides the widget.
+fn extract(contents: &str, pattern: Option<&Regex>) -> Option<String> {
+    let Some(pattern) = pattern else {
+        let trimmed = contents.trim();
+        return (!trimmed.is_empty()).then(|| trimmed.to_string());
+    };
+    let captures = pattern.captures(contents)?;
+    let value = captures.get(1).or_else(|| captures.get(0))?;
+    Some(value.as_str().trim().to_string())
+}
+
+fn render(format: &str, value: &str) -> String {
+    format.replace("{}", value)
+}
+
+struct Watcher {
+    label: glib::WeakRef<gtk4::Label>,
+    file: gio::File,
+    pattern: Option<Regex>,
+    format: String,
+    name: String,
+    // A read still in flight absorbs further triggers; a monitor burst (write
+    // + attribute change + done hint) would otherwise queue several reads.
+    reading: Cell<bool>,
+}
+
+impl Watcher {
+    fn refresh(self: &Rc<Self>) {
+        if self.reading.replace(true) {
+            return;
+        }
+        let watcher = self.clone();
+        glib::spawn_future_local(async move {
+            let result = watcher.file.load_contents_future().await;
+            watcher.reading.set(false);
+            let Some(label) = watcher.label.upgrade() else {
+                return;
+            };
+            let contents = match result {
+                Ok((contents, _etag)) => contents,
+                Err(e) => {
+                    debug!(name = watcher.name, "Could not read watched file: {}", e);
+                    label.set_visible(false);
+                    return;
+                }
+            };
+            let contents = String::from_utf8_lossy(&contents);
+            match extract(&contents, watcher.pattern.as_ref()) {
+                Some(value) => {
+                    label.set_text(&render(&watcher.format, &value));
+                    label.set_visible(true);
+                }
+                None => label.set_visible(false),
+            }
+        });
+    }
+}
+
+pub fn watch(label: &gtk4::Label, config: &FileWatchConfig) {
+    // config::parse already rejected invalid patterns; this only guards a
+    // config built some other way.
+    let pattern = match config.pattern.as_deref().map(Regex::new) {
+        None => None,
+        Some(Ok(pattern)) => Some(pattern),
+        Some(Err(e)) => {
+            warn!(name = config.name, "Invalid file watch pattern: {}", e);
+            return;
+        }
+    };
+    let watcher = Rc::new(Watcher {
+        label: label.downgrade(),
+        file: gio::File::for_path(&config.path),
+        pattern,
+        format: config.format.clone(),
+        name: config.name.clone(),
+        reading: Cell::new(false),
+    });
+    watcher.refresh();
+
+    // The monitor stops reporting once dropped, so whichever closure below
+    // outlives the other owns it: the polling timeout, or, with polling off,
+    // the label's destroy handler.
+    let monitor = match watcher
+        .file
+        .monitor_file(gio::FileMonitorFlags::NONE, gio::Cancellable::NONE)
+    {
+        Ok(monitor) => {
+            let watcher = watcher.clone();
+            monitor.connect_changed(move |_, _, _, event| {
+                if matches!(
+                    event,
+                    gio::FileMonitorEvent::ChangesDoneHint | gio::FileMonitorEvent::Created
+                ) {
+                    watcher.refresh();
+                }
+            });
+            Some(monitor)
+        }
+        Err(e) => {
+            debug!(
+                name = config.name,
+                "No change notifications for watched file: {}", e
+            );
+            None
+        }
+    };
+
+    if config.interval_seconds == 0 {
+        if let Some(monitor) = monitor {
+            label.connect_destroy(move |_| {
+                monitor.cancel();
+            });
+        }
+        return;
+    }
+    glib::timeout_add_seconds_local(config.interval_seconds, move || {
+        if watcher.label.upgrade().is_none() {
+            if let Some(monitor) = &monitor {
+                monitor.cancel();
+            }
+            return glib::ControlFlow::Break;
+        }
+        watcher.refresh();
+        glib::ControlFlow::Continue
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whole_file_is_trimmed() {
+        assert_eq!(extract("42000\n", None), Some("42000".to_string()));
+        assert_eq!(extract(" \n", None), None);
+    }
+
+    #[test]
+    fn first_capture_group_wins_over_the_match() {
+        let millidegrees = Regex::new(r"(\d+)\d{3}").unwrap();
+        assert_eq!(
+            extract("47000\n", Some(&millidegrees)),
+            Some("47".to_string())
+        );
+        let no_groups = Regex::new(r"\d+ kB").unwrap();
+        assert_eq!(
+            extract("MemFree: 1024 kB\n", Some(&no_groups)),
+            Some("1024 kB".to_string())
+        );
+        assert_eq!(extract("none here", Some(&no_groups)), None);
+    }
+
+    #[test]
+    fn format_places_the_value() {
+        assert_eq!(render("🌡 {}°C", "47"), "🌡 47°C");
+    }
+}
@@ -0,0 +1,177 @@
+// Number and unit formatting shared by the numeric widgets, so `[format]`
+// applies to all of them at once: the decimal separator, KiB against kB, and
+// °C against °F. A Format is resolved from the config once (the locale lookup
+// included) and handed to producers by value next to their own section.
+
+use std::env;
+
+use crate::config::{ByteUnits, DecimalSeparator, FormatConfig, TemperatureUnit};
+
+const SHORT_UNITS: [&str; 4] = ["B", "K", "M", "G"];
+const BINARY_UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+const DECIMAL_UNITS: [&str; 4] = ["B", "kB", "MB", "GB"];
+
+// Languages writing a decimal point; most others write a comma.
+const POINT_LANGUAGES: &[&str] = &[
+    "C", "POSIX", "en", "ja", "zh", "ko", "he", "th", "hi", "bn", "ta", "te", "ur", "ga", "mt",
+    "ms", "fil", "tl", "sw", "my",
+];
+// Regions that write a point although their language mostly does not.
+const POINT_REGIONS: &[&str] = &[
+    "de_CH", "de_LI", "it_CH", "fr_CH", "es_MX", "es_US", "es_PR",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Format {
+    comma: bool,
+    bytes: ByteUnits,
+    temperature: TemperatureUnit,
+}
+
+// "de_DE.UTF-8" -> true, "en_US.UTF-8" -> false, "de_CH.UTF-8" -> false.
+pub fn locale_uses_comma(locale: &str) -> bool {
+    let region = locale.split(['.', '@']).next().unwrap_or_default();
+    let language = region.split('_').next().unwrap_or_default();
+    !language.is_empty() && !POINT_LANGUAGES.contains(&language) && !POINT_REGIONS.contains(&region)
+}
+
+// setlocale(3)'s precedence: LC_ALL over LC_NUMERIC over LANG.
+fn numeric_locale() -> Option<String> {
+    ["LC_ALL", "LC_NUMERIC", "LANG"]
+        .into_iter()
+        .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+}
+
+impl Format {
+    pub fn new(config: &FormatConfig) -> Self {
+        let comma = match config.decimal_separator {
+            DecimalSeparator::Auto => {
+                numeric_locale().is_some_and(|locale| locale_uses_comma(&locale))
+            }
+            DecimalSeparator::Point => false,
+            DecimalSeparator::Comma => true,
+        };
+        Self {
+            comma,
+            bytes: config.byte_units,
+            temperature: config.temperature,
+        }
+    }
+
+    pub fn number(&self, value: f64, precision: usize) -> String {
+        let text = format!("{value:.precision$}");
+        if self.comma {
+            text.replace('.', ",")
+        } else {
+            text
+        }
+    }
+
+    fn base(&self) -> f64 {
+        match self.bytes {
+            ByteUnits::Decimal => 1000.0,
+            ByteUnits::Short | ByteUnits::Binary => 1024.0,
+        }
+    }
+
+    // Tooltips have room for the full names even in the short style.
+    fn unit(&self, power: usize, tooltip: bool) -> &'static str {
+        match self.bytes {
+            ByteUnits::Short if !tooltip => SHORT_UNITS[power],
+            ByteUnits::Short | ByteUnits::Binary => BINARY_UNITS[power],
+            ByteUnits::Decimal => DECIMAL_UNITS[power],
+        }
+    }
+
+    // Scaled below 1000 of the largest unit that allows it, so the number
+    // never needs more than three digits.
+    fn scale(&self, bytes: f64) -> (f64, usize) {
+        let mut value = bytes.max(0.0);
+        let mut power = 0;
+        while value >= 1000.0 && power < SHORT_UNITS.len() - 1 {
+            value /= self.base();
+            power += 1;
+        }
+        (value, power)
+    }
+
+    // One decimal only while it says something.
+    fn scaled_number(&self, value: f64, power: usize) -> String {
+        let precision = if power == 0 || value >= 10.0 { 0 } else { 1 };
+        self.number(value, precision)
+    }
+
+    // Bytes per second in at most four characters plus a unit, so the
+    // widget does not jitter as the rate changes.
+    pub fn rate(&self, bytes_per_second: f64) -> String {
+        let (value, power) = self.scale(bytes_per_second);
+        format!(
+            "{:>4}{}",
+            self.scaled_number(value, power),
+            self.unit(power, false)
+        )
+    }
+
+    // A size for tooltips: "51 MiB", "1,5 GiB".
+    pub fn size(&self, bytes: u64) -> String {
+        let (value, power) = self.scale(bytes as f64);
+        format!(
+            "{} {}",
+            self.scaled_number(value, power),
+            self.unit(power, true)
+        )
+    }
+
+    // "2.0/8.0G": both in the unit the total scales to.
+    pub fn used_of_total(&self, used: u64, total: u64) -> String {
+        let (total, power) = self.scale(total as f64);
+        let used = used as f64 / self.base().powi(power as i32);
+        format!(
+            "{}/{}{}",
+            self.number(used, 1),
+            self.number(total, 1),
+            self.unit(power, false)
+        )
+    }
+
+    pub fn temperature(&self, celsius: f64) -> String {
+        match self.temperature {
+            TemperatureUnit::Celsius => format!("{}°C", self.number(celsius, 0)),
+            TemperatureUnit::Fahrenheit => {
+                format!("{}°F", self.number(celsius * 9.0 / 5.0 + 32.0, 0))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units_and_separators_follow_the_config() {
+        let short = Format::default();
+        assert_eq!(short.rate(1536.0), " 1.5K");
+        assert_eq!(short.size(52224 * 1024), "51 MiB");
+        assert_eq!(short.used_of_total(2 << 30, 8 << 30), "2.0/8.0G");
+        assert_eq!(short.temperature(86.0), "86°C");
+
+        let european = Format::new(&FormatConfig {
+            decimal_separator: DecimalSeparator::Comma,
+            byte_units: ByteUnits::Decimal,
+            temperature: TemperatureUnit::Fahrenheit,
+        });
+        assert_eq!(european.rate(1500.0), " 1,5kB");
+        assert_eq!(european.size(1_500_000_000), "1,5 GB");
+        assert_eq!(european.temperature(100.0), "212°F");
+    }
+
+    #[test]
+    fn locales_pick_their_decimal_separator() {
+        assert!(locale_uses_comma("de_DE.UTF-8"));
+        assert!(locale_uses_comma("pt_BR.UTF-8"));
+        assert!(!locale_uses_comma("en_US.UTF-8"));
+        assert!(!locale_uses_comma("de_CH.UTF-8"));
+        assert!(!locale_uses_comma("C.UTF-8"));
+    }
+}
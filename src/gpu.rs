@@ -0,0 +1,232 @@
+// GPU load: utilization, VRAM in use and temperature, polled every `[gpu]
+// interval_seconds`. AMD cards come from amdgpu's sysfs files
+// (gpu_busy_percent, mem_info_vram_used/total, and the card's hwmon
+// temp1_input); NVIDIA cards from the driver's NVML library, compiled in with
+// the `nvidia` feature so nobody else links against it. Like cpufreq.rs this
+// polls, since neither source has change notification.
+//
+// The first AMD card wins, then the first NVML device; with neither the
+// monitor ends and the widget stays hidden. Crossing `[gpu]
+// warning_busy_percent` or `warning_temperature` marks the widget with the
+// `warning` class.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tracing::{debug, info, warn};
+
+use crate::bus::{Bus, GpuUpdate};
+use crate::config::GpuConfig;
+use crate::format::Format;
+use crate::resume;
+
+const DRM_ROOT: &str = "/sys/class/drm";
+const ICON: &str = "\u{f08ae}";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GpuSample {
+    pub busy_percent: u32,
+    pub vram_used_bytes: u64,
+    pub vram_total_bytes: u64,
+    pub temperature_celsius: Option<u32>,
+}
+
+enum Source {
+    // The card's `device` directory.
+    Amd(PathBuf),
+    #[cfg(feature = "nvidia")]
+    Nvidia(nvml_wrapper::Nvml),
+}
+
+fn read_number(path: &Path) -> Result<u64> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    text.trim()
+        .parse()
+        .with_context(|| format!("parse {}", path.display()))
+}
+
+// cardN, not the connector entries (card0-DP-1) next to it.
+fn amd_device(root: &Path) -> Option<PathBuf> {
+    let mut cards: Vec<PathBuf> = std::fs::read_dir(root)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix("card"))
+                .is_some_and(|index| index.chars().all(|c| c.is_ascii_digit()))
+        })
+        .map(|entry| entry.path().join("device"))
+        .filter(|device| device.join("gpu_busy_percent").exists())
+        .collect();
+    cards.sort();
+    cards.into_iter().next()
+}
+
+// hwmon/hwmonN/temp1_input is the edge sensor, in millidegrees.
+fn amd_temperature(device: &Path) -> Option<u32> {
+    let hwmon = std::fs::read_dir(device.join("hwmon"))
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| path.join("temp1_input").exists())?;
+    let millidegrees = read_number(&hwmon.join("temp1_input")).ok()?;
+    u32::try_from(millidegrees / 1000).ok()
+}
+
+pub fn read_amd(device: &Path) -> Result<GpuSample> {
+    let busy = read_number(&device.join("gpu_busy_percent"))?;
+    Ok(GpuSample {
+        busy_percent: u32::try_from(busy.min(100)).unwrap_or(100),
+        vram_used_bytes: read_number(&device.join("mem_info_vram_used"))?,
+        vram_total_bytes: read_number(&device.join("mem_info_vram_total"))?,
+        temperature_celsius: amd_temperature(device),
+    })
+}
+
+#[cfg(feature = "nvidia")]
+fn read_nvidia(nvml: &nvml_wrapper::Nvml) -> Result<GpuSample> {
+    use nvml_wrapper::enum_wrappers::device::TemperatureSensor;
+
+    let device = nvml.device_by_index(0).context("open NVML device 0")?;
+    let utilization = device.utilization_rates().context("read GPU utilization")?;
+    let memory = device.memory_info().context("read GPU memory")?;
+    Ok(GpuSample {
+        busy_percent: utilization.gpu.min(100),
+        vram_used_bytes: memory.used,
+        vram_total_bytes: memory.total,
+        temperature_celsius: device.temperature(TemperatureSensor::Gpu).ok(),
+    })
+}
+
+fn detect() -> Option<Source> {
+    if let Some(device) = amd_device(Path::new(DRM_ROOT)) {
+        info!(device = %device.display(), "Reading GPU load from amdgpu");
+        return Some(Source::Amd(device));
+    }
+    #[cfg(feature = "nvidia")]
+    match nvml_wrapper::Nvml::init() {
+        Ok(nvml) => {
+            info!("Reading GPU load from NVML");
+            return Some(Source::Nvidia(nvml));
+        }
+        Err(e) => debug!("NVML unavailable: {}", e),
+    }
+    None
+}
+
+fn read(source: &Source) -> Result<GpuSample> {
+    match source {
+        Source::Amd(device) => read_amd(device),
+        #[cfg(feature = "nvidia")]
+        Source::Nvidia(nvml) => read_nvidia(nvml),
+    }
+}
+
+pub fn display_text(sample: &GpuSample, format: &Format) -> String {
+    let mut text = format!(
+        "{ICON} {}% {}",
+        sample.busy_percent,
+        format.used_of_total(sample.vram_used_bytes, sample.vram_total_bytes)
+    );
+    if let Some(celsius) = sample.temperature_celsius {
+        text.push_str(&format!(" {}", format.temperature(f64::from(celsius))));
+    }
+    text
+}
+
+pub fn is_warning(sample: &GpuSample, config: &GpuConfig) -> bool {
+    sample.busy_percent >= config.warning_busy_percent
+        || sample
+            .temperature_celsius
+            .is_some_and(|celsius| celsius >= config.warning_temperature)
+}
+
+pub async fn run_gpu_updates(bus: Bus, config: GpuConfig, format: Format) {
+    let Some(source) = detect() else {
+        info!("No supported GPU found; the GPU widget stays hidden");
+        return;
+    };
+    let period = Duration::from_secs(u64::from(config.interval_seconds.max(1)));
+    let mut interval = tokio::time::interval(period);
+    let mut resumes = bus.resumes();
+    let mut last = None;
+    let mut failures = 0u32;
+    loop {
+        resume::tick_or_resume(&mut interval, &mut resumes).await;
+        let update = match read(&source) {
+            Ok(sample) => {
+                failures = 0;
+                GpuUpdate {
+                    text: display_text(&sample, &format),
+                    warning: is_warning(&sample, &config),
+                }
+            }
+            Err(e) => {
+                // A card that went away (eGPU unplugged, driver reloaded)
+                // hides the widget rather than freezing its last reading.
+                failures += 1;
+                if failures == 1 {
+                    warn!("Failed to read GPU load: {:#}", e);
+                }
+                GpuUpdate::default()
+            }
+        };
+        if last.as_ref() == Some(&update) {
+            continue;
+        }
+        last = Some(update.clone());
+        debug!(?update, "Sending GPU load");
+        if let Err(e) = bus.send_gpu_update(update) {
+            warn!("GPU consumer is gone: {:#}", e);
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn reads_amdgpu_sysfs() {
+        let root = TempDir::new("gpu");
+        let device = root.join("card1").join("device");
+        let hwmon = device.join("hwmon").join("hwmon3");
+        std::fs::create_dir_all(&hwmon).expect("create fake card");
+        std::fs::create_dir_all(root.join("card1-DP-1")).expect("create fake connector");
+        std::fs::write(device.join("gpu_busy_percent"), "37\n").expect("write busy");
+        std::fs::write(device.join("mem_info_vram_used"), "2147483648\n").expect("write used");
+        std::fs::write(device.join("mem_info_vram_total"), "8589934592\n").expect("write total");
+        std::fs::write(hwmon.join("temp1_input"), "86000\n").expect("write temperature");
+
+        let found = amd_device(&root);
+        let sample = read_amd(&device);
+        assert_eq!(found, Some(device));
+        let sample = sample.expect("fake card should read");
+        assert_eq!(
+            sample,
+            GpuSample {
+                busy_percent: 37,
+                vram_used_bytes: 2 << 30,
+                vram_total_bytes: 8 << 30,
+                temperature_celsius: Some(86),
+            }
+        );
+        assert_eq!(
+            display_text(&sample, &Format::default()),
+            format!("{ICON} 37% 2.0/8.0G 86°C")
+        );
+        assert!(is_warning(&sample, &GpuConfig::default()));
+        assert!(!is_warning(
+            &GpuSample {
+                temperature_celsius: None,
+                ..sample
+            },
+            &GpuConfig::default()
+        ));
+    }
+}
@@ -0,0 +1,294 @@
+// User hooks: `[hooks]` commands run on bar events, with the event's data in
+// environment variables (all prefixed BAR_, listed in the README):
+//
+//   on_workspace_change  the active workspace changed
+//   on_battery_low       the battery dropped to `battery_low_percent` while
+//                        discharging (once per discharge)
+//   on_bt_connected      a Bluetooth device connected
+//
+// Producers report events through Bus::send_hook_event whether or not any
+// hook is configured; this module decides which of them are worth a run.
+//
+// Hooks are user scripts run on someone else's schedule, so they get a narrow
+// environment: `sh -c` in the home directory with stdin closed, only the
+// session variables a desktop script needs plus the event's, in a process
+// group of their own, and killed with that group after HOOK_DEADLINE. Each
+// hook runs at most once at a time and at most once per
+// `min_interval_seconds`; events arriving meanwhile collapse into one run with
+// the latest data, so flicking through workspaces does not fork a shell per
+// keypress.
+
+use std::os::unix::process::CommandExt;
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use tokio::io::AsyncReadExt;
+use tokio::process::Command;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinSet;
+use tracing::{debug, info, warn};
+
+use crate::bus::HookEvent;
+use crate::config::HooksConfig;
+
+const HOOK_DEADLINE: Duration = Duration::from_secs(30);
+
+// Passed through from the bar's own environment; everything else is dropped.
+const INHERITED_VARIABLES: &[&str] = &[
+    "HOME",
+    "PATH",
+    "USER",
+    "LANG",
+    "LC_ALL",
+    "XDG_RUNTIME_DIR",
+    "XDG_CONFIG_HOME",
+    "XDG_DATA_HOME",
+    "WAYLAND_DISPLAY",
+    "DISPLAY",
+    "DBUS_SESSION_BUS_ADDRESS",
+    "HYPRLAND_INSTANCE_SIGNATURE",
+    "SWAYSOCK",
+];
+
+type Environment = Vec<(&'static str, String)>;
+
+// What earlier events left behind, to tell which new ones are news.
+#[derive(Debug, Default)]
+struct Triggers {
+    last_workspace: Option<i32>,
+    battery_was_low: bool,
+}
+
+impl Triggers {
+    // The event's hook and variables, or None when it should not run.
+    fn check(&mut self, event: &HookEvent, config: &HooksConfig) -> Option<(Hook, Environment)> {
+        match event {
+            HookEvent::Workspace { id, name } => {
+                if self.last_workspace.replace(*id) == Some(*id) {
+                    return None;
+                }
+                Some((
+                    Hook::WorkspaceChange,
+                    vec![
+                        ("BAR_WORKSPACE_ID", id.to_string()),
+                        ("BAR_WORKSPACE_NAME", name.clone()),
+                    ],
+                ))
+            }
+            HookEvent::Battery {
+                percent,
+                discharging,
+            } => {
+                let low = *discharging && *percent <= f64::from(config.battery_low_percent);
+                let was_low = std::mem::replace(&mut self.battery_was_low, low);
+                if !low || was_low {
+                    return None;
+                }
+                Some((
+                    Hook::BatteryLow,
+                    vec![("BAR_BATTERY_PERCENT", format!("{percent:.0}"))],
+                ))
+            }
+            HookEvent::BluetoothConnected { name, address } => Some((
+                Hook::BluetoothConnected,
+                vec![
+                    ("BAR_BT_NAME", name.clone()),
+                    ("BAR_BT_ADDRESS", address.clone()),
+                ],
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Hook {
+    WorkspaceChange,
+    BatteryLow,
+    BluetoothConnected,
+}
+
+impl Hook {
+    const ALL: [Hook; 3] = [
+        Hook::WorkspaceChange,
+        Hook::BatteryLow,
+        Hook::BluetoothConnected,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Hook::WorkspaceChange => "on_workspace_change",
+            Hook::BatteryLow => "on_battery_low",
+            Hook::BluetoothConnected => "on_bt_connected",
+        }
+    }
+
+    fn command(self, config: &HooksConfig) -> Option<&str> {
+        match self {
+            Hook::WorkspaceChange => config.on_workspace_change.as_deref(),
+            Hook::BatteryLow => config.on_battery_low.as_deref(),
+            Hook::BluetoothConnected => config.on_bt_connected.as_deref(),
+        }
+    }
+}
+
+async fn spawn_hook(hook: Hook, command: &str, environment: &Environment) -> Result<()> {
+    let mut process = std::process::Command::new("sh");
+    process
+        .arg("-c")
+        .arg(command)
+        .env_clear()
+        .envs(
+            INHERITED_VARIABLES
+                .iter()
+                .filter_map(|name| Some((*name, std::env::var_os(name)?))),
+        )
+        .env("BAR_EVENT", hook.name())
+        .envs(environment.iter().map(|(name, value)| (*name, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        // Out of the bar's process group, so a Ctrl-C meant for the bar does
+        // not reach the hook and vice versa.
+        .process_group(0);
+    if let Some(home) = std::env::var_os("HOME") {
+        process.current_dir(home);
+    }
+    let mut child = Command::from(process)
+        .kill_on_drop(true)
+        .spawn()
+        .context("launch hook")?;
+    let mut stderr = child.stderr.take().context("hook has no stderr pipe")?;
+    let mut message = Vec::new();
+    let finished = tokio::time::timeout(HOOK_DEADLINE, async {
+        let (read, status) = tokio::join!(stderr.read_to_end(&mut message), child.wait());
+        read.context("read hook stderr")?;
+        status.context("wait for hook")
+    })
+    .await;
+    let Ok(status) = finished else {
+        // Killing `sh` alone would leave whatever the hook started in the
+        // background running, so the whole group goes.
+        if let Some(pid) = child.id() {
+            kill_group(pid);
+        }
+        child.wait().await.context("reap timed-out hook")?;
+        bail!("hook exceeded its deadline and was killed");
+    };
+    let status = status?;
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&message);
+        bail!("hook failed ({}): {}", status, stderr.trim());
+    }
+    Ok(())
+}
+
+// The hook's shell leads its own process group (process_group(0)), so its pid
+// is the group's id.
+fn kill_group(pid: u32) {
+    let Ok(group) = libc::pid_t::try_from(pid) else {
+        warn!(pid, "Hook pid does not fit a pid_t");
+        return;
+    };
+    // SAFETY: killpg only sends a signal; a stale group id fails with ESRCH.
+    if unsafe { libc::killpg(group, libc::SIGKILL) } != 0 {
+        warn!(
+            group,
+            "Failed to kill the hook's process group: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+// One hook's runs, one after another; the watch channel keeps only the latest
+// pending event.
+async fn run_hook(
+    hook: Hook,
+    command: String,
+    min_interval: Duration,
+    mut pending: watch::Receiver<Environment>,
+) {
+    while pending.changed().await.is_ok() {
+        let environment = pending.borrow_and_update().clone();
+        info!(hook = hook.name(), ?environment, "Running hook");
+        match spawn_hook(hook, &command, &environment).await {
+            Ok(()) => debug!(hook = hook.name(), "Hook finished"),
+            Err(e) => warn!(hook = hook.name(), "Hook failed: {:#}", e),
+        }
+        tokio::time::sleep(min_interval).await;
+    }
+}
+
+pub async fn run_hooks(config: HooksConfig, mut events: mpsc::UnboundedReceiver<HookEvent>) {
+    let min_interval = Duration::from_secs(u64::from(config.min_interval_seconds));
+    // Dropped on return, which aborts the hook tasks with it.
+    let mut runners = JoinSet::new();
+    let mut senders = Vec::new();
+    for hook in Hook::ALL {
+        let Some(command) = hook.command(&config) else {
+            continue;
+        };
+        let (tx, rx) = watch::channel(Vec::new());
+        runners.spawn(run_hook(hook, command.to_string(), min_interval, rx));
+        senders.push((hook, tx));
+    }
+    if senders.is_empty() {
+        debug!("No hooks configured");
+        return;
+    }
+    info!(hooks = senders.len(), "Starting hooks");
+
+    let mut triggers = Triggers::default();
+    while let Some(event) = events.recv().await {
+        let Some((hook, environment)) = triggers.check(&event, &config) else {
+            continue;
+        };
+        if let Some((_, tx)) = senders.iter().find(|(candidate, _)| *candidate == hook) {
+            tx.send_replace(environment);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn battery_low_fires_once_per_discharge() {
+        let config = HooksConfig::default();
+        let mut triggers = Triggers::default();
+        let mut fired = |percent: f64, discharging: bool| {
+            triggers
+                .check(
+                    &HookEvent::Battery {
+                        percent,
+                        discharging,
+                    },
+                    &config,
+                )
+                .is_some()
+        };
+        assert!(!fired(40.0, true));
+        assert!(fired(15.0, true));
+        assert!(!fired(12.0, true));
+        assert!(!fired(12.0, false));
+        assert!(fired(11.0, true));
+    }
+
+    #[test]
+    fn repeated_workspace_reports_are_not_changes() {
+        let config = HooksConfig::default();
+        let mut triggers = Triggers::default();
+        let workspace = |id: i32| HookEvent::Workspace {
+            id,
+            name: id.to_string(),
+        };
+        let (hook, environment) = triggers
+            .check(&workspace(2), &config)
+            .expect("first change");
+        assert_eq!(hook, Hook::WorkspaceChange);
+        assert!(environment.contains(&("BAR_WORKSPACE_ID", "2".to_string())));
+        assert!(triggers.check(&workspace(2), &config).is_none());
+        assert!(triggers.check(&workspace(3), &config).is_some());
+    }
+}
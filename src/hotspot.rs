@@ -0,0 +1,349 @@
+// Hotspot and tethering indicator: 📡 while NetworkManager shares a
+// connection from this machine (a Wi-Fi hotspot, or any connection with the
+// IPv4 method "shared", as over a USB gadget or a cable), 📱 while the
+// machine is online through a phone's USB tethering (an Ethernet device bound
+// to one of the phone drivers). Like vpn.rs, PropertiesChanged on the manager
+// and its active connections wakes a re-read.
+//
+// A shared connection also shows how many clients it has, counted from the
+// kernel's ARP table: the resolved entries on the interfaces it shares on.
+// Clients come and go without NetworkManager noticing, so that count is
+// polled, but only while something is shared. An entry outlives its client
+// by a minute or so, and the count trails a disconnect by as much.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow};
+use tracing::{debug, info, warn};
+use zbus::message::Type as MessageType;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+use zbus::{Connection, MatchRule, Proxy};
+
+use crate::bus::Bus;
+use crate::buses::{BusKind, BusManager};
+use crate::dbus::supervise;
+
+const NETWORK_MANAGER: &str = "org.freedesktop.NetworkManager";
+const NETWORK_MANAGER_PATH: &str = "/org/freedesktop/NetworkManager";
+const NETWORK_MANAGER_IFACE: &str = "org.freedesktop.NetworkManager";
+const ACTIVE_CONNECTION_IFACE: &str = "org.freedesktop.NetworkManager.Connection.Active";
+const ACTIVE_CONNECTION_PATHS: &str = "/org/freedesktop/NetworkManager/ActiveConnection";
+const SETTINGS_CONNECTION_IFACE: &str = "org.freedesktop.NetworkManager.Settings.Connection";
+const DEVICE_IFACE: &str = "org.freedesktop.NetworkManager.Device";
+const PROC_NET_ARP: &str = "/proc/net/arp";
+const CLIENT_POLL_INTERVAL: Duration = Duration::from_secs(10);
+const ICON_HOTSPOT: &str = "📡";
+const ICON_USB_TETHER: &str = "📱";
+
+// NM_ACTIVE_CONNECTION_STATE_ACTIVATED
+const STATE_ACTIVATED: u32 = 2;
+// ATF_COM: the entry has resolved to a hardware address.
+const ARP_FLAG_COMPLETE: u32 = 0x2;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Tethering {
+    // Interfaces this machine shares a connection on.
+    hotspots: Vec<String>,
+    // A phone shares its connection with this machine over USB.
+    usb_tether: bool,
+}
+
+// Android's RNDIS and its CDC successors, and Apple's driver for iPhones.
+fn is_phone_tether_driver(driver: &str) -> bool {
+    matches!(driver, "rndis_host" | "cdc_ether" | "cdc_ncm" | "ipheth")
+}
+
+fn setting(
+    settings: &HashMap<String, HashMap<String, OwnedValue>>,
+    section: &str,
+    key: &str,
+) -> Option<String> {
+    settings
+        .get(section)
+        .and_then(|section| section.get(key))
+        .and_then(|value| String::try_from(value.clone()).ok())
+}
+
+// A hotspot as NetworkManager makes one: access-point mode, or any
+// connection whose clients it hands addresses to.
+fn is_shared(settings: &HashMap<String, HashMap<String, OwnedValue>>) -> bool {
+    setting(settings, "802-11-wireless", "mode").as_deref() == Some("ap")
+        || setting(settings, "ipv4", "method").as_deref() == Some("shared")
+}
+
+// Resolved ARP entries on `interfaces`, from /proc/net/arp's table under its
+// header line.
+fn count_clients(arp: &str, interfaces: &[String]) -> usize {
+    arp.lines()
+        .skip(1)
+        .filter(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (Some(flags), Some(device)) = (fields.get(2), fields.get(5)) else {
+                return false;
+            };
+            let complete = u32::from_str_radix(flags.trim_start_matches("0x"), 16)
+                .is_ok_and(|flags| flags & ARP_FLAG_COMPLETE != 0);
+            complete && interfaces.iter().any(|interface| interface == device)
+        })
+        .count()
+}
+
+// Empty when nothing is shared, which hides the widget. `clients` is None
+// when the ARP table could not be read.
+fn display_text(tethering: &Tethering, clients: Option<usize>) -> String {
+    let mut parts = Vec::new();
+    if !tethering.hotspots.is_empty() {
+        parts.push(match clients {
+            Some(clients) => format!("{ICON_HOTSPOT} {clients}"),
+            None => ICON_HOTSPOT.to_string(),
+        });
+    }
+    if tethering.usb_tether {
+        parts.push(ICON_USB_TETHER.to_string());
+    }
+    parts.join(" ")
+}
+
+fn text_for(tethering: &Tethering) -> String {
+    if tethering.hotspots.is_empty() {
+        return display_text(tethering, None);
+    }
+    let clients = match std::fs::read_to_string(PROC_NET_ARP) {
+        Ok(arp) => Some(count_clients(&arp, &tethering.hotspots)),
+        Err(e) => {
+            debug!("Cannot count hotspot clients: {}", e);
+            None
+        }
+    };
+    display_text(tethering, clients)
+}
+
+async fn read_tethering(connection: &Connection) -> Result<Tethering> {
+    let manager = Proxy::new(
+        connection,
+        NETWORK_MANAGER,
+        NETWORK_MANAGER_PATH,
+        NETWORK_MANAGER_IFACE,
+    )
+    .await
+    .context("create NetworkManager root proxy")?;
+    let active: Vec<OwnedObjectPath> = manager
+        .get_property("ActiveConnections")
+        .await
+        .context("read NetworkManager ActiveConnections")?;
+    let mut tethering = Tethering::default();
+    for path in active {
+        let proxy = Proxy::new(
+            connection,
+            NETWORK_MANAGER,
+            path.as_str(),
+            ACTIVE_CONNECTION_IFACE,
+        )
+        .await
+        .context("create NetworkManager active connection proxy")?;
+        // A connection can vanish between the list and its properties; skip
+        // it rather than fail the whole read.
+        let (Ok(state), Ok(connection_type), Ok(settings_path), Ok(devices)) = (
+            proxy.get_property::<u32>("State").await,
+            proxy.get_property::<String>("Type").await,
+            proxy.get_property::<OwnedObjectPath>("Connection").await,
+            proxy.get_property::<Vec<OwnedObjectPath>>("Devices").await,
+        ) else {
+            continue;
+        };
+        if state != STATE_ACTIVATED {
+            continue;
+        }
+        let settings_proxy = Proxy::new(
+            connection,
+            NETWORK_MANAGER,
+            settings_path.as_str(),
+            SETTINGS_CONNECTION_IFACE,
+        )
+        .await
+        .context("create NetworkManager connection proxy")?;
+        let Ok(settings) = settings_proxy
+            .call::<_, _, HashMap<String, HashMap<String, OwnedValue>>>("GetSettings", &())
+            .await
+        else {
+            continue;
+        };
+        let shared = is_shared(&settings);
+        for device in devices {
+            let device = Proxy::new(connection, NETWORK_MANAGER, device.as_str(), DEVICE_IFACE)
+                .await
+                .context("create NetworkManager device proxy")?;
+            if shared {
+                if let Ok(interface) = device.get_property::<String>("Interface").await {
+                    tethering.hotspots.push(interface);
+                }
+            } else if connection_type == "802-3-ethernet"
+                && let Ok(driver) = device.get_property::<String>("Driver").await
+                && is_phone_tether_driver(&driver)
+            {
+                tethering.usb_tether = true;
+            }
+        }
+    }
+    Ok(tethering)
+}
+
+fn send_text(bus: &Bus, text: String) -> Result<()> {
+    debug!(label = text, "Sending hotspot update");
+    bus.send_hotspot_update(text)
+}
+
+fn hotspot_rules() -> Result<[MatchRule<'static>; 2]> {
+    let properties = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender(NETWORK_MANAGER)
+        .context("hotspot properties rule: set sender")?
+        .interface("org.freedesktop.DBus.Properties")
+        .context("hotspot properties rule: set interface")?
+        .member("PropertiesChanged")
+        .context("hotspot properties rule: set member")?
+        .path_namespace(NETWORK_MANAGER_PATH)
+        .context("hotspot properties rule: set path namespace")?
+        .build();
+    let owner = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender("org.freedesktop.DBus")
+        .context("hotspot owner rule: set sender")?
+        .interface("org.freedesktop.DBus")
+        .context("hotspot owner rule: set interface")?
+        .member("NameOwnerChanged")
+        .context("hotspot owner rule: set member")?
+        .arg(0, NETWORK_MANAGER)
+        .context("hotspot owner rule: set service argument")?
+        .build();
+    Ok([properties, owner])
+}
+
+// The manager's ActiveConnections and the active connections' State say when
+// sharing starts and stops.
+fn event_path_is_relevant(path: &str) -> bool {
+    path == NETWORK_MANAGER_PATH || path.starts_with(ACTIVE_CONNECTION_PATHS)
+}
+
+async fn monitor_hotspot(bus: &Bus, buses: &BusManager) -> Result<()> {
+    let mut signals = buses
+        .subscribe(BusKind::System, hotspot_rules()?.to_vec())
+        .await
+        .context("subscribe to NetworkManager hotspot signals")?;
+    let reader = signals.connection().clone();
+
+    let mut tethering = read_tethering(&reader).await?;
+    info!(
+        hotspots = tethering.hotspots.len(),
+        usb_tether = tethering.usb_tether,
+        "Watching NetworkManager for hotspots and tethering"
+    );
+    let mut last = text_for(&tethering);
+    send_text(bus, last.clone())?;
+
+    loop {
+        tokio::select! {
+            message = signals.next() => {
+                let Some(message) = message else {
+                    return Err(anyhow!("NetworkManager D-Bus subscription ended"));
+                };
+                let header = message.header();
+                let member = header.member().map(|member| member.as_str()).unwrap_or_default();
+                let path = header.path().map(|path| path.as_str()).unwrap_or_default();
+                if member == "NameOwnerChanged" {
+                    let Ok((_, _, new_owner)) =
+                        message.body().deserialize::<(String, String, String)>()
+                    else {
+                        warn!("Malformed NetworkManager NameOwnerChanged signal");
+                        continue;
+                    };
+                    if new_owner.is_empty() {
+                        send_text(bus, String::new())?;
+                        return Err(anyhow!("NetworkManager left the bus"));
+                    }
+                } else if !event_path_is_relevant(path) {
+                    continue;
+                }
+                tethering = match read_tethering(&reader).await {
+                    Ok(tethering) => tethering,
+                    Err(e) => {
+                        warn!("Failed to read hotspot state: {:#}", e);
+                        continue;
+                    }
+                };
+            }
+            _ = tokio::time::sleep(CLIENT_POLL_INTERVAL), if !tethering.hotspots.is_empty() => {}
+        }
+        let text = text_for(&tethering);
+        if text != last {
+            send_text(bus, text.clone())?;
+            last = text;
+        }
+    }
+}
+
+pub async fn run_hotspot_monitor_supervised(bus: Bus, buses: BusManager) {
+    supervise("hotspot monitor", || monitor_hotspot(&bus, &buses)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use zbus::zvariant::Value;
+
+    use super::*;
+
+    fn settings(entries: &[(&str, &str, &str)]) -> HashMap<String, HashMap<String, OwnedValue>> {
+        let mut settings: HashMap<String, HashMap<String, OwnedValue>> = HashMap::new();
+        for (section, key, value) in entries {
+            let value = OwnedValue::try_from(Value::from(*value)).expect("string value");
+            settings
+                .entry(section.to_string())
+                .or_default()
+                .insert(key.to_string(), value);
+        }
+        settings
+    }
+
+    #[test]
+    fn access_point_mode_and_shared_method_are_hotspots() {
+        assert!(is_shared(&settings(&[("802-11-wireless", "mode", "ap")])));
+        assert!(is_shared(&settings(&[("ipv4", "method", "shared")])));
+        assert!(!is_shared(&settings(&[
+            ("802-11-wireless", "mode", "infrastructure"),
+            ("ipv4", "method", "auto"),
+        ])));
+        assert!(is_phone_tether_driver("rndis_host"));
+        assert!(is_phone_tether_driver("ipheth"));
+        assert!(!is_phone_tether_driver("r8169"));
+    }
+
+    #[test]
+    fn clients_are_resolved_arp_entries_on_the_shared_interface() {
+        let arp = "\
+IP address       HW type     Flags       HW address            Mask     Device
+10.42.0.23       0x1         0x2         aa:bb:cc:dd:ee:01     *        wlan0
+10.42.0.57       0x1         0x0         00:00:00:00:00:00     *        wlan0
+10.42.0.88       0x1         0x2         aa:bb:cc:dd:ee:02     *        wlan0
+192.168.1.1      0x1         0x2         aa:bb:cc:dd:ee:03     *        enp3s0
+";
+        assert_eq!(count_clients(arp, &["wlan0".to_string()]), 2);
+        assert_eq!(count_clients(arp, &[]), 0);
+    }
+
+    #[test]
+    fn label_shows_what_is_shared_and_hides_otherwise() {
+        assert_eq!(display_text(&Tethering::default(), None), "");
+        let hotspot = Tethering {
+            hotspots: vec!["wlan0".to_string()],
+            usb_tether: false,
+        };
+        assert_eq!(display_text(&hotspot, Some(2)), "📡 2");
+        assert_eq!(display_text(&hotspot, None), "📡");
+        let both = Tethering {
+            usb_tether: true,
+            ..hotspot
+        };
+        assert_eq!(display_text(&both, Some(0)), "📡 0 📱");
+    }
+}
@@ -0,0 +1,157 @@
+// Keyboard backlight: the level UPower's org.freedesktop.UPower.KbdBacklight
+// reports, as "level/max" since keyboards have a handful of steps rather than
+// a percentage. BrightnessChanged covers both the firmware hotkey and
+// SetBrightness from anyone (this widget included), so the label only ever
+// follows the signal. UPower exports the object only when it found a
+// backlight LED; without one the monitor ends and the widget stays hidden.
+//
+// Scrolling the widget steps the level through SetBrightness, which UPower
+// allows the active session without a polkit prompt.
+
+use std::ops::ControlFlow;
+
+use anyhow::{Context, Result, anyhow};
+use tracing::{debug, info, warn};
+use zbus::message::Type as MessageType;
+use zbus::{Connection, MatchRule, Proxy};
+
+use crate::bus::{Bus, KbdBacklightUpdate};
+use crate::buses::{BusKind, BusManager};
+use crate::dbus::supervise_until;
+
+const UPOWER: &str = "org.freedesktop.UPower";
+const KBD_BACKLIGHT_PATH: &str = "/org/freedesktop/UPower/KbdBacklight";
+const KBD_BACKLIGHT_IFACE: &str = "org.freedesktop.UPower.KbdBacklight";
+const ICON: &str = "\u{f11c}";
+
+// Empty without a backlight, which hides the widget.
+pub fn display_text(level: i32, max: i32) -> String {
+    if max <= 0 {
+        return String::new();
+    }
+    format!("{ICON} {}/{max}", level.clamp(0, max))
+}
+
+fn update(level: i32, max: i32) -> KbdBacklightUpdate {
+    KbdBacklightUpdate {
+        text: display_text(level, max),
+        level,
+        max,
+    }
+}
+
+async fn proxy(connection: &Connection) -> Result<Proxy<'static>> {
+    Proxy::new(connection, UPOWER, KBD_BACKLIGHT_PATH, KBD_BACKLIGHT_IFACE)
+        .await
+        .context("create UPower KbdBacklight proxy")
+}
+
+fn brightness_rule() -> Result<MatchRule<'static>> {
+    Ok(MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender(UPOWER)
+        .context("KbdBacklight rule: set sender")?
+        .interface(KBD_BACKLIGHT_IFACE)
+        .context("KbdBacklight rule: set interface")?
+        .member("BrightnessChanged")
+        .context("KbdBacklight rule: set member")?
+        .path(KBD_BACKLIGHT_PATH)
+        .context("KbdBacklight rule: set path")?
+        .build())
+}
+
+// UPower answers for an object it never exported with one of the Unknown*
+// errors; anything else (UPower not running, say) is worth a retry.
+fn is_missing_backlight(error: &zbus::Error) -> bool {
+    let zbus::Error::MethodError(name, _, _) = error else {
+        return false;
+    };
+    matches!(
+        name.as_str(),
+        "org.freedesktop.DBus.Error.UnknownObject"
+            | "org.freedesktop.DBus.Error.UnknownInterface"
+            | "org.freedesktop.DBus.Error.UnknownMethod"
+    )
+}
+
+// Ok(()) only when there is no backlight to watch.
+async fn monitor_kbd_backlight(bus: &Bus, buses: &BusManager) -> Result<()> {
+    let mut signals = buses
+        .subscribe(BusKind::System, vec![brightness_rule()?])
+        .await
+        .context("subscribe to UPower KbdBacklight signals")?;
+    let backlight = proxy(signals.connection()).await?;
+    let max: i32 = match backlight.call("GetMaxBrightness", &()).await {
+        Ok(max) => max,
+        Err(e) if is_missing_backlight(&e) => {
+            info!("UPower reports no keyboard backlight; the widget stays hidden");
+            return Ok(());
+        }
+        Err(e) => return Err(e).context("read keyboard backlight maximum"),
+    };
+    let mut level: i32 = backlight
+        .call("GetBrightness", &())
+        .await
+        .context("read keyboard backlight level")?;
+    info!(level, max, "Watching keyboard backlight");
+    bus.send_kbd_backlight_update(update(level, max))?;
+
+    while let Some(message) = signals.next().await {
+        let Ok(changed) = message.body().deserialize::<i32>() else {
+            warn!("Malformed KbdBacklight BrightnessChanged signal");
+            continue;
+        };
+        if changed == level {
+            continue;
+        }
+        level = changed;
+        debug!(level, "Keyboard backlight changed");
+        bus.send_kbd_backlight_update(update(level, max))?;
+    }
+    Err(anyhow!("UPower KbdBacklight subscription ended"))
+}
+
+// Ok means UPower has no keyboard backlight to watch, which a restart won't
+// change.
+pub async fn run_kbd_backlight_monitor_supervised(bus: Bus, buses: BusManager) {
+    supervise_until("keyboard backlight monitor", || async {
+        monitor_kbd_backlight(&bus, &buses)
+            .await
+            .map(ControlFlow::Break)
+    })
+    .await
+}
+
+async fn set(level: i32) -> Result<()> {
+    let connection = Connection::system()
+        .await
+        .context("connect to system D-Bus")?;
+    proxy(&connection)
+        .await?
+        .call::<_, _, ()>("SetBrightness", &(level,))
+        .await
+        .context("set keyboard backlight level")?;
+    Ok(())
+}
+
+// The widget's scroll handler: fire-and-forget from the GTK thread, like
+// vpn::toggle_connection. The label updates through BrightnessChanged.
+pub fn set_brightness(level: i32) {
+    tokio::spawn(async move {
+        if let Err(e) = set(level).await {
+            warn!(level, "Failed to set keyboard backlight: {:#}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_shows_level_of_max_and_hides_without_a_backlight() {
+        assert_eq!(display_text(2, 3), format!("{ICON} 2/3"));
+        assert_eq!(display_text(5, 3), format!("{ICON} 3/3"));
+        assert_eq!(display_text(0, 0), "");
+    }
+}
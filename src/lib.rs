@@ -0,0 +1,86 @@
+//! gtk-status-bar as a library, for programs that embed the bar or extend it
+//! with widgets of their own:
+//!
+//! ```no_run
+//! # fn activate(app: &gtk4::Application) -> anyhow::Result<()> {
+//! let bar = gtk_status_bar::Bar::builder()
+//!     .widget("workspace")
+//!     .widget("clock")
+//!     .layer(gtk_status_bar::Layer::Top)
+//!     .build(app)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The gtk-status-bar binary is one such program: it parses its command line
+//! and hands the result to the same builder (see [`Bar`] for the application
+//! around it). The configuration types are public so a program can build a
+//! Config in code instead of reading config.toml; the widgets themselves are
+//! reached by name through the builder.
+
+mod alarm;
+mod app;
+mod appearance;
+mod audio;
+mod autohide;
+mod autoprofile;
+mod backlight;
+mod bandwidth;
+mod battery;
+mod capture;
+mod clipboard;
+mod clock;
+mod command;
+pub mod config;
+mod control;
+mod cpufreq;
+mod dispatch;
+mod filewatch;
+mod format;
+mod gpu;
+mod hooks;
+mod hotspot;
+mod kbd_backlight;
+mod loadavg;
+mod lockkeys;
+#[cfg(feature = "metrics")]
+mod metrics;
+pub mod model;
+mod network;
+mod notification_popups;
+mod notification_server;
+mod notifications;
+mod osd;
+mod palette;
+mod pomodoro;
+mod power;
+mod process;
+mod progress;
+mod quick_settings;
+mod recorder;
+mod resume;
+mod rfkill;
+mod sinks;
+mod state;
+mod stats;
+#[cfg(test)]
+mod testutil;
+mod toplevel;
+mod tray;
+mod units;
+mod updates;
+mod views;
+mod vpn;
+mod widgets;
+mod wizard;
+
+pub use app::{Bar, BarBuilder, install_quit_action, register_primary_instance};
+pub use gtk4_layer_shell::Layer;
+pub use network::NetworkConfig;
+pub use status_bar_backends as backends;
+
+use status_bar_backends::format as text;
+use status_bar_backends::state as app_state;
+use status_bar_backends::{
+    bus, buses, channel, compositor, dbus, headset, hypr, logind, playback, privacy, pw, sleep,
+};
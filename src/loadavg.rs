@@ -0,0 +1,103 @@
+// Load average: the 1, 5 and 15 minute figures from /proc/loadavg divided by
+// the CPUs this process may run on, so 1.00 means every core busy on any
+// machine and one threshold fits a laptop and a workstation alike. The raw
+// figures, which is what `uptime` and `top` print, go in the popover.
+//
+// Crossing `[loadavg] warning_percent` or `critical_percent` with the
+// one-minute figure marks the widget with the `warning` or `critical` class.
+// Sampled by the stats poller next to the CPU frequency.
+
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+
+use crate::bus::LoadAvgUpdate;
+use crate::config::LoadAvgConfig;
+use crate::format::Format;
+
+pub const PROC_LOADAVG: &str = "/proc/loadavg";
+const ICON: &str = "\u{f0e4}";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoadAvg {
+    pub one: f64,
+    pub five: f64,
+    pub fifteen: f64,
+}
+
+// "0.42 0.38 0.30 2/1234 5678": the three averages lead.
+pub fn parse(loadavg: &str) -> Result<LoadAvg> {
+    let figures: Vec<f64> = loadavg
+        .split_whitespace()
+        .take(3)
+        .map(|figure| figure.parse().context("parse load average"))
+        .collect::<Result<_>>()?;
+    let [one, five, fifteen] = figures[..] else {
+        bail!("short /proc/loadavg: {loadavg:?}");
+    };
+    Ok(LoadAvg { one, five, fifteen })
+}
+
+pub fn read(path: &Path) -> Result<LoadAvg> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+    parse(&text)
+}
+
+// Honors affinity masks and cgroup quotas, like nproc.
+pub fn cores() -> usize {
+    std::thread::available_parallelism().map_or(1, usize::from)
+}
+
+pub fn update(
+    load: LoadAvg,
+    cores: usize,
+    config: &LoadAvgConfig,
+    format: &Format,
+) -> LoadAvgUpdate {
+    let divisor = cores.max(1) as f64;
+    let per_core = |figure: f64| format.number(figure / divisor, 2);
+    let one_percent = load.one * 100.0 / divisor;
+    LoadAvgUpdate {
+        text: format!(
+            "{ICON} {} {} {}",
+            per_core(load.one),
+            per_core(load.five),
+            per_core(load.fifteen)
+        ),
+        detail: format!(
+            "1 min: {}\n5 min: {}\n15 min: {}\n{cores} CPUs",
+            format.number(load.one, 2),
+            format.number(load.five, 2),
+            format.number(load.fifteen, 2)
+        ),
+        warning: one_percent >= f64::from(config.warning_percent),
+        critical: one_percent >= f64::from(config.critical_percent),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_are_normalized_per_core() {
+        let load = parse("6.40 3.20 1.60 3/1520 48211\n").expect("loadavg parses");
+        assert_eq!(
+            load,
+            LoadAvg {
+                one: 6.4,
+                five: 3.2,
+                fifteen: 1.6,
+            }
+        );
+        let update = update(load, 8, &LoadAvgConfig::default(), &Format::default());
+        assert_eq!(update.text, format!("{ICON} 0.80 0.40 0.20"));
+        assert_eq!(
+            update.detail,
+            "1 min: 6.40\n5 min: 3.20\n15 min: 1.60\n8 CPUs"
+        );
+        assert!(update.warning);
+        assert!(!update.critical);
+        assert!(parse("0.1 0.2\n").is_err());
+    }
+}
@@ -0,0 +1,120 @@
+// Caps Lock and Num Lock: the kernel's keyboard LED state under
+// /sys/class/leds (inputN::capslock, inputN::numlock), which follows the lock
+// state whether or not the keyboard has the LEDs, so keyboards without them
+// still get feedback. The bar never holds keyboard focus, so the compositor's
+// modifier events do not reach it and the LEDs are the one place to look.
+//
+// LED brightness files do not report changes (sysfs only notifies for
+// brightness_hw_changed), so this polls, fast enough that the indicator keeps
+// up with the key press. A lock counts as on when any keyboard's LED is lit;
+// the LED list is re-read every time, so plugging in a keyboard needs nothing.
+
+use std::path::Path;
+use std::time::Duration;
+
+use tracing::{debug, info, warn};
+
+use crate::bus::Bus;
+
+const LEDS_ROOT: &str = "/sys/class/leds";
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+const ICON: &str = "\u{f023}";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LockState {
+    pub caps: bool,
+    pub num: bool,
+}
+
+pub fn read_lock_state(root: &Path) -> LockState {
+    let mut state = LockState::default();
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return state;
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        let lock = if name.ends_with("::capslock") {
+            &mut state.caps
+        } else if name.ends_with("::numlock") {
+            &mut state.num
+        } else {
+            continue;
+        };
+        let lit = std::fs::read_to_string(entry.path().join("brightness"))
+            .ok()
+            .and_then(|brightness| brightness.trim().parse::<u32>().ok())
+            .is_some_and(|brightness| brightness > 0);
+        *lock |= lit;
+    }
+    state
+}
+
+// Empty with both locks off, which hides the widget.
+pub fn display_text(state: LockState) -> String {
+    let locks: Vec<&str> = [(state.caps, "Caps"), (state.num, "Num")]
+        .into_iter()
+        .filter_map(|(on, name)| on.then_some(name))
+        .collect();
+    if locks.is_empty() {
+        return String::new();
+    }
+    format!("{ICON} {}", locks.join(" "))
+}
+
+pub async fn run_lock_keys_updates(bus: Bus) {
+    if !Path::new(LEDS_ROOT).exists() {
+        info!("No {} here; the lock key widget stays hidden", LEDS_ROOT);
+        return;
+    }
+    info!("Starting lock key updates");
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    let mut last = None;
+    loop {
+        interval.tick().await;
+        let state = read_lock_state(Path::new(LEDS_ROOT));
+        if last == Some(state) {
+            continue;
+        }
+        last = Some(state);
+        debug!(?state, "Lock keys changed");
+        if let Err(e) = bus.send_lock_keys_update(display_text(state)) {
+            warn!("Lock key consumer is gone: {:#}", e);
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn any_lit_keyboard_led_turns_the_lock_on() {
+        let root = TempDir::new("leds");
+        for (led, brightness) in [
+            ("input3::capslock", "0\n"),
+            ("input7::capslock", "1\n"),
+            ("input3::numlock", "0\n"),
+            ("input3::scrolllock", "1\n"),
+        ] {
+            std::fs::create_dir_all(root.join(led)).expect("create fake LED");
+            std::fs::write(root.join(led).join("brightness"), brightness)
+                .expect("write brightness");
+        }
+
+        let state = read_lock_state(&root);
+        assert_eq!(
+            state,
+            LockState {
+                caps: true,
+                num: false
+            }
+        );
+        assert_eq!(display_text(state), format!("{ICON} Caps"));
+        assert_eq!(display_text(LockState::default()), "");
+    }
+}
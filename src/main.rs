@@ -1,268 +1,273 @@
-// Entry point: bring up tracing, the tokio runtime, the GTK application, and
-// wire each subsystem's GUI fan-out to its widget. activate() creates one Bus
-// plus the bidirectional tray endpoints, hands every UI half to its widget
-// drain, and only then spawns the supervised producers. Every consumer is
-// therefore wired before the first producer can send. Producers that crash are
-// restarted with exponential backoff by their run_*_supervised wrappers.
-
-mod appearance;
-mod bus;
-mod clock;
-mod dbus;
-mod hypr;
-mod network;
-mod pw;
-mod tray;
-mod widgets;
+// Entry point of the gtk-status-bar binary: parse the command line, then
+// either send one request to the running bar (`msg`) or bring up tracing, the
+// tokio runtime and the GTK application and build the bar in it through the
+// library's Bar::builder (lib.rs, app.rs). `--output json` and `--output
+// swaybar` skip GTK and print the backends' state instead (see the backends
+// crate's headless.rs and swaybar.rs).
 
 use std::env;
 use std::net::IpAddr;
-use std::time::{Duration, Instant};
+use std::path::PathBuf;
+use std::time::Duration;
 
 use anyhow::{Context, Result, bail};
-
-use gio::prelude::*;
+use clap::builder::{NonEmptyStringValueParser, TypedValueParser};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use gtk_status_bar::backends::buses::BusManager;
+use gtk_status_bar::backends::{headless, swaybar};
+use gtk_status_bar::{Bar, NetworkConfig, config};
 use gtk4::prelude::*;
-use tokio::sync::mpsc;
-use tracing::{debug, error, info, warn};
-use tray_ipc::IpcUiRequest;
-
-const USAGE: &str = "Usage: gtk-status-bar [OPTIONS]\n\n\
-Options:\n\
-  --monitor CONNECTOR\n\
-  --network-ping-target ADDRESS       Repeat to replace the Cloudflare defaults\n\
-  --network-stable-mean-seconds N     Default: 60\n\
-  --network-unstable-mean-seconds N   Default: 1\n\
-  --network-down-after-seconds N      Default: 15\n\
-  --network-recent-window-seconds N   Default: 60\n\
-  --network-ping-timeout-seconds N    Default: 2\n\
-  --network-dbus-timeout-seconds N    Default: 5\n\
-  -h, --help\n\n\
-CONNECTOR is the GDK output connector name, such as DVI-I-1 or DP-1. Ping\n\
+use tracing::{error, info};
+use tray_ipc::IpcRequest;
+
+const AFTER_HELP: &str = "\
+CONNECTOR is the GDK output connector name, such as DVI-I-1 or DP-1. Ping
 targets must be IPv4 or IPv6 addresses.";
 
-#[derive(Debug, PartialEq, Eq)]
-struct CliOptions {
+/// A GTK4 status bar for Hyprland and other wlroots compositors.
+#[derive(Debug, Parser)]
+#[command(
+    name = "gtk-status-bar",
+    after_help = AFTER_HELP,
+    args_conflicts_with_subcommands = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    mode: Option<Mode>,
+    /// Show the bar on this monitor only.
+    #[arg(long, value_name = "CONNECTOR", value_parser = NonEmptyStringValueParser::new())]
     monitor: Option<String>,
-    network: network::NetworkConfig,
+    /// Configuration file [default: $XDG_CONFIG_HOME/gtk-status-bar/config.toml].
+    #[arg(long, value_name = "PATH", value_parser = non_empty_path())]
+    config: Option<PathBuf>,
+    /// Stylesheet [default: $XDG_CONFIG_HOME/gtk-status-bar/style.css, then the built-in one].
+    #[arg(long, value_name = "PATH", value_parser = non_empty_path())]
+    style: Option<PathBuf>,
+    /// Quit a running bar and take its place.
+    #[arg(long)]
+    replace: bool,
+    /// What to draw the state with.
+    #[arg(long, value_enum, default_value_t = Output::Bar)]
+    output: Output,
+    #[command(flatten)]
+    network: NetworkArgs,
 }
 
-enum CliAction {
-    Run(CliOptions),
-    Help,
+#[derive(Debug, Subcommand)]
+enum Mode {
+    /// Send one command to the running bar over its IPC socket.
+    Msg {
+        #[command(subcommand)]
+        command: tray_ipc::Command,
+    },
 }
 
-fn parse_cli(arguments: &[String]) -> Result<CliAction> {
-    let mut options = CliOptions {
-        monitor: None,
-        network: network::NetworkConfig::default(),
-    };
-    let mut custom_targets = Vec::new();
-    let mut index = 0;
+// Unset timings keep NetworkConfig's defaults, which the help repeats.
+#[derive(Debug, Args)]
+struct NetworkArgs {
+    /// Address to ping; repeat to replace the Cloudflare defaults.
+    #[arg(long, value_name = "ADDRESS")]
+    network_ping_target: Vec<IpAddr>,
+    /// Mean seconds between pings while the connection is stable [default: 60].
+    #[arg(long, value_name = "N", value_parser = parse_seconds)]
+    network_stable_mean_seconds: Option<Duration>,
+    /// Mean seconds between pings while it is unstable [default: 1].
+    #[arg(long, value_name = "N", value_parser = parse_seconds)]
+    network_unstable_mean_seconds: Option<Duration>,
+    /// Seconds of failed pings before the network counts as down [default: 15].
+    #[arg(long, value_name = "N", value_parser = parse_seconds)]
+    network_down_after_seconds: Option<Duration>,
+    /// Seconds a failure keeps the connection marked unstable [default: 60].
+    #[arg(long, value_name = "N", value_parser = parse_seconds)]
+    network_recent_window_seconds: Option<Duration>,
+    /// Seconds to wait for one ping [default: 2].
+    #[arg(long, value_name = "N", value_parser = parse_seconds)]
+    network_ping_timeout_seconds: Option<Duration>,
+    /// Seconds to wait for NetworkManager over D-Bus [default: 5].
+    #[arg(long, value_name = "N", value_parser = parse_seconds)]
+    network_dbus_timeout_seconds: Option<Duration>,
+}
 
-    while index < arguments.len() {
-        let flag = arguments[index].as_str();
-        if flag == "--help" || flag == "-h" {
-            return Ok(CliAction::Help);
+impl NetworkArgs {
+    fn into_config(self) -> NetworkConfig {
+        let mut network = NetworkConfig::default();
+        if !self.network_ping_target.is_empty() {
+            network.ping_targets = self.network_ping_target;
         }
-        let Some(value) = arguments.get(index + 1) else {
-            if flag == "--monitor" {
-                bail!("--monitor requires a CONNECTOR\n\n{USAGE}");
-            }
-            bail!("{flag} requires a value\n\n{USAGE}");
-        };
-        match flag {
-            "--monitor" if !value.is_empty() => options.monitor = Some(value.clone()),
-            "--network-ping-target" => {
-                custom_targets.push(value.parse::<IpAddr>().with_context(|| {
-                    format!("--network-ping-target requires an IPv4 or IPv6 address: {value}")
-                })?);
-            }
-            "--network-stable-mean-seconds" => {
-                options.network.stable_mean = parse_seconds(flag, value)?;
-            }
-            "--network-unstable-mean-seconds" => {
-                options.network.unstable_mean = parse_seconds(flag, value)?;
+        let timings = [
+            (self.network_stable_mean_seconds, &mut network.stable_mean),
+            (
+                self.network_unstable_mean_seconds,
+                &mut network.unstable_mean,
+            ),
+            (
+                self.network_down_after_seconds,
+                &mut network.outage_confirmation,
+            ),
+            (
+                self.network_recent_window_seconds,
+                &mut network.recent_instability,
+            ),
+            (self.network_ping_timeout_seconds, &mut network.ping_timeout),
+            (self.network_dbus_timeout_seconds, &mut network.dbus_timeout),
+        ];
+        for (seconds, timing) in timings {
+            if let Some(seconds) = seconds {
+                *timing = seconds;
             }
-            "--network-down-after-seconds" => {
-                options.network.outage_confirmation = parse_seconds(flag, value)?;
-            }
-            "--network-recent-window-seconds" => {
-                options.network.recent_instability = parse_seconds(flag, value)?;
-            }
-            "--network-ping-timeout-seconds" => {
-                options.network.ping_timeout = parse_seconds(flag, value)?;
-            }
-            "--network-dbus-timeout-seconds" => {
-                options.network.dbus_timeout = parse_seconds(flag, value)?;
-            }
-            _ => bail!("unknown argument: {flag}\n\n{USAGE}"),
         }
-        index += 2;
-    }
-
-    if !custom_targets.is_empty() {
-        options.network.ping_targets = custom_targets;
+        network
     }
-    Ok(CliAction::Run(options))
 }
 
-fn parse_seconds(flag: &str, value: &str) -> Result<Duration> {
-    let seconds = value
-        .parse::<u64>()
-        .with_context(|| format!("{flag} requires a positive integer number of seconds"))?;
-    if seconds == 0 {
-        bail!("{flag} must be greater than zero");
-    }
-    Ok(Duration::from_secs(seconds))
+#[derive(Debug, PartialEq, Eq)]
+struct CliOptions {
+    monitor: Option<String>,
+    config: Option<PathBuf>,
+    style: Option<PathBuf>,
+    replace: bool,
+    output: Output,
+    network: NetworkConfig,
 }
 
-fn setup_logging() {
-    tracing_subscriber::fmt()
-        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
-        .init();
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Output {
+    /// The GTK bar.
+    Bar,
+    /// The workspace, window, audio and power state as one JSON object per
+    /// change on stdout, without GTK.
+    Json,
+    /// The i3bar protocol, for swaybar's status_command.
+    Swaybar,
 }
 
-async fn run_tray_ipc_supervised(ui_tx: mpsc::UnboundedSender<IpcUiRequest>) {
-    let max_delay = Duration::from_secs(60);
-    let reset_threshold = Duration::from_secs(30);
-    let mut delay = Duration::from_secs(1);
-
-    loop {
-        let started = Instant::now();
-        info!("Starting tray IPC server");
-        if let Err(error) = tray_ipc::run_server(ui_tx.clone()).await {
-            warn!(%error, "Tray IPC server stopped");
-        }
-
-        if started.elapsed() >= reset_threshold {
-            debug!(
-                elapsed = ?started.elapsed(),
-                "Tray IPC server was stable; resetting restart backoff"
-            );
-            delay = Duration::from_secs(1);
-        }
+#[derive(Debug)]
+enum CliAction {
+    Run(CliOptions),
+    Msg(IpcRequest),
+}
 
-        warn!(restart_delay = ?delay, "Restarting tray IPC server");
-        tokio::time::sleep(delay).await;
-        delay = std::cmp::min(delay * 2, max_delay);
+// `arguments` excludes the program name. Errors include --help and --version,
+// which clap prints and exits 0 for.
+fn parse_cli(arguments: &[String]) -> Result<CliAction, clap::Error> {
+    let cli = Cli::try_parse_from(
+        std::iter::once("gtk-status-bar").chain(arguments.iter().map(String::as_str)),
+    )?;
+    if let Some(Mode::Msg { command }) = cli.mode {
+        return Ok(CliAction::Msg(command.into()));
     }
+    Ok(CliAction::Run(CliOptions {
+        monitor: cli.monitor,
+        config: cli.config,
+        style: cli.style,
+        replace: cli.replace,
+        output: cli.output,
+        network: cli.network.into_config(),
+    }))
 }
 
-// Align GTK's theme preference with the desktop color scheme before any widget
-// realizes, so our symbolic icons and theme-based icon lookups resolve their
-// dark-mode variants. The bar's CSS is always dark, so when no preference is
-// exposed (e.g. a session without a color-scheme portal) we default to dark
-// rather than leaving GTK on its light default.
-fn configure_color_scheme() {
-    let prefer_dark = appearance::detect_prefer_dark().unwrap_or_else(|| {
-        debug!("No desktop color-scheme preference available; defaulting the bar to dark");
-        true
-    });
-    let Some(settings) = gtk4::Settings::default() else {
-        warn!("No default GtkSettings; cannot apply color-scheme preference");
-        return;
-    };
-    settings.set_gtk_application_prefer_dark_theme(prefer_dark);
-    info!(
-        prefer_dark,
-        "Applied color-scheme preference to GTK settings"
-    );
+fn non_empty_path() -> impl TypedValueParser<Value = PathBuf> {
+    NonEmptyStringValueParser::new().map(PathBuf::from)
 }
 
-fn activate(application: &gtk4::Application, options: &CliOptions) -> Result<()> {
-    info!("Activating GTK application");
-
-    configure_color_scheme();
-
-    let window = gtk4::ApplicationWindow::new(application);
-    window.add_css_class("layer-bar");
-
-    widgets::load_css_styles(&window);
-    widgets::configure_layer_shell(&window, options.monitor.as_deref())?;
-
-    let (
-        bar,
-        tray_widget,
-        bt_widget,
-        volume_widget,
-        network_widget,
-        battery_widget,
-        time_widget,
-        workspace_widget,
-        title_widget,
-    ) = widgets::create_experimental_bar();
-    window.set_child(Some(&bar));
-    window.set_visible(true);
-    widgets::setup_ui_watchdog();
-
-    let (bus, receivers) = bus::Bus::new();
-    let (tray_backend, tray_ui) = tray::channels();
-    let (tray_ipc_tx, tray_ipc_rx) = mpsc::unbounded_channel();
-    let (color_scheme_tx, color_scheme_rx) = mpsc::unbounded_channel();
-
-    widgets::update_time_widget(time_widget);
-    widgets::setup_tray_updates(tray_ui, tray_ipc_rx, tray_widget, &window);
-    widgets::setup_workspace_updates(receivers.workspace, workspace_widget, title_widget.clone());
-    widgets::setup_title_updates(receivers.title, title_widget);
-    widgets::setup_battery_updates(receivers.battery, battery_widget);
-    widgets::setup_bluetooth_updates(receivers.bluetooth, bt_widget);
-    widgets::setup_network_updates(receivers.network, network_widget);
-    widgets::setup_volume_updates(volume_widget)?;
-    widgets::setup_color_scheme_updates(color_scheme_rx);
-
-    // Every consumer above is wired before any producer below spawns. The
-    // D-Bus monitor serves both battery and bluetooth, while the tray also has
-    // a UI-to-backend command channel; both still obey the same ordering.
-    tokio::spawn(hypr::run_workspace_listener_supervised(bus.clone()));
-    tokio::spawn(hypr::run_title_listener_supervised(bus.clone()));
-    tokio::spawn(dbus::run_dbus_monitor_supervised(bus.clone()));
-    tokio::spawn(network::run_network_monitor_supervised(
-        bus,
-        options.network.clone(),
-    ));
-    tokio::spawn(tray::run_tray_supervised(tray_backend));
-    tokio::spawn(run_tray_ipc_supervised(tray_ipc_tx));
-    tokio::spawn(appearance::run_color_scheme_supervised(color_scheme_tx));
-
-    info!("Application activated successfully");
-    Ok(())
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    match value.parse::<u64>() {
+        Ok(0) => Err("must be greater than zero".to_string()),
+        Ok(seconds) => Ok(Duration::from_secs(seconds)),
+        Err(_) => Err("must be a positive integer number of seconds".to_string()),
+    }
+}
+
+fn setup_logging(output: Output) {
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env());
+    match output {
+        Output::Bar => subscriber.init(),
+        // stdout carries the status lines.
+        Output::Json | Output::Swaybar => subscriber.with_writer(std::io::stderr).init(),
+    }
 }
 
 fn create_tokio_runtime() -> Result<tokio::runtime::Runtime> {
     tokio::runtime::Runtime::new().context("Failed to create Tokio runtime")
 }
 
+// `msg` mode: one request to the running bar, no GTK and no logging. Tray items
+// (from `msg list`) are printed as JSON for scripts; a refused request becomes
+// the process error.
+fn send_message(request: &IpcRequest) -> Result<()> {
+    let response = create_tokio_runtime()?.block_on(tray_ipc::send_request(request))?;
+    if !response.ok {
+        bail!(
+            "{}",
+            response
+                .error
+                .as_deref()
+                .unwrap_or("request failed without an error message")
+        );
+    }
+    if !response.items.is_empty() {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&response.items).context("encode tray items as JSON")?
+        );
+    }
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let arguments: Vec<String> = env::args().skip(1).collect();
-    let options = match parse_cli(&arguments)? {
+    let options = match parse_cli(&arguments).unwrap_or_else(|error| error.exit()) {
         CliAction::Run(options) => options,
-        CliAction::Help => {
-            println!("{USAGE}");
-            return Ok(());
-        }
+        CliAction::Msg(request) => return send_message(&request),
     };
 
-    setup_logging();
+    setup_logging(options.output);
+    match options.output {
+        Output::Bar => {}
+        Output::Json => {
+            let output = headless::run(BusManager::new(), std::io::stdout());
+            return create_tokio_runtime()?.block_on(output);
+        }
+        Output::Swaybar => {
+            let output = swaybar::run(BusManager::new(), std::io::stdout());
+            return create_tokio_runtime()?.block_on(output);
+        }
+    }
     info!("Starting GTK status bar application");
 
+    let config = config::load(options.config.as_deref())?;
+
     let rt = create_tokio_runtime()?;
     let _guard = rt.enter();
 
-    let application = gtk4::Application::new(Some("sh.wmww.gtk-layer-example"), Default::default());
+    let application = gtk_status_bar::register_primary_instance(options.replace)?;
+    gtk_status_bar::install_quit_action(&application);
 
     application.connect_activate(move |app| {
-        // GApplication re-fires activate on the primary instance when a second
-        // copy of the binary launches under the same application id. Rebuilding
-        // the bar would double-spawn every producer, so present the existing
-        // window instead. Previously this path failed the OnceLock sender init
-        // and exit(1)'d the healthy bar.
+        // A second copy of the binary now exits (or replaces us) before it
+        // can activate, but `gapplication launch` and other D-Bus callers can
+        // still re-fire activate on the primary. Rebuilding the bar would
+        // double-spawn every producer, so present the existing window instead.
         if let Some(window) = app.active_window() {
             info!("Already activated; presenting existing window");
             window.present();
             return;
         }
-        if let Err(e) = activate(app, &options) {
+        let mut builder = Bar::builder()
+            .config(config.clone())
+            .network(options.network.clone())
+            .first_run_wizard(true);
+        if let Some(path) = &options.config {
+            builder = builder.config_path(path);
+        }
+        if let Some(path) = &options.style {
+            builder = builder.style(path);
+        }
+        if let Some(monitor) = &options.monitor {
+            builder = builder.monitor(monitor);
+        }
+        if let Err(e) = builder.build(app) {
             error!("Application activation failed: {:#}", e);
             std::process::exit(1);
         }
@@ -270,12 +275,16 @@ fn main() -> Result<()> {
 
     info!("Running GTK application");
     application.run_with_args(&["gtk-status-bar"]);
+    info!("GTK application exited");
 
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use clap::CommandFactory;
+    use clap::error::ErrorKind;
+
     use super::*;
 
     fn arguments(values: &[&str]) -> Vec<String> {
@@ -285,13 +294,17 @@ mod tests {
     #[test]
     fn monitor_is_optional() {
         let CliAction::Run(options) = parse_cli(&[]).expect("empty arguments should parse") else {
-            panic!("empty arguments unexpectedly requested help");
+            panic!("empty arguments unexpectedly sent a message");
         };
         assert_eq!(
             options,
             CliOptions {
                 monitor: None,
-                network: network::NetworkConfig::default(),
+                config: None,
+                style: None,
+                replace: false,
+                output: Output::Bar,
+                network: NetworkConfig::default(),
             }
         );
     }
@@ -301,23 +314,119 @@ mod tests {
         let CliAction::Run(options) =
             parse_cli(&arguments(&["--monitor", "DVI-I-1"])).expect("monitor should parse")
         else {
-            panic!("monitor arguments unexpectedly requested help");
+            panic!("monitor arguments unexpectedly sent a message");
         };
         assert_eq!(
             options,
             CliOptions {
                 monitor: Some("DVI-I-1".to_string()),
-                network: network::NetworkConfig::default(),
+                config: None,
+                style: None,
+                replace: false,
+                output: Output::Bar,
+                network: NetworkConfig::default(),
             }
         );
     }
 
+    #[test]
+    fn parses_config_path() {
+        let CliAction::Run(options) =
+            parse_cli(&arguments(&["--config", "/tmp/bar.toml"])).expect("config should parse")
+        else {
+            panic!("config arguments unexpectedly sent a message");
+        };
+        assert_eq!(options.config, Some(PathBuf::from("/tmp/bar.toml")));
+    }
+
+    #[test]
+    fn parses_style_path() {
+        let CliAction::Run(options) =
+            parse_cli(&arguments(&["--style", "/tmp/bar.css"])).expect("style should parse")
+        else {
+            panic!("style arguments unexpectedly sent a message");
+        };
+        assert_eq!(options.style, Some(PathBuf::from("/tmp/bar.css")));
+    }
+
+    #[test]
+    fn replace_is_a_bare_flag() {
+        let CliAction::Run(options) = parse_cli(&arguments(&["--replace", "--monitor", "DP-1"]))
+            .expect("replace should parse")
+        else {
+            panic!("replace arguments unexpectedly sent a message");
+        };
+        assert!(options.replace);
+        assert_eq!(options.monitor.as_deref(), Some("DP-1"));
+    }
+
+    #[test]
+    fn output_selects_json_or_the_bar() {
+        let CliAction::Run(options) =
+            parse_cli(&arguments(&["--output", "json"])).expect("output should parse")
+        else {
+            panic!("output arguments unexpectedly sent a message");
+        };
+        assert_eq!(options.output, Output::Json);
+        let CliAction::Run(options) =
+            parse_cli(&arguments(&["--output", "swaybar"])).expect("output should parse")
+        else {
+            panic!("output arguments unexpectedly sent a message");
+        };
+        assert_eq!(options.output, Output::Swaybar);
+        assert!(parse_cli(&arguments(&["--output", "yaml"])).is_err());
+    }
+
+    #[test]
+    fn msg_forwards_the_rest_as_an_ipc_command() {
+        let CliAction::Msg(request) =
+            parse_cli(&arguments(&["msg", "set-visible", "clock", "off"]))
+                .expect("msg should parse")
+        else {
+            panic!("msg arguments did not produce a message");
+        };
+        assert_eq!(
+            request,
+            IpcRequest::SetVisible {
+                widget: "clock".to_string(),
+                visible: false
+            }
+        );
+        assert!(parse_cli(&arguments(&["msg"])).is_err());
+    }
+
+    #[test]
+    fn command_line_is_well_formed() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn unknown_words_are_usage_errors() {
+        let kind = |values: &[&str]| {
+            parse_cli(&arguments(values))
+                .expect_err("should be rejected")
+                .kind()
+        };
+        assert_eq!(kind(&["mgs", "toggle"]), ErrorKind::InvalidSubcommand);
+        assert_eq!(kind(&["--monitr", "DP-1"]), ErrorKind::UnknownArgument);
+        assert_eq!(kind(&["--replace", "now"]), ErrorKind::ArgumentConflict);
+        assert_eq!(kind(&["--monitor", ""]), ErrorKind::InvalidValue);
+        assert_eq!(kind(&["--config", ""]), ErrorKind::InvalidValue);
+        assert_eq!(
+            kind(&["--monitor", "DP-1", "msg", "toggle"]),
+            ErrorKind::ArgumentConflict
+        );
+        assert_eq!(kind(&["msg", "toggle", "now"]), ErrorKind::UnknownArgument);
+        assert_eq!(kind(&["msg", "tgogle"]), ErrorKind::InvalidSubcommand);
+        assert_eq!(kind(&["msg", "--help"]), ErrorKind::DisplayHelp);
+        assert_eq!(kind(&["--help"]), ErrorKind::DisplayHelp);
+    }
+
     #[test]
     fn rejects_monitor_without_connector() {
-        let error = parse_cli(&arguments(&["--monitor"]))
-            .err()
-            .expect("missing connector should fail");
-        assert!(error.to_string().contains("requires a CONNECTOR"));
+        let error =
+            parse_cli(&arguments(&["--monitor"])).expect_err("missing connector should fail");
+        assert!(error.to_string().contains("--monitor <CONNECTOR>"));
     }
 
     #[test]
@@ -333,7 +442,7 @@ mod tests {
             "12",
         ]))
         .expect("network arguments should parse") else {
-            panic!("network arguments unexpectedly requested help");
+            panic!("network arguments unexpectedly sent a message");
         };
         assert_eq!(
             options.network.ping_targets,
@@ -0,0 +1,267 @@
+// Prometheus metrics: built with the `metrics` feature and given `[metrics]
+// listen`, the bar serves the text exposition format at /metrics on that
+// address, so Prometheus (and from there Grafana) can graph the laptop's
+// battery, volume, CPU and network over days.
+//
+// Everything is read when scraped: the battery and the CPU and byte counters
+// from sysfs and /proc as the recorder reads them (see recorder.rs), the volume
+// and brightness from the AppState. CPU time and bytes are exported as the
+// kernel's counters rather than as rates, so the bar keeps no history and
+// `rate()` in the query picks the window. A reading that is not there (no
+// battery, no PipeWire, no brightness OSD) leaves its series out.
+//
+// The server speaks just enough HTTP/1.1 for a scraper: one GET per
+// connection, answered and closed. There is no authentication, so keep the
+// address on loopback unless the network is trusted.
+
+use std::fmt::Display;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+use crate::app_state::AppState;
+use crate::bandwidth::PROC_NET_DEV;
+use crate::recorder::{self, CpuTimes, POWER_SUPPLY_ROOT, PROC_STAT};
+
+const PREFIX: &str = "status_bar";
+// A scraper's request line and headers fit easily.
+const MAX_REQUEST_BYTES: usize = 8192;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Volume {
+    sink: String,
+    percent: u8,
+    muted: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Sample {
+    battery_percent: Option<u8>,
+    volume: Option<Volume>,
+    brightness_percent: Option<u8>,
+    cpu: Option<CpuTimes>,
+    // Received and transmitted bytes, loopback excluded.
+    net: Option<(u64, u64)>,
+}
+
+fn sample(state: &AppState) -> Sample {
+    let volume = state.audio().borrow().as_ref().and_then(|audio| {
+        Some(Volume {
+            sink: audio.name.clone(),
+            percent: audio.channel_percent.or(audio.volume_percent)?,
+            muted: audio.is_muted == Some(true),
+        })
+    });
+    Sample {
+        battery_percent: recorder::read_battery_percent(Path::new(POWER_SUPPLY_ROOT)),
+        volume,
+        brightness_percent: *state.brightness().borrow(),
+        cpu: std::fs::read_to_string(PROC_STAT)
+            .ok()
+            .and_then(|stat| recorder::parse_cpu_times(&stat).ok()),
+        net: std::fs::read_to_string(PROC_NET_DEV)
+            .ok()
+            .map(|net_dev| recorder::parse_net_bytes(&net_dev)),
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+// One metric family with a single series; `labels` is empty or `{...}`.
+fn family(out: &mut String, name: &str, kind: &str, help: &str, labels: &str, value: impl Display) {
+    out.push_str(&format!("# HELP {PREFIX}_{name} {help}\n"));
+    out.push_str(&format!("# TYPE {PREFIX}_{name} {kind}\n"));
+    out.push_str(&format!("{PREFIX}_{name}{labels} {value}\n"));
+}
+
+fn render(sample: &Sample) -> String {
+    let mut out = String::new();
+    if let Some(percent) = sample.battery_percent {
+        let help = "Charge of the first battery.";
+        family(&mut out, "battery_percent", "gauge", help, "", percent);
+    }
+    if let Some(volume) = &sample.volume {
+        let labels = format!("{{sink=\"{}\"}}", escape_label(&volume.sink));
+        let help = "Volume of the default sink.";
+        family(
+            &mut out,
+            "volume_percent",
+            "gauge",
+            help,
+            &labels,
+            volume.percent,
+        );
+        let help = "1 while the default sink is muted.";
+        family(
+            &mut out,
+            "volume_muted",
+            "gauge",
+            help,
+            &labels,
+            u8::from(volume.muted),
+        );
+    }
+    if let Some(percent) = sample.brightness_percent {
+        let help = "Screen backlight level.";
+        family(&mut out, "brightness_percent", "gauge", help, "", percent);
+    }
+    if let Some(cpu) = sample.cpu {
+        let help = "CPU time not spent idle, in USER_HZ ticks summed over all cores.";
+        family(
+            &mut out,
+            "cpu_busy_ticks_total",
+            "counter",
+            help,
+            "",
+            cpu.busy,
+        );
+        let help = "CPU time, in USER_HZ ticks summed over all cores.";
+        family(&mut out, "cpu_ticks_total", "counter", help, "", cpu.total);
+    }
+    if let Some((received, transmitted)) = sample.net {
+        let help = "Bytes received on every interface but loopback.";
+        family(
+            &mut out,
+            "network_receive_bytes_total",
+            "counter",
+            help,
+            "",
+            received,
+        );
+        let help = "Bytes sent on every interface but loopback.";
+        family(
+            &mut out,
+            "network_transmit_bytes_total",
+            "counter",
+            help,
+            "",
+            transmitted,
+        );
+    }
+    out
+}
+
+// The path of a GET request line, without its query; None for anything else.
+fn request_path(request: &str) -> Option<&str> {
+    let mut parts = request.lines().next()?.split(' ');
+    if parts.next()? != "GET" {
+        return None;
+    }
+    let target = parts.next()?;
+    target.split('?').next()
+}
+
+async fn respond(mut stream: TcpStream, state: &AppState) -> Result<()> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        if request.len() > MAX_REQUEST_BYTES {
+            bail!("request headers too long");
+        }
+        let read = stream.read(&mut buffer).await.context("read request")?;
+        if read == 0 {
+            bail!("connection closed mid-request");
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let (status, body) = match request_path(&request) {
+        Some("/metrics") => ("200 OK", render(&sample(state))),
+        _ => ("404 Not Found", "Metrics are at /metrics\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {CONTENT_TYPE}\r\nContent-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream
+        .write_all(response.as_bytes())
+        .await
+        .context("write response")?;
+    stream.shutdown().await.context("close connection")
+}
+
+pub async fn run_metrics_server(listen: SocketAddr, state: AppState) {
+    let listener = match TcpListener::bind(listen).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!(%listen, "Could not serve metrics: {}", e);
+            return;
+        }
+    };
+    info!(%listen, "Serving metrics at /metrics");
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                // Out of file descriptors, most likely; let some close.
+                debug!("Failed to accept a metrics connection: {}", e);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let state = state.clone();
+        tokio::spawn(async move {
+            match tokio::time::timeout(REQUEST_TIMEOUT, respond(stream, &state)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => debug!(%peer, "Metrics request failed: {:#}", e),
+                Err(_) => debug!(%peer, "Metrics request timed out"),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_render_as_prometheus_text() {
+        let sample = Sample {
+            battery_percent: Some(80),
+            volume: Some(Volume {
+                sink: "Built-in \"Audio\"".to_string(),
+                percent: 42,
+                muted: true,
+            }),
+            brightness_percent: None,
+            cpu: Some(CpuTimes {
+                busy: 200,
+                total: 1000,
+            }),
+            net: None,
+        };
+        let text = render(&sample);
+        assert!(
+            text.contains(
+                "# TYPE status_bar_battery_percent gauge\nstatus_bar_battery_percent 80\n"
+            )
+        );
+        assert!(text.contains("status_bar_volume_muted{sink=\"Built-in \\\"Audio\\\"\"} 1\n"));
+        assert!(text.contains("status_bar_cpu_ticks_total 1000\n"));
+        assert!(!text.contains("brightness"));
+        assert!(!text.contains("network"));
+    }
+
+    #[test]
+    fn only_get_requests_have_a_path() {
+        assert_eq!(
+            request_path("GET /metrics?x=1 HTTP/1.1\r\nHost: a\r\n\r\n"),
+            Some("/metrics")
+        );
+        assert_eq!(request_path("POST /metrics HTTP/1.1\r\n\r\n"), None);
+        assert_eq!(request_path(""), None);
+    }
+}
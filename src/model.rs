@@ -0,0 +1,252 @@
+// Bar state as a GObject. The drains in widgets::setup_*_updates write these
+// properties (through the UiDispatcher, so a burst still collapses into one
+// pass) and the widgets bind to them with property expressions instead of
+// being poked with set_text. Keeping the state on a GObject is what lets a
+// layout defined outside Rust (GtkBuilder XML, Blueprint) reach the same data:
+// anything that can name a property can bind to it.
+//
+// Properties hold display-ready values — the formatting stays in the producer
+// and the drain — so a binding is a plain copy and a custom layout never has
+// to reimplement how a battery string is built.
+
+use std::cell::{Cell, RefCell};
+
+use gtk4::glib;
+use gtk4::glib::prelude::*;
+use gtk4::glib::subclass::prelude::*;
+
+mod imp {
+    use super::*;
+
+    #[derive(Default, glib::Properties)]
+    #[properties(wrapper_type = super::BarModel)]
+    pub struct BarModel {
+        #[property(get, set)]
+        workspace_name: RefCell<String>,
+        #[property(get, set)]
+        workspace_id: Cell<i32>,
+        #[property(get, set)]
+        workspace_special: Cell<bool>,
+        // CSS color for the title pill, already resolved from the workspace
+        // palette or the configured special-workspace color.
+        #[property(get, set)]
+        workspace_color: RefCell<String>,
+        // Next-window placement glyph; empty hides the pill.
+        #[property(get, set)]
+        split_hint: RefCell<String>,
+        #[property(get, set)]
+        title: RefCell<String>,
+        #[property(get, set)]
+        title_class: RefCell<String>,
+        // The focused window's process; 0 when unknown.
+        #[property(get, set)]
+        title_pid: Cell<i32>,
+        #[property(get, set)]
+        battery: RefCell<String>,
+        // Set while a discharging battery is at or below `[battery]`'s
+        // thresholds.
+        #[property(get, set)]
+        battery_warning: Cell<bool>,
+        #[property(get, set)]
+        battery_critical: Cell<bool>,
+        #[property(get, set)]
+        bluetooth: RefCell<String>,
+        // A device is connecting or pairing; its entry shows "…" meanwhile.
+        #[property(get, set)]
+        bluetooth_connecting: Cell<bool>,
+        #[property(get, set)]
+        network: RefCell<String>,
+        #[property(get, set)]
+        displays: RefCell<String>,
+        // Windows per occupied workspace, one "<id> <count>" pair per line.
+        #[property(get, set)]
+        window_counts: RefCell<String>,
+        // Floating windows on the active workspace; empty when there are none.
+        #[property(get, set)]
+        floating_badge: RefCell<String>,
+        // The special workspaces shown, one name per line.
+        #[property(get, set)]
+        scratchpads_open: RefCell<String>,
+        // Average frequency and governor; empty without a cpufreq driver.
+        #[property(get, set)]
+        cpufreq: RefCell<String>,
+        // Active VPN connections; empty hides the indicator.
+        #[property(get, set)]
+        vpn: RefCell<String>,
+        // Hotspot clients and USB tethering; empty hides the indicator.
+        #[property(get, set)]
+        hotspot: RefCell<String>,
+        // The quick-settings sliders, 0-100; -1 without a sink or a
+        // backlight, which hides the slider.
+        #[property(get, set)]
+        quick_volume: Cell<i32>,
+        #[property(get, set)]
+        quick_brightness: Cell<i32>,
+        // Airplane mode: ✈ and the blocked radios; empty without Wi-Fi or
+        // Bluetooth hardware.
+        #[property(get, set)]
+        rfkill: RefCell<String>,
+        #[property(get, set)]
+        rfkill_blocked: Cell<bool>,
+        #[property(get, set)]
+        rfkill_tooltip: RefCell<String>,
+        // Failed-unit badge; empty hides it. The names, one per line, fill
+        // its popover.
+        #[property(get, set)]
+        units: RefCell<String>,
+        #[property(get, set)]
+        units_failed: RefCell<String>,
+        // Pending package updates; empty (none, or no checker) hides it.
+        #[property(get, set)]
+        updates: RefCell<String>,
+        // Clipboard history entries; empty until the first count.
+        #[property(get, set)]
+        clipboard: RefCell<String>,
+        // Utilization, VRAM and temperature; empty without a supported GPU.
+        #[property(get, set)]
+        gpu: RefCell<String>,
+        #[property(get, set)]
+        gpu_warning: Cell<bool>,
+        // Per-core load averages; empty when /proc/loadavg is unreadable.
+        // The raw figures fill its popover.
+        #[property(get, set)]
+        loadavg: RefCell<String>,
+        #[property(get, set)]
+        loadavg_detail: RefCell<String>,
+        #[property(get, set)]
+        loadavg_warning: Cell<bool>,
+        #[property(get, set)]
+        loadavg_critical: Cell<bool>,
+        // Down and up rates; empty without a route to measure.
+        #[property(get, set)]
+        bandwidth: RefCell<String>,
+        // Bell with the unread count; empty without swaync or dunst.
+        #[property(get, set)]
+        notifications: RefCell<String>,
+        #[property(get, set)]
+        notifications_dnd: Cell<bool>,
+        // Keyboard backlight "level/max"; empty without a backlight. The
+        // level is also set ahead of UPower while scrolling, so quick scroll
+        // steps add up instead of all starting from the same reading.
+        #[property(get, set)]
+        kbd_backlight: RefCell<String>,
+        #[property(get, set)]
+        kbd_backlight_level: Cell<i32>,
+        #[property(get, set)]
+        kbd_backlight_max: Cell<i32>,
+        // Stylesheet errors of the last load, and each with its line and
+        // column; empty when the stylesheet parsed cleanly.
+        #[property(get, set)]
+        style_errors: RefCell<String>,
+        #[property(get, set)]
+        style_errors_detail: RefCell<String>,
+        // Caps and Num Lock while on; empty with both off.
+        #[property(get, set)]
+        lock_keys: RefCell<String>,
+        // Applications' LauncherEntry progress; empty text while none runs.
+        #[property(get, set)]
+        progress: RefCell<String>,
+        #[property(get, set)]
+        progress_fraction: Cell<f64>,
+        #[property(get, set)]
+        progress_tooltip: RefCell<String>,
+        #[property(get, set)]
+        volume: RefCell<String>,
+        #[property(get, set)]
+        volume_muted: Cell<bool>,
+        // No default sink or no PipeWire: the label says which instead of a
+        // level, greyed out.
+        #[property(get, set)]
+        volume_inactive: Cell<bool>,
+        // Bumped on every mute toggle, but not by the first report or by
+        // refreshes that repeat the state: the volume widget flashes on each
+        // bump. A counter rather than a flag, since a transition is an event
+        // and the model otherwise only holds state.
+        #[property(get, set)]
+        volume_mute_toggles: Cell<u32>,
+        // One glyph per running capture kind; empty hides the indicator.
+        #[property(get, set)]
+        privacy: RefCell<String>,
+        #[property(get, set)]
+        privacy_tooltip: RefCell<String>,
+        // The Bluetooth headset's profile; empty without one. `headset_hfp`
+        // marks headset mode, where playback is mono and narrowband.
+        #[property(get, set)]
+        headset: RefCell<String>,
+        #[property(get, set)]
+        headset_hfp: Cell<bool>,
+        #[property(get, set)]
+        headset_tooltip: RefCell<String>,
+        #[property(get, set)]
+        time: RefCell<String>,
+        #[property(get, set)]
+        pomodoro: RefCell<String>,
+        // Camera while idle, the elapsed time while a recorder runs.
+        #[property(get, set)]
+        capture: RefCell<String>,
+        #[property(get, set)]
+        capture_recording: Cell<bool>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for BarModel {
+        const NAME: &'static str = "GtkStatusBarModel";
+        type Type = super::BarModel;
+    }
+
+    #[glib::derived_properties]
+    impl ObjectImpl for BarModel {}
+}
+
+glib::wrapper! {
+    pub struct BarModel(ObjectSubclass<imp::BarModel>);
+}
+
+impl BarModel {
+    // Seeded with the same placeholders the widgets used to be constructed
+    // with, so the first frame looks unchanged before any producer reports.
+    pub fn new() -> Self {
+        glib::Object::builder()
+            .property("workspace-name", "Workspace ?")
+            .property("title", "Application Title")
+            .property("battery", "🔋 ??%")
+            .property("network", "🌐 ?")
+            .property("displays", "🖥 ?")
+            .property("volume", "Volume ?")
+            .build()
+    }
+}
+
+impl Default for BarModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // GObject property storage needs no display, so the model is testable
+    // without a GTK main loop.
+    #[test]
+    fn placeholders_seed_the_first_frame() {
+        let model = BarModel::new();
+        assert_eq!(model.workspace_name(), "Workspace ?");
+        assert_eq!(model.title(), "Application Title");
+        assert_eq!(model.bluetooth(), "");
+        assert!(!model.workspace_special());
+    }
+
+    #[test]
+    fn setters_notify_bound_consumers() {
+        let model = BarModel::new();
+        let seen = std::rc::Rc::new(RefCell::new(Vec::new()));
+        let sink = seen.clone();
+        model.connect_battery_notify(move |model| sink.borrow_mut().push(model.battery()));
+
+        model.set_battery("⚡ 64%");
+        assert_eq!(model.battery(), "⚡ 64%");
+        assert_eq!(*seen.borrow(), vec!["⚡ 64%".to_string()]);
+    }
+}
@@ -5,21 +5,22 @@
 // working local link cannot announce an upstream outage, so randomized probes
 // fill that gap without turning the UI into a fixed-interval polling loop.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::net::IpAddr;
 use std::process::Stdio;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::{Context, Result};
-use futures_util::StreamExt;
 use tokio::process::Command;
+use tokio::sync::mpsc;
 use tracing::{debug, error, info, warn};
-use zbus::fdo;
 use zbus::message::Type as MessageType;
-use zbus::zvariant::OwnedObjectPath;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
 use zbus::{Connection, MatchRule, Proxy};
 
 use crate::bus::Bus;
+use crate::buses::{BusKind, BusManager};
+use crate::dbus::supervise;
 
 const NETWORK_MANAGER: &str = "org.freedesktop.NetworkManager";
 const NETWORK_MANAGER_PATH: &str = "/org/freedesktop/NetworkManager";
@@ -27,6 +28,19 @@ const NETWORK_MANAGER_IFACE: &str = "org.freedesktop.NetworkManager";
 const ACTIVE_CONNECTION_IFACE: &str = "org.freedesktop.NetworkManager.Connection.Active";
 const WIRELESS_DEVICE_IFACE: &str = "org.freedesktop.NetworkManager.Device.Wireless";
 const ACCESS_POINT_IFACE: &str = "org.freedesktop.NetworkManager.AccessPoint";
+const DEVICE_IFACE: &str = "org.freedesktop.NetworkManager.Device";
+const SETTINGS_CONNECTION_IFACE: &str = "org.freedesktop.NetworkManager.Settings.Connection";
+
+// NM_DEVICE_TYPE_WIFI
+const DEVICE_TYPE_WIFI: u32 = 2;
+// NM80211ApFlags and NM80211ApSecurityFlags
+const AP_FLAGS_PRIVACY: u32 = 0x1;
+const AP_SEC_KEY_MGMT_PSK: u32 = 0x100;
+const AP_SEC_KEY_MGMT_802_1X: u32 = 0x200;
+const AP_SEC_KEY_MGMT_SAE: u32 = 0x400;
+const AP_SEC_KEY_MGMT_SUITE_B_192: u32 = 0x2000;
+const SCAN_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const SCAN_TIMEOUT: Duration = Duration::from_secs(10);
 
 const ICON_GLOBE: &str = "\u{f0ac}";
 const ICON_ETHERNET: &str = "\u{f0200}";
@@ -364,14 +378,7 @@ fn network_owner_rule() -> Result<MatchRule<'static>> {
 }
 
 async fn read_snapshot(connection: &Connection) -> Result<NetworkSnapshot> {
-    let manager = Proxy::new(
-        connection,
-        NETWORK_MANAGER,
-        NETWORK_MANAGER_PATH,
-        NETWORK_MANAGER_IFACE,
-    )
-    .await
-    .context("create NetworkManager root proxy")?;
+    let manager = manager_proxy(connection).await?;
 
     let nm_state: u32 = manager
         .get_property("State")
@@ -497,31 +504,27 @@ fn send_status(bus: &Bus, snapshot: &NetworkSnapshot, health: &ProbeHealth) {
     }
 }
 
-async fn monitor_network(bus: &Bus, config: &NetworkConfig) -> Result<()> {
-    let connection = Connection::system()
+async fn monitor_network(bus: &Bus, config: &NetworkConfig, buses: &BusManager) -> Result<()> {
+    let mut signals = buses
+        .subscribe(
+            BusKind::System,
+            vec![network_properties_rule()?, network_owner_rule()?],
+        )
         .await
-        .context("connect network monitor to system D-Bus")?;
-    let dbus = fdo::DBusProxy::new(&connection).await?;
-    for rule in [network_properties_rule()?, network_owner_rule()?] {
-        dbus.add_match_rule(rule)
-            .await
-            .context("register NetworkManager match rule")?;
-    }
-    let mut stream = zbus::MessageStream::from(&connection);
-
-    // Property reads run on their own connection, never the one feeding
-    // `stream`. While we await a read_snapshot, the select! below stops polling
-    // `stream`; under a burst of PropertiesChanged signals (e.g. a flapping
-    // Wi-Fi device) the stream connection's inbound queue fills, backpressures
-    // its socket reader, and the get_property replies never arrive -- the read
-    // hangs until dbus_timeout and the bar wrongly latches to "down" even while
-    // NetworkManager reports full connectivity. A dedicated connection gives
-    // those reads their own reader, independent of stream backpressure.
-    let snapshot_connection = Connection::system()
-        .await
-        .context("connect network snapshot reader to system D-Bus")?;
+        .context("subscribe to NetworkManager signals")?;
+    // Property reads share the subscription's connection: the shared
+    // connection's dispatcher keeps draining signals while we await a
+    // read_snapshot, so a burst of PropertiesChanged (a flapping Wi-Fi device,
+    // say) cannot backpressure the reader and starve the get_property replies
+    // until dbus_timeout.
+    let snapshot_connection = signals.connection().clone();
 
-    let mut snapshot = match tokio::time::timeout(config.dbus_timeout, read_snapshot(&snapshot_connection)).await {
+    let mut snapshot = match tokio::time::timeout(
+        config.dbus_timeout,
+        read_snapshot(&snapshot_connection),
+    )
+    .await
+    {
         Err(_) => {
             warn!(timeout = ?config.dbus_timeout, "Initial NetworkManager snapshot timed out");
             NetworkSnapshot::disconnected()
@@ -541,6 +544,7 @@ async fn monitor_network(bus: &Bus, config: &NetworkConfig) -> Result<()> {
     let mut rng = ProbeRng::seeded();
     let mut targets = TargetOrder::new(config.ping_targets.clone(), &mut rng);
     let mut next_probe = Instant::now();
+    let mut resumes = bus.resumes();
 
     loop {
         let sleep_until = if snapshot.has_network() {
@@ -549,11 +553,10 @@ async fn monitor_network(bus: &Bus, config: &NetworkConfig) -> Result<()> {
             Instant::now() + Duration::from_secs(24 * 60 * 60)
         };
         tokio::select! {
-            message = stream.next() => {
+            message = signals.next() => {
                 let Some(message) = message else {
-                    anyhow::bail!("NetworkManager D-Bus message stream ended");
+                    anyhow::bail!("NetworkManager D-Bus subscription ended");
                 };
-                let message = message.context("receive NetworkManager D-Bus signal")?;
                 let header = message.header();
                 let member = header.member().map(|member| member.as_str()).unwrap_or_default();
                 let interface = header.interface().map(|interface| interface.as_str()).unwrap_or_default();
@@ -603,6 +606,18 @@ async fn monitor_network(bus: &Bus, config: &NetworkConfig) -> Result<()> {
                 }
                 send_status(bus, &snapshot, &health);
             }
+            Ok(()) = resumes.changed() => {
+                // Reachability measured before the suspend says nothing about
+                // the network the machine woke up on; NetworkManager reports
+                // its own changes as usual.
+                info!("Resumed from sleep; probing the network again");
+                if snapshot.has_network() {
+                    let now = Instant::now();
+                    health.connection_changed(now, config);
+                    next_probe = now;
+                }
+                send_status(bus, &snapshot, &health);
+            }
             _ = tokio::time::sleep_until(sleep_until.into()) => {
                 if !snapshot.has_network() {
                     continue;
@@ -632,21 +647,363 @@ async fn monitor_network(bus: &Bus, config: &NetworkConfig) -> Result<()> {
     }
 }
 
-pub async fn run_network_monitor_supervised(bus: Bus, config: NetworkConfig) {
-    let mut delay = Duration::from_secs(1);
-    loop {
-        let started = Instant::now();
-        info!("Starting network monitor");
-        if let Err(error) = monitor_network(&bus, &config).await {
-            error!(error = %format_args!("{error:#}"), "Network monitor stopped");
+pub async fn run_network_monitor_supervised(bus: Bus, config: NetworkConfig, buses: BusManager) {
+    supervise("network monitor", || monitor_network(&bus, &config, &buses)).await
+}
+
+// Wi-Fi picker behind the network widget's popover: the access points the
+// Wi-Fi devices see, strongest first, and connecting to one. A saved
+// connection for the SSID is activated as it is; otherwise
+// AddAndActivateConnection makes one from the access point, with the typed
+// password for WPA/WPA3 Personal. Other secured networks are left to
+// NetworkManager's secret agent, if the session runs one.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Security {
+    Open,
+    Wep,
+    // WPA/WPA2 Personal, or a WPA2/WPA3 transition network.
+    Psk,
+    // WPA3 Personal only.
+    Sae,
+    Enterprise,
+}
+
+impl Security {
+    fn from_flags(flags: u32, wpa_flags: u32, rsn_flags: u32) -> Self {
+        let key_mgmt = wpa_flags | rsn_flags;
+        if key_mgmt & (AP_SEC_KEY_MGMT_802_1X | AP_SEC_KEY_MGMT_SUITE_B_192) != 0 {
+            Security::Enterprise
+        } else if key_mgmt & AP_SEC_KEY_MGMT_PSK != 0 {
+            Security::Psk
+        } else if key_mgmt & AP_SEC_KEY_MGMT_SAE != 0 {
+            Security::Sae
+        } else if flags & AP_FLAGS_PRIVACY != 0 && key_mgmt == 0 {
+            Security::Wep
+        } else {
+            // Open, or OWE (Enhanced Open), which needs no secret either.
+            Security::Open
+        }
+    }
+
+    // The `key-mgmt` for a typed password; None where the bar asks for none.
+    pub fn password_key_mgmt(self) -> Option<&'static str> {
+        match self {
+            Security::Psk => Some("wpa-psk"),
+            Security::Sae => Some("sae"),
+            Security::Open | Security::Wep | Security::Enterprise => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccessPoint {
+    pub ssid: String,
+    pub strength: u8,
+    pub security: Security,
+    pub active: bool,
+    // The saved connection for this SSID on the device, activated instead of
+    // adding another.
+    pub saved: Option<String>,
+    device: String,
+    path: String,
+}
+
+impl AccessPoint {
+    pub fn needs_password(&self) -> bool {
+        self.saved.is_none() && self.security.password_key_mgmt().is_some()
+    }
+
+    pub fn menu_text(&self) -> String {
+        let mark = if self.active { "✓ " } else { "" };
+        let lock = if self.security == Security::Open {
+            ""
+        } else {
+            " 🔒"
+        };
+        format!("{mark}{}  {}%{lock}", self.ssid, self.strength)
+    }
+}
+
+// One entry per SSID, its strongest access point (the active one wins, so
+// connecting again never switches BSSID), hidden networks left out; the
+// active network first, then by strength.
+fn merge_access_points(access_points: Vec<AccessPoint>) -> Vec<AccessPoint> {
+    let mut by_ssid: HashMap<String, AccessPoint> = HashMap::new();
+    for access_point in access_points {
+        if access_point.ssid.is_empty() {
+            continue;
+        }
+        let keep = by_ssid.get(&access_point.ssid).is_some_and(|kept| {
+            kept.active || (!access_point.active && kept.strength >= access_point.strength)
+        });
+        if !keep {
+            by_ssid.insert(access_point.ssid.clone(), access_point);
+        }
+    }
+    let mut merged: Vec<AccessPoint> = by_ssid.into_values().collect();
+    merged.sort_by(|a, b| {
+        b.active
+            .cmp(&a.active)
+            .then(b.strength.cmp(&a.strength))
+            .then_with(|| a.ssid.cmp(&b.ssid))
+    });
+    merged
+}
+
+async fn manager_proxy(connection: &Connection) -> Result<Proxy<'static>> {
+    Proxy::new(
+        connection,
+        NETWORK_MANAGER,
+        NETWORK_MANAGER_PATH,
+        NETWORK_MANAGER_IFACE,
+    )
+    .await
+    .context("create NetworkManager root proxy")
+}
+
+async fn wifi_devices(connection: &Connection) -> Result<Vec<OwnedObjectPath>> {
+    let manager = manager_proxy(connection).await?;
+    let devices: Vec<OwnedObjectPath> = manager
+        .call("GetDevices", &())
+        .await
+        .context("list NetworkManager devices")?;
+    let mut wifi = Vec::new();
+    for device in devices {
+        let proxy = Proxy::new(connection, NETWORK_MANAGER, device.as_str(), DEVICE_IFACE)
+            .await
+            .context("create NetworkManager device proxy")?;
+        if proxy.get_property::<u32>("DeviceType").await.ok() == Some(DEVICE_TYPE_WIFI) {
+            wifi.push(device);
+        }
+    }
+    Ok(wifi)
+}
+
+// SSID to saved connection, from the connections NetworkManager could
+// activate on the device.
+async fn saved_connections(
+    connection: &Connection,
+    device: &Proxy<'_>,
+) -> Result<HashMap<Vec<u8>, String>> {
+    let available: Vec<OwnedObjectPath> = device
+        .get_property("AvailableConnections")
+        .await
+        .context("read the device's AvailableConnections")?;
+    let mut saved = HashMap::new();
+    for path in available {
+        let proxy = Proxy::new(
+            connection,
+            NETWORK_MANAGER,
+            path.as_str(),
+            SETTINGS_CONNECTION_IFACE,
+        )
+        .await
+        .context("create NetworkManager connection proxy")?;
+        let Ok(settings) = proxy
+            .call::<_, _, HashMap<String, HashMap<String, OwnedValue>>>("GetSettings", &())
+            .await
+        else {
+            continue;
+        };
+        let ssid = settings
+            .get("802-11-wireless")
+            .and_then(|section| section.get("ssid"))
+            .and_then(|ssid| Vec::<u8>::try_from(ssid.clone()).ok());
+        if let Some(ssid) = ssid {
+            saved.insert(ssid, path.to_string());
+        }
+    }
+    Ok(saved)
+}
+
+async fn device_access_points(
+    connection: &Connection,
+    device: &OwnedObjectPath,
+) -> Result<Vec<AccessPoint>> {
+    let generic = Proxy::new(connection, NETWORK_MANAGER, device.as_str(), DEVICE_IFACE)
+        .await
+        .context("create NetworkManager device proxy")?;
+    let wireless = Proxy::new(
+        connection,
+        NETWORK_MANAGER,
+        device.as_str(),
+        WIRELESS_DEVICE_IFACE,
+    )
+    .await
+    .context("create NetworkManager wireless device proxy")?;
+    let saved = saved_connections(connection, &generic).await?;
+    let active: OwnedObjectPath = wireless
+        .get_property("ActiveAccessPoint")
+        .await
+        .context("read active Wi-Fi access point")?;
+    let paths: Vec<OwnedObjectPath> = wireless
+        .get_property("AccessPoints")
+        .await
+        .context("read the device's AccessPoints")?;
+    let mut access_points = Vec::new();
+    for path in paths {
+        let proxy = Proxy::new(
+            connection,
+            NETWORK_MANAGER,
+            path.as_str(),
+            ACCESS_POINT_IFACE,
+        )
+        .await
+        .context("create NetworkManager access point proxy")?;
+        // Access points come and go mid-scan; skip one that vanished rather
+        // than fail the list.
+        let (Ok(ssid), Ok(strength), Ok(flags), Ok(wpa_flags), Ok(rsn_flags)) = (
+            proxy.get_property::<Vec<u8>>("Ssid").await,
+            proxy.get_property::<u8>("Strength").await,
+            proxy.get_property::<u32>("Flags").await,
+            proxy.get_property::<u32>("WpaFlags").await,
+            proxy.get_property::<u32>("RsnFlags").await,
+        ) else {
+            continue;
+        };
+        access_points.push(AccessPoint {
+            ssid: String::from_utf8_lossy(&ssid).into_owned(),
+            strength,
+            security: Security::from_flags(flags, wpa_flags, rsn_flags),
+            active: path == active,
+            saved: saved.get(&ssid).cloned(),
+            device: device.to_string(),
+            path: path.to_string(),
+        });
+    }
+    Ok(access_points)
+}
+
+async fn read_access_points(connection: &Connection) -> Result<Vec<AccessPoint>> {
+    let mut access_points = Vec::new();
+    for device in wifi_devices(connection).await? {
+        access_points.extend(device_access_points(connection, &device).await?);
+    }
+    Ok(merge_access_points(access_points))
+}
+
+// LastScan of every Wi-Fi device, to tell when a requested scan is done.
+async fn last_scans(connection: &Connection, devices: &[OwnedObjectPath]) -> Vec<i64> {
+    let mut scans = Vec::new();
+    for device in devices {
+        let Ok(wireless) = Proxy::new(
+            connection,
+            NETWORK_MANAGER,
+            device.as_str(),
+            WIRELESS_DEVICE_IFACE,
+        )
+        .await
+        else {
+            continue;
+        };
+        scans.push(wireless.get_property::<i64>("LastScan").await.unwrap_or(-1));
+    }
+    scans
+}
+
+async fn scan_and_send(updates: &mpsc::UnboundedSender<Vec<AccessPoint>>) -> Result<()> {
+    let connection = Connection::system()
+        .await
+        .context("connect to system D-Bus")?;
+    let _ = updates.send(read_access_points(&connection).await?);
+
+    let devices = wifi_devices(&connection).await?;
+    let before = last_scans(&connection, &devices).await;
+    for device in &devices {
+        let wireless = Proxy::new(
+            &connection,
+            NETWORK_MANAGER,
+            device.as_str(),
+            WIRELESS_DEVICE_IFACE,
+        )
+        .await
+        .context("create NetworkManager wireless device proxy")?;
+        let options: HashMap<&str, Value<'_>> = HashMap::new();
+        // NetworkManager refuses a scan right after another; the list it
+        // already has is as fresh as that one.
+        if let Err(e) = wireless.call::<_, _, ()>("RequestScan", &(options,)).await {
+            debug!(device = device.as_str(), "Wi-Fi scan not started: {}", e);
+        }
+    }
+    // A bounded wait while the popover is open rather than a subscription:
+    // LastScan moves once per device when its scan completes.
+    let deadline = Instant::now() + SCAN_TIMEOUT;
+    while Instant::now() < deadline && !updates.is_closed() {
+        tokio::time::sleep(SCAN_POLL_INTERVAL).await;
+        if last_scans(&connection, &devices).await != before {
+            break;
         }
-        if started.elapsed() >= Duration::from_secs(30) {
-            delay = Duration::from_secs(1);
+    }
+    let _ = updates.send(read_access_points(&connection).await?);
+    Ok(())
+}
+
+// Sends the access points NetworkManager already knows, asks the devices to
+// scan, and sends them again once the scan is done, so the popover fills at
+// once and then catches up. Dropping the receiver stops the wait.
+pub fn scan_access_points(updates: mpsc::UnboundedSender<Vec<AccessPoint>>) {
+    tokio::spawn(async move {
+        if let Err(e) = scan_and_send(&updates).await {
+            warn!("Failed to list Wi-Fi networks: {:#}", e);
         }
-        warn!(restart_delay = ?delay, "Restarting network monitor");
-        tokio::time::sleep(delay).await;
-        delay = (delay * 2).min(Duration::from_secs(60));
+    });
+}
+
+async fn activate(access_point: &AccessPoint, password: Option<&str>) -> Result<()> {
+    let connection = Connection::system()
+        .await
+        .context("connect to system D-Bus")?;
+    let manager = manager_proxy(&connection).await?;
+    let device = OwnedObjectPath::try_from(access_point.device.as_str()).context("device path")?;
+    let specific =
+        OwnedObjectPath::try_from(access_point.path.as_str()).context("access point path")?;
+    let ssid = access_point.ssid.as_str();
+    if let Some(saved) = &access_point.saved {
+        let saved = OwnedObjectPath::try_from(saved.as_str()).context("connection path")?;
+        info!(ssid, "Activating saved Wi-Fi connection");
+        manager
+            .call::<_, _, OwnedObjectPath>("ActivateConnection", &(saved, device, specific))
+            .await
+            .with_context(|| format!("activate {ssid:?}"))?;
+        return Ok(());
     }
+    // NetworkManager completes the rest (SSID, mode, a name) from the access
+    // point.
+    let mut settings: HashMap<&str, HashMap<&str, Value<'_>>> = HashMap::new();
+    if let Some(password) = password
+        && let Some(key_mgmt) = access_point.security.password_key_mgmt()
+    {
+        settings.insert(
+            "802-11-wireless-security",
+            HashMap::from([
+                ("key-mgmt", Value::from(key_mgmt)),
+                ("psk", Value::from(password)),
+            ]),
+        );
+    }
+    info!(ssid, "Adding Wi-Fi connection");
+    manager
+        .call::<_, _, (OwnedObjectPath, OwnedObjectPath)>(
+            "AddAndActivateConnection",
+            &(settings, device, specific),
+        )
+        .await
+        .with_context(|| format!("add and activate {ssid:?}"))?;
+    Ok(())
+}
+
+// The popover's connect action: fire-and-forget from the GTK thread. The
+// widget's text follows through the monitor.
+pub fn connect_access_point(access_point: AccessPoint, password: Option<String>) {
+    tokio::spawn(async move {
+        if let Err(e) = activate(&access_point, password.as_deref()).await {
+            warn!(
+                ssid = access_point.ssid,
+                "Failed to connect to Wi-Fi: {:#}", e
+            );
+        }
+    });
 }
 
 #[cfg(test)]
@@ -791,4 +1148,78 @@ mod tests {
             .collect();
         assert_eq!(first_cycle.len(), config.ping_targets.len());
     }
+
+    fn access_point(ssid: &str, strength: u8, active: bool) -> AccessPoint {
+        AccessPoint {
+            ssid: ssid.to_string(),
+            strength,
+            security: Security::Psk,
+            active,
+            saved: None,
+            device: "/org/freedesktop/NetworkManager/Devices/3".to_string(),
+            path: format!("/org/freedesktop/NetworkManager/AccessPoint/{ssid}{strength}"),
+        }
+    }
+
+    #[test]
+    fn security_follows_the_key_management_flags() {
+        assert_eq!(Security::from_flags(0, 0, 0), Security::Open);
+        assert_eq!(Security::from_flags(AP_FLAGS_PRIVACY, 0, 0), Security::Wep);
+        assert_eq!(
+            Security::from_flags(AP_FLAGS_PRIVACY, 0, 0x188),
+            Security::Psk
+        );
+        // WPA2/WPA3 transition takes a plain PSK; WPA3-only needs SAE.
+        assert_eq!(
+            Security::from_flags(AP_FLAGS_PRIVACY, 0, 0x588),
+            Security::Psk
+        );
+        assert_eq!(
+            Security::from_flags(AP_FLAGS_PRIVACY, 0, 0x488),
+            Security::Sae
+        );
+        assert_eq!(
+            Security::from_flags(AP_FLAGS_PRIVACY, 0x288, 0x288),
+            Security::Enterprise
+        );
+        // OWE: encrypted, but no secret to ask for.
+        assert_eq!(
+            Security::from_flags(AP_FLAGS_PRIVACY, 0, 0x888),
+            Security::Open
+        );
+        assert_eq!(Security::Sae.password_key_mgmt(), Some("sae"));
+        assert_eq!(Security::Enterprise.password_key_mgmt(), None);
+    }
+
+    #[test]
+    fn access_points_merge_by_ssid_active_then_strongest() {
+        let merged = merge_access_points(vec![
+            access_point("Cafe", 80, false),
+            access_point("Home", 40, true),
+            access_point("Home", 90, false),
+            access_point("", 99, false),
+            access_point("Cafe", 60, false),
+            access_point("Library", 80, false),
+        ]);
+        let summary: Vec<_> = merged
+            .iter()
+            .map(|ap| (ap.ssid.as_str(), ap.strength, ap.active))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("Home", 40, true),
+                ("Cafe", 80, false),
+                ("Library", 80, false)
+            ]
+        );
+        assert_eq!(merged[0].menu_text(), "✓ Home  40% 🔒");
+        let open = AccessPoint {
+            security: Security::Open,
+            ..access_point("Guest", 20, false)
+        };
+        assert_eq!(open.menu_text(), "Guest  20%");
+        assert!(!open.needs_password());
+        assert!(merged[1].needs_password());
+    }
 }
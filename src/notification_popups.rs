@@ -0,0 +1,317 @@
+// Notification popups for the bar's own notification server (see
+// notification_server.rs): one overlay surface in the top right corner, below
+// the bar, holding a column of popups, oldest on top. Each shows the icon,
+// summary and body (with the spec's markup when it parses), and a button per
+// action. Clicking a popup invokes its "default" action, or dismisses it
+// without one; right-clicking always dismisses.
+//
+// A popup goes when its timeout runs out, when one of its actions is invoked,
+// or when the client closes it, and each of those is reported back to the
+// server for NotificationClosed. A notification replacing a shown one takes
+// its place in the column and restarts the timeout. Past `[notifications]
+// max_popups` the oldest makes room, reported as expired.
+
+use std::cell::{OnceCell, RefCell};
+use std::rc::Rc;
+
+use gtk4::glib;
+use gtk4::pango;
+use gtk4::prelude::*;
+use gtk4_layer_shell::{Edge, KeyboardMode, Layer, LayerShell};
+use tokio::sync::mpsc;
+use tracing::debug;
+
+use crate::notification_server::{CloseReason, Icon, Notification, PopupEvent, PopupReply};
+
+// Pixels between the column and the screen edges (and the bar).
+const MARGIN: i32 = 8;
+const ICON_SIZE: i32 = 48;
+const WIDTH_CHARS: i32 = 40;
+const BODY_LINES: i32 = 5;
+const DEFAULT_ACTION: &str = "default";
+
+struct Popup {
+    id: u32,
+    widget: gtk4::Box,
+    expire: Option<glib::SourceId>,
+}
+
+struct Widgets {
+    window: gtk4::ApplicationWindow,
+    column: gtk4::Box,
+}
+
+// The surface is built on the first notification, like the OSD's.
+pub struct Popups {
+    application: glib::WeakRef<gtk4::Application>,
+    max: usize,
+    replies: mpsc::UnboundedSender<PopupReply>,
+    widgets: OnceCell<Widgets>,
+    // Top to bottom.
+    shown: RefCell<Vec<Popup>>,
+}
+
+impl Popups {
+    pub fn new(
+        application: &gtk4::Application,
+        max: usize,
+        replies: mpsc::UnboundedSender<PopupReply>,
+    ) -> Rc<Self> {
+        Rc::new(Self {
+            application: application.downgrade(),
+            max,
+            replies,
+            widgets: OnceCell::new(),
+            shown: RefCell::new(Vec::new()),
+        })
+    }
+
+    fn show(self: &Rc<Self>, notification: &Notification) {
+        let Some(widgets) = self.widgets() else {
+            return;
+        };
+        let widget = build_popup(self, notification);
+        let expire = notification.timeout.map(|timeout| {
+            let popups = Rc::downgrade(self);
+            let id = notification.id;
+            glib::timeout_add_local_once(timeout, move || {
+                if let Some(popups) = popups.upgrade() {
+                    popups.expired(id);
+                }
+            })
+        });
+        let popup = Popup {
+            id: notification.id,
+            widget,
+            expire,
+        };
+
+        {
+            let mut shown = self.shown.borrow_mut();
+            match shown.iter_mut().find(|shown| shown.id == notification.id) {
+                Some(shown) => {
+                    widgets
+                        .column
+                        .insert_child_after(&popup.widget, Some(&shown.widget));
+                    widgets.column.remove(&shown.widget);
+                    let old = std::mem::replace(shown, popup);
+                    // Or it would close its replacement.
+                    if let Some(expire) = old.expire {
+                        expire.remove();
+                    }
+                }
+                None => {
+                    widgets.column.append(&popup.widget);
+                    shown.push(popup);
+                }
+            }
+        }
+        widgets.window.set_visible(true);
+
+        let oldest: Vec<u32> = {
+            let shown = self.shown.borrow();
+            let excess = shown.len().saturating_sub(self.max);
+            shown[..excess].iter().map(|popup| popup.id).collect()
+        };
+        for id in oldest {
+            self.close(id, CloseReason::Expired);
+        }
+    }
+
+    // The timeout fired, so there is no source left to remove.
+    fn expired(&self, id: u32) {
+        if let Some(popup) = self
+            .shown
+            .borrow_mut()
+            .iter_mut()
+            .find(|popup| popup.id == id)
+        {
+            popup.expire.take();
+        }
+        self.close(id, CloseReason::Expired);
+    }
+
+    fn invoke(&self, id: u32, key: &str) {
+        debug!(id, key, "Notification action invoked");
+        self.reply(PopupReply::Action {
+            id,
+            key: key.to_string(),
+        });
+        self.close(id, CloseReason::Dismissed);
+    }
+
+    // Closing an id that is no longer shown does nothing.
+    fn close(&self, id: u32, reason: CloseReason) {
+        let popup = {
+            let mut shown = self.shown.borrow_mut();
+            let Some(index) = shown.iter().position(|popup| popup.id == id) else {
+                return;
+            };
+            shown.remove(index)
+        };
+        if let Some(expire) = popup.expire {
+            expire.remove();
+        }
+        if let Some(widgets) = self.widgets.get() {
+            widgets.column.remove(&popup.widget);
+            if self.shown.borrow().is_empty() {
+                widgets.window.set_visible(false);
+            }
+        }
+        self.reply(PopupReply::Closed { id, reason });
+    }
+
+    fn reply(&self, reply: PopupReply) {
+        if let Err(error) = self.replies.send(reply) {
+            debug!(%error, "Notification server is gone");
+        }
+    }
+
+    // None once the application is gone.
+    fn widgets(&self) -> Option<&Widgets> {
+        if let Some(widgets) = self.widgets.get() {
+            return Some(widgets);
+        }
+        let application = self.application.upgrade()?;
+        Some(self.widgets.get_or_init(|| build_widgets(&application)))
+    }
+}
+
+fn build_widgets(application: &gtk4::Application) -> Widgets {
+    let window = gtk4::ApplicationWindow::builder()
+        .application(application)
+        .build();
+    window.set_decorated(false);
+    window.add_css_class("notification-popups");
+    window.init_layer_shell();
+    window.set_namespace(Some("gtk-status-bar-notifications"));
+    window.set_layer(Layer::Overlay);
+    window.set_anchor(Edge::Top, true);
+    window.set_anchor(Edge::Right, true);
+    window.set_margin(Edge::Top, MARGIN);
+    window.set_margin(Edge::Right, MARGIN);
+    // 0 keeps the column below the bar's exclusive zone.
+    window.set_exclusive_zone(0);
+    window.set_keyboard_mode(KeyboardMode::None);
+
+    let column = gtk4::Box::new(gtk4::Orientation::Vertical, MARGIN);
+    window.set_child(Some(&column));
+    Widgets { window, column }
+}
+
+fn build_popup(popups: &Rc<Popups>, notification: &Notification) -> gtk4::Box {
+    let popup = gtk4::Box::new(gtk4::Orientation::Vertical, 8);
+    popup.add_css_class("notification-popup");
+    if notification.is_critical() {
+        popup.add_css_class("critical");
+    }
+    if !notification.app_name.is_empty() {
+        popup.set_tooltip_text(Some(&notification.app_name));
+    }
+
+    let content = gtk4::Box::new(gtk4::Orientation::Horizontal, 12);
+    if let Some(icon) = &notification.icon {
+        let image = match icon {
+            Icon::Name(name) => gtk4::Image::from_icon_name(name),
+            Icon::Path(path) => gtk4::Image::from_file(path),
+        };
+        image.set_pixel_size(ICON_SIZE);
+        image.set_valign(gtk4::Align::Start);
+        image.add_css_class("notification-icon");
+        content.append(&image);
+    }
+    let text = gtk4::Box::new(gtk4::Orientation::Vertical, 2);
+    text.set_hexpand(true);
+    let summary = text_label(&notification.summary, "notification-summary");
+    text.append(&summary);
+    if !notification.body.is_empty() {
+        let body = text_label("", "notification-body");
+        // Clients may send markup Pango rejects (a bare &, the spec's <img>);
+        // that shows as written.
+        if pango::parse_markup(&notification.body, '\0').is_ok() {
+            body.set_markup(&notification.body);
+        } else {
+            body.set_text(&notification.body);
+        }
+        body.set_lines(BODY_LINES);
+        body.set_ellipsize(pango::EllipsizeMode::End);
+        text.append(&body);
+    }
+    content.append(&text);
+    popup.append(&content);
+
+    let id = notification.id;
+    let has_default = notification
+        .actions
+        .iter()
+        .any(|(key, _)| key == DEFAULT_ACTION);
+    let click = gtk4::GestureClick::new();
+    let weak = Rc::downgrade(popups);
+    click.connect_released(move |_, _, _, _| {
+        let Some(popups) = weak.upgrade() else {
+            return;
+        };
+        if has_default {
+            popups.invoke(id, DEFAULT_ACTION);
+        } else {
+            popups.close(id, CloseReason::Dismissed);
+        }
+    });
+    content.add_controller(click);
+    let dismiss = gtk4::GestureClick::new();
+    dismiss.set_button(3);
+    let weak = Rc::downgrade(popups);
+    dismiss.connect_released(move |_, _, _, _| {
+        if let Some(popups) = weak.upgrade() {
+            popups.close(id, CloseReason::Dismissed);
+        }
+    });
+    popup.add_controller(dismiss);
+
+    let buttons = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+    buttons.set_homogeneous(true);
+    buttons.add_css_class("notification-actions");
+    for (key, label) in &notification.actions {
+        if key == DEFAULT_ACTION {
+            continue;
+        }
+        let button = gtk4::Button::with_label(label);
+        let weak = Rc::downgrade(popups);
+        let key = key.clone();
+        button.connect_clicked(move |_| {
+            if let Some(popups) = weak.upgrade() {
+                popups.invoke(id, &key);
+            }
+        });
+        buttons.append(&button);
+    }
+    if buttons.first_child().is_some() {
+        popup.append(&buttons);
+    }
+    popup
+}
+
+fn text_label(text: &str, class: &str) -> gtk4::Label {
+    let label = gtk4::Label::new(Some(text));
+    label.add_css_class(class);
+    label.set_xalign(0.0);
+    label.set_wrap(true);
+    label.set_wrap_mode(pango::WrapMode::WordChar);
+    label.set_max_width_chars(WIDTH_CHARS);
+    label
+}
+
+pub fn spawn_notification_popups(
+    popups: Rc<Popups>,
+    mut events: mpsc::UnboundedReceiver<PopupEvent>,
+) {
+    debug!("Starting notification popups");
+    glib::spawn_future_local(async move {
+        while let Some(event) = events.recv().await {
+            match event {
+                PopupEvent::Show(notification) => popups.show(&notification),
+                PopupEvent::Close(id) => popups.close(id, CloseReason::Closed),
+            }
+        }
+    });
+}
@@ -0,0 +1,349 @@
+// A notification server: with `[notifications] server` the bar owns
+// org.freedesktop.Notifications itself and shows each notification as a popup
+// (see notification_popups.rs), so no mako or dunst has to run next to it.
+//
+// The D-Bus side lives here, on its own session connection like the tray's
+// watcher, since the name and the served object must go away with it. Notify
+// hands the popups a Notification over a channel; the popups answer with a
+// PopupReply when one is clicked, expires or is dismissed, and those become
+// the spec's ActionInvoked and NotificationClosed signals. Only one process
+// can own the name: with another daemon running the server logs that and
+// retries on the usual backoff, taking over once the other one quits.
+//
+// The bell widget keeps watching swaync or dunst; with this server instead it
+// hides, as the spec's interface has neither a count nor do-not-disturb.
+
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use tokio::sync::{Mutex, mpsc};
+use tracing::{debug, info, warn};
+use zbus::Connection;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::OwnedValue;
+
+use crate::dbus::supervise_until;
+
+const NAME: &str = "org.freedesktop.Notifications";
+const PATH: &str = "/org/freedesktop/Notifications";
+const SPEC_VERSION: &str = "1.2";
+// The spec's urgency hint; critical notifications stay until dismissed.
+const CRITICAL: u8 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Icon {
+    Name(String),
+    Path(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub id: u32,
+    pub app_name: String,
+    pub summary: String,
+    // May carry the spec's <b>, <i>, <u> and <a> markup.
+    pub body: String,
+    pub icon: Option<Icon>,
+    // (key, label) pairs in the order the client gave them. The "default"
+    // key is invoked by clicking the popup rather than shown as a button.
+    pub actions: Vec<(String, String)>,
+    pub urgency: u8,
+    // None stays until clicked.
+    pub timeout: Option<Duration>,
+}
+
+impl Notification {
+    pub fn is_critical(&self) -> bool {
+        self.urgency >= CRITICAL
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PopupEvent {
+    // A new notification, or new content for the one with the same id.
+    Show(Notification),
+    // CloseNotification: the popup goes and reports CloseReason::Closed.
+    Close(u32),
+}
+
+// NotificationClosed's reason codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloseReason {
+    Expired = 1,
+    Dismissed = 2,
+    Closed = 3,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PopupReply {
+    Closed { id: u32, reason: CloseReason },
+    Action { id: u32, key: String },
+}
+
+// The spec's flat [key, label, key, label, ...] list; a trailing key without
+// a label is dropped.
+fn parse_actions(actions: &[String]) -> Vec<(String, String)> {
+    actions
+        .chunks_exact(2)
+        .map(|pair| (pair[0].clone(), pair[1].clone()))
+        .collect()
+}
+
+// image-path wins over app_icon, as the spec orders them. Either is a theme
+// icon name, an absolute path or a file:// URI. Raw image-data is not
+// supported.
+fn icon(app_icon: &str, image_path: Option<&str>) -> Option<Icon> {
+    let source = image_path
+        .filter(|path| !path.is_empty())
+        .unwrap_or(app_icon);
+    if source.is_empty() {
+        return None;
+    }
+    if let Some(path) = source.strip_prefix("file://") {
+        return Some(Icon::Path(PathBuf::from(path)));
+    }
+    if source.starts_with('/') {
+        return Some(Icon::Path(PathBuf::from(source)));
+    }
+    Some(Icon::Name(source.to_string()))
+}
+
+// expire_timeout is -1 for the server's default and 0 for never; critical
+// notifications ignore the default and stay.
+fn expire_after(expire_timeout: i32, urgency: u8, default: Duration) -> Option<Duration> {
+    match expire_timeout {
+        0 => None,
+        timeout if timeout > 0 => Some(Duration::from_millis(timeout as u64)),
+        _ if urgency >= CRITICAL => None,
+        _ => Some(default),
+    }
+}
+
+fn hint_str<'a>(hints: &'a HashMap<String, OwnedValue>, key: &str) -> Option<&'a str> {
+    <&str>::try_from(&**hints.get(key)?).ok()
+}
+
+fn hint_u8(hints: &HashMap<String, OwnedValue>, key: &str) -> Option<u8> {
+    u8::try_from(&**hints.get(key)?).ok()
+}
+
+struct NotificationServer {
+    popups: mpsc::UnboundedSender<PopupEvent>,
+    default_timeout: Duration,
+    next_id: u32,
+}
+
+impl NotificationServer {
+    // replaces_id only counts for an id this server handed out; anything else
+    // gets a fresh one, as the spec asks.
+    fn assign_id(&mut self, replaces_id: u32) -> u32 {
+        if replaces_id != 0 && replaces_id < self.next_id {
+            return replaces_id;
+        }
+        let id = self.next_id;
+        // 0 means "none" to clients, so wrapping skips it.
+        self.next_id = self.next_id.checked_add(1).unwrap_or(1);
+        id
+    }
+
+    fn send(&self, event: PopupEvent) {
+        if let Err(error) = self.popups.send(event) {
+            debug!(%error, "Notification popups are gone");
+        }
+    }
+}
+
+#[zbus::interface(name = "org.freedesktop.Notifications")]
+impl NotificationServer {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &mut self,
+        app_name: String,
+        replaces_id: u32,
+        app_icon: String,
+        summary: String,
+        body: String,
+        actions: Vec<String>,
+        hints: HashMap<String, OwnedValue>,
+        expire_timeout: i32,
+    ) -> u32 {
+        let id = self.assign_id(replaces_id);
+        let urgency = hint_u8(&hints, "urgency").unwrap_or(1);
+        let notification = Notification {
+            id,
+            icon: icon(&app_icon, hint_str(&hints, "image-path")),
+            actions: parse_actions(&actions),
+            urgency,
+            timeout: expire_after(expire_timeout, urgency, self.default_timeout),
+            app_name,
+            summary,
+            body,
+        };
+        debug!(id, app = notification.app_name, "Notification received");
+        self.send(PopupEvent::Show(notification));
+        id
+    }
+
+    fn close_notification(&self, id: u32) {
+        self.send(PopupEvent::Close(id));
+    }
+
+    fn get_capabilities(&self) -> Vec<&'static str> {
+        vec![
+            "actions",
+            "body",
+            "body-hyperlinks",
+            "body-markup",
+            "icon-static",
+        ]
+    }
+
+    #[zbus(out_args("name", "vendor", "version", "spec_version"))]
+    fn get_server_information(&self) -> (&'static str, &'static str, &'static str, &'static str) {
+        (
+            env!("CARGO_PKG_NAME"),
+            "gtk-status-bar",
+            env!("CARGO_PKG_VERSION"),
+            SPEC_VERSION,
+        )
+    }
+
+    #[zbus(signal)]
+    async fn notification_closed(
+        emitter: &SignalEmitter<'_>,
+        id: u32,
+        reason: u32,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn action_invoked(
+        emitter: &SignalEmitter<'_>,
+        id: u32,
+        action_key: &str,
+    ) -> zbus::Result<()>;
+}
+
+// One server session: export, claim the name, then turn replies into signals
+// until the popups go away. The channel ends are borrowed so the supervisor
+// can retry with the same GTK-side wiring.
+async fn serve(
+    popups: &mpsc::UnboundedSender<PopupEvent>,
+    replies: &mut mpsc::UnboundedReceiver<PopupReply>,
+    default_timeout: Duration,
+) -> Result<()> {
+    let connection = Connection::session()
+        .await
+        .context("connect notification server to session D-Bus")?;
+    let server = NotificationServer {
+        popups: popups.clone(),
+        default_timeout,
+        next_id: 1,
+    };
+    connection
+        .object_server()
+        .at(PATH, server)
+        .await
+        .context("export notification server")?;
+    match connection.request_name(NAME).await {
+        Ok(()) => info!("Serving notifications"),
+        Err(zbus::Error::NameTaken) => bail!("another notification daemon owns {NAME}"),
+        Err(error) => return Err(error).context("request notification server bus name"),
+    }
+    let interface = connection
+        .object_server()
+        .interface::<_, NotificationServer>(PATH)
+        .await
+        .context("access notification server")?;
+    let emitter = interface.signal_emitter();
+
+    while let Some(reply) = replies.recv().await {
+        let sent = match &reply {
+            PopupReply::Closed { id, reason } => {
+                NotificationServer::notification_closed(emitter, *id, *reason as u32).await
+            }
+            PopupReply::Action { id, key } => {
+                NotificationServer::action_invoked(emitter, *id, key).await
+            }
+        };
+        if let Err(error) = sent {
+            warn!(?reply, %error, "Could not signal a notification reply");
+        }
+    }
+    bail!("notification popups closed")
+}
+
+// The replies sit behind a lock so each session can borrow them in turn.
+pub async fn run_notification_server_supervised(
+    popups: mpsc::UnboundedSender<PopupEvent>,
+    replies: mpsc::UnboundedReceiver<PopupReply>,
+    default_timeout: Duration,
+) {
+    let replies = Mutex::new(replies);
+    supervise_until("notification server", || async {
+        let result = serve(&popups, &mut *replies.lock().await, default_timeout).await;
+        if popups.is_closed() {
+            return Ok(ControlFlow::Break(()));
+        }
+        result.map(ControlFlow::Continue)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notify_arguments_follow_the_spec() {
+        let actions = ["default", "Open", "reply", "Reply", "dangling"].map(String::from);
+        assert_eq!(
+            parse_actions(&actions),
+            vec![
+                ("default".to_string(), "Open".to_string()),
+                ("reply".to_string(), "Reply".to_string()),
+            ]
+        );
+
+        assert_eq!(icon("", None), None);
+        assert_eq!(
+            icon("firefox", None),
+            Some(Icon::Name("firefox".to_string()))
+        );
+        assert_eq!(
+            icon("firefox", Some("file:///tmp/cover.png")),
+            Some(Icon::Path(PathBuf::from("/tmp/cover.png")))
+        );
+        assert_eq!(
+            icon("/usr/share/a.svg", Some("")),
+            Some(Icon::Path(PathBuf::from("/usr/share/a.svg")))
+        );
+
+        let default = Duration::from_secs(5);
+        assert_eq!(expire_after(-1, 1, default), Some(default));
+        assert_eq!(expire_after(-1, CRITICAL, default), None);
+        assert_eq!(expire_after(0, 0, default), None);
+        assert_eq!(
+            expire_after(800, CRITICAL, default),
+            Some(Duration::from_millis(800))
+        );
+    }
+
+    #[test]
+    fn replaces_id_only_reuses_ids_handed_out() {
+        let (popups, _events) = mpsc::unbounded_channel();
+        let mut server = NotificationServer {
+            popups,
+            default_timeout: Duration::from_secs(5),
+            next_id: 1,
+        };
+        assert_eq!(server.assign_id(0), 1);
+        assert_eq!(server.assign_id(1), 1);
+        assert_eq!(server.assign_id(7), 2);
+        server.next_id = u32::MAX;
+        assert_eq!(server.assign_id(0), u32::MAX);
+        assert_eq!(server.assign_id(0), 1);
+    }
+}
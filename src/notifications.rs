@@ -0,0 +1,312 @@
+// Notification center: do-not-disturb state and unread count from whichever of
+// SwayNotificationCenter or dunst runs on the session bus, through their own
+// control interfaces (the freedesktop Notifications spec has neither DND nor
+// a count). Event-driven: swaync broadcasts Subscribe/SubscribeV2 on every
+// change and dunst emits PropertiesChanged on org.dunstproject.cmd0; either
+// wakes a re-read. NameOwnerChanged for both names notices a daemon starting,
+// quitting or being swapped for the other, and with neither running the
+// widget hides.
+//
+// The count is swaync's NotificationCount (what its panel lists) or dunst's
+// waitingLength (what it holds back while paused). Clicking toggles DND;
+// right-clicking opens swaync's panel, or, since dunst has no panel, brings
+// back dunst's most recent notification from its history as `dunstctl
+// history-pop` does.
+
+use anyhow::{Context, Result, anyhow, bail};
+use tracing::{debug, info, warn};
+use zbus::fdo;
+use zbus::message::Type as MessageType;
+use zbus::names::BusName;
+use zbus::{Connection, MatchRule, Proxy};
+
+use crate::bus::{Bus, NotificationsUpdate};
+use crate::buses::{BusKind, BusManager};
+use crate::dbus::supervise;
+
+const SWAYNC: &str = "org.erikreider.swaync.cc";
+const SWAYNC_PATH: &str = "/org/erikreider/swaync/cc";
+// dunst serves its control interface next to the spec's, under the spec's
+// name.
+const DUNST: &str = "org.freedesktop.Notifications";
+const DUNST_PATH: &str = "/org/freedesktop/Notifications";
+const DUNST_IFACE: &str = "org.dunstproject.cmd0";
+const ICON: &str = "\u{f0f3}";
+const DND_ICON: &str = "\u{f1f6}";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Daemon {
+    Swaync,
+    Dunst,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NotificationState {
+    pub dnd: bool,
+    pub count: u32,
+}
+
+pub fn display_text(state: NotificationState) -> String {
+    let icon = if state.dnd { DND_ICON } else { ICON };
+    if state.count == 0 {
+        return icon.to_string();
+    }
+    format!("{icon} {}", state.count)
+}
+
+async fn has_owner(dbus: &fdo::DBusProxy<'_>, name: &'static str) -> Result<bool> {
+    let name = BusName::try_from(name).context("notification daemon bus name")?;
+    dbus.name_has_owner(name)
+        .await
+        .context("ask D-Bus whether a notification daemon is running")
+}
+
+async fn swaync_proxy(connection: &Connection) -> Result<Proxy<'static>> {
+    Proxy::new(connection, SWAYNC, SWAYNC_PATH, SWAYNC)
+        .await
+        .context("create swaync proxy")
+}
+
+async fn dunst_proxy(connection: &Connection) -> Result<Proxy<'static>> {
+    Proxy::new(connection, DUNST, DUNST_PATH, DUNST_IFACE)
+        .await
+        .context("create dunst proxy")
+}
+
+// swaync first: it is the one with a panel. mako and others also own the
+// spec's name, so dunst has to answer its own Ping to count.
+async fn detect(connection: &Connection) -> Result<Option<Daemon>> {
+    let dbus = fdo::DBusProxy::new(connection)
+        .await
+        .context("create D-Bus proxy")?;
+    if has_owner(&dbus, SWAYNC).await? {
+        return Ok(Some(Daemon::Swaync));
+    }
+    if !has_owner(&dbus, DUNST).await? {
+        return Ok(None);
+    }
+    let pinged = dunst_proxy(connection)
+        .await?
+        .call::<_, _, ()>("Ping", &())
+        .await;
+    Ok(pinged.is_ok().then_some(Daemon::Dunst))
+}
+
+async fn read_state(connection: &Connection, daemon: Daemon) -> Result<NotificationState> {
+    match daemon {
+        Daemon::Swaync => {
+            let proxy = swaync_proxy(connection).await?;
+            let dnd: bool = proxy.call("GetDnd", &()).await.context("read swaync DND")?;
+            let count: u32 = proxy
+                .call("NotificationCount", &())
+                .await
+                .context("read swaync notification count")?;
+            Ok(NotificationState { dnd, count })
+        }
+        Daemon::Dunst => {
+            let proxy = dunst_proxy(connection).await?;
+            let dnd: bool = proxy
+                .get_property("paused")
+                .await
+                .context("read dunst paused")?;
+            let count: u32 = proxy
+                .get_property("waitingLength")
+                .await
+                .context("read dunst waitingLength")?;
+            Ok(NotificationState { dnd, count })
+        }
+    }
+}
+
+fn notification_rules() -> Result<Vec<MatchRule<'static>>> {
+    // Any member: older swaync sends Subscribe, newer SubscribeV2.
+    let swaync = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender(SWAYNC)
+        .context("swaync rule: set sender")?
+        .interface(SWAYNC)
+        .context("swaync rule: set interface")?
+        .path(SWAYNC_PATH)
+        .context("swaync rule: set path")?
+        .build();
+    let dunst = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender(DUNST)
+        .context("dunst rule: set sender")?
+        .interface("org.freedesktop.DBus.Properties")
+        .context("dunst rule: set interface")?
+        .member("PropertiesChanged")
+        .context("dunst rule: set member")?
+        .path(DUNST_PATH)
+        .context("dunst rule: set path")?
+        .arg(0, DUNST_IFACE)
+        .context("dunst rule: set interface argument")?
+        .build();
+    let mut rules = vec![swaync, dunst];
+    for name in [SWAYNC, DUNST] {
+        rules.push(
+            MatchRule::builder()
+                .msg_type(MessageType::Signal)
+                .sender("org.freedesktop.DBus")
+                .context("notification owner rule: set sender")?
+                .interface("org.freedesktop.DBus")
+                .context("notification owner rule: set interface")?
+                .member("NameOwnerChanged")
+                .context("notification owner rule: set member")?
+                .arg(0, name)
+                .context("notification owner rule: set service argument")?
+                .build(),
+        );
+    }
+    Ok(rules)
+}
+
+fn update(daemon: Option<Daemon>, state: NotificationState) -> NotificationsUpdate {
+    if daemon.is_none() {
+        return NotificationsUpdate::default();
+    }
+    NotificationsUpdate {
+        text: display_text(state),
+        dnd: state.dnd,
+    }
+}
+
+async fn monitor_notifications(bus: &Bus, buses: &BusManager) -> Result<()> {
+    // Subscribed before looking, so a daemon starting in between is not
+    // missed.
+    let mut signals = buses
+        .subscribe(BusKind::Session, notification_rules()?)
+        .await
+        .context("subscribe to notification daemon signals")?;
+    let connection = signals.connection().clone();
+    let mut daemon = detect(&connection).await?;
+    info!(?daemon, "Watching notification daemon");
+
+    let mut last = None;
+    loop {
+        let state = match daemon {
+            Some(daemon) => match read_state(&connection, daemon).await {
+                Ok(state) => state,
+                Err(e) => {
+                    warn!(?daemon, "Failed to read notification state: {:#}", e);
+                    NotificationState::default()
+                }
+            },
+            None => NotificationState::default(),
+        };
+        let current = update(daemon, state);
+        if last.as_ref() != Some(&current) {
+            debug!(update = ?current, "Sending notification state");
+            bus.send_notifications_update(current.clone())?;
+            last = Some(current);
+        }
+
+        let Some(message) = signals.next().await else {
+            return Err(anyhow!("notification daemon subscription ended"));
+        };
+        let header = message.header();
+        if header
+            .member()
+            .is_some_and(|member| member.as_str() == "NameOwnerChanged")
+        {
+            daemon = detect(&connection).await?;
+            info!(?daemon, "Notification daemon changed");
+        }
+    }
+}
+
+pub async fn run_notifications_monitor_supervised(bus: Bus, buses: BusManager) {
+    supervise("notification monitor", || {
+        monitor_notifications(&bus, &buses)
+    })
+    .await
+}
+
+async fn running_daemon() -> Result<(Connection, Daemon)> {
+    let connection = Connection::session()
+        .await
+        .context("connect to session D-Bus")?;
+    let Some(daemon) = detect(&connection).await? else {
+        bail!("neither swaync nor dunst is running");
+    };
+    Ok((connection, daemon))
+}
+
+async fn toggle_dnd() -> Result<()> {
+    let (connection, daemon) = running_daemon().await?;
+    match daemon {
+        Daemon::Swaync => {
+            let dnd: bool = swaync_proxy(&connection)
+                .await?
+                .call("ToggleDnd", &())
+                .await
+                .context("toggle swaync DND")?;
+            info!(dnd, "Toggled swaync do-not-disturb");
+        }
+        Daemon::Dunst => {
+            let proxy = dunst_proxy(&connection).await?;
+            let paused: bool = proxy
+                .get_property("paused")
+                .await
+                .context("read dunst paused")?;
+            proxy
+                .set_property("paused", !paused)
+                .await
+                .context("set dunst paused")?;
+            info!(dnd = !paused, "Toggled dunst do-not-disturb");
+        }
+    }
+    Ok(())
+}
+
+async fn open_panel() -> Result<()> {
+    let (connection, daemon) = running_daemon().await?;
+    let (proxy, method) = match daemon {
+        Daemon::Swaync => (swaync_proxy(&connection).await?, "ToggleVisibility"),
+        Daemon::Dunst => (dunst_proxy(&connection).await?, "NotificationShow"),
+    };
+    proxy
+        .call::<_, _, ()>(method, &())
+        .await
+        .with_context(|| format!("call {method}"))?;
+    Ok(())
+}
+
+// The widget's click handlers: fire-and-forget from the GTK thread, like
+// vpn::toggle_connection. The widget itself updates through the monitor.
+pub fn toggle_do_not_disturb() {
+    tokio::spawn(async {
+        if let Err(e) = toggle_dnd().await {
+            warn!("Failed to toggle do-not-disturb: {:#}", e);
+        }
+    });
+}
+
+pub fn open_notification_panel() {
+    tokio::spawn(async {
+        if let Err(e) = open_panel().await {
+            warn!("Failed to open notifications: {:#}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_shows_dnd_and_count_and_hides_without_a_daemon() {
+        let quiet = NotificationState {
+            dnd: true,
+            count: 0,
+        };
+        assert_eq!(display_text(quiet), DND_ICON);
+        let unread = NotificationState {
+            dnd: false,
+            count: 3,
+        };
+        assert_eq!(display_text(unread), format!("{ICON} 3"));
+        assert_eq!(update(None, unread), NotificationsUpdate::default());
+        assert!(update(Some(Daemon::Dunst), quiet).dnd);
+    }
+}
@@ -0,0 +1,268 @@
+// On-screen displays: one small surface of its own on the overlay layer,
+// centered near the bottom of the focused output, that shows a level with an
+// icon and a bar for `[osd] timeout_ms` after each change. Pointer input
+// passes through it.
+//
+// The sources follow AppState channels: the volume (`[osd] volume`, any
+// change PipeWire reports: keys, pavucontrol, a scroll on the volume widget)
+// and the screen brightness (`[osd] brightness`, see backlight.rs). Each
+// skips its first reading, so nothing pops up at startup, and the volume
+// also skips a sink switch. They share the surface: a new level replaces
+// whatever is showing and restarts the timeout, so a burst of key repeats is
+// one popup that follows along rather than a queue of stale ones.
+
+use std::cell::{Cell, OnceCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4_layer_shell::{Edge, KeyboardMode, Layer, LayerShell};
+use tokio::sync::watch;
+use tracing::debug;
+
+use crate::bus::VolumeUpdate;
+
+// Pixels between the popup and the bottom edge.
+const BOTTOM_MARGIN: i32 = 96;
+const BRIGHTNESS_ICON: &str = "🔆";
+
+// One popup's content.
+#[derive(Debug, Clone, PartialEq)]
+struct Level {
+    text: String,
+    // 0.0 to 1.0; more is shown full.
+    fraction: f64,
+    // Greys the bar out, for a muted sink.
+    dimmed: bool,
+}
+
+struct Widgets {
+    window: gtk4::ApplicationWindow,
+    label: gtk4::Label,
+    bar: gtk4::ProgressBar,
+}
+
+// The shared surface, built on the first popup, so a bar whose levels never
+// move never maps a second surface.
+pub struct Osd {
+    application: glib::WeakRef<gtk4::Application>,
+    timeout: Duration,
+    widgets: OnceCell<Widgets>,
+    hide: Cell<Option<glib::SourceId>>,
+}
+
+impl Osd {
+    pub fn new(application: &gtk4::Application, timeout: Duration) -> Rc<Self> {
+        Rc::new(Self {
+            application: application.downgrade(),
+            timeout,
+            widgets: OnceCell::new(),
+            hide: Cell::new(None),
+        })
+    }
+
+    fn show(self: &Rc<Self>, level: &Level) {
+        let Some(widgets) = self.widgets() else {
+            return;
+        };
+        widgets.label.set_text(&level.text);
+        widgets.bar.set_fraction(level.fraction.clamp(0.0, 1.0));
+        if level.dimmed {
+            widgets.window.add_css_class("muted");
+        } else {
+            widgets.window.remove_css_class("muted");
+        }
+        widgets.window.set_visible(true);
+
+        if let Some(hide) = self.hide.take() {
+            hide.remove();
+        }
+        let osd = Rc::downgrade(self);
+        self.hide.set(Some(glib::timeout_add_local_once(
+            self.timeout,
+            move || {
+                let Some(osd) = osd.upgrade() else {
+                    return;
+                };
+                // Fired, so there is nothing left to remove.
+                osd.hide.take();
+                if let Some(widgets) = osd.widgets.get() {
+                    widgets.window.set_visible(false);
+                }
+            },
+        )));
+    }
+
+    // None once the application is gone.
+    fn widgets(&self) -> Option<&Widgets> {
+        if let Some(widgets) = self.widgets.get() {
+            return Some(widgets);
+        }
+        let application = self.application.upgrade()?;
+        Some(self.widgets.get_or_init(|| build_widgets(&application)))
+    }
+}
+
+fn build_widgets(application: &gtk4::Application) -> Widgets {
+    let window = gtk4::ApplicationWindow::builder()
+        .application(application)
+        .build();
+    window.set_decorated(false);
+    window.add_css_class("osd");
+    window.init_layer_shell();
+    window.set_namespace(Some("gtk-status-bar-osd"));
+    window.set_layer(Layer::Overlay);
+    window.set_anchor(Edge::Bottom, true);
+    window.set_margin(Edge::Bottom, BOTTOM_MARGIN);
+    window.set_exclusive_zone(0);
+    window.set_keyboard_mode(KeyboardMode::None);
+    window.connect_map(|window| {
+        if let Some(surface) = window.surface() {
+            surface.set_input_region(Some(&gtk4::cairo::Region::create()));
+            // GTK resets the input region on every layout.
+            surface.connect_layout(|surface, _width, _height| {
+                surface.set_input_region(Some(&gtk4::cairo::Region::create()));
+            });
+        }
+    });
+
+    let content = gtk4::Box::new(gtk4::Orientation::Horizontal, 12);
+    content.add_css_class("osd-content");
+    let label = gtk4::Label::new(None);
+    label.add_css_class("osd-label");
+    let bar = gtk4::ProgressBar::new();
+    bar.add_css_class("osd-bar");
+    bar.set_hexpand(true);
+    bar.set_valign(gtk4::Align::Center);
+    content.append(&label);
+    content.append(&bar);
+    window.set_child(Some(&content));
+    Widgets { window, label, bar }
+}
+
+// What the volume popup shows; equal readings are not a change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct VolumeReading {
+    sink: String,
+    percent: u8,
+    muted: bool,
+}
+
+impl VolumeReading {
+    fn new(update: &VolumeUpdate) -> Option<Self> {
+        Some(Self {
+            sink: update.name.clone(),
+            percent: update.channel_percent.or(update.volume_percent)?,
+            muted: update.is_muted == Some(true),
+        })
+    }
+
+    fn level(&self) -> Level {
+        let icon = if self.muted { "🔇" } else { "🔊" };
+        Level {
+            text: format!("{icon} {}%", self.percent),
+            fraction: f64::from(self.percent) / 100.0,
+            dimmed: self.muted,
+        }
+    }
+}
+
+// Whether `current` is news worth a popup after `previous`: a new level or
+// mute state on the same sink.
+fn volume_changed(previous: Option<&VolumeReading>, current: &VolumeReading) -> bool {
+    previous.is_some_and(|previous| previous.sink == current.sink && previous != current)
+}
+
+fn brightness_level(percent: u8) -> Level {
+    Level {
+        text: format!("{BRIGHTNESS_ICON} {percent}%"),
+        fraction: f64::from(percent) / 100.0,
+        dimmed: false,
+    }
+}
+
+pub fn spawn_volume_osd(osd: Rc<Osd>, mut audio: watch::Receiver<Option<VolumeUpdate>>) {
+    debug!("Starting the volume OSD");
+    glib::spawn_future_local(async move {
+        let mut previous: Option<VolumeReading> = None;
+        while audio.changed().await.is_ok() {
+            let reading = audio
+                .borrow_and_update()
+                .as_ref()
+                .and_then(VolumeReading::new);
+            let Some(reading) = reading else {
+                continue;
+            };
+            if volume_changed(previous.as_ref(), &reading) {
+                osd.show(&reading.level());
+            }
+            previous = Some(reading);
+        }
+    });
+}
+
+pub fn spawn_brightness_osd(osd: Rc<Osd>, mut brightness: watch::Receiver<Option<u8>>) {
+    debug!("Starting the brightness OSD");
+    glib::spawn_future_local(async move {
+        // The watch only reports real changes; the first is the startup read.
+        let mut seen = brightness.borrow_and_update().is_some();
+        while brightness.changed().await.is_ok() {
+            let Some(percent) = *brightness.borrow_and_update() else {
+                continue;
+            };
+            if seen {
+                osd.show(&brightness_level(percent));
+            }
+            seen = true;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(sink: &str, percent: u8, muted: bool) -> VolumeReading {
+        VolumeReading {
+            sink: sink.to_string(),
+            percent,
+            muted,
+        }
+    }
+
+    #[test]
+    fn only_volume_changes_on_the_same_sink_pop_up() {
+        let speakers = reading("Speakers", 40, false);
+        assert!(!volume_changed(None, &speakers));
+        assert!(!volume_changed(Some(&speakers), &speakers));
+        assert!(volume_changed(
+            Some(&speakers),
+            &reading("Speakers", 45, false)
+        ));
+        assert!(volume_changed(
+            Some(&speakers),
+            &reading("Speakers", 40, true)
+        ));
+        assert!(!volume_changed(
+            Some(&speakers),
+            &reading("Headphones", 70, false)
+        ));
+    }
+
+    #[test]
+    fn levels_carry_their_icon_and_fraction() {
+        let muted = reading("Speakers", 40, true).level();
+        assert_eq!(muted.text, "🔇 40%");
+        assert!(muted.dimmed);
+        assert_eq!(reading("Speakers", 150, false).level().fraction, 1.5);
+        assert_eq!(
+            brightness_level(60),
+            Level {
+                text: "🔆 60%".to_string(),
+                fraction: 0.6,
+                dimmed: false,
+            }
+        );
+    }
+}
@@ -0,0 +1,131 @@
+// Color variables for the stylesheets. themes/palette.css defines the named
+// colors (Tokyo Night) and the per-workspace title colors at application
+// priority, under any stylesheet; `[colors]` in config.toml is turned into
+// `@define-color` lines on a provider above the stylesheet and the theme
+// preset, so a config value always wins. Reloading the config reloads that
+// provider in place.
+//
+// The title pill takes its background from here too: `[workspace.colors]`
+// by id, then the palette's `@workspace_<id>`, then `@workspace_default`.
+
+use std::collections::BTreeMap;
+
+use tracing::info;
+
+use crate::config::WorkspaceConfig;
+
+const PALETTE: &str = include_str!("../themes/palette.css");
+// How many `@workspace_<id>` colors palette.css defines.
+const PALETTE_WORKSPACES: i32 = 10;
+
+pub fn variables_css(colors: &BTreeMap<String, String>) -> String {
+    colors
+        .iter()
+        .map(|(name, value)| format!("@define-color {name} {value};\n"))
+        .collect()
+}
+
+// Returns the `[colors]` provider for reloads to refill.
+pub fn apply_colors(
+    window: &gtk4::ApplicationWindow,
+    colors: &BTreeMap<String, String>,
+) -> gtk4::CssProvider {
+    let display = gtk4::prelude::WidgetExt::display(window);
+    let palette = gtk4::CssProvider::new();
+    palette.load_from_string(PALETTE);
+    gtk4::style_context_add_provider_for_display(
+        &display,
+        &palette,
+        gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
+    );
+
+    let overrides = gtk4::CssProvider::new();
+    load_colors(&overrides, colors);
+    gtk4::style_context_add_provider_for_display(
+        &display,
+        &overrides,
+        gtk4::STYLE_PROVIDER_PRIORITY_USER + 2,
+    );
+    overrides
+}
+
+pub fn load_colors(provider: &gtk4::CssProvider, colors: &BTreeMap<String, String>) {
+    provider.load_from_string(&variables_css(colors));
+    if !colors.is_empty() {
+        info!(count = colors.len(), "Applied configured colors");
+    }
+}
+
+// The title pill's background for each workspace.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkspaceColors {
+    special: String,
+    by_id: BTreeMap<i32, String>,
+}
+
+impl WorkspaceColors {
+    // The keys were checked when the config was parsed.
+    pub fn new(config: &WorkspaceConfig) -> Self {
+        Self {
+            special: config.special_color.clone(),
+            by_id: config
+                .colors
+                .iter()
+                .filter_map(|(id, color)| Some((id.parse().ok()?, color.clone())))
+                .collect(),
+        }
+    }
+
+    // A special workspace ignores the id palette: its id is a sentinel, and
+    // scratchpad mode should look the same whichever one is shown.
+    pub fn color(&self, id: i32, special: bool) -> String {
+        if special {
+            return self.special.clone();
+        }
+        if let Some(color) = self.by_id.get(&id) {
+            return color.clone();
+        }
+        if (1..=PALETTE_WORKSPACES).contains(&id) {
+            format!("@workspace_{id}")
+        } else {
+            "@workspace_default".to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configured_colors_become_definitions() {
+        let colors = BTreeMap::from([
+            ("blue".to_string(), "#89b4fa".to_string()),
+            ("accent".to_string(), "alpha(@blue, 0.7)".to_string()),
+        ]);
+        assert_eq!(
+            variables_css(&colors),
+            "@define-color accent alpha(@blue, 0.7);\n@define-color blue #89b4fa;\n"
+        );
+        for id in 1..=PALETTE_WORKSPACES {
+            assert!(PALETTE.contains(&format!("@define-color workspace_{id} ")));
+        }
+        assert!(PALETTE.contains("@define-color workspace_default "));
+    }
+
+    #[test]
+    fn workspace_colors_fall_back_to_the_palette() {
+        let colors = WorkspaceColors::new(&WorkspaceConfig {
+            colors: BTreeMap::from([("3".to_string(), "@red".to_string())]),
+            ..WorkspaceConfig::default()
+        });
+        assert_eq!(colors.color(3, false), "@red");
+        assert_eq!(colors.color(1, false), "@workspace_1");
+        assert_eq!(colors.color(11, false), "@workspace_default");
+        assert_eq!(colors.color(-98, false), "@workspace_default");
+        assert_eq!(
+            colors.color(-98, true),
+            crate::config::DEFAULT_SPECIAL_WORKSPACE_COLOR
+        );
+    }
+}
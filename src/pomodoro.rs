@@ -0,0 +1,217 @@
+// Pomodoro timer: click the widget to start a work phase, click again to stop.
+// Work and break phases alternate until stopped. The countdown runs on the GTK
+// clock tick and writes a display string to the model's `pomodoro` property.
+//
+// Focus mode: while a work phase runs, the `[pomodoro] focus_keywords` are
+// applied to Hyprland (no gaps, no animations, ...) and the previous values are
+// put back when the break starts or the timer stops. The keyword calls go
+// through hypr::run_focus_mode on tokio, which owns the saved values, so the
+// UI only says "focus on" or "focus off".
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+use crate::clock::Clock;
+use crate::model::BarModel;
+
+const IDLE_TEXT: &str = "🍅";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Work,
+    Break,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Running {
+    phase: Phase,
+    ends_at: DateTime<Local>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Durations {
+    pub work: Duration,
+    pub rest: Duration,
+}
+
+// The countdown without the main loop, so phase changes are testable.
+#[derive(Debug, Default)]
+struct Countdown {
+    running: Option<Running>,
+}
+
+impl Countdown {
+    fn start(&mut self, now: DateTime<Local>, durations: Durations) {
+        self.running = Some(Running {
+            phase: Phase::Work,
+            ends_at: now + durations.work,
+        });
+    }
+
+    fn stop(&mut self) {
+        self.running = None;
+    }
+
+    fn phase(&self) -> Option<Phase> {
+        self.running.map(|running| running.phase)
+    }
+
+    // Advance past `now`, returning the new phase when one ended. A tick that
+    // arrives late (suspend, a stalled main loop) starts the next phase from
+    // now rather than replaying every phase that would have elapsed.
+    fn tick(&mut self, now: DateTime<Local>, durations: Durations) -> Option<Phase> {
+        let running = self.running.as_mut()?;
+        if now < running.ends_at {
+            return None;
+        }
+        let (phase, length) = match running.phase {
+            Phase::Work => (Phase::Break, durations.rest),
+            Phase::Break => (Phase::Work, durations.work),
+        };
+        *running = Running {
+            phase,
+            ends_at: now + length,
+        };
+        Some(phase)
+    }
+
+    fn text(&self, now: DateTime<Local>) -> String {
+        let Some(running) = self.running else {
+            return IDLE_TEXT.to_string();
+        };
+        let left = (running.ends_at - now).num_seconds().max(0);
+        let icon = match running.phase {
+            Phase::Work => "🍅",
+            Phase::Break => "☕",
+        };
+        format!("{} {:02}:{:02}", icon, left / 60, left % 60)
+    }
+}
+
+struct Inner {
+    countdown: RefCell<Countdown>,
+    durations: Durations,
+    model: BarModel,
+    // None when no focus keywords are configured.
+    focus_tx: Option<mpsc::UnboundedSender<bool>>,
+}
+
+// Shared by the widget's click handler and the clock tick. Cheap to clone.
+#[derive(Clone)]
+pub struct PomodoroTimer {
+    inner: Rc<Inner>,
+}
+
+impl PomodoroTimer {
+    pub fn new(
+        model: BarModel,
+        durations: Durations,
+        focus_tx: Option<mpsc::UnboundedSender<bool>>,
+    ) -> Self {
+        model.set_pomodoro(IDLE_TEXT);
+        Self {
+            inner: Rc::new(Inner {
+                countdown: RefCell::default(),
+                durations,
+                model,
+                focus_tx,
+            }),
+        }
+    }
+
+    pub fn toggle(&self) {
+        let now = Local::now();
+        let started = {
+            let mut countdown = self.inner.countdown.borrow_mut();
+            if countdown.phase().is_some() {
+                countdown.stop();
+                false
+            } else {
+                countdown.start(now, self.inner.durations);
+                true
+            }
+        };
+        info!(started, "Pomodoro toggled");
+        self.set_focus(started);
+        self.refresh(now);
+    }
+
+    fn tick(&self, now: DateTime<Local>) {
+        let changed = self
+            .inner
+            .countdown
+            .borrow_mut()
+            .tick(now, self.inner.durations);
+        if let Some(phase) = changed {
+            info!(?phase, "Pomodoro phase changed");
+            self.set_focus(phase == Phase::Work);
+        }
+        self.refresh(now);
+    }
+
+    fn refresh(&self, now: DateTime<Local>) {
+        let text = self.inner.countdown.borrow().text(now);
+        if self.inner.model.pomodoro() != text {
+            self.inner.model.set_pomodoro(text);
+        }
+    }
+
+    fn set_focus(&self, on: bool) {
+        let Some(focus_tx) = &self.inner.focus_tx else {
+            return;
+        };
+        debug!(on, "Requesting focus mode change");
+        if focus_tx.send(on).is_err() {
+            warn!("Focus mode task is gone; Hyprland keywords left as they are");
+        }
+    }
+
+    pub fn start_ticking(&self) {
+        let timer = self.clone();
+        Clock::new().on_second(move |now| timer.tick(now)).start();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DURATIONS: Durations = Durations {
+        work: Duration::from_secs(25 * 60),
+        rest: Duration::from_secs(5 * 60),
+    };
+
+    #[test]
+    fn work_and_break_alternate() {
+        let start = Local::now();
+        let mut countdown = Countdown::default();
+        countdown.start(start, DURATIONS);
+        assert_eq!(
+            countdown.tick(start + Duration::from_secs(60), DURATIONS),
+            None
+        );
+
+        let work_end = start + DURATIONS.work;
+        assert_eq!(countdown.tick(work_end, DURATIONS), Some(Phase::Break));
+        assert_eq!(
+            countdown.tick(work_end + DURATIONS.rest, DURATIONS),
+            Some(Phase::Work)
+        );
+    }
+
+    #[test]
+    fn text_counts_down_and_idles() {
+        let start = Local::now();
+        let mut countdown = Countdown::default();
+        assert_eq!(countdown.text(start), IDLE_TEXT);
+        countdown.start(start, DURATIONS);
+        assert_eq!(countdown.text(start + Duration::from_secs(61)), "🍅 23:59");
+        countdown.stop();
+        assert_eq!(countdown.tick(start + DURATIONS.work, DURATIONS), None);
+    }
+}
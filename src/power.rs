@@ -0,0 +1,156 @@
+// The power menu: lock, log out, suspend, reboot and shut down from the power
+// widget's popover. An action with a `[power]` command runs it through
+// `sh -c`; otherwise it asks logind over the system bus, Lock and Terminate on
+// the bar's own session and Suspend, Reboot and PowerOff on the manager.
+// Those last three are interactive calls, so polkit may ask for a password
+// when another user is logged in. logind's Lock only asks the session's idle
+// daemon to lock; without one running `[power] lock` has to name a locker.
+
+use anyhow::{Context, Result};
+use tracing::{info, warn};
+use zbus::{Connection, Proxy};
+
+use crate::command;
+use crate::config::PowerConfig;
+use crate::logind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerAction {
+    Lock,
+    Logout,
+    Suspend,
+    Reboot,
+    Shutdown,
+}
+
+impl PowerAction {
+    // In popover order.
+    pub const ALL: [PowerAction; 5] = [
+        PowerAction::Lock,
+        PowerAction::Logout,
+        PowerAction::Suspend,
+        PowerAction::Reboot,
+        PowerAction::Shutdown,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PowerAction::Lock => "Lock",
+            PowerAction::Logout => "Log out",
+            PowerAction::Suspend => "Suspend",
+            PowerAction::Reboot => "Reboot",
+            PowerAction::Shutdown => "Shut down",
+        }
+    }
+
+    // The actions `[power] confirm` asks about: the ones that lose open
+    // windows. Locking and suspending are undone by logging back in.
+    pub fn needs_confirmation(self) -> bool {
+        matches!(
+            self,
+            PowerAction::Logout | PowerAction::Reboot | PowerAction::Shutdown
+        )
+    }
+
+    fn command(self, config: &PowerConfig) -> Option<&str> {
+        match self {
+            PowerAction::Lock => config.lock.as_deref(),
+            PowerAction::Logout => config.logout.as_deref(),
+            PowerAction::Suspend => config.suspend.as_deref(),
+            PowerAction::Reboot => config.reboot.as_deref(),
+            PowerAction::Shutdown => config.shutdown.as_deref(),
+        }
+    }
+
+    // The logind method, and whether it is on the session rather than the
+    // manager.
+    fn logind_method(self) -> (&'static str, bool) {
+        match self {
+            PowerAction::Lock => ("Lock", true),
+            PowerAction::Logout => ("Terminate", true),
+            PowerAction::Suspend => ("Suspend", false),
+            PowerAction::Reboot => ("Reboot", false),
+            PowerAction::Shutdown => ("PowerOff", false),
+        }
+    }
+}
+
+// The popover's click handler: fire-and-forget from the GTK thread.
+pub fn run(action: PowerAction, config: &PowerConfig) {
+    if let Some(command) = action.command(config) {
+        command::spawn_shell(command, action.label());
+        return;
+    }
+    tokio::spawn(async move {
+        if let Err(e) = call_logind(action).await {
+            warn!(?action, "Power action failed: {:#}", e);
+        }
+    });
+}
+
+async fn call_logind(action: PowerAction) -> Result<()> {
+    let connection = Connection::system()
+        .await
+        .context("connect to system D-Bus")?;
+    let (method, on_session) = action.logind_method();
+    info!(?action, method, "Asking logind");
+    if on_session {
+        let scope = logind::resolve_session_scope(&connection).await?;
+        let session = Proxy::new(
+            &connection,
+            logind::LOGIND,
+            scope.session_path.as_str(),
+            logind::SESSION_IFACE,
+        )
+        .await
+        .context("create logind session proxy")?;
+        session
+            .call::<_, _, ()>(method, &())
+            .await
+            .with_context(|| format!("logind {method}"))?;
+        return Ok(());
+    }
+    let manager = Proxy::new(
+        &connection,
+        logind::LOGIND,
+        logind::LOGIND_PATH,
+        logind::MANAGER_IFACE,
+    )
+    .await
+    .context("create logind manager proxy")?;
+    // interactive = true: let polkit ask instead of refusing outright.
+    manager
+        .call::<_, _, ()>(method, &(true,))
+        .await
+        .with_context(|| format!("logind {method}"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_replace_logind_per_action() {
+        let config = PowerConfig {
+            lock: Some("hyprlock".to_string()),
+            ..PowerConfig::default()
+        };
+        assert_eq!(PowerAction::Lock.command(&config), Some("hyprlock"));
+        assert_eq!(PowerAction::Shutdown.command(&config), None);
+        assert_eq!(PowerAction::Shutdown.logind_method(), ("PowerOff", false));
+        assert_eq!(PowerAction::Logout.logind_method(), ("Terminate", true));
+        let confirmed: Vec<_> = PowerAction::ALL
+            .into_iter()
+            .filter(|action| action.needs_confirmation())
+            .collect();
+        assert_eq!(
+            confirmed,
+            [
+                PowerAction::Logout,
+                PowerAction::Reboot,
+                PowerAction::Shutdown
+            ]
+        );
+    }
+}
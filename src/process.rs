@@ -0,0 +1,86 @@
+// Process details for the title tooltip: the focused window's PID (from
+// Hyprland's client list or Sway's tree), its command line and resident
+// memory. The /proc reads happen when the tooltip opens rather than on every
+// focus change, since most windows are never hovered, and they are small
+// generated files that do not block.
+
+use anyhow::{Context, Result};
+
+use crate::format::Format;
+use crate::text;
+
+// Electron and JVM command lines run to kilobytes.
+const MAX_COMMAND_LINE: usize = 120;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub pid: i32,
+    pub command_line: String,
+    pub rss_kib: Option<u64>,
+}
+
+// /proc/<pid>/cmdline separates arguments with NULs and ends with one.
+pub fn command_line(raw: &[u8]) -> String {
+    raw.split(|byte| *byte == 0)
+        .filter(|argument| !argument.is_empty())
+        .map(String::from_utf8_lossy)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// The "VmRSS:    123456 kB" line of /proc/<pid>/status; kernel threads have
+// none.
+pub fn rss_kib(status: &str) -> Option<u64> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|value| value.split_whitespace().next())
+        .and_then(|kib| kib.parse().ok())
+}
+
+pub fn read(pid: i32) -> Result<ProcessInfo> {
+    let proc = format!("/proc/{pid}");
+    let raw =
+        std::fs::read(format!("{proc}/cmdline")).with_context(|| format!("read {proc}/cmdline"))?;
+    let status = std::fs::read_to_string(format!("{proc}/status"))
+        .with_context(|| format!("read {proc}/status"))?;
+    Ok(ProcessInfo {
+        pid,
+        command_line: text::format_title_string(command_line(&raw), MAX_COMMAND_LINE),
+        rss_kib: rss_kib(&status),
+    })
+}
+
+pub fn tooltip_text(info: &ProcessInfo, format: &Format) -> String {
+    let mut text = format!("PID {}\n{}", info.pid, info.command_line);
+    if let Some(kib) = info.rss_kib {
+        text.push_str(&format!("\n{} resident", format.size(kib * 1024)));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_command_line_and_resident_memory() {
+        assert_eq!(
+            command_line(b"/usr/bin/foot\0--server\0\0"),
+            "/usr/bin/foot --server"
+        );
+        let status = "Name:\tfoot\nVmPeak:\t  99999 kB\nVmRSS:\t   52224 kB\nThreads:\t3\n";
+        assert_eq!(rss_kib(status), Some(52224));
+        assert_eq!(rss_kib("Name:\tkthreadd\n"), None);
+
+        let info = ProcessInfo {
+            pid: 4242,
+            command_line: "foot --server".to_string(),
+            rss_kib: Some(3 * 1024 * 1024 / 2),
+        };
+        assert_eq!(
+            tooltip_text(&info, &Format::default()),
+            "PID 4242\nfoot --server\n1.5 GiB resident"
+        );
+    }
+}
@@ -0,0 +1,244 @@
+// Progress chip: download and copy progress that applications publish through
+// the Unity LauncherEntry API, the same one docks (Plank, Dash to Dock, KDE's
+// task manager) read. Firefox and Chromium downloads, Nautilus and Nemo file
+// operations and Transmission broadcast `Update(app_uri, properties)` on
+// com.canonical.Unity.LauncherEntry with `progress` (0.0 to 1.0) and
+// `progress-visible`. org.freedesktop.FileManager1 only opens folders and has
+// no progress of its own, so LauncherEntry is all there is to listen to.
+//
+// An Update carries only the properties that changed, so jobs are merged per
+// sender and application. An application that quits mid-copy never sends
+// progress-visible=false, hence NameOwnerChanged drops whatever its unique name
+// left behind. With several jobs the chip shows their average and how many
+// there are; the tooltip lists each.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result, anyhow};
+use tracing::{debug, info, warn};
+use zbus::MatchRule;
+use zbus::message::Type as MessageType;
+use zbus::zvariant::OwnedValue;
+
+use crate::bus::{Bus, ProgressUpdate};
+use crate::buses::{BusKind, BusManager};
+use crate::dbus::supervise;
+
+const LAUNCHER_ENTRY: &str = "com.canonical.Unity.LauncherEntry";
+const ICON: &str = "\u{f019}";
+
+#[derive(Debug, Clone, PartialEq)]
+struct Job {
+    sender: String,
+    app: String,
+    progress: f64,
+    visible: bool,
+}
+
+// "application://org.gnome.Nautilus.desktop" -> "org.gnome.Nautilus".
+fn app_name(app_uri: &str) -> String {
+    let name = app_uri.strip_prefix("application://").unwrap_or(app_uri);
+    name.strip_suffix(".desktop").unwrap_or(name).to_string()
+}
+
+#[derive(Debug, Default)]
+pub struct Jobs {
+    jobs: Vec<Job>,
+}
+
+impl Jobs {
+    pub fn update(
+        &mut self,
+        sender: &str,
+        app_uri: &str,
+        progress: Option<f64>,
+        visible: Option<bool>,
+    ) {
+        let app = app_name(app_uri);
+        let index = match self
+            .jobs
+            .iter()
+            .position(|job| job.sender == sender && job.app == app)
+        {
+            Some(index) => index,
+            None => {
+                self.jobs.push(Job {
+                    sender: sender.to_string(),
+                    app,
+                    progress: 0.0,
+                    visible: false,
+                });
+                self.jobs.len() - 1
+            }
+        };
+        let job = &mut self.jobs[index];
+        if let Some(progress) = progress {
+            job.progress = progress.clamp(0.0, 1.0);
+        }
+        if let Some(visible) = visible {
+            job.visible = visible;
+        }
+    }
+
+    pub fn sender_left(&mut self, sender: &str) {
+        self.jobs.retain(|job| job.sender != sender);
+    }
+
+    // Empty text hides the chip.
+    pub fn display(&self) -> ProgressUpdate {
+        let visible: Vec<&Job> = self.jobs.iter().filter(|job| job.visible).collect();
+        if visible.is_empty() {
+            return ProgressUpdate::default();
+        }
+        let fraction = visible.iter().map(|job| job.progress).sum::<f64>() / visible.len() as f64;
+        let percent = fraction * 100.0;
+        let text = match visible.len() {
+            1 => format!("{ICON} {percent:.0}%"),
+            count => format!("{ICON} {percent:.0}% ×{count}"),
+        };
+        let tooltip = visible
+            .iter()
+            .map(|job| format!("{} {:.0}%", job.app, job.progress * 100.0))
+            .collect::<Vec<_>>()
+            .join("\n");
+        ProgressUpdate {
+            text,
+            fraction,
+            tooltip,
+        }
+    }
+}
+
+fn progress_rules() -> Result<Vec<MatchRule<'static>>> {
+    // Entries are exported from any path, but nothing else uses this
+    // interface, so it pins the signal on its own.
+    let update = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .interface(LAUNCHER_ENTRY)
+        .context("LauncherEntry rule: set interface")?
+        .member("Update")
+        .context("LauncherEntry rule: set member")?
+        .build();
+    let owner = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender("org.freedesktop.DBus")
+        .context("LauncherEntry owner rule: set sender")?
+        .path("/org/freedesktop/DBus")
+        .context("LauncherEntry owner rule: set path")?
+        .interface("org.freedesktop.DBus")
+        .context("LauncherEntry owner rule: set interface")?
+        .member("NameOwnerChanged")
+        .context("LauncherEntry owner rule: set member")?
+        .build();
+    Ok(vec![update, owner])
+}
+
+fn handle_signal(message: &zbus::Message, jobs: &mut Jobs) {
+    let header = message.header();
+    let member = header
+        .member()
+        .map(|member| member.as_str())
+        .unwrap_or_default();
+    if member == "NameOwnerChanged" {
+        let Ok((name, _, new_owner)) = message.body().deserialize::<(String, String, String)>()
+        else {
+            warn!("Malformed NameOwnerChanged signal");
+            return;
+        };
+        if new_owner.is_empty() && name.starts_with(':') {
+            jobs.sender_left(&name);
+        }
+        return;
+    }
+    let Some(sender) = header.sender().map(|sender| sender.to_string()) else {
+        return;
+    };
+    let Ok((app_uri, properties)) = message
+        .body()
+        .deserialize::<(String, HashMap<String, OwnedValue>)>()
+    else {
+        warn!(sender, "Malformed LauncherEntry Update signal");
+        return;
+    };
+    let progress = properties
+        .get("progress")
+        .and_then(|value| f64::try_from(value.clone()).ok());
+    let visible = properties
+        .get("progress-visible")
+        .and_then(|value| bool::try_from(value.clone()).ok());
+    if progress.is_none() && visible.is_none() {
+        // Badge counts and urgency, for docks.
+        return;
+    }
+    debug!(
+        sender,
+        app_uri,
+        ?progress,
+        ?visible,
+        "LauncherEntry progress"
+    );
+    jobs.update(&sender, &app_uri, progress, visible);
+}
+
+async fn monitor_progress(bus: &Bus, buses: &BusManager) -> Result<()> {
+    let mut signals = buses
+        .subscribe(BusKind::Session, progress_rules()?)
+        .await
+        .context("subscribe to LauncherEntry signals")?;
+    info!("Watching LauncherEntry progress");
+    // Jobs started before the bar are unknown until their next Update, which
+    // comes within a second for anything still moving.
+    let mut jobs = Jobs::default();
+    let mut last = ProgressUpdate::default();
+    bus.send_progress_update(last.clone())?;
+    while let Some(message) = signals.next().await {
+        handle_signal(&message, &mut jobs);
+        let current = jobs.display();
+        if current != last {
+            bus.send_progress_update(current.clone())?;
+            last = current;
+        }
+    }
+    Err(anyhow!("LauncherEntry subscription ended"))
+}
+
+pub async fn run_progress_monitor_supervised(bus: Bus, buses: BusManager) {
+    supervise("progress monitor", || monitor_progress(&bus, &buses)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_updates_merge_and_departed_senders_drop_out() {
+        let mut jobs = Jobs::default();
+        jobs.update(
+            ":1.7",
+            "application://firefox.desktop",
+            Some(0.2),
+            Some(true),
+        );
+        assert_eq!(jobs.display().text, format!("{ICON} 20%"));
+
+        jobs.update(
+            ":1.9",
+            "application://org.gnome.Nautilus.desktop",
+            None,
+            Some(true),
+        );
+        jobs.update(
+            ":1.9",
+            "application://org.gnome.Nautilus.desktop",
+            Some(0.6),
+            None,
+        );
+        let both = jobs.display();
+        assert_eq!(both.text, format!("{ICON} 40% ×2"));
+        assert_eq!(both.tooltip, "firefox 20%\norg.gnome.Nautilus 60%");
+
+        jobs.update(":1.7", "application://firefox.desktop", None, Some(false));
+        jobs.sender_left(":1.9");
+        assert_eq!(jobs.display(), ProgressUpdate::default());
+    }
+}
@@ -0,0 +1,153 @@
+// Quick settings: the gear popover that gathers volume, brightness, the Wi-Fi
+// and Bluetooth radios and the power profile in one place. It has no monitor
+// of its own. The sliders follow the AppState the volume and brightness OSDs
+// read (see views.rs), the switches read rfkill each time the popover opens,
+// and the profile list comes from power-profiles-daemon the same way.
+//
+// Moving a slider runs `wpctl set-volume` on the default sink or logind's
+// SetBrightness (see backlight.rs); a switch writes an rfkill block; picking a
+// profile sets ActiveProfile, which power-profiles-daemon's polkit rules let
+// the active session do.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+use zbus::zvariant::OwnedValue;
+use zbus::{Connection, Proxy};
+
+use crate::command;
+
+const POWER_PROFILES: &str = "net.hadess.PowerProfiles";
+const POWER_PROFILES_PATH: &str = "/net/hadess/PowerProfiles";
+const POWER_PROFILES_IFACE: &str = "net.hadess.PowerProfiles";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PowerProfiles {
+    // In the daemon's order: power-saver, balanced, performance where the
+    // hardware has it.
+    pub available: Vec<String>,
+    pub active: String,
+}
+
+// Each entry of the Profiles property is a dict whose "Profile" names it.
+fn profile_names(profiles: &[HashMap<String, OwnedValue>]) -> Vec<String> {
+    profiles
+        .iter()
+        .filter_map(|profile| profile.get("Profile"))
+        .filter_map(|name| String::try_from(name.clone()).ok())
+        .collect()
+}
+
+// "power-saver" reads "Power saver" in the drop-down.
+pub fn profile_label(name: &str) -> String {
+    let spaced = name.replace('-', " ");
+    let mut chars = spaced.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+async fn profiles_proxy(connection: &Connection) -> Result<Proxy<'static>> {
+    Proxy::new(
+        connection,
+        POWER_PROFILES,
+        POWER_PROFILES_PATH,
+        POWER_PROFILES_IFACE,
+    )
+    .await
+    .context("create power-profiles-daemon proxy")
+}
+
+async fn read_power_profiles() -> Result<PowerProfiles> {
+    let connection = Connection::system()
+        .await
+        .context("connect to system D-Bus")?;
+    let proxy = profiles_proxy(&connection).await?;
+    let profiles: Vec<HashMap<String, OwnedValue>> = proxy
+        .get_property("Profiles")
+        .await
+        .context("read Profiles")?;
+    let active: String = proxy
+        .get_property("ActiveProfile")
+        .await
+        .context("read ActiveProfile")?;
+    Ok(PowerProfiles {
+        available: profile_names(&profiles),
+        active,
+    })
+}
+
+// Sends the profiles once; without power-profiles-daemon the sender just
+// drops, which leaves the selector hidden.
+pub fn load_power_profiles(updates: mpsc::UnboundedSender<PowerProfiles>) {
+    tokio::spawn(async move {
+        match read_power_profiles().await {
+            Ok(profiles) => {
+                debug!(?profiles, "Read power profiles");
+                let _ = updates.send(profiles);
+            }
+            Err(e) => debug!("No power profiles: {:#}", e),
+        }
+    });
+}
+
+async fn write_power_profile(name: &str) -> Result<()> {
+    let connection = Connection::system()
+        .await
+        .context("connect to system D-Bus")?;
+    let proxy = profiles_proxy(&connection).await?;
+    proxy
+        .set_property("ActiveProfile", name)
+        .await
+        .with_context(|| format!("set ActiveProfile to {name:?}"))?;
+    Ok(())
+}
+
+// Fire-and-forget from the GTK thread.
+pub fn set_power_profile(name: String) {
+    tokio::spawn(async move {
+        info!(profile = name, "Setting power profile");
+        if let Err(e) = write_power_profile(&name).await {
+            warn!("Failed to set the power profile: {:#}", e);
+        }
+    });
+}
+
+pub fn set_volume(percent: u8) {
+    let percent = percent.min(100);
+    command::spawn_shell(
+        &format!("wpctl set-volume @DEFAULT_AUDIO_SINK@ {percent}%"),
+        "volume",
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use zbus::zvariant::Value;
+
+    use super::*;
+
+    #[test]
+    fn profile_names_come_from_the_profile_key() {
+        let entry = |key: &str, value: &str| {
+            let value = OwnedValue::try_from(Value::from(value)).expect("string value");
+            HashMap::from([(key.to_string(), value)])
+        };
+        let profiles = [
+            entry("Profile", "power-saver"),
+            entry("Driver", "platform_profile"),
+            entry("Profile", "balanced"),
+        ];
+        assert_eq!(profile_names(&profiles), ["power-saver", "balanced"]);
+    }
+
+    #[test]
+    fn profile_labels_are_capitalized_words() {
+        assert_eq!(profile_label("power-saver"), "Power saver");
+        assert_eq!(profile_label("performance"), "Performance");
+        assert_eq!(profile_label(""), "");
+    }
+}
@@ -0,0 +1,274 @@
+// Metrics recorder: appends one CSV row per interval with the metrics chosen
+// in `[recorder]` (battery charge, CPU load, network throughput), for plotting
+// later. Off unless `[recorder] path` is set.
+//
+// The values are sampled from the kernel, not scraped from the widgets' labels:
+// the labels carry icons and rounding, and CPU load and byte rates have no
+// widget of their own. CPU load and rates are deltas, so the first row comes one
+// interval after startup.
+//
+// When the file grows past max_bytes it is rotated like logrotate does
+// (data.csv -> data.csv.1 -> ... -> data.csv.<keep>, the oldest dropped) and a
+// fresh file starts with the header. Appends are a few dozen bytes every
+// interval, so they are written synchronously from the tokio task.
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+use chrono::Local;
+use tracing::{debug, info, warn};
+
+use crate::bandwidth::{self, PROC_NET_DEV};
+use crate::config::{Metric, RecorderConfig};
+
+pub const POWER_SUPPLY_ROOT: &str = "/sys/class/power_supply";
+pub const PROC_STAT: &str = "/proc/stat";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuTimes {
+    pub busy: u64,
+    pub total: u64,
+}
+
+// The aggregate "cpu" line of /proc/stat. Idle time is idle + iowait.
+pub fn parse_cpu_times(stat: &str) -> Result<CpuTimes> {
+    let Some(line) = stat.lines().find(|line| line.starts_with("cpu ")) else {
+        bail!("no aggregate cpu line");
+    };
+    let fields: Vec<u64> = line
+        .split_whitespace()
+        .skip(1)
+        .map(|field| field.parse().context("parse cpu time"))
+        .collect::<Result<_>>()?;
+    if fields.len() < 5 {
+        bail!("short cpu line: {line:?}");
+    }
+    let total = fields.iter().sum();
+    let idle = fields[3] + fields[4];
+    Ok(CpuTimes {
+        busy: total - idle,
+        total,
+    })
+}
+
+fn cpu_percent(before: CpuTimes, after: CpuTimes) -> Option<f64> {
+    let total = after.total.checked_sub(before.total)?;
+    let busy = after.busy.checked_sub(before.busy)?;
+    (total > 0).then(|| busy as f64 * 100.0 / total as f64)
+}
+
+// Received and transmitted bytes summed over every interface but loopback.
+pub fn parse_net_bytes(net_dev: &str) -> (u64, u64) {
+    bandwidth::net_dev_counters(net_dev)
+        .filter(|(interface, _, _)| *interface != "lo")
+        .fold((0, 0), |(rx, tx), (_, r, t)| (rx + r, tx + t))
+}
+
+// The first battery's capacity, None on machines without one.
+pub fn read_battery_percent(root: &Path) -> Option<u8> {
+    let mut batteries: Vec<PathBuf> = std::fs::read_dir(root)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("BAT"))
+        })
+        .collect();
+    batteries.sort();
+    let capacity = std::fs::read_to_string(batteries.first()?.join("capacity")).ok()?;
+    capacity.trim().parse().ok()
+}
+
+fn header(metrics: &[Metric]) -> String {
+    let mut columns = vec!["timestamp"];
+    for metric in metrics {
+        match metric {
+            Metric::Battery => columns.push("battery_percent"),
+            Metric::Cpu => columns.push("cpu_percent"),
+            Metric::Net => columns.extend(["net_rx_bytes_per_sec", "net_tx_bytes_per_sec"]),
+        }
+    }
+    columns.join(",")
+}
+
+// Shift path.N to path.N+1 (dropping path.<keep>) and move path to path.1.
+fn rotate(path: &Path, keep: u32) -> Result<()> {
+    let numbered = |n: u32| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{n}"));
+        PathBuf::from(name)
+    };
+    if keep == 0 {
+        return std::fs::remove_file(path).with_context(|| format!("remove {}", path.display()));
+    }
+    for n in (1..keep).rev() {
+        let from = numbered(n);
+        if from.exists() {
+            std::fs::rename(&from, numbered(n + 1))
+                .with_context(|| format!("rotate {}", from.display()))?;
+        }
+    }
+    std::fs::rename(path, numbered(1)).with_context(|| format!("rotate {}", path.display()))
+}
+
+// Previous counters, for the deltas.
+struct Sampler {
+    metrics: Vec<Metric>,
+    cpu: Option<CpuTimes>,
+    net: Option<(u64, u64)>,
+    sampled_at: Instant,
+}
+
+impl Sampler {
+    fn new(metrics: Vec<Metric>) -> Self {
+        let mut sampler = Self {
+            metrics,
+            cpu: None,
+            net: None,
+            sampled_at: Instant::now(),
+        };
+        sampler.read_counters();
+        sampler
+    }
+
+    fn read_counters(&mut self) {
+        self.cpu = std::fs::read_to_string(PROC_STAT)
+            .ok()
+            .and_then(|stat| parse_cpu_times(&stat).ok());
+        self.net = std::fs::read_to_string(PROC_NET_DEV)
+            .ok()
+            .map(|net_dev| parse_net_bytes(&net_dev));
+        self.sampled_at = Instant::now();
+    }
+
+    // One CSV row; a metric that can't be read leaves its cells empty.
+    fn row(&mut self) -> String {
+        let (cpu_before, net_before, since) = (self.cpu, self.net, self.sampled_at.elapsed());
+        self.read_counters();
+        let mut cells = vec![Local::now().to_rfc3339()];
+        for metric in &self.metrics {
+            match metric {
+                Metric::Battery => cells.push(
+                    read_battery_percent(Path::new(POWER_SUPPLY_ROOT))
+                        .map(|percent| percent.to_string())
+                        .unwrap_or_default(),
+                ),
+                Metric::Cpu => cells.push(
+                    cpu_before
+                        .zip(self.cpu)
+                        .and_then(|(before, after)| cpu_percent(before, after))
+                        .map(|percent| format!("{percent:.1}"))
+                        .unwrap_or_default(),
+                ),
+                Metric::Net => {
+                    let rates = net_before.zip(self.net).map(|(before, after)| {
+                        let seconds = since.as_secs_f64().max(f64::EPSILON);
+                        let rate =
+                            |a: u64, b: u64| format!("{:.0}", b.saturating_sub(a) as f64 / seconds);
+                        (rate(before.0, after.0), rate(before.1, after.1))
+                    });
+                    let (rx, tx) = rates.unwrap_or_default();
+                    cells.extend([rx, tx]);
+                }
+            }
+        }
+        cells.join(",")
+    }
+}
+
+fn append_row(path: &Path, header: &str, row: &str, max_bytes: u64, keep: u32) -> Result<()> {
+    if let Ok(metadata) = std::fs::metadata(path)
+        && metadata.len() >= max_bytes
+    {
+        info!(path = %path.display(), "Rotating metrics file");
+        rotate(path, keep)?;
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).with_context(|| format!("create {}", parent.display()))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("open {}", path.display()))?;
+    if file.metadata().map(|metadata| metadata.len()).unwrap_or(0) == 0 {
+        writeln!(file, "{header}").with_context(|| format!("write {}", path.display()))?;
+    }
+    writeln!(file, "{row}").with_context(|| format!("write {}", path.display()))
+}
+
+pub async fn run_recorder(config: RecorderConfig) {
+    let Some(path) = config.path else {
+        return;
+    };
+    info!(path = %path.display(), metrics = ?config.metrics, "Recording metrics");
+    let header = header(&config.metrics);
+    let period = Duration::from_secs(u64::from(config.interval_seconds.max(1)));
+    let mut sampler = Sampler::new(config.metrics);
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    loop {
+        interval.tick().await;
+        let row = sampler.row();
+        debug!(row, "Recording metrics row");
+        if let Err(e) = append_row(&path, &header, &row, config.max_bytes, config.keep) {
+            warn!("Failed to record metrics: {:#}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn cpu_load_is_busy_share_of_the_delta() {
+        let before = parse_cpu_times("cpu  100 0 100 700 100 0 0 0 0 0\ncpu0 1 2 3 4 5\n")
+            .expect("stat parses");
+        let after = parse_cpu_times("cpu  200 0 200 1400 200 0 0 0 0 0\n").expect("stat parses");
+        assert_eq!(
+            before,
+            CpuTimes {
+                busy: 200,
+                total: 1000
+            }
+        );
+        assert_eq!(cpu_percent(before, after), Some(20.0));
+        assert_eq!(cpu_percent(after, before), None);
+    }
+
+    #[test]
+    fn net_bytes_skip_loopback() {
+        let net_dev = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:  5000      50    0    0    0     0          0         0     5000      50    0    0    0     0       0          0
+wlan0:  1200      10    0    0    0     0          0         0      300       5    0    0    0     0       0          0
+ eth0:   800       8    0    0    0     0          0         0      100       2    0    0    0     0       0          0
+";
+        assert_eq!(parse_net_bytes(net_dev), (2000, 400));
+    }
+
+    #[test]
+    fn full_files_rotate_before_appending() {
+        let dir = TempDir::new("recorder");
+        let path = dir.join("metrics.csv");
+        let header = header(&[Metric::Battery, Metric::Net]);
+        assert_eq!(
+            header,
+            "timestamp,battery_percent,net_rx_bytes_per_sec,net_tx_bytes_per_sec"
+        );
+        append_row(&path, &header, "a", 1000, 2).expect("first row");
+        append_row(&path, &header, "b", 1, 2).expect("rotating row");
+        append_row(&path, &header, "c", 1, 2).expect("second rotation");
+        let current = std::fs::read_to_string(&path).expect("current file");
+        let older = std::fs::read_to_string(dir.join("metrics.csv.2")).expect("oldest file");
+        assert_eq!(current, format!("{header}\nc\n"));
+        assert_eq!(older, format!("{header}\na\n"));
+    }
+}
@@ -0,0 +1,52 @@
+// Pollers and resume: a poller waits on tick_or_resume instead of its bare
+// interval, so waking from suspend (see status_bar_backends::sleep) samples
+// at once rather than leaving the widget on what it read before the lid
+// closed until the period comes around.
+
+use tokio::sync::watch;
+use tokio::time::Interval;
+use tracing::debug;
+
+pub async fn tick_or_resume(interval: &mut Interval, resumes: &mut watch::Receiver<u64>) {
+    tokio::select! {
+        _ = interval.tick() => {}
+        // Without a sender this branch is disabled and only the interval is
+        // left.
+        Ok(()) = resumes.changed() => {
+            debug!("Resumed from sleep; polling now");
+            // A full period until the next tick again.
+            interval.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::bus::Bus;
+
+    #[test]
+    fn a_resume_cuts_the_wait_short() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("build test runtime");
+        runtime.block_on(async {
+            let (bus, _receivers) = Bus::new();
+            let mut resumes = bus.resumes();
+            let mut interval = tokio::time::interval(Duration::from_secs(3600));
+            // The first tick is immediate.
+            tick_or_resume(&mut interval, &mut resumes).await;
+
+            bus.send_resume();
+            tokio::time::timeout(
+                Duration::from_secs(5),
+                tick_or_resume(&mut interval, &mut resumes),
+            )
+            .await
+            .expect("the resume ends the wait");
+        });
+    }
+}
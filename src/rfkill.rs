@@ -0,0 +1,323 @@
+// Airplane mode: the rfkill state of the Wi-Fi and Bluetooth radios, read
+// from /sys/class/rfkill whenever /dev/rfkill reports a change (a radio added
+// or removed, a block set or lifted), so nothing polls. The widget shows ✈,
+// followed by the radios that are blocked and lit while any is, so a dead
+// Wi-Fi or Bluetooth has its explanation on the bar. A radio held off by a
+// hardware switch or a firmware key says so in the tooltip, since no click
+// lifts that.
+//
+// Clicking toggles airplane mode the way `rfkill block` and `rfkill unblock`
+// do, with a change-all event per radio type written to /dev/rfkill: any
+// soft-blocked radio means unblock them all, otherwise block them all. The
+// quick-settings switches block or unblock one type the same way. systemd's
+// uaccess rule gives the active session write access to it.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::{debug, info, warn};
+
+use crate::bus::{Bus, RfkillUpdate};
+
+const RFKILL_ROOT: &str = "/sys/class/rfkill";
+const RFKILL_DEVICE: &str = "/dev/rfkill";
+const ICON: &str = "✈";
+
+// From linux/rfkill.h: enum rfkill_type and enum rfkill_operation.
+const TYPE_WLAN: u8 = 1;
+const TYPE_BLUETOOTH: u8 = 2;
+const OP_CHANGE_ALL: u8 = 3;
+// struct rfkill_event; kernels since 5.11 append a byte that reads leave
+// out unless asked for more.
+const EVENT_SIZE: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Wifi,
+    Bluetooth,
+}
+
+impl Kind {
+    fn from_sysfs(kind: &str) -> Option<Self> {
+        match kind {
+            "wlan" => Some(Kind::Wifi),
+            "bluetooth" => Some(Kind::Bluetooth),
+            _ => None,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Kind::Wifi => "Wi-Fi",
+            Kind::Bluetooth => "BT",
+        }
+    }
+
+    fn event_type(self) -> u8 {
+        match self {
+            Kind::Wifi => TYPE_WLAN,
+            Kind::Bluetooth => TYPE_BLUETOOTH,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Radio {
+    kind: Kind,
+    soft: bool,
+    hard: bool,
+}
+
+fn read_flag(path: &Path) -> bool {
+    std::fs::read_to_string(path).is_ok_and(|value| value.trim() == "1")
+}
+
+// The Wi-Fi and Bluetooth radios; other rfkill types (WWAN, NFC, GPS) are
+// not the widget's business.
+fn read_radios(root: &Path) -> Vec<Radio> {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            let kind = std::fs::read_to_string(path.join("type")).ok()?;
+            Some(Radio {
+                kind: Kind::from_sysfs(kind.trim())?,
+                soft: read_flag(&path.join("soft")),
+                hard: read_flag(&path.join("hard")),
+            })
+        })
+        .collect()
+}
+
+// Empty text without radios, which hides the widget.
+fn summary(radios: &[Radio]) -> RfkillUpdate {
+    if radios.is_empty() {
+        return RfkillUpdate::default();
+    }
+    let mut blocked = Vec::new();
+    let mut tooltip = Vec::new();
+    for kind in [Kind::Wifi, Kind::Bluetooth] {
+        let of_kind: Vec<&Radio> = radios.iter().filter(|radio| radio.kind == kind).collect();
+        if of_kind.is_empty() {
+            continue;
+        }
+        let state = if of_kind.iter().any(|radio| radio.hard) {
+            "off by a hardware switch"
+        } else if of_kind.iter().any(|radio| radio.soft) {
+            "blocked"
+        } else {
+            "on"
+        };
+        if state != "on" {
+            blocked.push(kind.label());
+        }
+        tooltip.push(format!("{}: {state}", kind.label()));
+    }
+    let text = if blocked.is_empty() {
+        ICON.to_string()
+    } else {
+        format!("{ICON} {}", blocked.join(" "))
+    };
+    tooltip.push(if radios.iter().any(|radio| radio.soft) {
+        "Click to turn airplane mode off".to_string()
+    } else {
+        "Click to turn airplane mode on".to_string()
+    });
+    RfkillUpdate {
+        text,
+        blocked: !blocked.is_empty(),
+        tooltip: tooltip.join("\n"),
+    }
+}
+
+fn change_all_event(kind: Kind, block: bool) -> [u8; EVENT_SIZE] {
+    let mut event = [0; EVENT_SIZE];
+    // idx (u32) stays 0: change-all ignores it.
+    event[4] = kind.event_type();
+    event[5] = OP_CHANGE_ALL;
+    event[6] = u8::from(block);
+    event
+}
+
+pub async fn run_rfkill_updates(bus: Bus) {
+    let mut events = match tokio::fs::File::open(RFKILL_DEVICE).await {
+        Ok(events) => events,
+        Err(e) => {
+            info!(
+                "Cannot watch {}: {}; the rfkill widget stays hidden",
+                RFKILL_DEVICE, e
+            );
+            return;
+        }
+    };
+    info!("Starting rfkill updates");
+    let mut last = None;
+    // Opening queues an event per existing radio, so the first read returns
+    // at once; later ones wait for a change.
+    let mut event = [0; 32];
+    loop {
+        let update = summary(&read_radios(Path::new(RFKILL_ROOT)));
+        if last.as_ref() != Some(&update) {
+            debug!(?update, "Radio blocks changed");
+            if let Err(e) = bus.send_rfkill_update(update.clone()) {
+                warn!("rfkill consumer is gone: {:#}", e);
+                return;
+            }
+            last = Some(update);
+        }
+        match events.read(&mut event).await {
+            Ok(0) => {
+                warn!("{} closed; rfkill updates stop", RFKILL_DEVICE);
+                return;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                warn!("Failed to read {}: {}", RFKILL_DEVICE, e);
+                return;
+            }
+        }
+    }
+}
+
+async fn write_blocks(kinds: &[Kind], block: bool) -> Result<()> {
+    let mut device = tokio::fs::OpenOptions::new()
+        .write(true)
+        .open(RFKILL_DEVICE)
+        .await
+        .with_context(|| format!("open {RFKILL_DEVICE} for writing"))?;
+    for kind in kinds {
+        device
+            .write_all(&change_all_event(*kind, block))
+            .await
+            .with_context(|| format!("set the {} block", kind.label()))?;
+    }
+    Ok(())
+}
+
+async fn set_airplane_mode() -> Result<()> {
+    let radios = read_radios(Path::new(RFKILL_ROOT));
+    let block = !radios.iter().any(|radio| radio.soft);
+    info!(block, "Toggling airplane mode");
+    write_blocks(&[Kind::Wifi, Kind::Bluetooth], block).await
+}
+
+// Whether the radios of one type are soft-blocked, for a quick-settings
+// switch; None without such a radio.
+pub fn soft_blocked(kind: Kind) -> Option<bool> {
+    let radios = read_radios(Path::new(RFKILL_ROOT));
+    let of_kind: Vec<&Radio> = radios.iter().filter(|radio| radio.kind == kind).collect();
+    if of_kind.is_empty() {
+        return None;
+    }
+    Some(of_kind.iter().any(|radio| radio.soft))
+}
+
+// Fire-and-forget from the GTK thread, like toggle_airplane_mode.
+pub fn set_blocked(kind: Kind, block: bool) {
+    tokio::spawn(async move {
+        info!(?kind, block, "Setting radio block");
+        if let Err(e) = write_blocks(&[kind], block).await {
+            warn!(?kind, "Failed to set the radio block: {:#}", e);
+        }
+    });
+}
+
+// The widget's click handler: fire-and-forget from the GTK thread. The label
+// follows through the events the change itself raises.
+pub fn toggle_airplane_mode() {
+    tokio::spawn(async {
+        if let Err(e) = set_airplane_mode().await {
+            warn!("Failed to toggle airplane mode: {:#}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn radios_come_from_sysfs_and_skip_other_types() {
+        let root = TempDir::new("rfkill");
+        for (device, kind, soft, hard) in [
+            ("rfkill0", "wlan", "1", "0"),
+            ("rfkill1", "bluetooth", "0", "1"),
+            ("rfkill2", "wwan", "1", "0"),
+        ] {
+            let path = root.join(device);
+            std::fs::create_dir_all(&path).expect("create fake rfkill device");
+            std::fs::write(path.join("type"), format!("{kind}\n")).expect("write type");
+            std::fs::write(path.join("soft"), format!("{soft}\n")).expect("write soft");
+            std::fs::write(path.join("hard"), format!("{hard}\n")).expect("write hard");
+        }
+
+        let mut radios = read_radios(&root);
+        radios.sort_by_key(|radio| radio.kind == Kind::Bluetooth);
+        assert_eq!(
+            radios,
+            [
+                Radio {
+                    kind: Kind::Wifi,
+                    soft: true,
+                    hard: false
+                },
+                Radio {
+                    kind: Kind::Bluetooth,
+                    soft: false,
+                    hard: true
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn summary_names_the_blocked_radios() {
+        assert_eq!(summary(&[]), RfkillUpdate::default());
+        let wifi = Radio {
+            kind: Kind::Wifi,
+            soft: false,
+            hard: false,
+        };
+        let bluetooth = Radio {
+            kind: Kind::Bluetooth,
+            soft: false,
+            hard: false,
+        };
+
+        let on = summary(&[wifi, bluetooth]);
+        assert_eq!(on.text, ICON);
+        assert!(!on.blocked);
+        assert_eq!(
+            on.tooltip,
+            "Wi-Fi: on\nBT: on\nClick to turn airplane mode on"
+        );
+
+        let airplane = summary(&[
+            Radio { soft: true, ..wifi },
+            Radio {
+                hard: true,
+                ..bluetooth
+            },
+        ]);
+        assert_eq!(airplane.text, format!("{ICON} Wi-Fi BT"));
+        assert!(airplane.blocked);
+        assert_eq!(
+            airplane.tooltip,
+            "Wi-Fi: blocked\nBT: off by a hardware switch\nClick to turn airplane mode off"
+        );
+    }
+
+    #[test]
+    fn change_all_events_match_the_kernel_layout() {
+        assert_eq!(change_all_event(Kind::Wifi, true), [0, 0, 0, 0, 1, 3, 1, 0]);
+        assert_eq!(
+            change_all_event(Kind::Bluetooth, false),
+            [0, 0, 0, 0, 2, 3, 0, 0]
+        );
+    }
+}
@@ -0,0 +1,67 @@
+// Audio sink hotplug: when PipeWire reports a device-backed sink that was not
+// there at startup (a USB DAC, an HDMI monitor waking up, Bluetooth headphones
+// connecting), post a desktop notification offering to make it the default.
+//
+// The button activates `app.set-default-sink` with the node id as target.
+// Notification servers invoke it over the GApplication's D-Bus interface, so
+// it reaches this process even after the notification outlived the popup. The
+// switch itself is `wpctl set-default`, which writes the same
+// default.configured.audio.sink metadata WirePlumber persists; pw.rs then sees
+// the metadata change and the volume widget follows like any other switch.
+
+use gtk4::gio;
+use gtk4::gio::prelude::*;
+use gtk4::glib;
+use gtk4::prelude::*;
+use tokio::sync::mpsc;
+use tracing::{debug, info};
+
+use crate::bus::SinkAdded;
+use crate::command;
+
+const SET_DEFAULT_ACTION: &str = "set-default-sink";
+
+// Register `app.set-default-sink(u)`. Once per application, before the first
+// notification can be clicked.
+pub fn install_default_sink_action(application: &gtk4::Application) {
+    let action = gio::SimpleAction::new(SET_DEFAULT_ACTION, Some(glib::VariantTy::UINT32));
+    action.connect_activate(|_, parameter| {
+        let Some(id) = parameter.and_then(|parameter| parameter.get::<u32>()) else {
+            return;
+        };
+        // A node id is only valid while the node exists; if the sink was
+        // unplugged again wpctl reports the stale id and nothing changes.
+        info!(id, "Making plugged-in sink the default");
+        command::spawn_shell(&format!("wpctl set-default {id}"), "default sink");
+    });
+    application.add_action(&action);
+}
+
+// One notification per sink name, so a flapping connection replaces its own
+// notification rather than stacking copies.
+fn notify_sink_added(application: &gtk4::Application, sink: &SinkAdded) {
+    let notification = gio::Notification::new("Audio device connected");
+    notification.set_body(Some(&sink.description));
+    notification.add_button_with_target_value(
+        "Make default",
+        &format!("app.{SET_DEFAULT_ACTION}"),
+        Some(&sink.id.to_variant()),
+    );
+    application.send_notification(Some(&format!("sink-{}", sink.node_name)), &notification);
+}
+
+pub fn setup_sink_notifications(
+    mut rx: mpsc::UnboundedReceiver<SinkAdded>,
+    application: &gtk4::Application,
+) {
+    debug!("Setting up audio sink hotplug notifications");
+    let application = application.downgrade();
+    glib::spawn_future_local(async move {
+        while let Some(sink) = rx.recv().await {
+            let Some(application) = application.upgrade() else {
+                return;
+            };
+            notify_sink_added(&application, &sink);
+        }
+    });
+}
@@ -0,0 +1,163 @@
+// Runtime state the bar writes itself, as opposed to config.toml, which only
+// the user edits: `$XDG_STATE_HOME/gtk-status-bar/state.json`, falling back to
+// `~/.local/state/...`. A missing file is a fresh start. An unreadable or
+// corrupt one is logged and left alone until the next save replaces it; losing
+// an alarm is better than a bar that refuses to start.
+//
+// GTK-main-thread only: the widgets that edit state share one StateFile
+// through an Rc, and every edit is written through immediately (the file is
+// tiny and edits are user actions, never bursts).
+
+use std::cell::RefCell;
+use std::env;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info, warn};
+
+use crate::alarm::Alarm;
+
+const STATE_DIR_NAME: &str = "gtk-status-bar";
+const STATE_FILE_NAME: &str = "state.json";
+
+// No deny_unknown_fields: a newer bar may have written fields this one does
+// not know, and a downgrade should keep the rest rather than discard the file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    pub alarms: Vec<Alarm>,
+    // The first-run wizard was saved or skipped; it is not offered again.
+    pub wizard_done: bool,
+}
+
+pub fn state_path() -> Option<PathBuf> {
+    let base = match env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME").filter(|home| !home.is_empty())?)
+            .join(".local")
+            .join("state"),
+    };
+    Some(base.join(STATE_DIR_NAME).join(STATE_FILE_NAME))
+}
+
+pub fn parse(text: &str) -> Result<State> {
+    serde_json::from_str(text).context("parse state JSON")
+}
+
+fn read(path: &Path) -> Result<State> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(error) if error.kind() == ErrorKind::NotFound => {
+            debug!(path = %path.display(), "No state file; starting fresh");
+            return Ok(State::default());
+        }
+        Err(error) => {
+            return Err(error).with_context(|| format!("read state file {}", path.display()));
+        }
+    };
+    parse(&text).with_context(|| format!("invalid state in {}", path.display()))
+}
+
+// Write to a sibling temp file and rename over the old one, so a crash mid-save
+// leaves either the previous state or the new one, never half of each.
+fn write(path: &Path, state: &State) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("create state directory {}", dir.display()))?;
+    }
+    let encoded = serde_json::to_vec_pretty(state).context("encode state JSON")?;
+    let temp = path.with_extension("json.tmp");
+    std::fs::write(&temp, encoded)
+        .with_context(|| format!("write state file {}", temp.display()))?;
+    std::fs::rename(&temp, path).with_context(|| format!("replace state file {}", path.display()))
+}
+
+pub struct StateFile {
+    // None when neither XDG_STATE_HOME nor HOME is usable; state then lives
+    // for this run only.
+    path: Option<PathBuf>,
+    state: RefCell<State>,
+}
+
+impl StateFile {
+    pub fn load() -> Self {
+        let path = state_path();
+        let state = match &path {
+            Some(path) => read(path).unwrap_or_else(|e| {
+                warn!("Ignoring saved state: {:#}", e);
+                State::default()
+            }),
+            None => {
+                warn!("Neither XDG_STATE_HOME nor HOME is set; state will not be saved");
+                State::default()
+            }
+        };
+        if let Some(path) = &path {
+            info!(path = %path.display(), alarms = state.alarms.len(), "Loaded state");
+        }
+        Self {
+            path,
+            state: RefCell::new(state),
+        }
+    }
+
+    pub fn read<R>(&self, read: impl FnOnce(&State) -> R) -> R {
+        read(&self.state.borrow())
+    }
+
+    // Apply `edit` and save. A failed save is logged, not returned: the edit
+    // still holds for this run, which is all a widget callback could offer.
+    pub fn update<R>(&self, edit: impl FnOnce(&mut State) -> R) -> R {
+        let result = edit(&mut self.state.borrow_mut());
+        if let Some(path) = &self.path
+            && let Err(e) = write(path, &self.state.borrow())
+        {
+            error!("Failed to save state: {:#}", e);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    #[test]
+    fn empty_object_is_default_state() {
+        assert_eq!(
+            parse("{}").expect("empty state should parse"),
+            State::default()
+        );
+    }
+
+    #[test]
+    fn unknown_fields_are_kept_out_of_the_way() {
+        let state = parse(r#"{"alarms":[{"at":1700000000,"label":"Standup"}],"later":1}"#)
+            .expect("state with an unknown field should parse");
+        assert_eq!(
+            state.alarms,
+            vec![Alarm {
+                at: 1_700_000_000,
+                label: "Standup".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn write_then_read_round_trips() {
+        let dir = TempDir::new("state");
+        let path = dir.join(STATE_FILE_NAME);
+        let state = State {
+            alarms: vec![Alarm {
+                at: 42,
+                label: "Tea".to_string(),
+            }],
+            wizard_done: true,
+        };
+        write(&path, &state).expect("state should save");
+        assert_eq!(read(&path).expect("state should load"), state);
+    }
+}
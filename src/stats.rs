@@ -0,0 +1,61 @@
+// The stats poller: one timer for the kernel statistics that have no change
+// notification and cost a couple of small reads, the cpufreq policies and
+// /proc/loadavg. Each widget's update is sent only when its text changes, so
+// an idle machine sends nothing between ticks.
+
+use std::path::Path;
+use std::time::Duration;
+
+use tracing::{debug, info, warn};
+
+use crate::bus::{Bus, LoadAvgUpdate};
+use crate::config::LoadAvgConfig;
+use crate::cpufreq;
+use crate::format::Format;
+use crate::loadavg;
+use crate::resume;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+pub async fn run_stats_updates(bus: Bus, loadavg_config: LoadAvgConfig, format: Format) {
+    info!("Starting CPU frequency and load average updates");
+    let cores = loadavg::cores();
+    let mut last_cpufreq = None;
+    let mut last_loadavg = None;
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    let mut resumes = bus.resumes();
+    loop {
+        resume::tick_or_resume(&mut interval, &mut resumes).await;
+
+        // An empty string hides the widget (no cpufreq driver, as in most VMs).
+        let text = match cpufreq::read_cpufreq(Path::new(cpufreq::CPUFREQ_ROOT)) {
+            Ok(cpufreq) => cpufreq::display_text(&cpufreq, &format),
+            Err(e) => {
+                debug!("CPU frequency unavailable: {:#}", e);
+                String::new()
+            }
+        };
+        if last_cpufreq.as_ref() != Some(&text) {
+            last_cpufreq = Some(text.clone());
+            if let Err(e) = bus.send_cpufreq_update(text) {
+                warn!("CPU frequency consumer is gone: {:#}", e);
+                return;
+            }
+        }
+
+        let update = match loadavg::read(Path::new(loadavg::PROC_LOADAVG)) {
+            Ok(load) => loadavg::update(load, cores, &loadavg_config, &format),
+            Err(e) => {
+                debug!("Load average unavailable: {:#}", e);
+                LoadAvgUpdate::default()
+            }
+        };
+        if last_loadavg.as_ref() != Some(&update) {
+            last_loadavg = Some(update.clone());
+            if let Err(e) = bus.send_loadavg_update(update) {
+                warn!("Load average consumer is gone: {:#}", e);
+                return;
+            }
+        }
+    }
+}
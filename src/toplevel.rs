@@ -0,0 +1,415 @@
+// Window list over wlr-foreign-toplevel-management, for compositors other than
+// Hyprland (Sway, river, labwc, Wayfire, ...). One task feeds both the title
+// widget (the activated toplevel) and the taskbar (every toplevel), and takes
+// the taskbar's focus/close clicks back as WindowCommands.
+//
+// The protocol has no workspaces, so every window is reported on workspace 0,
+// which is also the "active" one: the taskbar shows them all. Addresses are
+// the handles' protocol ids, valid only for this connection; a reconnect
+// starts a fresh list, so stale buttons are replaced rather than reused.
+//
+// The connection is our own (not GTK's), read from tokio through an AsyncFd
+// on the socket, so no thread of its own is needed.
+
+use std::io::ErrorKind;
+use std::os::fd::OwnedFd;
+
+use anyhow::{Context, Result, bail};
+use tokio::io::Interest;
+use tokio::io::unix::AsyncFd;
+use tokio::sync::{Mutex, mpsc};
+use tracing::{debug, info, warn};
+use wayland_client::globals::{GlobalListContents, registry_queue_init};
+use wayland_client::protocol::{wl_registry, wl_seat};
+use wayland_client::{Connection, Dispatch, Proxy, QueueHandle, WaylandError, event_created_child};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::{
+    zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+    zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+};
+
+use crate::bus::{Bus, TaskbarUpdate, TaskbarWindow, TitleUpdate};
+use crate::compositor::AnyCompositor;
+use crate::dbus::supervise;
+use crate::text;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WindowCommand {
+    Activate(String),
+    Close(String),
+}
+
+// How the taskbar's clicks reach the compositor, chosen once at startup from
+// `[windows] backend`.
+#[derive(Clone)]
+pub enum WindowActions {
+    Compositor(AnyCompositor),
+    Wlr(mpsc::UnboundedSender<WindowCommand>),
+}
+
+impl Default for WindowActions {
+    fn default() -> Self {
+        Self::Compositor(AnyCompositor::default())
+    }
+}
+
+impl WindowActions {
+    pub fn focus(&self, address: &str) {
+        match self {
+            Self::Compositor(compositor) => compositor.focus_window(address),
+            Self::Wlr(commands) => {
+                send_command(commands, WindowCommand::Activate(address.to_string()))
+            }
+        }
+    }
+
+    pub fn close(&self, address: &str) {
+        match self {
+            Self::Compositor(compositor) => compositor.close_window(address),
+            Self::Wlr(commands) => {
+                send_command(commands, WindowCommand::Close(address.to_string()))
+            }
+        }
+    }
+}
+
+fn send_command(commands: &mpsc::UnboundedSender<WindowCommand>, command: WindowCommand) {
+    if commands.send(command).is_err() {
+        warn!("Toplevel listener is gone; window command dropped");
+    }
+}
+
+// What the bar needs of one toplevel, as of its last `done` event.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct WindowInfo {
+    address: String,
+    title: String,
+    app_id: String,
+    activated: bool,
+}
+
+struct Toplevel {
+    handle: ZwlrForeignToplevelHandleV1,
+    // Applied by `done`; title, app_id and state arrive as separate events.
+    pending: WindowInfo,
+    current: Option<WindowInfo>,
+}
+
+struct State {
+    seat: Option<wl_seat::WlSeat>,
+    // Creation order, which is also the taskbar's order.
+    toplevels: Vec<Toplevel>,
+    finished: bool,
+    last_taskbar: Option<TaskbarUpdate>,
+    last_title: Option<TitleUpdate>,
+}
+
+// The state array is a list of native-endian u32 enum values.
+fn is_activated(state: &[u8]) -> bool {
+    let activated = zwlr_foreign_toplevel_handle_v1::State::Activated as u32;
+    state
+        .chunks_exact(4)
+        .filter_map(|chunk| chunk.try_into().ok().map(u32::from_ne_bytes))
+        .any(|value| value == activated)
+}
+
+fn taskbar_update(windows: &[&WindowInfo]) -> TaskbarUpdate {
+    TaskbarUpdate {
+        windows: windows
+            .iter()
+            .map(|window| TaskbarWindow {
+                address: window.address.clone(),
+                class: window.app_id.clone(),
+                title: window.title.clone(),
+                workspace_id: 0,
+                focused: window.activated,
+                pid: None,
+            })
+            .collect(),
+        active_workspace: 0,
+    }
+}
+
+fn title_update(windows: &[&WindowInfo]) -> TitleUpdate {
+    match windows.iter().find(|window| window.activated) {
+        Some(window) => TitleUpdate {
+            title: text::format_title_string(window.title.clone(), text::MAX_TITLE_CHARS),
+            class: window.app_id.clone(),
+            pid: None,
+        },
+        None => TitleUpdate::default(),
+    }
+}
+
+impl State {
+    // Send whatever changed since the last call.
+    fn publish(&mut self, bus: &Bus) -> Result<()> {
+        let windows: Vec<&WindowInfo> = self
+            .toplevels
+            .iter()
+            .filter_map(|toplevel| toplevel.current.as_ref())
+            .collect();
+        let taskbar = taskbar_update(&windows);
+        let title = title_update(&windows);
+        if self.last_taskbar.as_ref() != Some(&taskbar) {
+            debug!(windows = taskbar.windows.len(), "Toplevels changed");
+            bus.send_taskbar_update(taskbar.clone())?;
+            self.last_taskbar = Some(taskbar);
+        }
+        if self.last_title.as_ref() != Some(&title) {
+            debug!(
+                title = title.title,
+                class = title.class,
+                "Activated toplevel changed"
+            );
+            bus.send_title_update(title.clone())?;
+            self.last_title = Some(title);
+        }
+        Ok(())
+    }
+
+    fn run(&self, command: WindowCommand) {
+        let (WindowCommand::Activate(address) | WindowCommand::Close(address)) = &command;
+        let Some(toplevel) = self
+            .toplevels
+            .iter()
+            .find(|toplevel| toplevel.pending.address == *address)
+        else {
+            debug!(address, "Window command for a toplevel that is gone");
+            return;
+        };
+        match command {
+            WindowCommand::Activate(_) => {
+                let Some(seat) = &self.seat else {
+                    warn!("No seat to activate the window with");
+                    return;
+                };
+                toplevel.handle.activate(seat);
+            }
+            WindowCommand::Close(_) => toplevel.handle.close(),
+        }
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for State {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<wl_seat::WlSeat, ()> for State {
+    fn event(
+        _: &mut Self,
+        _: &wl_seat::WlSeat,
+        _: wl_seat::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrForeignToplevelManagerV1,
+        event: zwlr_foreign_toplevel_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } => {
+                let pending = WindowInfo {
+                    address: toplevel.id().protocol_id().to_string(),
+                    ..WindowInfo::default()
+                };
+                state.toplevels.push(Toplevel {
+                    handle: toplevel,
+                    pending,
+                    current: None,
+                });
+            }
+            zwlr_foreign_toplevel_manager_v1::Event::Finished => state.finished = true,
+            _ => {}
+        }
+    }
+
+    event_created_child!(State, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for State {
+    fn event(
+        state: &mut Self,
+        handle: &ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let Some(index) = state
+            .toplevels
+            .iter()
+            .position(|toplevel| toplevel.handle == *handle)
+        else {
+            return;
+        };
+        let toplevel = &mut state.toplevels[index];
+        match event {
+            zwlr_foreign_toplevel_handle_v1::Event::Title { title } => {
+                toplevel.pending.title = title
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::AppId { app_id } => {
+                toplevel.pending.app_id = app_id
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::State { state: flags } => {
+                toplevel.pending.activated = is_activated(&flags)
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Done => {
+                toplevel.current = Some(toplevel.pending.clone())
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::Closed => {
+                toplevel.handle.destroy();
+                state.toplevels.remove(index);
+            }
+            _ => {}
+        }
+    }
+}
+
+// One connection's lifetime. Returns only with an error; the supervisor
+// reconnects.
+async fn run_toplevel_session(
+    bus: &Bus,
+    commands: &mut mpsc::UnboundedReceiver<WindowCommand>,
+) -> Result<()> {
+    let connection = Connection::connect_to_env().context("connect to the Wayland display")?;
+    let (globals, mut queue) =
+        registry_queue_init::<State>(&connection).context("list Wayland globals")?;
+    let qh = queue.handle();
+    let _manager: ZwlrForeignToplevelManagerV1 = globals
+        .bind(&qh, 1..=3, ())
+        .context("compositor lacks zwlr_foreign_toplevel_manager_v1")?;
+    let seat = match globals.bind::<wl_seat::WlSeat, _, _>(&qh, 1..=1, ()) {
+        Ok(seat) => Some(seat),
+        Err(e) => {
+            warn!("No Wayland seat, windows cannot be focused: {}", e);
+            None
+        }
+    };
+    let socket: OwnedFd = connection
+        .backend()
+        .poll_fd()
+        .try_clone_to_owned()
+        .context("duplicate the Wayland socket")?;
+    let socket = AsyncFd::with_interest(socket, Interest::READABLE)
+        .context("register the Wayland socket with tokio")?;
+
+    let mut state = State {
+        seat,
+        toplevels: Vec::new(),
+        finished: false,
+        last_taskbar: None,
+        last_title: None,
+    };
+    info!("Listening for Wayland toplevels");
+    loop {
+        queue
+            .dispatch_pending(&mut state)
+            .context("dispatch Wayland events")?;
+        state.publish(bus)?;
+        if state.finished {
+            bail!("compositor stopped the toplevel manager");
+        }
+        queue.flush().context("flush Wayland requests")?;
+        // None means events were queued meanwhile: dispatch them first.
+        let Some(guard) = queue.prepare_read() else {
+            continue;
+        };
+        tokio::select! {
+            ready = socket.readable() => {
+                let mut ready = ready.context("wait for the Wayland socket")?;
+                match guard.read() {
+                    Ok(_) => {}
+                    Err(WaylandError::Io(e)) if e.kind() == ErrorKind::WouldBlock => {
+                        ready.clear_ready();
+                    }
+                    Err(e) => return Err(e).context("read Wayland events"),
+                }
+            }
+            Some(command) = commands.recv() => {
+                drop(guard);
+                debug!(?command, "Window command");
+                state.run(command);
+            }
+        }
+    }
+}
+
+// Same backoff policy as the Hyprland listeners. The command receiver outlives
+// each session so clicks made during a reconnect are not lost for good; the
+// lock only lends it to one session at a time.
+pub async fn run_toplevel_listener_supervised(
+    bus: Bus,
+    commands: mpsc::UnboundedReceiver<WindowCommand>,
+) {
+    let commands = Mutex::new(commands);
+    supervise("Wayland toplevel listener", || async {
+        run_toplevel_session(&bus, &mut *commands.lock().await).await
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn activated_comes_from_the_state_array() {
+        let maximized = zwlr_foreign_toplevel_handle_v1::State::Maximized as u32;
+        let activated = zwlr_foreign_toplevel_handle_v1::State::Activated as u32;
+        let state: Vec<u8> = [maximized, activated]
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
+        assert!(is_activated(&state));
+        assert!(!is_activated(&maximized.to_ne_bytes()));
+        assert!(!is_activated(&[]));
+    }
+
+    #[test]
+    fn title_follows_the_activated_window() {
+        let editor = WindowInfo {
+            address: "12".to_string(),
+            title: "notes.md".to_string(),
+            app_id: "org.gnome.TextEditor".to_string(),
+            activated: true,
+        };
+        let terminal = WindowInfo {
+            address: "13".to_string(),
+            title: "~".to_string(),
+            app_id: "foot".to_string(),
+            activated: false,
+        };
+        let windows = [&terminal, &editor];
+        assert_eq!(
+            title_update(&windows),
+            TitleUpdate {
+                title: "notes.md".to_string(),
+                class: "org.gnome.TextEditor".to_string(),
+                pid: None,
+            }
+        );
+        let taskbar = taskbar_update(&windows);
+        assert_eq!(taskbar.windows.len(), 2);
+        assert!(taskbar.windows[1].focused);
+        assert_eq!(title_update(&[&terminal]), TitleUpdate::default());
+    }
+}
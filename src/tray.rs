@@ -1,17 +1,18 @@
 use std::collections::HashMap;
-use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result, anyhow, bail};
 use futures_util::StreamExt;
-use tokio::sync::mpsc;
+use tokio::sync::{Mutex, mpsc};
 use tokio::task::JoinHandle;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, info, warn};
 use zbus::fdo::DBusProxy;
 use zbus::message::Header;
 use zbus::names::BusName;
 use zbus::object_server::SignalEmitter;
 use zbus::{Connection, Proxy};
 
+use crate::dbus::supervise;
+
 const WATCHER_NAME: &str = "org.kde.StatusNotifierWatcher";
 const WATCHER_PATH: &str = "/StatusNotifierWatcher";
 const WATCHER_INTERFACE: &str = "org.kde.StatusNotifierWatcher";
@@ -27,7 +28,9 @@ pub struct TrayItem {
     pub status: String,
     pub item_is_menu: bool,
     pub icon_name: String,
-    pub icon_pixmap: Option<IconPixmap>,
+    /// Every well-formed IconPixmap size the item offers, smallest first; the
+    /// widget picks the one that fits the output's scale.
+    pub icon_pixmaps: Vec<IconPixmap>,
     pub icon_theme_path: String,
     /// Object path of the item's `com.canonical.dbusmenu` interface, or empty
     /// when the item exposes no menu at all. Read from the SNI `Menu` property.
@@ -53,6 +56,10 @@ pub struct TrayMenuItem {
     pub is_separator: bool,
     pub toggle_type: Option<String>,
     pub toggle_state: Option<bool>,
+    /// `children-display` is "submenu": the entry opens a submenu, whose
+    /// children an app may only fill in once it is told (AboutToShow) the
+    /// submenu is about to open.
+    pub has_submenu: bool,
     pub children: Vec<TrayMenuItem>,
 }
 
@@ -467,13 +474,15 @@ where
     }
 }
 
-fn largest_valid_pixmap(pixmaps: Vec<IconPixmap>) -> Option<IconPixmap> {
-    pixmaps
+fn valid_pixmaps(pixmaps: Vec<IconPixmap>) -> Vec<IconPixmap> {
+    let mut valid: Vec<IconPixmap> = pixmaps
         .into_iter()
         .filter(|(width, height, data)| {
             *width > 0 && *height > 0 && data.len() == (*width as usize) * (*height as usize) * 4
         })
-        .max_by_key(|(width, height, _)| width * height)
+        .collect();
+    valid.sort_by_key(|(width, height, _)| width * height);
+    valid
 }
 
 // Read a full snapshot of one status notifier item. Every property is optional
@@ -520,7 +529,7 @@ async fn fetch_item(connection: &Connection, key: &str) -> Result<TrayItem> {
         status,
         item_is_menu,
         icon_name,
-        icon_pixmap: largest_valid_pixmap(pixmaps),
+        icon_pixmaps: valid_pixmaps(pixmaps),
         icon_theme_path,
         menu_path,
     })
@@ -599,6 +608,83 @@ async fn fetch_menu(connection: &Connection, key: &str, menu_path: &str) -> Resu
         .await
         .with_context(|| format!("create dbusmenu proxy for {key}"))?;
 
+    // Many apps (nm-applet, Electron, Steam) build their menus lazily: the
+    // root only gets its entries after AboutToShow(0), and each submenu after
+    // its own AboutToShow. We paint the whole tree at once, so announce every
+    // submenu up front and fetch again if any of them changed.
+    about_to_show(&menu_proxy, key, 0).await;
+    let mut root = get_layout(&menu_proxy, key, menu_path).await?;
+    let submenus = submenu_ids(&root.children);
+    if !submenus.is_empty() && about_to_show_group(&menu_proxy, key, &submenus).await {
+        debug!(
+            item = key,
+            count = submenus.len(),
+            "dbusmenu submenus changed; fetching layout again"
+        );
+        root = get_layout(&menu_proxy, key, menu_path).await?;
+    }
+
+    debug!(
+        item = key,
+        count = root.children.len(),
+        "dbusmenu layout parsed"
+    );
+
+    Ok(TrayMenu {
+        key: key.to_string(),
+        menu_path: menu_path.to_string(),
+        items: root.children,
+        request_id: 0,
+        keyboard_grab: false,
+    })
+}
+
+// `AboutToShow(i id) -> (b needUpdate)`. Optional in practice: older
+// implementations lack it and static menus ignore it, so a failure only means
+// the layout is already as complete as it gets. Returns needUpdate.
+async fn about_to_show(menu_proxy: &Proxy<'_>, key: &str, id: i32) -> bool {
+    match menu_proxy.call::<_, _, bool>("AboutToShow", &(id,)).await {
+        Ok(need_update) => need_update,
+        Err(error) => {
+            debug!(item = key, entry = id, %error, "dbusmenu AboutToShow failed");
+            false
+        }
+    }
+}
+
+// `AboutToShowGroup(ai ids) -> (ai updatesNeeded, ai idErrors)`, one round
+// trip for every submenu, falling back to one AboutToShow each for
+// implementations that predate it. Returns whether any entry needs an update.
+async fn about_to_show_group(menu_proxy: &Proxy<'_>, key: &str, ids: &[i32]) -> bool {
+    let ids = ids.to_vec();
+    match menu_proxy
+        .call::<_, _, (Vec<i32>, Vec<i32>)>("AboutToShowGroup", &(ids.clone(),))
+        .await
+    {
+        Ok((updates_needed, _)) => !updates_needed.is_empty(),
+        Err(error) => {
+            debug!(item = key, %error, "dbusmenu AboutToShowGroup failed; announcing submenus one by one");
+            let mut need_update = false;
+            for id in ids {
+                need_update |= about_to_show(menu_proxy, key, id).await;
+            }
+            need_update
+        }
+    }
+}
+
+// Ids of every entry, at any depth, that opens a submenu.
+fn submenu_ids(items: &[TrayMenuItem]) -> Vec<i32> {
+    items
+        .iter()
+        .flat_map(|item| {
+            let own = item.has_submenu.then_some(item.id);
+            own.into_iter().chain(submenu_ids(&item.children))
+        })
+        .collect()
+}
+
+async fn get_layout(menu_proxy: &Proxy<'_>, key: &str, menu_path: &str) -> Result<TrayMenuItem> {
     // Must be a Vec (serialized as the `as` array the signature expects), not a
     // fixed-size `[&str; N]` — zbus serializes those as a struct `(ss…)`, which
     // the bus rejects with "Invalid arguments 'ii(ss…)' expecting 'iias'".
@@ -658,20 +744,7 @@ async fn fetch_menu(connection: &Connection, key: &str, menu_path: &str) -> Resu
             "dbusmenu root node for {key} does not parse as a layout node"
         ));
     };
-
-    debug!(
-        item = key,
-        count = root.children.len(),
-        "dbusmenu layout parsed"
-    );
-
-    Ok(TrayMenu {
-        key: key.to_string(),
-        menu_path: menu_path.to_string(),
-        items: root.children,
-        request_id: 0,
-        keyboard_grab: false,
-    })
+    Ok(root)
 }
 
 // Forward a menu entry activation back to the app through dbusmenu's `Event`
@@ -719,15 +792,16 @@ async fn dbusmenu_event(
 fn menu_prop<'a>(props: &'a zvariant::Dict<'a, 'a>, name: &str) -> Option<&'a zvariant::Value<'a>> {
     for (candidate, value) in props.iter() {
         if let zvariant::Value::Str(text) = candidate
-            && text.as_str() == name {
-                // dbusmenu properties are `a{sv}`; each value is a D-Bus variant,
-                // so zvariant hands it back as `Value::Value(inner)`. Unwrap that
-                // one level so the actual string/bool/int is what we match on.
-                return match value {
-                    zvariant::Value::Value(inner) => Some(inner),
-                    other => Some(other),
-                };
-            }
+            && text.as_str() == name
+        {
+            // dbusmenu properties are `a{sv}`; each value is a D-Bus variant,
+            // so zvariant hands it back as `Value::Value(inner)`. Unwrap that
+            // one level so the actual string/bool/int is what we match on.
+            return match value {
+                zvariant::Value::Value(inner) => Some(inner),
+                other => Some(other),
+            };
+        }
     }
     None
 }
@@ -836,6 +910,7 @@ fn parse_menu_node(node: &zvariant::Structure<'_>) -> Option<TrayMenuItem> {
     let visible = menu_prop_bool(props, "visible", true);
     let toggle_type = menu_prop_str(props, "toggle-type");
     let toggle_state = menu_prop_toggle_state(props);
+    let has_submenu = menu_prop_str(props, "children-display").as_deref() == Some("submenu");
 
     let mut children = Vec::new();
     for child in children_raw.iter() {
@@ -858,6 +933,7 @@ fn parse_menu_node(node: &zvariant::Structure<'_>) -> Option<TrayMenuItem> {
         is_separator,
         toggle_type,
         toggle_state,
+        has_submenu,
         children,
     })
 }
@@ -1064,40 +1140,18 @@ async fn run_tray(
 // This function never returns and is meant to be `tokio::spawn`ed from the
 // widget setup. It owns the channel ends across retries: the GTK side keeps
 // its receiver and senders wired to the same channels for the process lifetime.
+// Sessions never overlap, so the command lock is only ever taken by one.
 pub async fn run_tray_supervised(backend: TrayBackend) {
     let TrayBackend {
         updates,
-        mut commands,
+        commands,
         menus,
     } = backend;
-    let max_delay = Duration::from_secs(60);
-    let reset_threshold = Duration::from_secs(30);
-    let mut delay = Duration::from_secs(1);
-
-    loop {
-        let started = Instant::now();
-        info!("🔌 Starting system tray backend");
-        match run_tray(&updates, &mut commands, &menus).await {
-            Ok(()) => {
-                warn!("⚠️ Tray backend returned cleanly (stream closed)");
-            }
-            Err(e) => {
-                error!("❌ Tray backend crashed: {:#}", e);
-            }
-        }
-
-        if started.elapsed() >= reset_threshold {
-            debug!(
-                "🔄 Tray backend ran for {:?}, resetting backoff",
-                started.elapsed()
-            );
-            delay = Duration::from_secs(1);
-        }
-
-        warn!("🔄 Restarting tray backend in {:?}", delay);
-        tokio::time::sleep(delay).await;
-        delay = std::cmp::min(delay * 2, max_delay);
-    }
+    let commands = Mutex::new(commands);
+    supervise("system tray backend", || async {
+        run_tray(&updates, &mut *commands.lock().await, &menus).await
+    })
+    .await
 }
 
 #[cfg(test)]
@@ -1155,13 +1209,13 @@ mod tests {
     }
 
     #[test]
-    fn picks_largest_well_formed_pixmap() {
+    fn keeps_well_formed_pixmaps_smallest_first() {
         let small = (1, 1, vec![0; 4]);
         let large = (2, 2, vec![0; 16]);
         let malformed = (32, 32, vec![0; 12]);
         assert_eq!(
-            largest_valid_pixmap(vec![small, large.clone(), malformed]),
-            Some(large)
+            valid_pixmaps(vec![large.clone(), malformed, small.clone()]),
+            vec![small, large]
         );
     }
 
@@ -1212,6 +1266,7 @@ mod tests {
 
         let parsed = parse_menu_node(&root).expect("root node parses");
         assert_eq!(parsed.id, 0);
+        assert!(parsed.has_submenu);
         assert_eq!(parsed.children.len(), 1);
 
         let entry = &parsed.children[0];
@@ -1235,4 +1290,34 @@ mod tests {
         assert!(parsed.is_separator);
         assert_eq!(parsed.label, None);
     }
+
+    #[test]
+    fn submenus_are_collected_at_any_depth() {
+        let nested = layout_node(
+            5,
+            vec![("children-display", zvariant::Value::from("submenu"))],
+            Vec::new(),
+        );
+        let outer = layout_node(
+            2,
+            vec![("children-display", zvariant::Value::from("submenu"))],
+            vec![zvariant::Value::Structure(nested)],
+        );
+        let plain = layout_node(
+            9,
+            vec![("label", zvariant::Value::from("About"))],
+            Vec::new(),
+        );
+        let root = layout_node(
+            0,
+            Vec::new(),
+            vec![
+                zvariant::Value::Structure(outer),
+                zvariant::Value::Structure(plain),
+            ],
+        );
+
+        let parsed = parse_menu_node(&root).expect("root node parses");
+        assert_eq!(submenu_ids(&parsed.children), vec![2, 5]);
+    }
 }
@@ -0,0 +1,218 @@
+// Systemd unit health: watches the units listed in `[units]` (user units on
+// the session bus, system units on the system bus) and reports the ones whose
+// ActiveState is "failed". The widget shows a warning badge with their count
+// and lists them in a popover; with nothing failed it hides.
+//
+// systemd only emits unit signals to clients that called Manager.Subscribe,
+// which we do on each bus before loading the units. A unit that does not exist
+// still loads (LoadState "not-found") and simply never fails, so a typo in the
+// list shows up in the log, not in the badge.
+
+use std::collections::BTreeSet;
+
+use anyhow::{Context, Result, bail};
+use tracing::{debug, info, warn};
+use zbus::message::Type as MessageType;
+use zbus::zvariant::OwnedObjectPath;
+use zbus::{Connection, MatchRule, Message, Proxy};
+
+use crate::bus::Bus;
+use crate::buses::{BusKind, BusManager, Signals};
+use crate::config::UnitsConfig;
+use crate::dbus::supervise;
+
+const SYSTEMD: &str = "org.freedesktop.systemd1";
+const SYSTEMD_PATH: &str = "/org/freedesktop/systemd1";
+const MANAGER_IFACE: &str = "org.freedesktop.systemd1.Manager";
+const UNIT_IFACE: &str = "org.freedesktop.systemd1.Unit";
+const UNIT_PATHS: &str = "/org/freedesktop/systemd1/unit";
+
+struct Watched {
+    kind: BusKind,
+    // As the popover lists it: the unit name and which manager runs it.
+    label: String,
+    path: OwnedObjectPath,
+}
+
+fn unit_label(kind: BusKind, name: &str) -> String {
+    match kind {
+        BusKind::System => format!("{name} (system)"),
+        BusKind::Session => format!("{name} (user)"),
+    }
+}
+
+fn unit_rule() -> Result<MatchRule<'static>> {
+    Ok(MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender(SYSTEMD)
+        .context("systemd unit rule: set sender")?
+        .interface("org.freedesktop.DBus.Properties")
+        .context("systemd unit rule: set interface")?
+        .member("PropertiesChanged")
+        .context("systemd unit rule: set member")?
+        .path_namespace(UNIT_PATHS)
+        .context("systemd unit rule: set path namespace")?
+        .build())
+}
+
+async fn load_units(
+    connection: &Connection,
+    kind: BusKind,
+    names: &[String],
+) -> Result<Vec<Watched>> {
+    let manager = Proxy::new(connection, SYSTEMD, SYSTEMD_PATH, MANAGER_IFACE)
+        .await
+        .context("create systemd manager proxy")?;
+    manager
+        .call::<_, _, ()>("Subscribe", &())
+        .await
+        .context("subscribe to systemd unit signals")?;
+    let mut watched = Vec::new();
+    for name in names {
+        let path: OwnedObjectPath = manager
+            .call("LoadUnit", &(name.as_str(),))
+            .await
+            .with_context(|| format!("load unit {name}"))?;
+        watched.push(Watched {
+            kind,
+            label: unit_label(kind, name),
+            path,
+        });
+    }
+    Ok(watched)
+}
+
+async fn is_failed(connection: &Connection, unit: &Watched) -> Result<bool> {
+    let proxy = Proxy::new(connection, SYSTEMD, unit.path.as_str(), UNIT_IFACE)
+        .await
+        .context("create systemd unit proxy")?;
+    let state: String = proxy
+        .get_property("ActiveState")
+        .await
+        .with_context(|| format!("read ActiveState of {}", unit.label))?;
+    Ok(state == "failed")
+}
+
+// One bus's subscription, connection and units; None when its list is empty.
+struct Watch {
+    signals: Signals,
+    units: Vec<Watched>,
+}
+
+async fn watch(buses: &BusManager, kind: BusKind, names: &[String]) -> Result<Option<Watch>> {
+    if names.is_empty() {
+        return Ok(None);
+    }
+    // Subscribed before the units are read, so no change slips between.
+    let signals = buses.subscribe(kind, vec![unit_rule()?]).await?;
+    let units = load_units(signals.connection(), kind, names).await?;
+    Ok(Some(Watch { signals, units }))
+}
+
+async fn next_signal(watch: &mut Option<Watch>) -> Option<Message> {
+    match watch {
+        Some(watch) => watch.signals.next().await,
+        None => std::future::pending().await,
+    }
+}
+
+fn send(bus: &Bus, failed: &BTreeSet<String>) -> Result<()> {
+    debug!(?failed, "Sending failed units");
+    bus.send_units_update(failed.iter().cloned().collect())
+}
+
+async fn monitor_units(bus: &Bus, buses: &BusManager, config: &UnitsConfig) -> Result<()> {
+    let mut system = watch(buses, BusKind::System, &config.system).await?;
+    let mut session = watch(buses, BusKind::Session, &config.user).await?;
+
+    let mut failed = BTreeSet::new();
+    for watch in [&system, &session].into_iter().flatten() {
+        for unit in &watch.units {
+            if is_failed(watch.signals.connection(), unit).await? {
+                failed.insert(unit.label.clone());
+            }
+        }
+    }
+    info!(failed = failed.len(), "Watching systemd units");
+    send(bus, &failed)?;
+
+    loop {
+        let (kind, message) = tokio::select! {
+            message = next_signal(&mut system) => (BusKind::System, message),
+            message = next_signal(&mut session) => (BusKind::Session, message),
+        };
+        let watch = match kind {
+            BusKind::System => system.as_ref(),
+            BusKind::Session => session.as_ref(),
+        };
+        let (Some(watch), Some(message)) = (watch, message) else {
+            bail!("systemd D-Bus subscription ended");
+        };
+        let header = message.header();
+        let Some(path) = header.path() else {
+            continue;
+        };
+        let Some(unit) = watch
+            .units
+            .iter()
+            .find(|unit| unit.path.as_str() == path.as_str())
+        else {
+            continue;
+        };
+        let now_failed = match is_failed(watch.signals.connection(), unit).await {
+            Ok(now_failed) => now_failed,
+            Err(e) => {
+                warn!(unit = unit.label, "Failed to read unit state: {:#}", e);
+                continue;
+            }
+        };
+        let changed = if now_failed {
+            failed.insert(unit.label.clone())
+        } else {
+            failed.remove(&unit.label)
+        };
+        if changed {
+            info!(
+                unit = unit.label,
+                failed = now_failed,
+                "Unit health changed"
+            );
+            send(bus, &failed)?;
+        }
+    }
+}
+
+pub async fn run_units_monitor_supervised(bus: Bus, buses: BusManager, config: UnitsConfig) {
+    if config.system.is_empty() && config.user.is_empty() {
+        debug!("No systemd units configured; not watching any");
+        return;
+    }
+    supervise("systemd unit monitor", || {
+        monitor_units(&bus, &buses, &config)
+    })
+    .await
+}
+
+// The badge: empty hides it.
+pub fn badge_text(failed: &[String]) -> String {
+    if failed.is_empty() {
+        return String::new();
+    }
+    format!("⚠ {}", failed.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn badge_counts_failed_units() {
+        assert_eq!(badge_text(&[]), "");
+        let failed = vec![
+            unit_label(BusKind::Session, "mako.service"),
+            unit_label(BusKind::System, "NetworkManager.service"),
+        ];
+        assert_eq!(failed[0], "mako.service (user)");
+        assert_eq!(badge_text(&failed), "⚠ 2");
+    }
+}
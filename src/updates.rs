@@ -0,0 +1,118 @@
+// Pending package updates: runs `[updates] command` (`checkupdates`, `dnf
+// check-update`, ...) through `sh -c` every `interval_minutes` and shows how
+// many updates it reports, hiding while there are none. Checkers hit the
+// network and the package database, so they run far less often than the
+// other pollers, with a deadline in case a mirror hangs.
+//
+// The count is the number of non-empty output lines, or the number itself when
+// the command prints a single integer (`yay -Qu | wc -l`). Exit statuses
+// differ by checker (checkupdates exits 2 with nothing to do, dnf 100 with
+// updates pending), so a failure status only counts as an error when the
+// command printed nothing.
+
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{Context, Result, bail};
+use tokio::process::Command;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, info, warn};
+
+use crate::bus::Bus;
+use crate::config::UpdatesConfig;
+
+const ICON: &str = "\u{f487}";
+const CHECK_DEADLINE: Duration = Duration::from_secs(300);
+
+pub fn count_updates(output: &str) -> usize {
+    let lines: Vec<&str> = output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    if let [only] = lines.as_slice()
+        && let Ok(count) = only.parse()
+    {
+        return count;
+    }
+    lines.len()
+}
+
+// Empty hides the widget.
+pub fn display_text(count: usize) -> String {
+    if count == 0 {
+        return String::new();
+    }
+    format!("{ICON} {count}")
+}
+
+async fn check(command: &str) -> Result<usize> {
+    let output = Command::new("sh")
+        .kill_on_drop(true)
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .output();
+    let output = tokio::time::timeout(CHECK_DEADLINE, output)
+        .await
+        .context("update check exceeded its deadline")?
+        .context("launch update check")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() && stdout.trim().is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // checkupdates' "nothing to do"; anything else has to say why.
+        if output.status.code() == Some(2) && stderr.trim().is_empty() {
+            return Ok(0);
+        }
+        bail!("update check failed ({}): {}", output.status, stderr.trim());
+    }
+    Ok(count_updates(&stdout))
+}
+
+pub async fn run_updates_checker(bus: Bus, config: UpdatesConfig) {
+    if config.command.trim().is_empty() {
+        debug!("No update checker configured");
+        return;
+    }
+    info!(command = config.command, "Starting update checks");
+    let period = Duration::from_secs(u64::from(config.interval_minutes.max(1)) * 60);
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut last = None;
+    loop {
+        interval.tick().await;
+        // A failed check keeps the last count on screen: a flaky mirror says
+        // nothing about what is installed.
+        let count = match check(&config.command).await {
+            Ok(count) => count,
+            Err(e) => {
+                warn!(command = config.command, "Update check failed: {:#}", e);
+                continue;
+            }
+        };
+        debug!(count, "Checked for updates");
+        if last == Some(count) {
+            continue;
+        }
+        last = Some(count);
+        if let Err(e) = bus.send_updates_update(display_text(count)) {
+            warn!("Updates consumer is gone: {:#}", e);
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_lines_or_a_bare_number() {
+        let checkupdates = "linux 6.9.1-1 -> 6.9.2-1\nmesa 24.1.0-1 -> 24.1.1-1\n";
+        assert_eq!(count_updates(checkupdates), 2);
+        assert_eq!(count_updates("  17\n"), 17);
+        assert_eq!(count_updates("\n\n"), 0);
+        assert_eq!(display_text(0), "");
+        assert_eq!(display_text(2), format!("{ICON} 2"));
+    }
+}
@@ -0,0 +1,153 @@
+// Derived views: model properties computed from more than one source. Each
+// view subscribes to the AppState channels it combines (and to any setting
+// that feeds it) and recomputes whenever one of them changes, so no drain has
+// to know about another widget's inputs.
+//
+// The title pill's color combines the workspace on screen with the workspace
+// palette; a config reload swaps the palette through its watch channel and
+// the view repaints without the workspace changing.
+//
+// The quick-settings sliders follow the volume and brightness the OSDs show,
+// straight from the same channels.
+
+use gtk4::glib;
+use tokio::sync::watch;
+use tracing::debug;
+
+use crate::bus::{VolumeUpdate, WorkspaceUpdate};
+use crate::dispatch::{Slot, UiDispatcher};
+use crate::model::BarModel;
+use crate::palette::WorkspaceColors;
+
+// None until the compositor reports a workspace; the pill keeps its
+// stylesheet color meanwhile.
+pub fn workspace_color(
+    workspace: Option<&WorkspaceUpdate>,
+    colors: &WorkspaceColors,
+) -> Option<String> {
+    workspace.map(|workspace| colors.color(workspace.id, workspace.special))
+}
+
+pub fn spawn_workspace_color(
+    model: BarModel,
+    mut workspace: watch::Receiver<Option<WorkspaceUpdate>>,
+    mut colors: watch::Receiver<WorkspaceColors>,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Deriving the title color from the workspace");
+    glib::spawn_future_local(async move {
+        loop {
+            let color = workspace_color(
+                workspace.borrow_and_update().as_ref(),
+                &colors.borrow_and_update(),
+            );
+            if let Some(color) = color {
+                let model = model.clone();
+                dispatcher.submit_changed(Slot::WorkspaceColor, color, move |color| {
+                    model.set_workspace_color(color)
+                });
+            }
+            // Both senders live as long as the bar; either closing ends the
+            // view.
+            let changed = tokio::select! {
+                changed = workspace.changed() => changed,
+                changed = colors.changed() => changed,
+            };
+            if changed.is_err() {
+                return;
+            }
+        }
+    });
+}
+
+// The first channel follows user changes most closely, like the OSD's
+// reading. -1 hides the slider until PipeWire reports a sink.
+pub fn volume_level(update: Option<&VolumeUpdate>) -> i32 {
+    update
+        .and_then(|update| update.channel_percent.or(update.volume_percent))
+        .map_or(-1, i32::from)
+}
+
+pub fn brightness_level(percent: Option<u8>) -> i32 {
+    percent.map_or(-1, i32::from)
+}
+
+pub fn spawn_quick_levels(
+    model: BarModel,
+    mut audio: watch::Receiver<Option<VolumeUpdate>>,
+    mut brightness: watch::Receiver<Option<u8>>,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Deriving the quick-settings levels");
+    glib::spawn_future_local(async move {
+        loop {
+            let volume = volume_level(audio.borrow_and_update().as_ref());
+            let model_volume = model.clone();
+            dispatcher.submit_changed(Slot::QuickVolume, volume, move |volume| {
+                model_volume.set_quick_volume(volume)
+            });
+            let level = brightness_level(*brightness.borrow_and_update());
+            let model_brightness = model.clone();
+            dispatcher.submit_changed(Slot::QuickBrightness, level, move |level| {
+                model_brightness.set_quick_brightness(level)
+            });
+            let changed = tokio::select! {
+                changed = audio.changed() => changed,
+                changed = brightness.changed() => changed,
+            };
+            if changed.is_err() {
+                return;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::config::WorkspaceConfig;
+
+    #[test]
+    fn the_color_follows_workspace_and_palette() {
+        let colors = WorkspaceColors::new(&WorkspaceConfig {
+            colors: BTreeMap::from([("2".to_string(), "@green".to_string())]),
+            ..WorkspaceConfig::default()
+        });
+        let workspace = |id, special| WorkspaceUpdate {
+            name: String::new(),
+            id,
+            special,
+        };
+        assert_eq!(workspace_color(None, &colors), None);
+        assert_eq!(
+            workspace_color(Some(&workspace(2, false)), &colors),
+            Some("@green".to_string())
+        );
+        assert_eq!(
+            workspace_color(Some(&workspace(3, false)), &colors),
+            Some("@workspace_3".to_string())
+        );
+        assert_eq!(
+            workspace_color(Some(&workspace(-98, true)), &WorkspaceColors::default()),
+            Some(String::new())
+        );
+    }
+
+    #[test]
+    fn quick_levels_prefer_the_first_channel() {
+        let update = |volume_percent, channel_percent| VolumeUpdate {
+            name: "Speakers".to_string(),
+            volume_percent,
+            channel_percent,
+            is_muted: Some(false),
+        };
+        assert_eq!(volume_level(None), -1);
+        assert_eq!(volume_level(Some(&update(Some(40), Some(42)))), 42);
+        assert_eq!(volume_level(Some(&update(Some(40), None))), 40);
+        assert_eq!(volume_level(Some(&update(None, None))), -1);
+        assert_eq!(brightness_level(Some(75)), 75);
+        assert_eq!(brightness_level(None), -1);
+    }
+}
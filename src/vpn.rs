@@ -0,0 +1,373 @@
+// VPN indicator: the names of NetworkManager's active VPN and WireGuard
+// connections, event-driven like the network monitor (PropertiesChanged on the
+// manager and its active connections wakes a re-read; nothing polls while NM
+// is running). Without NetworkManager on the bus there is nothing to listen
+// to, so the monitor falls back to scanning /sys/class/net for wg* and tun*
+// interfaces every few seconds, and hands over to NM once it appears.
+//
+// Clicking the widget toggles `[vpn] connection` (a connection name as `nmcli
+// connection show` lists it) through ActivateConnection and
+// DeactivateConnection. NetworkManager's own polkit rules decide whether the
+// session may do that.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow};
+use tracing::{debug, info, warn};
+use zbus::fdo;
+use zbus::message::Type as MessageType;
+use zbus::names::BusName;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+use zbus::{Connection, MatchRule, Proxy};
+
+use crate::bus::Bus;
+use crate::buses::{BusKind, BusManager};
+use crate::dbus::supervise;
+
+const NETWORK_MANAGER: &str = "org.freedesktop.NetworkManager";
+const NETWORK_MANAGER_PATH: &str = "/org/freedesktop/NetworkManager";
+const NETWORK_MANAGER_IFACE: &str = "org.freedesktop.NetworkManager";
+const SETTINGS_PATH: &str = "/org/freedesktop/NetworkManager/Settings";
+const SETTINGS_IFACE: &str = "org.freedesktop.NetworkManager.Settings";
+const SETTINGS_CONNECTION_IFACE: &str = "org.freedesktop.NetworkManager.Settings.Connection";
+const ACTIVE_CONNECTION_IFACE: &str = "org.freedesktop.NetworkManager.Connection.Active";
+const ACTIVE_CONNECTION_PATHS: &str = "/org/freedesktop/NetworkManager/ActiveConnection";
+const SYS_CLASS_NET: &str = "/sys/class/net";
+const INTERFACE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const ICON: &str = "🔒";
+
+// NM_ACTIVE_CONNECTION_STATE_ACTIVATING / _ACTIVATED
+const STATE_ACTIVATING: u32 = 1;
+const STATE_ACTIVATED: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ActiveVpn {
+    name: String,
+    // Object path of the active connection, for DeactivateConnection.
+    path: String,
+    activating: bool,
+}
+
+fn is_vpn_type(connection_type: &str) -> bool {
+    matches!(connection_type, "vpn" | "wireguard")
+}
+
+fn is_tunnel_interface(name: &str) -> bool {
+    name.starts_with("wg") || name.starts_with("tun")
+}
+
+// Empty when no VPN is up, which hides the widget.
+fn display_text(names: &[(String, bool)]) -> String {
+    if names.is_empty() {
+        return String::new();
+    }
+    let names: Vec<String> = names
+        .iter()
+        .map(|(name, activating)| {
+            if *activating {
+                format!("{name}…")
+            } else {
+                name.clone()
+            }
+        })
+        .collect();
+    format!("{ICON} {}", names.join(", "))
+}
+
+async fn manager_proxy(connection: &Connection) -> Result<Proxy<'static>> {
+    Proxy::new(
+        connection,
+        NETWORK_MANAGER,
+        NETWORK_MANAGER_PATH,
+        NETWORK_MANAGER_IFACE,
+    )
+    .await
+    .context("create NetworkManager root proxy")
+}
+
+async fn read_active_vpns(connection: &Connection) -> Result<Vec<ActiveVpn>> {
+    let manager = manager_proxy(connection).await?;
+    let active: Vec<OwnedObjectPath> = manager
+        .get_property("ActiveConnections")
+        .await
+        .context("read NetworkManager ActiveConnections")?;
+    let mut vpns = Vec::new();
+    for path in active {
+        let proxy = Proxy::new(
+            connection,
+            NETWORK_MANAGER,
+            path.as_str(),
+            ACTIVE_CONNECTION_IFACE,
+        )
+        .await
+        .context("create NetworkManager active connection proxy")?;
+        // A connection can vanish between the list and its properties; skip
+        // it rather than fail the whole read.
+        let Ok(connection_type) = proxy.get_property::<String>("Type").await else {
+            continue;
+        };
+        if !is_vpn_type(&connection_type) {
+            continue;
+        }
+        let (Ok(name), Ok(state)) = (
+            proxy.get_property::<String>("Id").await,
+            proxy.get_property::<u32>("State").await,
+        ) else {
+            continue;
+        };
+        if state != STATE_ACTIVATING && state != STATE_ACTIVATED {
+            continue;
+        }
+        vpns.push(ActiveVpn {
+            name,
+            path: path.to_string(),
+            activating: state == STATE_ACTIVATING,
+        });
+    }
+    Ok(vpns)
+}
+
+fn tunnel_interfaces(root: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| is_tunnel_interface(name))
+        .collect();
+    names.sort();
+    names
+}
+
+fn send_text(bus: &Bus, text: String) -> Result<()> {
+    debug!(label = text, "Sending VPN update");
+    bus.send_vpn_update(text)
+}
+
+fn vpn_rules() -> Result<[MatchRule<'static>; 2]> {
+    let properties = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender(NETWORK_MANAGER)
+        .context("VPN properties rule: set sender")?
+        .interface("org.freedesktop.DBus.Properties")
+        .context("VPN properties rule: set interface")?
+        .member("PropertiesChanged")
+        .context("VPN properties rule: set member")?
+        .path_namespace(NETWORK_MANAGER_PATH)
+        .context("VPN properties rule: set path namespace")?
+        .build();
+    let owner = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .sender("org.freedesktop.DBus")
+        .context("VPN owner rule: set sender")?
+        .interface("org.freedesktop.DBus")
+        .context("VPN owner rule: set interface")?
+        .member("NameOwnerChanged")
+        .context("VPN owner rule: set member")?
+        .arg(0, NETWORK_MANAGER)
+        .context("VPN owner rule: set service argument")?
+        .build();
+    Ok([properties, owner])
+}
+
+// Only the manager's own properties (ActiveConnections) and the active
+// connections' (State) matter; device and access point chatter does not.
+fn event_path_is_relevant(path: &str) -> bool {
+    path == NETWORK_MANAGER_PATH || path.starts_with(ACTIVE_CONNECTION_PATHS)
+}
+
+async fn nm_is_running(dbus: &fdo::DBusProxy<'_>) -> Result<bool> {
+    let name = BusName::try_from(NETWORK_MANAGER).context("NetworkManager bus name")?;
+    dbus.name_has_owner(name)
+        .await
+        .context("ask D-Bus whether NetworkManager is running")
+}
+
+// Returns once NetworkManager shows up, so the supervisor restarts into the
+// event-driven path.
+async fn watch_interfaces(bus: &Bus, dbus: &fdo::DBusProxy<'_>) -> Result<()> {
+    info!("NetworkManager is not running; watching tunnel interfaces instead");
+    let mut last = None;
+    loop {
+        let interfaces: Vec<(String, bool)> = tunnel_interfaces(Path::new(SYS_CLASS_NET))
+            .into_iter()
+            .map(|name| (name, false))
+            .collect();
+        let text = display_text(&interfaces);
+        if last.as_ref() != Some(&text) {
+            send_text(bus, text.clone())?;
+            last = Some(text);
+        }
+        tokio::time::sleep(INTERFACE_POLL_INTERVAL).await;
+        if nm_is_running(dbus).await? {
+            info!("NetworkManager appeared; switching to its VPN events");
+            return Ok(());
+        }
+    }
+}
+
+async fn monitor_vpn(bus: &Bus, buses: &BusManager) -> Result<()> {
+    let connection = buses.connection(BusKind::System).await?;
+    let dbus = fdo::DBusProxy::new(&connection).await?;
+    if !nm_is_running(&dbus).await? {
+        return watch_interfaces(bus, &dbus).await;
+    }
+    let mut signals = buses
+        .subscribe(BusKind::System, vpn_rules()?.to_vec())
+        .await
+        .context("subscribe to NetworkManager VPN signals")?;
+    let reader = signals.connection().clone();
+
+    let vpns = read_active_vpns(&reader).await?;
+    info!(
+        count = vpns.len(),
+        "Watching NetworkManager VPN connections"
+    );
+    let mut last = display_text(&names(&vpns));
+    send_text(bus, last.clone())?;
+
+    while let Some(message) = signals.next().await {
+        let header = message.header();
+        let member = header
+            .member()
+            .map(|member| member.as_str())
+            .unwrap_or_default();
+        let path = header.path().map(|path| path.as_str()).unwrap_or_default();
+        if member == "NameOwnerChanged" {
+            let Ok((_, _, new_owner)) = message.body().deserialize::<(String, String, String)>()
+            else {
+                warn!("Malformed NetworkManager NameOwnerChanged signal");
+                continue;
+            };
+            if new_owner.is_empty() {
+                send_text(bus, String::new())?;
+                return Err(anyhow!("NetworkManager left the bus"));
+            }
+        } else if !event_path_is_relevant(path) {
+            continue;
+        }
+        let text = match read_active_vpns(&reader).await {
+            Ok(vpns) => display_text(&names(&vpns)),
+            Err(e) => {
+                warn!("Failed to read VPN connections: {:#}", e);
+                continue;
+            }
+        };
+        if text != last {
+            send_text(bus, text.clone())?;
+            last = text;
+        }
+    }
+    Err(anyhow!("NetworkManager D-Bus subscription ended"))
+}
+
+fn names(vpns: &[ActiveVpn]) -> Vec<(String, bool)> {
+    vpns.iter()
+        .map(|vpn| (vpn.name.clone(), vpn.activating))
+        .collect()
+}
+
+pub async fn run_vpn_monitor_supervised(bus: Bus, buses: BusManager) {
+    supervise("VPN monitor", || monitor_vpn(&bus, &buses)).await
+}
+
+// The settings object of the connection named `name`.
+async fn find_connection(connection: &Connection, name: &str) -> Result<OwnedObjectPath> {
+    let settings = Proxy::new(connection, NETWORK_MANAGER, SETTINGS_PATH, SETTINGS_IFACE)
+        .await
+        .context("create NetworkManager settings proxy")?;
+    let paths: Vec<OwnedObjectPath> = settings
+        .call("ListConnections", &())
+        .await
+        .context("list NetworkManager connections")?;
+    for path in paths {
+        let proxy = Proxy::new(
+            connection,
+            NETWORK_MANAGER,
+            path.as_str(),
+            SETTINGS_CONNECTION_IFACE,
+        )
+        .await
+        .context("create NetworkManager connection proxy")?;
+        let Ok(settings) = proxy
+            .call::<_, _, HashMap<String, HashMap<String, OwnedValue>>>("GetSettings", &())
+            .await
+        else {
+            continue;
+        };
+        let id = settings
+            .get("connection")
+            .and_then(|section| section.get("id"))
+            .and_then(|id| String::try_from(id.clone()).ok());
+        if id.as_deref() == Some(name) {
+            return Ok(path);
+        }
+    }
+    Err(anyhow!("NetworkManager has no connection named {name:?}"))
+}
+
+async fn toggle(name: &str) -> Result<()> {
+    let connection = Connection::system()
+        .await
+        .context("connect to system D-Bus")?;
+    let manager = manager_proxy(&connection).await?;
+    let active = read_active_vpns(&connection).await?;
+    if let Some(vpn) = active.iter().find(|vpn| vpn.name == name) {
+        info!(name, "Deactivating VPN");
+        let path =
+            OwnedObjectPath::try_from(vpn.path.as_str()).context("active connection path")?;
+        manager
+            .call::<_, _, ()>("DeactivateConnection", &(path,))
+            .await
+            .with_context(|| format!("deactivate {name:?}"))?;
+        return Ok(());
+    }
+    let settings = find_connection(&connection, name).await?;
+    // "/" for device and specific object: NetworkManager picks them, which is
+    // what VPN and WireGuard connections want.
+    let none = OwnedObjectPath::try_from("/").context("root object path")?;
+    info!(name, "Activating VPN");
+    manager
+        .call::<_, _, OwnedObjectPath>("ActivateConnection", &(settings, none.clone(), none))
+        .await
+        .with_context(|| format!("activate {name:?}"))?;
+    Ok(())
+}
+
+// The widget's click handler: fire-and-forget from the GTK thread. The
+// indicator itself updates through the monitor.
+pub fn toggle_connection(name: &str) {
+    let name = name.to_string();
+    tokio::spawn(async move {
+        if let Err(e) = toggle(&name).await {
+            warn!(name, "Failed to toggle VPN: {:#}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_vpn_and_tunnel_names_count() {
+        assert!(is_vpn_type("vpn"));
+        assert!(is_vpn_type("wireguard"));
+        assert!(!is_vpn_type("802-11-wireless"));
+        assert!(is_tunnel_interface("wg0"));
+        assert!(is_tunnel_interface("tun1"));
+        assert!(!is_tunnel_interface("wlan0"));
+    }
+
+    #[test]
+    fn label_lists_connections_and_hides_when_none() {
+        assert_eq!(display_text(&[]), "");
+        assert_eq!(
+            display_text(&[("Work".to_string(), false), ("wg-home".to_string(), true)]),
+            "🔒 Work, wg-home…"
+        );
+    }
+}
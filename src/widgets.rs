@@ -1,22 +1,68 @@
 // Widget layer: builds the GTK4 bar tree and owns the consumer side of every
 // subsystem channel. Each setup_*_updates takes a receiver from Bus::new and
-// drains it onto its label on the GTK main thread (glib::spawn_future_local).
+// drains it on the GTK main thread (glib::spawn_future_local), submitting a
+// BarModel property write to the shared UiDispatcher so bursts coalesce into
+// one frame. Widgets bind to the model's properties and never see a channel.
 // The producers are spawned separately by activate() with Bus clones, AFTER
 // all consumers here are wired — so a producer's first send can never race an
 // unwired channel. This module never knows what's inside the channel, only
-// that strings/structs come out and labels go in. The volume path is the
-// exception: pw's producer is a std::thread, so setup_volume_updates still
-// owns both the channel and the thread spawn.
+// that strings/structs come out and labels go in. The PipeWire paths are the
+// exception: pw's producer is a std::thread, so setup_pipewire_updates still
+// owns both channels (volume and privacy) and the thread spawn.
+//
+// The widgets live in the child modules below, grouped by what they show
+// (workspaces.rs, volume.rs, systray.rs, ...), each with the drains that feed
+// it. This file keeps what they share: the model bindings, the slot and group
+// layout, the factory table and custom GtkBuilder layouts.
+
+mod connectivity;
+mod controls;
+mod datetime;
+mod hardware;
+mod indicators;
+mod style;
+mod systray;
+mod taskbar;
+mod title;
+mod volume;
+mod window;
+mod workspaces;
+
+pub use connectivity::{
+    setup_bandwidth_updates, setup_bluetooth_updates, setup_hotspot_updates, setup_network_updates,
+    setup_rfkill_updates, setup_vpn_updates,
+};
+pub use datetime::update_time_widget;
+pub use hardware::{
+    setup_battery_updates, setup_cpufreq_updates, setup_displays_updates, setup_gpu_updates,
+    setup_kbd_backlight_updates, setup_loadavg_updates, setup_lock_keys_updates,
+};
+pub use indicators::{
+    setup_clipboard_updates, setup_notifications_updates, setup_package_updates,
+    setup_progress_updates, setup_units_updates,
+};
+pub use style::{apply_theme, load_css_styles, setup_color_scheme_updates};
+pub use systray::{create_tray_widget, setup_tray_updates};
+pub use taskbar::{TaskbarStore, setup_taskbar_updates};
+pub use title::setup_title_updates;
+pub use volume::setup_pipewire_updates;
+pub use window::{
+    apply_floating, apply_position, clear_floating, configure_layer_shell, floating_provider,
+};
+pub use workspaces::{
+    setup_floating_updates, setup_scratchpad_updates, setup_split_updates,
+    setup_window_count_updates, setup_workspace_updates,
+};
 
 use std::cell::{Cell, RefCell};
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 
-use anyhow::{Result, bail};
+use anyhow::{Context, Result, bail};
 use chrono::Local;
 use gtk4::gdk;
 use gtk4::gio::prelude::*;
@@ -27,10 +73,78 @@ use tokio::sync::mpsc;
 use tracing::{debug, info, trace, warn};
 use tray_ipc::{IpcRequest, IpcResponse, IpcTrayItem, IpcUiRequest};
 
-use crate::bus::{TitleUpdate, VolumeUpdate, WorkspaceUpdate};
-use crate::clock::Clock;
+use crate::alarm::{Alarm, AlarmBook};
+use crate::app_state::AppState;
+use crate::audio::{self, AudioCommand, AudioSinks};
+use crate::backlight;
+use crate::battery::{self, BatteryAlerts, BatteryLevel, ChargingAnimation};
+use crate::bus::{
+    self, BatteryUpdate, BluetoothUpdate, GpuUpdate, HeadsetMode, HeadsetUpdate,
+    KbdBacklightUpdate, LoadAvgUpdate, NoAudio, NotificationsUpdate, PlaybackStream,
+    PlaybackUpdate, PrivacyUpdate, ProgressUpdate, RfkillUpdate, SinkAdded, SinkList,
+    TaskbarUpdate, TaskbarWindow, TitleUpdate, VolumeReport, WorkspaceUpdate,
+};
+use crate::capture::Capture;
+use crate::channel;
+use crate::clock::{self, Clock};
+use crate::command;
+use crate::compositor::AnyCompositor;
+use crate::config::{
+    self, BatteryConfig, CaptureConfig, ClipboardConfig, Config, CpuFreqConfig, DisplaysConfig,
+    Ellipsize, FileWatchConfig, FloatingConfig, GroupConfig, Position, PowerConfig, SeparatorStyle,
+    Theme, TitleConfig, TitleRewriteRule, UpdatesConfig, VpnConfig, WidgetSettings,
+    WorkspaceConfig,
+};
+use crate::cpufreq;
+use crate::dispatch::{Slot, UiDispatcher};
+use crate::filewatch;
+use crate::format::Format;
+use crate::headset;
+use crate::hypr;
+use crate::kbd_backlight;
+use crate::model::BarModel;
+use crate::network;
+use crate::notifications;
+use crate::playback;
+use crate::pomodoro::PomodoroTimer;
+use crate::power::{self, PowerAction};
+use crate::privacy;
+use crate::process;
 use crate::pw;
-use crate::tray::{TrayAction, TrayCommand, TrayItem, TrayMenu, TrayMenuItem, TrayUi, TrayUpdate};
+use crate::quick_settings;
+use crate::rfkill;
+use crate::sinks;
+use crate::text::{self, Ellipsis, TitleRewrite};
+use crate::toplevel::WindowActions;
+use crate::tray::{
+    IconPixmap, TrayAction, TrayCommand, TrayItem, TrayMenu, TrayMenuItem, TrayUi, TrayUpdate,
+};
+use crate::units;
+use crate::updates;
+use crate::vpn;
+
+use connectivity::{
+    create_bandwidth_widget, create_bt_widget, create_hotspot_widget, create_network_widget,
+    create_rfkill_widget, create_vpn_widget,
+};
+use controls::{create_power_widget, create_quick_settings_widget};
+use datetime::create_time_widget;
+use hardware::{
+    create_battery_widget, create_cpufreq_widget, create_displays_widget, create_gpu_widget,
+    create_kbd_backlight_widget, create_loadavg_widget, create_lock_keys_widget,
+};
+use indicators::{
+    create_capture_widget, create_clipboard_widget, create_file_watch_widget,
+    create_notifications_widget, create_pomodoro_widget, create_progress_widget,
+    create_style_errors_widget, create_units_widget, create_updates_widget,
+};
+use systray::tray_icon_pixel_size;
+use taskbar::{create_taskbar_widget, setup_playback_updates};
+use title::{create_title_widget, update_title_icon};
+use volume::{create_headset_widget, create_privacy_widget, create_volume_widget};
+use workspaces::{
+    add_workspace_scroll, create_scratchpad_widget, create_split_widget, create_workspace_widget,
+};
 
 const UI_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
 const UI_WATCHDOG_INTERVAL: Duration = Duration::from_secs(5);
@@ -77,259 +191,953 @@ pub fn setup_ui_watchdog() {
 // Widget constructors are infallible — gtk4::Label::new, add_css_class, and
 // set_halign all return (). The previous Result<…> signatures were speculative,
 // forcing every caller to `?`-thread an error that could not be produced.
-pub fn create_workspace_widget() -> gtk4::Label {
-    debug!("Creating workspace widget");
-    let label = gtk4::Label::new(Some("Workspace ?"));
-    label.add_css_class("workspace-widget");
-    label.set_halign(gtk4::Align::Center);
-    label
+//
+// Each constructor binds its widget to the BarModel property it displays; the
+// drains next to it only ever write the model. setup_*_updates are infallible
+// too now that there is no global sender to double-initialize — they only
+// move a receiver into a glib-local drain task.
+
+fn model_property(property: &str) -> gtk4::PropertyExpression {
+    gtk4::PropertyExpression::new(BarModel::static_type(), None::<&gtk4::Expression>, property)
 }
 
-pub fn create_volume_widget() -> gtk4::Label {
-    debug!("Creating volume widget");
-    let label = gtk4::Label::new(Some("Volume ?"));
-    label.add_css_class("volume-widget");
-    label.set_halign(gtk4::Align::Center);
-    label
+fn bind_label_text(label: &gtk4::Label, model: &BarModel, property: &str) {
+    model_property(property).bind(label, "label", Some(model));
 }
 
-#[derive(Clone)]
-pub struct TitleWidget {
-    root: gtk4::CenterBox,
-    icon: gtk4::Image,
-    label: gtk4::Label,
+// Battery, bluetooth and the split hint hide their pill entirely when the
+// producer has nothing to show.
+// NOTE: Originally tried CSS approach with label.add_css_class("widget-hidden")
+// and .widget-hidden { display: none !important; } but GTK4 CSS specificity
+// issues prevented it from working. GTK's native visible property works reliably.
+fn bind_label_visible_when_non_empty(label: &gtk4::Label, model: &BarModel, property: &str) {
+    model_property(property)
+        .chain_closure::<bool>(glib::closure!(|_: Option<glib::Object>, text: &str| !text
+            .trim()
+            .is_empty()))
+        .bind(label, "visible", Some(model));
+}
+
+fn set_bar_keyboard_mode(popover: &gtk4::Popover, mode: KeyboardMode) {
+    let Some(window) = popover.root().and_downcast::<gtk4::Window>() else {
+        return;
+    };
+    window.set_keyboard_mode(mode);
+}
+
+// A built bar tree. Every placed widget sits alone in a slot Box keyed by its
+// factory name (plus "tray"), which is what the set-visible control verb
+// toggles: hiding the slot rather than the widget leaves the widgets' own
+// model-bound `visible` (battery, bluetooth, split) untouched.
+pub struct Bar {
+    pub root: gtk4::Widget,
+    pub slots: Slots,
+}
+
+type Slots = HashMap<String, gtk4::Box>;
+
+// What a widget factory builds from: the model it binds to, the configuration,
+// and the GTK-side stores some widgets edit in place.
+pub struct WidgetContext<'a> {
+    pub model: &'a BarModel,
+    pub config: &'a Config,
+    pub alarms: &'a AlarmBook,
+    pub pomodoro: &'a PomodoroTimer,
+    pub capture: &'a Capture,
+    pub audio: &'a AudioSinks,
+    pub taskbar: &'a TaskbarStore,
+    pub compositor: &'a AnyCompositor,
+}
+
+// Build the configured layout around an existing tray strip. The strip is
+// created once by the caller because setup_tray_updates drives it for the
+// whole run; a configuration reload moves it into the new tree.
+pub fn build_bar(cx: &WidgetContext, tray: &gtk4::Box) -> Result<Bar> {
+    let bar = match &cx.config.layout.file {
+        Some(path) => create_bar_from_builder(path, cx, tray)?,
+        None => create_experimental_bar(cx, tray),
+    };
+    apply_widget_settings(&bar.slots, cx.config);
+    if cx.config.workspace.scroll_bar {
+        add_workspace_scroll(&bar.root, cx.compositor, &cx.config.workspace);
+    }
+    Ok(bar)
+}
+
+// `[widgets.<name>]`: sizing and commands go on the widget a slot holds, so
+// they work the same in the built-in layout and in a custom one.
+fn apply_widget_settings(slots: &Slots, config: &Config) {
+    for (name, settings) in &config.widgets {
+        let Some(widget) = slots.get(name).and_then(|slot| slot.first_child()) else {
+            if !config.bar.is_disabled(name) {
+                warn!(
+                    widget = name,
+                    "Settings configured for a widget the layout does not place"
+                );
+            }
+            continue;
+        };
+        debug!(widget = name, ?settings, "Applying widget settings");
+        attach_widget_commands(&widget, name, settings);
+        if let Some(width) = settings.min_width {
+            widget.set_width_request(i32::from(width));
+        }
+        if let Some(align) = settings.align {
+            widget.set_halign(match align {
+                config::Align::Start => gtk4::Align::Start,
+                config::Align::Center => gtk4::Align::Center,
+                config::Align::End => gtk4::Align::End,
+                config::Align::Fill => gtk4::Align::Fill,
+            });
+        }
+        if let Some(chars) = settings.max_width {
+            cap_label_widths(&widget, i32::from(chars));
+        }
+    }
+}
+
+// `on_click` and friends. Each configured command replaces what the widget
+// does itself with that button or scroll direction: the handlers run in the
+// capture phase and claim the click (or stop the scroll), so the widget's own
+// controllers never see it, while the buttons and directions left unset keep
+// working as before.
+fn attach_widget_commands(widget: &gtk4::Widget, name: &str, settings: &WidgetSettings) {
+    let clicks = [
+        (1, &settings.on_click),
+        (2, &settings.on_middle_click),
+        (3, &settings.on_right_click),
+    ];
+    for (button, command) in clicks {
+        let Some(command) = command.clone() else {
+            continue;
+        };
+        let gesture = gtk4::GestureClick::new();
+        gesture.set_button(button);
+        gesture.set_propagation_phase(gtk4::PropagationPhase::Capture);
+        gesture.connect_pressed(|gesture, _, _, _| {
+            gesture.set_state(gtk4::EventSequenceState::Claimed);
+        });
+        let what = format!("{name} click");
+        gesture.connect_released(move |_, _, _, _| command::spawn_shell(&command, &what));
+        widget.add_controller(gesture);
+    }
+
+    if settings.on_scroll_up.is_none() && settings.on_scroll_down.is_none() {
+        return;
+    }
+    let scroll = gtk4::EventControllerScroll::new(
+        gtk4::EventControllerScrollFlags::VERTICAL | gtk4::EventControllerScrollFlags::DISCRETE,
+    );
+    scroll.set_propagation_phase(gtk4::PropagationPhase::Capture);
+    let up = settings.on_scroll_up.clone();
+    let down = settings.on_scroll_down.clone();
+    let what = format!("{name} scroll");
+    scroll.connect_scroll(move |_, _dx, dy| {
+        let command = match dy {
+            dy if dy < 0.0 => up.as_deref(),
+            dy if dy > 0.0 => down.as_deref(),
+            _ => None,
+        };
+        let Some(command) = command else {
+            return glib::Propagation::Proceed;
+        };
+        command::spawn_shell(command, &what);
+        glib::Propagation::Stop
+    });
+    widget.add_controller(scroll);
+}
+
+// Every label in the widget's own tree, but not in its popovers, which are
+// separate surfaces with room of their own. A label that already ellipsizes
+// (the title, in the middle) keeps its mode.
+fn cap_label_widths(widget: &gtk4::Widget, chars: i32) {
+    if widget.is::<gtk4::Popover>() {
+        return;
+    }
+    if let Some(label) = widget.downcast_ref::<gtk4::Label>() {
+        label.set_max_width_chars(chars);
+        if label.ellipsize() == gtk4::pango::EllipsizeMode::None {
+            label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+        }
+    }
+    let mut child = widget.first_child();
+    while let Some(current) = child {
+        cap_label_widths(&current, chars);
+        child = current.next_sibling();
+    }
+}
+
+// Take the tray strip out of its slot so the next build_bar can place it.
+// Returns the slot it left, for putting it back if that build fails.
+pub fn detach_tray(tray: &gtk4::Box) -> Option<gtk4::Box> {
+    let slot = tray.parent()?.downcast::<gtk4::Box>().ok()?;
+    slot.remove(tray);
+    Some(slot)
 }
 
-pub fn create_title_widget() -> TitleWidget {
-    debug!("Creating title widget");
-
-    let root = gtk4::CenterBox::new();
-    root.add_css_class("title-widget");
-    root.set_halign(gtk4::Align::End);
-    root.set_valign(gtk4::Align::Start);
-
-    let icon = gtk4::Image::new();
-    icon.add_css_class("title-icon");
-    icon.set_valign(gtk4::Align::Center);
-    icon.set_visible(false);
-    root.set_start_widget(Some(&icon));
-
-    let label = gtk4::Label::new(Some("Application Title"));
-    label.add_css_class("title-label");
-    label.set_valign(gtk4::Align::Center);
-    // The producer already crops long titles by character count, but wide
-    // glyphs can still exceed the remaining monitor width when right-side
-    // pills are added. Ellipsizing gives GTK permission to shrink the label's
-    // minimum width instead of expanding the layer surface past the output.
-    label.set_ellipsize(gtk4::pango::EllipsizeMode::Middle);
-    label.set_single_line_mode(true);
-    // Label is the CenterBox's own center widget (icon is the start widget)
-    // so GtkCenterLayout keeps the title text truly centered in the pill
-    // regardless of whether the icon is showing — packing both into one
-    // "center" child instead visually centers the icon+label group, which
-    // pulls short titles off-center once an icon appears.
-    root.set_center_widget(Some(&label));
-
-    TitleWidget { root, icon, label }
+fn new_slot() -> gtk4::Box {
+    gtk4::Box::new(gtk4::Orientation::Horizontal, 0)
 }
 
-pub fn create_time_widget() -> gtk4::Label {
-    debug!("Creating time widget");
-    let time_str = get_current_time();
-    let label = gtk4::Label::new(Some(&time_str));
-    label.add_css_class("time-widget");
-    label.set_halign(gtk4::Align::End);
-    label
+// A widget listed in `[bar] disabled_widgets` gets an empty slot that is left
+// out of `slots`, so the backends behind it are never started.
+fn place_widget(name: &'static str, cx: &WidgetContext, slots: &mut Slots) -> gtk4::Box {
+    let slot = new_slot();
+    if cx.config.bar.is_disabled(name) {
+        debug!(widget = name, "Widget disabled in the configuration");
+        return slot;
+    }
+    match WIDGET_FACTORIES
+        .iter()
+        .find(|(factory_name, _)| *factory_name == name)
+    {
+        Some((_, factory)) => slot.append(&build_widget(name, *factory, cx)),
+        None => warn!(widget = name, "No factory for built-in widget"),
+    }
+    slots.insert(name.to_string(), slot.clone());
+    slot
 }
 
-pub fn get_current_time() -> String {
-    Local::now().format("%l:%M %p").to_string()
+// A built-in widget as its slot holds it: wrapped for `reveal_on_hover` when
+// its `[widgets.<name>]` asks for that.
+fn build_widget(name: &str, factory: WidgetFactory, cx: &WidgetContext) -> gtk4::Widget {
+    let widget = factory(cx);
+    match cx.config.widgets.get(name) {
+        Some(settings) if settings.reveal_on_hover => {
+            debug!(widget = name, "Revealing the widget on hover");
+            hover_reveal(widget, settings.compact.as_deref())
+        }
+        _ => widget,
+    }
 }
 
-pub fn update_time_widget(label: gtk4::Label) {
-    debug!("Setting up time widget updates");
+const COMPACT_CLASS: &str = "compact";
 
-    let label_weak = label.downgrade();
-    Clock::new()
-        .on_second(move |now| {
-            let Some(label) = label_weak.upgrade() else {
-                return;
-            };
+// The compact form of a widget's text: its first word, which for most
+// widgets is the icon ("🔊 45%" is "🔊").
+fn compact_text(text: &str) -> &str {
+    text.split_whitespace().next().unwrap_or_default()
+}
 
-            let text = now.format("%l:%M %p").to_string();
-            debug!("Updating time label: {text}");
-            label.set_text(&text);
+// A compact label that slides open into the full widget while the pointer is
+// over either. The label follows the widget's text and CSS classes (plus
+// `compact`), so it wears the same pill and state colors, and the pair shows
+// and hides with the widget. While one of the widget's popovers is open the
+// widget stays expanded, as collapsing it would unmap the popover.
+fn hover_reveal(widget: gtk4::Widget, compact: Option<&str>) -> gtk4::Widget {
+    let Some(label) = first_label(&widget) else {
+        warn!("reveal_on_hover needs a widget that shows text; leaving it expanded");
+        return widget;
+    };
+    let compact_label = gtk4::Label::new(compact);
+    if compact.is_none() {
+        label
+            .bind_property("label", &compact_label, "label")
+            .transform_to(|_, text: String| Some(compact_text(&text).to_string()))
+            .sync_create()
+            .build();
+    }
+    widget
+        .bind_property("css-classes", &compact_label, "css-classes")
+        .transform_to(|_, mut classes: Vec<String>| {
+            classes.push(COMPACT_CLASS.to_string());
+            Some(classes)
         })
-        .start();
+        .sync_create()
+        .build();
+
+    let collapsed = gtk4::Revealer::builder()
+        .transition_type(gtk4::RevealerTransitionType::SlideLeft)
+        .reveal_child(true)
+        .child(&compact_label)
+        .build();
+    let expanded = gtk4::Revealer::builder()
+        .transition_type(gtk4::RevealerTransitionType::SlideRight)
+        .reveal_child(false)
+        .build();
+    let root = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
+    root.add_css_class("hover-reveal");
+    root.set_halign(widget.halign());
+    root.set_valign(widget.valign());
+    widget
+        .bind_property("visible", &root, "visible")
+        .sync_create()
+        .build();
+    let popovers = popovers(&widget);
+    expanded.set_child(Some(&widget));
+    root.append(&collapsed);
+    root.append(&expanded);
+
+    // Weak, as the controllers holding these closures live on the root.
+    let collapsed_weak = collapsed.downgrade();
+    let expanded_weak = expanded.downgrade();
+    let reveal = move |full: bool| {
+        let (Some(collapsed), Some(expanded)) = (collapsed_weak.upgrade(), expanded_weak.upgrade())
+        else {
+            return;
+        };
+        collapsed.set_reveal_child(!full);
+        expanded.set_reveal_child(full);
+    };
+    let motion = gtk4::EventControllerMotion::new();
+    let on_enter = reveal.clone();
+    motion.connect_enter(move |_, _, _| on_enter(true));
+    let on_leave = reveal.clone();
+    let open_popovers: Vec<_> = popovers.iter().map(|popover| popover.downgrade()).collect();
+    motion.connect_leave(move |_| {
+        let popover_open = open_popovers.iter().any(|popover| {
+            popover
+                .upgrade()
+                .is_some_and(|popover| popover.is_visible())
+        });
+        if !popover_open {
+            on_leave(false);
+        }
+    });
+    let motion_weak = motion.downgrade();
+    for popover in popovers {
+        let reveal = reveal.clone();
+        let motion_weak = motion_weak.clone();
+        popover.connect_closed(move |_| {
+            if !motion_weak
+                .upgrade()
+                .is_some_and(|motion| motion.contains_pointer())
+            {
+                reveal(false);
+            }
+        });
+    }
+    root.add_controller(motion);
+    root.upcast()
 }
 
-pub fn create_bt_widget() -> gtk4::Label {
-    debug!("Creating bluetooth widget");
-    let label = gtk4::Label::new(None); // Start with no text, will be hidden until devices found
-    label.add_css_class("bt-widget");
-    label.set_halign(gtk4::Align::End);
-    label
+fn first_label(widget: &gtk4::Widget) -> Option<gtk4::Label> {
+    if widget.is::<gtk4::Popover>() {
+        return None;
+    }
+    if let Some(label) = widget.downcast_ref::<gtk4::Label>() {
+        return Some(label.clone());
+    }
+    let mut child = widget.first_child();
+    while let Some(current) = child {
+        if let Some(label) = first_label(&current) {
+            return Some(label);
+        }
+        child = current.next_sibling();
+    }
+    None
+}
+
+fn popovers(widget: &gtk4::Widget) -> Vec<gtk4::Popover> {
+    if let Some(popover) = widget.downcast_ref::<gtk4::Popover>() {
+        return vec![popover.clone()];
+    }
+    let mut found = Vec::new();
+    let mut child = widget.first_child();
+    while let Some(current) = child {
+        found.extend(popovers(&current));
+        child = current.next_sibling();
+    }
+    found
+}
+
+// A `[[file_watch]]` widget, placed in a slot under its configured name like
+// any built-in one. A name that shadows a built-in widget is skipped.
+fn place_file_watch(watch: &FileWatchConfig, slots: &mut Slots) -> Option<gtk4::Box> {
+    if slots.contains_key(&watch.name) || is_reserved_slot(&watch.name) {
+        warn!(
+            name = watch.name,
+            "File watch name is already taken; skipping it"
+        );
+        return None;
+    }
+    let slot = new_slot();
+    slot.append(&create_file_watch_widget(watch));
+    slots.insert(watch.name.clone(), slot.clone());
+    Some(slot)
+}
+
+// A file watch added over the control socket (see control.rs), placed after
+// `after`, the previous such widget, or else right after the tray, which
+// every layout is expected to place in a box.
+pub fn place_runtime_widget(
+    bar: &mut Bar,
+    watch: &FileWatchConfig,
+    after: Option<&gtk4::Box>,
+) -> Result<()> {
+    place_extra_widget(bar, &watch.name, after, || {
+        create_file_watch_widget(watch).upcast()
+    })
+}
+
+// A widget an embedding program adds with BarBuilder::custom_widget. The
+// factory runs on every build of the bar, a reload included, like a built-in
+// widget's constructor.
+#[derive(Clone)]
+pub struct CustomWidget {
+    pub name: String,
+    pub build: Rc<dyn Fn(&BarModel) -> gtk4::Widget>,
+}
+
+// Custom widgets go right after the tray, in order.
+pub fn place_custom_widgets(
+    bar: &mut Bar,
+    model: &BarModel,
+    custom: &[CustomWidget],
+) -> Result<()> {
+    let mut after = None;
+    for widget in custom {
+        place_extra_widget(bar, &widget.name, after.as_ref(), || (widget.build)(model))?;
+        after = bar.slots.get(&widget.name).cloned();
+    }
+    Ok(())
+}
+
+// A widget placed once the layout is built: after `after`, or after the tray
+// slot. `create` only runs once the name is known to be free.
+fn place_extra_widget(
+    bar: &mut Bar,
+    name: &str,
+    after: Option<&gtk4::Box>,
+    create: impl FnOnce() -> gtk4::Widget,
+) -> Result<()> {
+    if bar.slots.contains_key(name) || is_reserved_slot(name) {
+        bail!("the bar already has a widget named {:?}", name);
+    }
+    let anchor = match after {
+        Some(slot) => slot.clone(),
+        None => bar
+            .slots
+            .get(TRAY_SLOT)
+            .cloned()
+            .context("the layout has no tray slot to place the widget next to")?,
+    };
+    let Some(parent) = anchor.parent().and_downcast::<gtk4::Box>() else {
+        bail!("the tray slot is not inside a box");
+    };
+    let slot = new_slot();
+    slot.append(&create());
+    parent.insert_child_after(&slot, Some(&anchor));
+    bar.slots.insert(name.to_string(), slot);
+    Ok(())
+}
+
+// Dropping the label stops its watcher.
+pub fn remove_runtime_widget(bar: &mut Bar, name: &str) {
+    let Some(slot) = bar.slots.remove(name) else {
+        return;
+    };
+    if let Some(parent) = slot.parent().and_downcast::<gtk4::Box>() {
+        parent.remove(&slot);
+    }
+}
+
+// The `popover` control verb: opens the popover a click on the widget in slot
+// `name` opens. From a keybind the pointer is elsewhere, so the bar takes the
+// keyboard until the popover closes; the tray has keyboard-menu for that.
+pub fn open_widget_popover(bar: &Bar, name: &str) -> Result<()> {
+    if name == TRAY_SLOT {
+        bail!("tray menus open with keyboard-menu");
+    }
+    let Some(slot) = bar.slots.get(name) else {
+        bail!("the bar has no widget {name:?}");
+    };
+    let Some(popover) = find_popover(slot.upcast_ref()) else {
+        bail!("widget {name:?} has no popover");
+    };
+    if !slot.is_mapped() {
+        bail!("widget {name:?} is not on screen");
+    }
+    debug!(name, "Opening widget popover");
+    popover.popup();
+    // After popup: the clock's and the network's map handlers ask for
+    // on-demand focus, which would not reach a popover opened without a click.
+    set_bar_keyboard_mode(&popover, KeyboardMode::Exclusive);
+    let handler = Rc::new(Cell::new(None::<glib::SignalHandlerId>));
+    let once = handler.clone();
+    handler.set(Some(popover.connect_closed(move |popover| {
+        set_bar_keyboard_mode(popover, KeyboardMode::None);
+        if let Some(handler) = once.take() {
+            popover.disconnect(handler);
+        }
+    })));
+    Ok(())
+}
+
+// A MenuButton's popover, or one set_parent put on a plain widget (the
+// volume label's), both children in the widget tree.
+fn find_popover(widget: &gtk4::Widget) -> Option<gtk4::Popover> {
+    if let Some(popover) = widget.downcast_ref::<gtk4::Popover>() {
+        return Some(popover.clone());
+    }
+    let mut child = widget.first_child();
+    while let Some(current) = child {
+        if let Some(popover) = find_popover(&current) {
+            return Some(popover);
+        }
+        child = current.next_sibling();
+    }
+    None
+}
+
+fn is_reserved_slot(name: &str) -> bool {
+    name == TRAY_SLOT
+        || name == LAYOUT_ROOT
+        || WIDGET_FACTORIES
+            .iter()
+            .any(|(factory_name, _)| *factory_name == name)
+}
+
+const SEPARATOR_CLASS: &str = "widget-separator";
+
+// `[groups.<side>]`, applied once the group holds its slots.
+fn style_group(group: &gtk4::Box, config: &GroupConfig) {
+    group.set_spacing(i32::from(config.spacing));
+    if group.orientation() == gtk4::Orientation::Vertical {
+        group.set_margin_top(i32::from(config.padding));
+        group.set_margin_bottom(i32::from(config.padding));
+    } else {
+        group.set_margin_start(i32::from(config.padding));
+        group.set_margin_end(i32::from(config.padding));
+    }
+    if config.separator == SeparatorStyle::None {
+        return;
+    }
+
+    let mut slots = Vec::new();
+    let mut child = group.first_child();
+    while let Some(current) = child {
+        child = current.next_sibling();
+        slots.push(current);
+    }
+    let group_weak = group.downgrade();
+    let refresh = move || {
+        if let Some(group) = group_weak.upgrade() {
+            update_separators(&group);
+        }
+    };
+    for (index, slot) in slots.iter().enumerate() {
+        if index > 0 {
+            let separator = new_separator(config, group.orientation());
+            group.insert_child_after(&separator, slot.prev_sibling().as_ref());
+        }
+        // Most widgets hide themselves (no battery, nothing to report), and
+        // the slot hides under set-visible; either can leave a separator
+        // with nothing on one side.
+        let on_slot = refresh.clone();
+        slot.connect_visible_notify(move |_| on_slot());
+        if let Some(widget) = slot.first_child() {
+            let on_widget = refresh.clone();
+            widget.connect_visible_notify(move |_| on_widget());
+        }
+    }
+    update_separators(group);
 }
 
-pub fn create_battery_widget() -> gtk4::Label {
-    debug!("Creating battery widget");
-    let label = gtk4::Label::new(Some("🔋 ??%"));
-    label.add_css_class("battery-widget");
-    label.set_halign(gtk4::Align::End);
-    label
+// A divider line runs across the group, so it is vertical in a horizontal bar.
+fn new_separator(config: &GroupConfig, along: gtk4::Orientation) -> gtk4::Widget {
+    let across = match along {
+        gtk4::Orientation::Vertical => gtk4::Orientation::Horizontal,
+        _ => gtk4::Orientation::Vertical,
+    };
+    let separator: gtk4::Widget = match config.separator {
+        SeparatorStyle::Divider => gtk4::Separator::new(across).upcast(),
+        SeparatorStyle::Text | SeparatorStyle::None => {
+            gtk4::Label::new(Some(&config.separator_text)).upcast()
+        }
+    };
+    separator.add_css_class(SEPARATOR_CLASS);
+    separator
+}
+
+// A separator shows only between two showing widgets.
+fn update_separators(group: &gtk4::Box) {
+    let mut seen_widget = false;
+    let mut pending: Option<gtk4::Widget> = None;
+    let mut child = group.first_child();
+    while let Some(current) = child {
+        child = current.next_sibling();
+        if current.has_css_class(SEPARATOR_CLASS) {
+            current.set_visible(false);
+            pending = Some(current);
+            continue;
+        }
+        let showing = current.is_visible()
+            && current
+                .first_child()
+                .is_some_and(|widget| widget.is_visible());
+        if !showing {
+            continue;
+        }
+        if let Some(separator) = pending.take()
+            && seen_widget
+        {
+            separator.set_visible(true);
+        }
+        seen_widget = true;
+    }
 }
 
-pub fn create_network_widget() -> gtk4::Label {
-    debug!("Creating network widget");
-    let label = gtk4::Label::new(Some("🌐 ?"));
-    label.add_css_class("network-widget");
-    label.set_halign(gtk4::Align::End);
-    label
+// The direction the bar runs in: along the edge it sits on.
+fn bar_orientation(position: Position) -> gtk4::Orientation {
+    if position.is_vertical() {
+        gtk4::Orientation::Vertical
+    } else {
+        gtk4::Orientation::Horizontal
+    }
 }
 
-pub fn create_tray_widget() -> gtk4::Box {
-    debug!("Creating system tray widget");
-    let tray = gtk4::Box::new(gtk4::Orientation::Horizontal, 4);
-    tray.add_css_class("tray-widget");
-    tray.set_visible(false);
-    tray
+// For the spacers that take up the slack along the bar.
+fn expand_along(widget: &impl IsA<gtk4::Widget>, orientation: gtk4::Orientation) {
+    match orientation {
+        gtk4::Orientation::Vertical => widget.set_vexpand(true),
+        _ => widget.set_hexpand(true),
+    }
 }
 
-pub fn create_left_group() -> (gtk4::Box, gtk4::Label) {
+fn create_left_group(cx: &WidgetContext, slots: &mut Slots) -> gtk4::Box {
     debug!("Creating left group");
+    let orientation = bar_orientation(cx.config.bar.position);
 
-    let left_container = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
+    let left_container = gtk4::Box::new(orientation, 0);
     left_container.add_css_class("left-container");
     left_container.set_valign(gtk4::Align::Start);
     left_container.set_hexpand(false);
 
-    let left_group = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
+    let left_group = gtk4::Box::new(orientation, 0);
     left_group.add_css_class("left-group");
     left_group.set_hexpand(false);
 
-    let workspace_widget = create_workspace_widget();
-    left_group.append(&workspace_widget);
+    left_group.append(&place_widget("workspace", cx, slots));
+    left_group.append(&place_widget("split", cx, slots));
+    left_group.append(&place_widget("scratchpad", cx, slots));
+    if cx.config.taskbar.enabled {
+        left_group.append(&place_widget("taskbar", cx, slots));
+    }
+    style_group(&left_group, &cx.config.groups.left);
 
-    let left_spacer = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
-    left_spacer.set_hexpand(true);
+    let left_spacer = gtk4::Box::new(orientation, 0);
+    expand_along(&left_spacer, orientation);
 
     left_container.append(&left_group);
     left_container.append(&left_spacer);
 
-    (left_container, workspace_widget)
+    left_container
 }
 
-pub fn create_right_group() -> (
-    gtk4::Box,
-    gtk4::Box,
-    gtk4::Label,
-    gtk4::Label,
-    gtk4::Label,
-    gtk4::Label,
-    gtk4::Label,
-) {
+fn create_right_group(cx: &WidgetContext, tray: &gtk4::Box, slots: &mut Slots) -> gtk4::Box {
     debug!("Creating right group");
+    let orientation = bar_orientation(cx.config.bar.position);
 
-    let right_container = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
+    let right_container = gtk4::Box::new(orientation, 0);
     right_container.add_css_class("right-container");
     right_container.set_hexpand(false);
     right_container.set_valign(gtk4::Align::Start);
 
-    let right_spacer = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
-    right_spacer.set_hexpand(true);
+    let right_spacer = gtk4::Box::new(orientation, 0);
+    expand_along(&right_spacer, orientation);
 
-    let right_group = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
+    let right_group = gtk4::Box::new(orientation, 0);
     right_group.add_css_class("right-group");
     right_group.set_hexpand(false);
 
-    let tray_widget = create_tray_widget();
-    right_group.append(&tray_widget);
-
-    let bt_widget = create_bt_widget();
-    right_group.append(&bt_widget);
-
-    let volume_widget = create_volume_widget();
-    right_group.append(&volume_widget);
-
-    let network_widget = create_network_widget();
-    right_group.append(&network_widget);
-
-    let battery_widget = create_battery_widget();
-    right_group.append(&battery_widget);
-
-    let time_widget = create_time_widget();
-    right_group.append(&time_widget);
+    // Set on every build, as a reload can move the bar to another edge.
+    tray.set_orientation(orientation);
+    let tray_slot = new_slot();
+    tray_slot.append(tray);
+    slots.insert(TRAY_SLOT.to_string(), tray_slot.clone());
+    right_group.append(&tray_slot);
+
+    for watch in &cx.config.file_watch {
+        if let Some(slot) = place_file_watch(watch, slots) {
+            right_group.append(&slot);
+        }
+    }
+
+    for name in [
+        "style_errors",
+        "privacy",
+        "cpufreq",
+        "loadavg",
+        "gpu",
+        "displays",
+        "bluetooth",
+        "volume",
+        "headset",
+        "kbd_backlight",
+        "lock_keys",
+        "units",
+        "updates",
+        "clipboard",
+        "progress",
+        "vpn",
+        "hotspot",
+        "rfkill",
+        "bandwidth",
+        "network",
+        "battery",
+        "capture",
+        "pomodoro",
+        "notifications",
+        "clock",
+        "quick_settings",
+        "power",
+    ] {
+        right_group.append(&place_widget(name, cx, slots));
+    }
+    style_group(&right_group, &cx.config.groups.right);
 
     right_container.append(&right_spacer);
     right_container.append(&right_group);
 
-    (
-        right_container,
-        tray_widget,
-        bt_widget,
-        volume_widget,
-        network_widget,
-        battery_widget,
-        time_widget,
-    )
+    right_container
 }
 
-pub fn create_experimental_bar() -> (
-    gtk4::CenterBox,
-    gtk4::Box,
-    gtk4::Label,
-    gtk4::Label,
-    gtk4::Label,
-    gtk4::Label,
-    gtk4::Label,
-    gtk4::Label,
-    TitleWidget,
-) {
+fn create_experimental_bar(cx: &WidgetContext, tray: &gtk4::Box) -> Bar {
     debug!("Creating experimental bar");
 
+    let vertical = cx.config.bar.position.is_vertical();
     let main_box = gtk4::CenterBox::new();
-    main_box.set_hexpand(true);
-    main_box.set_valign(gtk4::Align::Start);
-
-    let (left_group, workspace_widget) = create_left_group();
-    let title_widget = create_title_widget();
-    let (
-        right_group,
-        tray_widget,
-        bt_widget,
-        volume_widget,
-        network_widget,
-        battery_widget,
-        time_widget,
-    ) = create_right_group();
+    main_box.set_orientation(bar_orientation(cx.config.bar.position));
+    if vertical {
+        main_box.set_vexpand(true);
+    } else {
+        main_box.set_hexpand(true);
+        main_box.set_valign(gtk4::Align::Start);
+    }
+
+    let mut slots = HashMap::new();
+    let left_group = create_left_group(cx, &mut slots);
+    // A window title read a word per line would take up most of a side bar.
+    let title_slot = (!vertical).then(|| place_widget("title", cx, &mut slots));
+    let right_group = create_right_group(cx, tray, &mut slots);
 
     // GtkCenterLayout keeps the title at the monitor midpoint independently
     // of the side groups' widths. Equal expanding spacers cannot guarantee
     // that once the dynamic right group grows wider than its 20em container.
     main_box.set_start_widget(Some(&left_group));
-    main_box.set_center_widget(Some(&title_widget.root));
+    main_box.set_center_widget(title_slot.as_ref());
     main_box.set_end_widget(Some(&right_group));
 
-    // Pin the height once the font is resolvable, so dynamic content (title
-    // length, tray removal) can't resize the bar and shift windows below it.
-    let bar_weak = main_box.downgrade();
+    if vertical {
+        // The width follows the widest widget instead of being pinned.
+        stack_label_words(main_box.upcast_ref());
+        for slot in slots.values() {
+            if let Some(widget) = slot.first_child() {
+                widget.set_halign(gtk4::Align::Center);
+            }
+        }
+    } else {
+        schedule_bar_height_pin(main_box.upcast_ref());
+    }
+
+    Bar {
+        root: main_box.upcast(),
+        slots,
+    }
+}
+
+// On a side bar each word of a label gets a line of its own, so "🔊 45%"
+// shows the icon above the value and the bar stays narrow.
+fn stack_label_words(widget: &gtk4::Widget) {
+    if widget.is::<gtk4::Popover>() {
+        return;
+    }
+    if let Some(label) = widget.downcast_ref::<gtk4::Label>() {
+        label.set_wrap(true);
+        label.set_wrap_mode(gtk4::pango::WrapMode::Word);
+        label.set_max_width_chars(1);
+        label.set_justify(gtk4::Justification::Center);
+    }
+    let mut child = widget.first_child();
+    while let Some(current) = child {
+        stack_label_words(&current);
+        child = current.next_sibling();
+    }
+}
+
+// Pin the height once the font is resolvable, so dynamic content (title
+// length, tray removal) can't resize the bar and shift windows below it.
+fn schedule_bar_height_pin(bar: &gtk4::Widget) {
+    let bar_weak = bar.downgrade();
     glib::idle_add_local_once(move || {
         if let Some(bar) = bar_weak.upgrade() {
             pin_bar_height_to_font(&bar);
         }
     });
+}
+
+// Named widget factories a custom GtkBuilder layout can place. The layout marks
+// a spot with an empty GtkBox whose id is the factory name; the factory's
+// widget is appended to it. "tray" is handled separately because setup_tray_updates
+// needs the tray strip itself.
+type WidgetFactory = fn(&WidgetContext) -> gtk4::Widget;
+
+const WIDGET_FACTORIES: &[(&str, WidgetFactory)] = &[
+    ("workspace", |cx| {
+        create_workspace_widget(cx.model, cx.compositor, &cx.config.workspace).upcast()
+    }),
+    ("split", |cx| create_split_widget(cx.model).upcast()),
+    ("scratchpad", |cx| {
+        create_scratchpad_widget(cx.model, cx.compositor, &cx.config.workspace).upcast()
+    }),
+    ("title", |cx| {
+        create_title_widget(
+            cx.model,
+            Format::new(&cx.config.format),
+            &cx.config.title,
+            &cx.config.title_rewrite,
+        )
+        .root
+        .upcast()
+    }),
+    ("taskbar", |cx| {
+        create_taskbar_widget(cx.taskbar, cx.config.taskbar.all_workspaces).upcast()
+    }),
+    ("displays", |cx| {
+        create_displays_widget(cx.model, &cx.config.displays)
+    }),
+    ("cpufreq", |cx| {
+        create_cpufreq_widget(cx.model, &cx.config.cpufreq).upcast()
+    }),
+    ("gpu", |cx| create_gpu_widget(cx.model).upcast()),
+    ("loadavg", |cx| create_loadavg_widget(cx.model).upcast()),
+    ("bluetooth", |cx| create_bt_widget(cx.model).upcast()),
+    ("volume", |cx| {
+        create_volume_widget(cx.model, cx.audio).upcast()
+    }),
+    ("kbd_backlight", |cx| {
+        create_kbd_backlight_widget(cx.model).upcast()
+    }),
+    ("lock_keys", |cx| create_lock_keys_widget(cx.model).upcast()),
+    ("privacy", |cx| create_privacy_widget(cx.model).upcast()),
+    ("headset", |cx| {
+        create_headset_widget(cx.model, cx.audio).upcast()
+    }),
+    ("style_errors", |cx| {
+        create_style_errors_widget(cx.model).upcast()
+    }),
+    ("units", |cx| create_units_widget(cx.model).upcast()),
+    ("updates", |cx| {
+        create_updates_widget(cx.model, &cx.config.updates).upcast()
+    }),
+    ("clipboard", |cx| {
+        create_clipboard_widget(cx.model, &cx.config.clipboard).upcast()
+    }),
+    ("vpn", |cx| {
+        create_vpn_widget(cx.model, &cx.config.vpn).upcast()
+    }),
+    ("hotspot", |cx| create_hotspot_widget(cx.model).upcast()),
+    ("rfkill", |cx| create_rfkill_widget(cx.model).upcast()),
+    ("bandwidth", |cx| create_bandwidth_widget(cx.model).upcast()),
+    ("network", |cx| create_network_widget(cx.model).upcast()),
+    ("battery", |cx| create_battery_widget(cx.model).upcast()),
+    ("capture", |cx| {
+        create_capture_widget(cx.model, cx.capture, &cx.config.capture).upcast()
+    }),
+    ("pomodoro", |cx| {
+        create_pomodoro_widget(cx.model, cx.pomodoro).upcast()
+    }),
+    ("progress", |cx| create_progress_widget(cx.model).upcast()),
+    ("notifications", |cx| {
+        create_notifications_widget(cx.model).upcast()
+    }),
+    ("clock", |cx| create_time_widget(cx.model, cx.alarms)),
+    ("quick_settings", |cx| {
+        create_quick_settings_widget(cx.model).upcast()
+    }),
+    ("power", |cx| create_power_widget(&cx.config.power).upcast()),
+];
+
+// The built-in widgets `[bar] disabled_widgets` can name.
+pub fn builtin_widget_names() -> impl Iterator<Item = &'static str> {
+    WIDGET_FACTORIES.iter().map(|(name, _)| *name)
+}
+
+// Disable every built-in widget but `names`, for BarBuilder::widget. An empty
+// list leaves the configuration alone.
+pub fn keep_only_widgets(bar: &mut config::BarConfig, names: &[String]) {
+    if names.is_empty() {
+        return;
+    }
+    bar.disabled_widgets.extend(
+        builtin_widget_names()
+            .filter(|builtin| !names.iter().any(|name| name == builtin))
+            .map(str::to_string),
+    );
+}
+
+const TRAY_SLOT: &str = "tray";
+const LAYOUT_ROOT: &str = "bar";
+
+// Build the bar from a user-supplied GtkBuilder file instead of the fixed
+// create_experimental_bar tree. The root must carry id "bar"; every other
+// object is the user's own (nested boxes, frames, CenterBoxes). The model is
+// exposed to the builder as "model", so a layout can also bind its own labels
+// straight to BarModel properties with <lookup name="battery">model</lookup>.
+// Blueprint files work once compiled to .ui with blueprint-compiler.
+fn create_bar_from_builder(path: &Path, cx: &WidgetContext, tray: &gtk4::Box) -> Result<Bar> {
+    info!(path = %path.display(), "Building bar from custom layout");
+
+    // Expressions in the XML name the model's type, which must be registered
+    // before the parser meets it.
+    BarModel::static_type();
+    let builder = gtk4::Builder::new();
+    builder.expose_object("model", cx.model);
+    builder
+        .add_from_file(path)
+        .with_context(|| format!("load layout {}", path.display()))?;
+
+    let bar = builder
+        .object::<gtk4::Widget>(LAYOUT_ROOT)
+        .with_context(|| {
+            format!(
+                "layout {} has no widget with id \"{}\"",
+                path.display(),
+                LAYOUT_ROOT
+            )
+        })?;
+
+    let mut slots = HashMap::new();
+    for (name, factory) in WIDGET_FACTORIES {
+        let Some(slot) = builder.object::<gtk4::Box>(name) else {
+            debug!(slot = name, "Layout does not place this widget");
+            continue;
+        };
+        if cx.config.bar.is_disabled(name) {
+            debug!(widget = name, "Widget disabled in the configuration");
+            continue;
+        }
+        slot.append(&build_widget(name, *factory, cx));
+        slots.insert(name.to_string(), slot);
+    }
+
+    match builder.object::<gtk4::Box>(TRAY_SLOT) {
+        Some(slot) => {
+            slot.append(tray);
+            slots.insert(TRAY_SLOT.to_string(), slot);
+        }
+        None => warn!("Layout has no \"tray\" slot; tray items will not be shown"),
+    }
+
+    for watch in &cx.config.file_watch {
+        if slots.contains_key(&watch.name) || is_reserved_slot(&watch.name) {
+            warn!(
+                name = watch.name,
+                "File watch name is already taken; skipping it"
+            );
+            continue;
+        }
+        let Some(slot) = builder.object::<gtk4::Box>(&watch.name) else {
+            debug!(slot = watch.name, "Layout does not place this file watch");
+            continue;
+        };
+        slot.append(&create_file_watch_widget(watch));
+        slots.insert(watch.name.clone(), slot);
+    }
 
-    (
-        main_box,
-        tray_widget,
-        bt_widget,
-        volume_widget,
-        network_widget,
-        battery_widget,
-        time_widget,
-        workspace_widget,
-        title_widget,
-    )
+    schedule_bar_height_pin(&bar);
+    Ok(Bar { root: bar, slots })
 }
 
 // Multiplier applied to the measured tall-character height when pinning the bar
@@ -345,7 +1153,7 @@ const BAR_HEIGHT_CHAR_MULTIPLIER: f64 = 1.4;
 // move every window below the bar. We measure on the realized widget so the
 // font (and thus its metrics) is actually resolvable, mirroring how the tray
 // sizes its icon to a tall glyph rather than a fixed pixel count.
-fn pin_bar_height_to_font(bar: &gtk4::CenterBox) {
+fn pin_bar_height_to_font(bar: &gtk4::Widget) {
     let ctx = bar.pango_context();
     let layout = gtk4::pango::Layout::new(&ctx);
     if let Some(font) = ctx.font_description() {
@@ -369,2816 +1177,15 @@ fn pin_bar_height_to_font(bar: &gtk4::CenterBox) {
     }
 }
 
-fn argb_to_rgba(width: i32, height: i32, argb: &[u8]) -> Option<Vec<u8>> {
-    if width <= 0 || height <= 0 {
-        warn!(
-            width,
-            height, "Ignoring tray pixmap with invalid dimensions"
-        );
-        return None;
-    }
-
-    let expected_len = match (width as usize)
-        .checked_mul(height as usize)
-        .and_then(|pixels| pixels.checked_mul(4))
-    {
-        Some(expected_len) => expected_len,
-        None => {
-            warn!(width, height, "Tray pixmap dimensions overflowed");
-            return None;
-        }
-    };
-    if argb.len() != expected_len {
-        warn!(
-            width,
-            height,
-            actual = argb.len(),
-            expected_len,
-            "Ignoring malformed tray pixmap"
-        );
-        return None;
-    }
-
-    let mut rgba = Vec::with_capacity(argb.len());
-    for pixel in argb.chunks_exact(4) {
-        rgba.extend_from_slice(&[pixel[1], pixel[2], pixel[3], pixel[0]]);
-    }
-    Some(rgba)
-}
-
-// Fallback only used when the widget's font is not yet resolvable (e.g. before
-// the widget is realized). Once available we size the icon to a tall character
-// of the actual font so it tracks the text rather than a fixed pixel count —
-// that keeps it from forcing the bar taller than the text on any machine,
-// regardless of DPI or configured font size.
-const TRAY_ICON_SIZE_FALLBACK: i32 = 16;
-
-// Height of a tall character for the given image's font, in device pixels.
-// We measure the pixel height of a representative glyph ("0") so the icon
-// occupies about one character cell, matching the text widgets and scaling
-// across machines regardless of DPI or configured font size. This keeps the
-// icon from forcing the bar taller than the surrounding text.
-fn tray_icon_pixel_size(image: &gtk4::Image) -> i32 {
-    let ctx = image.pango_context();
-    let layout = gtk4::pango::Layout::new(&ctx);
-    if let Some(font) = ctx.font_description() {
-        layout.set_font_description(Some(&font));
-    }
-    layout.set_text("0");
-    let (_width, height) = layout.pixel_size();
-    if height > 0 {
-        height.clamp(1, 64)
-    } else {
-        TRAY_ICON_SIZE_FALLBACK
-    }
-}
-
-// A themed icon that has_icon() reports as present can still be impossible to
-// draw: when the matching file needs a gdk-pixbuf loader that is not installed
-// (e.g. an SVG-only icon with no librsvg loader) GTK resolves it to nothing and
-// silently paints "image-missing". Reproduce the decode here so the failure is
-// observable in the logs and callers can fall back instead of showing a broken
-// glyph. Returns None when the icon is drawable, or Some(reason) when it is not.
-fn named_icon_render_error(
-    icon_theme: &gtk4::IconTheme,
-    name: &str,
-    size: i32,
-    scale: i32,
-) -> Option<String> {
-    let paintable = icon_theme.lookup_icon(
-        name,
-        &[],
-        size,
-        scale.max(1),
-        gtk4::TextDirection::None,
-        gtk4::IconLookupFlags::empty(),
-    );
-    let Some(path) = paintable.file().and_then(|file| file.path()) else {
-        return Some("theme entry resolved to no drawable file".to_string());
-    };
-    match gdk::Texture::from_file(&gtk4::gio::File::for_path(&path)) {
-        Ok(_texture) => None,
-        Err(error) => Some(format!("{} failed to decode: {error}", path.display())),
-    }
-}
-
-fn update_tray_image(image: &gtk4::Image, item: &TrayItem) {
-    let icon_size = tray_icon_pixel_size(image);
-    image.set_pixel_size(icon_size);
-
-    let display = image.display();
-    let icon_theme = gtk4::IconTheme::for_display(&display);
-
-    // Some trays (fcitx5, KDE/appindicator apps) ship their icons in a private
-    // directory and advertise it through IconThemePath rather than installing into
-    // a standard XDG theme. Register that directory so has_icon/set_icon_name can
-    // find the named icon; without this those items fall through to image-missing.
-    if !item.icon_theme_path.is_empty() {
-        let theme_path = Path::new(&item.icon_theme_path);
-        let already_searched = icon_theme
-            .search_path()
-            .iter()
-            .any(|existing| existing == theme_path);
-        if !already_searched {
-            debug!(path = item.icon_theme_path, "Adding tray icon theme path");
-            icon_theme.add_search_path(theme_path);
-        }
-    }
-
-    // An absolute path points straight at an icon file on disk.
-    let icon_path = Path::new(&item.icon_name);
-    if !item.icon_name.is_empty() && icon_path.is_absolute() && icon_path.is_file() {
-        image.set_from_file(Some(icon_path));
-        return;
-    }
-    // A named icon is resolved through the theme. set_icon_name (rather than
-    // hand-loading a pixbuf) lets GTK recolor symbolic icons to the widget's CSS
-    // color, which is what keeps fctix's input-keyboard-symbolic visible. Verify
-    // the icon can actually be drawn first: has_icon() only checks the theme
-    // index, so a missing pixbuf loader would otherwise fail silently.
-    if !item.icon_name.is_empty() && icon_theme.has_icon(&item.icon_name) {
-        match named_icon_render_error(
-            &icon_theme,
-            &item.icon_name,
-            icon_size,
-            image.scale_factor(),
-        ) {
-            None => {
-                image.set_icon_name(Some(&item.icon_name));
-                return;
-            }
-            Some(reason) => warn!(
-                item = item.key,
-                icon = item.icon_name,
-                reason,
-                "Named tray icon is in the theme but cannot be drawn (missing gdk-pixbuf loader?); falling back"
-            ),
-        }
-    }
-
-    match &item.icon_pixmap {
-        Some((width, height, argb)) => match argb_to_rgba(*width, *height, argb) {
-            Some(rgba) => {
-                let bytes = glib::Bytes::from_owned(rgba);
-                let texture = gdk::MemoryTexture::new(
-                    *width,
-                    *height,
-                    gdk::MemoryFormat::R8g8b8a8,
-                    &bytes,
-                    (*width as usize) * 4,
-                );
-                image.set_paintable(Some(&texture));
-            }
-            None => image.set_icon_name(Some("image-missing")),
-        },
-        None => {
-            warn!(
-                item = item.key,
-                icon_name = item.icon_name,
-                "Tray item has no usable icon"
-            );
-            image.set_icon_name(Some("image-missing"));
-        }
-    }
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-fn update_tray_button(button: &gtk4::Button, image: &gtk4::Image, item: &TrayItem) {
-    for class in ["active", "passive", "needs-attention"] {
-        button.remove_css_class(class);
+    #[test]
+    fn the_compact_form_is_the_first_word() {
+        assert_eq!(compact_text("🔊 45%"), "🔊");
+        assert_eq!(compact_text("  󰂯 P80 🎧 70%"), "󰂯");
+        assert_eq!(compact_text("12:30"), "12:30");
+        assert_eq!(compact_text(""), "");
     }
-    let status_class = match item.status.as_str() {
-        status if status.eq_ignore_ascii_case("NeedsAttention") => "needs-attention",
-        status if status.eq_ignore_ascii_case("Passive") => "passive",
-        _ => "active",
-    };
-    button.add_css_class(status_class);
-    // The tooltip text is now supplied at query time (see create_tray_entry),
-    // which no longer maintains the accessible description the old
-    // set_tooltip_text call provided as a side effect. Keep it updated here so
-    // screen readers still get the item title on every upsert.
-    let description = if item.title.is_empty() {
-        &item.key
-    } else {
-        &item.title
-    };
-    button.update_property(&[gtk4::accessible::Property::Description(description)]);
-    update_tray_image(image, item);
-}
-
-// One live tray icon: the button in the bar, the image inside it, the latest
-// item state shared with the click handler (so upserts retarget clicks without
-// rebuilding the widget — apps commonly set Menu/ItemIsMenu only after they
-// register), and the popovers of the currently open dropdown menu (tracked so
-// they can be unparented when the menu closes or the item goes away).
-struct TrayEntry {
-    button: gtk4::Button,
-    image: gtk4::Image,
-    state: Rc<RefCell<TrayItem>>,
-    open_menu: Rc<TrayMenuHost>,
-}
-
-// One popover surface shared by every tray item. Changing the selected item
-// replaces the menu contents and moves the anchor rectangle, but keeps this
-// native popup mapped so GTK and the compositor never tear down their grabs
-// during tray-wide navigation.
-struct TrayMenuHost {
-    popover: gtk4::Popover,
-    open: RefCell<Option<OpenTrayMenu>>,
-}
-
-struct OpenTrayMenu {
-    key: String,
-    request_id: u64,
-    keyboard_grab: bool,
-    // Submenus only. The top-level popover lives on TrayMenuHost and persists
-    // across item switches.
-    popovers: Vec<gtk4::Popover>,
-    entries: Vec<OpenTrayMenuEntry>,
-    selected: Option<usize>,
-}
-
-struct OpenTrayMenuEntry {
-    button: gtk4::Button,
-    id: i32,
-    ancestors: Vec<gtk4::Popover>,
-    submenu: Option<gtk4::Popover>,
-}
-
-struct KeyboardGrab {
-    application: glib::object::WeakRef<gtk4::Application>,
-    bar_window: glib::object::WeakRef<gtk4::ApplicationWindow>,
-    monitor: Option<gdk::Monitor>,
-    active_request: Cell<Option<u64>>,
-}
-
-struct KeyboardGrabLease {
-    state: Rc<KeyboardGrab>,
-    request_id: u64,
-    window: gtk4::ApplicationWindow,
-}
-
-impl KeyboardGrab {
-    fn acquire(
-        self: &Rc<Self>,
-        request_id: u64,
-        nav_tx: &mpsc::UnboundedSender<NavCmd>,
-    ) -> Option<KeyboardGrabLease> {
-        let Some(application) = self.application.upgrade() else {
-            warn!(
-                request_id,
-                "Cannot grab the keyboard after the GTK application was dropped"
-            );
-            return None;
-        };
-        let window = gtk4::ApplicationWindow::builder()
-            .application(&application)
-            .default_width(1)
-            .default_height(1)
-            .build();
-        let focus_target = gtk4::Fixed::new();
-        focus_target.set_size_request(1, 1);
-        focus_target.set_focusable(true);
-        window.set_child(Some(&focus_target));
-        // Without this the default titlebar makes the "1x1" helper surface
-        // 200x200 on the overlay layer, parked right where wide tray menus
-        // render their top rows (hyprctl layers, 2026-07-19).
-        window.set_decorated(false);
-        window.init_layer_shell();
-        window.set_namespace(Some("gtk-status-bar-keyboard-grab"));
-        window.set_layer(Layer::Overlay);
-        window.set_monitor(self.monitor.as_ref());
-        window.set_anchor(Edge::Top, true);
-        window.set_anchor(Edge::Right, true);
-        window.set_exclusive_zone(0);
-        window.set_keyboard_mode(KeyboardMode::Exclusive);
-        window.add_controller(nav_key_controller(nav_tx, "keyboard-grab"));
-
-        let monitor = self
-            .monitor
-            .as_ref()
-            .and_then(gdk::Monitor::connector)
-            .unwrap_or_else(|| "compositor-default".into());
-        window.connect_map(move |window| {
-            if let Some(surface) = window.surface() {
-                surface.set_input_region(Some(&gtk4::cairo::Region::create()));
-                // GTK rewrites the input region on resize/style changes, so a
-                // single clear on map is not durable. Re-clear after every
-                // layout so the helper can never swallow pointer input.
-                surface.connect_layout(|surface, _width, _height| {
-                    surface.set_input_region(Some(&gtk4::cairo::Region::create()));
-                });
-            }
-            info!(
-                request_id,
-                active = window.is_active(),
-                keyboard_mode = ?window.keyboard_mode(),
-                layer = ?window.layer(),
-                width = window.width(),
-                height = window.height(),
-                monitor = %monitor,
-                "Tray keyboard helper mapped"
-            );
-        });
-        window.connect_unmap(move |_| {
-            info!(request_id, "Tray keyboard helper unmapped");
-        });
-        window.connect_is_active_notify(move |window| {
-            info!(
-                request_id,
-                active = window.is_active(),
-                "Tray keyboard helper active state changed"
-            );
-        });
-
-        self.active_request.set(Some(request_id));
-        window.present();
-        focus_target.grab_focus();
-        info!(
-            request_id,
-            helper_active = window.is_active(),
-            bar_active = self
-                .bar_window
-                .upgrade()
-                .is_some_and(|window| window.is_active()),
-            "Tray menu requested exclusive keyboard focus"
-        );
-        Some(KeyboardGrabLease {
-            state: self.clone(),
-            request_id,
-            window,
-        })
-    }
-
-    fn release(&self, request_id: u64) {
-        if self.active_request.get() != Some(request_id) {
-            return;
-        }
-        self.active_request.set(None);
-        info!(request_id, "Tray menu released keyboard focus");
-    }
-}
-
-impl Drop for KeyboardGrabLease {
-    fn drop(&mut self) {
-        self.window.set_keyboard_mode(KeyboardMode::None);
-        self.window.close();
-        self.state.release(self.request_id);
-    }
-}
-
-impl KeyboardGrabLease {
-    fn focus_state(&self) -> (bool, bool) {
-        let helper_active = self.window.is_active();
-        let bar_active = self
-            .state
-            .bar_window
-            .upgrade()
-            .is_some_and(|window| window.is_active());
-        (helper_active, bar_active)
-    }
-}
-
-// One directional intent from the keyboard while the tray grab is active. The
-// key controllers stay dumb: they translate a keypress into one of these and
-// send it to the tray task, which owns the whole navigation state machine (so
-// socket verbs and native keys can never disagree about where we are).
-#[derive(Debug)]
-enum NavCmd {
-    Left,     // h / Left
-    Right,    // l / Right
-    Down,     // j / Down
-    Up,       // k / Up
-    First,    // gg / Home
-    Last,     // G / End
-    Activate, // Enter
-    Escape,   // q / Esc (two-step: deeper -> level 0, level 0 -> release)
-    UserClosed { request_id: u64 },
-    MenuTimeout { request_id: u64 },
-    MouseAction(TrayCommand),
-    // The fused popover surface unmapped. Sent on every unmap (unlike
-    // UserClosed, which is nav-only) so the loop can restore tray tooltips
-    // after click-outside dismissal, which otherwise never reaches it.
-    MenuSurfaceUnmapped,
-}
-
-// The live keyboard-navigation session. Present only while the grab is held.
-// `key` identifies the pre-selected tray icon (level 0); `in_menu` is false at
-// level 0 (scrolling icons, dropdown open but no entry selected) and true once
-// we have descended into the open dropdown. Dropping `lease` releases the
-// keyboard.
-struct NavActive {
-    lease: KeyboardGrabLease,
-    key: String,
-    menu_request_id: u64,
-    in_menu: bool,
-}
-
-struct MenuBuildContext<'a> {
-    command_tx: &'a mpsc::UnboundedSender<TrayCommand>,
-    menu: &'a TrayMenu,
-    top_popover: &'a glib::object::WeakRef<gtk4::Popover>,
-    popovers: &'a mut Vec<gtk4::Popover>,
-    entries: &'a mut Vec<OpenTrayMenuEntry>,
-}
-
-fn ipc_item(index: usize, item: &TrayItem) -> IpcTrayItem {
-    IpcTrayItem {
-        index,
-        key: item.key.clone(),
-        title: item.title.clone(),
-        status: item.status.clone(),
-        item_is_menu: item.item_is_menu,
-    }
-}
-
-fn resolve_ipc_target<'a>(
-    target: &str,
-    order: &'a [String],
-    entries: &'a HashMap<String, TrayEntry>,
-) -> std::result::Result<(usize, &'a TrayEntry), String> {
-    if let Some(entry) = entries.get(target) {
-        let index = order
-            .iter()
-            .position(|key| key == target)
-            .ok_or_else(|| format!("tray item {target:?} is missing from the display order"))?;
-        return Ok((index, entry));
-    }
-
-    let mut matches = order.iter().enumerate().filter_map(|(index, key)| {
-        let entry = entries.get(key)?;
-        (entry.state.borrow().title == target).then_some((index, entry))
-    });
-    if let Some(found) = matches.next() {
-        if matches.next().is_some() {
-            return Err(format!(
-                "more than one tray item is titled {target:?}; use its index or key"
-            ));
-        }
-        return Ok(found);
-    }
-
-    let index = target
-        .parse::<usize>()
-        .map_err(|_| format!("no tray item matches {target:?}"))?;
-    let key = order
-        .get(index)
-        .ok_or_else(|| format!("tray item index {index} is out of range"))?;
-    let entry = entries
-        .get(key)
-        .ok_or_else(|| format!("tray item index {index} is no longer available"))?;
-    Ok((index, entry))
-}
-
-fn tray_button_coordinates(button: &gtk4::Button) -> (i32, i32) {
-    let x = f64::from(button.width()) / 2.0;
-    let y = f64::from(button.height()) / 2.0;
-    let Some(root) = button.root() else {
-        return (x.round() as i32, y.round() as i32);
-    };
-    let Ok(window) = root.downcast::<gtk4::Window>() else {
-        return (x.round() as i32, y.round() as i32);
-    };
-    let point = gtk4::graphene::Point::new(x as f32, y as f32);
-    button
-        .compute_point(&window, &point)
-        .map(|point| (point.x().round() as i32, point.y().round() as i32))
-        .unwrap_or((x.round() as i32, y.round() as i32))
-}
-
-fn next_menu_request(sequence: &Cell<u64>) -> u64 {
-    let next = sequence.get().wrapping_add(1);
-    sequence.set(next);
-    next
-}
-
-fn select_menu_index(menu: &mut OpenTrayMenu, selected: usize) -> i32 {
-    if let Some(previous) = menu.selected {
-        menu.entries[previous].button.remove_css_class("selected");
-    }
-    let selected_entry = &menu.entries[selected];
-    for popover in &menu.popovers {
-        if !selected_entry
-            .ancestors
-            .iter()
-            .any(|ancestor| ancestor == popover)
-        {
-            popover.popdown();
-        }
-    }
-    for popover in &selected_entry.ancestors {
-        popover.popup();
-    }
-    selected_entry.button.add_css_class("selected");
-    let id = selected_entry.id;
-    menu.selected = Some(selected);
-    id
-}
-
-fn entries_in_scope(menu: &OpenTrayMenu) -> Vec<usize> {
-    let scope = menu
-        .selected
-        .map(|selected| menu.entries[selected].ancestors.as_slice())
-        .unwrap_or_default();
-    menu.entries
-        .iter()
-        .enumerate()
-        .filter_map(|(index, entry)| (entry.ancestors.as_slice() == scope).then_some(index))
-        .collect()
-}
-
-fn select_menu_entry(
-    open_menu: &TrayMenuHost,
-    direction: isize,
-) -> std::result::Result<i32, String> {
-    let mut open_menu = open_menu.open.borrow_mut();
-    let menu = open_menu
-        .as_mut()
-        .ok_or_else(|| "tray item has no open menu".to_string())?;
-    if menu.entries.is_empty() {
-        return Err("open tray menu has no enabled entries".to_string());
-    }
-    let candidates = entries_in_scope(menu);
-    if candidates.is_empty() {
-        // entries_in_scope can be empty while `entries` is not: e.g. the current
-        // scope is the top level but every enabled entry lives inside a submenu.
-        // Guard before indexing/rem_euclid so a keypress can't panic the bar.
-        return Err("open tray menu has no enabled entries in the current scope".to_string());
-    }
-    let len = candidates.len() as isize;
-    let candidate = match menu.selected.and_then(|selected| {
-        candidates
-            .iter()
-            .position(|candidate| *candidate == selected)
-    }) {
-        Some(selected) => (selected as isize + direction).rem_euclid(len) as usize,
-        None if direction < 0 => candidates.len() - 1,
-        None => 0,
-    };
-    Ok(select_menu_index(menu, candidates[candidate]))
-}
-
-fn jump_menu_entry(open_menu: &TrayMenuHost, last: bool) -> std::result::Result<i32, String> {
-    let mut open_menu = open_menu.open.borrow_mut();
-    let menu = open_menu
-        .as_mut()
-        .ok_or_else(|| "tray item has no open menu".to_string())?;
-    let candidates = entries_in_scope(menu);
-    let selected = if last {
-        candidates.last().copied()
-    } else {
-        candidates.first().copied()
-    }
-    .ok_or_else(|| "open tray menu has no enabled entries".to_string())?;
-    Ok(select_menu_index(menu, selected))
-}
-
-fn enter_submenu(open_menu: &TrayMenuHost) -> std::result::Result<bool, String> {
-    let mut open_menu = open_menu.open.borrow_mut();
-    let menu = open_menu
-        .as_mut()
-        .ok_or_else(|| "tray item has no open menu".to_string())?;
-    let Some(selected) = menu.selected else {
-        return Ok(false);
-    };
-    let Some(submenu) = menu.entries[selected].submenu.clone() else {
-        return Ok(false);
-    };
-    let child = menu
-        .entries
-        .iter()
-        .position(|candidate| candidate.ancestors.last() == Some(&submenu))
-        .ok_or_else(|| "open tray submenu has no enabled entries".to_string())?;
-    submenu.popup();
-    select_menu_index(menu, child);
-    Ok(true)
-}
-
-fn leave_submenu(open_menu: &TrayMenuHost) -> std::result::Result<bool, String> {
-    let mut open_menu = open_menu.open.borrow_mut();
-    let menu = open_menu
-        .as_mut()
-        .ok_or_else(|| "tray item has no open menu".to_string())?;
-    let Some(selected) = menu.selected else {
-        return Ok(false);
-    };
-    let Some(submenu) = menu.entries[selected].ancestors.last().cloned() else {
-        return Ok(false);
-    };
-    let parent = menu
-        .entries
-        .iter()
-        .position(|candidate| candidate.submenu.as_ref() == Some(&submenu))
-        .ok_or_else(|| "open tray submenu has no parent entry".to_string())?;
-    submenu.popdown();
-    select_menu_index(menu, parent);
-    Ok(true)
-}
-
-fn menu_entry_id(
-    open_menu: &TrayMenuHost,
-    requested: Option<i32>,
-) -> std::result::Result<i32, String> {
-    let open_menu = open_menu.open.borrow();
-    let menu = open_menu
-        .as_ref()
-        .ok_or_else(|| "tray item has no open menu".to_string())?;
-    match requested {
-        Some(id) if menu.entries.iter().any(|entry| entry.id == id) => Ok(id),
-        Some(id) => Err(format!("open tray menu has no enabled entry {id}")),
-        None => menu
-            .selected
-            .map(|selected| menu.entries[selected].id)
-            .ok_or_else(|| "open tray menu has no selected entry".to_string()),
-    }
-}
-
-fn handle_ipc_request(
-    request: IpcRequest,
-    order: &[String],
-    entries: &HashMap<String, TrayEntry>,
-    open_menu: &TrayMenuHost,
-    commands: &mpsc::UnboundedSender<TrayCommand>,
-    menu_requests: &Cell<u64>,
-) -> IpcResponse {
-    let (target, action) = match &request {
-        IpcRequest::List => {
-            let items = order
-                .iter()
-                .enumerate()
-                .filter_map(|(index, key)| {
-                    entries
-                        .get(key)
-                        .map(|entry| ipc_item(index, &entry.state.borrow()))
-                })
-                .collect();
-            return IpcResponse::success(items);
-        }
-        IpcRequest::CloseMenus => {
-            next_menu_request(menu_requests);
-            close_tray_menu(open_menu);
-            return IpcResponse::success(Vec::new());
-        }
-        // Open needs the live nav state (grab, container), so the select loop
-        // handles it directly; it never reaches this stateless helper.
-        IpcRequest::Open => {
-            return IpcResponse::error("open is handled by the nav session directly");
-        }
-        IpcRequest::Activate { target } => (target.clone(), None),
-        IpcRequest::SecondaryActivate { target } => {
-            (target.clone(), Some(TrayAction::SecondaryActivate))
-        }
-        IpcRequest::ContextMenu { target } | IpcRequest::KeyboardMenu { target } => {
-            (target.clone(), None)
-        }
-        IpcRequest::MenuNext { target } => (target.clone(), None),
-        IpcRequest::MenuPrevious { target } => (target.clone(), None),
-        IpcRequest::MenuActivate { target } => (target.clone(), None),
-        IpcRequest::MenuClick { target, entry } => {
-            (target.clone(), Some(TrayAction::MenuEvent(*entry)))
-        }
-    };
-    let (index, entry) = match resolve_ipc_target(&target, order, entries) {
-        Ok(found) => found,
-        Err(error) => return IpcResponse::error(error),
-    };
-    let item = entry.state.borrow();
-    let selected = ipc_item(index, &item);
-    if matches!(
-        &request,
-        IpcRequest::MenuNext { .. }
-            | IpcRequest::MenuPrevious { .. }
-            | IpcRequest::MenuActivate { .. }
-            | IpcRequest::MenuClick { .. }
-    ) {
-        let open_key = open_menu
-            .open
-            .borrow()
-            .as_ref()
-            .map(|menu| menu.key.clone());
-        if open_key.as_deref() != Some(item.key.as_str()) {
-            return IpcResponse::error(format!(
-                "tray item {:?} does not own the open menu",
-                item.key
-            ));
-        }
-    }
-    let action = match request {
-        IpcRequest::Activate { .. } if item.item_is_menu => TrayAction::ContextMenu {
-            request_id: next_menu_request(menu_requests),
-            keyboard_grab: false,
-        },
-        IpcRequest::Activate { .. } => TrayAction::Activate,
-        IpcRequest::MenuNext { .. } => match select_menu_entry(open_menu, 1) {
-            Ok(_id) => return IpcResponse::success(vec![selected]),
-            Err(error) => return IpcResponse::error(error),
-        },
-        IpcRequest::MenuPrevious { .. } => match select_menu_entry(open_menu, -1) {
-            Ok(_id) => return IpcResponse::success(vec![selected]),
-            Err(error) => return IpcResponse::error(error),
-        },
-        IpcRequest::MenuActivate { .. } => {
-            match enter_submenu(open_menu) {
-                Ok(true) => return IpcResponse::success(vec![selected]),
-                Ok(false) => {}
-                Err(error) => return IpcResponse::error(error),
-            }
-            match menu_entry_id(open_menu, None) {
-                Ok(id) => TrayAction::MenuEvent(id),
-                Err(error) => return IpcResponse::error(error),
-            }
-        }
-        IpcRequest::MenuClick { entry: id, .. } => match menu_entry_id(open_menu, Some(id)) {
-            Ok(id) => TrayAction::MenuEvent(id),
-            Err(error) => return IpcResponse::error(error),
-        },
-        IpcRequest::ContextMenu { .. } => TrayAction::ContextMenu {
-            request_id: next_menu_request(menu_requests),
-            keyboard_grab: false,
-        },
-        IpcRequest::KeyboardMenu { .. } => TrayAction::ContextMenu {
-            request_id: next_menu_request(menu_requests),
-            keyboard_grab: true,
-        },
-        IpcRequest::SecondaryActivate { .. } => {
-            let Some(action) = action else {
-                return IpcResponse::error("tray action was not resolved");
-            };
-            action
-        }
-        IpcRequest::List | IpcRequest::CloseMenus | IpcRequest::Open => {
-            return IpcResponse::error("request was handled before target resolution");
-        }
-    };
-    let (x, y) = tray_button_coordinates(&entry.button);
-    let command = TrayCommand {
-        key: item.key.clone(),
-        action,
-        x,
-        y,
-        menu_path: item.menu_path.clone(),
-    };
-    if let Err(error) = commands.send(command) {
-        return IpcResponse::error(format!("tray backend is not available: {error}"));
-    }
-    if matches!(action, TrayAction::MenuEvent(_)) {
-        drop(item);
-        close_tray_menu(open_menu);
-    }
-    IpcResponse::success(vec![selected])
-}
-
-fn ipc_request_targets_nav(
-    request: &IpcRequest,
-    nav: &Option<NavActive>,
-    order: &[String],
-    entries: &HashMap<String, TrayEntry>,
-) -> bool {
-    let Some(active) = nav.as_ref() else {
-        return false;
-    };
-    let target = match request {
-        IpcRequest::Activate { target }
-        | IpcRequest::SecondaryActivate { target }
-        | IpcRequest::ContextMenu { target }
-        | IpcRequest::KeyboardMenu { target }
-        | IpcRequest::MenuNext { target }
-        | IpcRequest::MenuPrevious { target }
-        | IpcRequest::MenuActivate { target }
-        | IpcRequest::MenuClick { target, .. } => target,
-        IpcRequest::List | IpcRequest::CloseMenus | IpcRequest::Open => return false,
-    };
-    resolve_ipc_target(target, order, entries)
-        .is_ok_and(|(_, entry)| entry.state.borrow().key == active.key)
-}
-
-fn create_tray_entry(
-    item: TrayItem,
-    menu_requests: &Rc<Cell<u64>>,
-    nav_tx: &mpsc::UnboundedSender<NavCmd>,
-    open_menu: &Rc<TrayMenuHost>,
-    keyboard_grab: &Rc<KeyboardGrab>,
-) -> TrayEntry {
-    let button = gtk4::Button::new();
-    button.add_css_class("tray-item");
-    button.set_focusable(false);
-
-    let image = gtk4::Image::new();
-    button.set_child(Some(&image));
-    update_tray_button(&button, &image, &item);
-
-    let state = Rc::new(RefCell::new(item));
-
-    // Native tooltips must never map while a tray dropdown (or the nav
-    // keyboard grab) is up: the dropdown is a grabbing xdg_popup, a tooltip
-    // would be a second popup on the same parent, Hyprland never configures
-    // that illegal sibling, and gdk_wayland_surface_present_popup blocks the
-    // whole GTK main loop inside wl_display_dispatch_queue waiting for it
-    // (confirmed twice by backtrace on the live desktop, 2026-07-19). Answer
-    // the tooltip query dynamically instead of setting static text so the
-    // tooltip simply declines to exist whenever a menu could be holding the
-    // grab, and reappears on the next hover once everything is closed. This
-    // query gate is the backstop; the durable switch is has-tooltip itself,
-    // toggled around the grab lifetime by set_tray_tooltips_enabled, because
-    // GTK's reposition path can remap a visible tooltip without re-querying.
-    button.set_has_tooltip(
-        open_menu.open.borrow().is_none() && keyboard_grab.active_request.get().is_none(),
-    );
-    let state_for_tooltip = state.clone();
-    let open_menu_for_tooltip = Rc::downgrade(open_menu);
-    let keyboard_grab_for_tooltip = Rc::downgrade(keyboard_grab);
-    button.connect_query_tooltip(move |_button, _x, _y, _keyboard_mode, tooltip| {
-        let menu_open = open_menu_for_tooltip
-            .upgrade()
-            .is_some_and(|open_menu| open_menu.open.borrow().is_some());
-        let grab_active = keyboard_grab_for_tooltip
-            .upgrade()
-            .is_some_and(|grab| grab.active_request.get().is_some());
-        if menu_open || grab_active {
-            trace!(
-                menu_open,
-                grab_active, "Declining tray tooltip while a menu is up"
-            );
-            return false;
-        }
-        let item = state_for_tooltip.borrow();
-        tooltip.set_text(Some(if item.title.is_empty() {
-            &item.key
-        } else {
-            &item.title
-        }));
-        true
-    });
-
-    let gesture = gtk4::GestureClick::new();
-    gesture.set_button(0);
-    // Run in the capture phase and claim the sequence so the button's own
-    // internal GestureClick does not swallow the release — without this our
-    // `released` handler never fires and clicks do nothing.
-    gesture.set_propagation_phase(gtk4::PropagationPhase::Capture);
-    let state_for_press = state.clone();
-    gesture.connect_pressed(move |gesture_pressed, _press_count, x, y| {
-        let item = state_for_press.borrow();
-        info!(
-            item = item.key,
-            button = gesture_pressed.current_button(),
-            x,
-            y,
-            "Tray button pressed"
-        );
-        gesture_pressed.set_state(gtk4::EventSequenceState::Claimed);
-    });
-    let nav_tx = nav_tx.clone();
-    let menu_requests = menu_requests.clone();
-    let state_for_release = state.clone();
-    // Weak: the gesture lives on the button, so capturing the button strongly
-    // here would cycle (button -> gesture -> closure -> button) and leak it
-    // once the item is removed from the bar.
-    let button_weak = button.downgrade();
-    gesture.connect_released(move |gesture, _press_count, x, y| {
-        let (key, item_is_menu, menu_path) = {
-            let item = state_for_release.borrow();
-            (item.key.clone(), item.item_is_menu, item.menu_path.clone())
-        };
-        info!(
-            item = key,
-            button = gesture.current_button(),
-            x,
-            y,
-            item_is_menu,
-            "Tray button released"
-        );
-        let action = match gesture.current_button() {
-            1 if item_is_menu => TrayAction::ContextMenu {
-                request_id: next_menu_request(&menu_requests),
-                keyboard_grab: false,
-            },
-            1 => TrayAction::Activate,
-            2 => TrayAction::SecondaryActivate,
-            3 => TrayAction::ContextMenu {
-                request_id: next_menu_request(&menu_requests),
-                keyboard_grab: false,
-            },
-            button => {
-                debug!(button, item = key, "Ignoring unsupported tray mouse button");
-                return;
-            }
-        };
-        let Some(button) = button_weak.upgrade() else {
-            debug!(item = key, "Tray button was dropped before click handling");
-            return;
-        };
-        let (x, y) = match button.root() {
-            Some(root) => match root.downcast::<gtk4::Window>() {
-                Ok(root_window) => {
-                    let point = gtk4::graphene::Point::new(x as f32, y as f32);
-                    match button.compute_point(&root_window, &point) {
-                        Some(point) => (point.x().round() as i32, point.y().round() as i32),
-                        None => {
-                            debug!(
-                                item = key,
-                                "Could not translate tray click to bar coordinates"
-                            );
-                            (x.round() as i32, y.round() as i32)
-                        }
-                    }
-                }
-                Err(_root) => {
-                    debug!(item = key, "Tray root is not a GTK window");
-                    (x.round() as i32, y.round() as i32)
-                }
-            },
-            None => {
-                debug!(item = key, "Tray button has no root during click");
-                (x.round() as i32, y.round() as i32)
-            }
-        };
-        let command = TrayCommand {
-            key: key.clone(),
-            action,
-            x,
-            y,
-            menu_path,
-        };
-        if let Err(error) = nav_tx.send(NavCmd::MouseAction(command)) {
-            warn!(item = key, %error, "Could not queue tray click for D-Bus backend");
-        }
-    });
-    button.add_controller(gesture);
-
-    TrayEntry {
-        button,
-        image,
-        state,
-        open_menu: open_menu.clone(),
-    }
-}
-
-pub fn setup_tray_updates(
-    TrayUi {
-        mut updates,
-        commands,
-        mut menus,
-    }: TrayUi,
-    mut ipc_requests: mpsc::UnboundedReceiver<IpcUiRequest>,
-    container: gtk4::Box,
-    window: &gtk4::ApplicationWindow,
-) {
-    debug!("Setting up system tray updates");
-
-    let menu_requests = Rc::new(Cell::new(0));
-    let application = window
-        .application()
-        .expect("the status bar window must belong to its GTK application");
-    let keyboard_grab = Rc::new(KeyboardGrab {
-        application: application.downgrade(),
-        bar_window: window.downgrade(),
-        monitor: window.monitor(),
-        active_request: Cell::new(None),
-    });
-    // Key controllers on the open dropdowns forward NavCmd here; the task owns
-    // the navigation state so socket verbs and native keys share one truth.
-    let (nav_tx, mut nav_rx) = mpsc::unbounded_channel::<NavCmd>();
-    let popover = gtk4::Popover::new();
-    popover.set_parent(&container);
-    popover.set_position(gtk4::PositionType::Bottom);
-    popover.set_has_arrow(false);
-    popover.add_controller(nav_key_controller(&nav_tx, "fused-tray-menu"));
-    let open_menu = Rc::new(TrayMenuHost {
-        popover,
-        open: RefCell::new(None),
-    });
-    let open_menu_weak = Rc::downgrade(&open_menu);
-    open_menu.popover.connect_map(move |_| {
-        let Some(open_menu) = open_menu_weak.upgrade() else {
-            return;
-        };
-        let open = open_menu.open.borrow();
-        match open.as_ref() {
-            Some(menu) => info!(
-                item = menu.key,
-                request_id = menu.request_id,
-                keyboard_grab = menu.keyboard_grab,
-                "Fused tray menu surface mapped"
-            ),
-            None => warn!("Fused tray menu surface mapped without menu state"),
-        }
-    });
-    let open_menu_weak = Rc::downgrade(&open_menu);
-    let nav_tx_unmapped = nav_tx.clone();
-    open_menu.popover.connect_unmap(move |_| {
-        let Some(open_menu) = open_menu_weak.upgrade() else {
-            return;
-        };
-        let open = open_menu.open.borrow();
-        let state = open
-            .as_ref()
-            .map(|menu| (menu.key.clone(), menu.request_id, menu.keyboard_grab));
-        drop(open);
-        match &state {
-            Some((item, request_id, keyboard_grab)) => info!(
-                item,
-                request_id, keyboard_grab, "Fused tray menu surface unmapped"
-            ),
-            None => info!("Fused tray menu surface unmapped without menu state"),
-        }
-        if let Some((_, request_id, true)) = state {
-            let _ = nav_tx_unmapped.send(NavCmd::UserClosed { request_id });
-        }
-        let _ = nav_tx_unmapped.send(NavCmd::MenuSurfaceUnmapped);
-    });
-    let open_menu_weak = Rc::downgrade(&open_menu);
-    let nav_tx_closed = nav_tx.clone();
-    open_menu.popover.connect_closed(move |_popover| {
-        let Some(open_menu) = open_menu_weak.upgrade() else {
-            return;
-        };
-        let Some(closed) = open_menu.open.borrow_mut().take() else {
-            // Programmatic close paths take the state before popdown, so only
-            // genuine user dismissal reaches this branch with a live menu.
-            return;
-        };
-        if closed.keyboard_grab {
-            let _ = nav_tx_closed.send(NavCmd::UserClosed {
-                request_id: closed.request_id,
-            });
-        }
-        glib::idle_add_local_once(move || teardown_tray_submenus(closed));
-    });
-
-    glib::spawn_future_local(async move {
-        let mut entries: HashMap<String, TrayEntry> = HashMap::new();
-        let mut order = Vec::new();
-        let mut nav: Option<NavActive> = None;
-        // The icon the most recent nav session ended on. Runtime-only, so a bar
-        // restart forgets it; `open` resumes here when the item still exists.
-        let mut last_nav_key: Option<String> = None;
-        let mut ipc_available = true;
-        loop {
-            tokio::select! {
-                update = updates.recv() => {
-                    let Some(update) = update else { break };
-                    match update {
-                        TrayUpdate::Upsert(item) => match entries.get(&item.key) {
-                            Some(entry) => {
-                                update_tray_button(&entry.button, &entry.image, &item);
-                                *entry.state.borrow_mut() = item;
-                                if open_menu.open.borrow().is_none()
-                                    && keyboard_grab.active_request.get().is_none()
-                                {
-                                    entry.button.trigger_tooltip_query();
-                                }
-                            }
-                            None => {
-                                let key = item.key.clone();
-                                let entry = create_tray_entry(
-                                    item,
-                                    &menu_requests,
-                                    &nav_tx,
-                                    &open_menu,
-                                    &keyboard_grab,
-                                );
-                                container.append(&entry.button);
-                                entries.insert(key.clone(), entry);
-                                order.push(key.clone());
-                                container.set_visible(true);
-                                info!(item = key, "Added system tray item");
-                            }
-                        },
-                        TrayUpdate::Remove(key) => match entries.remove(&key) {
-                            Some(entry) => {
-                                let removed_index = order.iter().position(|item_key| item_key == &key);
-                                let removed_active_item = nav
-                                    .as_ref()
-                                    .is_some_and(|active| active.key == key);
-                                // The shared surface stays attached to the tray,
-                                // but its per-item submenu widgets must be torn
-                                // down before this button is removed.
-                                let removed_menu_is_open = open_menu
-                                    .open
-                                    .borrow()
-                                    .as_ref()
-                                    .is_some_and(|menu| menu.key == key);
-                                if removed_menu_is_open {
-                                    close_tray_menu(&open_menu);
-                                }
-                                container.remove(&entry.button);
-                                order.retain(|item_key| item_key != &key);
-                                container.set_visible(!entries.is_empty());
-                                info!(item = key, "Removed system tray item");
-
-                                if removed_active_item {
-                                    let Some(index) = removed_index
-                                        .map(|index| index.min(order.len().saturating_sub(1)))
-                                        .filter(|_| !order.is_empty())
-                                    else {
-                                        end_nav(&mut nav, &mut last_nav_key, &container, &entries, &open_menu, &menu_requests);
-                                        continue;
-                                    };
-                                    if let Err(error) = start_nav(
-                                        &mut nav,
-                                        index,
-                                        &keyboard_grab,
-                                        &nav_tx,
-                                        &container,
-                                        &entries,
-                                        &order,
-                                        &commands,
-                                        &menu_requests,
-                                    ) {
-                                        warn!(%error, "Ending tray navigation after item removal");
-                                        end_nav(&mut nav, &mut last_nav_key, &container, &entries, &open_menu, &menu_requests);
-                                    }
-                                }
-                            }
-                            None => debug!(item = key, "Ignoring removal of unknown tray item"),
-                        },
-                    }
-                }
-                menu = menus.recv() => {
-                    // The supervised backend holds menu_tx for the process
-                    // lifetime, so None means teardown, same as the update arm
-                    // (a `continue` here would busy-spin the select loop).
-                    let Some(menu) = menu else { break };
-                    if menu.request_id != menu_requests.get() {
-                        debug!(
-                            item = menu.key,
-                            request_id = menu.request_id,
-                            current_request_id = menu_requests.get(),
-                            "Ignoring canceled or superseded tray menu"
-                        );
-                        continue;
-                    }
-                    match entries.get(&menu.key) {
-                        Some(entry) => {
-                            set_tray_tooltips_enabled(&entries, false);
-                            show_tray_menu(
-                                entry,
-                                &container,
-                                &menu,
-                                &commands,
-                                &nav_tx,
-                            );
-                        }
-                        None => debug!(item = menu.key, "Ignoring menu for unknown tray item"),
-                    }
-                }
-                nav_cmd = nav_rx.recv() => {
-                    // nav_tx lives for the whole task, so recv() only ends at
-                    // teardown (same semantics as the arms above).
-                    let Some(nav_cmd) = nav_cmd else { break };
-                    handle_nav(
-                        nav_cmd,
-                        &mut nav,
-                        &mut last_nav_key,
-                        &nav_tx,
-                        &order,
-                        &entries,
-                        &container,
-                        &open_menu,
-                        &commands,
-                        &menu_requests,
-                    );
-                }
-                request = ipc_requests.recv(), if ipc_available => {
-                    let Some(request) = request else {
-                        ipc_available = false;
-                        debug!("Tray IPC UI channel closed; tray updates remain active");
-                        continue;
-                    };
-                    if request.response.is_closed() {
-                        debug!("Skipping tray IPC request after its client timed out");
-                        continue;
-                    }
-                    let response = match request.request {
-                        // keyboard-menu starts (or retargets) a nav session at
-                        // level 0 rather than opening one throwaway grab menu.
-                        IpcRequest::KeyboardMenu { target } => {
-                            match resolve_ipc_target(&target, &order, &entries) {
-                                Ok((index, entry)) => {
-                                    let selected = ipc_item(index, &entry.state.borrow());
-                                    start_nav(
-                                        &mut nav,
-                                        index,
-                                        &keyboard_grab,
-                                        &nav_tx,
-                                        &container,
-                                        &entries,
-                                        &order,
-                                        &commands,
-                                        &menu_requests,
-                                    )
-                                    .map_or_else(
-                                        IpcResponse::error,
-                                        |()| IpcResponse::success(vec![selected]),
-                                    )
-                                }
-                                Err(error) => IpcResponse::error(error),
-                            }
-                        }
-                        // open is keyboard-menu with no target: resume on the
-                        // icon the last session ended on, else the first icon.
-                        // Always tear down first: this command is the recovery
-                        // boundary for a stale menu or lost compositor grab.
-                        IpcRequest::Open => {
-                            if let Some(active) = nav.as_ref() {
-                                let (helper_active, bar_active) = active.lease.focus_state();
-                                info!(
-                                    item = active.key,
-                                    request_id = active.menu_request_id,
-                                    helper_active,
-                                    bar_active,
-                                    "Tray open resetting existing navigation session"
-                                );
-                            }
-                            end_nav(
-                                &mut nav,
-                                &mut last_nav_key,
-                                &container,
-                                &entries,
-                                &open_menu,
-                                &menu_requests,
-                            );
-                            let index = last_nav_key
-                                .as_ref()
-                                .and_then(|key| {
-                                    order.iter().position(|candidate| candidate == key)
-                                })
-                                .unwrap_or(0);
-                            match order.get(index).and_then(|key| entries.get(key)) {
-                                Some(entry) => {
-                                    let selected = ipc_item(index, &entry.state.borrow());
-                                    start_nav(
-                                        &mut nav,
-                                        index,
-                                        &keyboard_grab,
-                                        &nav_tx,
-                                        &container,
-                                        &entries,
-                                        &order,
-                                        &commands,
-                                        &menu_requests,
-                                    )
-                                    .map_or_else(
-                                        IpcResponse::error,
-                                        |()| IpcResponse::success(vec![selected]),
-                                    )
-                                }
-                                None => IpcResponse::error("the tray is empty"),
-                            }
-                        }
-                        // close-menus also releases the grab, so it is a
-                        // reliable kill-switch for a stuck nav session.
-                        IpcRequest::CloseMenus => {
-                            end_nav(&mut nav, &mut last_nav_key, &container, &entries, &open_menu, &menu_requests);
-                            handle_ipc_request(
-                                IpcRequest::CloseMenus,
-                                &order,
-                                &entries,
-                                &open_menu,
-                                &commands,
-                                &menu_requests,
-                            )
-                        }
-                        other => {
-                            let targets_nav = ipc_request_targets_nav(
-                                &other,
-                                &nav,
-                                &order,
-                                &entries,
-                            );
-                            let ends_nav = matches!(
-                                &other,
-                                IpcRequest::Activate { .. }
-                                    | IpcRequest::SecondaryActivate { .. }
-                                    | IpcRequest::ContextMenu { .. }
-                                    | IpcRequest::MenuClick { .. }
-                            );
-                            let activates_menu =
-                                matches!(&other, IpcRequest::MenuActivate { .. });
-                            let enters_menu = matches!(
-                                &other,
-                                IpcRequest::MenuNext { .. } | IpcRequest::MenuPrevious { .. }
-                            );
-                            let response = handle_ipc_request(
-                                other,
-                                &order,
-                                &entries,
-                                &open_menu,
-                                &commands,
-                                &menu_requests,
-                            );
-                            if response.ok && ends_nav {
-                                end_nav(&mut nav, &mut last_nav_key, &container, &entries, &open_menu, &menu_requests);
-                            } else if response.ok && targets_nav && enters_menu {
-                                set_nav_in_menu(&mut nav, &container, true);
-                            } else if response.ok && targets_nav && activates_menu {
-                                let menu_still_open = nav.as_ref().is_some_and(|active| {
-                                    open_menu
-                                        .open
-                                        .borrow()
-                                        .as_ref()
-                                        .is_some_and(|menu| menu.key == active.key)
-                                });
-                                if menu_still_open {
-                                    set_nav_in_menu(&mut nav, &container, true);
-                                } else {
-                                    end_nav(&mut nav, &mut last_nav_key, &container, &entries, &open_menu, &menu_requests);
-                                }
-                            }
-                            response
-                        }
-                    };
-                    if request.response.send(response).is_err() {
-                        debug!("Tray IPC client disconnected before receiving its response");
-                    }
-                }
-            }
-        }
-        debug!("System tray UI update channel closed");
-    });
-}
-
-fn teardown_tray_submenus(menu: OpenTrayMenu) {
-    for popover in menu.popovers {
-        popover.popdown();
-        popover.unparent();
-    }
-}
-
-// Close the shared top-level surface without unparenting it. Submenus are
-// still per-layout popovers, so they are torn down with the menu contents.
-fn close_tray_menu(open_menu: &TrayMenuHost) {
-    let Some(menu) = open_menu.open.borrow_mut().take() else {
-        return;
-    };
-    open_menu.popover.popdown();
-    teardown_tray_submenus(menu);
-}
-
-fn point_tray_menu_at(
-    open_menu: &TrayMenuHost,
-    button: &gtk4::Button,
-    container: &gtk4::Box,
-) -> std::result::Result<(), String> {
-    let bounds = button
-        .compute_bounds(container)
-        .ok_or_else(|| "could not translate tray icon bounds to the tray container".to_string())?;
-    let rectangle = gdk::Rectangle::new(
-        bounds.x().floor() as i32,
-        bounds.y().floor() as i32,
-        bounds.width().ceil().max(1.0) as i32,
-        bounds.height().ceil().max(1.0) as i32,
-    );
-    open_menu.popover.set_pointing_to(Some(&rectangle));
-    if open_menu.popover.is_mapped() {
-        open_menu.popover.present();
-    }
-    debug!(
-        x = rectangle.x(),
-        y = rectangle.y(),
-        width = rectangle.width(),
-        height = rectangle.height(),
-        "Moved fused tray menu anchor"
-    );
-    Ok(())
-}
-
-// Translate keys into NavCmd and forward them to the tray task. The controller
-// holds no navigation state of its own (the task owns it); it only knows the
-// `gg` two-key jump, which needs to remember a lone `g` between presses.
-fn nav_key_controller(
-    nav_tx: &mpsc::UnboundedSender<NavCmd>,
-    item_key: &str,
-) -> gtk4::EventControllerKey {
-    let controller = gtk4::EventControllerKey::new();
-    controller.set_propagation_phase(gtk4::PropagationPhase::Capture);
-    let nav_tx = nav_tx.clone();
-    let item_key = item_key.to_string();
-    let pending_g = Cell::new(false);
-    controller.connect_key_pressed(move |_controller, key, _code, _modifiers| {
-        if key == gdk::Key::g {
-            // First `g` arms the jump; the second fires it.
-            if pending_g.replace(true) {
-                pending_g.set(false);
-                let _ = nav_tx.send(NavCmd::First);
-            }
-            return glib::Propagation::Stop;
-        }
-        pending_g.set(false);
-
-        let cmd = match key {
-            gdk::Key::h | gdk::Key::Left => NavCmd::Left,
-            gdk::Key::l | gdk::Key::Right => NavCmd::Right,
-            gdk::Key::j | gdk::Key::Down => NavCmd::Down,
-            gdk::Key::k | gdk::Key::Up => NavCmd::Up,
-            gdk::Key::G | gdk::Key::End => NavCmd::Last,
-            gdk::Key::Home => NavCmd::First,
-            gdk::Key::Return | gdk::Key::KP_Enter => NavCmd::Activate,
-            gdk::Key::Escape | gdk::Key::q => NavCmd::Escape,
-            _ => return glib::Propagation::Proceed,
-        };
-        info!(item = item_key, ?cmd, "Tray nav key received");
-        if nav_tx.send(cmd).is_err() {
-            debug!(item = item_key, "Tray nav channel closed; dropping key");
-        }
-        glib::Propagation::Stop
-    });
-    controller
-}
-
-// Highlight exactly one tray icon as the level-0 pre-selection. Clearing first
-// keeps the "at most one" invariant even as items are added or removed.
-fn update_nav_highlight(entries: &HashMap<String, TrayEntry>, order: &[String], index: usize) {
-    for entry in entries.values() {
-        entry.button.remove_css_class("nav-selected");
-    }
-    if let Some(entry) = order.get(index).and_then(|key| entries.get(key)) {
-        entry.button.add_css_class("nav-selected");
-    }
-}
-
-fn set_nav_in_menu(nav: &mut Option<NavActive>, container: &gtk4::Box, in_menu: bool) {
-    let Some(active) = nav.as_mut() else {
-        return;
-    };
-    active.in_menu = in_menu;
-    if in_menu {
-        container.remove_css_class("nav-level-zero");
-    } else {
-        container.add_css_class("nav-level-zero");
-    }
-}
-
-fn move_tray_index(index: usize, len: usize, cmd: &NavCmd) -> Option<usize> {
-    if len == 0 {
-        return None;
-    }
-    match cmd {
-        NavCmd::Left => Some((index + len - 1) % len),
-        NavCmd::Right => Some((index + 1) % len),
-        NavCmd::First => Some(0),
-        NavCmd::Last => Some(len - 1),
-        _ => None,
-    }
-}
-
-// Drop back to level 0 within an open dropdown: forget the selected entry (and
-// its highlight) and collapse any open submenus so only the top level shows.
-fn clear_menu_selection(open_menu: &TrayMenuHost) {
-    let mut open_menu = open_menu.open.borrow_mut();
-    let Some(menu) = open_menu.as_mut() else {
-        return;
-    };
-    if let Some(selected) = menu.selected.take()
-        && let Some(entry) = menu.entries.get(selected) {
-            entry.button.remove_css_class("selected");
-        }
-    for popover in &menu.popovers {
-        popover.popdown();
-    }
-}
-
-// Ask the backend to (re)open one icon's dropdown as a keyboard-grab menu. The
-// grab itself belongs to the nav session; this only flags the menu so
-// show_tray_menu wires up the nav controller and pops up focused.
-fn open_icon_menu(
-    entry: &TrayEntry,
-    commands: &mpsc::UnboundedSender<TrayCommand>,
-    menu_requests: &Rc<Cell<u64>>,
-    nav_tx: &mpsc::UnboundedSender<NavCmd>,
-) -> std::result::Result<u64, String> {
-    let (x, y) = tray_button_coordinates(&entry.button);
-    let item = entry.state.borrow();
-    let request_id = next_menu_request(menu_requests);
-    let command = TrayCommand {
-        key: item.key.clone(),
-        action: TrayAction::ContextMenu {
-            request_id,
-            keyboard_grab: true,
-        },
-        x,
-        y,
-        menu_path: item.menu_path.clone(),
-    };
-    commands
-        .send(command)
-        .map_err(|error| format!("could not open tray menu for keyboard nav: {error}"))?;
-    let nav_tx = nav_tx.clone();
-    glib::timeout_add_local_once(Duration::from_secs(3), move || {
-        let _ = nav_tx.send(NavCmd::MenuTimeout { request_id });
-    });
-    Ok(request_id)
-}
-
-// Tooltips must not be mappable while any tray popup grab can exist: GTK can
-// remap an already-visible tooltip without re-querying the application, and an
-// illegal second popup under an active grab deadlocks the main loop (see the
-// query handler in create_tray_entry). The has-tooltip property is the durable
-// switch GTK itself consults on that reposition path, so flip it for every
-// icon around the whole grab lifetime; the query handler stays as a backstop.
-fn set_tray_tooltips_enabled(entries: &HashMap<String, TrayEntry>, enabled: bool) {
-    for entry in entries.values() {
-        entry.button.set_has_tooltip(enabled);
-    }
-}
-
-// Start (or retarget) a keyboard-navigation session at `index`, entering at
-// level 0 with that icon's dropdown auto-opened. The first call acquires the
-// grab; later calls just move the pre-selection.
-#[allow(clippy::too_many_arguments)]
-fn start_nav(
-    nav: &mut Option<NavActive>,
-    index: usize,
-    keyboard_grab: &Rc<KeyboardGrab>,
-    nav_tx: &mpsc::UnboundedSender<NavCmd>,
-    container: &gtk4::Box,
-    entries: &HashMap<String, TrayEntry>,
-    order: &[String],
-    commands: &mpsc::UnboundedSender<TrayCommand>,
-    menu_requests: &Rc<Cell<u64>>,
-) -> std::result::Result<(), String> {
-    let key = order
-        .get(index)
-        .ok_or_else(|| format!("tray item index {index} is no longer available"))?
-        .clone();
-    let entry = entries
-        .get(&key)
-        .ok_or_else(|| format!("tray item {key:?} is no longer available"))?;
-
-    // Suppress native tooltips before creating the grab; the menu layout can
-    // arrive later, but no tooltip popup may map anywhere in between.
-    set_tray_tooltips_enabled(entries, false);
-    match nav.as_mut() {
-        Some(active) => {
-            active.key = key.clone();
-        }
-        None => {
-            let request_id = next_menu_request(menu_requests);
-            let Some(lease) = keyboard_grab.acquire(request_id, nav_tx) else {
-                return Err("could not acquire the keyboard grab for tray navigation".to_string());
-            };
-            container.add_css_class("nav-focus");
-            *nav = Some(NavActive {
-                lease,
-                key: key.clone(),
-                menu_request_id: request_id,
-                in_menu: false,
-            });
-        }
-    }
-    set_nav_in_menu(nav, container, false);
-    clear_menu_selection(&entry.open_menu);
-    if let Err(error) = point_tray_menu_at(&entry.open_menu, &entry.button, container) {
-        debug!(item = key, %error, "Could not move tray menu before loading its layout");
-    }
-    update_nav_highlight(entries, order, index);
-    let request_id = open_icon_menu(entry, commands, menu_requests, nav_tx)?;
-    if let Some(active) = nav.as_mut() {
-        active.menu_request_id = request_id;
-    }
-    Ok(())
-}
-
-// Tear the session down: release the grab (dropping the lease restores focus to
-// whatever held it before), close the dropdown, and clear all focus visuals.
-fn end_nav(
-    nav: &mut Option<NavActive>,
-    last_nav_key: &mut Option<String>,
-    container: &gtk4::Box,
-    entries: &HashMap<String, TrayEntry>,
-    open_menu: &TrayMenuHost,
-    menu_requests: &Cell<u64>,
-) {
-    let Some(active) = nav.take() else {
-        return;
-    };
-    info!(
-        item = active.key,
-        request_id = active.menu_request_id,
-        in_menu = active.in_menu,
-        menu_surface_mapped = open_menu.popover.is_mapped(),
-        "Ending tray keyboard navigation"
-    );
-    // Remember where the user left off so a later `open` resumes here. The key
-    // is kept even when the item just vanished — `open` re-checks existence and
-    // falls back to the first icon, so a stale key is harmless.
-    *last_nav_key = Some(active.key.clone());
-    if menu_requests.get() == active.menu_request_id {
-        next_menu_request(menu_requests);
-    }
-    close_tray_menu(open_menu);
-    info!(
-        item = active.key,
-        request_id = active.menu_request_id,
-        "Tray menu closed while ending keyboard navigation"
-    );
-    for entry in entries.values() {
-        entry.button.remove_css_class("nav-selected");
-        // Unmapping the exclusive helper can leave GTK's hover state on the
-        // icon under a stationary pointer. Clear that transient tint with the
-        // keyboard state; normal pointer motion will establish hover again.
-        entry.button.unset_state_flags(gtk4::StateFlags::PRELIGHT);
-    }
-    container.remove_css_class("nav-focus");
-    container.remove_css_class("nav-level-zero");
-    // A mapped popover just sent MenuSurfaceUnmapped, which restores tooltips
-    // once this teardown finishes. A grab that never got its menu mapped has
-    // no such signal coming, so restore here.
-    if !open_menu.popover.is_mapped() && open_menu.open.borrow().is_none() {
-        set_tray_tooltips_enabled(entries, true);
-    }
-    info!(
-        item = active.key,
-        request_id = active.menu_request_id,
-        "Releasing tray keyboard helper"
-    );
-    drop(active);
-    info!("Tray keyboard navigation teardown completed");
-}
-
-// The navigation state machine, driven by one NavCmd at a time. Level 0 scrolls
-// across tray icons (auto-opening dropdowns); deeper levels move within the open
-// dropdown. Escape is two-step: a deeper level drops to level 0, level 0
-// releases the grab.
-#[allow(clippy::too_many_arguments)]
-fn handle_nav(
-    cmd: NavCmd,
-    nav: &mut Option<NavActive>,
-    last_nav_key: &mut Option<String>,
-    nav_tx: &mpsc::UnboundedSender<NavCmd>,
-    order: &[String],
-    entries: &HashMap<String, TrayEntry>,
-    container: &gtk4::Box,
-    open_menu: &TrayMenuHost,
-    commands: &mpsc::UnboundedSender<TrayCommand>,
-    menu_requests: &Rc<Cell<u64>>,
-) {
-    let active = nav.as_ref();
-    info!(
-        ?cmd,
-        nav_item = active.map(|active| active.key.as_str()),
-        nav_request_id = active.map(|active| active.menu_request_id),
-        in_menu = active.map(|active| active.in_menu),
-        "Handling tray navigation input"
-    );
-    let cmd = match cmd {
-        NavCmd::MouseAction(command) => {
-            info!(
-                item = command.key,
-                action = ?command.action,
-                "Mouse action is ending tray keyboard navigation"
-            );
-            end_nav(
-                nav,
-                last_nav_key,
-                container,
-                entries,
-                open_menu,
-                menu_requests,
-            );
-            info!(
-                item = command.key,
-                action = ?command.action,
-                "Tray keyboard navigation ended for mouse action"
-            );
-            if let Err(error) = commands.send(command) {
-                warn!(%error, "Could not send tray click to D-Bus backend");
-            }
-            return;
-        }
-        NavCmd::MenuSurfaceUnmapped => {
-            // Fires on every popover unmap, including click-outside dismissal
-            // of a pointer-opened menu (which has no nav session). Restore
-            // tooltips only once nothing that could hold a popup grab is left;
-            // a superseding menu's own unmap handles the restore otherwise.
-            if nav.is_none() && open_menu.open.borrow().is_none() {
-                set_tray_tooltips_enabled(entries, true);
-            }
-            return;
-        }
-        other => other,
-    };
-
-    // Snapshot the session; a late key after release finds nothing to drive.
-    let (key, in_menu, menu_request_id) = match nav.as_ref() {
-        Some(active) => (active.key.clone(), active.in_menu, active.menu_request_id),
-        None => return,
-    };
-    let Some(index) = order.iter().position(|candidate| candidate == &key) else {
-        warn!(
-            item = key,
-            "Ending tray navigation after its selected item disappeared"
-        );
-        end_nav(
-            nav,
-            last_nav_key,
-            container,
-            entries,
-            open_menu,
-            menu_requests,
-        );
-        return;
-    };
-
-    match &cmd {
-        NavCmd::UserClosed { request_id } if *request_id == menu_request_id => {
-            end_nav(
-                nav,
-                last_nav_key,
-                container,
-                entries,
-                open_menu,
-                menu_requests,
-            );
-            return;
-        }
-        NavCmd::UserClosed { request_id } => {
-            debug!(
-                request_id,
-                current_request_id = menu_request_id,
-                "Ignoring stale tray menu close"
-            );
-            return;
-        }
-        NavCmd::MenuTimeout { request_id } if *request_id == menu_request_id => {
-            let menu_loaded = open_menu
-                .open
-                .borrow()
-                .as_ref()
-                .is_some_and(|menu| menu.key == key && menu.request_id == *request_id);
-            let surface_mapped = open_menu.popover.is_mapped();
-            let (helper_active, bar_active) = nav
-                .as_ref()
-                .map(|active| active.lease.focus_state())
-                .unwrap_or((false, false));
-            let keyboard_active = helper_active || bar_active;
-            let menu_ready = menu_loaded && surface_mapped && keyboard_active;
-            info!(
-                item = key,
-                request_id,
-                menu_loaded,
-                surface_mapped,
-                helper_active,
-                bar_active,
-                "Tray menu readiness checked"
-            );
-            if !menu_ready {
-                warn!(
-                    item = key,
-                    request_id,
-                    menu_loaded,
-                    surface_mapped,
-                    helper_active,
-                    bar_active,
-                    "Tray menu was not ready before timeout"
-                );
-                end_nav(
-                    nav,
-                    last_nav_key,
-                    container,
-                    entries,
-                    open_menu,
-                    menu_requests,
-                );
-            }
-            return;
-        }
-        NavCmd::MenuTimeout { .. } => return,
-        NavCmd::Escape => {
-            if in_menu {
-                if let Some(entry) = order.get(index).and_then(|key| entries.get(key)) {
-                    clear_menu_selection(&entry.open_menu);
-                }
-                set_nav_in_menu(nav, container, false);
-            } else {
-                end_nav(
-                    nav,
-                    last_nav_key,
-                    container,
-                    entries,
-                    open_menu,
-                    menu_requests,
-                );
-            }
-            return;
-        }
-        _ => {}
-    }
-
-    if !in_menu {
-        // LEVEL 0: scroll across icons; the landed icon auto-opens.
-        let len = order.len();
-        if len == 0 {
-            return;
-        }
-        match cmd {
-            NavCmd::Left | NavCmd::Right | NavCmd::First | NavCmd::Last => {
-                let Some(new_index) = move_tray_index(index, len, &cmd) else {
-                    return;
-                };
-                if new_index == index {
-                    return;
-                }
-                if let Some(active) = nav.as_mut() {
-                    active.key = order[new_index].clone();
-                }
-                update_nav_highlight(entries, order, new_index);
-                if let Some(entry) = order.get(new_index).and_then(|key| entries.get(key)) {
-                    clear_menu_selection(open_menu);
-                    if let Err(error) = point_tray_menu_at(open_menu, &entry.button, container) {
-                        debug!(%error, "Could not move fused tray menu");
-                    }
-                    match open_icon_menu(entry, commands, menu_requests, nav_tx) {
-                        Ok(request_id) => {
-                            if let Some(active) = nav.as_mut() {
-                                active.menu_request_id = request_id;
-                            }
-                        }
-                        Err(error) => {
-                            warn!(%error, "Ending tray navigation after icon switch failed");
-                            end_nav(
-                                nav,
-                                last_nav_key,
-                                container,
-                                entries,
-                                open_menu,
-                                menu_requests,
-                            );
-                        }
-                    }
-                }
-            }
-            NavCmd::Down | NavCmd::Up | NavCmd::Activate => {
-                // Either vertical direction enters the dropdown. Down/Enter
-                // select its first entry; Up selects its last entry.
-                if let Some(entry) = order.get(index).and_then(|key| entries.get(key)) {
-                    let direction = if matches!(cmd, NavCmd::Up) { -1 } else { 1 };
-                    match select_menu_entry(&entry.open_menu, direction) {
-                        Ok(_) => {
-                            set_nav_in_menu(nav, container, true);
-                        }
-                        Err(error) => debug!(%error, "Cannot descend into tray dropdown"),
-                    }
-                }
-            }
-            _ => {}
-        }
-        return;
-    }
-
-    // IN A DROPDOWN (level >= 1).
-    let Some(entry) = order.get(index).and_then(|key| entries.get(key)) else {
-        return;
-    };
-    let open_menu = &entry.open_menu;
-    match cmd {
-        NavCmd::Down => {
-            if let Err(error) = select_menu_entry(open_menu, 1) {
-                debug!(%error, "Could not move down in tray dropdown");
-            }
-        }
-        NavCmd::Up => {
-            if let Err(error) = select_menu_entry(open_menu, -1) {
-                debug!(%error, "Could not move up in tray dropdown");
-            }
-        }
-        NavCmd::First => {
-            if let Err(error) = jump_menu_entry(open_menu, false) {
-                debug!(%error, "Could not jump to first tray dropdown entry");
-            }
-        }
-        NavCmd::Last => {
-            if let Err(error) = jump_menu_entry(open_menu, true) {
-                debug!(%error, "Could not jump to last tray dropdown entry");
-            }
-        }
-        NavCmd::Right => {
-            if let Err(error) = enter_submenu(open_menu) {
-                debug!(%error, "Could not enter tray submenu");
-            }
-        }
-        NavCmd::Left => match leave_submenu(open_menu) {
-            Ok(true) => {} // stepped up one submenu level
-            Ok(false) => {
-                // Already at the dropdown's top level: return to level 0.
-                clear_menu_selection(open_menu);
-                set_nav_in_menu(nav, container, false);
-            }
-            Err(error) => debug!(%error, "Could not leave tray submenu"),
-        },
-        NavCmd::Activate => match enter_submenu(open_menu) {
-            Ok(true) => {}
-            Ok(false) => {
-                let button = open_menu
-                    .open
-                    .borrow()
-                    .as_ref()
-                    .and_then(|menu| menu.selected.map(|i| menu.entries[i].button.clone()));
-                match button {
-                    // Fires MenuEvent and closes the menu; the resulting
-                    // UserClosed then ends the session.
-                    Some(button) => button.emit_clicked(),
-                    None => debug!("Tray dropdown has no selected entry to activate"),
-                }
-            }
-            Err(error) => debug!(%error, "Could not activate tray dropdown entry"),
-        },
-        _ => {}
-    }
-}
-
-// Render a tray item's dbusmenu inside the shared GTK popover. We build the menu
-// from concrete `Button` widgets (instead of `PopoverMenu::from_model`) because
-// the model-based popover constructs its widgets lazily and cannot measure its
-// size at `popup()` time, which makes it present at zero width/height on a
-// layer-shell surface. Concrete widgets measure immediately. Activating an
-// entry forwards the entry id back to the backend, which calls dbusmenu's
-// `Event`.
-fn show_tray_menu(
-    entry: &TrayEntry,
-    container: &gtk4::Box,
-    menu: &TrayMenu,
-    command_tx: &mpsc::UnboundedSender<TrayCommand>,
-    nav_tx: &mpsc::UnboundedSender<NavCmd>,
-) {
-    let open_menu = &entry.open_menu;
-    let popover = &open_menu.popover;
-    let was_mapped = popover.is_mapped();
-    if let Some(previous) = open_menu.open.borrow_mut().take() {
-        teardown_tray_submenus(previous);
-    }
-
-    let box_ = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
-    box_.add_css_class("tray-menu");
-    debug!(
-        item = menu.key,
-        labels = ?menu.items.iter().map(|i| &i.label).collect::<Vec<_>>(),
-        "Tray menu entry labels"
-    );
-    let popover_weak = popover.downgrade();
-    let mut popovers = Vec::new();
-    let mut menu_entries = Vec::new();
-    let mut build = MenuBuildContext {
-        command_tx,
-        menu,
-        top_popover: &popover_weak,
-        popovers: &mut popovers,
-        entries: &mut menu_entries,
-    };
-    build_menu_box(&box_, &menu.items, &mut build, &[]);
-
-    popover.set_child(Some(&box_));
-
-    // Each submenu is its own native popover and can receive keyboard focus.
-    // Install the controller on all of them so navigation does not fall back to
-    // GTK's default handling when a submenu opens.
-    let is_nav = menu.keyboard_grab;
-    if is_nav {
-        for submenu in &popovers {
-            submenu.add_controller(nav_key_controller(nav_tx, &menu.key));
-        }
-    }
-
-    *open_menu.open.borrow_mut() = Some(OpenTrayMenu {
-        key: menu.key.clone(),
-        request_id: menu.request_id,
-        keyboard_grab: menu.keyboard_grab,
-        popovers,
-        entries: menu_entries,
-        selected: None,
-    });
-    // A tooltip that is already visible when the menu maps never re-queries:
-    // GTK remaps it directly (gtk_tooltip_window_map), which is the popup
-    // stacking violation that deadlocks the main loop. The caller has already
-    // flipped has-tooltip off for every icon (the durable guard GTK consults
-    // on that reposition path); this forced query makes GTK re-evaluate and
-    // hide a currently-visible tooltip right now. Note the query only runs
-    // when the pointer is on this widget's native surface — which is exactly
-    // when a tray tooltip can be visible, since all icons share the bar.
-    entry.button.trigger_tooltip_query();
-    if let Err(error) = point_tray_menu_at(open_menu, &entry.button, container) {
-        warn!(item = menu.key, %error, "Could not anchor fused tray menu to its icon");
-    }
-
-    info!(
-        item = menu.key,
-        entries = menu.items.len(),
-        keyboard_grab = menu.keyboard_grab,
-        reused_surface = was_mapped,
-        "Presenting tray menu"
-    );
-    if was_mapped {
-        info!(
-            item = menu.key,
-            "Presenting already-mapped tray menu surface"
-        );
-        popover.present();
-        info!(
-            item = menu.key,
-            "Already-mapped tray menu surface presented"
-        );
-        return;
-    }
-    if is_nav {
-        // Defer the popup so the exclusive helper surface (grabbed by the nav
-        // session) has settled keyboard focus before the popover maps. We land
-        // at level 0: the dropdown is open but no entry is selected yet.
-        let item_key = menu.key.clone();
-        let request_id = menu.request_id;
-        let popover_weak = popover.downgrade();
-        let open_menu_weak = Rc::downgrade(open_menu);
-        glib::timeout_add_local_once(Duration::from_millis(100), move || {
-            let Some(open_menu) = open_menu_weak.upgrade() else {
-                return;
-            };
-            let still_current = open_menu
-                .open
-                .borrow()
-                .as_ref()
-                .is_some_and(|menu| menu.request_id == request_id);
-            if !still_current {
-                return;
-            }
-            let Some(popover) = popover_weak.upgrade() else {
-                return;
-            };
-            info!(item = item_key, request_id, "Mapping tray menu surface");
-            popover.popup();
-            info!(
-                item = item_key,
-                request_id, "Tray menu popup call completed"
-            );
-            // Keep GTK focus on the exclusive helper surface. Moving it to a
-            // layer-shell popover can block inside grab_focus() while Wayland
-            // is resolving the two surfaces, wedging the entire GTK loop.
-            // Both surfaces already have the same navigation controller.
-            info!(
-                item = item_key,
-                request_id, "Tray dropdown opened at level 0"
-            );
-        });
-    } else {
-        info!(item = menu.key, "Mapping pointer-driven tray menu surface");
-        popover.popup();
-        info!(
-            item = menu.key,
-            "Pointer-driven tray menu popup call completed"
-        );
-    }
-}
-
-// Recursively fill `box_` with the dbusmenu entries. `top_popover` is the top
-// popover so any leaf entry activation can close the whole menu, including
-// submenus (whose own activation bubbles up to the same popover). Every popover
-// built here is also pushed into `popovers` so the caller can unparent the full
-// set when the menu goes away.
-// Matches the `.tray-menu` foreground in style.css. Monochrome menu icons are
-// repainted to it (see recolored_if_dark) so KDE's dark Breeze glyphs stay
-// legible against the dark popover instead of vanishing into it.
-const MENU_ICON_FG: (u8, u8, u8) = (0xc0, 0xca, 0xf5);
-const MENU_ICON_SIZE: i32 = 16;
-
-// dbusmenu entries carry a themed `icon-name` and often an embedded `icon-data`
-// PNG. A named icon is only usable when our icon theme actually has it: KDE apps
-// name menu icons with the Breeze theme (`smartphonedisconnected`, `battery-060`,
-// `klipper`, …), which resolve to nothing under Adwaita, and handing those
-// straight to Image::from_icon_name paints GTK's broken "image-missing" glyph.
-// So prefer a resolvable named icon, fall back to the embedded PNG, and render
-// no icon at all when neither is drawable.
-fn menu_icon_image(reference: &gtk4::Box, item: &TrayMenuItem) -> Option<gtk4::Image> {
-    let icon_theme = gtk4::IconTheme::for_display(&reference.display());
-    if let Some(name) = item.icon_name.as_deref().filter(|name| !name.is_empty())
-        && icon_theme.has_icon(name)
-            && named_icon_render_error(&icon_theme, name, MENU_ICON_SIZE, reference.scale_factor())
-                .is_none()
-        {
-            return Some(gtk4::Image::from_icon_name(name));
-        }
-
-    let texture = decode_menu_icon_data(item.icon_data.as_deref()?)?;
-    let image = gtk4::Image::new();
-    image.set_paintable(Some(&texture));
-    Some(image)
-}
-
-fn decode_menu_icon_data(data: &[u8]) -> Option<gdk::Texture> {
-    let bytes = glib::Bytes::from(data);
-    let stream = gtk4::gio::MemoryInputStream::from_bytes(&bytes);
-    let pixbuf = match gtk4::gdk_pixbuf::Pixbuf::from_stream(&stream, gtk4::gio::Cancellable::NONE)
-    {
-        Ok(pixbuf) => pixbuf,
-        Err(error) => {
-            debug!(%error, "Could not decode dbusmenu icon-data as an image");
-            return None;
-        }
-    };
-    let pixbuf = recolored_if_dark(&pixbuf).unwrap_or(pixbuf);
-    Some(gdk::Texture::for_pixbuf(&pixbuf))
-}
-
-// A menu icon-data PNG that is a monochrome dark mask (KDE renders them in
-// Breeze's near-black "text" color, meant for light panels) is invisible on our
-// dark popover. When every visible pixel is dark, repaint the mask in the menu
-// foreground and keep its alpha so it reads like the rest of the menu. Colourful
-// or already-light icons are left untouched (returns None).
-const MENU_ICON_DARK_LUMA_MAX: u16 = 110;
-const MENU_ICON_ALPHA_FLOOR: u8 = 24;
-
-fn recolored_if_dark(pixbuf: &gtk4::gdk_pixbuf::Pixbuf) -> Option<gtk4::gdk_pixbuf::Pixbuf> {
-    if pixbuf.colorspace() != gtk4::gdk_pixbuf::Colorspace::Rgb
-        || pixbuf.bits_per_sample() != 8
-        || !pixbuf.has_alpha()
-        || pixbuf.n_channels() != 4
-    {
-        return None;
-    }
-    let width = pixbuf.width();
-    let height = pixbuf.height();
-    if width <= 0 || height <= 0 {
-        return None;
-    }
-    let rowstride = pixbuf.rowstride() as usize;
-    let width_u = width as usize;
-    let height_u = height as usize;
-    let src = pixbuf.read_pixel_bytes();
-
-    // Scan first: only fully dark glyphs are recolored, so a colored icon is
-    // never flattened to a single tint.
-    let mut any_ink = false;
-    for y in 0..height_u {
-        let row = &src[y * rowstride..y * rowstride + width_u * 4];
-        for pixel in row.chunks_exact(4) {
-            if pixel[3] < MENU_ICON_ALPHA_FLOOR {
-                continue;
-            }
-            any_ink = true;
-            let luma = 30 * pixel[0] as u16 + 59 * pixel[1] as u16 + 11 * pixel[2] as u16;
-            if luma / 100 > MENU_ICON_DARK_LUMA_MAX {
-                return None;
-            }
-        }
-    }
-    if !any_ink {
-        return None;
-    }
-
-    let mut recolored = src.to_vec();
-    for y in 0..height_u {
-        let base = y * rowstride;
-        for x in 0..width_u {
-            recolored[base + x * 4] = MENU_ICON_FG.0;
-            recolored[base + x * 4 + 1] = MENU_ICON_FG.1;
-            recolored[base + x * 4 + 2] = MENU_ICON_FG.2;
-            // Alpha (recolored[base + x*4 + 3]) is left as the source mask.
-        }
-    }
-    Some(gtk4::gdk_pixbuf::Pixbuf::from_bytes(
-        &glib::Bytes::from_owned(recolored),
-        gtk4::gdk_pixbuf::Colorspace::Rgb,
-        true,
-        8,
-        width,
-        height,
-        rowstride as i32,
-    ))
-}
-
-fn build_menu_box(
-    box_: &gtk4::Box,
-    items: &[TrayMenuItem],
-    build: &mut MenuBuildContext<'_>,
-    ancestors: &[gtk4::Popover],
-) {
-    // The first visible, non-separator row is the menu's "lead". When an app
-    // ships that lead row disabled it is a non-selectable title/category for the
-    // group below it (e.g. Discord's "Discord"), not an unavailable action, so we
-    // band it as a header. Every other disabled row is a genuinely non-selectable
-    // action (e.g. blueman's "Reconnect to..."). "First row" is the load-bearing
-    // signal here: a disabled action can also sit right after a separator, so
-    // "follows a separator" would misclassify it as a header.
-    let mut lead_row_seen = false;
-    // A header row carries its own bottom hairline that reads as the group
-    // divider, so a real separator right beneath it would draw a doubled line.
-    // Swallow that one separator.
-    let mut prev_was_header = false;
-    for item in items {
-        if !item.visible {
-            continue;
-        }
-        if item.is_separator {
-            if prev_was_header {
-                prev_was_header = false;
-                continue;
-            }
-            box_.append(&gtk4::Separator::new(gtk4::Orientation::Horizontal));
-            continue;
-        }
-
-        let is_header = !lead_row_seen && !item.enabled;
-        lead_row_seen = true;
-        prev_was_header = is_header;
-
-        let entry = gtk4::Button::new();
-        entry.set_has_frame(false);
-        entry.add_css_class("tray-menu-item");
-        if is_header {
-            entry.add_css_class("tray-menu-header");
-        } else if !item.enabled {
-            entry.add_css_class("tray-menu-disabled");
-        }
-        entry.set_hexpand(true);
-        // GTK focuses the first focusable child when the popover maps. That
-        // focus is invisible (selection uses .selected, and the CSS focus
-        // rule is neutralized), collides with :hover on the first entry, and
-        // GTK-driven focus moves inside layer-shell popovers have wedged the
-        // main loop before. Menu entries are driven by pointer clicks and the
-        // nav session, never by GTK keyboard focus, so opt out entirely.
-        entry.set_focusable(false);
-        if !item.enabled {
-            entry.set_sensitive(false);
-        }
-
-        let content = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
-        content.set_margin_start(8);
-        content.set_margin_end(8);
-        content.set_margin_top(4);
-        content.set_margin_bottom(4);
-
-        let leading = match (&item.toggle_type, item.toggle_state) {
-            (Some(toggle_type), Some(true)) if toggle_type == "checkmark" => {
-                Some(gtk4::Image::from_icon_name("object-select-symbolic"))
-            }
-            (Some(toggle_type), Some(true)) if toggle_type == "radio" => {
-                Some(gtk4::Image::from_icon_name("media-record-symbolic"))
-            }
-            _ => menu_icon_image(box_, item),
-        };
-        if let Some(icon) = leading {
-            content.append(&icon);
-        }
-        let label = gtk4::Label::new(item.label.as_deref());
-        label.set_halign(gtk4::Align::Start);
-        label.set_hexpand(true);
-        content.append(&label);
-        entry.set_child(Some(&content));
-
-        if item.children.is_empty() {
-            if item.enabled {
-                build.entries.push(OpenTrayMenuEntry {
-                    button: entry.clone(),
-                    id: item.id,
-                    ancestors: ancestors.to_vec(),
-                    submenu: None,
-                });
-            }
-            let command_tx = build.command_tx.clone();
-            let key = build.menu.key.clone();
-            let menu_path = build.menu.menu_path.clone();
-            let top_popover = build.top_popover.clone();
-            let id = item.id;
-            entry.connect_clicked(move |_button| {
-                // `clicked` fires mid release-event dispatch; popping the menu
-                // down right here unmaps the widgets under the pointer and
-                // breaks GTK's active-state accounting ("Broken accounting of
-                // active state" warnings on the entry icons). Defer it.
-                let top_popover = top_popover.clone();
-                glib::idle_add_local_once(move || {
-                    if let Some(popover) = top_popover.upgrade() {
-                        popover.popdown();
-                    }
-                });
-                let command = TrayCommand {
-                    key: key.clone(),
-                    action: TrayAction::MenuEvent(id),
-                    x: 0,
-                    y: 0,
-                    menu_path: menu_path.clone(),
-                };
-                if let Err(error) = command_tx.send(command) {
-                    warn!(item = key, %error, "Could not send tray menu event to backend");
-                }
-            });
-        } else {
-            let sub_popover = gtk4::Popover::new();
-            sub_popover.set_parent(&entry);
-            sub_popover.set_position(gtk4::PositionType::Right);
-            let sub_box = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
-            sub_box.add_css_class("tray-menu");
-            let mut child_ancestors = ancestors.to_vec();
-            child_ancestors.push(sub_popover.clone());
-            if item.enabled {
-                build.entries.push(OpenTrayMenuEntry {
-                    button: entry.clone(),
-                    id: item.id,
-                    ancestors: ancestors.to_vec(),
-                    submenu: Some(sub_popover.clone()),
-                });
-            }
-            build_menu_box(&sub_box, &item.children, build, &child_ancestors);
-            sub_popover.set_child(Some(&sub_box));
-            build.popovers.push(sub_popover.clone());
-            // Weak for the same cycle reason as the tray button gesture: the
-            // closure lives on `entry`, which the popover is attached to.
-            let sub_popover_weak = sub_popover.downgrade();
-            entry.connect_clicked(move |_button| {
-                // Same deferral as leaf entries, for the same accounting reason.
-                let sub_popover_weak = sub_popover_weak.clone();
-                glib::idle_add_local_once(move || {
-                    if let Some(popover) = sub_popover_weak.upgrade() {
-                        popover.popup();
-                    }
-                });
-            });
-        }
-
-        box_.append(&entry);
-    }
-}
-
-pub fn load_css_styles(window: &gtk4::ApplicationWindow) {
-    debug!("Loading CSS styles");
-
-    let css_provider = gtk4::CssProvider::new();
-    let css_data = include_str!("../style.css");
-    css_provider.load_from_string(css_data);
-
-    gtk4::style_context_add_provider_for_display(
-        &gtk4::prelude::WidgetExt::display(window),
-        &css_provider,
-        gtk4::STYLE_PROVIDER_PRIORITY_USER,
-    );
-
-    info!("CSS styles loaded successfully");
-}
-
-pub fn configure_layer_shell(
-    window: &gtk4::ApplicationWindow,
-    monitor_connector: Option<&str>,
-) -> Result<()> {
-    debug!("Configuring layer shell");
-
-    window.init_layer_shell();
-    if let Some(requested) = monitor_connector {
-        let display = gtk4::prelude::WidgetExt::display(window);
-        let monitors = display.monitors();
-        let mut available = Vec::new();
-        let mut selected = None;
-
-        for index in 0..monitors.n_items() {
-            let Some(object) = monitors.item(index) else {
-                warn!(index, "GDK monitor list omitted an advertised item");
-                continue;
-            };
-            let Ok(monitor) = object.downcast::<gdk::Monitor>() else {
-                warn!(index, "GDK monitor list contained a non-monitor object");
-                continue;
-            };
-            let Some(connector) = monitor.connector() else {
-                warn!(index, "GDK monitor has no connector name");
-                continue;
-            };
-
-            if connector == requested {
-                selected = Some(monitor);
-                break;
-            }
-            available.push(connector.to_string());
-        }
-
-        let Some(monitor) = selected else {
-            available.sort();
-            let available = if available.is_empty() {
-                "none".to_string()
-            } else {
-                available.join(", ")
-            };
-            bail!(
-                "monitor connector {requested:?} was not found; available connectors: {available}"
-            );
-        };
-
-        window.set_monitor(Some(&monitor));
-        info!(monitor = requested, "Selected layer-shell monitor");
-    }
-    window.set_layer(Layer::Bottom);
-    window.auto_exclusive_zone_enable();
-
-    let anchors = [
-        (Edge::Left, true),
-        (Edge::Right, true),
-        (Edge::Top, true),
-        (Edge::Bottom, false),
-    ];
-
-    for (anchor, state) in anchors {
-        window.set_anchor(anchor, state);
-    }
-
-    info!("Layer shell configured successfully");
-    Ok(())
-}
-
-fn update_title_widget_workspace_color(
-    title_widget: &TitleWidget,
-    workspace_id: hyprland::shared::WorkspaceId,
-) {
-    // Get workspace color based on ID
-    let color = get_workspace_color(workspace_id);
-
-    // Apply color directly via CSS provider for immediate update
-    let css_provider = gtk4::CssProvider::new();
-    let css = format!(".title-widget {{ background-color: {}; }}", color);
-
-    css_provider.load_from_string(&css);
-
-    let style_context = title_widget.root.style_context();
-    style_context.add_provider(&css_provider, gtk4::STYLE_PROVIDER_PRIORITY_USER + 1);
-
-    debug!(
-        "Updated title widget color to: {} for workspace: {}",
-        color, workspace_id
-    );
-}
-
-fn get_workspace_color(workspace_id: hyprland::shared::WorkspaceId) -> &'static str {
-    match workspace_id {
-        1 => "rgba(122, 162, 247, 0.5)",
-        2 => "rgba(125, 207, 255, 0.5)",
-        3 => "rgba(158, 206, 106, 0.5)",
-        4 => "rgba(187, 154, 247, 0.5)",
-        5 => "rgba(247, 118, 142, 0.5)",
-        6 => "rgba(255, 158, 102, 0.5)",
-        7 => "rgba(157, 124, 216, 0.5)",
-        8 => "rgba(224, 175, 104, 0.5)",
-        9 => "rgba(42, 195, 222, 0.5)",
-        10 => "rgba(13, 185, 215, 0.5)",
-        _ => "rgba(67, 233, 123, 0.5)", // Default color
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    // Workspaces 1..=10 have explicit color entries; everything else hits the
-    // default arm. Tests pin the boundaries — a typo in the match arms
-    // (e.g. duplicate id, wrong default fallthrough) would flip these.
-    #[test]
-    fn workspace_color_1_is_blue_ish() {
-        assert_eq!(get_workspace_color(1), "rgba(122, 162, 247, 0.5)");
-    }
-
-    #[test]
-    fn workspace_color_10_is_last_explicit() {
-        assert_eq!(get_workspace_color(10), "rgba(13, 185, 215, 0.5)");
-    }
-
-    #[test]
-    fn workspace_color_11_falls_through_to_default() {
-        let default = "rgba(67, 233, 123, 0.5)";
-        assert_eq!(get_workspace_color(11), default);
-        assert_eq!(get_workspace_color(100), default);
-    }
-
-    // Hyprland uses negative workspace IDs for special workspaces; verify we
-    // don't accidentally match a positive arm and that we hit the default.
-    #[test]
-    fn workspace_color_negative_id_falls_through_to_default() {
-        let default = "rgba(67, 233, 123, 0.5)";
-        assert_eq!(get_workspace_color(-1), default);
-        assert_eq!(get_workspace_color(-99), default);
-    }
-
-    // Every explicit arm returns a different color — if a regression turns
-    // two of them into the same rgba, this catches it.
-    #[test]
-    fn workspace_colors_are_all_distinct() {
-        let mut colors: Vec<&str> = (1..=10).map(get_workspace_color).collect();
-        colors.push(get_workspace_color(0)); // default
-        colors.sort();
-        let len_before = colors.len();
-        colors.dedup();
-        assert_eq!(colors.len(), len_before, "expected all distinct colors");
-    }
-
-    #[test]
-    fn tray_pixmap_argb_is_converted_to_rgba() {
-        assert_eq!(
-            argb_to_rgba(2, 1, &[255, 1, 2, 3, 128, 4, 5, 6]),
-            Some(vec![1, 2, 3, 255, 4, 5, 6, 128])
-        );
-    }
-
-    #[test]
-    fn tray_pixmap_rejects_invalid_dimensions_and_length() {
-        assert_eq!(argb_to_rgba(0, 1, &[]), None);
-        assert_eq!(argb_to_rgba(1, 1, &[0, 1, 2]), None);
-    }
-
-    // Pack a flat RGBA buffer (rowstride == width*4) into a Pixbuf the way
-    // decode_menu_icon_data would after decoding a PNG.
-    fn rgba_pixbuf(width: i32, height: i32, pixels: &[u8]) -> gtk4::gdk_pixbuf::Pixbuf {
-        gtk4::gdk_pixbuf::Pixbuf::from_bytes(
-            &glib::Bytes::from(pixels),
-            gtk4::gdk_pixbuf::Colorspace::Rgb,
-            true,
-            8,
-            width,
-            height,
-            width * 4,
-        )
-    }
-
-    // KDE menu icons arrive as near-black masks; recoloring must repaint the ink
-    // to the menu foreground while preserving the alpha silhouette.
-    #[test]
-    fn dark_menu_icon_mask_is_recolored_to_foreground() {
-        // Two opaque dark pixels, one fully transparent hole.
-        let pixels = [10, 12, 14, 255, 20, 18, 16, 255, 0, 0, 0, 0, 5, 5, 5, 200];
-        let pixbuf = rgba_pixbuf(2, 2, &pixels);
-        let recolored = recolored_if_dark(&pixbuf).expect("dark mask should be recolored");
-        let out = recolored.read_pixel_bytes();
-        // Opaque ink takes the foreground color; alpha is carried through.
-        assert_eq!(
-            &out[0..4],
-            &[MENU_ICON_FG.0, MENU_ICON_FG.1, MENU_ICON_FG.2, 255]
-        );
-        assert_eq!(
-            &out[12..16],
-            &[MENU_ICON_FG.0, MENU_ICON_FG.1, MENU_ICON_FG.2, 200]
-        );
-        // The transparent hole stays transparent.
-        assert_eq!(out[11], 0);
-    }
-
-    // A light or colorful icon must be left exactly as decoded.
-    #[test]
-    fn light_menu_icon_is_left_untouched() {
-        let pixels = [
-            200, 210, 240, 255, 180, 190, 220, 255, 0, 0, 0, 0, 0, 0, 0, 0,
-        ];
-        let pixbuf = rgba_pixbuf(2, 2, &pixels);
-        assert!(recolored_if_dark(&pixbuf).is_none());
-    }
-
-    // Nothing to recolor when the glyph is entirely transparent.
-    #[test]
-    fn fully_transparent_menu_icon_is_left_untouched() {
-        let pixels = [10, 10, 10, 0, 10, 10, 10, 0, 10, 10, 10, 0, 10, 10, 10, 0];
-        let pixbuf = rgba_pixbuf(2, 2, &pixels);
-        assert!(recolored_if_dark(&pixbuf).is_none());
-    }
-
-    #[test]
-    fn tray_level_zero_navigation_wraps() {
-        assert_eq!(move_tray_index(0, 3, &NavCmd::Left), Some(2));
-        assert_eq!(move_tray_index(2, 3, &NavCmd::Right), Some(0));
-    }
-
-    #[test]
-    fn tray_level_zero_navigation_jumps_to_ends() {
-        assert_eq!(move_tray_index(1, 3, &NavCmd::First), Some(0));
-        assert_eq!(move_tray_index(1, 3, &NavCmd::Last), Some(2));
-    }
-
-    #[test]
-    fn tray_level_zero_navigation_rejects_non_horizontal_commands() {
-        assert_eq!(move_tray_index(0, 3, &NavCmd::Down), None);
-        assert_eq!(move_tray_index(0, 0, &NavCmd::Right), None);
-    }
-}
-
-// setup_*_updates are infallible now that there is no global sender to
-// double-initialize — they only move a receiver into a glib-local drain task.
-
-pub fn setup_workspace_updates(
-    mut rx: mpsc::UnboundedReceiver<WorkspaceUpdate>,
-    label: gtk4::Label,
-    title_widget: TitleWidget,
-) {
-    debug!("Setting up workspace updates");
-
-    // Handle combined workspace updates (name + ID) in single frame
-    glib::spawn_future_local(async move {
-        while let Some(update) = rx.recv().await {
-            debug!(
-                "Updating workspace - label: '{}', color for workspace: {}",
-                update.name, update.id
-            );
-            // Update both workspace text and title color atomically
-            label.set_text(&update.name);
-            update_title_widget_workspace_color(&title_widget, update.id);
-        }
-    });
-}
-
-fn desktop_app_class_match_score(app: &gio_unix::DesktopAppInfo, class: &str) -> u8 {
-    if app
-        .startup_wm_class()
-        .is_some_and(|wm_class| wm_class.eq_ignore_ascii_case(class))
-    {
-        return 4;
-    }
-
-    if let Some(id) = app.id() {
-        let desktop_id = id.strip_suffix(".desktop").unwrap_or(&id);
-        if desktop_id.eq_ignore_ascii_case(class) {
-            return 3;
-        }
-        if desktop_id
-            .rsplit('.')
-            .next()
-            .is_some_and(|leaf| leaf.eq_ignore_ascii_case(class))
-        {
-            return 2;
-        }
-    }
-
-    u8::from(app.name().eq_ignore_ascii_case(class))
-}
-
-fn desktop_icon_for_class(class: &str) -> Option<gtk4::gio::Icon> {
-    gtk4::gio::AppInfo::all()
-        .into_iter()
-        .filter_map(|app| app.downcast::<gio_unix::DesktopAppInfo>().ok())
-        .filter_map(|app| {
-            let score = desktop_app_class_match_score(&app, class);
-            (score > 0).then_some((score, app))
-        })
-        .max_by_key(|(score, _app)| *score)
-        .and_then(|(_score, app)| app.icon())
-}
-
-fn update_title_icon(image: &gtk4::Image, class: &str) {
-    let class = class.trim();
-    if class.is_empty() {
-        image.set_visible(false);
-        return;
-    }
-
-    image.set_pixel_size(tray_icon_pixel_size(image));
-    if let Some(icon) = desktop_icon_for_class(class) {
-        image.set_from_gicon(&icon);
-        image.set_visible(true);
-        debug!(
-            class,
-            "Resolved title icon from desktop application metadata"
-        );
-        return;
-    }
-
-    let icon_theme = gtk4::IconTheme::for_display(&image.display());
-    let lowercase = class.to_lowercase();
-    let leaf = lowercase.rsplit('.').next().unwrap_or(&lowercase);
-    for candidate in [class, lowercase.as_str(), leaf] {
-        if icon_theme.has_icon(candidate) {
-            image.set_icon_name(Some(candidate));
-            image.set_visible(true);
-            debug!(
-                class,
-                icon = candidate,
-                "Resolved title icon directly from theme"
-            );
-            return;
-        }
-    }
-
-    image.set_icon_name(Some("application-x-executable-symbolic"));
-    image.set_visible(true);
-    debug!(class, "Using generic fallback for title icon");
-}
-
-pub fn setup_title_updates(
-    mut rx: mpsc::UnboundedReceiver<TitleUpdate>,
-    title_widget: TitleWidget,
-) {
-    debug!("Setting up title updates");
-
-    glib::spawn_future_local(async move {
-        let mut current_class = String::new();
-        while let Some(update) = rx.recv().await {
-            debug!(
-                title = update.title,
-                class = update.class,
-                "Updating title widget"
-            );
-            // NOTE: Title widget always remains visible even when empty, unlike battery/bluetooth widgets.
-            // This provides consistent visual layout and shows the centered position in the bar.
-            title_widget.label.set_text(&update.title);
-            if update.class != current_class {
-                update_title_icon(&title_widget.icon, &update.class);
-                current_class = update.class;
-            }
-        }
-    });
-}
-
-// Apply live desktop color-scheme changes to GTK. The appearance producer sends
-// the current preference on connect and again on every portal SettingChanged, so
-// the bar's prefer-dark follows a light/dark switch instead of only the value
-// read at startup. The `!=` guard keeps the no-op re-sends (and the initial
-// value, already applied synchronously in configure_color_scheme) from churning.
-pub fn setup_color_scheme_updates(mut rx: mpsc::UnboundedReceiver<bool>) {
-    glib::spawn_future_local(async move {
-        while let Some(prefer_dark) = rx.recv().await {
-            let Some(settings) = gtk4::Settings::default() else {
-                warn!("No default GtkSettings; cannot apply color-scheme change");
-                continue;
-            };
-            if settings.is_gtk_application_prefer_dark_theme() != prefer_dark {
-                settings.set_gtk_application_prefer_dark_theme(prefer_dark);
-                info!(prefer_dark, "Applied desktop color-scheme change to GTK");
-            }
-        }
-    });
-}
-
-pub fn setup_battery_updates(mut rx: mpsc::UnboundedReceiver<String>, label: gtk4::Label) {
-    debug!("Setting up battery updates");
-
-    glib::spawn_future_local(async move {
-        while let Some(update) = rx.recv().await {
-            debug!("Updating battery label: {}", update);
-
-            // Hide widget if no battery data, show if there is data
-            // NOTE: Originally tried CSS approach with label.add_css_class("widget-hidden")
-            // and .widget-hidden { display: none !important; } but GTK4 CSS specificity
-            // issues prevented it from working. GTK's native set_visible() works reliably.
-            if update.trim().is_empty() {
-                label.set_visible(false);
-                debug!("🙈 HIDING battery widget with set_visible(false)");
-            } else {
-                label.set_visible(true);
-                label.set_text(&update);
-                debug!("👁️  SHOWING battery widget - data: {}", update);
-            }
-        }
-    });
-}
-
-pub fn setup_bluetooth_updates(mut rx: mpsc::UnboundedReceiver<String>, label: gtk4::Label) {
-    debug!("Setting up Bluetooth battery updates");
-
-    glib::spawn_future_local(async move {
-        while let Some(update) = rx.recv().await {
-            debug!("Updating Bluetooth battery label: {}", update);
-
-            // Hide widget if no Bluetooth devices, show if there are devices
-            // NOTE: Using GTK's native set_visible() since CSS approach didn't work reliably
-            if update.trim().is_empty() {
-                label.set_visible(false);
-                debug!("🙈 HIDING Bluetooth widget - no devices");
-            } else {
-                label.set_visible(true);
-                label.set_text(&update);
-                debug!("👁️  SHOWING Bluetooth widget - data: {}", update);
-            }
-        }
-    });
-}
-
-pub fn setup_network_updates(mut rx: mpsc::UnboundedReceiver<String>, label: gtk4::Label) {
-    debug!("Setting up network updates");
-
-    glib::spawn_future_local(async move {
-        while let Some(update) = rx.recv().await {
-            debug!("Updating network label: {}", update);
-            label.set_text(&update);
-        }
-    });
-}
-
-pub fn setup_volume_updates(label: gtk4::Label) -> Result<()> {
-    debug!("Setting up volume updates with tokio async channels");
-
-    let (sender, mut receiver) = mpsc::unbounded_channel::<VolumeUpdate>();
-
-    // Start PipeWire monitoring on dedicated thread
-    pw::start_pipewire_thread(sender)?;
-
-    // Spawn async task on GTK main thread to handle volume updates
-    glib::spawn_future_local(async move {
-        debug!("🚀 Starting async volume update loop...");
-
-        while let Some(update) = receiver.recv().await {
-            // Use channel volume first (more accurate), fallback to main volume
-            if let Some(volume_percent) = update.channel_percent.or(update.volume_percent) {
-                let first_char = update.name.chars().next().unwrap_or('A');
-                let emoji = if update.is_muted == Some(true) {
-                    "🔇"
-                } else {
-                    "🔊"
-                };
-                let display_text = format!("{}{}{}", emoji, first_char, volume_percent);
-                label.set_text(&display_text);
-                debug!("📺 GTK UI updated via ASYNC: {}", display_text);
-            } else {
-                debug!("📺 Skipping GUI update - no volume data available");
-            }
-        }
-
-        debug!("⚠️ Volume update loop ended");
-    });
-
-    Ok(())
 }
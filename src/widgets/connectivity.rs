@@ -0,0 +1,384 @@
+// Connection widgets: the network menu with its Wi-Fi list and password
+// prompt, Bluetooth, VPN, hotspot, radio kill switches and bandwidth.
+
+use super::*;
+
+pub fn create_bandwidth_widget(model: &BarModel) -> gtk4::Label {
+    debug!("Creating bandwidth widget");
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "bandwidth");
+    label.add_css_class("bandwidth-widget");
+    label.set_halign(gtk4::Align::End);
+    model_property("bandwidth")
+        .chain_closure::<bool>(glib::closure!(|_: Option<glib::Object>, text: &str| !text
+            .trim()
+            .is_empty()))
+        .bind(&label, "visible", Some(model));
+    label
+}
+
+pub fn create_vpn_widget(model: &BarModel, config: &VpnConfig) -> gtk4::Button {
+    debug!("Creating VPN widget");
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "vpn");
+    let button = gtk4::Button::new();
+    button.add_css_class("vpn-widget");
+    button.set_halign(gtk4::Align::End);
+    button.set_child(Some(&label));
+    model_property("vpn")
+        .chain_closure::<bool>(glib::closure!(|_: Option<glib::Object>, text: &str| !text
+            .trim()
+            .is_empty()))
+        .bind(&button, "visible", Some(model));
+    if let Some(connection) = config.connection.clone() {
+        button.set_tooltip_text(Some(&format!("Click to toggle {connection}")));
+        button.connect_clicked(move |_| vpn::toggle_connection(&connection));
+    }
+    button
+}
+
+pub fn create_hotspot_widget(model: &BarModel) -> gtk4::Label {
+    debug!("Creating hotspot widget");
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "hotspot");
+    label.add_css_class("hotspot-widget");
+    label.set_halign(gtk4::Align::End);
+    label.set_tooltip_text(Some(
+        "📡 sharing this machine's connection (and its clients), 📱 USB tethering",
+    ));
+    model_property("hotspot")
+        .chain_closure::<bool>(glib::closure!(|_: Option<glib::Object>, text: &str| !text
+            .trim()
+            .is_empty()))
+        .bind(&label, "visible", Some(model));
+    label
+}
+
+pub fn create_rfkill_widget(model: &BarModel) -> gtk4::Button {
+    debug!("Creating rfkill widget");
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "rfkill");
+    let button = gtk4::Button::new();
+    button.add_css_class("rfkill-widget");
+    button.set_halign(gtk4::Align::End);
+    button.set_child(Some(&label));
+    model_property("rfkill-tooltip").bind(&button, "tooltip-text", Some(model));
+    model_property("rfkill")
+        .chain_closure::<bool>(glib::closure!(|_: Option<glib::Object>, text: &str| !text
+            .trim()
+            .is_empty()))
+        .bind(&button, "visible", Some(model));
+    let button_weak = button.downgrade();
+    model.connect_rfkill_blocked_notify(move |model| {
+        let Some(button) = button_weak.upgrade() else {
+            return;
+        };
+        if model.rfkill_blocked() {
+            button.add_css_class("blocked");
+        } else {
+            button.remove_css_class("blocked");
+        }
+    });
+    button.connect_clicked(|_| rfkill::toggle_airplane_mode());
+    button
+}
+
+pub fn create_bt_widget(model: &BarModel) -> gtk4::Label {
+    debug!("Creating bluetooth widget");
+    let label = gtk4::Label::new(None); // Start with no text, will be hidden until devices found
+    label.add_css_class("bt-widget");
+    label.set_halign(gtk4::Align::End);
+    bind_label_text(&label, model, "bluetooth");
+    bind_label_visible_when_non_empty(&label, model, "bluetooth");
+    let label_weak = label.downgrade();
+    model.connect_bluetooth_connecting_notify(move |model| {
+        let Some(label) = label_weak.upgrade() else {
+            return;
+        };
+        if model.bluetooth_connecting() {
+            label.add_css_class("connecting");
+        } else {
+            label.remove_css_class("connecting");
+        }
+    });
+    label
+}
+
+// With a Wi-Fi picker: opening the popover lists the networks NetworkManager
+// already sees and asks for a scan, and the list refills once it is done. A
+// network that needs a password opens a field for it under the list.
+pub fn create_network_widget(model: &BarModel) -> gtk4::MenuButton {
+    debug!("Creating network widget");
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "network");
+
+    let list = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+    let prompt = gtk4::Label::new(None);
+    prompt.set_xalign(0.0);
+    let entry = gtk4::PasswordEntry::new();
+    entry.set_show_peek_icon(true);
+    let form = gtk4::Box::new(gtk4::Orientation::Vertical, 4);
+    form.add_css_class("wifi-password");
+    form.append(&prompt);
+    form.append(&entry);
+    form.set_visible(false);
+
+    let menu = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+    menu.add_css_class("tray-menu");
+    menu.append(&list);
+    menu.append(&form);
+    let popover = gtk4::Popover::new();
+    popover.set_has_arrow(false);
+    popover.set_child(Some(&menu));
+
+    let password = WifiPassword {
+        form: form.downgrade(),
+        prompt: prompt.downgrade(),
+        entry: entry.downgrade(),
+        pending: Rc::default(),
+    };
+    let pending = password.pending.clone();
+    let popover_weak = popover.downgrade();
+    entry.connect_activate(move |entry| {
+        let Some(access_point) = pending.take() else {
+            return;
+        };
+        network::connect_access_point(access_point, Some(entry.text().to_string()));
+        entry.set_text("");
+        if let Some(popover) = popover_weak.upgrade() {
+            popover.popdown();
+        }
+    });
+
+    // Typing the password needs keyboard focus, which the bar surface only
+    // takes on demand while the popover is up. Dropping the receiver when the
+    // popover is gone ends the scan's wait.
+    let list_weak = list.downgrade();
+    popover.connect_map(move |popover| {
+        set_bar_keyboard_mode(popover, KeyboardMode::OnDemand);
+        let Some(list) = list_weak.upgrade() else {
+            return;
+        };
+        password.hide();
+        show_wifi_message(&list, "Scanning…");
+        let (updates, mut access_points) = mpsc::unbounded_channel();
+        network::scan_access_points(updates);
+        let list_weak = list.downgrade();
+        let popover_weak = popover.downgrade();
+        let password = password.clone();
+        glib::spawn_future_local(async move {
+            let mut listed = false;
+            while let Some(update) = access_points.recv().await {
+                let (Some(list), Some(popover)) = (list_weak.upgrade(), popover_weak.upgrade())
+                else {
+                    return;
+                };
+                if !popover.is_visible() {
+                    return;
+                }
+                fill_wifi_list(&list, &popover, &password, update);
+                listed = true;
+            }
+            if !listed && let Some(list) = list_weak.upgrade() {
+                show_wifi_message(&list, "Wi-Fi unavailable");
+            }
+        });
+    });
+    popover.connect_closed(|popover| set_bar_keyboard_mode(popover, KeyboardMode::None));
+
+    let button = gtk4::MenuButton::new();
+    button.add_css_class("network-widget");
+    button.set_halign(gtk4::Align::End);
+    button.set_always_show_arrow(false);
+    button.set_child(Some(&label));
+    button.set_popover(Some(&popover));
+    button
+}
+
+// The password field under the Wi-Fi list and the network it is for.
+#[derive(Clone)]
+struct WifiPassword {
+    form: glib::WeakRef<gtk4::Box>,
+    prompt: glib::WeakRef<gtk4::Label>,
+    entry: glib::WeakRef<gtk4::PasswordEntry>,
+    pending: Rc<RefCell<Option<network::AccessPoint>>>,
+}
+
+impl WifiPassword {
+    fn ask(&self, access_point: network::AccessPoint) {
+        let (Some(form), Some(prompt), Some(entry)) = (
+            self.form.upgrade(),
+            self.prompt.upgrade(),
+            self.entry.upgrade(),
+        ) else {
+            return;
+        };
+        prompt.set_text(&format!("Password for {}", access_point.ssid));
+        entry.set_text("");
+        form.set_visible(true);
+        entry.grab_focus();
+        self.pending.replace(Some(access_point));
+    }
+
+    fn hide(&self) {
+        self.pending.take();
+        if let Some(form) = self.form.upgrade() {
+            form.set_visible(false);
+        }
+    }
+}
+
+fn show_wifi_message(list: &gtk4::Box, text: &str) {
+    while let Some(child) = list.first_child() {
+        list.remove(&child);
+    }
+    let message = gtk4::Label::new(Some(text));
+    message.add_css_class("tray-menu-item");
+    list.append(&message);
+}
+
+fn fill_wifi_list(
+    list: &gtk4::Box,
+    popover: &gtk4::Popover,
+    password: &WifiPassword,
+    access_points: Vec<network::AccessPoint>,
+) {
+    if access_points.is_empty() {
+        show_wifi_message(list, "No Wi-Fi networks");
+        return;
+    }
+    while let Some(child) = list.first_child() {
+        list.remove(&child);
+    }
+    for access_point in access_points {
+        let button = gtk4::Button::with_label(&access_point.menu_text());
+        button.add_css_class("tray-menu-item");
+        let password = password.clone();
+        let popover_weak = popover.downgrade();
+        button.connect_clicked(move |_| {
+            if access_point.needs_password() {
+                password.ask(access_point.clone());
+                return;
+            }
+            if !access_point.active {
+                network::connect_access_point(access_point.clone(), None);
+            }
+            if let Some(popover) = popover_weak.upgrade() {
+                popover.popdown();
+            }
+        });
+        list.append(&button);
+    }
+}
+
+pub fn setup_bluetooth_updates(
+    mut rx: channel::Receiver<BluetoothUpdate>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up Bluetooth battery updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            debug!(
+                "Queueing Bluetooth battery label: {} (connecting: {})",
+                update.text, update.connecting
+            );
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::Bluetooth, update, move |update| {
+                model.set_bluetooth(update.text);
+                model.set_bluetooth_connecting(update.connecting);
+            });
+        }
+    });
+}
+
+pub fn setup_network_updates(
+    mut rx: channel::Receiver<String>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up network updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            debug!("Queueing network label: {}", update);
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::Network, update, move |update| {
+                model.set_network(update)
+            });
+        }
+    });
+}
+
+pub fn setup_bandwidth_updates(
+    mut rx: channel::Receiver<String>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up bandwidth updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            trace!("Queueing bandwidth label: {}", update);
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::Bandwidth, update, move |update| {
+                model.set_bandwidth(update)
+            });
+        }
+    });
+}
+
+pub fn setup_vpn_updates(
+    mut rx: channel::Receiver<String>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up VPN updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            debug!("Queueing VPN label: {}", update);
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::Vpn, update, move |update| model.set_vpn(update));
+        }
+    });
+}
+
+pub fn setup_hotspot_updates(
+    mut rx: channel::Receiver<String>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up hotspot updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            debug!("Queueing hotspot label: {}", update);
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::Hotspot, update, move |update| {
+                model.set_hotspot(update)
+            });
+        }
+    });
+}
+
+pub fn setup_rfkill_updates(
+    mut rx: channel::Receiver<RfkillUpdate>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up rfkill updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            debug!(?update, "Queueing rfkill label");
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::Rfkill, update, move |update| {
+                model.set_rfkill(update.text);
+                model.set_rfkill_blocked(update.blocked);
+                model.set_rfkill_tooltip(update.tooltip);
+            });
+        }
+    });
+}
@@ -0,0 +1,274 @@
+// The popover menus that act rather than report: the power menu and the
+// quick-settings panel with its sliders, radio switches and power profiles.
+
+use super::*;
+
+// The power button. Its popover lists the actions; with `[power] confirm` the
+// ones that end the session swap the list for a yes/no page first, and
+// closing the popover goes back to the list.
+pub fn create_power_widget(config: &PowerConfig) -> gtk4::MenuButton {
+    debug!("Creating power widget");
+    let config = Rc::new(config.clone());
+    let pending = Rc::new(Cell::new(None::<PowerAction>));
+
+    let actions = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+    let question = gtk4::Label::new(None);
+    question.add_css_class("power-question");
+    let cancel = gtk4::Button::with_label("Cancel");
+    cancel.add_css_class("tray-menu-item");
+    let proceed = gtk4::Button::new();
+    proceed.add_css_class("tray-menu-item");
+    proceed.add_css_class("power-confirm");
+    let answers = gtk4::Box::new(gtk4::Orientation::Horizontal, 4);
+    answers.set_homogeneous(true);
+    answers.append(&cancel);
+    answers.append(&proceed);
+    let confirm = gtk4::Box::new(gtk4::Orientation::Vertical, 4);
+    confirm.append(&question);
+    confirm.append(&answers);
+
+    let stack = gtk4::Stack::new();
+    stack.add_css_class("tray-menu");
+    stack.add_named(&actions, Some("actions"));
+    stack.add_named(&confirm, Some("confirm"));
+    let popover = gtk4::Popover::new();
+    popover.set_has_arrow(false);
+    popover.set_child(Some(&stack));
+    let stack_weak = stack.downgrade();
+    popover.connect_closed(move |_| {
+        if let Some(stack) = stack_weak.upgrade() {
+            stack.set_visible_child_name("actions");
+        }
+    });
+
+    for action in PowerAction::ALL {
+        let button = gtk4::Button::with_label(action.label());
+        button.add_css_class("tray-menu-item");
+        let config = config.clone();
+        let pending = pending.clone();
+        let popover_weak = popover.downgrade();
+        let stack_weak = stack.downgrade();
+        let question_weak = question.downgrade();
+        let proceed_weak = proceed.downgrade();
+        button.connect_clicked(move |_| {
+            if config.confirm && action.needs_confirmation() {
+                let (Some(stack), Some(question), Some(proceed)) = (
+                    stack_weak.upgrade(),
+                    question_weak.upgrade(),
+                    proceed_weak.upgrade(),
+                ) else {
+                    return;
+                };
+                pending.set(Some(action));
+                question.set_text(&format!("{}?", action.label()));
+                proceed.set_label(action.label());
+                stack.set_visible_child_name("confirm");
+                return;
+            }
+            if let Some(popover) = popover_weak.upgrade() {
+                popover.popdown();
+            }
+            power::run(action, &config);
+        });
+        actions.append(&button);
+    }
+
+    let stack_weak = stack.downgrade();
+    cancel.connect_clicked(move |_| {
+        if let Some(stack) = stack_weak.upgrade() {
+            stack.set_visible_child_name("actions");
+        }
+    });
+    let popover_weak = popover.downgrade();
+    proceed.connect_clicked(move |_| {
+        let Some(action) = pending.take() else {
+            return;
+        };
+        if let Some(popover) = popover_weak.upgrade() {
+            popover.popdown();
+        }
+        power::run(action, &config);
+    });
+
+    let button = gtk4::MenuButton::new();
+    button.add_css_class("power-widget");
+    button.set_halign(gtk4::Align::End);
+    button.set_always_show_arrow(false);
+    button.set_label("\u{23fb}");
+    button.set_tooltip_text(Some("Power"));
+    button.set_popover(Some(&popover));
+    button
+}
+
+// One row of the quick-settings popover: an icon and its control.
+fn quick_settings_row(icon: &str, control: &impl IsA<gtk4::Widget>) -> gtk4::Box {
+    let row = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+    row.add_css_class("quick-settings-row");
+    let icon = gtk4::Label::new(Some(icon));
+    icon.add_css_class("quick-settings-icon");
+    row.append(&icon);
+    control.set_hexpand(true);
+    row.append(control);
+    row
+}
+
+// A 0-100 slider that calls `set` for the user's own moves only: change-value
+// is not emitted when the model moves it.
+fn quick_settings_slider(icon: &str, set: fn(u8)) -> (gtk4::Box, gtk4::Scale) {
+    let scale = gtk4::Scale::with_range(gtk4::Orientation::Horizontal, 0.0, 100.0, 1.0);
+    scale.set_draw_value(false);
+    scale.connect_change_value(move |scale, _, value| {
+        let percent = value.round().clamp(0.0, 100.0);
+        if percent != scale.value().round() {
+            set(percent as u8);
+        }
+        glib::Propagation::Proceed
+    });
+    let row = quick_settings_row(icon, &scale);
+    (row, scale)
+}
+
+// -1 hides the row (no sink, no backlight).
+fn show_quick_level(row: &gtk4::Box, scale: &gtk4::Scale, level: i32) {
+    row.set_visible(level >= 0);
+    if level >= 0 {
+        scale.set_value(f64::from(level));
+    }
+}
+
+// A radio switch, turned on and off through rfkill. Its position is read
+// again each time the popover opens; the handler is blocked meanwhile so the
+// refresh does not write a block of its own.
+fn quick_settings_switch(popover: &gtk4::Popover, icon: &str, kind: rfkill::Kind) -> gtk4::Box {
+    let switch = gtk4::Switch::new();
+    switch.set_halign(gtk4::Align::End);
+    let handler = switch.connect_state_set(move |_, on| {
+        rfkill::set_blocked(kind, !on);
+        glib::Propagation::Proceed
+    });
+    let row = quick_settings_row(icon, &switch);
+    let row_weak = row.downgrade();
+    let switch_weak = switch.downgrade();
+    popover.connect_map(move |_| {
+        let (Some(row), Some(switch)) = (row_weak.upgrade(), switch_weak.upgrade()) else {
+            return;
+        };
+        let blocked = rfkill::soft_blocked(kind);
+        row.set_visible(blocked.is_some());
+        switch.block_signal(&handler);
+        switch.set_active(blocked == Some(false));
+        switch.unblock_signal(&handler);
+    });
+    row
+}
+
+// The power profile picker, filled from power-profiles-daemon each time the
+// popover opens and hidden without it.
+fn quick_settings_profiles(popover: &gtk4::Popover) -> gtk4::Box {
+    let profiles = gtk4::DropDown::from_strings(&[]);
+    let names = Rc::new(RefCell::new(Vec::<String>::new()));
+    let picked = names.clone();
+    let handler = Rc::new(profiles.connect_selected_notify(move |profiles| {
+        let Some(name) = picked.borrow().get(profiles.selected() as usize).cloned() else {
+            return;
+        };
+        quick_settings::set_power_profile(name);
+    }));
+    let row = quick_settings_row("⚡", &profiles);
+    row.set_visible(false);
+    let row_weak = row.downgrade();
+    let profiles_weak = profiles.downgrade();
+    popover.connect_map(move |_| {
+        let (updates, mut loaded) = mpsc::unbounded_channel();
+        quick_settings::load_power_profiles(updates);
+        let row_weak = row_weak.clone();
+        let profiles_weak = profiles_weak.clone();
+        let names = names.clone();
+        let handler = handler.clone();
+        glib::spawn_future_local(async move {
+            let update = loaded.recv().await;
+            let (Some(row), Some(profiles)) = (row_weak.upgrade(), profiles_weak.upgrade()) else {
+                return;
+            };
+            let Some(update) = update.filter(|update| !update.available.is_empty()) else {
+                row.set_visible(false);
+                return;
+            };
+            let labels: Vec<String> = update
+                .available
+                .iter()
+                .map(|name| quick_settings::profile_label(name))
+                .collect();
+            let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+            let active = update
+                .available
+                .iter()
+                .position(|name| *name == update.active);
+            profiles.block_signal(&handler);
+            profiles.set_model(Some(&gtk4::StringList::new(&labels)));
+            profiles.set_selected(active.map_or(gtk4::INVALID_LIST_POSITION, |index| index as u32));
+            profiles.unblock_signal(&handler);
+            *names.borrow_mut() = update.available;
+            row.set_visible(true);
+        });
+    });
+    row
+}
+
+// A gear whose popover gathers volume, brightness, the Wi-Fi and Bluetooth
+// radios and the power profile (see quick_settings.rs). A row whose backend is
+// missing stays hidden.
+pub fn create_quick_settings_widget(model: &BarModel) -> gtk4::MenuButton {
+    debug!("Creating quick settings widget");
+    let popover = gtk4::Popover::new();
+    popover.set_has_arrow(false);
+
+    let (volume_row, volume) = quick_settings_slider("🔊", quick_settings::set_volume);
+    show_quick_level(&volume_row, &volume, model.quick_volume());
+    let row_weak = volume_row.downgrade();
+    let scale_weak = volume.downgrade();
+    model.connect_quick_volume_notify(move |model| {
+        let (Some(row), Some(scale)) = (row_weak.upgrade(), scale_weak.upgrade()) else {
+            return;
+        };
+        show_quick_level(&row, &scale, model.quick_volume());
+    });
+
+    let (brightness_row, brightness) = quick_settings_slider("☀", backlight::set_brightness);
+    show_quick_level(&brightness_row, &brightness, model.quick_brightness());
+    let row_weak = brightness_row.downgrade();
+    let scale_weak = brightness.downgrade();
+    model.connect_quick_brightness_notify(move |model| {
+        let (Some(row), Some(scale)) = (row_weak.upgrade(), scale_weak.upgrade()) else {
+            return;
+        };
+        show_quick_level(&row, &scale, model.quick_brightness());
+    });
+
+    let menu = gtk4::Box::new(gtk4::Orientation::Vertical, 6);
+    menu.add_css_class("tray-menu");
+    menu.add_css_class("quick-settings");
+    menu.append(&volume_row);
+    menu.append(&brightness_row);
+    menu.append(&quick_settings_switch(
+        &popover,
+        "Wi-Fi",
+        rfkill::Kind::Wifi,
+    ));
+    menu.append(&quick_settings_switch(
+        &popover,
+        "BT",
+        rfkill::Kind::Bluetooth,
+    ));
+    menu.append(&quick_settings_profiles(&popover));
+    popover.set_child(Some(&menu));
+
+    let button = gtk4::MenuButton::new();
+    button.add_css_class("quick-settings-widget");
+    button.set_halign(gtk4::Align::End);
+    button.set_always_show_arrow(false);
+    button.set_label("⚙");
+    button.set_tooltip_text(Some("Quick settings"));
+    button.set_popover(Some(&popover));
+    button
+}
@@ -0,0 +1,154 @@
+// The clock with its alarm popover, and the per-minute (or per-second, for a
+// format that shows them) tick that keeps the model's time current.
+
+use super::*;
+
+// The clock, with a popover for one-shot alarms: the pending ones (each with a
+// remove button) and a time + label row to add another.
+pub fn create_time_widget(model: &BarModel, alarms: &AlarmBook) -> gtk4::Widget {
+    debug!("Creating time widget");
+    model.set_time(get_current_time());
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "time");
+
+    let list = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+    let time_entry = gtk4::Entry::new();
+    time_entry.set_placeholder_text(Some("07:30"));
+    time_entry.set_max_width_chars(5);
+    let label_entry = gtk4::Entry::new();
+    label_entry.set_placeholder_text(Some("Label"));
+    let add_button = gtk4::Button::with_label("Add alarm");
+    add_button.add_css_class("tray-menu-item");
+
+    let form = gtk4::Box::new(gtk4::Orientation::Horizontal, 4);
+    form.add_css_class("alarm-form");
+    form.append(&time_entry);
+    form.append(&label_entry);
+    form.append(&add_button);
+
+    let menu = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+    menu.add_css_class("tray-menu");
+    menu.append(&list);
+    menu.append(&form);
+    let popover = gtk4::Popover::new();
+    popover.set_has_arrow(false);
+    popover.set_child(Some(&menu));
+
+    let add = {
+        let alarms = alarms.clone();
+        let list = list.downgrade();
+        let time_entry = time_entry.downgrade();
+        let label_entry = label_entry.downgrade();
+        move || {
+            let (Some(list), Some(time_entry), Some(label_entry)) =
+                (list.upgrade(), time_entry.upgrade(), label_entry.upgrade())
+            else {
+                return;
+            };
+            match alarms.add(&time_entry.text(), &label_entry.text()) {
+                Ok(_) => {
+                    time_entry.remove_css_class("error");
+                    time_entry.set_text("");
+                    label_entry.set_text("");
+                    fill_alarm_list(&list, &alarms);
+                }
+                Err(e) => {
+                    debug!("Rejected alarm: {:#}", e);
+                    time_entry.add_css_class("error");
+                }
+            }
+        }
+    };
+    let add = Rc::new(add);
+    let add_clicked = add.clone();
+    add_button.connect_clicked(move |_| add_clicked());
+    let add_activated = add.clone();
+    time_entry.connect_activate(move |_| add_activated());
+    label_entry.connect_activate(move |_| add());
+
+    // The list is rebuilt on every open, so alarms that rang while the popover
+    // was closed are gone from it. Typing needs keyboard focus, which the bar
+    // surface only takes on demand while the popover is up.
+    let alarms = alarms.clone();
+    let list_weak = list.downgrade();
+    popover.connect_map(move |popover| {
+        if let Some(list) = list_weak.upgrade() {
+            fill_alarm_list(&list, &alarms);
+        }
+        set_bar_keyboard_mode(popover, KeyboardMode::OnDemand);
+    });
+    popover.connect_closed(|popover| set_bar_keyboard_mode(popover, KeyboardMode::None));
+
+    let button = gtk4::MenuButton::new();
+    button.add_css_class("time-widget");
+    button.set_halign(gtk4::Align::End);
+    button.set_always_show_arrow(false);
+    button.set_child(Some(&label));
+    button.set_popover(Some(&popover));
+    button.upcast()
+}
+
+fn fill_alarm_list(list: &gtk4::Box, alarms: &AlarmBook) {
+    while let Some(child) = list.first_child() {
+        list.remove(&child);
+    }
+    for alarm in alarms.alarms() {
+        list.append(&alarm_row(list, alarms, alarm));
+    }
+}
+
+fn alarm_row(list: &gtk4::Box, alarms: &AlarmBook, alarm: Alarm) -> gtk4::Box {
+    let row = gtk4::Box::new(gtk4::Orientation::Horizontal, 4);
+    let text = if alarm.label.is_empty() {
+        alarm.time_text()
+    } else {
+        format!("{}  {}", alarm.time_text(), alarm.label)
+    };
+    let label = gtk4::Label::new(Some(&text));
+    label.set_hexpand(true);
+    label.set_xalign(0.0);
+    let remove = gtk4::Button::with_label("✕");
+    remove.add_css_class("tray-menu-item");
+
+    let alarms = alarms.clone();
+    let list = list.downgrade();
+    remove.connect_clicked(move |_| {
+        alarms.remove(&alarm);
+        if let Some(list) = list.upgrade() {
+            fill_alarm_list(&list, &alarms);
+        }
+    });
+    row.append(&label);
+    row.append(&remove);
+    row
+}
+
+pub fn get_current_time() -> String {
+    Local::now().format("%l:%M %p").to_string()
+}
+
+pub fn update_time_widget(
+    model: BarModel,
+    format: String,
+    resumes: tokio::sync::watch::Receiver<u64>,
+) {
+    debug!("Setting up time widget updates");
+
+    let seconds = clock::format_has_seconds(&format);
+    let model_weak = model.downgrade();
+    let update = move |now: chrono::DateTime<Local>| {
+        let Some(model) = model_weak.upgrade() else {
+            return;
+        };
+
+        let text = now.format(&format).to_string();
+        debug!("Updating time label: {text}");
+        model.set_time(text);
+    };
+    let ticker = Clock::new().resync_on(resumes);
+    if seconds {
+        ticker.on_second(update).start();
+    } else {
+        ticker.on_minute(update).start();
+    }
+}
@@ -0,0 +1,396 @@
+// Widgets reading the machine itself: displays, CPU frequency, GPU, load
+// average, lock keys, keyboard backlight and battery.
+
+use super::*;
+
+// Output count, with a popover of the configured display profiles (kanshi,
+// wlr-randr, hyprctl keyword monitor ... — any shell command). Without profiles
+// it is a plain count pill.
+pub fn create_displays_widget(model: &BarModel, config: &DisplaysConfig) -> gtk4::Widget {
+    debug!("Creating displays widget");
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "displays");
+
+    if config.profiles.is_empty() {
+        label.add_css_class("displays-widget");
+        label.set_halign(gtk4::Align::End);
+        return label.upcast();
+    }
+
+    let menu = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+    menu.add_css_class("tray-menu");
+    let popover = gtk4::Popover::new();
+    popover.set_has_arrow(false);
+    popover.set_child(Some(&menu));
+    for profile in &config.profiles {
+        let button = gtk4::Button::with_label(&profile.name);
+        button.add_css_class("tray-menu-item");
+        let command = profile.command.clone();
+        let popover_weak = popover.downgrade();
+        button.connect_clicked(move |_| {
+            command::spawn_shell(&command, "display profile");
+            if let Some(popover) = popover_weak.upgrade() {
+                popover.popdown();
+            }
+        });
+        menu.append(&button);
+    }
+
+    let button = gtk4::MenuButton::new();
+    button.add_css_class("displays-widget");
+    button.set_halign(gtk4::Align::End);
+    button.set_always_show_arrow(false);
+    button.set_child(Some(&label));
+    button.set_popover(Some(&popover));
+    button.upcast()
+}
+
+pub fn create_cpufreq_widget(model: &BarModel, config: &CpuFreqConfig) -> gtk4::Button {
+    debug!("Creating CPU frequency widget");
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "cpufreq");
+    let button = gtk4::Button::new();
+    button.add_css_class("cpufreq-widget");
+    button.set_halign(gtk4::Align::End);
+    button.set_tooltip_text(Some("Click to switch the CPU governor"));
+    button.set_child(Some(&label));
+    // The button, not the label, has to hide: an empty button still draws.
+    model_property("cpufreq")
+        .chain_closure::<bool>(glib::closure!(|_: Option<glib::Object>, text: &str| !text
+            .trim()
+            .is_empty()))
+        .bind(&button, "visible", Some(model));
+    let governor_command = config.governor_command.clone();
+    button.connect_clicked(move |_| cpufreq::cycle_governor(&governor_command));
+    button
+}
+
+pub fn create_gpu_widget(model: &BarModel) -> gtk4::Label {
+    debug!("Creating GPU widget");
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "gpu");
+    label.add_css_class("gpu-widget");
+    label.set_halign(gtk4::Align::End);
+    model_property("gpu")
+        .chain_closure::<bool>(glib::closure!(|_: Option<glib::Object>, text: &str| !text
+            .trim()
+            .is_empty()))
+        .bind(&label, "visible", Some(model));
+    let label_weak = label.downgrade();
+    model.connect_gpu_warning_notify(move |model| {
+        let Some(label) = label_weak.upgrade() else {
+            return;
+        };
+        if model.gpu_warning() {
+            label.add_css_class("warning");
+        } else {
+            label.remove_css_class("warning");
+        }
+    });
+    label
+}
+
+pub fn create_loadavg_widget(model: &BarModel) -> gtk4::MenuButton {
+    debug!("Creating load average widget");
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "loadavg");
+
+    let detail = gtk4::Label::new(None);
+    detail.add_css_class("loadavg-detail");
+    detail.set_xalign(0.0);
+    bind_label_text(&detail, model, "loadavg-detail");
+    let heading = gtk4::Label::new(Some("Load average"));
+    heading.add_css_class("loadavg-heading");
+    heading.set_xalign(0.0);
+    let menu = gtk4::Box::new(gtk4::Orientation::Vertical, 4);
+    menu.add_css_class("tray-menu");
+    menu.append(&heading);
+    menu.append(&detail);
+    let popover = gtk4::Popover::new();
+    popover.set_has_arrow(false);
+    popover.set_child(Some(&menu));
+
+    let button = gtk4::MenuButton::new();
+    button.add_css_class("loadavg-widget");
+    button.set_halign(gtk4::Align::End);
+    button.set_always_show_arrow(false);
+    button.set_child(Some(&label));
+    button.set_popover(Some(&popover));
+    model_property("loadavg")
+        .chain_closure::<bool>(glib::closure!(|_: Option<glib::Object>, text: &str| !text
+            .trim()
+            .is_empty()))
+        .bind(&button, "visible", Some(model));
+    let button_weak = button.downgrade();
+    model.connect_loadavg_warning_notify(move |model| {
+        let Some(button) = button_weak.upgrade() else {
+            return;
+        };
+        if model.loadavg_warning() {
+            button.add_css_class("warning");
+        } else {
+            button.remove_css_class("warning");
+        }
+    });
+    let button_weak = button.downgrade();
+    model.connect_loadavg_critical_notify(move |model| {
+        let Some(button) = button_weak.upgrade() else {
+            return;
+        };
+        if model.loadavg_critical() {
+            button.add_css_class("critical");
+        } else {
+            button.remove_css_class("critical");
+        }
+    });
+    button
+}
+
+pub fn create_lock_keys_widget(model: &BarModel) -> gtk4::Label {
+    debug!("Creating lock keys widget");
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "lock-keys");
+    label.add_css_class("lock-keys-widget");
+    label.set_halign(gtk4::Align::End);
+    model_property("lock-keys")
+        .chain_closure::<bool>(glib::closure!(|_: Option<glib::Object>, text: &str| !text
+            .trim()
+            .is_empty()))
+        .bind(&label, "visible", Some(model));
+    label
+}
+
+// Scroll steps are sent once they pause for this long, so a flick is one
+// SetBrightness with the final level rather than a burst of calls that may
+// land out of order.
+const KBD_BACKLIGHT_SCROLL_SETTLE: Duration = Duration::from_millis(150);
+
+pub fn create_kbd_backlight_widget(model: &BarModel) -> gtk4::Label {
+    debug!("Creating keyboard backlight widget");
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "kbd-backlight");
+    label.add_css_class("kbd-backlight-widget");
+    label.set_halign(gtk4::Align::End);
+    label.set_tooltip_text(Some("Scroll to change the keyboard backlight"));
+    model_property("kbd-backlight")
+        .chain_closure::<bool>(glib::closure!(|_: Option<glib::Object>, text: &str| !text
+            .trim()
+            .is_empty()))
+        .bind(&label, "visible", Some(model));
+
+    let scroll = gtk4::EventControllerScroll::new(
+        gtk4::EventControllerScrollFlags::VERTICAL | gtk4::EventControllerScrollFlags::DISCRETE,
+    );
+    let model_weak = model.downgrade();
+    let pending = Rc::new(Cell::new(false));
+    scroll.connect_scroll(move |_, _dx, dy| {
+        let Some(model) = model_weak.upgrade() else {
+            return glib::Propagation::Proceed;
+        };
+        let max = model.kbd_backlight_max();
+        if max <= 0 || dy == 0.0 {
+            return glib::Propagation::Proceed;
+        }
+        // Scrolling up brightens.
+        let step = if dy < 0.0 { 1 } else { -1 };
+        let level = (model.kbd_backlight_level() + step).clamp(0, max);
+        if level == model.kbd_backlight_level() {
+            return glib::Propagation::Stop;
+        }
+        model.set_kbd_backlight_level(level);
+        if !pending.replace(true) {
+            let model_weak = model.downgrade();
+            let pending = pending.clone();
+            glib::timeout_add_local_once(KBD_BACKLIGHT_SCROLL_SETTLE, move || {
+                pending.set(false);
+                if let Some(model) = model_weak.upgrade() {
+                    kbd_backlight::set_brightness(model.kbd_backlight_level());
+                }
+            });
+        }
+        glib::Propagation::Stop
+    });
+    label.add_controller(scroll);
+    label
+}
+
+pub fn create_battery_widget(model: &BarModel) -> gtk4::Label {
+    debug!("Creating battery widget");
+    let label = gtk4::Label::new(None);
+    label.add_css_class("battery-widget");
+    label.set_halign(gtk4::Align::End);
+    bind_label_text(&label, model, "battery");
+    bind_label_visible_when_non_empty(&label, model, "battery");
+    let label_weak = label.downgrade();
+    model.connect_battery_warning_notify(move |model| {
+        let Some(label) = label_weak.upgrade() else {
+            return;
+        };
+        if model.battery_warning() {
+            label.add_css_class("warning");
+        } else {
+            label.remove_css_class("warning");
+        }
+    });
+    let label_weak = label.downgrade();
+    model.connect_battery_critical_notify(move |model| {
+        let Some(label) = label_weak.upgrade() else {
+            return;
+        };
+        if model.battery_critical() {
+            label.add_css_class("critical");
+        } else {
+            label.remove_css_class("critical");
+        }
+    });
+    label
+}
+
+// Alerts go out as they arrive rather than through the dispatcher: a
+// notification is no frame's business.
+pub fn setup_battery_updates(
+    mut rx: channel::Receiver<BatteryUpdate>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+    application: &gtk4::Application,
+    config: BatteryConfig,
+) {
+    debug!("Setting up battery updates");
+
+    let application = application.downgrade();
+    glib::spawn_future_local(async move {
+        let mut alerts = BatteryAlerts::default();
+        let mut animation = ChargingAnimation::new(model.clone(), &config);
+        while let Some(update) = rx.recv().await {
+            debug!("Queueing battery label: {}", update.text);
+            let level = battery::level(&update, &config);
+            if let Some(alert) = alerts.check(level)
+                && config.notify
+                && let Some(application) = application.upgrade()
+            {
+                battery::send_alert(&application, alert, &update);
+            }
+            let text = animation.text(&update);
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::Battery, (text, level), move |(text, level)| {
+                model.set_battery(text);
+                model.set_battery_warning(level == BatteryLevel::Warning);
+                model.set_battery_critical(level == BatteryLevel::Critical);
+            });
+        }
+    });
+}
+
+pub fn setup_displays_updates(
+    mut rx: channel::Receiver<String>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up displays updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            debug!("Queueing displays label: {}", update);
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::Displays, update, move |update| {
+                model.set_displays(update)
+            });
+        }
+    });
+}
+
+pub fn setup_cpufreq_updates(
+    mut rx: channel::Receiver<String>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up CPU frequency updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            debug!("Queueing CPU frequency label: {}", update);
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::CpuFreq, update, move |update| {
+                model.set_cpufreq(update)
+            });
+        }
+    });
+}
+
+pub fn setup_gpu_updates(
+    mut rx: channel::Receiver<GpuUpdate>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up GPU updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            debug!(?update, "Queueing GPU label");
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::Gpu, update, move |update| {
+                model.set_gpu(update.text);
+                model.set_gpu_warning(update.warning);
+            });
+        }
+    });
+}
+
+pub fn setup_loadavg_updates(
+    mut rx: channel::Receiver<LoadAvgUpdate>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up load average updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            debug!(?update, "Queueing load average label");
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::LoadAvg, update, move |update| {
+                model.set_loadavg(update.text);
+                model.set_loadavg_detail(update.detail);
+                model.set_loadavg_warning(update.warning);
+                model.set_loadavg_critical(update.critical);
+            });
+        }
+    });
+}
+
+pub fn setup_lock_keys_updates(
+    mut rx: channel::Receiver<String>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up lock keys updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            debug!("Queueing lock keys label: {}", update);
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::LockKeys, update, move |update| {
+                model.set_lock_keys(update)
+            });
+        }
+    });
+}
+
+pub fn setup_kbd_backlight_updates(
+    mut rx: channel::Receiver<KbdBacklightUpdate>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up keyboard backlight updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            debug!(?update, "Queueing keyboard backlight label");
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::KbdBacklight, update, move |update| {
+                model.set_kbd_backlight(update.text);
+                model.set_kbd_backlight_level(update.level);
+                model.set_kbd_backlight_max(update.max);
+            });
+        }
+    });
+}
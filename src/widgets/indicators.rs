@@ -0,0 +1,318 @@
+// Status indicators for services and user tools: stylesheet errors, progress
+// reports, notifications, systemd units, package updates, clipboard history,
+// the pomodoro timer, screen capture and `[[file_watch]]` widgets.
+
+use super::*;
+
+pub fn create_style_errors_widget(model: &BarModel) -> gtk4::Label {
+    debug!("Creating stylesheet errors widget");
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "style-errors");
+    label.add_css_class("style-errors-widget");
+    label.set_halign(gtk4::Align::End);
+    model_property("style-errors-detail").bind(&label, "tooltip-text", Some(model));
+    model_property("style-errors")
+        .chain_closure::<bool>(glib::closure!(|_: Option<glib::Object>, text: &str| !text
+            .trim()
+            .is_empty()))
+        .bind(&label, "visible", Some(model));
+    label
+}
+
+pub fn create_progress_widget(model: &BarModel) -> gtk4::Box {
+    debug!("Creating progress widget");
+    let chip = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+    chip.add_css_class("progress-widget");
+    chip.set_halign(gtk4::Align::End);
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "progress");
+    let bar = gtk4::ProgressBar::new();
+    bar.set_valign(gtk4::Align::Center);
+    model_property("progress-fraction").bind(&bar, "fraction", Some(model));
+    chip.append(&label);
+    chip.append(&bar);
+    model_property("progress-tooltip").bind(&chip, "tooltip-text", Some(model));
+    model_property("progress")
+        .chain_closure::<bool>(glib::closure!(|_: Option<glib::Object>, text: &str| !text
+            .trim()
+            .is_empty()))
+        .bind(&chip, "visible", Some(model));
+    chip
+}
+
+pub fn create_notifications_widget(model: &BarModel) -> gtk4::Button {
+    debug!("Creating notifications widget");
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "notifications");
+    let button = gtk4::Button::new();
+    button.add_css_class("notifications-widget");
+    button.set_halign(gtk4::Align::End);
+    button.set_child(Some(&label));
+    button.set_tooltip_text(Some(
+        "Click to toggle do-not-disturb, right-click for notifications",
+    ));
+    model_property("notifications")
+        .chain_closure::<bool>(glib::closure!(|_: Option<glib::Object>, text: &str| !text
+            .trim()
+            .is_empty()))
+        .bind(&button, "visible", Some(model));
+    let button_weak = button.downgrade();
+    model.connect_notifications_dnd_notify(move |model| {
+        let Some(button) = button_weak.upgrade() else {
+            return;
+        };
+        if model.notifications_dnd() {
+            button.add_css_class("dnd");
+        } else {
+            button.remove_css_class("dnd");
+        }
+    });
+    button.connect_clicked(|_| notifications::toggle_do_not_disturb());
+    let gesture = gtk4::GestureClick::new();
+    gesture.set_button(3);
+    gesture.connect_released(|_, _, _, _| notifications::open_notification_panel());
+    button.add_controller(gesture);
+    button
+}
+
+pub fn create_units_widget(model: &BarModel) -> gtk4::MenuButton {
+    debug!("Creating unit health widget");
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "units");
+
+    let names = gtk4::Label::new(None);
+    names.add_css_class("units-failed");
+    names.set_xalign(0.0);
+    bind_label_text(&names, model, "units-failed");
+    let heading = gtk4::Label::new(Some("Failed units"));
+    heading.add_css_class("units-heading");
+    heading.set_xalign(0.0);
+    let menu = gtk4::Box::new(gtk4::Orientation::Vertical, 4);
+    menu.add_css_class("tray-menu");
+    menu.append(&heading);
+    menu.append(&names);
+    let popover = gtk4::Popover::new();
+    popover.set_has_arrow(false);
+    popover.set_child(Some(&menu));
+
+    let button = gtk4::MenuButton::new();
+    button.add_css_class("units-widget");
+    button.set_halign(gtk4::Align::End);
+    button.set_always_show_arrow(false);
+    button.set_child(Some(&label));
+    button.set_popover(Some(&popover));
+    model_property("units")
+        .chain_closure::<bool>(glib::closure!(|_: Option<glib::Object>, text: &str| !text
+            .trim()
+            .is_empty()))
+        .bind(&button, "visible", Some(model));
+    button
+}
+
+pub fn create_updates_widget(model: &BarModel, config: &UpdatesConfig) -> gtk4::Label {
+    debug!("Creating updates widget");
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "updates");
+    label.add_css_class("updates-widget");
+    label.set_halign(gtk4::Align::End);
+    label.set_tooltip_text(Some(&format!(
+        "Pending updates reported by {}",
+        config.command
+    )));
+    model_property("updates")
+        .chain_closure::<bool>(glib::closure!(|_: Option<glib::Object>, text: &str| !text
+            .trim()
+            .is_empty()))
+        .bind(&label, "visible", Some(model));
+    label
+}
+
+pub fn create_clipboard_widget(model: &BarModel, config: &ClipboardConfig) -> gtk4::Button {
+    debug!("Creating clipboard widget");
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "clipboard");
+    let button = gtk4::Button::new();
+    button.add_css_class("clipboard-widget");
+    button.set_halign(gtk4::Align::End);
+    button.set_tooltip_text(Some("Clipboard history; click to pick an entry"));
+    button.set_child(Some(&label));
+    model_property("clipboard")
+        .chain_closure::<bool>(glib::closure!(|_: Option<glib::Object>, text: &str| !text
+            .trim()
+            .is_empty()))
+        .bind(&button, "visible", Some(model));
+    let picker = config.picker.clone();
+    button.connect_clicked(move |_| command::spawn_shell(&picker, "clipboard picker"));
+    button
+}
+
+pub fn create_pomodoro_widget(model: &BarModel, timer: &PomodoroTimer) -> gtk4::Button {
+    debug!("Creating pomodoro widget");
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "pomodoro");
+    let button = gtk4::Button::new();
+    button.add_css_class("pomodoro-widget");
+    button.set_halign(gtk4::Align::End);
+    button.set_child(Some(&label));
+    let timer = timer.clone();
+    button.connect_clicked(move |_| timer.toggle());
+    button
+}
+
+// Click for a screenshot, right-click to start recording; while a recording
+// runs either click stops it.
+pub fn create_capture_widget(
+    model: &BarModel,
+    capture: &Capture,
+    config: &CaptureConfig,
+) -> gtk4::Button {
+    debug!("Creating capture widget");
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "capture");
+    let button = gtk4::Button::new();
+    button.add_css_class("capture-widget");
+    button.set_halign(gtk4::Align::End);
+    button.set_tooltip_text(Some("Click for a screenshot, right-click to record"));
+    button.set_child(Some(&label));
+    if model.capture_recording() {
+        button.add_css_class("recording");
+    }
+    let button_weak = button.downgrade();
+    model.connect_capture_recording_notify(move |model| {
+        let Some(button) = button_weak.upgrade() else {
+            return;
+        };
+        if model.capture_recording() {
+            button.add_css_class("recording");
+        } else {
+            button.remove_css_class("recording");
+        }
+    });
+
+    let screenshot = config.screenshot.clone();
+    let record = config.record.clone();
+    let clicked = capture.clone();
+    button.connect_clicked(move |_| {
+        if clicked.is_recording() {
+            clicked.toggle_recording(&record);
+        } else {
+            clicked.screenshot(&screenshot);
+        }
+    });
+    let record = config.record.clone();
+    let capture = capture.clone();
+    let gesture = gtk4::GestureClick::new();
+    gesture.set_button(3);
+    gesture.connect_released(move |_, _, _, _| capture.toggle_recording(&record));
+    button.add_controller(gesture);
+    button
+}
+
+// Shows a file's contents (or a regex capture from them), refreshed when the
+// file changes and on an interval. The label owns its watcher, so a rebuilt
+// bar drops the old one. CSS can target all of them with .file-watch-widget
+// or one with #name.
+pub fn create_file_watch_widget(config: &FileWatchConfig) -> gtk4::Label {
+    debug!(name = config.name, "Creating file watch widget");
+    let label = gtk4::Label::new(None);
+    label.add_css_class("file-watch-widget");
+    label.set_widget_name(&config.name);
+    label.set_halign(gtk4::Align::End);
+    label.set_visible(false);
+    filewatch::watch(&label, config);
+    label
+}
+
+pub fn setup_progress_updates(
+    mut rx: channel::Receiver<ProgressUpdate>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up progress updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            trace!(?update, "Queueing progress chip");
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::Progress, update, move |update| {
+                model.set_progress(update.text);
+                model.set_progress_fraction(update.fraction);
+                model.set_progress_tooltip(update.tooltip);
+            });
+        }
+    });
+}
+
+pub fn setup_notifications_updates(
+    mut rx: channel::Receiver<NotificationsUpdate>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up notifications updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            debug!(?update, "Queueing notifications label");
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::Notifications, update, move |update| {
+                model.set_notifications(update.text);
+                model.set_notifications_dnd(update.dnd);
+            });
+        }
+    });
+}
+
+pub fn setup_units_updates(
+    mut rx: channel::Receiver<Vec<String>>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up unit health updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(failed) = rx.recv().await {
+            debug!(?failed, "Queueing failed units");
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::Units, failed, move |failed| {
+                model.set_units(units::badge_text(&failed));
+                model.set_units_failed(failed.join("\n"));
+            });
+        }
+    });
+}
+
+pub fn setup_package_updates(
+    mut rx: channel::Receiver<String>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up package update counts");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            debug!("Queueing updates label: {}", update);
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::Updates, update, move |update| {
+                model.set_updates(update)
+            });
+        }
+    });
+}
+
+pub fn setup_clipboard_updates(
+    mut rx: channel::Receiver<String>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up clipboard history counts");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            debug!("Queueing clipboard label: {}", update);
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::Clipboard, update, move |update| {
+                model.set_clipboard(update)
+            });
+        }
+    });
+}
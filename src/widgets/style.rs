@@ -0,0 +1,221 @@
+// The stylesheet: which file is loaded (`--style`, the user's style.css or
+// the built-in one), how GTK's parse errors reach the style_errors widget,
+// the `[bar] theme` presets and the desktop's light/dark preference.
+
+use super::*;
+
+const STYLE_FILE_NAME: &str = "style.css";
+const EMBEDDED_STYLE: &str = include_str!("../../style.css");
+const SOLID_THEME: &str = include_str!("../../themes/solid.css");
+const LIGHT_THEME: &str = include_str!("../../themes/light.css");
+
+// Where the stylesheet comes from. The first source found replaces the others
+// outright rather than layering on them, so a user file is a complete theme.
+#[derive(Debug, PartialEq, Eq)]
+enum StyleSource {
+    File(PathBuf),
+    Embedded,
+}
+
+// `--style PATH` must exist. Without it, `$XDG_CONFIG_HOME/gtk-status-bar/style.css`
+// is used when present, else the stylesheet compiled into the binary, so the
+// bar is never unstyled however it was launched.
+fn resolve_style_source(explicit: Option<&Path>, config_dir: Option<&Path>) -> Result<StyleSource> {
+    if let Some(path) = explicit {
+        if !path.is_file() {
+            bail!("stylesheet {} does not exist", path.display());
+        }
+        return Ok(StyleSource::File(path.to_path_buf()));
+    }
+    match config_dir.map(|dir| dir.join(STYLE_FILE_NAME)) {
+        Some(path) if path.is_file() => Ok(StyleSource::File(path)),
+        _ => Ok(StyleSource::Embedded),
+    }
+}
+
+// A stylesheet problem GTK skipped over, 1-based like an editor counts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct StyleError {
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+// The chip's text and tooltip for the errors of the last load; empty text
+// hides the chip.
+fn style_error_summary(errors: &[StyleError]) -> (String, String) {
+    let details = errors
+        .iter()
+        .map(|error| format!("{}:{} {}", error.line, error.column, error.message))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let text = match errors {
+        [] => String::new(),
+        [only] => format!("\u{f071} CSS {}:{}", only.line, only.column),
+        more => format!("\u{f071} CSS {} errors", more.len()),
+    };
+    (text, details)
+}
+
+// GTK drops the rules it cannot parse and styles the rest, which looks like a
+// theme quietly not applying; the chip and a notification say where to look.
+fn report_style_errors(
+    window: &gtk4::ApplicationWindow,
+    model: &BarModel,
+    name: &str,
+    errors: &[StyleError],
+) {
+    let (text, details) = style_error_summary(errors);
+    model.set_style_errors(text);
+    model.set_style_errors_detail(details.clone());
+    let Some(first) = errors.first() else {
+        return;
+    };
+    let Some(application) = window.application() else {
+        return;
+    };
+    let notification = gtk4::gio::Notification::new(&format!("{name} has errors"));
+    let body = match errors.len() {
+        1 => format!("Line {}:{}: {}", first.line, first.column, first.message),
+        count => format!(
+            "{count} errors, the first at line {}:{}: {}",
+            first.line, first.column, first.message
+        ),
+    };
+    notification.set_body(Some(&body));
+    application.send_notification(Some("style-errors"), &notification);
+}
+
+pub fn load_css_styles(
+    window: &gtk4::ApplicationWindow,
+    explicit: Option<&Path>,
+    model: &BarModel,
+) -> Result<()> {
+    debug!("Loading CSS styles");
+
+    let source = resolve_style_source(explicit, config::config_dir().as_deref())?;
+    let css_provider = gtk4::CssProvider::new();
+    // Emitted synchronously while load_from_string parses.
+    let errors = Rc::new(RefCell::new(Vec::new()));
+    let errors_for_signal = errors.clone();
+    css_provider.connect_parsing_error(move |_, section, error| {
+        warn!(location = %section.to_str(), "Stylesheet error: {}", error);
+        let start = section.start_location();
+        errors_for_signal.borrow_mut().push(StyleError {
+            line: start.lines() + 1,
+            column: start.line_chars() + 1,
+            message: error.message().to_string(),
+        });
+    });
+    match &source {
+        StyleSource::File(path) => {
+            let css_data = std::fs::read_to_string(path)
+                .with_context(|| format!("read stylesheet {}", path.display()))?;
+            css_provider.load_from_string(&css_data);
+            info!(path = %path.display(), "Loaded stylesheet");
+        }
+        StyleSource::Embedded => {
+            css_provider.load_from_string(EMBEDDED_STYLE);
+            info!("Loaded built-in stylesheet");
+        }
+    }
+    let name = match &source {
+        StyleSource::File(path) => path.display().to_string(),
+        StyleSource::Embedded => "The built-in stylesheet".to_string(),
+    };
+    report_style_errors(window, model, &name, &errors.borrow());
+
+    gtk4::style_context_add_provider_for_display(
+        &gtk4::prelude::WidgetExt::display(window),
+        &css_provider,
+        gtk4::STYLE_PROVIDER_PRIORITY_USER,
+    );
+    Ok(())
+}
+
+// Layer a `[bar] theme` preset over whichever stylesheet load_css_styles
+// picked. One priority step above it, so the preset wins without !important.
+pub fn apply_theme(window: &gtk4::ApplicationWindow, theme: Theme) {
+    let css = match theme {
+        Theme::Default => return,
+        Theme::Solid => SOLID_THEME,
+        Theme::Light => LIGHT_THEME,
+    };
+    info!(?theme, "Applying theme preset");
+    let css_provider = gtk4::CssProvider::new();
+    css_provider.load_from_string(css);
+    gtk4::style_context_add_provider_for_display(
+        &gtk4::prelude::WidgetExt::display(window),
+        &css_provider,
+        gtk4::STYLE_PROVIDER_PRIORITY_USER + 1,
+    );
+}
+
+// Apply live desktop color-scheme changes to GTK. The appearance producer sends
+// the current preference on connect and again on every portal SettingChanged, so
+// the bar's prefer-dark follows a light/dark switch instead of only the value
+// read at startup. The `!=` guard keeps the no-op re-sends (and the initial
+// value, already applied synchronously in configure_color_scheme) from churning.
+pub fn setup_color_scheme_updates(mut rx: mpsc::UnboundedReceiver<bool>) {
+    glib::spawn_future_local(async move {
+        while let Some(prefer_dark) = rx.recv().await {
+            let Some(settings) = gtk4::Settings::default() else {
+                warn!("No default GtkSettings; cannot apply color-scheme change");
+                continue;
+            };
+            if settings.is_gtk_application_prefer_dark_theme() != prefer_dark {
+                settings.set_gtk_application_prefer_dark_theme(prefer_dark);
+                info!(prefer_dark, "Applied desktop color-scheme change to GTK");
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn style_falls_back_to_the_embedded_sheet() {
+        let missing = Path::new("/nonexistent/gtk-status-bar");
+        assert_eq!(
+            resolve_style_source(None, Some(missing)).expect("fallback should resolve"),
+            StyleSource::Embedded
+        );
+        assert_eq!(
+            resolve_style_source(None, None).expect("fallback should resolve"),
+            StyleSource::Embedded
+        );
+        assert!(resolve_style_source(Some(&missing.join("style.css")), None).is_err());
+    }
+
+    #[test]
+    fn style_errors_summarize_with_line_and_column() {
+        assert_eq!(style_error_summary(&[]), (String::new(), String::new()));
+        let errors = [
+            StyleError {
+                line: 12,
+                column: 3,
+                message: "Expected ';'".to_string(),
+            },
+            StyleError {
+                line: 40,
+                column: 1,
+                message: "No property named \"colour\"".to_string(),
+            },
+        ];
+        assert_eq!(
+            style_error_summary(&errors[..1]),
+            (
+                "\u{f071} CSS 12:3".to_string(),
+                "12:3 Expected ';'".to_string()
+            )
+        );
+        let (text, details) = style_error_summary(&errors);
+        assert_eq!(text, "\u{f071} CSS 2 errors");
+        assert_eq!(
+            details,
+            "12:3 Expected ';'\n40:1 No property named \"colour\""
+        );
+    }
+}
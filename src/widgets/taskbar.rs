@@ -0,0 +1,302 @@
+// The taskbar: one button per window, reconciled by TaskbarStore rather than
+// through a model property, with a badge for windows whose process is
+// playing audio.
+
+use super::*;
+
+// The taskbar's GTK side. Window lists don't fit a model property, so the
+// drain hands each TaskbarUpdate to this store, which reconciles every live
+// taskbar widget button by button: a button (and its icon lookup) is only
+// created for a window the widget hasn't shown yet. A widget dropped by a
+// config reload falls out of the store on the next update.
+//
+// PipeWire's playing streams come through the store as well: a window whose
+// process (or a child of it) is playing gets a badge, and right-clicking the
+// button mutes or unmutes those streams.
+#[derive(Clone)]
+pub struct TaskbarStore {
+    inner: Rc<TaskbarInner>,
+}
+
+struct TaskbarInner {
+    actions: WindowActions,
+    audio: AudioSinks,
+    latest: RefCell<TaskbarUpdate>,
+    playback: RefCell<PlaybackUpdate>,
+    views: RefCell<Vec<TaskbarView>>,
+}
+
+struct TaskbarView {
+    root: glib::WeakRef<gtk4::Box>,
+    all_workspaces: bool,
+    buttons: RefCell<HashMap<String, TaskbarButton>>,
+}
+
+struct TaskbarButton {
+    button: gtk4::Button,
+    badge: gtk4::Label,
+    // The window's playing streams as of the last render, for the
+    // right-click handler.
+    streams: Rc<RefCell<Vec<PlaybackStream>>>,
+}
+
+impl TaskbarStore {
+    pub fn new(actions: WindowActions, audio: AudioSinks) -> Self {
+        Self {
+            inner: Rc::new(TaskbarInner {
+                actions,
+                audio,
+                latest: RefCell::default(),
+                playback: RefCell::default(),
+                views: RefCell::default(),
+            }),
+        }
+    }
+
+    fn add_view(&self, root: &gtk4::Box, all_workspaces: bool) {
+        let view = TaskbarView {
+            root: root.downgrade(),
+            all_workspaces,
+            buttons: RefCell::default(),
+        };
+        view.render(
+            &self.inner.latest.borrow(),
+            &self.inner.playback.borrow(),
+            &self.inner,
+        );
+        self.inner.views.borrow_mut().push(view);
+    }
+
+    fn update(&self, update: TaskbarUpdate) {
+        self.inner
+            .views
+            .borrow_mut()
+            .retain(|view| view.render(&update, &self.inner.playback.borrow(), &self.inner));
+        *self.inner.latest.borrow_mut() = update;
+    }
+
+    fn update_playback(&self, playback: PlaybackUpdate) {
+        self.inner
+            .views
+            .borrow_mut()
+            .retain(|view| view.render(&self.inner.latest.borrow(), &playback, &self.inner));
+        *self.inner.playback.borrow_mut() = playback;
+    }
+}
+
+// The badge for a window's playing streams: muted only when all of them are.
+fn playback_badge(streams: &[&PlaybackStream]) -> Option<&'static str> {
+    if streams.is_empty() {
+        return None;
+    }
+    Some(if streams.iter().all(|stream| stream.muted) {
+        "🔇"
+    } else {
+        "🔊"
+    })
+}
+
+// The windows a taskbar shows, in order.
+fn taskbar_windows(update: &TaskbarUpdate, all_workspaces: bool) -> Vec<&TaskbarWindow> {
+    update
+        .windows
+        .iter()
+        .filter(|window| all_workspaces || window.workspace_id == update.active_workspace)
+        .collect()
+}
+
+impl TaskbarView {
+    // False once the widget is gone.
+    fn render(
+        &self,
+        update: &TaskbarUpdate,
+        playback: &PlaybackUpdate,
+        inner: &TaskbarInner,
+    ) -> bool {
+        let Some(root) = self.root.upgrade() else {
+            return false;
+        };
+        let windows = taskbar_windows(update, self.all_workspaces);
+        let mut buttons = self.buttons.borrow_mut();
+        buttons.retain(|address, taskbar_button| {
+            let keep = windows.iter().any(|window| window.address == *address);
+            if !keep {
+                root.remove(&taskbar_button.button);
+            }
+            keep
+        });
+
+        let mut previous: Option<gtk4::Widget> = None;
+        for window in windows {
+            let taskbar_button = buttons.entry(window.address.clone()).or_insert_with(|| {
+                let taskbar_button = create_taskbar_button(window, &inner.actions, &inner.audio);
+                root.append(&taskbar_button.button);
+                taskbar_button
+            });
+            let button = &taskbar_button.button;
+            if window.focused {
+                button.add_css_class("focused");
+            } else {
+                button.remove_css_class("focused");
+            }
+            let streams = window
+                .pid
+                .map(|pid| playback::streams_for(playback, pid))
+                .unwrap_or_default();
+            let badge = playback_badge(&streams);
+            taskbar_button.badge.set_text(badge.unwrap_or_default());
+            taskbar_button.badge.set_visible(badge.is_some());
+            button.set_tooltip_text(Some(&match badge {
+                Some(_) => format!(
+                    "{}\nPlaying audio; right-click to mute or unmute",
+                    window.title
+                ),
+                None => window.title.clone(),
+            }));
+            *taskbar_button.streams.borrow_mut() = streams.into_iter().cloned().collect();
+            root.reorder_child_after(button, previous.as_ref());
+            previous = Some(button.clone().upcast());
+        }
+        root.set_visible(!buttons.is_empty());
+        true
+    }
+}
+
+// Left click focuses the window (switching workspace if needed), middle click
+// closes it, through whichever window backend feeds the list. Right click
+// mutes the window's playing streams, or unmutes them once all are muted.
+fn create_taskbar_button(
+    window: &TaskbarWindow,
+    actions: &WindowActions,
+    audio: &AudioSinks,
+) -> TaskbarButton {
+    let image = gtk4::Image::new();
+    update_title_icon(&image, &window.class);
+    let badge = gtk4::Label::new(None);
+    badge.add_css_class("taskbar-audio");
+    badge.set_halign(gtk4::Align::End);
+    badge.set_valign(gtk4::Align::Start);
+    badge.set_can_target(false);
+    badge.set_visible(false);
+    let overlay = gtk4::Overlay::new();
+    overlay.set_child(Some(&image));
+    overlay.add_overlay(&badge);
+    let button = gtk4::Button::new();
+    button.add_css_class("taskbar-button");
+    button.set_child(Some(&overlay));
+
+    let address = window.address.clone();
+    let focus_actions = actions.clone();
+    button.connect_clicked(move |_| focus_actions.focus(&address));
+
+    let middle = gtk4::GestureClick::new();
+    middle.set_button(2);
+    let address = window.address.clone();
+    let close_actions = actions.clone();
+    middle.connect_released(move |_, _, _, _| close_actions.close(&address));
+    button.add_controller(middle);
+
+    let streams: Rc<RefCell<Vec<PlaybackStream>>> = Rc::default();
+    let right = gtk4::GestureClick::new();
+    right.set_button(3);
+    let audio = audio.clone();
+    let streams_for_click = streams.clone();
+    right.connect_released(move |_, _, _, _| {
+        let streams = streams_for_click.borrow();
+        let mute = !streams.iter().all(|stream| stream.muted);
+        for stream in streams.iter() {
+            audio.set_stream_mute(stream.node_id, mute);
+        }
+    });
+    button.add_controller(right);
+    TaskbarButton {
+        button,
+        badge,
+        streams,
+    }
+}
+
+pub fn create_taskbar_widget(store: &TaskbarStore, all_workspaces: bool) -> gtk4::Box {
+    debug!(all_workspaces, "Creating taskbar widget");
+    let root = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
+    root.add_css_class("taskbar-widget");
+    root.set_visible(false);
+    store.add_view(&root, all_workspaces);
+    root
+}
+
+pub fn setup_taskbar_updates(
+    mut rx: channel::Receiver<TaskbarUpdate>,
+    store: TaskbarStore,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up taskbar updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            debug!(windows = update.windows.len(), "Queueing taskbar update");
+            let store = store.clone();
+            dispatcher.submit_changed(Slot::Taskbar, update, move |update| store.update(update));
+        }
+    });
+}
+
+pub(super) fn setup_playback_updates(
+    mut rx: channel::Receiver<PlaybackUpdate>,
+    store: TaskbarStore,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up taskbar audio badges");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            debug!(streams = update.streams.len(), "Queueing playback streams");
+            let store = store.clone();
+            dispatcher.submit_changed(Slot::Playback, update, move |update| {
+                store.update_playback(update)
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn taskbar_narrows_to_the_active_workspace() {
+        let window = |address: &str, workspace_id| TaskbarWindow {
+            address: address.to_string(),
+            class: "kitty".to_string(),
+            title: address.to_string(),
+            workspace_id,
+            focused: false,
+            pid: None,
+        };
+        let update = TaskbarUpdate {
+            windows: vec![window("0x1", 1), window("0x2", 2), window("0x3", 2)],
+            active_workspace: 2,
+        };
+        let addresses = |all| {
+            taskbar_windows(&update, all)
+                .iter()
+                .map(|window| window.address.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(addresses(false), vec!["0x2", "0x3"]);
+        assert_eq!(addresses(true), vec!["0x1", "0x2", "0x3"]);
+    }
+
+    #[test]
+    fn audio_badge_shows_muted_only_when_every_stream_is() {
+        let stream = |node_id, muted| PlaybackStream {
+            node_id,
+            pids: vec![4242],
+            muted,
+        };
+        let (playing, muted) = (stream(60, false), stream(61, true));
+        assert_eq!(playback_badge(&[]), None);
+        assert_eq!(playback_badge(&[&playing, &muted]), Some("🔊"));
+        assert_eq!(playback_badge(&[&muted]), Some("🔇"));
+    }
+}
@@ -0,0 +1,445 @@
+// The focused window's title: the rewrite rules and length cut, the
+// application icon looked up from desktop entries or the icon theme, and the
+// `.app-*` CSS class for per-application styling.
+
+use super::*;
+
+#[derive(Clone)]
+pub struct TitleWidget {
+    pub(super) root: gtk4::CenterBox,
+    icon: gtk4::Image,
+}
+
+// The config loader has already rejected bad regexes; one failing here can
+// only come from a rule that bypassed it, and is dropped with a warning.
+fn compile_title_rewrites(rules: &[TitleRewriteRule]) -> Vec<TitleRewrite> {
+    rules
+        .iter()
+        .filter_map(|rule| {
+            match TitleRewrite::new(
+                rule.class.as_deref(),
+                rule.pattern.as_deref(),
+                &rule.replace,
+            ) {
+                Ok(rewrite) => Some(rewrite),
+                Err(e) => {
+                    warn!("Skipping title rewrite: {:#}", e);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+// `[[title_rewrite]]` first, then `[title]`'s cut unless GTK does the cutting.
+fn title_text(title: &str, class: &str, rewrites: &[TitleRewrite], config: &TitleConfig) -> String {
+    let title = text::rewrite_title(title, class, rewrites);
+    if config.native {
+        return title;
+    }
+    let at = match config.ellipsize {
+        Ellipsize::Start => Ellipsis::Start,
+        Ellipsize::Middle => Ellipsis::Middle,
+        Ellipsize::End => Ellipsis::End,
+    };
+    text::ellipsize(&title, usize::from(config.max_chars), at)
+}
+
+fn pango_ellipsize(mode: Ellipsize) -> gtk4::pango::EllipsizeMode {
+    match mode {
+        Ellipsize::Start => gtk4::pango::EllipsizeMode::Start,
+        Ellipsize::Middle => gtk4::pango::EllipsizeMode::Middle,
+        Ellipsize::End => gtk4::pango::EllipsizeMode::End,
+    }
+}
+
+// A pixel width as the label's maximum width in characters, which is what
+// GTK sizes labels by, through the font's average character width. Needs
+// the font, so it runs once the label is realized.
+fn cap_label_pixels(label: &gtk4::Label, pixels: u16) {
+    let char_width = label
+        .pango_context()
+        .metrics(None, None)
+        .approximate_char_width()
+        / gtk4::pango::SCALE;
+    if char_width <= 0 {
+        warn!("Could not resolve font metrics; the title's pixel width is not capped");
+        return;
+    }
+    let chars = (i32::from(pixels) / char_width).max(1);
+    let current = label.max_width_chars();
+    debug!(pixels, char_width, chars, "Capping title width");
+    label.set_max_width_chars(if current > 0 {
+        current.min(chars)
+    } else {
+        chars
+    });
+}
+
+pub fn create_title_widget(
+    model: &BarModel,
+    format: Format,
+    config: &TitleConfig,
+    rewrite_rules: &[TitleRewriteRule],
+) -> TitleWidget {
+    debug!("Creating title widget");
+
+    let root = gtk4::CenterBox::new();
+    root.add_css_class("title-widget");
+    root.set_halign(gtk4::Align::End);
+    root.set_valign(gtk4::Align::Start);
+
+    let icon = gtk4::Image::new();
+    icon.add_css_class("title-icon");
+    icon.set_valign(gtk4::Align::Center);
+    icon.set_visible(false);
+    root.set_start_widget(Some(&icon));
+
+    let label = gtk4::Label::new(None);
+    label.add_css_class("title-label");
+    label.set_valign(gtk4::Align::Center);
+    // title_text already crops long titles by grapheme count, but wide
+    // glyphs can still exceed the remaining monitor width when right-side
+    // pills are added. Ellipsizing gives GTK permission to shrink the label's
+    // minimum width instead of expanding the layer surface past the output.
+    label.set_ellipsize(pango_ellipsize(config.ellipsize));
+    label.set_single_line_mode(true);
+    if config.native {
+        label.set_max_width_chars(i32::from(config.max_chars));
+    }
+    if let Some(pixels) = config.max_width_px {
+        label.connect_realize(move |label| cap_label_pixels(label, pixels));
+    }
+    // Label is the CenterBox's own center widget (icon is the start widget)
+    // so GtkCenterLayout keeps the title text truly centered in the pill
+    // regardless of whether the icon is showing — packing both into one
+    // "center" child instead visually centers the icon+label group, which
+    // pulls short titles off-center once an icon appears.
+    root.set_center_widget(Some(&label));
+    let rewrites = compile_title_rewrites(rewrite_rules);
+    label.set_text(&title_text(
+        &model.title(),
+        &model.title_class(),
+        &rewrites,
+        config,
+    ));
+    let label_weak = label.downgrade();
+    let title_config = config.clone();
+    // Class rules need the class of the title being shown, so the drain sets
+    // title-class before title.
+    model.connect_title_notify(move |model| {
+        let Some(label) = label_weak.upgrade() else {
+            return;
+        };
+        label.set_text(&title_text(
+            &model.title(),
+            &model.title_class(),
+            &rewrites,
+            &title_config,
+        ));
+    });
+
+    // Asked on hover, so /proc is read for the window being looked at and
+    // never for the ones merely focused on the way.
+    root.set_has_tooltip(true);
+    let model_weak = model.downgrade();
+    root.connect_query_tooltip(move |_root, _x, _y, _keyboard_mode, tooltip| {
+        let Some(model) = model_weak.upgrade() else {
+            return false;
+        };
+        let pid = model.title_pid();
+        if pid <= 0 {
+            return false;
+        }
+        match process::read(pid) {
+            Ok(info) => {
+                tooltip.set_text(Some(&process::tooltip_text(&info, &format)));
+                true
+            }
+            Err(e) => {
+                debug!(pid, "No process details for the title tooltip: {:#}", e);
+                false
+            }
+        }
+    });
+
+    let title_widget = TitleWidget { root, icon };
+
+    // The icon lookup and the per-workspace color are not plain property
+    // copies, so they follow the model through notify handlers instead of
+    // expressions. The drain only touches title-class when it actually
+    // changes, keeping desktop-file lookups off the per-title-change path.
+    let icon_weak = title_widget.icon.downgrade();
+    let root_weak = title_widget.root.downgrade();
+    update_title_app_class(&title_widget.root, &model.title_class());
+    model.connect_title_class_notify(move |model| {
+        if let Some(icon) = icon_weak.upgrade() {
+            update_title_icon(&icon, &model.title_class());
+        }
+        if let Some(root) = root_weak.upgrade() {
+            update_title_app_class(&root, &model.title_class());
+        }
+    });
+    let root_weak = title_widget.root.downgrade();
+    model.connect_workspace_color_notify(move |model| {
+        if let Some(root) = root_weak.upgrade() {
+            update_title_widget_workspace_color(
+                &root,
+                model.workspace_id(),
+                &model.workspace_color(),
+            );
+        }
+    });
+    let root_weak = title_widget.root.downgrade();
+    model.connect_workspace_special_notify(move |model| {
+        let Some(root) = root_weak.upgrade() else {
+            return;
+        };
+        if model.workspace_special() {
+            root.add_css_class("special-workspace");
+        } else {
+            root.remove_css_class("special-workspace");
+        }
+    });
+
+    title_widget
+}
+
+fn update_title_widget_workspace_color(
+    title_root: &gtk4::CenterBox,
+    workspace_id: hyprland::shared::WorkspaceId,
+    color: &str,
+) {
+    // Apply color directly via CSS provider for immediate update
+    let css_provider = gtk4::CssProvider::new();
+    let css = format!(".title-widget {{ background-color: {}; }}", color);
+
+    css_provider.load_from_string(&css);
+
+    let style_context = title_root.style_context();
+    style_context.add_provider(&css_provider, gtk4::STYLE_PROVIDER_PRIORITY_USER + 1);
+
+    debug!(
+        "Updated title widget color to: {} for workspace: {}",
+        color, workspace_id
+    );
+}
+
+fn desktop_app_class_match_score(app: &gio_unix::DesktopAppInfo, class: &str) -> u8 {
+    if app
+        .startup_wm_class()
+        .is_some_and(|wm_class| wm_class.eq_ignore_ascii_case(class))
+    {
+        return 4;
+    }
+
+    if let Some(id) = app.id() {
+        let desktop_id = id.strip_suffix(".desktop").unwrap_or(&id);
+        if desktop_id.eq_ignore_ascii_case(class) {
+            return 3;
+        }
+        if desktop_id
+            .rsplit('.')
+            .next()
+            .is_some_and(|leaf| leaf.eq_ignore_ascii_case(class))
+        {
+            return 2;
+        }
+    }
+
+    u8::from(app.name().eq_ignore_ascii_case(class))
+}
+
+fn desktop_icon_for_class(class: &str) -> Option<gtk4::gio::Icon> {
+    gtk4::gio::AppInfo::all()
+        .into_iter()
+        .filter_map(|app| app.downcast::<gio_unix::DesktopAppInfo>().ok())
+        .filter_map(|app| {
+            let score = desktop_app_class_match_score(&app, class);
+            (score > 0).then_some((score, app))
+        })
+        .max_by_key(|(score, _app)| *score)
+        .and_then(|(_score, app)| app.icon())
+}
+
+// The window class as a CSS class, for per-application styling from the
+// stylesheet: "org.gnome.Nautilus" is `.app-org-gnome-nautilus`. Anything but
+// ASCII letters, digits, `-` and `_` becomes a single `-`; None for a class
+// with nothing left.
+const APP_CLASS_PREFIX: &str = "app-";
+
+fn app_css_class(class: &str) -> Option<String> {
+    let mut name = String::from(APP_CLASS_PREFIX);
+    for c in class.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            name.push(c.to_ascii_lowercase());
+        } else if !name.ends_with('-') {
+            name.push('-');
+        }
+    }
+    let name = name.trim_end_matches('-');
+    (name.len() > APP_CLASS_PREFIX.len()).then(|| name.to_string())
+}
+
+// Swaps the previous window's `.app-*` class for the current one's.
+fn update_title_app_class(root: &gtk4::CenterBox, class: &str) {
+    for css_class in root.css_classes() {
+        if css_class.starts_with(APP_CLASS_PREFIX) {
+            root.remove_css_class(&css_class);
+        }
+    }
+    if let Some(css_class) = app_css_class(class) {
+        root.add_css_class(&css_class);
+    }
+}
+
+pub(super) fn update_title_icon(image: &gtk4::Image, class: &str) {
+    let class = class.trim();
+    if class.is_empty() {
+        image.set_visible(false);
+        return;
+    }
+
+    image.set_pixel_size(tray_icon_pixel_size(image));
+    if let Some(icon) = desktop_icon_for_class(class) {
+        image.set_from_gicon(&icon);
+        image.set_visible(true);
+        debug!(
+            class,
+            "Resolved title icon from desktop application metadata"
+        );
+        return;
+    }
+
+    let icon_theme = gtk4::IconTheme::for_display(&image.display());
+    let lowercase = class.to_lowercase();
+    let leaf = lowercase.rsplit('.').next().unwrap_or(&lowercase);
+    for candidate in [class, lowercase.as_str(), leaf] {
+        if icon_theme.has_icon(candidate) {
+            image.set_icon_name(Some(candidate));
+            image.set_visible(true);
+            debug!(
+                class,
+                icon = candidate,
+                "Resolved title icon directly from theme"
+            );
+            return;
+        }
+    }
+
+    image.set_icon_name(Some("application-x-executable-symbolic"));
+    image.set_visible(true);
+    debug!(class, "Using generic fallback for title icon");
+}
+
+pub fn setup_title_updates(
+    mut rx: channel::Receiver<TitleUpdate>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up title updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::Title, update, move |update| {
+                debug!(
+                    title = update.title,
+                    class = update.class,
+                    "Updating title widget"
+                );
+                // NOTE: Title widget always remains visible even when empty, unlike battery/bluetooth widgets.
+                // This provides consistent visual layout and shows the centered position in the bar.
+                if model.title_class() != update.class {
+                    model.set_title_class(update.class);
+                }
+                model.set_title(update.title);
+                model.set_title_pid(update.pid.unwrap_or(0));
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_classes_become_css_classes() {
+        assert_eq!(app_css_class("firefox").as_deref(), Some("app-firefox"));
+        assert_eq!(
+            app_css_class("org.gnome.Nautilus").as_deref(),
+            Some("app-org-gnome-nautilus")
+        );
+        assert_eq!(
+            app_css_class(" Code - OSS ").as_deref(),
+            Some("app-code-oss")
+        );
+        assert_eq!(
+            app_css_class("steam_app_570").as_deref(),
+            Some("app-steam_app_570")
+        );
+        assert_eq!(app_css_class(""), None);
+        assert_eq!(app_css_class("…"), None);
+    }
+
+    #[test]
+    fn titles_cut_where_configured() {
+        let title = "Inbox - Mail - Firefox";
+        let config = |ellipsize| TitleConfig {
+            max_chars: 10,
+            ellipsize,
+            ..TitleConfig::default()
+        };
+        let cut = |config| title_text(title, "firefox", &[], &config);
+        assert_eq!(cut(config(Ellipsize::Start)), "…- Firefox");
+        assert_eq!(cut(config(Ellipsize::Middle)), "Inbo…refox");
+        assert_eq!(cut(config(Ellipsize::End)), "Inbox - M…");
+        let native = TitleConfig {
+            native: true,
+            ..config(Ellipsize::End)
+        };
+        assert_eq!(cut(native), title);
+    }
+
+    #[test]
+    fn title_rewrites_apply_before_the_cut() {
+        let rules = [
+            TitleRewriteRule {
+                class: Some("^org\\.wezfurlong\\.wezterm$".to_string()),
+                pattern: None,
+                replace: "Terminal".to_string(),
+            },
+            TitleRewriteRule {
+                class: None,
+                pattern: Some(" — Mozilla Firefox$".to_string()),
+                replace: String::new(),
+            },
+        ];
+        let rewrites = compile_title_rewrites(&rules);
+        let config = TitleConfig::default();
+        assert_eq!(
+            title_text(
+                "vim src/main.rs",
+                "org.wezfurlong.wezterm",
+                &rewrites,
+                &config
+            ),
+            "Terminal"
+        );
+        assert_eq!(
+            title_text("Inbox — Mozilla Firefox", "firefox", &rewrites, &config),
+            "Inbox"
+        );
+        assert_eq!(title_text("Files", "nautilus", &rewrites, &config), "Files");
+        let short = TitleConfig {
+            max_chars: 4,
+            ellipsize: Ellipsize::End,
+            ..config
+        };
+        assert_eq!(
+            title_text("Inbox — Mozilla Firefox", "firefox", &rewrites, &short),
+            "Inb…"
+        );
+    }
+}
@@ -0,0 +1,343 @@
+// Audio widgets: the volume label and its sink menu, the privacy indicator
+// (screencast, camera or microphone in use) and the Bluetooth headset's
+// profile switch. setup_pipewire_updates owns the PipeWire thread and fans its
+// channels out to these drains and to the taskbar's playback badges.
+
+use super::*;
+
+// Right-click lists the audio sinks PipeWire knows and makes the chosen one the
+// default.
+// Matches the mute-flash animation in style.css.
+const MUTE_FLASH_DURATION: Duration = Duration::from_millis(600);
+
+pub fn create_volume_widget(model: &BarModel, audio: &AudioSinks) -> gtk4::Label {
+    debug!("Creating volume widget");
+    let label = gtk4::Label::new(None);
+    label.add_css_class("volume-widget");
+    label.set_halign(gtk4::Align::Center);
+    bind_label_text(&label, model, "volume");
+
+    let label_weak = label.downgrade();
+    model.connect_volume_muted_notify(move |model| {
+        let Some(label) = label_weak.upgrade() else {
+            return;
+        };
+        if model.volume_muted() {
+            label.add_css_class("muted");
+        } else {
+            label.remove_css_class("muted");
+        }
+    });
+    let label_weak = label.downgrade();
+    model.connect_volume_inactive_notify(move |model| {
+        let Some(label) = label_weak.upgrade() else {
+            return;
+        };
+        if model.volume_inactive() {
+            label.add_css_class("inactive");
+        } else {
+            label.remove_css_class("inactive");
+        }
+    });
+    // The class is removed and re-added so back-to-back toggles restart the
+    // animation; the timeout only ends a flash nothing has restarted since.
+    let label_weak = label.downgrade();
+    let flash_generation = Rc::new(Cell::new(0u32));
+    model.connect_volume_mute_toggles_notify(move |_| {
+        let Some(label) = label_weak.upgrade() else {
+            return;
+        };
+        let generation = flash_generation.get().wrapping_add(1);
+        flash_generation.set(generation);
+        label.remove_css_class("mute-flash");
+        label.add_css_class("mute-flash");
+        let label_weak = label.downgrade();
+        let flash_generation = flash_generation.clone();
+        glib::timeout_add_local_once(MUTE_FLASH_DURATION, move || {
+            if flash_generation.get() != generation {
+                return;
+            }
+            if let Some(label) = label_weak.upgrade() {
+                label.remove_css_class("mute-flash");
+            }
+        });
+    });
+
+    let menu = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+    menu.add_css_class("tray-menu");
+    let popover = gtk4::Popover::new();
+    popover.set_has_arrow(false);
+    popover.set_position(gtk4::PositionType::Bottom);
+    popover.set_child(Some(&menu));
+    popover.set_parent(&label);
+    // A popover is not a regular child; its parent has to let go of it.
+    let popover_for_destroy = popover.clone();
+    label.connect_destroy(move |_| popover_for_destroy.unparent());
+
+    // Filled on map, so the popover control verb gets the same list.
+    let audio = audio.clone();
+    popover.connect_map(move |popover| fill_sink_menu(&menu, popover, &audio));
+
+    let gesture = gtk4::GestureClick::new();
+    gesture.set_button(3);
+    // Weak: the gesture lives on the label, which owns the popover.
+    let popover_weak = popover.downgrade();
+    gesture.connect_released(move |_, _, _, _| {
+        if let Some(popover) = popover_weak.upgrade() {
+            popover.popup();
+        }
+    });
+    label.add_controller(gesture);
+    label
+}
+
+// Rebuilt on every open, so the list is never staler than the click.
+fn fill_sink_menu(menu: &gtk4::Box, popover: &gtk4::Popover, audio: &AudioSinks) {
+    while let Some(child) = menu.first_child() {
+        menu.remove(&child);
+    }
+    let list = audio.list();
+    if list.sinks.is_empty() {
+        let empty = gtk4::Label::new(Some("No audio outputs"));
+        empty.add_css_class("tray-menu-item");
+        menu.append(&empty);
+        return;
+    }
+    for sink in list.sinks {
+        let is_default = list.default.as_deref() == Some(sink.node_name.as_str());
+        let text = if is_default {
+            format!("✓ {}", sink.description)
+        } else {
+            sink.description.clone()
+        };
+        let button = gtk4::Button::with_label(&text);
+        button.add_css_class("tray-menu-item");
+        let audio = audio.clone();
+        let popover_weak = popover.downgrade();
+        button.connect_clicked(move |_| {
+            if !is_default {
+                audio.set_default(&sink.node_name);
+            }
+            if let Some(popover) = popover_weak.upgrade() {
+                popover.popdown();
+            }
+        });
+        menu.append(&button);
+    }
+}
+
+// Lit while PipeWire serves a screencast, a camera or a microphone; hidden
+// otherwise, like the other status pills.
+pub fn create_privacy_widget(model: &BarModel) -> gtk4::Label {
+    debug!("Creating privacy widget");
+    let label = gtk4::Label::new(None);
+    label.add_css_class("privacy-widget");
+    label.set_halign(gtk4::Align::Center);
+    bind_label_text(&label, model, "privacy");
+    bind_label_visible_when_non_empty(&label, model, "privacy");
+    model_property("privacy-tooltip").bind(&label, "tooltip-text", Some(model));
+    label
+}
+
+pub fn create_headset_widget(model: &BarModel, audio: &AudioSinks) -> gtk4::Button {
+    debug!("Creating headset widget");
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "headset");
+    let button = gtk4::Button::new();
+    button.add_css_class("headset-widget");
+    button.set_halign(gtk4::Align::Center);
+    button.set_child(Some(&label));
+    model_property("headset-tooltip").bind(&button, "tooltip-text", Some(model));
+    model_property("headset")
+        .chain_closure::<bool>(glib::closure!(|_: Option<glib::Object>, text: &str| !text
+            .trim()
+            .is_empty()))
+        .bind(&button, "visible", Some(model));
+    let button_weak = button.downgrade();
+    model.connect_headset_hfp_notify(move |model| {
+        let Some(button) = button_weak.upgrade() else {
+            return;
+        };
+        if model.headset_hfp() {
+            button.add_css_class("hfp");
+        } else {
+            button.remove_css_class("hfp");
+        }
+    });
+    let audio = audio.clone();
+    button.connect_clicked(move |_| audio.toggle_headset_profile());
+    button
+}
+
+// A changed mute state and whether it is a toggle: None for a refresh that
+// repeats the state (or carries none), and no toggle for the first report.
+fn mute_change(previous: Option<bool>, reported: Option<bool>) -> Option<(bool, bool)> {
+    let reported = reported?;
+    (previous != Some(reported)).then_some((reported, previous.is_some()))
+}
+
+pub fn setup_pipewire_updates(
+    model: BarModel,
+    dispatcher: UiDispatcher,
+    application: &gtk4::Application,
+    audio: AudioSinks,
+    commands: pipewire::channel::Receiver<AudioCommand>,
+    taskbar: TaskbarStore,
+    state: AppState,
+) -> Result<pw::PipewireHandle> {
+    debug!("Setting up volume updates with async channels");
+
+    let (sender, mut receiver) = channel::channel::<VolumeReport>("volume", bus::CAPACITY);
+    let (privacy_sender, privacy_receiver) =
+        channel::channel::<PrivacyUpdate>("privacy", bus::CAPACITY);
+    let (playback_sender, playback_receiver) =
+        channel::channel::<PlaybackUpdate>("playback", bus::CAPACITY);
+    let (sink_sender, sink_receiver) = mpsc::unbounded_channel::<SinkAdded>();
+    let (sink_list_sender, sink_list_receiver) =
+        channel::channel::<SinkList>("sink_list", bus::CAPACITY);
+    let (headset_sender, headset_receiver) =
+        channel::channel::<HeadsetUpdate>("headset", bus::CAPACITY);
+
+    // Start PipeWire monitoring on dedicated thread
+    let pipewire = pw::start_pipewire_thread(pw::PipewireChannels {
+        volume: sender,
+        privacy: privacy_sender,
+        playback: playback_sender,
+        sink_added: sink_sender,
+        sink_list: sink_list_sender,
+        headset: headset_sender,
+        commands,
+        state,
+    })?;
+    setup_privacy_updates(privacy_receiver, model.clone(), dispatcher.clone());
+    setup_headset_updates(headset_receiver, model.clone(), dispatcher.clone());
+    setup_playback_updates(playback_receiver, taskbar, dispatcher.clone());
+    sinks::setup_sink_notifications(sink_receiver, application);
+    audio::setup_sink_list_updates(sink_list_receiver, audio);
+
+    // Spawn async task on GTK main thread to handle volume updates
+    glib::spawn_future_local(async move {
+        debug!("🚀 Starting async volume update loop...");
+
+        // The last mute state reported, to tell toggles from refreshes.
+        let mut muted: Option<bool> = None;
+        while let Some(report) = receiver.recv().await {
+            let update = match report {
+                VolumeReport::Level(update) => update,
+                VolumeReport::NoAudio(reason) => {
+                    debug!(?reason, "Queueing volume without a level");
+                    let model = model.clone();
+                    let update = (no_audio_text(reason).to_string(), true);
+                    dispatcher.submit_changed(Slot::Volume, update, move |(text, inactive)| {
+                        model.set_volume(text);
+                        model.set_volume_inactive(inactive);
+                    });
+                    continue;
+                }
+            };
+            if let Some((now_muted, toggled)) = mute_change(muted, update.is_muted) {
+                muted = Some(now_muted);
+                debug!(muted = now_muted, toggled, "Mute state changed");
+                let model = model.clone();
+                dispatcher.submit(Slot::Mute, move || {
+                    model.set_volume_muted(now_muted);
+                    if toggled {
+                        model.set_volume_mute_toggles(model.volume_mute_toggles().wrapping_add(1));
+                    }
+                });
+            }
+            // Use channel volume first (more accurate), fallback to main volume
+            if let Some(volume_percent) = update.channel_percent.or(update.volume_percent) {
+                let first_char = update.name.chars().next().unwrap_or('A');
+                let emoji = if update.is_muted == Some(true) {
+                    "🔇"
+                } else {
+                    "🔊"
+                };
+                let display_text = format!("{}{}{}", emoji, first_char, volume_percent);
+                let model = model.clone();
+                // Shares the slot with the no-sink text, which it replaces.
+                let update = (display_text, false);
+                dispatcher.submit_changed(Slot::Volume, update, move |(display_text, inactive)| {
+                    model.set_volume(display_text.as_str());
+                    model.set_volume_inactive(inactive);
+                    debug!("📺 GTK UI updated via ASYNC: {}", display_text);
+                });
+            } else {
+                debug!("📺 Skipping GUI update - no volume data available");
+            }
+        }
+
+        debug!("⚠️ Volume update loop ended");
+    });
+
+    Ok(pipewire)
+}
+
+// The volume label's text while there is no level. It arrives on the levels'
+// channel and is submitted on their slot, so the last report PipeWire made is
+// the one shown.
+fn no_audio_text(reason: NoAudio) -> &'static str {
+    match reason {
+        NoAudio::NoSink => "🔇 no sink",
+        NoAudio::Unavailable => "🔇 PipeWire unavailable",
+    }
+}
+
+fn setup_headset_updates(
+    mut rx: channel::Receiver<HeadsetUpdate>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up headset profile updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            debug!(?update, "Queueing headset profile");
+            let text = headset::indicator_text(&update);
+            let hfp = update.mode == HeadsetMode::Headset;
+            let tooltip = headset::indicator_tooltip(&update);
+            let model = model.clone();
+            let update = (text, hfp, tooltip);
+            dispatcher.submit_changed(Slot::Headset, update, move |(text, hfp, tooltip)| {
+                model.set_headset(text);
+                model.set_headset_hfp(hfp);
+                model.set_headset_tooltip(tooltip);
+            });
+        }
+    });
+}
+
+fn setup_privacy_updates(
+    mut rx: channel::Receiver<PrivacyUpdate>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up privacy indicator updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            debug!("Queueing privacy indicators: {:?}", update);
+            let text = privacy::indicator_text(&update);
+            let tooltip = privacy::indicator_tooltip(&update);
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::Privacy, (text, tooltip), move |(text, tooltip)| {
+                model.set_privacy(text);
+                model.set_privacy_tooltip(tooltip);
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_a_changed_mute_state_after_the_first_is_a_toggle() {
+        assert_eq!(mute_change(None, Some(true)), Some((true, false)));
+        assert_eq!(mute_change(Some(true), Some(true)), None);
+        assert_eq!(mute_change(Some(true), None), None);
+        assert_eq!(mute_change(Some(true), Some(false)), Some((false, true)));
+    }
+}
@@ -0,0 +1,130 @@
+// Placing the bar's layer-shell window: the monitor, the anchored edges for
+// each `[bar] position`, and `[bar.floating]` margins and corners.
+
+use super::*;
+
+pub fn apply_position(window: &gtk4::ApplicationWindow, position: Position) {
+    let vertical = position.is_vertical();
+    let anchors = [
+        (Edge::Left, !vertical || position == Position::Left),
+        (Edge::Right, !vertical || position == Position::Right),
+        (Edge::Top, vertical || position == Position::Top),
+        (Edge::Bottom, vertical || position == Position::Bottom),
+    ];
+
+    for (anchor, state) in anchors {
+        window.set_anchor(anchor, state);
+    }
+}
+
+// The provider for the floating corners, added once; apply_floating refills
+// it, so a reload replaces the radius rather than stacking another provider.
+pub fn floating_provider(window: &gtk4::ApplicationWindow) -> gtk4::CssProvider {
+    let provider = gtk4::CssProvider::new();
+    gtk4::style_context_add_provider_for_display(
+        &gtk4::prelude::WidgetExt::display(window),
+        &provider,
+        gtk4::STYLE_PROVIDER_PRIORITY_USER + 1,
+    );
+    provider
+}
+
+// `[bar.floating]`: layer-shell margins keep the bar off the screen edges and
+// the stylesheet's `window.layer-bar.floating` gives it a background, whose
+// corners are rounded here. Like auto-hide it reserves no space, so it moves
+// to the Top layer to stay above the windows that now reach under it.
+pub fn apply_floating(
+    window: &gtk4::ApplicationWindow,
+    provider: &gtk4::CssProvider,
+    position: Position,
+    config: &FloatingConfig,
+) {
+    info!(?config, "Floating the bar");
+    let (edge, ends) = match position {
+        Position::Top => (Edge::Top, [Edge::Left, Edge::Right]),
+        Position::Bottom => (Edge::Bottom, [Edge::Left, Edge::Right]),
+        Position::Left => (Edge::Left, [Edge::Top, Edge::Bottom]),
+        Position::Right => (Edge::Right, [Edge::Top, Edge::Bottom]),
+    };
+    window.set_margin(edge, i32::from(config.edge_margin));
+    for end in ends {
+        window.set_margin(end, i32::from(config.side_margin));
+    }
+    window.set_layer(Layer::Top);
+    window.set_exclusive_zone(0);
+    window.add_css_class("floating");
+    provider.load_from_string(&format!(
+        "window.layer-bar.floating {{ border-radius: {}px; }}",
+        config.corner_radius
+    ));
+}
+
+// Undo apply_floating before a reload places the bar again: its margins sat
+// on the old position's edges, and `[bar.floating]` may now be off.
+pub fn clear_floating(window: &gtk4::ApplicationWindow, layer: Layer) {
+    for edge in [Edge::Left, Edge::Right, Edge::Top, Edge::Bottom] {
+        window.set_margin(edge, 0);
+    }
+    window.set_layer(layer);
+    window.auto_exclusive_zone_enable();
+    window.remove_css_class("floating");
+}
+
+pub fn configure_layer_shell(
+    window: &gtk4::ApplicationWindow,
+    monitor_connector: Option<&str>,
+    position: Position,
+    layer: Layer,
+) -> Result<()> {
+    debug!("Configuring layer shell");
+
+    window.init_layer_shell();
+    if let Some(requested) = monitor_connector {
+        let display = gtk4::prelude::WidgetExt::display(window);
+        let monitors = display.monitors();
+        let mut available = Vec::new();
+        let mut selected = None;
+
+        for index in 0..monitors.n_items() {
+            let Some(object) = monitors.item(index) else {
+                warn!(index, "GDK monitor list omitted an advertised item");
+                continue;
+            };
+            let Ok(monitor) = object.downcast::<gdk::Monitor>() else {
+                warn!(index, "GDK monitor list contained a non-monitor object");
+                continue;
+            };
+            let Some(connector) = monitor.connector() else {
+                warn!(index, "GDK monitor has no connector name");
+                continue;
+            };
+
+            if connector == requested {
+                selected = Some(monitor);
+                break;
+            }
+            available.push(connector.to_string());
+        }
+
+        let Some(monitor) = selected else {
+            available.sort();
+            let available = if available.is_empty() {
+                "none".to_string()
+            } else {
+                available.join(", ")
+            };
+            bail!(
+                "monitor connector {requested:?} was not found; available connectors: {available}"
+            );
+        };
+
+        window.set_monitor(Some(&monitor));
+        info!(monitor = requested, "Selected layer-shell monitor");
+    }
+    window.set_layer(layer);
+    window.auto_exclusive_zone_enable();
+    apply_position(window, position);
+
+    info!("Layer shell configured successfully");
+    Ok(())
+}
@@ -0,0 +1,324 @@
+// The workspace side of the bar: the workspace name with its floating-window
+// badge and per-workspace buttons, scrolling between workspaces, the split
+// hint and the scratchpad buttons, and the drains that feed them.
+
+use super::*;
+
+// The name, a badge counting the active workspace's floating windows (easy
+// to lose behind tiled ones), and a button per occupied workspace showing how
+// many windows it holds.
+pub fn create_workspace_widget(
+    model: &BarModel,
+    compositor: &AnyCompositor,
+    config: &WorkspaceConfig,
+) -> gtk4::Box {
+    debug!("Creating workspace widget");
+    let root = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
+    root.add_css_class("workspace-widget");
+    root.set_halign(gtk4::Align::Center);
+    add_workspace_scroll(&root, compositor, config);
+    let label = gtk4::Label::new(None);
+    bind_label_text(&label, model, "workspace-name");
+    root.append(&label);
+
+    let badge = gtk4::Label::new(None);
+    badge.add_css_class("floating-badge");
+    badge.set_tooltip_text(Some("Floating windows on this workspace"));
+    bind_label_text(&badge, model, "floating-badge");
+    bind_label_visible_when_non_empty(&badge, model, "floating-badge");
+    root.append(&badge);
+
+    let buttons = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
+    buttons.add_css_class("workspace-buttons");
+    buttons.set_visible(false);
+    root.append(&buttons);
+    let (buttons_weak, labels) = (buttons.downgrade(), config.labels.clone());
+    model.connect_window_counts_notify(move |model| {
+        if let Some(buttons) = buttons_weak.upgrade() {
+            render_workspace_buttons(&buttons, model, &labels);
+        }
+    });
+    let (buttons_weak, labels) = (buttons.downgrade(), config.labels.clone());
+    model.connect_workspace_id_notify(move |model| {
+        if let Some(buttons) = buttons_weak.upgrade() {
+            render_workspace_buttons(&buttons, model, &labels);
+        }
+    });
+
+    let root_weak = root.downgrade();
+    model.connect_workspace_special_notify(move |model| {
+        let Some(root) = root_weak.upgrade() else {
+            return;
+        };
+        if model.workspace_special() {
+            root.add_css_class("special-workspace");
+        } else {
+            root.remove_css_class("special-workspace");
+        }
+    });
+    root
+}
+
+// The counts only come from Hyprland, so elsewhere the strip stays empty and
+// hidden. Rebuilt whole on each change; there are only a handful of buttons.
+fn render_workspace_buttons(
+    buttons: &gtk4::Box,
+    model: &BarModel,
+    labels: &BTreeMap<String, String>,
+) {
+    while let Some(child) = buttons.first_child() {
+        buttons.remove(&child);
+    }
+    let counts = parse_window_counts(&model.window_counts());
+    buttons.set_visible(!counts.is_empty());
+    for (id, count) in counts {
+        let name = labels
+            .get(&id.to_string())
+            .cloned()
+            .unwrap_or_else(|| id.to_string());
+        let content = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
+        content.append(&gtk4::Label::new(Some(&name)));
+        let badge = gtk4::Label::new(Some(&hypr::format_window_badge(count)));
+        badge.add_css_class("window-badge");
+        content.append(&badge);
+
+        let button = gtk4::Button::new();
+        button.set_child(Some(&content));
+        button.add_css_class("workspace-button");
+        if id == model.workspace_id() {
+            button.add_css_class("active");
+        }
+        let windows = if count == 1 { "window" } else { "windows" };
+        button.set_tooltip_text(Some(&format!(
+            "{count} {windows} on workspace {name}; click to switch to it"
+        )));
+        button.connect_clicked(move |_| {
+            tokio::spawn(async move {
+                if let Err(e) = hypr::focus_workspace(id).await {
+                    warn!(id, "Failed to switch workspace: {:#}", e);
+                }
+            });
+        });
+        buttons.append(&button);
+    }
+}
+
+// The model's window-counts text back into (id, count) pairs, skipping any
+// malformed line.
+fn parse_window_counts(text: &str) -> Vec<(i32, usize)> {
+    text.lines()
+        .filter_map(|line| {
+            let (id, count) = line.split_once(' ')?;
+            Some((id.parse().ok()?, count.parse().ok()?))
+        })
+        .collect()
+}
+
+fn format_window_counts(counts: &BTreeMap<i32, usize>) -> String {
+    counts
+        .iter()
+        .map(|(id, count)| format!("{id} {count}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Scrolling down moves to the next workspace, up to the previous one. Stops
+// the scroll, so with `[workspace] scroll_bar` the bar's own handler does not
+// switch a second time over the workspace widget.
+pub(super) fn add_workspace_scroll(
+    widget: &impl IsA<gtk4::Widget>,
+    compositor: &AnyCompositor,
+    config: &WorkspaceConfig,
+) {
+    let scroll = gtk4::EventControllerScroll::new(
+        gtk4::EventControllerScrollFlags::VERTICAL | gtk4::EventControllerScrollFlags::DISCRETE,
+    );
+    let compositor = compositor.clone();
+    let (wrap, skip_empty) = (config.scroll_wrap, config.scroll_skip_empty);
+    scroll.connect_scroll(move |_, _dx, dy| {
+        if dy == 0.0 {
+            return glib::Propagation::Proceed;
+        }
+        let step = if dy > 0.0 { 1 } else { -1 };
+        compositor.scroll_workspaces(step, wrap, skip_empty);
+        glib::Propagation::Stop
+    });
+    widget.add_controller(scroll);
+}
+
+pub fn create_split_widget(model: &BarModel) -> gtk4::Label {
+    debug!("Creating split hint widget");
+    let label = gtk4::Label::new(None);
+    label.add_css_class("split-widget");
+    label.set_halign(gtk4::Align::Center);
+    label.set_tooltip_text(Some("Where the next tiled window opens"));
+    bind_label_text(&label, model, "split-hint");
+    bind_label_visible_when_non_empty(&label, model, "split-hint");
+    label
+}
+
+// A toggle per configured special workspace, lit while it is shown on some
+// monitor. Hyprland only: elsewhere the box stays empty and hidden.
+pub fn create_scratchpad_widget(
+    model: &BarModel,
+    compositor: &AnyCompositor,
+    config: &WorkspaceConfig,
+) -> gtk4::Box {
+    debug!("Creating scratchpad widget");
+    let root = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
+    root.add_css_class("scratchpad-widget");
+    root.set_halign(gtk4::Align::Center);
+    if !compositor.is_hyprland() || config.scratchpads.is_empty() {
+        root.set_visible(false);
+        return root;
+    }
+
+    let mut buttons = Vec::new();
+    for name in &config.scratchpads {
+        let text = config
+            .labels
+            .get(&format!("special:{name}"))
+            .cloned()
+            .unwrap_or_else(|| name.clone());
+        let button = gtk4::Button::with_label(&text);
+        button.add_css_class("scratchpad-button");
+        button.set_tooltip_text(Some(&format!("Click to show or hide special:{name}")));
+        let target = name.clone();
+        button.connect_clicked(move |_| {
+            let target = target.clone();
+            tokio::spawn(async move {
+                if let Err(e) = hypr::toggle_special_workspace(&target).await {
+                    warn!(name = target, "Failed to toggle special workspace: {:#}", e);
+                }
+            });
+        });
+        root.append(&button);
+        buttons.push((name.clone(), button.downgrade()));
+    }
+
+    model.connect_scratchpads_open_notify(move |model| {
+        let open = model.scratchpads_open();
+        for (name, button) in &buttons {
+            let Some(button) = button.upgrade() else {
+                continue;
+            };
+            if open.lines().any(|shown| shown == name) {
+                button.add_css_class("open");
+            } else {
+                button.remove_css_class("open");
+            }
+        }
+    });
+    root
+}
+
+pub fn setup_workspace_updates(
+    mut rx: channel::Receiver<WorkspaceUpdate>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up workspace updates");
+
+    // Handle combined workspace updates (name + ID) in single frame
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::Workspace, update, move |update| {
+                debug!(
+                    "Updating workspace - label: '{}', id: {}",
+                    update.name, update.id
+                );
+                model.set_workspace_name(update.name);
+                model.set_workspace_id(update.id);
+                model.set_workspace_special(update.special);
+            });
+        }
+    });
+}
+
+pub fn setup_window_count_updates(
+    mut rx: channel::Receiver<BTreeMap<i32, usize>>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up window count updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(counts) = rx.recv().await {
+            debug!(?counts, "Queueing window counts");
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::WindowCount, counts, move |counts| {
+                model.set_window_counts(format_window_counts(&counts))
+            });
+        }
+    });
+}
+
+pub fn setup_floating_updates(
+    mut rx: channel::Receiver<String>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up floating window updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            debug!("Queueing floating badge: {:?}", update);
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::Floating, update, move |update| {
+                model.set_floating_badge(update)
+            });
+        }
+    });
+}
+
+pub fn setup_scratchpad_updates(
+    mut rx: channel::Receiver<Vec<String>>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up scratchpad updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(open) = rx.recv().await {
+            debug!(?open, "Queueing open special workspaces");
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::Scratchpad, open.join("\n"), move |open| {
+                model.set_scratchpads_open(open)
+            });
+        }
+    });
+}
+
+pub fn setup_split_updates(
+    mut rx: channel::Receiver<String>,
+    model: BarModel,
+    dispatcher: UiDispatcher,
+) {
+    debug!("Setting up split hint updates");
+
+    glib::spawn_future_local(async move {
+        while let Some(update) = rx.recv().await {
+            debug!("Queueing split hint: {}", update);
+            let model = model.clone();
+            dispatcher.submit_changed(Slot::Split, update, move |update| {
+                model.set_split_hint(update)
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_counts_survive_the_model_round_trip() {
+        let counts = BTreeMap::from([(1, 2), (4, 1), (10, 7)]);
+        let text = format_window_counts(&counts);
+        assert_eq!(text, "1 2\n4 1\n10 7");
+        assert_eq!(parse_window_counts(&text), [(1, 2), (4, 1), (10, 7)]);
+        assert_eq!(parse_window_counts("3 x\n\n5 1"), [(5, 1)]);
+        assert!(parse_window_counts("").is_empty());
+    }
+}
@@ -0,0 +1,254 @@
+// First-run wizard: on a start with no config.toml (and no --config), a
+// popover under the bar offers the few choices most people make first (which
+// widgets, top or bottom, a theme preset) and writes them out as a starting
+// config.toml, so nobody has to learn the TOML keys before the bar looks
+// right.
+//
+// Saving applies the edge and theme at once and rebuilds the layout through
+// the same reload-config path `trayctl` uses. Either button marks the wizard
+// done in state.json, so it never comes back, even if the config file is
+// deleted later. An existing file is never overwritten: if one appeared while
+// the popover was open, saving reports it instead.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use anyhow::{Context, Result};
+use gtk4::glib;
+use gtk4::prelude::*;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{info, warn};
+use tray_ipc::{IpcRequest, IpcUiRequest};
+
+use crate::config::{Position, Theme};
+use crate::state::StateFile;
+use crate::widgets;
+
+const POSITIONS: [(Position, &str); 4] = [
+    (Position::Top, "top"),
+    (Position::Bottom, "bottom"),
+    (Position::Left, "left"),
+    (Position::Right, "right"),
+];
+const THEMES: [(Theme, &str); 3] = [
+    (Theme::Default, "default"),
+    (Theme::Solid, "solid"),
+    (Theme::Light, "light"),
+];
+
+// Off by default: the built-in layout only shows it when asked to.
+const OPT_IN_WIDGETS: &[&str] = &["taskbar"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Choices {
+    // Built-in widgets the user unticked.
+    pub disabled_widgets: Vec<String>,
+    pub taskbar: bool,
+    pub position: Position,
+    pub theme: Theme,
+}
+
+fn name_of<T: PartialEq + Copy>(table: &[(T, &'static str)], value: T) -> &'static str {
+    table
+        .iter()
+        .find(|(candidate, _)| *candidate == value)
+        .map(|(_, name)| *name)
+        .unwrap_or_default()
+}
+
+pub fn render_config(choices: &Choices) -> String {
+    let disabled = choices
+        .disabled_widgets
+        .iter()
+        .map(|name| format!("{name:?}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "# Written by the first-run wizard. Every option is described in the README.\n\
+         \n\
+         [bar]\n\
+         position = \"{}\"\n\
+         theme = \"{}\"\n\
+         disabled_widgets = [{disabled}]\n\
+         \n\
+         [taskbar]\n\
+         enabled = {}\n",
+        name_of(&POSITIONS, choices.position),
+        name_of(&THEMES, choices.theme),
+        choices.taskbar,
+    )
+}
+
+// create_new: a file the user wrote meanwhile wins over the wizard's.
+fn write_config(path: &PathBuf, choices: &Choices) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("create config directory {}", dir.display()))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .with_context(|| format!("create {}", path.display()))?;
+    file.write_all(render_config(choices).as_bytes())
+        .with_context(|| format!("write {}", path.display()))
+}
+
+fn drop_down(table: &[(impl Copy, &'static str)]) -> gtk4::DropDown {
+    let labels: Vec<String> = table
+        .iter()
+        .map(|(_, name)| {
+            let mut label = name.to_string();
+            label[..1].make_ascii_uppercase();
+            label
+        })
+        .collect();
+    let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+    gtk4::DropDown::from_strings(&labels)
+}
+
+fn labeled_row(label: &str, child: &impl IsA<gtk4::Widget>) -> gtk4::Box {
+    let row = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+    let label = gtk4::Label::new(Some(label));
+    label.set_hexpand(true);
+    label.set_xalign(0.0);
+    row.append(&label);
+    row.append(child);
+    row
+}
+
+pub struct Wizard {
+    pub anchor: gtk4::Widget,
+    pub window: gtk4::ApplicationWindow,
+    pub path: PathBuf,
+    pub state: Rc<StateFile>,
+    // The control channel, for the reload after saving.
+    pub control: mpsc::UnboundedSender<IpcUiRequest>,
+}
+
+pub fn show(wizard: Wizard) {
+    info!(path = %wizard.path.display(), "No configuration yet; showing the first-run wizard");
+    let popover = gtk4::Popover::new();
+    popover.add_css_class("wizard");
+    // Only the buttons close it: a stray click elsewhere must not lose the
+    // choices.
+    popover.set_autohide(false);
+    popover.set_parent(&wizard.anchor);
+
+    let content = gtk4::Box::new(gtk4::Orientation::Vertical, 8);
+    let heading = gtk4::Label::new(Some("Set up the bar"));
+    heading.add_css_class("wizard-heading");
+    content.append(&heading);
+
+    let grid = gtk4::Grid::new();
+    grid.set_column_spacing(12);
+    let checks: Vec<(&'static str, gtk4::CheckButton)> = widgets::builtin_widget_names()
+        .enumerate()
+        .map(|(index, name)| {
+            let check = gtk4::CheckButton::with_label(name);
+            check.set_active(!OPT_IN_WIDGETS.contains(&name));
+            grid.attach(&check, (index % 3) as i32, (index / 3) as i32, 1, 1);
+            (name, check)
+        })
+        .collect();
+    content.append(&grid);
+
+    let position = drop_down(&POSITIONS);
+    content.append(&labeled_row("Position", &position));
+    let theme = drop_down(&THEMES);
+    content.append(&labeled_row("Theme", &theme));
+
+    let error = gtk4::Label::new(None);
+    error.add_css_class("wizard-error");
+    error.set_wrap(true);
+    error.set_visible(false);
+    content.append(&error);
+
+    let buttons = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+    buttons.set_halign(gtk4::Align::End);
+    let skip = gtk4::Button::with_label("Skip");
+    let save = gtk4::Button::with_label("Save");
+    buttons.append(&skip);
+    buttons.append(&save);
+    content.append(&buttons);
+    popover.set_child(Some(&content));
+
+    let state = wizard.state.clone();
+    let popover_weak = popover.downgrade();
+    skip.connect_clicked(move |_| {
+        info!("First-run wizard skipped");
+        state.update(|state| state.wizard_done = true);
+        if let Some(popover) = popover_weak.upgrade() {
+            popover.popdown();
+        }
+    });
+
+    let popover_weak = popover.downgrade();
+    save.connect_clicked(move |_| {
+        let enabled = |name: &str| {
+            checks
+                .iter()
+                .any(|(candidate, check)| *candidate == name && check.is_active())
+        };
+        let choices = Choices {
+            disabled_widgets: checks
+                .iter()
+                .filter(|(name, check)| !check.is_active() && !OPT_IN_WIDGETS.contains(name))
+                .map(|(name, _)| name.to_string())
+                .collect(),
+            taskbar: enabled("taskbar"),
+            position: POSITIONS[position.selected() as usize % POSITIONS.len()].0,
+            theme: THEMES[theme.selected() as usize % THEMES.len()].0,
+        };
+        if let Err(e) = write_config(&wizard.path, &choices) {
+            warn!("First-run wizard could not save: {:#}", e);
+            error.set_text(&format!("{e:#}"));
+            error.set_visible(true);
+            return;
+        }
+        info!(?choices, "First-run wizard saved the configuration");
+        wizard.state.update(|state| state.wizard_done = true);
+        widgets::apply_position(&wizard.window, choices.position);
+        widgets::apply_theme(&wizard.window, choices.theme);
+        // The reload replaces the bar tree, and this popover with it.
+        let (response, _) = oneshot::channel();
+        let request = IpcUiRequest {
+            request: IpcRequest::ReloadConfig,
+            response,
+        };
+        if wizard.control.send(request).is_err() {
+            warn!("Bar control is gone; the new layout applies on the next start");
+        }
+        if let Some(popover) = popover_weak.upgrade() {
+            popover.popdown();
+        }
+    });
+
+    popover.connect_closed(|popover| {
+        let popover = popover.clone();
+        glib::idle_add_local_once(move || popover.unparent());
+    });
+    popover.popup();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+
+    #[test]
+    fn rendered_config_parses_back() {
+        let choices = Choices {
+            disabled_widgets: vec!["cpufreq".to_string(), "network".to_string()],
+            taskbar: true,
+            position: Position::Bottom,
+            theme: Theme::Light,
+        };
+        let config = config::parse(&render_config(&choices)).expect("wizard output parses");
+        assert_eq!(config.bar.disabled_widgets, choices.disabled_widgets);
+        assert_eq!(config.bar.position, Position::Bottom);
+        assert_eq!(config.bar.theme, Theme::Light);
+        assert!(config.taskbar.enabled);
+    }
+}